@@ -0,0 +1,130 @@
+// Benchmarks for the work the addon does on every request: counting tokens,
+// validating the payload, and transforming the upstream SSE stream.
+// Compare against a saved baseline with `bun run bench:native`.
+
+use copilot_api_native::internals::{
+    anthropic_to_openai, count_tokens, openai_to_anthropic, validate, Message, SseTransformer,
+};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use serde_json::{json, Value};
+
+const PARAGRAPH: &str = "The gateway forwards chat completions to Copilot, counting tokens \
+    and validating the payload on the way in and translating the stream on the way out. ";
+
+fn conversation(turns: usize, nonce: usize) -> Value {
+    let messages: Vec<Value> = (0..turns)
+        .map(|i| {
+            let role = if i % 2 == 0 { "user" } else { "assistant" };
+            json!({ "role": role, "content": format!("{} {}", nonce, PARAGRAPH.repeat(4)) })
+        })
+        .collect();
+    json!({ "model": "gpt-4o", "max_tokens": 1024, "messages": messages })
+}
+
+fn messages(payload: &Value) -> Vec<Message> {
+    serde_json::from_value(payload["messages"].clone()).unwrap()
+}
+
+fn sse_stream(chunks: usize) -> Vec<u8> {
+    let mut body = String::new();
+    for i in 0..chunks {
+        let chunk = json!({
+            "id": "chatcmpl-bench",
+            "object": "chat.completion.chunk",
+            "model": "gpt-4o",
+            "choices": [{ "index": 0, "delta": { "content": format!("token{} ", i) }, "finish_reason": null }],
+        });
+        body.push_str(&format!("data: {}\n\n", chunk));
+    }
+    body.push_str("data: [DONE]\n\n");
+    body.into_bytes()
+}
+
+fn tokenizer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenizer");
+    for turns in [4, 100] {
+        let payload = conversation(turns, 0);
+        group.throughput(Throughput::Bytes(payload.to_string().len() as u64));
+
+        // Agent loops resend the conversation, so most segments hit the cache
+        group.bench_function(format!("cached/{}", turns), |b| {
+            b.iter_batched(|| messages(&payload), |m| black_box(count_tokens(m)), BatchSize::SmallInput)
+        });
+
+        let mut nonce = 0;
+        group.bench_function(format!("cold/{}", turns), |b| {
+            b.iter_batched(
+                || {
+                    nonce += 1;
+                    messages(&conversation(turns, nonce))
+                },
+                |m| black_box(count_tokens(m)),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn validation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validation");
+    for turns in [4, 100] {
+        let payload = conversation(turns, 0);
+        let bytes = payload.to_string();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        // Parsing included, as validatePayload receives the JSON bytes
+        group.bench_function(format!("openai/{}", turns), |b| {
+            b.iter(|| validate(&serde_json::from_str::<Value>(black_box(&bytes)).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn sse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sse");
+    let stream = sse_stream(500);
+    group.throughput(Throughput::Bytes(stream.len() as u64));
+    // Network reads rarely line up with events
+    for read_size in [64, 4096] {
+        group.bench_function(format!("transform/{}", read_size), |b| {
+            b.iter(|| {
+                let mut transformer = SseTransformer::default();
+                let mut events = 0;
+                for chunk in stream.chunks(read_size) {
+                    events += transformer.push(black_box(chunk)).len();
+                }
+                events + transformer.flush().len()
+            })
+        });
+    }
+
+    // First event out of a fresh transformer, the native share of time-to-first-byte
+    let first = &stream[..stream.iter().position(|&b| b == b'\n').unwrap() + 2];
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("first_event", |b| {
+        b.iter(|| SseTransformer::default().push(black_box(first)))
+    });
+    group.finish();
+}
+
+fn translation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("translation");
+    let request = conversation(20, 0);
+    group.bench_function("anthropic_to_openai", |b| b.iter(|| anthropic_to_openai(black_box(&request))));
+
+    let response = json!({
+        "id": "chatcmpl-bench",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": PARAGRAPH.repeat(8) },
+            "finish_reason": "stop",
+        }],
+        "usage": { "prompt_tokens": 1200, "completion_tokens": 300, "total_tokens": 1500 },
+    });
+    group.bench_function("openai_to_anthropic", |b| b.iter(|| openai_to_anthropic(black_box(&response))));
+    group.finish();
+}
+
+criterion_group!(benches, tokenizer, validation, sse, translation);
+criterion_main!(benches);
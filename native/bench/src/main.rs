@@ -0,0 +1,91 @@
+// Fails when a benchmark got slower than the saved baseline. Criterion
+// reports changes but always exits 0, so this reads the comparison it
+// leaves in target/criterion after `cargo bench -- --baseline <name>`.
+//
+//   regression-gate [threshold]    default 0.10, i.e. 10% slower
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const DEFAULT_THRESHOLD: f64 = 0.10;
+
+struct Change {
+    id: String,
+    mean: f64,
+    lower_bound: f64,
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+fn change_dirs(dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.file_name().is_some_and(|name| name == "change") {
+            found.push(path);
+        } else {
+            change_dirs(&path, found);
+        }
+    }
+}
+
+fn read_change(dir: &Path) -> Option<Change> {
+    let estimates = read_json(&dir.join("estimates.json"))?;
+    let benchmark = dir.parent()?;
+    let id = read_json(&benchmark.join("new/benchmark.json"))
+        .and_then(|info| info["full_id"].as_str().map(str::to_string))
+        .unwrap_or_else(|| benchmark.display().to_string());
+    Some(Change {
+        id,
+        mean: estimates["mean"]["point_estimate"].as_f64()?,
+        lower_bound: estimates["mean"]["confidence_interval"]["lower_bound"].as_f64()?,
+    })
+}
+
+fn main() -> ExitCode {
+    let threshold = match std::env::args().nth(1).map(|arg| arg.parse::<f64>()) {
+        None => DEFAULT_THRESHOLD,
+        Some(Ok(threshold)) if threshold > 0.0 => threshold,
+        Some(_) => {
+            eprintln!("The threshold must be a positive fraction, like 0.10 for 10%");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut dirs = Vec::new();
+    change_dirs(Path::new("target/criterion"), &mut dirs);
+    let mut changes: Vec<Change> = dirs.iter().filter_map(|dir| read_change(dir)).collect();
+    if changes.is_empty() {
+        eprintln!("No baseline comparisons in target/criterion, run `cargo bench -- --baseline <name>` first");
+        return ExitCode::from(2);
+    }
+    changes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    // Only a change whose whole confidence interval is past the threshold
+    // counts, so noise on a busy machine doesn't fail the gate
+    let mut regressions = 0;
+    for change in &changes {
+        let regressed = change.lower_bound > threshold;
+        regressions += usize::from(regressed);
+        println!(
+            "{} {:<40} {:+7.2}%",
+            if regressed { "REGRESSED" } else { "ok       " },
+            change.id,
+            change.mean * 100.0
+        );
+    }
+
+    if regressions > 0 {
+        eprintln!("{} of {} benchmarks regressed by more than {:.0}%", regressions, changes.len(), threshold * 100.0);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,15 @@
+#![no_main]
+
+use copilot_api_native::internals::anthropic_to_openai;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(payload) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return;
+    };
+
+    if let Ok(translated) = anthropic_to_openai(&payload) {
+        assert!(translated["messages"].is_array());
+        serde_json::to_vec(&translated).expect("translated payloads serialize");
+    }
+});
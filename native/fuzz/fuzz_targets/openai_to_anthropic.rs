@@ -0,0 +1,16 @@
+#![no_main]
+
+use copilot_api_native::internals::openai_to_anthropic;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(response) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return;
+    };
+
+    if let Ok(translated) = openai_to_anthropic(&response) {
+        assert_eq!(translated["type"], "message");
+        assert!(translated["content"].is_array());
+        serde_json::to_vec(&translated).expect("translated responses serialize");
+    }
+});
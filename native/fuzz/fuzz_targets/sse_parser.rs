@@ -0,0 +1,27 @@
+#![no_main]
+
+use copilot_api_native::internals::{SseEvent, SseTransformer};
+use libfuzzer_sys::fuzz_target;
+
+fn parse(chunks: &[&[u8]]) -> serde_json::Value {
+    let mut transformer = SseTransformer::default();
+    let mut events: Vec<SseEvent> = Vec::new();
+    for chunk in chunks {
+        events.extend(transformer.push(chunk));
+    }
+    events.extend(transformer.flush());
+    serde_json::to_value(events).expect("events serialize")
+}
+
+// The first byte picks where the stream is split, the rest is the stream.
+// Upstream chunks end anywhere, so the events must not depend on where.
+fuzz_target!(|data: &[u8]| {
+    let Some((&seed, stream)) = data.split_first() else {
+        return;
+    };
+
+    let whole = parse(&[stream]);
+    let step = usize::from(seed).max(1);
+    let chunks: Vec<&[u8]> = stream.chunks(step).collect();
+    assert_eq!(whole, parse(&chunks));
+});
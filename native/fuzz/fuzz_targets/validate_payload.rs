@@ -0,0 +1,16 @@
+#![no_main]
+
+use copilot_api_native::internals::validate;
+use libfuzzer_sys::fuzz_target;
+
+// Same path as validatePayload: bytes from the client, parsed, then validated
+fuzz_target!(|data: &[u8]| {
+    let Ok(payload) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return;
+    };
+
+    let result = validate(&payload);
+    assert_eq!(result.valid, result.errors.is_empty());
+    assert_eq!(result.valid, result.error.is_none());
+    serde_json::to_vec(&result).expect("validation results serialize");
+});
@@ -0,0 +1,105 @@
+// Property tests for the same entry points as the fuzz targets. These run on
+// stable with `cargo test` from this directory.
+
+use copilot_api_native::internals::{anthropic_to_openai, openai_to_anthropic, validate, SseTransformer};
+use proptest::prelude::*;
+use serde_json::{json, Value};
+
+fn json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        ".*".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+            prop::collection::btree_map("[a-z_]{1,8}", inner, 0..4)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn sse_body(texts: &[String]) -> String {
+    let mut body = String::new();
+    for text in texts {
+        let chunk = json!({ "choices": [{ "index": 0, "delta": { "content": text } }] });
+        body.push_str(&format!("data: {}\n\n", chunk));
+    }
+    body.push_str("data: [DONE]\n\n");
+    body
+}
+
+proptest! {
+    // Tool calls made by the assistant survive the trip to OpenAI and back
+    #[test]
+    fn tool_use_input_round_trips(id in "[a-zA-Z0-9_]{1,16}", name in "[a-z_]{1,16}", input in json_value()) {
+        let request = json!({
+            "model": "claude-sonnet-4",
+            "messages": [{
+                "role": "assistant",
+                "content": [{ "type": "tool_use", "id": id, "name": name, "input": input }],
+            }],
+        });
+        let openai = anthropic_to_openai(&request).unwrap();
+        let message = &openai["messages"][0];
+
+        let response = json!({ "choices": [{ "message": message, "finish_reason": "tool_calls" }] });
+        let anthropic = openai_to_anthropic(&response).unwrap();
+        let tool_use = &anthropic["content"][0];
+        prop_assert_eq!(&tool_use["id"], &json!(id));
+        prop_assert_eq!(&tool_use["name"], &json!(name));
+        prop_assert_eq!(&tool_use["input"], &input);
+        prop_assert_eq!(&anthropic["stop_reason"], &json!("tool_use"));
+    }
+
+    #[test]
+    fn assistant_text_round_trips(text in ".+") {
+        let request = json!({
+            "model": "claude-sonnet-4",
+            "messages": [{ "role": "assistant", "content": [{ "type": "text", "text": text }] }],
+        });
+        let openai = anthropic_to_openai(&request).unwrap();
+
+        let response = json!({ "choices": [{ "message": openai["messages"][0], "finish_reason": "stop" }] });
+        let anthropic = openai_to_anthropic(&response).unwrap();
+        prop_assert_eq!(&anthropic["content"], &json!([{ "type": "text", "text": text }]));
+    }
+
+    // Translated requests pass the same validation as native OpenAI ones
+    #[test]
+    fn translated_requests_validate(texts in prop::collection::vec(".+", 1..6)) {
+        let messages: Vec<Value> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| json!({ "role": if i % 2 == 0 { "user" } else { "assistant" }, "content": text }))
+            .collect();
+        let request = json!({ "model": "claude-sonnet-4", "max_tokens": 1024, "messages": messages });
+
+        let openai = anthropic_to_openai(&request).unwrap();
+        let result = validate(&openai);
+        prop_assert!(result.valid, "{:?}", result.error);
+        prop_assert!(result.warnings.is_empty());
+    }
+
+    // Upstream chunks end anywhere, including inside a multi-byte character
+    #[test]
+    fn sse_text_survives_any_split(texts in prop::collection::vec(".+", 1..8), step in 1usize..64) {
+        let body = sse_body(&texts);
+        let mut transformer = SseTransformer::default();
+        let mut events = Vec::new();
+        for chunk in body.as_bytes().chunks(step) {
+            events.extend(transformer.push(chunk));
+        }
+        events.extend(transformer.flush());
+
+        let events = serde_json::to_value(events).unwrap();
+        let expected: Vec<Value> = texts
+            .iter()
+            .map(|text| json!({ "type": "text_delta", "index": 0, "text": text }))
+            .chain([json!({ "type": "done" })])
+            .collect();
+        prop_assert_eq!(events, Value::from(expected));
+    }
+}
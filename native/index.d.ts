@@ -0,0 +1,298 @@
+// Type definitions for the Rust native module (index.node).
+// Keep in sync with the exports registered in src/lib.rs.
+//
+// Arguments and results cross the boundary as plain objects that Rust
+// deserializes into typed serde structs. Shape mismatches throw an Error
+// naming the offending field.
+//
+// Other failures throw an Error whose name is the kind (e.g. "ValidationError")
+// and whose code is a NativeErrorCode. Panics throw (or reject with) an
+// InternalError instead of aborting the process.
+//
+// JSON arguments can also be UTF-8 bytes, which Rust parses in place instead of
+// JS stringifying the object first. Worth it for multi-megabyte conversations.
+export type JsonBytes = Uint8Array | ArrayBuffer
+
+export type NativeErrorCode =
+  | "ERR_NATIVE_VALIDATION"
+  | "ERR_NATIVE_UPSTREAM"
+  | "ERR_NATIVE_AUTH"
+  | "ERR_NATIVE_RATE_LIMIT"
+  | "ERR_NATIVE_INTERNAL"
+
+export interface ContentPart {
+  type: string
+  text?: string
+}
+
+export interface ToolCall {
+  function: { name?: string; arguments?: string }
+}
+
+export interface Message {
+  role: string
+  content?: string | Array<ContentPart> | null
+  // Counted as "name(arguments)" lines after the content
+  tool_calls?: Array<ToolCall> | null
+}
+
+export interface TokenCount {
+  input: number
+  output: number
+}
+
+export interface TokenCacheStats {
+  entries: number
+  capacity: number
+  hits: number
+  misses: number
+}
+
+// Counters kept inside the native module since it was loaded
+export interface NativeMetrics {
+  tokenizer: { calls: number; seconds: number }
+  // Payloads failing validation, by error code
+  validationFailures: Record<string, number>
+  rateLimit: { allowed: number; rejected: number }
+  // createChatCompletions and createEmbeddings, seconds include retries
+  upstream: { ok: number; failed: number; cancelled: number; seconds: number }
+}
+
+export interface LimitConfig {
+  intervalSecs: number
+  // Defaults to 5
+  burstCapacity?: number
+}
+
+export interface RateLimitDecision {
+  allowed: boolean
+  // Until every bucket has a token again, 0 when allowed
+  retryAfterMs: number
+  // Tokens left in the emptiest bucket, after this request
+  remaining: number
+}
+
+export interface RateLimitStats {
+  activeLimiters: number
+  // Total limiters freed by cleanup, the janitor and expire-on-read
+  reclaimedLimiters: number
+  janitorRunning: boolean
+}
+
+export type ErrorCode =
+  | "invalid_payload"
+  | "first_message_not_user"
+  | "missing_tool_call_id"
+  | "unknown_tool_call_id"
+
+export type WarningCode =
+  | "consecutive_same_role"
+  | "parameter_ignored"
+  | "large_system_prompt"
+
+export interface Violation<Code extends string = ErrorCode | WarningCode> {
+  code: Code
+  // Of the offending message, if the problem is with one
+  index?: number
+  message: string
+}
+
+export interface ValidationResult {
+  valid: boolean
+  // All errors in one message
+  error?: string
+  // "text", "anthropic", or either followed by any of "vision", "audio",
+  // "tools" and "reasoning" joined by "+" in that order, e.g.
+  // "vision+tools" or "anthropic+vision"
+  contentType?: string
+  // Omitted when there are none
+  errors?: Array<Violation<ErrorCode>>
+  // Valid but suspicious, omitted when there are none
+  warnings?: Array<Violation<WarningCode>>
+}
+
+export interface ValidationCacheStats {
+  entries: number
+  // 0 when the cache is off
+  capacity: number
+  hits: number
+  misses: number
+}
+
+export interface RetryPolicy {
+  maxRetries: number
+  baseDelayMs: number
+  // Also the longest Retry-After waited for; a longer one fails the request
+  maxDelayMs: number
+  jitter: boolean
+  retryOnStatus: Array<number>
+}
+
+export interface HttpConfig {
+  connectTimeoutMs: number
+  requestTimeoutMs: number | null
+  poolMaxIdlePerHost: number
+  poolIdleTimeoutMs: number
+  tcpKeepaliveMs: number
+  http2: boolean
+  http2PriorKnowledge: boolean
+  http2AdaptiveWindow: boolean
+  // Keeps idle HTTP/2 connections alive with PINGs, off when null
+  http2KeepAliveIntervalMs: number | null
+  // Requests gzip or zstd and decodes the response
+  compression: boolean
+  proxy: string | null
+}
+
+export interface RequestOptions {
+  baseUrl: string
+  headers?: Record<string, string>
+  // "buffer" resolves with the body as a Buffer, defaults to "text"
+  responseType?: "text" | "buffer"
+}
+
+export interface RequestHandle<Body = string> {
+  id: string
+  // Rejects with an error named "AbortError" when cancelled
+  response: Promise<Body>
+}
+
+export type SseEvent =
+  | { type: "text_delta"; index: number; text: string }
+  | {
+      type: "tool_call_delta"
+      index: number
+      toolIndex: number
+      id?: string
+      name?: string
+      arguments?: string
+    }
+  | { type: "finish"; index: number; reason: string }
+  | {
+      type: "usage"
+      promptTokens: number
+      completionTokens: number
+      totalTokens: number
+    }
+  | { type: "done" }
+  | { type: "error"; message: string; data: string }
+
+export interface PluginLimits {
+  // Roughly one unit per WASM instruction, defaults to 100 million
+  fuel: number
+  // Defaults to 64 MiB
+  maxMemoryBytes: number
+  // Largest answer read back from a plugin, defaults to 16 MiB
+  maxOutputBytes: number
+}
+
+export type PluginHook = "request" | "response"
+
+export interface PluginInfo {
+  id: number
+  hooks: Array<PluginHook>
+  limits: PluginLimits
+}
+
+declare const sseTransformer: unique symbol
+export interface SseTransformerHandle {
+  readonly [sseTransformer]: true
+}
+
+// Utility functions
+export function getTokenCount(messages: Array<Message> | JsonBytes): TokenCount
+export function getNativeMetrics(): NativeMetrics
+export function getTokenCacheStats(): TokenCacheStats
+
+// GitHub API client functions
+// Bytes are sent as the request body as they are
+export function createChatCompletions(
+  payload: object | JsonBytes,
+  options: RequestOptions & { responseType: "buffer" },
+): RequestHandle<Buffer>
+export function createChatCompletions(
+  payload: object | JsonBytes,
+  options: RequestOptions,
+): RequestHandle
+export function createEmbeddings(
+  payload: object | JsonBytes,
+  options: RequestOptions & { responseType: "buffer" },
+): RequestHandle<Buffer>
+export function createEmbeddings(
+  payload: object | JsonBytes,
+  options: RequestOptions,
+): RequestHandle
+export function getModels(): Promise<string>
+export function withRetry(options: Partial<RetryPolicy>): RetryPolicy
+export function cancelRequest(id: string): boolean
+export function configureHttp(options: Partial<HttpConfig>): HttpConfig
+
+// WASM plugins, see native/src/plugins.rs for the ABI
+export function loadPlugin(
+  path: string,
+  limits: Partial<PluginLimits>,
+): PluginInfo
+// The transformed JSON, or null when the plugin left the input unchanged
+export function runPlugin(
+  id: number,
+  hook: PluginHook,
+  input: string,
+): string | null
+
+// Authentication functions, not implemented natively yet: both reject with an
+// AuthError
+export function setupGitHubToken(): Promise<string>
+export function refreshToken(): Promise<string>
+
+// Rate limiting and validation
+// Also consumes from each configured ancestor: "user:alice" -> "user" -> "global"
+export function checkRateLimit(
+  key: string,
+  intervalSecs?: number,
+  burstCapacity?: number,
+): boolean
+// Same as checkRateLimit, with how long to wait before a token frees up
+export function checkRateLimitDetailed(
+  key: string,
+  intervalSecs?: number,
+  burstCapacity?: number,
+): RateLimitDecision
+// Keys are "global", exact limiter keys or "prefix:*" patterns
+export function setRateLimitDefaults(config: Record<string, LimitConfig>): void
+export function getRateLimitStats(): RateLimitStats
+// Accepts an exact key or a glob ("user:*", "org:?"), returns the number removed
+export function resetRateLimit(pattern: string): number
+export function resetAllRateLimits(): number
+// Prunes limiters not checked for maxIdleSecs (default 3600), allowed or not,
+// every intervalSecs on a background thread; returns false if already running
+export function startRateLimitJanitor(
+  intervalSecs: number,
+  maxIdleSecs?: number,
+  onSweep?: (reclaimed: number) => void,
+): boolean
+export function stopRateLimitJanitor(): boolean
+export function validatePayload(payload: unknown): boolean
+export function validatePayloadDetailed(payload: unknown): ValidationResult
+// Caches results by payload JSON, off (0) by default
+export function configureValidationCache(capacity: number): ValidationCacheStats
+// Returns the number of results dropped
+export function clearValidationCache(): number
+export function getValidationCacheStats(): ValidationCacheStats
+
+// Payload format translation
+// API payloads are passed through untyped; see the TS translator for their shape.
+// Bytes in give the translated JSON back as a Buffer.
+export function translateAnthropicToOpenAI(payload: JsonBytes): Buffer
+export function translateAnthropicToOpenAI(payload: object): unknown
+export function translateOpenAIToAnthropic(response: JsonBytes): Buffer
+export function translateOpenAIToAnthropic(response: object): unknown
+
+// Streaming (SSE) parsing
+export function createSseTransformer(): SseTransformerHandle
+export function pushSseChunk(
+  transformer: SseTransformerHandle,
+  chunk: string | Uint8Array,
+): Array<SseEvent>
+export function flushSseTransformer(
+  transformer: SseTransformerHandle,
+): Array<SseEvent>
@@ -1,12 +1,14 @@
 use neon::prelude::*;
 
-// Placeholder implementations for now - will be implemented in Phase 3
+use crate::errors::NativeError;
+
+// Placeholder implementations for now - will be implemented in Phase 3.
+// The JS layer owns authentication, so these reject instead of pretending to succeed.
 pub fn setup_github_token(mut cx: FunctionContext) -> JsResult<JsPromise> {
     let (deferred, promise) = cx.promise();
     
     deferred.settle_with(&cx.channel(), move |mut cx| {
-        let result = cx.string("{\"placeholder\": \"auth_not_implemented\"}");
-        Ok(result)
+        NativeError::Auth("setupGitHubToken is not implemented in the native module".to_string()).throw::<_, Handle<JsString>>(&mut cx)
     });
     
     Ok(promise)
@@ -16,9 +18,8 @@ pub fn refresh_token(mut cx: FunctionContext) -> JsResult<JsPromise> {
     let (deferred, promise) = cx.promise();
     
     deferred.settle_with(&cx.channel(), move |mut cx| {
-        let result = cx.string("{\"placeholder\": \"refresh_not_implemented\"}");
-        Ok(result)
+        NativeError::Auth("refreshToken is not implemented in the native module".to_string()).throw::<_, Handle<JsString>>(&mut cx)
     });
     
     Ok(promise)
-}
\ No newline at end of file
+}
@@ -0,0 +1,66 @@
+use neon::prelude::*;
+use std::any::Any;
+
+// Errors thrown to JS. Each kind becomes an Error whose `name` and `code` are
+// stable, so rust-core.ts can rethrow it as the matching Error subclass.
+#[derive(Debug, thiserror::Error)]
+pub enum NativeError {
+    // Bad arguments or payloads
+    #[error("{0}")]
+    Validation(String),
+    // The request to Copilot failed, after retries
+    #[error("{0}")]
+    Upstream(String),
+    #[error("{0}")]
+    Auth(String),
+    // Rate limiter misconfiguration
+    #[error("{0}")]
+    RateLimit(String),
+    // A bug in the native module, e.g. a panic in a background task
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl NativeError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NativeError::Validation(_) => "ValidationError",
+            NativeError::Upstream(_) => "UpstreamError",
+            NativeError::Auth(_) => "AuthError",
+            NativeError::RateLimit(_) => "RateLimitError",
+            NativeError::Internal(_) => "InternalError",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            NativeError::Validation(_) => "ERR_NATIVE_VALIDATION",
+            NativeError::Upstream(_) => "ERR_NATIVE_UPSTREAM",
+            NativeError::Auth(_) => "ERR_NATIVE_AUTH",
+            NativeError::RateLimit(_) => "ERR_NATIVE_RATE_LIMIT",
+            NativeError::Internal(_) => "ERR_NATIVE_INTERNAL",
+        }
+    }
+
+    // Panics on the calling thread are already caught by Neon, which throws
+    // "internal error in Neon module: ..."; this covers tasks and threads
+    pub fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "unknown panic".to_string(),
+            },
+        };
+        NativeError::Internal(format!("panic in native module: {}", message))
+    }
+
+    pub fn throw<'a, C: Context<'a>, T>(self, cx: &mut C) -> NeonResult<T> {
+        let error = cx.error(self.to_string())?;
+        let name = cx.string(self.name());
+        let code = cx.string(self.code());
+        error.set(cx, "name", name)?;
+        error.set(cx, "code", code)?;
+        cx.throw(error)
+    }
+}
@@ -0,0 +1,144 @@
+use neon::prelude::*;
+use neon::types::extract::{Json, TryIntoJs};
+use serde::{Deserialize, Serialize};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+use crate::errors::NativeError;
+
+// Connection settings for the shared HTTP client
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub connect_timeout_ms: u64,
+    pub request_timeout_ms: Option<u64>,
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout_ms: u64,
+    pub tcp_keepalive_ms: u64,
+    pub http2: bool,
+    // Skips ALPN and speaks HTTP/2 from the first byte
+    pub http2_prior_knowledge: bool,
+    // Grows the HTTP/2 flow control window with the measured bandwidth
+    pub http2_adaptive_window: bool,
+    // PING interval that keeps idle HTTP/2 connections from being dropped
+    pub http2_keep_alive_interval_ms: Option<u64>,
+    // Sends Accept-Encoding: gzip, zstd and decodes compressed responses
+    pub compression: bool,
+    pub proxy: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 10_000,
+            // Streaming completions can legitimately run for minutes
+            request_timeout_ms: None,
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout_ms: 90_000,
+            tcp_keepalive_ms: 60_000,
+            http2: true,
+            http2_prior_knowledge: false,
+            http2_adaptive_window: false,
+            http2_keep_alive_interval_ms: None,
+            compression: true,
+            proxy: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    fn build_client(&self) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_millis(self.connect_timeout_ms))
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_millis(self.pool_idle_timeout_ms))
+            .tcp_keepalive(Duration::from_millis(self.tcp_keepalive_ms));
+
+        if let Some(timeout) = self.request_timeout_ms {
+            builder = builder.timeout(Duration::from_millis(timeout));
+        }
+        // HTTP/2 is negotiated through ALPN when enabled, otherwise pin HTTP/1.1
+        if !self.http2 {
+            if self.http2_prior_knowledge {
+                return Err("http2PriorKnowledge requires http2".to_string());
+            }
+            builder = builder.http1_only();
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if self.http2_adaptive_window {
+            builder = builder.http2_adaptive_window(true);
+        }
+        if let Some(interval) = self.http2_keep_alive_interval_ms {
+            builder = builder
+                .http2_keep_alive_interval(Duration::from_millis(interval))
+                .http2_keep_alive_while_idle(true);
+        }
+        if !self.compression {
+            builder = builder.no_gzip().no_zstd();
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| format!("Invalid proxy: {}", e))?;
+            builder = builder.proxy(proxy);
+        }
+
+        builder.build().map_err(|e| e.to_string())
+    }
+}
+
+struct SharedClient {
+    config: HttpConfig,
+    client: Option<reqwest::Client>,
+}
+
+lazy_static::lazy_static! {
+    static ref HTTP_CLIENT: RwLock<SharedClient> = RwLock::new(SharedClient {
+        config: HttpConfig::default(),
+        client: None,
+    });
+}
+
+// Returns the shared client, building it on first use.
+// reqwest::Client is reference counted, so clones share one connection pool.
+pub fn http_client() -> Result<reqwest::Client, String> {
+    if let Some(client) = &HTTP_CLIENT.read().unwrap_or_else(PoisonError::into_inner).client {
+        return Ok(client.clone());
+    }
+
+    let mut shared = HTTP_CLIENT.write().unwrap_or_else(PoisonError::into_inner);
+    if shared.client.is_none() {
+        shared.client = Some(shared.config.build_client()?);
+    }
+    Ok(shared.client.clone().unwrap())
+}
+
+// Merges the given options into the current config, rebuilds the client and returns the config.
+// Requests already in flight keep using the previous pool.
+pub fn configure_http(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let Json(options): Json<serde_json::Value> = cx.arg()?;
+
+    let fields = match options.as_object() {
+        Some(fields) => fields,
+        None => return NativeError::Validation("HTTP options must be an object".to_string()).throw(&mut cx),
+    };
+
+    let mut shared = HTTP_CLIENT.write().unwrap_or_else(PoisonError::into_inner);
+    let mut merged = serde_json::to_value(&shared.config).unwrap();
+    for (key, value) in fields {
+        merged[key] = value.clone();
+    }
+
+    let config: HttpConfig = match serde_json::from_value(merged) {
+        Ok(config) => config,
+        Err(e) => return NativeError::Validation(format!("Invalid HTTP options: {}", e)).throw(&mut cx),
+    };
+    let client = match config.build_client() {
+        Ok(client) => client,
+        Err(e) => return NativeError::Validation(e).throw(&mut cx),
+    };
+
+    shared.config = config.clone();
+    shared.client = Some(client);
+    Json(config).try_into_js(&mut cx)
+}
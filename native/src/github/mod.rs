@@ -1,36 +1,147 @@
 use neon::prelude::*;
+use neon::types::extract::Json;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
 
-// Placeholder implementations for now - will be implemented in Phase 3
-pub fn create_chat_completions(mut cx: FunctionContext) -> JsResult<JsPromise> {
-    let (deferred, promise) = cx.promise();
-    
-    // For now, return a placeholder response
-    deferred.settle_with(&cx.channel(), move |mut cx| {
-        let result = cx.string("{\"placeholder\": \"chat_completions_not_implemented\"}");
-        Ok(result)
-    });
-    
-    Ok(promise)
+use crate::errors::NativeError;
+use crate::utils::metrics::{self, UpstreamOutcome};
+use crate::utils::json::json_body;
+use crate::utils::lock;
+
+mod client;
+mod retry;
+
+pub use client::configure_http;
+pub use retry::with_retry;
+
+lazy_static::lazy_static! {
+    static ref RUNTIME: tokio::runtime::Runtime =
+        tokio::runtime::Runtime::new().expect("Failed to start Tokio runtime");
+    // In-flight requests by ID, so JS can abort them when the client disconnects
+    static ref IN_FLIGHT: Mutex<HashMap<String, oneshot::Sender<()>>> = Mutex::new(HashMap::new());
 }
 
-pub fn create_embeddings(mut cx: FunctionContext) -> JsResult<JsPromise> {
+// Connection details supplied by the JS layer, which owns the Copilot token
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestOptions {
+    base_url: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    response_type: ResponseType,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ResponseType {
+    #[default]
+    Text,
+    // Skips decoding the body into a JS string
+    Buffer,
+}
+
+// Returns { id, response } where response is a promise for the body text.
+// The payload may be JSON bytes, which are sent without parsing them.
+fn post_json<'a>(mut cx: FunctionContext<'a>, path: &str) -> JsResult<'a, JsObject> {
+    let (_, Json(options)): (Handle<JsValue>, Json<RequestOptions>) = cx.args()?;
+    let body = json_body(&mut cx, 0)?;
+
+    let url = format!("{}{}", options.base_url.trim_end_matches('/'), path);
+    let client = match client::http_client() {
+        Ok(client) => client,
+        Err(e) => return NativeError::Validation(e).throw(&mut cx),
+    };
+    let mut request = client
+        .post(url)
+        .header("content-type", "application/json")
+        .body(body);
+    for (name, value) in &options.headers {
+        request = request.header(name, value);
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    lock(&IN_FLIGHT).insert(id.clone(), cancel_tx);
+
     let (deferred, promise) = cx.promise();
-    
-    deferred.settle_with(&cx.channel(), move |mut cx| {
-        let result = cx.string("{\"placeholder\": \"embeddings_not_implemented\"}");
-        Ok(result)
+    let channel = cx.channel();
+    let request_id = id.clone();
+    let response_type = options.response_type;
+
+    RUNTIME.spawn(async move {
+        let start = std::time::Instant::now();
+        // Dropping the request future aborts the underlying connection
+        let send = RUNTIME.spawn(async move {
+            tokio::select! {
+                result = retry::send_with_retry(request) => Some(result.map_err(NativeError::Upstream)),
+                _ = cancel_rx => None,
+            }
+        });
+        // Sent from its own task so a panic still settles the promise
+        let result = match send.await {
+            Ok(result) => result,
+            Err(e) => Some(Err(NativeError::from_panic(e.into_panic()))),
+        };
+        lock(&IN_FLIGHT).remove(&request_id);
+        let outcome = match &result {
+            Some(Ok(_)) => UpstreamOutcome::Ok,
+            Some(Err(_)) => UpstreamOutcome::Failed,
+            None => UpstreamOutcome::Cancelled,
+        };
+        metrics::record_upstream_call(start.elapsed(), outcome);
+
+        deferred.settle_with(&channel, move |mut cx| match result {
+            Some(Ok(body)) => match response_type {
+                ResponseType::Text => Ok(cx.string(body).upcast::<JsValue>()),
+                ResponseType::Buffer => Ok(JsBuffer::from_slice(&mut cx, body.as_bytes())?.upcast()),
+            },
+            Some(Err(e)) => e.throw(&mut cx),
+            None => {
+                let error = cx.error("Request cancelled")?;
+                let name = cx.string("AbortError");
+                error.set(&mut cx, "name", name)?;
+                cx.throw(error)
+            }
+        });
     });
-    
-    Ok(promise)
+
+    let handle = cx.empty_object();
+    let id = cx.string(id);
+    handle.set(&mut cx, "id", id)?;
+    handle.set(&mut cx, "response", promise)?;
+    Ok(handle)
+}
+
+pub fn cancel_request(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+    let id = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    // A request that already settled is no longer registered
+    let cancelled = match lock(&IN_FLIGHT).remove(&id) {
+        Some(cancel_tx) => cancel_tx.send(()).is_ok(),
+        None => false,
+    };
+    Ok(cx.boolean(cancelled))
 }
 
+pub fn create_chat_completions(cx: FunctionContext) -> JsResult<JsObject> {
+    post_json(cx, "/chat/completions")
+}
+
+pub fn create_embeddings(cx: FunctionContext) -> JsResult<JsObject> {
+    post_json(cx, "/embeddings")
+}
+
+// Placeholder implementation for now - will be implemented in Phase 3
 pub fn get_models(mut cx: FunctionContext) -> JsResult<JsPromise> {
     let (deferred, promise) = cx.promise();
-    
+
     deferred.settle_with(&cx.channel(), move |mut cx| {
         let result = cx.string("{\"placeholder\": \"models_not_implemented\"}");
         Ok(result)
     });
-    
+
     Ok(promise)
-}
\ No newline at end of file
+}
@@ -0,0 +1,160 @@
+use neon::prelude::*;
+use neon::types::extract::{Json, TryIntoJs};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::NativeError;
+use crate::utils::lock;
+
+// Retry policy shared by every native GitHub API call
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: bool,
+    pub retry_on_status: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 250,
+            max_delay_ms: 10_000,
+            jitter: true,
+            retry_on_status: vec![429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.min(32))
+            .min(self.max_delay_ms);
+
+        // "Equal jitter": half fixed, half random, so retries never bunch up at zero
+        let delay = if self.jitter {
+            exponential / 2 + random_u64() % (exponential / 2 + 1)
+        } else {
+            exponential
+        };
+        Duration::from_millis(delay)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref RETRY_POLICY: Mutex<RetryPolicy> = Mutex::new(RetryPolicy::default());
+}
+
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+// Delay-seconds or an HTTP-date, which is in the past when the clocks disagree
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+// Connection failures and timeouts are worth retrying, malformed requests are not
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+// reqwest's Display stops at "error sending request", so append the underlying causes
+fn describe_error(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+pub async fn send_with_retry(request: reqwest::RequestBuilder) -> Result<String, String> {
+    let policy = lock(&RETRY_POLICY).clone();
+    let mut attempt = 0;
+
+    loop {
+        let attempt_request = request
+            .try_clone()
+            .ok_or("Request body cannot be retried")?;
+
+        let delay = match attempt_request.send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status.is_success() {
+                    return response.text().await.map_err(|e| e.to_string());
+                }
+
+                let retry_after = parse_retry_after(response.headers());
+                let body = response.text().await.unwrap_or_default();
+                if attempt >= policy.max_retries || !policy.retry_on_status.contains(&status.as_u16()) {
+                    return Err(format!("HTTP {}: {}", status.as_u16(), body));
+                }
+
+                match retry_after {
+                    // Retrying sooner than asked would only be refused again
+                    Some(delay) if delay > Duration::from_millis(policy.max_delay_ms) => {
+                        return Err(format!(
+                            "HTTP {}: {} (Retry-After of {}s is over maxDelayMs)",
+                            status.as_u16(),
+                            body,
+                            delay.as_secs()
+                        ));
+                    }
+                    Some(delay) => delay,
+                    None => policy.backoff(attempt),
+                }
+            }
+            Err(e) => {
+                if attempt >= policy.max_retries || !is_transient(&e) {
+                    return Err(describe_error(&e));
+                }
+                policy.backoff(attempt)
+            }
+        };
+
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+    }
+}
+
+// Merges the given options into the current policy and returns the result
+pub fn with_retry(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let Json(options): Json<serde_json::Value> = cx.arg()?;
+
+    let mut current = serde_json::to_value(&*lock(&RETRY_POLICY)).unwrap();
+    match options.as_object() {
+        Some(fields) => {
+            for (key, value) in fields {
+                current[key] = value.clone();
+            }
+        }
+        None => return NativeError::Validation("Retry options must be an object".to_string()).throw(&mut cx),
+    }
+
+    let policy: RetryPolicy = match serde_json::from_value(current) {
+        Ok(policy) => policy,
+        Err(e) => return NativeError::Validation(format!("Invalid retry options: {}", e)).throw(&mut cx),
+    };
+    if policy.base_delay_ms > policy.max_delay_ms {
+        return NativeError::Validation("baseDelayMs must not exceed maxDelayMs".to_string()).throw(&mut cx);
+    }
+
+    *lock(&RETRY_POLICY) = policy.clone();
+    Json(policy).try_into_js(&mut cx)
+}
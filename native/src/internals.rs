@@ -0,0 +1,9 @@
+// The hot path without its Neon wrappers: the entry points that take
+// untrusted client or upstream input, for the cargo-fuzz targets and
+// property tests in fuzz/, and the benchmarks in bench/. Not part of the
+// addon API.
+
+pub use crate::processing::translation::{anthropic_to_openai, openai_to_anthropic};
+pub use crate::streaming::sse::{SseEvent, SseTransformer};
+pub use crate::utils::tokenizer::{count_tokens, Message, TokenCount};
+pub use crate::utils::validation::{validate, ValidationResult, Violation};
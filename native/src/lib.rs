@@ -1,18 +1,39 @@
+#[cfg(feature = "node")]
 use neon::prelude::*;
 
+#[cfg(feature = "node")]
+mod errors;
+#[cfg(feature = "node")]
 mod github;
+#[cfg(feature = "node")]
 mod auth;
+#[cfg(feature = "node")]
+mod processing;
+#[cfg(feature = "node")]
+mod streaming;
 mod utils;
+#[cfg(feature = "plugins")]
+mod plugins;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "internals")]
+pub mod internals;
 
+#[cfg(feature = "node")]
 #[neon::main]
 fn main(mut cx: ModuleContext) -> NeonResult<()> {
     // Utility functions
     cx.export_function("getTokenCount", utils::tokenizer::get_token_count)?;
+    cx.export_function("getTokenCacheStats", utils::tokenizer::get_token_cache_stats)?;
+    cx.export_function("getNativeMetrics", utils::metrics::get_native_metrics)?;
     
     // GitHub API client functions
     cx.export_function("createChatCompletions", github::create_chat_completions)?;
     cx.export_function("createEmbeddings", github::create_embeddings)?;
     cx.export_function("getModels", github::get_models)?;
+    cx.export_function("withRetry", github::with_retry)?;
+    cx.export_function("cancelRequest", github::cancel_request)?;
+    cx.export_function("configureHttp", github::configure_http)?;
     
     // Authentication functions
     cx.export_function("setupGitHubToken", auth::setup_github_token)?;
@@ -20,10 +41,34 @@ fn main(mut cx: ModuleContext) -> NeonResult<()> {
     
     // Rate limiting and validation
     cx.export_function("checkRateLimit", utils::rate_limit::check_rate_limit)?;
+    cx.export_function("checkRateLimitDetailed", utils::rate_limit::check_rate_limit_detailed)?;
     cx.export_function("getRateLimitStats", utils::rate_limit::get_rate_limit_stats)?;
     cx.export_function("resetRateLimit", utils::rate_limit::reset_rate_limit)?;
+    cx.export_function("resetAllRateLimits", utils::rate_limit::reset_all_rate_limits)?;
+    cx.export_function("setRateLimitDefaults", utils::rate_limit::set_rate_limit_defaults)?;
+    cx.export_function("startRateLimitJanitor", utils::rate_limit::start_rate_limit_janitor)?;
+    cx.export_function("stopRateLimitJanitor", utils::rate_limit::stop_rate_limit_janitor)?;
     cx.export_function("validatePayload", utils::validation::validate_payload)?;
     cx.export_function("validatePayloadDetailed", utils::validation::validate_payload_detailed)?;
+    cx.export_function("configureValidationCache", utils::validation::configure_validation_cache)?;
+    cx.export_function("clearValidationCache", utils::validation::clear_validation_cache)?;
+    cx.export_function("getValidationCacheStats", utils::validation::get_validation_cache_stats)?;
+    
+    // Payload format translation
+    cx.export_function("translateAnthropicToOpenAI", processing::translation::translate_anthropic_to_openai)?;
+    cx.export_function("translateOpenAIToAnthropic", processing::translation::translate_openai_to_anthropic)?;
+    
+    // WASM plugins
+    #[cfg(feature = "plugins")]
+    {
+        cx.export_function("loadPlugin", plugins::load_plugin)?;
+        cx.export_function("runPlugin", plugins::run_plugin)?;
+    }
+
+    // Streaming (SSE) parsing
+    cx.export_function("createSseTransformer", streaming::sse::create_sse_transformer)?;
+    cx.export_function("pushSseChunk", streaming::sse::push_sse_chunk)?;
+    cx.export_function("flushSseTransformer", streaming::sse::flush_sse_transformer)?;
     
     Ok(())
 }
\ No newline at end of file
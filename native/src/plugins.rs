@@ -0,0 +1,187 @@
+// Request and response transforms loaded from user-supplied WASM modules.
+//
+// A plugin exports `memory`, `alloc(len: i32) -> i32` and at least one of
+// `transform_request` and `transform_response`, both
+// `(ptr: i32, len: i32) -> i64`. The host writes a UTF-8 JSON document to
+// memory obtained from `alloc` and calls the transform, which returns
+// `(ptr << 32) | len` of its JSON result, or 0 to leave the input unchanged.
+// Plugins get no imports, so they cannot reach files, the network or the
+// clock, and every call runs in a fresh instance with bounded fuel and
+// memory.
+
+use anyhow::{bail, Context as _};
+use neon::prelude::*;
+use neon::types::extract::{Json, TryIntoJs};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::errors::NativeError;
+use crate::utils::lock;
+
+const DEFAULT_FUEL: u64 = 100_000_000;
+const DEFAULT_MAX_MEMORY_BYTES: usize = 64 << 20;
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 16 << 20;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PluginLimits {
+    // Roughly one unit per WASM instruction; a call that runs out traps
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+    #[serde(default = "default_max_memory_bytes")]
+    pub max_memory_bytes: usize,
+    // Checked before the output is copied out of the plugin's memory
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+}
+
+fn default_fuel() -> u64 {
+    DEFAULT_FUEL
+}
+
+fn default_max_memory_bytes() -> usize {
+    DEFAULT_MAX_MEMORY_BYTES
+}
+
+fn default_max_output_bytes() -> usize {
+    DEFAULT_MAX_OUTPUT_BYTES
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hook {
+    Request,
+    Response,
+}
+
+impl Hook {
+    fn export_name(self) -> &'static str {
+        match self {
+            Hook::Request => "transform_request",
+            Hook::Response => "transform_response",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    pub id: usize,
+    pub hooks: Vec<Hook>,
+    pub limits: PluginLimits,
+}
+
+struct Plugin {
+    module: Module,
+    limits: PluginLimits,
+}
+
+lazy_static::lazy_static! {
+    static ref ENGINE: Engine = {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        Engine::new(&config).expect("Failed to start the WASM engine")
+    };
+    // Indexed by plugin id; plugins are only added at startup
+    static ref PLUGINS: Mutex<Vec<Plugin>> = Mutex::new(Vec::new());
+}
+
+// Compiles a module and checks it against the ABI before it is registered
+fn compile(bytes: &[u8], limits: PluginLimits) -> anyhow::Result<PluginInfo> {
+    let module = Module::new(&ENGINE, bytes)?;
+    if let Some(import) = module.imports().next() {
+        bail!(
+            "plugins cannot import host functions, found {}::{}",
+            import.module(),
+            import.name()
+        );
+    }
+    for required in ["memory", "alloc"] {
+        if module.get_export(required).is_none() {
+            bail!("missing export `{}`", required);
+        }
+    }
+    let hooks: Vec<Hook> = [Hook::Request, Hook::Response]
+        .into_iter()
+        .filter(|hook| module.get_export(hook.export_name()).is_some())
+        .collect();
+    if hooks.is_empty() {
+        bail!("exports neither `transform_request` nor `transform_response`");
+    }
+
+    let mut plugins = lock(&PLUGINS);
+    plugins.push(Plugin { module, limits });
+    Ok(PluginInfo {
+        id: plugins.len() - 1,
+        hooks,
+        limits,
+    })
+}
+
+fn run(module: &Module, limits: PluginLimits, hook: Hook, input: &str) -> anyhow::Result<Option<String>> {
+    let mut store = Store::new(
+        &ENGINE,
+        StoreLimitsBuilder::new().memory_size(limits.max_memory_bytes).build(),
+    );
+    store.limiter(|limits: &mut StoreLimits| limits);
+    store.set_fuel(limits.fuel)?;
+
+    let instance = Instance::new(&mut store, module, &[])?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .context("`memory` is not a memory")?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+    let transform = instance
+        .get_typed_func::<(i32, i32), i64>(&mut store, hook.export_name())
+        .with_context(|| format!("plugin has no `{}`", hook.export_name()))?;
+
+    let len = i32::try_from(input.len()).context("input too large")?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory.write(&mut store, ptr as u32 as usize, input.as_bytes())?;
+
+    let packed = transform.call(&mut store, (ptr, len))? as u64;
+    if packed == 0 {
+        return Ok(None);
+    }
+    // The length comes from the plugin, so it is checked before allocating
+    let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+    if out_len > limits.max_output_bytes {
+        bail!("output of {} bytes is over the {} byte limit", out_len, limits.max_output_bytes);
+    }
+    if out_ptr.checked_add(out_len).is_none_or(|end| end > memory.data_size(&store)) {
+        bail!("output at {}+{} is outside the plugin's memory", out_ptr, out_len);
+    }
+    let mut output = vec![0; out_len];
+    memory.read(&store, out_ptr, &mut output)?;
+    Ok(Some(String::from_utf8(output).context("output is not UTF-8")?))
+}
+
+pub fn load_plugin(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let (path, Json(limits)): (String, Json<PluginLimits>) = cx.args()?;
+
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) => return NativeError::Validation(format!("Failed to read plugin {}: {}", path, e)).throw(&mut cx),
+    };
+    match compile(&bytes, limits) {
+        Ok(info) => Json(info).try_into_js(&mut cx),
+        Err(e) => NativeError::Validation(format!("Invalid plugin {}: {:#}", path, e)).throw(&mut cx),
+    }
+}
+
+// Returns the transformed JSON, or null when the plugin left the input unchanged
+pub fn run_plugin(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let (id, Json(hook), input): (f64, Json<Hook>, String) = cx.args()?;
+
+    // Modules are reference counted, so the lock is not held during the call
+    let (module, limits) = match lock(&PLUGINS).get(id as usize) {
+        Some(plugin) => (plugin.module.clone(), plugin.limits),
+        None => return NativeError::Validation(format!("Unknown plugin {}", id)).throw(&mut cx),
+    };
+    match run(&module, limits, hook, &input) {
+        Ok(Some(output)) => Ok(cx.string(output).upcast()),
+        Ok(None) => Ok(cx.null().upcast()),
+        Err(e) => NativeError::Validation(format!("{:#}", e)).throw(&mut cx),
+    }
+}
@@ -0,0 +1 @@
+pub mod translation;
\ No newline at end of file
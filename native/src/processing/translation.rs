@@ -0,0 +1,294 @@
+use neon::prelude::*;
+use serde_json::{json, Map, Value};
+
+use crate::errors::NativeError;
+use crate::utils::json::{json_arg, json_output};
+
+// Anthropic Messages API <-> OpenAI Chat Completions translation.
+// Mirrors src/routes/messages/non-stream-translation.ts.
+
+fn handle_system_prompt(system: Option<&Value>) -> Vec<Value> {
+    match system {
+        Some(Value::String(text)) if !text.is_empty() => {
+            vec![json!({ "role": "system", "content": text })]
+        }
+        Some(Value::Array(blocks)) => {
+            let text = blocks
+                .iter()
+                .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<&str>>()
+                .join("\n\n");
+            vec![json!({ "role": "system", "content": text })]
+        }
+        _ => vec![],
+    }
+}
+
+fn block_type(block: &Value) -> Option<&str> {
+    block.get("type").and_then(|t| t.as_str())
+}
+
+fn map_content(content: &Value) -> Value {
+    let blocks = match content {
+        Value::String(_) => return content.clone(),
+        Value::Array(blocks) => blocks,
+        _ => return Value::Null,
+    };
+
+    let has_image = blocks.iter().any(|block| block_type(block) == Some("image"));
+    if !has_image {
+        let text = blocks
+            .iter()
+            .filter(|block| block_type(block) == Some("text"))
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<&str>>()
+            .join("\n\n");
+        return Value::String(text);
+    }
+
+    let parts: Vec<Value> = blocks
+        .iter()
+        .filter_map(|block| match block_type(block) {
+            Some("text") => Some(json!({ "type": "text", "text": block["text"] })),
+            Some("image") => {
+                let source = &block["source"];
+                let url = format!(
+                    "data:{};base64,{}",
+                    source["media_type"].as_str().unwrap_or(""),
+                    source["data"].as_str().unwrap_or("")
+                );
+                Some(json!({ "type": "image_url", "image_url": { "url": url } }))
+            }
+            _ => None,
+        })
+        .collect();
+    Value::Array(parts)
+}
+
+fn handle_user_message(message: &Value) -> Vec<Value> {
+    let content = &message["content"];
+    let blocks = match content.as_array() {
+        Some(blocks) => blocks,
+        None => return vec![json!({ "role": "user", "content": map_content(content) })],
+    };
+
+    let (tool_results, other_blocks): (Vec<&Value>, Vec<&Value>) = blocks
+        .iter()
+        .partition(|block| block_type(block) == Some("tool_result"));
+
+    let mut messages = Vec::new();
+    if !other_blocks.is_empty() {
+        let other = Value::Array(other_blocks.into_iter().cloned().collect());
+        messages.push(json!({ "role": "user", "content": map_content(&other) }));
+    }
+
+    messages.extend(tool_results.into_iter().map(|block| {
+        json!({
+            "role": "tool",
+            "tool_call_id": block["tool_use_id"],
+            "content": block["content"],
+        })
+    }));
+
+    messages
+}
+
+fn handle_assistant_message(message: &Value) -> Vec<Value> {
+    let content = &message["content"];
+    let blocks = match content.as_array() {
+        Some(blocks) => blocks,
+        None => return vec![json!({ "role": "assistant", "content": map_content(content) })],
+    };
+
+    let tool_uses: Vec<&Value> = blocks
+        .iter()
+        .filter(|block| block_type(block) == Some("tool_use"))
+        .collect();
+
+    if tool_uses.is_empty() {
+        return vec![json!({ "role": "assistant", "content": map_content(content) })];
+    }
+
+    let text = blocks
+        .iter()
+        .filter(|block| block_type(block) == Some("text"))
+        .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+        .collect::<Vec<&str>>()
+        .join("\n\n");
+
+    let tool_calls: Vec<Value> = tool_uses
+        .into_iter()
+        .map(|tool_use| {
+            json!({
+                "id": tool_use["id"],
+                "type": "function",
+                "function": {
+                    "name": tool_use["name"],
+                    "arguments": tool_use["input"].to_string(),
+                },
+            })
+        })
+        .collect();
+
+    vec![json!({
+        "role": "assistant",
+        "content": if text.is_empty() { Value::Null } else { Value::String(text) },
+        "tool_calls": tool_calls,
+    })]
+}
+
+fn translate_tools(tools: Option<&Value>) -> Option<Value> {
+    let tools = tools?.as_array()?;
+    Some(Value::Array(
+        tools
+            .iter()
+            .map(|tool| {
+                let mut function = Map::new();
+                function.insert("name".to_string(), tool["name"].clone());
+                if let Some(description) = tool.get("description") {
+                    function.insert("description".to_string(), description.clone());
+                }
+                function.insert("parameters".to_string(), tool["input_schema"].clone());
+                json!({ "type": "function", "function": function })
+            })
+            .collect(),
+    ))
+}
+
+fn translate_tool_choice(tool_choice: Option<&Value>) -> Option<Value> {
+    let tool_choice = tool_choice?;
+    match block_type(tool_choice)? {
+        "auto" => Some(json!("auto")),
+        "any" => Some(json!("required")),
+        "none" => Some(json!("none")),
+        "tool" => {
+            let name = tool_choice.get("name").and_then(|n| n.as_str())?;
+            Some(json!({ "type": "function", "function": { "name": name } }))
+        }
+        _ => None,
+    }
+}
+
+pub fn anthropic_to_openai(payload: &Value) -> Result<Value, String> {
+    let anthropic_messages = payload
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .ok_or("Missing or invalid 'messages' field")?;
+
+    let mut messages = handle_system_prompt(payload.get("system"));
+    for message in anthropic_messages {
+        match message.get("role").and_then(|r| r.as_str()) {
+            Some("user") => messages.extend(handle_user_message(message)),
+            Some("assistant") => messages.extend(handle_assistant_message(message)),
+            _ => return Err("Messages must have role 'user' or 'assistant'".to_string()),
+        }
+    }
+
+    let mut result = Map::new();
+    result.insert("model".to_string(), payload.get("model").cloned().unwrap_or(Value::Null));
+    result.insert("messages".to_string(), Value::Array(messages));
+
+    // Optional fields are only copied when present, like JSON.stringify drops undefined
+    for (from, to) in [
+        ("max_tokens", "max_tokens"),
+        ("stop_sequences", "stop"),
+        ("stream", "stream"),
+        ("temperature", "temperature"),
+        ("top_p", "top_p"),
+    ] {
+        if let Some(value) = payload.get(from) {
+            result.insert(to.to_string(), value.clone());
+        }
+    }
+
+    if let Some(user_id) = payload.get("metadata").and_then(|m| m.get("user_id")) {
+        result.insert("user".to_string(), user_id.clone());
+    }
+    if let Some(tools) = translate_tools(payload.get("tools")) {
+        result.insert("tools".to_string(), tools);
+    }
+    if let Some(tool_choice) = translate_tool_choice(payload.get("tool_choice")) {
+        result.insert("tool_choice".to_string(), tool_choice);
+    }
+
+    Ok(Value::Object(result))
+}
+
+pub fn map_stop_reason(finish_reason: &Value) -> Value {
+    match finish_reason.as_str() {
+        Some("stop") | Some("content_filter") => json!("end_turn"),
+        Some("length") => json!("max_tokens"),
+        Some("tool_calls") => json!("tool_use"),
+        _ => Value::Null,
+    }
+}
+
+pub fn openai_to_anthropic(response: &Value) -> Result<Value, String> {
+    let choice = response
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .and_then(|c| c.first())
+        .ok_or("Response has no choices")?;
+    let message = &choice["message"];
+
+    let mut content: Vec<Value> = match &message["content"] {
+        Value::String(text) => vec![json!({ "type": "text", "text": text })],
+        Value::Array(parts) => parts
+            .iter()
+            .filter(|part| block_type(part) == Some("text"))
+            .map(|part| json!({ "type": "text", "text": part["text"] }))
+            .collect(),
+        _ => vec![],
+    };
+
+    if let Some(tool_calls) = message.get("tool_calls").and_then(|t| t.as_array()) {
+        for tool_call in tool_calls {
+            let arguments = tool_call["function"]["arguments"].as_str().unwrap_or("{}");
+            let input: Value = serde_json::from_str(arguments)
+                .map_err(|e| format!("Invalid tool call arguments: {}", e))?;
+            content.push(json!({
+                "type": "tool_use",
+                "id": tool_call["id"],
+                "name": tool_call["function"]["name"],
+                "input": input,
+            }));
+        }
+    }
+
+    let usage = &response["usage"];
+    Ok(json!({
+        "id": response["id"],
+        "type": "message",
+        "role": "assistant",
+        "model": response["model"],
+        "content": content,
+        "stop_reason": map_stop_reason(&choice["finish_reason"]),
+        "stop_sequence": null,
+        "usage": {
+            "input_tokens": usage["prompt_tokens"].as_u64().unwrap_or(0),
+            "output_tokens": usage["completion_tokens"].as_u64().unwrap_or(0),
+        },
+    }))
+}
+
+// API payloads pass through as JSON values, translated field by field like the TS version.
+// Bytes in give bytes out, so a large payload never becomes a JS object.
+fn translate_with<'a>(
+    mut cx: FunctionContext<'a>,
+    translate: fn(&Value) -> Result<Value, String>,
+) -> JsResult<'a, JsValue> {
+    let input = json_arg::<Value>(&mut cx, 0)?;
+
+    match translate(&input.value) {
+        Ok(output) => json_output(&mut cx, output, input.bytes),
+        Err(e) => NativeError::Validation(e).throw(&mut cx),
+    }
+}
+
+pub fn translate_anthropic_to_openai(cx: FunctionContext) -> JsResult<JsValue> {
+    translate_with(cx, anthropic_to_openai)
+}
+
+pub fn translate_openai_to_anthropic(cx: FunctionContext) -> JsResult<JsValue> {
+    translate_with(cx, openai_to_anthropic)
+}
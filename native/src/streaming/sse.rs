@@ -0,0 +1,226 @@
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use neon::types::extract::{Json, TryIntoJs};
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::RefCell;
+
+use crate::errors::NativeError;
+
+// Normalized event, serialized as { "type": "text_delta", ... }
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SseEvent {
+    TextDelta {
+        index: u64,
+        text: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ToolCallDelta {
+        index: u64,
+        tool_index: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        arguments: Option<String>,
+    },
+    Finish {
+        index: u64,
+        reason: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Usage {
+        prompt_tokens: u64,
+        completion_tokens: u64,
+        total_tokens: u64,
+    },
+    Done,
+    Error {
+        message: String,
+        data: String,
+    },
+}
+
+// Incremental parser for Copilot chat completion SSE streams.
+// Network chunks can end anywhere, including inside a multi-byte character,
+// so raw bytes are buffered until a full line is available. Lines end in
+// "\r\n", "\n" or "\r", as in the SSE spec.
+#[derive(Default)]
+pub struct SseTransformer {
+    buffer: Vec<u8>,
+    // Bytes of the buffer already searched for a line end
+    scanned: usize,
+    // The last chunk ended in "\r", so a "\n" opening the next one ends no line
+    after_cr: bool,
+    data_lines: Vec<String>,
+    done: bool,
+}
+
+impl Finalize for SseTransformer {}
+
+impl SseTransformer {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut chunk = chunk;
+        if std::mem::take(&mut self.after_cr) && chunk.first() == Some(&b'\n') {
+            chunk = &chunk[1..];
+        }
+        self.buffer.extend_from_slice(chunk);
+
+        // Taken so lines can borrow it while process_line borrows self
+        let buffer = std::mem::take(&mut self.buffer);
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut i = self.scanned;
+        while i < buffer.len() {
+            let byte = buffer[i];
+            if byte == b'\n' || byte == b'\r' {
+                self.process_line(&String::from_utf8_lossy(&buffer[start..i]), &mut events);
+                if byte == b'\r' {
+                    match buffer.get(i + 1) {
+                        Some(b'\n') => i += 1,
+                        Some(_) => {}
+                        None => self.after_cr = true,
+                    }
+                }
+                start = i + 1;
+            }
+            i += 1;
+        }
+
+        self.buffer = buffer;
+        self.buffer.drain(..start);
+        self.scanned = self.buffer.len();
+        events
+    }
+
+    // Dispatches whatever is left once the upstream connection closes
+    pub fn flush(&mut self) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        if !self.buffer.is_empty() {
+            let rest = std::mem::take(&mut self.buffer);
+            self.process_line(&String::from_utf8_lossy(&rest), &mut events);
+        }
+        self.scanned = 0;
+        self.after_cr = false;
+        self.dispatch(&mut events);
+        events
+    }
+
+    fn process_line(&mut self, line: &str, events: &mut Vec<SseEvent>) {
+        if line.is_empty() {
+            self.dispatch(events);
+            return;
+        }
+        // Comments and keep-alives
+        if line.starts_with(':') {
+            return;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        if field == "data" {
+            self.data_lines.push(value.to_string());
+        }
+    }
+
+    fn dispatch(&mut self, events: &mut Vec<SseEvent>) {
+        if self.data_lines.is_empty() {
+            return;
+        }
+        let data = self.data_lines.join("\n");
+        self.data_lines.clear();
+
+        if self.done {
+            return;
+        }
+        if data.trim() == "[DONE]" {
+            self.done = true;
+            events.push(SseEvent::Done);
+            return;
+        }
+
+        match serde_json::from_str::<Value>(&data) {
+            Ok(chunk) => normalize_chunk(&chunk, events),
+            Err(e) => events.push(SseEvent::Error {
+                message: format!("Invalid JSON in SSE data: {}", e),
+                data,
+            }),
+        }
+    }
+}
+
+fn normalize_chunk(chunk: &Value, events: &mut Vec<SseEvent>) {
+    let choices = chunk["choices"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+    for choice in choices {
+        let index = choice["index"].as_u64().unwrap_or(0);
+        let delta = &choice["delta"];
+
+        if let Some(text) = delta["content"].as_str().filter(|t| !t.is_empty()) {
+            events.push(SseEvent::TextDelta { index, text: text.to_string() });
+        }
+
+        if let Some(tool_calls) = delta["tool_calls"].as_array() {
+            for tool_call in tool_calls {
+                let field = |value: &Value| value.as_str().map(str::to_string);
+                events.push(SseEvent::ToolCallDelta {
+                    index,
+                    tool_index: tool_call["index"].as_u64().unwrap_or(0),
+                    id: field(&tool_call["id"]),
+                    name: field(&tool_call["function"]["name"]),
+                    arguments: field(&tool_call["function"]["arguments"]),
+                });
+            }
+        }
+
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            events.push(SseEvent::Finish { index, reason: reason.to_string() });
+        }
+    }
+
+    let usage = &chunk["usage"];
+    if usage.is_object() {
+        events.push(SseEvent::Usage {
+            prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+            completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+            total_tokens: usage["total_tokens"].as_u64().unwrap_or(0),
+        });
+    }
+}
+
+type BoxedTransformer = JsBox<RefCell<SseTransformer>>;
+
+fn events_to_js<'a>(cx: &mut FunctionContext<'a>, events: Vec<SseEvent>) -> JsResult<'a, JsValue> {
+    Json(events).try_into_js(cx)
+}
+
+pub fn create_sse_transformer(mut cx: FunctionContext) -> JsResult<BoxedTransformer> {
+    Ok(cx.boxed(RefCell::new(SseTransformer::default())))
+}
+
+// Accepts a string or a Buffer/Uint8Array and returns the completed events
+pub fn push_sse_chunk(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let transformer = cx.argument::<BoxedTransformer>(0)?;
+    let chunk = cx.argument::<JsValue>(1)?;
+
+    let bytes = if let Ok(text) = chunk.downcast::<JsString, _>(&mut cx) {
+        text.value(&mut cx).into_bytes()
+    } else if let Ok(buffer) = chunk.downcast::<JsUint8Array, _>(&mut cx) {
+        buffer.as_slice(&cx).to_vec()
+    } else {
+        return NativeError::Validation("Chunk must be a string or Uint8Array".to_string()).throw(&mut cx);
+    };
+
+    let events = transformer.borrow_mut().push(&bytes);
+    events_to_js(&mut cx, events)
+}
+
+pub fn flush_sse_transformer(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let transformer = cx.argument::<BoxedTransformer>(0)?;
+    let events = transformer.borrow_mut().flush();
+    events_to_js(&mut cx, events)
+}
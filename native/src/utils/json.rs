@@ -0,0 +1,77 @@
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use neon::types::extract::{Json, TryFromJs, TryIntoJs};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::NativeError;
+
+// JSON arguments may also be passed as UTF-8 bytes (Buffer, Uint8Array or
+// ArrayBuffer). Rust then parses them in place, instead of JS stringifying the
+// object and copying the string across, which matters for multi-megabyte
+// conversations.
+pub struct JsonInput<T> {
+    pub value: T,
+    // Whether the argument was bytes, so outputs can be bytes too
+    pub bytes: bool,
+}
+
+// Hands f back when the value is not bytes
+fn with_bytes<R, F: FnOnce(&[u8]) -> R>(cx: &mut FunctionContext, value: Handle<JsValue>, f: F) -> Result<R, F> {
+    // A Buffer is a Uint8Array
+    if let Ok(array) = value.downcast::<JsTypedArray<u8>, _>(cx) {
+        return Ok(f(array.as_slice(cx)));
+    }
+    if let Ok(buffer) = value.downcast::<JsArrayBuffer, _>(cx) {
+        return Ok(f(buffer.as_slice(cx)));
+    }
+    Err(f)
+}
+
+pub fn json_arg<T: DeserializeOwned>(cx: &mut FunctionContext, i: usize) -> NeonResult<JsonInput<T>> {
+    let value = cx.argument::<JsValue>(i)?;
+
+    match with_bytes(cx, value, |bytes| serde_json::from_slice::<T>(bytes)) {
+        Ok(Ok(value)) => Ok(JsonInput { value, bytes: true }),
+        Ok(Err(e)) => NativeError::Validation(format!("Invalid JSON: {}", e)).throw(cx),
+        Err(_) => Json::<T>::from_js(cx, value).map(|Json(value)| JsonInput { value, bytes: false }),
+    }
+}
+
+// Calls f with the argument as JSON text: bytes as they are, anything else
+// through JSON.stringify. For callers that look at the text before parsing it.
+pub fn with_json_text<R>(cx: &mut FunctionContext, i: usize, f: impl FnOnce(&[u8]) -> R) -> NeonResult<R> {
+    let value = cx.argument::<JsValue>(i)?;
+
+    let f = match with_bytes(cx, value, f) {
+        Ok(result) => return Ok(result),
+        Err(f) => f,
+    };
+    let stringify: Handle<JsFunction> = cx.global::<JsObject>("JSON")?.get(cx, "stringify")?;
+    let text = stringify.call_with(cx).arg(value).apply::<JsValue, _>(cx)?;
+    match text.downcast::<JsString, _>(cx) {
+        Ok(text) => Ok(f(text.value(cx).as_bytes())),
+        Err(_) => NativeError::Validation("Expected a JSON value".to_string()).throw(cx),
+    }
+}
+
+// A request body: bytes are sent as they are, without parsing them first
+pub fn json_body(cx: &mut FunctionContext, i: usize) -> NeonResult<Vec<u8>> {
+    let value = cx.argument::<JsValue>(i)?;
+
+    match with_bytes(cx, value, <[u8]>::to_vec) {
+        Ok(body) => Ok(body),
+        Err(_) => Json::<serde_json::Value>::from_js(cx, value).map(|Json(payload)| payload.to_string().into_bytes()),
+    }
+}
+
+// The JSON as a Buffer when bytes is set, skipping JSON.parse, else as an object
+pub fn json_output<'a, T: Serialize>(cx: &mut FunctionContext<'a>, value: T, bytes: bool) -> JsResult<'a, JsValue> {
+    if !bytes {
+        return Json(value).try_into_js(cx);
+    }
+    match serde_json::to_vec(&value) {
+        Ok(data) => Ok(JsBuffer::from_slice(cx, &data)?.upcast()),
+        Err(e) => NativeError::Internal(e.to_string()).throw(cx),
+    }
+}
@@ -0,0 +1,89 @@
+use neon::prelude::*;
+use neon::types::extract::{Json, TryIntoJs};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use super::lock;
+use std::time::Duration;
+
+// Counters kept inside the native module. getNativeMetrics hands a snapshot
+// to the Node server, which reports it alongside its own metrics.
+#[derive(Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeMetrics {
+    pub tokenizer: TokenizerMetrics,
+    // By error code, e.g. "unknown_tool_call_id"
+    pub validation_failures: BTreeMap<&'static str, u64>,
+    pub rate_limit: RateLimitMetrics,
+    pub upstream: UpstreamMetrics,
+}
+
+#[derive(Default, Clone, Serialize)]
+pub struct TokenizerMetrics {
+    pub calls: u64,
+    pub seconds: f64,
+}
+
+#[derive(Default, Clone, Serialize)]
+pub struct RateLimitMetrics {
+    pub allowed: u64,
+    pub rejected: u64,
+}
+
+// Requests sent through createChatCompletions and createEmbeddings
+#[derive(Default, Clone, Serialize)]
+pub struct UpstreamMetrics {
+    pub ok: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    // Including retries
+    pub seconds: f64,
+}
+
+pub enum UpstreamOutcome {
+    Ok,
+    Failed,
+    Cancelled,
+}
+
+lazy_static::lazy_static! {
+    static ref METRICS: Mutex<NativeMetrics> = Mutex::new(NativeMetrics::default());
+}
+
+pub fn record_tokenizer_call(elapsed: Duration) {
+    let mut metrics = lock(&METRICS);
+    metrics.tokenizer.calls += 1;
+    metrics.tokenizer.seconds += elapsed.as_secs_f64();
+}
+
+pub fn record_validation_failure(code: &'static str) {
+    *lock(&METRICS).validation_failures.entry(code).or_default() += 1;
+}
+
+pub fn record_rate_limit(allowed: bool) {
+    let mut metrics = lock(&METRICS);
+    if allowed {
+        metrics.rate_limit.allowed += 1;
+    } else {
+        metrics.rate_limit.rejected += 1;
+    }
+}
+
+pub fn record_upstream_call(elapsed: Duration, outcome: UpstreamOutcome) {
+    let mut metrics = lock(&METRICS);
+    match outcome {
+        UpstreamOutcome::Ok => metrics.upstream.ok += 1,
+        UpstreamOutcome::Failed => metrics.upstream.failed += 1,
+        UpstreamOutcome::Cancelled => metrics.upstream.cancelled += 1,
+    }
+    metrics.upstream.seconds += elapsed.as_secs_f64();
+}
+
+pub fn snapshot() -> NativeMetrics {
+    lock(&METRICS).clone()
+}
+
+pub fn get_native_metrics(mut cx: FunctionContext) -> JsResult<JsValue> {
+    Json(snapshot()).try_into_js(&mut cx)
+}
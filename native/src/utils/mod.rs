@@ -1,3 +1,22 @@
 pub mod tokenizer;
+#[cfg(feature = "node")]
+pub mod json;
+#[cfg(feature = "node")]
+pub mod metrics;
+#[cfg(feature = "node")]
 pub mod rate_limit;
-pub mod validation;
\ No newline at end of file
+pub mod validation;
+use sha2::{Digest, Sha256};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+// A panic while a global lock is held must not break every later call, and the
+// state behind these locks stays consistent between statements
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Cache key for payloads and message segments: 32 bytes however large the
+// input, and collisions are out of reach, so a hit needs no equality check
+pub fn digest(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
@@ -1,12 +1,49 @@
 use neon::prelude::*;
-use std::sync::{Arc, Mutex};
+use neon::types::extract::{Json, TryIntoJs};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::panic;
+use std::thread;
 use std::time::{Duration, Instant};
 use std::collections::HashMap;
 
+use super::lock;
+use crate::errors::NativeError;
+
+const GLOBAL_KEY: &str = "global";
+const DEFAULT_BURST_CAPACITY: u32 = 5;
+
+// Per-pattern limits set through setRateLimitDefaults
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitConfig {
+    pub interval_secs: u64,
+    #[serde(default = "default_burst_capacity")]
+    pub burst_capacity: u32,
+}
+
+fn default_burst_capacity() -> u32 {
+    DEFAULT_BURST_CAPACITY
+}
+
+// What checkRateLimitDetailed returns, across the key and its ancestors
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    // Until every bucket has a token again, 0 when allowed
+    pub retry_after_ms: u64,
+    // Tokens left in the emptiest bucket, after this request
+    pub remaining: u32,
+}
+
 // Enhanced rate limiter with memory management and burst handling
 #[derive(Clone)]
 struct RateLimiter {
-    last_request: Option<Instant>,
+    // Any check, allowed or not, so a client hammering an empty bucket never
+    // counts as idle and gets a fresh burst
+    last_access: Instant,
     interval: Duration,
     burst_capacity: u32,
     current_tokens: u32,
@@ -14,19 +51,9 @@ struct RateLimiter {
 }
 
 impl RateLimiter {
-    fn new(interval_secs: u64) -> Self {
-        Self {
-            last_request: None,
-            interval: Duration::from_secs(interval_secs),
-            burst_capacity: 5, // Allow burst of 5 requests
-            current_tokens: 5,
-            last_refill: Instant::now(),
-        }
-    }
-    
     fn new_with_burst(interval_secs: u64, burst_capacity: u32) -> Self {
         Self {
-            last_request: None,
+            last_access: Instant::now(),
             interval: Duration::from_secs(interval_secs),
             burst_capacity,
             current_tokens: burst_capacity,
@@ -34,34 +61,36 @@ impl RateLimiter {
         }
     }
     
-    fn check(&mut self) -> bool {
-        let now = Instant::now();
-        
+    fn refill(&mut self, now: Instant) {
         // Refill tokens based on time passed
         let time_since_refill = now.duration_since(self.last_refill);
         let tokens_to_add = (time_since_refill.as_secs_f64() / self.interval.as_secs_f64()) as u32;
         
         if tokens_to_add > 0 {
-            self.current_tokens = (self.current_tokens + tokens_to_add).min(self.burst_capacity);
-            self.last_refill = now;
+            self.current_tokens = self.current_tokens.saturating_add(tokens_to_add).min(self.burst_capacity);
+            // Keep the time towards the next token unless the bucket is full
+            self.last_refill = if self.current_tokens == self.burst_capacity {
+                now
+            } else {
+                self.last_refill + self.interval * tokens_to_add
+            };
         }
-        
-        // Check if we have tokens available
+    }
+
+    // Zero while a token is available
+    fn time_until_token(&self, now: Instant) -> Duration {
         if self.current_tokens > 0 {
-            self.current_tokens -= 1;
-            self.last_request = Some(now);
-            true
-        } else {
-            false // Rate limited
+            return Duration::ZERO;
         }
+        (self.last_refill + self.interval).saturating_duration_since(now)
+    }
+    
+    fn consume(&mut self) {
+        self.current_tokens -= 1;
     }
     
     fn is_expired(&self, max_idle_duration: Duration) -> bool {
-        if let Some(last_time) = self.last_request {
-            Instant::now().duration_since(last_time) > max_idle_duration
-        } else {
-            false
-        }
+        Instant::now().duration_since(self.last_access) > max_idle_duration
     }
 }
 
@@ -71,66 +100,322 @@ lazy_static::lazy_static! {
         Arc::new(Mutex::new(HashMap::new()));
     static ref LAST_CLEANUP: Arc<Mutex<Instant>> = 
         Arc::new(Mutex::new(Instant::now()));
+    static ref LIMIT_DEFAULTS: Mutex<HashMap<String, LimitConfig>> =
+        Mutex::new(HashMap::new());
+    static ref MAX_IDLE_DURATION: Mutex<Duration> = Mutex::new(DEFAULT_MAX_IDLE_DURATION);
+    static ref JANITOR: Mutex<Option<Janitor>> = Mutex::new(None);
+}
+
+static RECLAIMED_LIMITERS: AtomicU64 = AtomicU64::new(0);
+
+struct Janitor {
+    stop: mpsc::Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+// Exact keys win over "prefix:*" patterns, and longer prefixes over shorter ones
+fn lookup_defaults(defaults: &HashMap<String, LimitConfig>, key: &str) -> Option<LimitConfig> {
+    if let Some(config) = defaults.get(key) {
+        return Some(*config);
+    }
+    defaults
+        .iter()
+        .filter_map(|(pattern, config)| {
+            let prefix = pattern.strip_suffix('*')?;
+            (prefix.ends_with(':') && key.starts_with(prefix)).then_some((prefix.len(), *config))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, config)| config)
+}
+
+// "org:acme:user" -> "org:acme" -> "org" -> "global"
+fn parent_key(key: &str) -> Option<&str> {
+    if key == GLOBAL_KEY {
+        return None;
+    }
+    Some(key.rsplit_once(':').map_or(GLOBAL_KEY, |(parent, _)| parent))
 }
 
 const CLEANUP_INTERVAL: Duration = Duration::from_secs(300); // Cleanup every 5 minutes
-const MAX_IDLE_DURATION: Duration = Duration::from_secs(3600); // Remove limiters idle for 1 hour
+const DEFAULT_MAX_IDLE_DURATION: Duration = Duration::from_secs(3600); // Remove limiters idle for 1 hour
+
+// Removes idle limiters and returns how many were reclaimed
+fn prune_expired_limiters() -> usize {
+    let max_idle = *lock(&MAX_IDLE_DURATION);
+    let mut limiters = lock(&RATE_LIMITERS);
+    
+    let before = limiters.len();
+    limiters.retain(|_, limiter| !limiter.is_expired(max_idle));
+    let reclaimed = before - limiters.len();
+    
+    RECLAIMED_LIMITERS.fetch_add(reclaimed as u64, Ordering::Relaxed);
+    reclaimed
+}
 
 fn cleanup_expired_limiters() {
-    let mut last_cleanup = LAST_CLEANUP.lock().unwrap();
+    let mut last_cleanup = lock(&LAST_CLEANUP);
     let now = Instant::now();
     
     if now.duration_since(*last_cleanup) < CLEANUP_INTERVAL {
         return; // Too early for cleanup
     }
     
-    let mut limiters = RATE_LIMITERS.lock().unwrap();
-    limiters.retain(|_, limiter| !limiter.is_expired(MAX_IDLE_DURATION));
-    
+    prune_expired_limiters();
     *last_cleanup = now;
 }
 
-pub fn check_rate_limit(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let key = cx.argument::<JsString>(0)?.value(&mut cx);
-    let interval_secs = cx.argument::<JsNumber>(1)?.value(&mut cx) as u64;
-    
-    // Optional third argument for burst capacity
-    let burst_capacity = if cx.len() > 2 {
-        cx.argument::<JsNumber>(2)?.value(&mut cx) as u32
-    } else {
-        5 // Default burst capacity
-    };
+// Missing and undefined arguments are both treated as omitted
+fn optional_number(cx: &mut FunctionContext, index: usize) -> NeonResult<Option<f64>> {
+    match cx.argument_opt(index) {
+        Some(arg) if !arg.is_a::<JsUndefined, _>(cx) => {
+            Ok(Some(arg.downcast_or_throw::<JsNumber, _>(cx)?.value(cx)))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Consumes one token from the key's bucket and from every ancestor with configured
+// defaults (e.g. "user:alice" -> "user" -> "global"). Either all buckets are charged or none.
+fn charge(key: &str, interval_secs: Option<u64>, burst_capacity: Option<u32>) -> Result<RateLimitDecision, String> {
+    let mut levels = Vec::new();
+    {
+        let defaults = lock(&LIMIT_DEFAULTS);
+        let key_defaults = lookup_defaults(&defaults, key);
+        let leaf = match (interval_secs, key_defaults) {
+            (Some(interval_secs), _) => LimitConfig {
+                interval_secs,
+                burst_capacity: burst_capacity
+                    .or(key_defaults.map(|config| config.burst_capacity))
+                    .unwrap_or(DEFAULT_BURST_CAPACITY),
+            },
+            (None, Some(config)) => config,
+            (None, None) => return Err(format!("No rate limit configured for '{}'", key)),
+        };
+        levels.push((key.to_string(), leaf));
+        
+        let mut current = key;
+        while let Some(parent) = parent_key(current) {
+            if let Some(config) = lookup_defaults(&defaults, parent) {
+                levels.push((parent.to_string(), config));
+            }
+            current = parent;
+        }
+    }
     
     // Cleanup expired limiters periodically
     cleanup_expired_limiters();
     
-    let mut limiters = RATE_LIMITERS.lock().unwrap();
-    let limiter = limiters
-        .entry(key)
-        .or_insert_with(|| RateLimiter::new_with_burst(interval_secs, burst_capacity));
+    let now = Instant::now();
+    let max_idle = *lock(&MAX_IDLE_DURATION);
+    let mut limiters = lock(&RATE_LIMITERS);
+    for (level_key, config) in &levels {
+        let limiter = limiters
+            .entry(level_key.clone())
+            .or_insert_with(|| RateLimiter::new_with_burst(config.interval_secs, config.burst_capacity));
+        
+        // Expire on read so idle limiters start over with the current config
+        if limiter.is_expired(max_idle) {
+            *limiter = RateLimiter::new_with_burst(config.interval_secs, config.burst_capacity);
+            RECLAIMED_LIMITERS.fetch_add(1, Ordering::Relaxed);
+        }
+        limiter.last_access = now;
+        limiter.refill(now);
+    }
+    
+    let buckets = || levels.iter().map(|(level_key, _)| &limiters[level_key]);
+    let retry_after = buckets().map(|limiter| limiter.time_until_token(now)).max().unwrap_or_default();
+    let allowed = retry_after.is_zero();
+    if allowed {
+        for (level_key, _) in &levels {
+            limiters.get_mut(level_key).unwrap().consume();
+        }
+    }
+    super::metrics::record_rate_limit(allowed);
+    
+    Ok(RateLimitDecision {
+        allowed,
+        // Rounded up so sleeping this long always frees a token
+        retry_after_ms: retry_after.as_micros().div_ceil(1000) as u64,
+        remaining: levels.iter().map(|(level_key, _)| limiters[level_key].current_tokens).min().unwrap_or(0),
+    })
+}
+
+// Interval and burst capacity fall back to setRateLimitDefaults when omitted
+fn charge_from_args(cx: &mut FunctionContext) -> NeonResult<RateLimitDecision> {
+    let key = cx.argument::<JsString>(0)?.value(cx);
+    let interval_secs = optional_number(cx, 1)?;
+    let burst_capacity = optional_number(cx, 2)?;
+    // As in setRateLimitDefaults: a zero interval would refill without bound
+    for (name, value) in [("intervalSecs", interval_secs), ("burstCapacity", burst_capacity)] {
+        if let Some(n) = value.filter(|n| !(n.is_finite() && *n >= 1.0)) {
+            return NativeError::RateLimit(format!("{} must be at least 1, got {}", name, n)).throw(cx);
+        }
+    }
+    
+    charge(&key, interval_secs.map(|n| n as u64), burst_capacity.map(|n| n as u32))
+        .or_else(|message| NativeError::RateLimit(message).throw(cx))
+}
+
+pub fn check_rate_limit(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+    let decision = charge_from_args(&mut cx)?;
+    Ok(cx.boolean(decision.allowed))
+}
+
+// Like checkRateLimit, but also says how long to wait when the request is refused
+pub fn check_rate_limit_detailed(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let decision = charge_from_args(&mut cx)?;
+    Json(decision).try_into_js(&mut cx)
+}
+
+// Replaces the per-pattern defaults, e.g. { "global": {...}, "user:*": {...} }
+pub fn set_rate_limit_defaults(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let Json(config): Json<HashMap<String, LimitConfig>> = cx.arg()?;
+    
+    if let Some((pattern, _)) = config.iter().find(|(_, limit)| limit.interval_secs == 0 || limit.burst_capacity == 0) {
+        return NativeError::RateLimit(format!("Rate limit for '{}' must have a positive interval and burst capacity", pattern)).throw(&mut cx);
+    }
     
-    let allowed = limiter.check();
-    Ok(cx.boolean(allowed))
+    *lock(&LIMIT_DEFAULTS) = config;
+    Ok(cx.undefined())
 }
 
-// Additional function to get rate limiter stats
+// Additional function to get rate limiter stats; expired limiters are not counted as active
 pub fn get_rate_limit_stats(mut cx: FunctionContext) -> JsResult<JsObject> {
-    let limiters = RATE_LIMITERS.lock().unwrap();
+    let max_idle = *lock(&MAX_IDLE_DURATION);
+    let active = lock(&RATE_LIMITERS)
+        .values()
+        .filter(|limiter| !limiter.is_expired(max_idle))
+        .count();
     
     let result = cx.empty_object();
-    let active_limiters = cx.number(limiters.len() as f64);
+    let active_limiters = cx.number(active as f64);
+    let reclaimed_limiters = cx.number(RECLAIMED_LIMITERS.load(Ordering::Relaxed) as f64);
+    let janitor_running = cx.boolean(lock(&JANITOR).is_some());
     
     result.set(&mut cx, "activeLimiters", active_limiters)?;
+    result.set(&mut cx, "reclaimedLimiters", reclaimed_limiters)?;
+    result.set(&mut cx, "janitorRunning", janitor_running)?;
     
     Ok(result)
 }
 
-// Function to reset a specific rate limiter
-pub fn reset_rate_limit(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let key = cx.argument::<JsString>(0)?.value(&mut cx);
+// Starts a background thread pruning idle limiters every intervalSecs, so an idle
+// process still frees memory. Optional args: maxIdleSecs and an onSweep(reclaimed)
+// callback invoked when a sweep frees anything. Returns false if already running.
+pub fn start_rate_limit_janitor(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+    let interval_secs = cx.argument::<JsNumber>(0)?.value(&mut cx);
+    let max_idle_secs = optional_number(&mut cx, 1)?;
+    let on_sweep = match cx.argument_opt(2) {
+        Some(arg) if !arg.is_a::<JsUndefined, _>(&mut cx) => {
+            Some(Arc::new(arg.downcast_or_throw::<JsFunction, _>(&mut cx)?.root(&mut cx)))
+        }
+        _ => None,
+    };
+    
+    if !(interval_secs > 0.0 && interval_secs.is_finite()) {
+        return NativeError::RateLimit("intervalSecs must be a positive number".to_string()).throw(&mut cx);
+    }
+    if let Some(max_idle_secs) = max_idle_secs {
+        if !(max_idle_secs > 0.0 && max_idle_secs.is_finite()) {
+            return NativeError::RateLimit("maxIdleSecs must be a positive number".to_string()).throw(&mut cx);
+        }
+    }
+    
+    let mut janitor = lock(&JANITOR);
+    if janitor.is_some() {
+        return Ok(cx.boolean(false));
+    }
+    if let Some(max_idle_secs) = max_idle_secs {
+        *lock(&MAX_IDLE_DURATION) = Duration::from_secs_f64(max_idle_secs);
+    }
+    
+    // The janitor must not keep the Node process alive
+    let mut channel = cx.channel();
+    channel.unref(&mut cx);
+    
+    let interval = Duration::from_secs_f64(interval_secs);
+    let (stop, stop_rx) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+            // A panicking sweep must not kill the janitor
+            let reclaimed = panic::catch_unwind(prune_expired_limiters).unwrap_or(0);
+            if let (Some(on_sweep), true) = (&on_sweep, reclaimed > 0) {
+                let on_sweep = Arc::clone(on_sweep);
+                channel.send(move |mut cx| {
+                    let reclaimed = cx.number(reclaimed as f64);
+                    on_sweep.to_inner(&mut cx).call_with(&cx).arg(reclaimed).exec(&mut cx)
+                });
+            }
+        }
+    });
+    
+    *janitor = Some(Janitor { stop, handle });
+    Ok(cx.boolean(true))
+}
+
+// Stops the janitor and restores the default idle timeout. Returns false if it wasn't running.
+pub fn stop_rate_limit_janitor(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+    let janitor = lock(&JANITOR).take();
+    let stopped = match janitor {
+        Some(janitor) => {
+            let _ = janitor.stop.send(());
+            let _ = janitor.handle.join();
+            true
+        }
+        None => false,
+    };
+    *lock(&MAX_IDLE_DURATION) = DEFAULT_MAX_IDLE_DURATION;
+    
+    Ok(cx.boolean(stopped))
+}
+
+// Glob match supporting "*" (any run of characters) and "?" (one character)
+fn glob_matches(pattern: &str, key: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let key: Vec<char> = key.chars().collect();
+    let (mut p, mut k) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
     
-    let mut limiters = RATE_LIMITERS.lock().unwrap();
-    let removed = limiters.remove(&key).is_some();
+    while k < key.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == key[k]) {
+            p += 1;
+            k += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, k));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last "*" swallow one more character and retry
+            p = star + 1;
+            k = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Removes the limiter for a key, or every limiter matching a glob such as "user:*".
+// Returns the number of limiters removed.
+pub fn reset_rate_limit(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let pattern = cx.argument::<JsString>(0)?.value(&mut cx);
     
-    Ok(cx.boolean(removed))
-}
\ No newline at end of file
+    let mut limiters = lock(&RATE_LIMITERS);
+    let removed = if pattern.contains(['*', '?']) {
+        let before = limiters.len();
+        limiters.retain(|key, _| !glob_matches(&pattern, key));
+        before - limiters.len()
+    } else {
+        usize::from(limiters.remove(&pattern).is_some())
+    };
+    
+    Ok(cx.number(removed as f64))
+}
+
+pub fn reset_all_rate_limits(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let mut limiters = lock(&RATE_LIMITERS);
+    let removed = limiters.len();
+    limiters.clear();
+    
+    Ok(cx.number(removed as f64))
+}
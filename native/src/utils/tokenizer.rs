@@ -1,153 +1,229 @@
+#[cfg(feature = "node")]
 use neon::prelude::*;
-use serde_json;
-use tiktoken_rs::get_bpe_from_model;
+#[cfg(feature = "node")]
+use neon::types::extract::{Json, TryIntoJs};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tiktoken_rs::{o200k_base_singleton, CoreBPE};
 
-#[derive(serde::Deserialize)]
-struct Message {
-    role: String,
-    content: serde_json::Value,
+use super::{digest, lock};
+
+#[derive(Deserialize)]
+pub struct Message {
+    pub role: String,
+    #[serde(default)]
+    pub content: Option<MessageContent>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCall>>,
+}
+
+#[derive(Deserialize)]
+pub struct ToolCall {
+    #[serde(default)]
+    pub function: ToolCallFunction,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ToolCallFunction {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
 }
 
-#[derive(serde::Deserialize)]
-struct TextPart {
+#[derive(Deserialize)]
+pub struct ContentPart {
     #[serde(rename = "type")]
-    part_type: String,
-    text: Option<String>,
-}
-
-fn is_nullish(value: &serde_json::Value) -> bool {
-    value.is_null() || (value.is_string() && value.as_str().unwrap_or("").is_empty())
-}
-
-fn extract_content_text(content: &serde_json::Value) -> String {
-    match content {
-        serde_json::Value::String(s) => s.clone(),
-        serde_json::Value::Array(arr) => {
-            arr.iter()
-                .filter_map(|part| {
-                    if part.get("type").and_then(|t| t.as_str()) == Some("text") {
-                        part.get("text").and_then(|t| t.as_str()).map(|s| s.to_string())
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<String>>()
-                .join("")
+    pub kind: String,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TokenCount {
+    pub input: usize,
+    pub output: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+const TOKEN_CACHE_CAPACITY: usize = 4096;
+
+// Token counts of formatted message segments, keyed by segment digest.
+// Successive requests in a chat resend the same history, so only new
+// messages need encoding.
+#[derive(Default)]
+struct TokenCache {
+    entries: HashMap<[u8; 32], (usize, u64)>, // digest -> (token count, last use)
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl TokenCache {
+    fn count(&mut self, segment: &str, bpe: &CoreBPE) -> usize {
+        let key = digest(segment.as_bytes());
+        self.tick += 1;
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.1 = self.tick;
+            self.hits += 1;
+            return entry.0;
+        }
+
+        self.misses += 1;
+        let count = bpe.encode_with_special_tokens(segment).len();
+        self.entries.insert(key, (count, self.tick));
+        if self.entries.len() > TOKEN_CACHE_CAPACITY {
+            self.evict();
         }
-        _ => String::new(),
+        count
+    }
+
+    // Drops the least recently used quarter in one pass to keep eviction amortized
+    fn evict(&mut self) {
+        let mut ticks: Vec<u64> = self.entries.values().map(|(_, tick)| *tick).collect();
+        let cutoff_index = ticks.len() / 4;
+        let cutoff = *ticks.select_nth_unstable(cutoff_index).1;
+        self.entries.retain(|_, (_, tick)| *tick > cutoff);
     }
 }
 
-pub fn get_token_count(mut cx: FunctionContext) -> JsResult<JsObject> {
-    let messages_json = cx.argument::<JsString>(0)?.value(&mut cx);
-    
-    // Parse messages from JSON
-    let raw_messages: Vec<serde_json::Value> = match serde_json::from_str(&messages_json) {
-        Ok(msgs) => msgs,
-        Err(_) => return cx.throw_error("Invalid JSON input"),
-    };
-    
-    // Get GPT-4o tokenizer to match JavaScript implementation
-    let bpe = match get_bpe_from_model("gpt-4o") {
-        Ok(bpe) => bpe,
-        Err(_) => return cx.throw_error("Failed to load gpt-4o tokenizer"),
-    };
-    
-    // Step 1: Sanitize messages (handle nullish content)
-    let sanitized_messages: Vec<serde_json::Value> = raw_messages
-        .into_iter()
-        .map(|mut message| {
-            if let Some(content) = message.get("content") {
-                if is_nullish(content) {
-                    message["content"] = serde_json::Value::String("".to_string());
-                }
-            } else {
-                message["content"] = serde_json::Value::String("".to_string());
-            }
-            message
-        })
-        .collect();
-    
-    // Step 2: Simplify messages (extract text content)
-    let simplified_messages: Vec<serde_json::Value> = sanitized_messages
-        .into_iter()
-        .map(|mut message| {
-            if let Some(content) = message.get("content") {
-                let content_text = extract_content_text(content);
-                message["content"] = serde_json::Value::String(content_text);
-            }
-            message
-        })
-        .collect();
-    
-    // Step 3: Filter and separate input/output messages
-    let filtered_messages: Vec<&serde_json::Value> = simplified_messages
+lazy_static::lazy_static! {
+    static ref TOKEN_CACHE: Mutex<TokenCache> = Mutex::new(TokenCache::default());
+}
+
+// Counts "role: content" lines joined by "\n" as a sum of per-message segments.
+// This is exact because o200k pre-tokenization never merges a newline with a
+// following letter, so every "\n" before a role name ends a token. Roles that
+// don't start with a letter fall back to encoding the joined text.
+fn count_formatted(messages: &[&(String, String)], bpe: &CoreBPE) -> usize {
+    let segmentable = messages
         .iter()
-        .filter(|message| {
-            message.get("role").and_then(|r| r.as_str()) != Some("tool")
-        })
-        .collect();
-    
-    let mut input_messages = &filtered_messages[..];
-    let mut output_messages: Vec<&serde_json::Value> = vec![];
-    
-    // Check if last message is from assistant
-    if let Some(last_message) = filtered_messages.last() {
-        if last_message.get("role").and_then(|r| r.as_str()) == Some("assistant") {
-            if filtered_messages.len() > 1 {
-                input_messages = &filtered_messages[..filtered_messages.len() - 1];
-            } else {
-                input_messages = &[];
-            }
-            output_messages = vec![*last_message];
-        }
-    }
-    
-    // Step 4: Convert to format expected by tiktoken (similar to gpt-tokenizer)
-    let input_tokens = if input_messages.is_empty() {
-        // Even empty message arrays have base tokens in gpt-tokenizer
-        bpe.encode_with_special_tokens("").len()
-    } else {
-        // Format messages similar to how gpt-tokenizer does it
-        let formatted_input: Vec<String> = input_messages
+        .all(|(role, _)| role.chars().next().is_some_and(char::is_alphabetic));
+
+    if !segmentable {
+        let text = messages
             .iter()
-            .map(|msg| {
-                format!("{}: {}", 
-                    msg.get("role").and_then(|r| r.as_str()).unwrap_or(""),
-                    msg.get("content").and_then(|c| c.as_str()).unwrap_or("")
-                )
-            })
-            .collect();
-        
-        let input_text = formatted_input.join("\n");
-        bpe.encode_with_special_tokens(&input_text).len()
-    };
-    
-    let output_tokens = if output_messages.is_empty() {
-        // Base tokens for empty output
-        bpe.encode_with_special_tokens("").len()
-    } else {
-        let formatted_output: Vec<String> = output_messages
+            .map(|(role, content)| format!("{}: {}", role, content))
+            .collect::<Vec<String>>()
+            .join("\n");
+        return bpe.encode_with_special_tokens(&text).len();
+    }
+
+    let mut cache = lock(&TOKEN_CACHE);
+    let last = messages.len().saturating_sub(1);
+    messages
+        .iter()
+        .enumerate()
+        .map(|(i, (role, content))| {
+            let separator = if i < last { "\n" } else { "" };
+            cache.count(&format!("{}: {}{}", role, content, separator), bpe)
+        })
+        .sum()
+}
+
+// Nullish content counts as empty text, image parts are ignored, and each
+// tool call adds a "name(arguments)" line
+fn message_text(message: &Message) -> String {
+    let content = match &message.content {
+        Some(MessageContent::Text(s)) => s.clone(),
+        Some(MessageContent::Parts(parts)) => parts
             .iter()
-            .map(|msg| {
-                format!("{}: {}", 
-                    msg.get("role").and_then(|r| r.as_str()).unwrap_or(""),
-                    msg.get("content").and_then(|c| c.as_str()).unwrap_or("")
-                )
-            })
-            .collect();
-        
-        let output_text = formatted_output.join("\n");
-        bpe.encode_with_special_tokens(&output_text).len()
+            .filter(|part| part.kind == "text")
+            .filter_map(|part| part.text.as_deref())
+            .collect::<Vec<&str>>()
+            .join(""),
+        None => String::new(),
     };
-    
-    // Create return object
-    let result = cx.empty_object();
-    let input_val = cx.number(input_tokens as f64);
-    let output_val = cx.number(output_tokens as f64);
-    
-    result.set(&mut cx, "input", input_val)?;
-    result.set(&mut cx, "output", output_val)?;
-    
-    Ok(result)
-}
\ No newline at end of file
+
+    let calls = message.tool_calls.iter().flatten().map(|call| {
+        format!("{}({})", call.function.name, call.function.arguments)
+    });
+    std::iter::once(content)
+        .filter(|content| !content.is_empty())
+        .chain(calls)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Counts the prompt (input) and completion (output) tokens of a
+/// conversation. The Node and WASM exports both count through
+/// [`O200kCounter`], so every entry point reports the same numbers.
+pub trait TokenCounter {
+    fn count(&self, messages: &[Message]) -> TokenCount;
+}
+
+/// The GPT-4o tokenizer (o200k_base), with messages formatted as
+/// "role: content" lines like gpt-tokenizer does. A trailing assistant
+/// message is the output, tool results are not counted.
+pub struct O200kCounter;
+
+impl TokenCounter for O200kCounter {
+    fn count(&self, messages: &[Message]) -> TokenCount {
+        let bpe_singleton = o200k_base_singleton();
+        let bpe = bpe_singleton.lock();
+
+        let (input, output) = match messages.split_last() {
+            Some((last, rest)) if last.role == "assistant" => (rest, std::slice::from_ref(last)),
+            _ => (messages, &[][..]),
+        };
+        let simplify = |messages: &[Message]| -> Vec<(String, String)> {
+            messages
+                .iter()
+                .filter(|message| message.role != "tool")
+                .map(|message| (message.role.clone(), message_text(message)))
+                .collect()
+        };
+        let input = simplify(input);
+        let output = simplify(output);
+
+        TokenCount {
+            input: count_formatted(&input.iter().collect::<Vec<_>>(), &bpe),
+            output: count_formatted(&output.iter().collect::<Vec<_>>(), &bpe),
+        }
+    }
+}
+
+pub fn count_tokens(messages: Vec<Message>) -> TokenCount {
+    O200kCounter.count(&messages)
+}
+
+pub fn token_cache_stats() -> TokenCacheStats {
+    let cache = lock(&TOKEN_CACHE);
+    TokenCacheStats {
+        entries: cache.entries.len(),
+        capacity: TOKEN_CACHE_CAPACITY,
+        hits: cache.hits,
+        misses: cache.misses,
+    }
+}
+
+#[cfg(feature = "node")]
+pub fn get_token_count(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let messages = super::json::json_arg::<Vec<Message>>(&mut cx, 0)?.value;
+    let start = std::time::Instant::now();
+    let count = count_tokens(messages);
+    super::metrics::record_tokenizer_call(start.elapsed());
+    Json(count).try_into_js(&mut cx)
+}
+
+#[cfg(feature = "node")]
+pub fn get_token_cache_stats(mut cx: FunctionContext) -> JsResult<JsValue> {
+    Json(token_cache_stats()).try_into_js(&mut cx)
+}
@@ -1,24 +1,68 @@
+#[cfg(feature = "node")]
 use neon::prelude::*;
-use serde_json;
+#[cfg(feature = "node")]
+use neon::types::extract::{Json, TryIntoJs};
+use serde::Serialize;
+#[cfg(feature = "node")]
+use std::collections::HashMap;
+#[cfg(feature = "node")]
+use std::sync::Mutex;
 
-#[derive(serde::Deserialize)]
-struct ChatCompletionRequest {
-    messages: Vec<serde_json::Value>,
-    model: String,
-    max_tokens: Option<i32>,
-    temperature: Option<f64>,
-    stream: Option<bool>,
+#[cfg(feature = "node")]
+use super::lock;
+#[cfg(feature = "node")]
+use crate::errors::NativeError;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationResult {
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    // Make the payload invalid
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<Violation>,
+    // Valid but suspicious, worth surfacing to the user
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Violation>,
+}
+
+/// A problem found in a payload, with a stable `code` for callers to match
+/// on and the index of the offending message, if any.
+#[derive(Serialize, Clone)]
+pub struct Violation {
+    pub code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Findings {
+    errors: Vec<Violation>,
+    warnings: Vec<Violation>,
 }
 
-#[derive(serde::Deserialize)]
-struct AnthropicRequest {
-    messages: Vec<serde_json::Value>,
-    model: String,
-    max_tokens: i32,
-    temperature: Option<f64>,
-    stream: Option<bool>,
+impl Findings {
+    fn error(&mut self, code: &'static str, index: Option<usize>, message: String) {
+        self.errors.push(Violation { code, index, message });
+    }
+
+    fn warning(&mut self, code: &'static str, index: Option<usize>, message: String) {
+        self.warnings.push(Violation { code, index, message });
+    }
 }
 
+// About 25k tokens of English text
+const LARGE_SYSTEM_PROMPT_CHARS: usize = 100_000;
+
+// Sampling parameters that reasoning models ignore, see BUILTIN_PARAM_RULES
+// in src/lib/param-policy.ts
+const REASONING_MODEL_PREFIXES: [&str; 3] = ["o1", "o3", "o4"];
+const SAMPLING_PARAMETERS: [&str; 4] = ["temperature", "top_p", "presence_penalty", "frequency_penalty"];
+
 fn validate_message(message: &serde_json::Value) -> bool {
     // Check if message has required role field
     if let Some(role) = message.get("role").and_then(|r| r.as_str()) {
@@ -33,7 +77,119 @@ fn validate_message(message: &serde_json::Value) -> bool {
     message.get("content").is_some()
 }
 
-fn validate_openai_chat_completion(payload: &serde_json::Value) -> Result<String, String> {
+// Tool messages must answer a tool call of an earlier assistant message,
+// the conversation must open with the user, and roles should alternate
+fn check_message_order(messages: &[serde_json::Value], findings: &mut Findings) {
+    let mut tool_call_ids = std::collections::HashSet::new();
+    let mut seen_non_system = false;
+    let mut previous_role: Option<&str> = None;
+
+    for (index, message) in messages.iter().enumerate() {
+        let role = message.get("role").and_then(|r| r.as_str()).unwrap_or_default();
+
+        if role != "system" && !seen_non_system {
+            seen_non_system = true;
+            if role != "user" {
+                findings.error(
+                    "first_message_not_user",
+                    Some(index),
+                    format!("The first non-system message must be from the user, not {}", role),
+                );
+            }
+        }
+
+        match role {
+            "assistant" => {
+                let ids = message
+                    .get("tool_calls")
+                    .and_then(|calls| calls.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|call| call.get("id").and_then(|id| id.as_str()));
+                tool_call_ids.extend(ids);
+            }
+            "tool" => match message.get("tool_call_id").and_then(|id| id.as_str()) {
+                None => findings.error(
+                    "missing_tool_call_id",
+                    Some(index),
+                    "Tool messages must have a tool_call_id".to_string(),
+                ),
+                Some(id) if !tool_call_ids.contains(id) => findings.error(
+                    "unknown_tool_call_id",
+                    Some(index),
+                    format!("No preceding assistant message made the tool call {}", id),
+                ),
+                Some(_) => {}
+            },
+            _ => {}
+        }
+
+        // Parallel tool calls are answered by consecutive tool messages, and
+        // other repeats are merged or tolerated by most models
+        if role != "tool" && previous_role == Some(role) {
+            findings.warning(
+                "consecutive_same_role",
+                Some(index),
+                format!("Consecutive {} messages", role),
+            );
+        }
+        previous_role = Some(role);
+    }
+}
+
+fn text_length(content: &serde_json::Value) -> usize {
+    match content {
+        serde_json::Value::String(text) => text.chars().count(),
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .map(|text| text.chars().count())
+            .sum(),
+        _ => 0,
+    }
+}
+
+// Advice for payloads that are valid but probably not doing what the
+// caller expects. Covers both formats.
+fn check_parameters(payload: &serde_json::Value, findings: &mut Findings) {
+    let model = payload.get("model").and_then(|m| m.as_str()).unwrap_or_default();
+    if REASONING_MODEL_PREFIXES.iter().any(|prefix| model.starts_with(prefix)) {
+        for parameter in SAMPLING_PARAMETERS {
+            if payload.get(parameter).is_some_and(|value| !value.is_null()) {
+                findings.warning(
+                    "parameter_ignored",
+                    None,
+                    format!("{} is likely ignored by {}", parameter, model),
+                );
+            }
+        }
+    }
+
+    // OpenAI system messages, or the Anthropic top-level system prompt
+    let system_messages = payload
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|message| message.get("role").and_then(|r| r.as_str()) == Some("system"))
+        .filter_map(|message| message.get("content"));
+    let system_chars: usize = system_messages.chain(payload.get("system")).map(text_length).sum();
+    if system_chars > LARGE_SYSTEM_PROMPT_CHARS {
+        findings.warning(
+            "large_system_prompt",
+            None,
+            format!(
+                "The system prompt is {} characters long, which leaves less room for the conversation",
+                system_chars
+            ),
+        );
+    }
+}
+
+fn validate_openai_chat_completion(
+    payload: &serde_json::Value,
+    findings: &mut Findings,
+) -> Result<(), String> {
     // Validate messages array
     let messages = payload.get("messages")
         .and_then(|m| m.as_array())
@@ -72,7 +228,7 @@ fn validate_openai_chat_completion(payload: &serde_json::Value) -> Result<String
     
     if let Some(temperature) = payload.get("temperature") {
         if let Some(temp) = temperature.as_f64() {
-            if temp < 0.0 || temp > 2.0 {
+            if !(0.0..=2.0).contains(&temp) {
                 return Err("temperature must be between 0.0 and 2.0".to_string());
             }
         } else {
@@ -80,26 +236,11 @@ fn validate_openai_chat_completion(payload: &serde_json::Value) -> Result<String
         }
     }
     
-    // Check for vision content
-    let has_vision = messages.iter().any(|msg| {
-        if let Some(content) = msg.get("content") {
-            if let Some(content_array) = content.as_array() {
-                return content_array.iter().any(|part| {
-                    part.get("type").and_then(|t| t.as_str()) == Some("image_url")
-                });
-            }
-        }
-        false
-    });
-    
-    if has_vision {
-        Ok("vision".to_string())
-    } else {
-        Ok("text".to_string())
-    }
+    check_message_order(messages, findings);
+    Ok(())
 }
 
-fn validate_anthropic_request(payload: &serde_json::Value) -> Result<String, String> {
+fn validate_anthropic_request(payload: &serde_json::Value) -> Result<(), String> {
     // Similar validation for Anthropic format
     let messages = payload.get("messages")
         .and_then(|m| m.as_array())
@@ -125,68 +266,278 @@ fn validate_anthropic_request(payload: &serde_json::Value) -> Result<String, Str
         return Err("max_tokens must be between 1 and 32000".to_string());
     }
     
-    Ok("anthropic".to_string())
+    Ok(())
 }
 
-pub fn validate_payload(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let payload_json = cx.argument::<JsString>(0)?.value(&mut cx);
-    
-    // Try to parse the JSON payload
-    let payload: serde_json::Value = match serde_json::from_str(&payload_json) {
-        Ok(val) => val,
-        Err(_) => return Ok(cx.boolean(false)),
+#[derive(Default)]
+struct ContentFlags {
+    vision: bool,
+    audio: bool,
+    tools: bool,
+    reasoning: bool,
+}
+
+impl ContentFlags {
+    fn part(&mut self, part: &serde_json::Value) {
+        match part.get("type").and_then(|t| t.as_str()) {
+            Some("image_url" | "image") => self.vision = true,
+            Some("input_audio") => self.audio = true,
+            Some("tool_use" | "tool_result") => self.tools = true,
+            Some("thinking" | "redacted_thinking") => self.reasoning = true,
+            _ => {}
+        }
+    }
+
+    // The flags joined by "+", e.g. "vision+tools", after "anthropic" for
+    // payloads only the Anthropic rules accept. "text" when nothing applies.
+    fn content_type(&self, anthropic: bool) -> String {
+        let flags = [
+            (anthropic, "anthropic"),
+            (self.vision, "vision"),
+            (self.audio, "audio"),
+            (self.tools, "tools"),
+            (self.reasoning, "reasoning"),
+        ];
+        let names: Vec<&str> = flags.iter().filter(|(set, _)| *set).map(|(_, name)| *name).collect();
+        if names.is_empty() {
+            "text".to_string()
+        } else {
+            names.join("+")
+        }
+    }
+}
+
+fn is_set(value: Option<&serde_json::Value>) -> bool {
+    match value {
+        None | Some(serde_json::Value::Null) => false,
+        Some(serde_json::Value::Array(items)) => !items.is_empty(),
+        Some(_) => true,
+    }
+}
+
+// One pass over an OpenAI or Anthropic payload, which the router uses to
+// pick upstream headers and endpoints
+fn classify(payload: &serde_json::Value, anthropic: bool) -> String {
+    let mut flags = ContentFlags {
+        audio: is_set(payload.get("audio"))
+            || payload
+                .get("modalities")
+                .and_then(|m| m.as_array())
+                .is_some_and(|modalities| modalities.iter().any(|m| m.as_str() == Some("audio"))),
+        tools: is_set(payload.get("tools")),
+        reasoning: is_set(payload.get("reasoning_effort"))
+            || payload
+                .get("thinking")
+                .and_then(|t| t.get("type"))
+                .and_then(|t| t.as_str())
+                .is_some_and(|kind| kind != "disabled"),
+        ..ContentFlags::default()
     };
-    
-    // Try OpenAI format first
-    let is_valid = validate_openai_chat_completion(&payload).is_ok() || 
-                   validate_anthropic_request(&payload).is_ok();
-    
-    Ok(cx.boolean(is_valid))
+
+    let messages = payload.get("messages").and_then(|m| m.as_array()).into_iter().flatten();
+    for message in messages {
+        if is_set(message.get("tool_calls")) || message.get("role").and_then(|r| r.as_str()) == Some("tool") {
+            flags.tools = true;
+        }
+        if let Some(parts) = message.get("content").and_then(|c| c.as_array()) {
+            parts.iter().for_each(|part| flags.part(part));
+        }
+    }
+
+    flags.content_type(anthropic)
 }
 
-// Detailed validation with error messages
-pub fn validate_payload_detailed(mut cx: FunctionContext) -> JsResult<JsObject> {
-    let payload_json = cx.argument::<JsString>(0)?.value(&mut cx);
-    
-    // Try to parse the JSON payload
-    let payload: serde_json::Value = match serde_json::from_str(&payload_json) {
-        Ok(val) => val,
+// Detailed validation with error messages. Payloads stay untyped here:
+// checking their shape is the point of validation.
+pub fn validate(payload: &serde_json::Value) -> ValidationResult {
+    let mut findings = Findings::default();
+    // Try OpenAI format first, then Anthropic
+    let anthropic = match validate_openai_chat_completion(payload, &mut findings) {
+        Ok(()) => Ok(false),
+        Err(e) => validate_anthropic_request(payload)
+            .map(|()| true)
+            .map_err(|e2| format!("OpenAI validation: {}. Anthropic validation: {}", e, e2)),
+    };
+    let anthropic = match anthropic {
+        Ok(anthropic) => anthropic,
         Err(e) => {
-            let result = cx.empty_object();
-            let valid = cx.boolean(false);
-            let error = cx.string(format!("Invalid JSON: {}", e));
-            
-            result.set(&mut cx, "valid", valid)?;
-            result.set(&mut cx, "error", error)?;
-            return Ok(result);
+            return ValidationResult {
+                valid: false,
+                error: Some(e.clone()),
+                content_type: None,
+                errors: vec![Violation { code: "invalid_payload", index: None, message: e }],
+                warnings: Vec::new(),
+            };
         }
     };
-    
-    // Try OpenAI format first
-    let (is_valid, error_message, content_type) = match validate_openai_chat_completion(&payload) {
-        Ok(content_type) => (true, None, Some(content_type)),
-        Err(e) => {
-            // Try Anthropic format
-            match validate_anthropic_request(&payload) {
-                Ok(content_type) => (true, None, Some(content_type)),
-                Err(e2) => (false, Some(format!("OpenAI validation: {}. Anthropic validation: {}", e, e2)), None)
+
+    check_parameters(payload, &mut findings);
+    let error = (!findings.errors.is_empty()).then(|| {
+        findings
+            .errors
+            .iter()
+            .map(|violation| match violation.index {
+                Some(index) => format!("Message {}: {}", index, violation.message),
+                None => violation.message.clone(),
+            })
+            .collect::<Vec<String>>()
+            .join("; ")
+    });
+    ValidationResult {
+        valid: findings.errors.is_empty(),
+        error,
+        content_type: Some(classify(payload, anthropic)),
+        errors: findings.errors,
+        warnings: findings.warnings,
+    }
+}
+
+#[cfg(feature = "node")]
+#[derive(Serialize)]
+pub struct ValidationCacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+// Results by payload JSON. Agent loops resend the same payloads, and a repeat
+// then skips both parsing and validation. Keyed on the digest of the bytes,
+// so an entry's size doesn't grow with its payload. Off until given a
+// capacity with configureValidationCache.
+#[cfg(feature = "node")]
+#[derive(Default)]
+struct ValidationCache {
+    entries: HashMap<[u8; 32], (ValidationResult, u64)>, // payload digest -> (result, last use)
+    capacity: usize,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+#[cfg(feature = "node")]
+impl ValidationCache {
+    fn get(&mut self, key: &[u8; 32]) -> Option<ValidationResult> {
+        self.tick += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.1 = self.tick;
+                self.hits += 1;
+                Some(entry.0.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
             }
         }
-    };
-    
-    let result = cx.empty_object();
-    let valid = cx.boolean(is_valid);
-    result.set(&mut cx, "valid", valid)?;
-    
-    if let Some(error) = error_message {
-        let error_str = cx.string(error);
-        result.set(&mut cx, "error", error_str)?;
     }
-    
-    if let Some(content_type) = content_type {
-        let content_type_str = cx.string(content_type);
-        result.set(&mut cx, "contentType", content_type_str)?;
+
+    fn insert(&mut self, key: [u8; 32], result: ValidationResult) {
+        self.entries.insert(key, (result, self.tick));
+        if self.entries.len() > self.capacity {
+            self.evict();
+        }
     }
-    
+
+    // Drops the least recently used quarter in one pass, like the token cache
+    fn evict(&mut self) {
+        let mut ticks: Vec<u64> = self.entries.values().map(|(_, tick)| *tick).collect();
+        let cutoff_index = ticks.len() / 4;
+        let cutoff = *ticks.select_nth_unstable(cutoff_index).1;
+        self.entries.retain(|_, (_, tick)| *tick > cutoff);
+    }
+
+    fn stats(&self) -> ValidationCacheStats {
+        ValidationCacheStats {
+            entries: self.entries.len(),
+            capacity: self.capacity,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+}
+
+#[cfg(feature = "node")]
+lazy_static::lazy_static! {
+    static ref VALIDATION_CACHE: Mutex<ValidationCache> = Mutex::new(ValidationCache::default());
+}
+
+#[cfg(feature = "node")]
+fn validate_json(json: &[u8]) -> Result<ValidationResult, String> {
+    let parse = |json: &[u8]| serde_json::from_slice(json).map_err(|e| format!("Invalid JSON: {}", e));
+    if lock(&VALIDATION_CACHE).capacity == 0 {
+        return parse(json).map(|payload| validate(&payload));
+    }
+
+    let key = super::digest(json);
+    if let Some(result) = lock(&VALIDATION_CACHE).get(&key) {
+        return Ok(result);
+    }
+    // Not holding the lock while validating a large payload
+    let result = validate(&parse(json)?);
+    lock(&VALIDATION_CACHE).insert(key, result.clone());
     Ok(result)
-}
\ No newline at end of file
+}
+
+// Counts each error of an invalid payload for getNativeMetrics, cached or not
+#[cfg(feature = "node")]
+fn validate_counted(cx: &mut FunctionContext) -> NeonResult<ValidationResult> {
+    let result = match super::json::with_json_text(cx, 0, validate_json)? {
+        Ok(result) => result,
+        Err(e) => return NativeError::Validation(e).throw(cx),
+    };
+    for violation in &result.errors {
+        super::metrics::record_validation_failure(violation.code);
+    }
+    Ok(result)
+}
+
+#[cfg(feature = "node")]
+pub fn validate_payload(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+    let result = validate_counted(&mut cx)?;
+    Ok(cx.boolean(result.valid))
+}
+
+#[cfg(feature = "node")]
+pub fn validate_payload_detailed(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let result = validate_counted(&mut cx)?;
+    Json(result).try_into_js(&mut cx)
+}
+
+// Sets how many results to keep, 0 turns the cache off; returns the stats
+#[cfg(feature = "node")]
+pub fn configure_validation_cache(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let capacity = cx.argument::<JsNumber>(0)?.value(&mut cx);
+    if !(capacity >= 0.0 && capacity.is_finite()) {
+        return NativeError::Validation("capacity must be a non-negative number".to_string()).throw(&mut cx);
+    }
+
+    let mut cache = lock(&VALIDATION_CACHE);
+    cache.capacity = capacity as usize;
+    if cache.capacity == 0 {
+        cache.entries.clear();
+    } else {
+        while cache.entries.len() > cache.capacity {
+            cache.evict();
+        }
+    }
+    let stats = cache.stats();
+    drop(cache);
+    Json(stats).try_into_js(&mut cx)
+}
+
+// Returns the number of results dropped; hit counters are kept
+#[cfg(feature = "node")]
+pub fn clear_validation_cache(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let mut cache = lock(&VALIDATION_CACHE);
+    let cleared = cache.entries.len();
+    cache.entries.clear();
+    drop(cache);
+    Ok(cx.number(cleared as f64))
+}
+
+#[cfg(feature = "node")]
+pub fn get_validation_cache_stats(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let stats = lock(&VALIDATION_CACHE).stats();
+    Json(stats).try_into_js(&mut cx)
+}
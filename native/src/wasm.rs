@@ -0,0 +1,37 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::utils::{tokenizer, validation};
+
+// Values cross the boundary through JSON so the typed serde structs are
+// shared with the Node addon
+fn from_js<T: DeserializeOwned>(value: &JsValue) -> Result<T, JsValue> {
+    let json: String = js_sys::JSON::stringify(value)?.into();
+    serde_json::from_str(&json).map_err(|e| js_sys::Error::new(&e.to_string()).into())
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(value).map_err(|e| js_sys::Error::new(&e.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
+
+#[wasm_bindgen(js_name = getTokenCount)]
+pub fn get_token_count(messages: JsValue) -> Result<JsValue, JsValue> {
+    to_js(&tokenizer::count_tokens(from_js(&messages)?))
+}
+
+#[wasm_bindgen(js_name = getTokenCacheStats)]
+pub fn get_token_cache_stats() -> Result<JsValue, JsValue> {
+    to_js(&tokenizer::token_cache_stats())
+}
+
+#[wasm_bindgen(js_name = validatePayload)]
+pub fn validate_payload(payload: JsValue) -> Result<bool, JsValue> {
+    Ok(validation::validate(&from_js(&payload)?).valid)
+}
+
+#[wasm_bindgen(js_name = validatePayloadDetailed)]
+pub fn validate_payload_detailed(payload: JsValue) -> Result<JsValue, JsValue> {
+    to_js(&validation::validate(&from_js(&payload)?))
+}
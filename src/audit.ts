@@ -0,0 +1,151 @@
+#!/usr/bin/env node
+
+import { defineCommand } from "citty"
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { withAccountingStore } from "./lib/accounting-store"
+import { pruneAuditLog, readAuditEntry, readAuditLog } from "./lib/audit"
+import { loadEnvConfig, parseIntegerOption } from "./lib/env-config"
+
+interface RunAuditExportOptions {
+  since?: string
+  until?: string
+  output?: string
+  accountingStore?: string
+}
+
+// A bare date covers the whole day
+const toTimestamp = (value: string, endOfDay: boolean): string => {
+  const date = new Date(
+    /^\d{4}-\d{2}-\d{2}$/.test(value) && endOfDay ?
+      `${value}T23:59:59.999Z`
+    : value,
+  )
+  if (Number.isNaN(date.getTime())) {
+    throw new TypeError(`Invalid date: ${value}`)
+  }
+  return date.toISOString()
+}
+
+export async function runAuditExport(
+  options: RunAuditExportOptions,
+): Promise<void> {
+  const range = {
+    since: options.since && toTimestamp(options.since, false),
+    until: options.until && toTimestamp(options.until, true),
+  }
+  const entries = await withAccountingStore(
+    options.accountingStore ?? loadEnvConfig().accountingStore,
+    () => readAuditLog(range),
+  )
+  const output = entries.map((entry) => `${JSON.stringify(entry)}\n`).join("")
+
+  if (!options.output || options.output === "-") {
+    process.stdout.write(output)
+    return
+  }
+
+  await fs.writeFile(options.output, output, { mode: 0o600 })
+  consola.success(`Exported ${entries.length} audit entries to ${options.output}`)
+}
+
+const accountingStoreArg = {
+  type: "string",
+  description:
+    "Database the audit log is kept in, as given to `start` (default: the files)",
+} as const
+
+const exportCommand = defineCommand({
+  meta: {
+    name: "export",
+    description: "Export audit entries as JSON Lines",
+  },
+  args: {
+    since: {
+      type: "string",
+      description: "Only entries at or after this date or ISO timestamp",
+    },
+    until: {
+      type: "string",
+      description: "Only entries at or before this date or ISO timestamp",
+    },
+    output: {
+      alias: "o",
+      type: "string",
+      description: "File to write to (default: stdout)",
+    },
+    "accounting-store": accountingStoreArg,
+  },
+  run({ args }) {
+    return runAuditExport({
+      since: args.since,
+      until: args.until,
+      output: args.output,
+      accountingStore: args["accounting-store"],
+    })
+  },
+})
+
+const prune = defineCommand({
+  meta: {
+    name: "prune",
+    description: "Delete audit logs older than the retention period",
+  },
+  args: {
+    retention: {
+      type: "string",
+      default: "30",
+      description: "Days to keep",
+    },
+    "accounting-store": accountingStoreArg,
+  },
+  async run({ args }) {
+    const retentionDays = parseIntegerOption(
+      "--retention",
+      args.retention,
+      1,
+      3650,
+    )
+    const removed = await withAccountingStore(
+      args["accounting-store"] ?? loadEnvConfig().accountingStore,
+      () => pruneAuditLog(retentionDays),
+    )
+    consola.success(`Removed ${removed} audit files or database rows`)
+  },
+})
+
+const show = defineCommand({
+  meta: {
+    name: "show",
+    description: "Print the audit entry of one request, by its x-audit-id",
+  },
+  args: {
+    id: {
+      type: "positional",
+      description: "The x-audit-id response header of the request",
+    },
+    "accounting-store": accountingStoreArg,
+  },
+  async run({ args }) {
+    const entry = await withAccountingStore(
+      args["accounting-store"] ?? loadEnvConfig().accountingStore,
+      () => readAuditEntry(args.id),
+    )
+    if (!entry) {
+      consola.error(`No audit entry ${args.id}`)
+      process.exitCode = 1
+      return
+    }
+    process.stdout.write(`${JSON.stringify(entry, null, 2)}\n`)
+  },
+})
+
+export const audit = defineCommand({
+  meta: {
+    name: "audit",
+    description:
+      "Export, show or prune the request audit log written by `start --audit`",
+  },
+  subCommands: { export: exportCommand, show, prune },
+})
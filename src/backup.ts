@@ -0,0 +1,144 @@
+#!/usr/bin/env node
+
+import { defineCommand } from "citty"
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { withAccountingStore } from "./lib/accounting-store"
+import {
+  createBackup,
+  restoreBackup,
+  type GatewayBackup,
+} from "./lib/backup"
+import { loadEnvConfig } from "./lib/env-config"
+
+interface RunExportOptions {
+  out?: string
+  includeTokens: boolean
+  config?: Array<string>
+  accountingStore?: string
+}
+
+export async function runExport(options: RunExportOptions): Promise<void> {
+  const backup = await withAccountingStore(
+    options.accountingStore ?? loadEnvConfig().accountingStore,
+    () =>
+      createBackup({
+        includeTokens: options.includeTokens,
+        configFiles: options.config,
+      }),
+  )
+  const content = `${JSON.stringify(backup, null, 2)}\n`
+
+  if (!options.out || options.out === "-") {
+    process.stdout.write(content)
+    return
+  }
+  // May hold GitHub tokens
+  await fs.writeFile(options.out, content, { mode: 0o600 })
+  consola.success(
+    `Exported ${Object.keys(backup.team.members).length} API keys and ${backup.usage.length} usage records to ${options.out}`,
+  )
+  if (!options.includeTokens && Object.keys(backup.team.members).length > 0) {
+    consola.info(
+      "Team members' GitHub tokens were left out, use --include-tokens to move the team",
+    )
+  }
+}
+
+interface RunImportOptions {
+  file: string
+  force: boolean
+  accountingStore?: string
+}
+
+export async function runImport(options: RunImportOptions): Promise<void> {
+  const backup = JSON.parse(
+    await fs.readFile(options.file, "utf8"),
+  ) as GatewayBackup
+  const summary = await withAccountingStore(
+    options.accountingStore ?? loadEnvConfig().accountingStore,
+    () => restoreBackup(backup, { force: options.force }),
+  )
+
+  consola.success(
+    `Imported ${summary.members} API keys, ${summary.usage} usage records, ${summary.usageSnapshots} usage snapshots and ${summary.config.length} config files`,
+  )
+  if (summary.githubToken) consola.info("Restored the GitHub token")
+  if (summary.skippedMembers.length > 0) {
+    consola.warn(
+      `Skipped ${summary.skippedMembers.join(", ")}: exported without their GitHub token`,
+    )
+  }
+}
+
+const accountingStoreArg = {
+  type: "string",
+  description:
+    "Database the usage records are kept in, as given to `start` (default: the files)",
+} as const
+
+export const exportBackup = defineCommand({
+  meta: {
+    name: "export",
+    description:
+      "Write the team's API keys, usage records and config files to one JSON file, to move the gateway to another host",
+  },
+  args: {
+    out: {
+      alias: "o",
+      type: "string",
+      description: "File to write to (default: stdout)",
+    },
+    "include-tokens": {
+      type: "boolean",
+      default: false,
+      description:
+        "Include the gateway's and the team members' GitHub tokens",
+    },
+    config: {
+      type: "string",
+      description:
+        "Comma-separated config files to include, such as policies, restored to the same path",
+    },
+    "accounting-store": accountingStoreArg,
+  },
+  run({ args }) {
+    return runExport({
+      out: args.out,
+      includeTokens: args["include-tokens"],
+      config: args.config
+        ?.split(",")
+        .map((file) => file.trim())
+        .filter(Boolean),
+      accountingStore: args["accounting-store"],
+    })
+  },
+})
+
+export const importBackup = defineCommand({
+  meta: {
+    name: "import",
+    description: "Restore a file written by `export` on this host",
+  },
+  args: {
+    file: {
+      type: "positional",
+      description: "File written by `export`",
+    },
+    force: {
+      type: "boolean",
+      default: false,
+      description:
+        "Replace the GitHub token, API keys and config files that already exist here",
+    },
+    "accounting-store": accountingStoreArg,
+  },
+  run({ args }) {
+    return runImport({
+      file: args.file,
+      force: args.force,
+      accountingStore: args["accounting-store"],
+    })
+  },
+})
@@ -0,0 +1,359 @@
+#!/usr/bin/env node
+
+import type { ArgsDef, CommandDef, Resolvable } from "citty"
+
+import { defineCommand } from "citty"
+
+const SHELLS = ["bash", "zsh", "fish", "powershell"] as const
+type Shell = (typeof SHELLS)[number]
+
+interface FlagSpec {
+  name: string
+  alias?: string
+  description: string
+  takesValue: boolean
+}
+
+interface CommandSpec {
+  name: string
+  description: string
+  flags: Array<FlagSpec>
+  // Nested commands such as `service install`, completed after the name
+  subcommands: Array<CommandSpec>
+}
+
+interface CliSpec {
+  name: string
+  description: string
+  commands: Array<CommandSpec>
+}
+
+const resolve = async <T>(value: Resolvable<T>): Promise<T> =>
+  typeof value === "function" ? await (value as () => T | Promise<T>)() : value
+
+async function collectCommands(
+  parent: CommandDef,
+): Promise<Array<CommandSpec>> {
+  const subCommands =
+    parent.subCommands ? await resolve(parent.subCommands) : {}
+
+  return Promise.all(
+    Object.entries(subCommands).map(async ([name, command]) => {
+      const definition = await resolve(command as Resolvable<CommandDef>)
+      const commandMeta =
+        definition.meta ? await resolve(definition.meta) : {}
+      const args: ArgsDef = definition.args ? await resolve(definition.args) : {}
+
+      const flags = Object.entries(args)
+        .filter(([, arg]) => arg.type !== "positional")
+        .map(([flag, arg]) => ({
+          name: flag,
+          alias: (arg as { alias?: string }).alias,
+          description: arg.description ?? "",
+          takesValue: arg.type !== "boolean",
+        }))
+
+      return {
+        name,
+        description: commandMeta.description ?? "",
+        flags,
+        subcommands: await collectCommands(definition),
+      }
+    }),
+  )
+}
+
+async function collectSpec(root: CommandDef): Promise<CliSpec> {
+  const meta = root.meta ? await resolve(root.meta) : {}
+  return {
+    name: meta.name ?? "copilot-api",
+    description: meta.description ?? "",
+    commands: await collectCommands(root),
+  }
+}
+
+// Every command with the names leading to it, parents before their children
+const flattenCommands = (
+  commands: Array<CommandSpec>,
+  parents: Array<string> = [],
+): Array<{ path: Array<string>; command: CommandSpec }> =>
+  commands.flatMap((command) => {
+    const path = [...parents, command.name]
+    return [{ path, command }, ...flattenCommands(command.subcommands, path)]
+  })
+
+const names = (commands: Array<CommandSpec>) =>
+  commands.map((command) => command.name)
+
+const flagWords = (command: CommandSpec) =>
+  command.flags.flatMap((flag) => [
+    `--${flag.name}`,
+    ...(flag.alias ? [`-${flag.alias}`] : []),
+  ])
+
+const escapeSingle = (text: string) => text.replaceAll("'", String.raw`'\''`)
+
+// The `case` branches for the commands at word `depth`, nested for groups
+function bashCases(
+  commands: Array<CommandSpec>,
+  depth: number,
+  indent: string,
+): string {
+  return commands
+    .map((command) => {
+      if (command.subcommands.length === 0) {
+        return `${indent}${command.name}) opts="${flagWords(command).join(" ")}" ;;`
+      }
+      return [
+        `${indent}${command.name})`,
+        `${indent}  if [ "$COMP_CWORD" -eq ${depth + 1} ]; then`,
+        `${indent}    opts="${names(command.subcommands).join(" ")}"`,
+        `${indent}  else`,
+        `${indent}    case "\${COMP_WORDS[${depth + 1}]}" in`,
+        bashCases(command.subcommands, depth + 1, `${indent}      `),
+        `${indent}    esac`,
+        `${indent}  fi ;;`,
+      ].join("\n")
+    })
+    .join("\n")
+}
+
+function bashScript(spec: CliSpec): string {
+  const fn = `_${spec.name.replaceAll("-", "_")}_completions`
+  const cases = bashCases(spec.commands, 1, "    ")
+
+  return `${fn}() {
+  local cur="\${COMP_WORDS[COMP_CWORD]}"
+  if [ "$COMP_CWORD" -eq 1 ]; then
+    COMPREPLY=($(compgen -W "${spec.commands.map((c) => c.name).join(" ")}" -- "$cur"))
+    return
+  fi
+  local opts=""
+  case "\${COMP_WORDS[1]}" in
+${cases}
+  esac
+  COMPREPLY=($(compgen -W "$opts" -- "$cur"))
+}
+complete -o default -F ${fn} ${spec.name}
+`
+}
+
+// One function per command group, which completes the group's commands and
+// hands their arguments to _arguments or to the function of a nested group
+function zshFunctions(fn: string, commands: Array<CommandSpec>): string {
+  const commandList = commands
+    .map(
+      (command) =>
+        `    '${command.name}:${escapeSingle(command.description)}'`,
+    )
+    .join("\n")
+  const cases = commands
+    .map((command) => {
+      if (command.subcommands.length > 0) {
+        return `    ${command.name})\n      ${fn}_${command.name}\n      ;;`
+      }
+      const specs = command.flags
+        .map(
+          (flag) =>
+            `        '--${flag.name}[${escapeSingle(flag.description).replaceAll("]", String.raw`\]`)}]${flag.takesValue ? ":value:" : ""}'`,
+        )
+        .join(" \\\n")
+      return `    ${command.name})\n      _arguments \\\n${specs}\n      ;;`
+    })
+    .join("\n")
+  const nested = commands
+    .filter((command) => command.subcommands.length > 0)
+    .map((command) =>
+      zshFunctions(`${fn}_${command.name}`, command.subcommands),
+    )
+
+  return [
+    ...nested,
+    `${fn}() {
+  local -a commands
+  commands=(
+${commandList}
+  )
+  if (( CURRENT == 2 )); then
+    _describe 'command' commands
+    return
+  fi
+  shift words
+  (( CURRENT-- ))
+  case "$words[1]" in
+${cases}
+  esac
+}
+`,
+  ].join("\n")
+}
+
+function zshScript(spec: CliSpec): string {
+  const fn = `_${spec.name.replaceAll("-", "_")}`
+  return `#compdef ${spec.name}
+
+${zshFunctions(fn, spec.commands)}
+compdef ${fn} ${spec.name}
+`
+}
+
+// True when the words after the program start with `path`. Matched by
+// position, since `team audit` and `audit` share a name.
+const fishPathCondition = (path: Array<string>) =>
+  path
+    .map(
+      (name, index) => `contains -- ${name} (commandline -opc)[${index + 2}]`,
+    )
+    .join("; and ")
+
+function fishScript(spec: CliSpec): string {
+  const lines = [`complete -c ${spec.name} -f`]
+  for (const command of spec.commands) {
+    lines.push(
+      `complete -c ${spec.name} -n '__fish_use_subcommand' -a ${command.name} -d '${escapeSingle(command.description)}'`,
+    )
+  }
+  for (const { path, command } of flattenCommands(spec.commands)) {
+    const condition = fishPathCondition(path)
+    for (const subcommand of command.subcommands) {
+      lines.push(
+        `complete -c ${spec.name} -n '${condition}; and not __fish_seen_subcommand_from ${names(command.subcommands).join(" ")}' -a ${subcommand.name} -d '${escapeSingle(subcommand.description)}'`,
+      )
+    }
+    for (const flag of command.flags) {
+      const short = flag.alias ? ` -s ${flag.alias}` : ""
+      const value = flag.takesValue ? " -r" : ""
+      lines.push(
+        `complete -c ${spec.name} -n '${condition}' -l ${flag.name}${short}${value} -d '${escapeSingle(flag.description)}'`,
+      )
+    }
+  }
+  return `${lines.join("\n")}\n`
+}
+
+const powershellList = (words: Array<string>) =>
+  `@(${words.map((word) => `'${word}'`).join(", ")})`
+
+// The `switch` branches for the commands at word `depth`, nested for groups
+function powershellCases(
+  commands: Array<CommandSpec>,
+  depth: number,
+  indent: string,
+): string {
+  return commands
+    .map((command) => {
+      if (command.subcommands.length === 0) {
+        return `${indent}'${command.name}' { ${powershellList(flagWords(command))} }`
+      }
+      return [
+        `${indent}'${command.name}' {`,
+        `${indent}  if ($words.Count -le ${depth + 1} -or ($words.Count -eq ${depth + 2} -and $wordToComplete)) {`,
+        `${indent}    ${powershellList(names(command.subcommands))}`,
+        `${indent}  } else {`,
+        `${indent}    switch ($words[${depth + 1}]) {`,
+        powershellCases(command.subcommands, depth + 1, `${indent}      `),
+        `${indent}      default { @() }`,
+        `${indent}    }`,
+        `${indent}  }`,
+        `${indent}}`,
+      ].join("\n")
+    })
+    .join("\n")
+}
+
+function powershellScript(spec: CliSpec): string {
+  const cases = powershellCases(spec.commands, 1, "    ")
+
+  return `Register-ArgumentCompleter -Native -CommandName '${spec.name}' -ScriptBlock {
+  param($wordToComplete, $commandAst, $cursorPosition)
+  $words = $commandAst.CommandElements | ForEach-Object { $_.ToString() }
+  if ($words.Count -le 1 -or ($words.Count -eq 2 -and $wordToComplete)) {
+    $candidates = @(${spec.commands.map((c) => `'${c.name}'`).join(", ")})
+  } else {
+    $candidates = switch ($words[1]) {
+${cases}
+    default { @() }
+    }
+  }
+  $candidates | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+    [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+  }
+}
+`
+}
+
+const escapeRoff = (text: string) =>
+  text.replaceAll("\\", String.raw`\e`).replaceAll("-", String.raw`\-`)
+
+function manPage(spec: CliSpec): string {
+  const lines = [
+    `.TH ${spec.name.toUpperCase()} 1`,
+    ".SH NAME",
+    `${spec.name} \\- ${escapeRoff(spec.description)}`,
+    ".SH SYNOPSIS",
+    `.B ${spec.name}`,
+    String.raw`\fICOMMAND\fR [\fIOPTIONS\fR]`,
+    ".SH COMMANDS",
+  ]
+
+  for (const { path, command } of flattenCommands(spec.commands)) {
+    lines.push(".TP", `.B ${path.join(" ")}`, escapeRoff(command.description))
+    for (const flag of command.flags) {
+      const alias = flag.alias ? `, \\-${flag.alias}` : ""
+      lines.push(
+        ".RS",
+        ".TP",
+        `.B \\-\\-${escapeRoff(flag.name)}${alias}`,
+        escapeRoff(flag.description),
+        ".RE",
+      )
+    }
+  }
+
+  return `${lines.join("\n")}\n`
+}
+
+const generators: Record<Shell, (spec: CliSpec) => string> = {
+  bash: bashScript,
+  zsh: zshScript,
+  fish: fishScript,
+  powershell: powershellScript,
+}
+
+export const createCompletionsCommand = (root: () => CommandDef) =>
+  defineCommand({
+    meta: {
+      name: "completions",
+      description: "Print a shell completion script (bash, zsh, fish, powershell)",
+    },
+    args: {
+      shell: {
+        type: "positional",
+        required: true,
+        description: `Shell to generate completions for (${SHELLS.join(", ")})`,
+      },
+    },
+    async run({ args }) {
+      const shell = args.shell as Shell
+      if (!SHELLS.includes(shell)) {
+        throw new Error(
+          `Unsupported shell "${args.shell}", expected one of: ${SHELLS.join(", ")}`,
+        )
+      }
+
+      const spec = await collectSpec(root())
+      process.stdout.write(generators[shell](spec))
+    },
+  })
+
+export const createManCommand = (root: () => CommandDef) =>
+  defineCommand({
+    meta: {
+      name: "man",
+      description: "Print a man page in roff format",
+    },
+    async run() {
+      const spec = await collectSpec(root())
+      process.stdout.write(manPage(spec))
+    },
+  })
@@ -0,0 +1,283 @@
+#!/usr/bin/env node
+
+import { defineCommand } from "citty"
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { copilotBaseUrl } from "./lib/api-config"
+import { HTTPError } from "./lib/error"
+import { PATHS } from "./lib/paths"
+import { describeUpstreamError, PROXY_ENV_VARS } from "./lib/readiness"
+import { features, rustCore } from "./lib/rust-core"
+import { state } from "./lib/state"
+import { getTokenCount } from "./lib/tokenizer"
+import { cacheVSCodeVersion } from "./lib/utils"
+import { getModels } from "./services/copilot/get-models"
+import { getCopilotToken } from "./services/github/get-copilot-token"
+import { getGitHubUser } from "./services/github/get-user"
+
+interface RunDoctorOptions {
+  verbose: boolean
+  accountType: string
+  githubToken?: string
+}
+
+type CheckStatus = "ok" | "warn" | "fail"
+
+interface CheckResult {
+  name: string
+  status: CheckStatus
+  message: string
+  hint?: string
+}
+
+async function checkGitHubToken(
+  options: RunDoctorOptions,
+): Promise<CheckResult> {
+  if (options.githubToken) {
+    state.githubToken = options.githubToken
+    return {
+      name: "GitHub token",
+      status: "ok",
+      message: "Using token from --github-token",
+    }
+  }
+
+  const token = await fs
+    .readFile(PATHS.GITHUB_TOKEN_PATH, "utf8")
+    .catch(() => "")
+
+  if (!token.trim()) {
+    return {
+      name: "GitHub token",
+      status: "fail",
+      message: `No token found at ${PATHS.GITHUB_TOKEN_PATH}`,
+      hint: "Run `copilot-api auth` to log in.",
+    }
+  }
+
+  state.githubToken = token.trim()
+  return {
+    name: "GitHub token",
+    status: "ok",
+    message: `Loaded from ${PATHS.GITHUB_TOKEN_PATH}`,
+  }
+}
+
+async function checkGitHubUser(): Promise<CheckResult> {
+  try {
+    const user = await getGitHubUser()
+    return {
+      name: "GitHub user",
+      status: "ok",
+      message: `Logged in as ${user.login}`,
+    }
+  } catch (error) {
+    return {
+      name: "GitHub user",
+      status: "fail",
+      ...describeUpstreamError(error),
+    }
+  }
+}
+
+async function checkCopilotToken(): Promise<CheckResult> {
+  try {
+    const { token, expires_at } = await getCopilotToken()
+    state.copilotToken = token
+
+    const expiresIn = expires_at - Math.floor(Date.now() / 1000)
+    if (expiresIn <= 0) {
+      return {
+        name: "Copilot token",
+        status: "fail",
+        message: "Copilot token is already expired",
+        hint: "Check that your system clock is correct.",
+      }
+    }
+
+    return {
+      name: "Copilot token",
+      status: "ok",
+      message: `Token exchange succeeded, expires in ${expiresIn}s`,
+    }
+  } catch (error) {
+    return {
+      name: "Copilot token",
+      status: "fail",
+      ...describeUpstreamError(error),
+    }
+  }
+}
+
+async function checkModels(): Promise<CheckResult> {
+  try {
+    const models = await getModels()
+    return {
+      name: "Models",
+      status: "ok",
+      message: `Fetched ${models.data.length} models from ${copilotBaseUrl(state)}`,
+    }
+  } catch (error) {
+    const result: CheckResult = {
+      name: "Models",
+      status: "fail",
+      ...describeUpstreamError(error),
+    }
+    if (error instanceof HTTPError && !result.hint) {
+      result.hint = `Check that --account-type (currently "${state.accountType}") matches your Copilot plan.`
+    }
+    return result
+  }
+}
+
+async function checkTokenizers(): Promise<Array<CheckResult>> {
+  const sample = [{ role: "user" as const, content: "Hello, world!" }]
+  const results: Array<CheckResult> = []
+
+  try {
+    const { input } = getTokenCount(sample)
+    results.push({
+      name: "JS tokenizer",
+      status: "ok",
+      message: `gpt-tokenizer available (${input} tokens for sample)`,
+    })
+  } catch (error) {
+    results.push({
+      name: "JS tokenizer",
+      status: "fail",
+      message: (error as Error).message,
+    })
+  }
+
+  try {
+    const { input } = await rustCore.getTokenCount(sample)
+    results.push({
+      name: "Rust tokenizer",
+      status: "ok",
+      message: `Native module available (${input} tokens for sample)`,
+    })
+  } catch (error) {
+    results.push({
+      name: "Rust tokenizer",
+      status: features.USE_RUST_TOKENIZER ? "fail" : "warn",
+      message: (error as Error).message,
+      hint: "Build the native module with `bun run build:native`, or unset USE_RUST_TOKENIZER.",
+    })
+  }
+
+  return results
+}
+
+function printConfig(): void {
+  const proxy = PROXY_ENV_VARS.find((name) => process.env[name])
+  const enabledFeatures = Object.entries(features)
+    .filter(([, enabled]) => enabled)
+    .map(([name]) => name)
+
+  consola.box(
+    [
+      `App directory:   ${PATHS.APP_DIR}`,
+      `Token path:      ${PATHS.GITHUB_TOKEN_PATH}`,
+      `Account type:    ${state.accountType}`,
+      `Copilot API:     ${copilotBaseUrl(state)}`,
+      `VSCode version:  ${state.vsCodeVersion}`,
+      `Proxy:           ${proxy ? process.env[proxy] : "none"}`,
+      `Feature flags:   ${enabledFeatures.join(", ") || "none"}`,
+    ].join("\n"),
+  )
+}
+
+function report(result: CheckResult): void {
+  const line = `${result.name}: ${result.message}`
+  switch (result.status) {
+    case "ok": {
+      consola.success(line)
+      break
+    }
+    case "warn": {
+      consola.warn(line)
+      break
+    }
+    case "fail": {
+      consola.error(line)
+      break
+    }
+  }
+  if (result.hint) consola.info(`  → ${result.hint}`)
+}
+
+export async function runDoctor(options: RunDoctorOptions): Promise<void> {
+  if (options.verbose) {
+    consola.level = 5
+    consola.info("Verbose logging enabled")
+  }
+
+  state.accountType = options.accountType
+
+  await cacheVSCodeVersion()
+  printConfig()
+
+  const results: Array<CheckResult> = []
+  const tokenResult = await checkGitHubToken(options)
+  results.push(tokenResult)
+
+  // Every upstream check depends on the previous one succeeding
+  if (tokenResult.status === "ok") {
+    const userResult = await checkGitHubUser()
+    results.push(userResult)
+
+    if (userResult.status === "ok") {
+      const copilotResult = await checkCopilotToken()
+      results.push(copilotResult)
+
+      if (copilotResult.status === "ok") results.push(await checkModels())
+    }
+  }
+
+  results.push(...(await checkTokenizers()))
+
+  for (const result of results) report(result)
+
+  const failures = results.filter((result) => result.status === "fail")
+  if (failures.length > 0) {
+    consola.error(`${failures.length} check(s) failed`)
+    process.exitCode = 1
+    return
+  }
+
+  consola.success("All checks passed")
+}
+
+export const doctor = defineCommand({
+  meta: {
+    name: "doctor",
+    description: "Check credentials, connectivity and configuration",
+  },
+  args: {
+    verbose: {
+      alias: "v",
+      type: "boolean",
+      default: false,
+      description: "Enable verbose logging",
+    },
+    "account-type": {
+      alias: "a",
+      type: "string",
+      default: "individual",
+      description: "Account type to use (individual, business, enterprise)",
+    },
+    "github-token": {
+      alias: "g",
+      type: "string",
+      description: "Check a GitHub token directly instead of the stored one",
+    },
+  },
+  run({ args }) {
+    return runDoctor({
+      verbose: args.verbose,
+      accountType: args["account-type"],
+      githubToken: args["github-token"],
+    })
+  },
+})
@@ -0,0 +1,251 @@
+import type * as Grpc from "@grpc/grpc-js"
+
+import consola from "consola"
+import { fileURLToPath } from "node:url"
+
+import type {
+  ChatCompletionChunk,
+  ChatCompletionsPayload,
+  Message,
+} from "./services/copilot/create-chat-completions"
+
+import { HTTPError } from "./lib/error"
+import { checkKeyModelAccess } from "./lib/model-policy"
+import { checkRateLimit } from "./lib/rate-limit"
+import { state } from "./lib/state"
+import { streamCompletion } from "./lib/streamed-completion"
+import { createEmbeddings } from "./services/copilot/create-embeddings"
+
+// Resolves from both src/ and the bundled dist/
+const PROTO_PATH = fileURLToPath(
+  new URL("../proto/copilot_gateway.proto", import.meta.url),
+)
+
+// Field names as decoded by proto-loader, which camelCases them
+interface ChatRequest {
+  model: string
+  messages: Array<{
+    role: Message["role"]
+    content: string
+    name?: string
+    toolCallId?: string
+  }>
+  temperature?: number
+  maxTokens?: number
+  topP?: number
+  stop: Array<string>
+}
+
+interface Usage {
+  promptTokens: number
+  completionTokens: number
+  totalTokens: number
+}
+
+interface ChatChunk {
+  id: string
+  model: string
+  content: string
+  finishReason?: string
+  usage?: Usage
+}
+
+interface EmbedRequest {
+  model: string
+  input: Array<string>
+}
+
+interface EmbedResponse {
+  model: string
+  data: Array<{ index: number; values: Array<number> }>
+  usage: Usage
+}
+
+interface ListModelsResponse {
+  models: Array<{
+    id: string
+    name: string
+    vendor: string
+    contextWindow?: number
+    maxOutputTokens?: number
+  }>
+}
+
+interface GatewayPackage {
+  copilot: {
+    gateway: { v1: { CopilotGateway: Grpc.ServiceClientConstructor } }
+  }
+}
+
+type GrpcModule = typeof Grpc
+
+function toServiceError(
+  grpc: GrpcModule,
+  error: unknown,
+): Partial<Grpc.ServiceError> {
+  if (!(error instanceof HTTPError)) {
+    return { code: grpc.status.INTERNAL, details: (error as Error).message }
+  }
+
+  const codes: Record<number, Grpc.status> = {
+    400: grpc.status.INVALID_ARGUMENT,
+    401: grpc.status.UNAUTHENTICATED,
+    403: grpc.status.PERMISSION_DENIED,
+    404: grpc.status.NOT_FOUND,
+    429: grpc.status.RESOURCE_EXHAUSTED,
+  }
+  return {
+    code: codes[error.response.status] ?? grpc.status.UNAVAILABLE,
+    details: `${error.message} (HTTP ${error.response.status})`,
+  }
+}
+
+// The API key sent as `x-api-key` or `authorization: Bearer` metadata, the
+// same headers the HTTP routes read
+function apiKeyOf(metadata: Grpc.Metadata): string | undefined {
+  const [key] = metadata.get("x-api-key")
+  const [authorization] = metadata.get("authorization")
+  const value =
+    key === undefined ?
+      authorization?.toString().replace(/^Bearer\s+/i, "")
+    : key.toString()
+  return value || undefined
+}
+
+function toChatPayload(request: ChatRequest): ChatCompletionsPayload {
+  return {
+    model: request.model,
+    messages: request.messages.map((message) => ({
+      role: message.role,
+      content: message.content,
+      name: message.name,
+      tool_call_id: message.toolCallId,
+    })),
+    temperature: request.temperature,
+    max_tokens: request.maxTokens,
+    top_p: request.topP,
+    stop: request.stop.length > 0 ? request.stop : undefined,
+    stream: true,
+  }
+}
+
+function toChatChunk(chunk: ChatCompletionChunk): ChatChunk {
+  const choice = chunk.choices.at(0)
+  return {
+    id: chunk.id,
+    model: chunk.model,
+    content: choice?.delta.content ?? "",
+    finishReason: choice?.finish_reason ?? undefined,
+    usage:
+      chunk.usage ?
+        {
+          promptTokens: chunk.usage.prompt_tokens,
+          completionTokens: chunk.usage.completion_tokens,
+          totalTokens: chunk.usage.total_tokens,
+        }
+      : undefined,
+  }
+}
+
+function createHandlers(grpc: GrpcModule): Grpc.UntypedServiceImplementation {
+  return {
+    async chatCompletion(call: Grpc.ServerWritableStream<ChatRequest, ChatChunk>) {
+      let cancelled = false
+      call.on("cancelled", () => {
+        cancelled = true
+      })
+
+      try {
+        const finished = await streamCompletion(toChatPayload(call.request), {
+          endpoint: "/copilot.gateway.v1.CopilotGateway/ChatCompletion",
+          apiKey: apiKeyOf(call.metadata),
+          isClosed: () => cancelled,
+          onChunk: (chunk) => call.write(toChatChunk(chunk)),
+        })
+        if (finished) call.end()
+      } catch (error) {
+        consola.error("gRPC ChatCompletion failed:", error)
+        if (!cancelled) call.emit("error", toServiceError(grpc, error))
+      }
+    },
+
+    async embed(
+      call: Grpc.ServerUnaryCall<EmbedRequest, EmbedResponse>,
+      callback: Grpc.sendUnaryData<EmbedResponse>,
+    ) {
+      try {
+        await checkRateLimit(state)
+        checkKeyModelAccess(apiKeyOf(call.metadata), call.request.model)
+        const response = await createEmbeddings({
+          model: call.request.model,
+          input: call.request.input,
+        })
+        callback(null, {
+          model: response.model,
+          data: response.data.map((item) => ({
+            index: item.index,
+            values: item.embedding,
+          })),
+          usage: {
+            promptTokens: response.usage.prompt_tokens,
+            completionTokens: 0,
+            totalTokens: response.usage.total_tokens,
+          },
+        })
+      } catch (error) {
+        consola.error("gRPC Embed failed:", error)
+        callback(toServiceError(grpc, error))
+      }
+    },
+
+    listModels(
+      _call: Grpc.ServerUnaryCall<unknown, ListModelsResponse>,
+      callback: Grpc.sendUnaryData<ListModelsResponse>,
+    ) {
+      callback(null, {
+        models: (state.models?.data ?? []).map((model) => ({
+          id: model.id,
+          name: model.name,
+          vendor: model.vendor,
+          contextWindow: model.capabilities.limits.max_context_window_tokens,
+          maxOutputTokens: model.capabilities.limits.max_output_tokens,
+        })),
+      })
+    },
+  }
+}
+
+/**
+ * Serves the gRPC API on `port`. `@grpc/grpc-js` and `@grpc/proto-loader`
+ * are optional dependencies, so they are only loaded when gRPC is enabled.
+ * @returns The bound port, which differs from `port` when it is 0.
+ */
+export async function startGrpcServer(
+  port: number,
+  host = "0.0.0.0",
+): Promise<number> {
+  const grpc = await import("@grpc/grpc-js")
+  const protoLoader = await import("@grpc/proto-loader")
+
+  const definition = await protoLoader.load(PROTO_PATH, {
+    longs: Number,
+    arrays: true,
+  })
+  const gateway = (
+    grpc.loadPackageDefinition(definition) as unknown as GatewayPackage
+  ).copilot.gateway.v1
+
+  const server = new grpc.Server()
+  server.addService(gateway.CopilotGateway.service, createHandlers(grpc))
+
+  return new Promise((resolve, reject) => {
+    server.bindAsync(
+      `${host}:${port}`,
+      grpc.ServerCredentials.createInsecure(),
+      (error, boundPort) => {
+        if (error) reject(error)
+        else resolve(boundPort)
+      },
+    )
+  })
+}
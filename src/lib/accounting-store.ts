@@ -0,0 +1,401 @@
+// Where usage records and audit entries are kept. By default they are JSON
+// Lines files, one per UTC day; replicas behind a load balancer can share a
+// Postgres database instead, and a single instance can use SQLite.
+
+import type { Database } from "bun:sqlite"
+
+import fs from "node:fs/promises"
+import path from "node:path"
+import { promisify } from "node:util"
+import zlib from "node:zlib"
+
+import type { AuditEntry } from "./audit"
+import type { UsageRecord } from "./usage-ledger"
+
+import { PATHS } from "./paths"
+import { state } from "./state"
+
+export interface AccountingStore {
+  kind: "files" | "sqlite" | "postgres"
+  appendUsage: (record: UsageRecord) => Promise<void>
+  /** Records at or after `since`, of one key id when given, oldest first. */
+  readUsage: (filter: {
+    key?: string
+    since?: string
+  }) => Promise<Array<UsageRecord>>
+  appendAudit: (entry: AuditEntry) => Promise<void>
+  /** Entries between the ISO timestamps (inclusive), oldest first. */
+  readAudit: (range: {
+    since?: string
+    until?: string
+  }) => Promise<Array<AuditEntry>>
+  /** The entry with this id, or undefined when there is none. */
+  getAudit: (id: string) => Promise<AuditEntry | undefined>
+  /** Drops entries before the UTC day; returns the files or rows removed. */
+  pruneAudit: (beforeDay: string) => Promise<number>
+  close: () => Promise<void>
+}
+
+const DAY_FILE = /^\d{4}-\d{2}-\d{2}\.jsonl$/
+// Audit days may also have compressed entries and their index
+const AUDIT_FILE = /^\d{4}-\d{2}-\d{2}\.(?:jsonl|br|index\.jsonl)$/
+
+async function listDays(dir: string, pattern = DAY_FILE): Promise<Array<string>> {
+  try {
+    const files = await fs.readdir(dir)
+    const days = files
+      .filter((file) => pattern.test(file))
+      .map((file) => file.slice(0, 10))
+    return [...new Set(days)].sort()
+  } catch {
+    return []
+  }
+}
+
+async function readOptional(filePath: string): Promise<string | undefined> {
+  try {
+    return await fs.readFile(filePath, "utf8")
+  } catch (error) {
+    if ((error as NodeJS.ErrnoException).code === "ENOENT") return undefined
+    throw error
+  }
+}
+
+async function appendLine(dir: string, time: string, value: unknown) {
+  await fs.mkdir(dir, { recursive: true, mode: 0o700 })
+  await fs.appendFile(
+    path.join(dir, `${time.slice(0, 10)}.jsonl`),
+    `${JSON.stringify(value)}\n`,
+    { mode: 0o600 },
+  )
+}
+
+// Reads the lines of the day files from `since` to `until`, oldest first
+async function readLines<T extends { time: string }>(
+  dir: string,
+  range: { since?: string; until?: string },
+): Promise<Array<T>> {
+  const { since, until } = range
+  const values: Array<T> = []
+  for (const day of await listDays(dir)) {
+    if (since && day < since.slice(0, 10)) continue
+    if (until && day > until.slice(0, 10)) continue
+
+    const content = await fs.readFile(path.join(dir, `${day}.jsonl`), "utf8")
+    for (const line of content.split("\n")) {
+      if (!line) continue
+      const value = JSON.parse(line) as T
+      if (since && value.time < since) continue
+      if (until && value.time > until) continue
+      values.push(value)
+    }
+  }
+  return values
+}
+
+/**
+ * Where a compressed audit entry is in its day's `.br` file. Each entry is
+ * a brotli stream of its own, so one can be read without the others.
+ */
+interface AuditIndexEntry {
+  id: string
+  time: string
+  endpoint: string
+  model: string
+  offset: number
+  length: number
+  // Of the uncompressed JSON
+  size: number
+}
+
+const brotliCompress = promisify(zlib.brotliCompress)
+const brotliDecompress = promisify(zlib.brotliDecompress)
+
+// Quality 11, the default, costs too much CPU for every request
+const BROTLI_OPTIONS = {
+  params: { [zlib.constants.BROTLI_PARAM_QUALITY]: 5 },
+}
+
+const auditFile = (day: string, extension: string) =>
+  path.join(PATHS.AUDIT_DIR, `${day}.${extension}`)
+
+// Offsets come from the file size, so appends must not overlap
+let compressedWrites = Promise.resolve()
+
+function appendCompressedAudit(entry: AuditEntry): Promise<void> {
+  const write = compressedWrites.then(async () => {
+    const json = JSON.stringify(entry)
+    const compressed = await brotliCompress(json, BROTLI_OPTIONS)
+    const day = entry.time.slice(0, 10)
+    await fs.mkdir(PATHS.AUDIT_DIR, { recursive: true, mode: 0o700 })
+
+    const offset = await fs
+      .stat(auditFile(day, "br"))
+      .then((stat) => stat.size)
+      .catch(() => 0)
+    await fs.appendFile(auditFile(day, "br"), compressed, { mode: 0o600 })
+    const index: AuditIndexEntry = {
+      id: entry.id ?? "",
+      time: entry.time,
+      endpoint: entry.endpoint,
+      model: entry.model,
+      offset,
+      length: compressed.length,
+      size: Buffer.byteLength(json),
+    }
+    await fs.appendFile(
+      auditFile(day, "index.jsonl"),
+      `${JSON.stringify(index)}\n`,
+      { mode: 0o600 },
+    )
+  })
+  // A failed write must not fail the ones queued after it
+  compressedWrites = write.catch(() => {})
+  return write
+}
+
+async function readAuditIndex(day: string): Promise<Array<AuditIndexEntry>> {
+  const content = await readOptional(auditFile(day, "index.jsonl"))
+  return (content ?? "")
+    .split("\n")
+    .filter(Boolean)
+    .map((line) => JSON.parse(line) as AuditIndexEntry)
+}
+
+async function readCompressedAudit(
+  day: string,
+  entries: Array<AuditIndexEntry>,
+): Promise<Array<AuditEntry>> {
+  if (entries.length === 0) return []
+  const file = await fs.open(auditFile(day, "br"), "r")
+  try {
+    const values: Array<AuditEntry> = []
+    for (const entry of entries) {
+      const buffer = Buffer.alloc(entry.length)
+      await file.read(buffer, 0, entry.length, entry.offset)
+      const json = await brotliDecompress(buffer)
+      values.push(JSON.parse(json.toString("utf8")) as AuditEntry)
+    }
+    return values
+  } finally {
+    await file.close()
+  }
+}
+
+// Plain and compressed entries, as the setting may change between restarts
+async function readAuditFiles(range: {
+  since?: string
+  until?: string
+}): Promise<Array<AuditEntry>> {
+  const { since, until } = range
+  const plain = await readLines<AuditEntry>(PATHS.AUDIT_DIR, range)
+  const compressed: Array<AuditEntry> = []
+  for (const day of await listDays(PATHS.AUDIT_DIR, AUDIT_FILE)) {
+    if (since && day < since.slice(0, 10)) continue
+    if (until && day > until.slice(0, 10)) continue
+    const entries = (await readAuditIndex(day)).filter(
+      (entry) =>
+        (!since || entry.time >= since) && (!until || entry.time <= until),
+    )
+    compressed.push(...(await readCompressedAudit(day, entries)))
+  }
+  if (compressed.length === 0) return plain
+  return [...plain, ...compressed].sort((a, b) => a.time.localeCompare(b.time))
+}
+
+// Newest days first, through the indexes before scanning plain files
+async function findAuditFile(id: string): Promise<AuditEntry | undefined> {
+  const days = (await listDays(PATHS.AUDIT_DIR, AUDIT_FILE)).reverse()
+  for (const day of days) {
+    const entry = (await readAuditIndex(day)).find((item) => item.id === id)
+    if (entry) return (await readCompressedAudit(day, [entry]))[0]
+  }
+  for (const day of days) {
+    const content = await readOptional(auditFile(day, "jsonl"))
+    for (const line of content?.split("\n") ?? []) {
+      if (!line.includes(id)) continue
+      const entry = JSON.parse(line) as AuditEntry
+      if (entry.id === id) return entry
+    }
+  }
+  return undefined
+}
+
+// Reads PATHS on every call, so tests can point it elsewhere
+const fileStore: AccountingStore = {
+  kind: "files",
+  appendUsage: (record) => appendLine(PATHS.USAGE_DIR, record.time, record),
+  async readUsage(filter) {
+    const records = await readLines<UsageRecord>(PATHS.USAGE_DIR, filter)
+    return filter.key === undefined ? records : (
+        records.filter((record) => record.key === filter.key)
+      )
+  },
+  appendAudit: (entry) =>
+    state.auditCompress ?
+      appendCompressedAudit(entry)
+    : appendLine(PATHS.AUDIT_DIR, entry.time, entry),
+  readAudit: readAuditFiles,
+  getAudit: findAuditFile,
+  async pruneAudit(beforeDay) {
+    let removed = 0
+    for (const file of await fs.readdir(PATHS.AUDIT_DIR).catch(() => [])) {
+      if (!AUDIT_FILE.test(file) || file.slice(0, 10) >= beforeDay) continue
+      await fs.rm(path.join(PATHS.AUDIT_DIR, file), { force: true })
+      removed++
+    }
+    return removed
+  },
+  close: async () => {},
+}
+
+// Prefixed, as the database may be shared with other applications. Times
+// are ISO timestamps, which compare correctly as text; absent bounds are
+// bound as NULL.
+const SCHEMA = [
+  "CREATE TABLE IF NOT EXISTS copilot_api_usage (time TEXT NOT NULL, key TEXT NOT NULL, record TEXT NOT NULL)",
+  "CREATE INDEX IF NOT EXISTS copilot_api_usage_key_time ON copilot_api_usage (key, time)",
+  "CREATE TABLE IF NOT EXISTS copilot_api_audit (time TEXT NOT NULL, entry TEXT NOT NULL)",
+  "CREATE INDEX IF NOT EXISTS copilot_api_audit_time ON copilot_api_audit (time)",
+]
+
+/** SQLite through the driver built into Bun, so it is unavailable under Node. */
+async function openSqliteStore(
+  filePath: string,
+): Promise<AccountingStore> {
+  const { Database } = await import("bun:sqlite")
+  const db: Database = new Database(filePath, { create: true })
+  db.exec("PRAGMA journal_mode = WAL")
+  for (const statement of SCHEMA) db.exec(statement)
+
+  const insertUsage = db.query(
+    "INSERT INTO copilot_api_usage (time, key, record) VALUES (?1, ?2, ?3)",
+  )
+  const selectUsage = db.query<
+    { record: string },
+    [string | null, string | null]
+  >(
+    "SELECT record FROM copilot_api_usage WHERE (?1 IS NULL OR time >= ?1) AND (?2 IS NULL OR key = ?2) ORDER BY time",
+  )
+  const insertAudit = db.query(
+    "INSERT INTO copilot_api_audit (time, entry) VALUES (?1, ?2)",
+  )
+  const selectAudit = db.query<
+    { entry: string },
+    [string | null, string | null]
+  >(
+    "SELECT entry FROM copilot_api_audit WHERE (?1 IS NULL OR time >= ?1) AND (?2 IS NULL OR time <= ?2) ORDER BY time",
+  )
+  const selectAuditById = db.query<{ entry: string }, [string]>(
+    "SELECT entry FROM copilot_api_audit WHERE json_extract(entry, '$.id') = ?1",
+  )
+  const deleteAudit = db.query("DELETE FROM copilot_api_audit WHERE time < ?1")
+
+  return {
+    kind: "sqlite",
+    appendUsage: async (record) => {
+      insertUsage.run(record.time, record.key, JSON.stringify(record))
+    },
+    readUsage: async ({ key, since }) =>
+      selectUsage
+        .all(since ?? null, key ?? null)
+        .map((row) => JSON.parse(row.record) as UsageRecord),
+    appendAudit: async (entry) => {
+      insertAudit.run(entry.time, JSON.stringify(entry))
+    },
+    readAudit: async ({ since, until }) =>
+      selectAudit
+        .all(since ?? null, until ?? null)
+        .map((row) => JSON.parse(row.entry) as AuditEntry),
+    getAudit: async (id) => {
+      const row = selectAuditById.get(id)
+      return row ? (JSON.parse(row.entry) as AuditEntry) : undefined
+    },
+    pruneAudit: async (beforeDay) => deleteAudit.run(beforeDay).changes,
+    close: async () => db.close(),
+  }
+}
+
+// jsonb columns come back parsed, but be lenient with drivers that do not
+const parseJson = <T>(value: unknown): T =>
+  (typeof value === "string" ? JSON.parse(value) : value) as T
+
+/** Postgres through the client built into Bun, so it is unavailable under Node. */
+async function openPostgresStore(
+  url: string,
+): Promise<AccountingStore> {
+  const { SQL } = await import("bun")
+  const sql = new SQL(url)
+  await sql`CREATE TABLE IF NOT EXISTS copilot_api_usage (time TEXT NOT NULL, key TEXT NOT NULL, record JSONB NOT NULL)`
+  await sql`CREATE INDEX IF NOT EXISTS copilot_api_usage_key_time ON copilot_api_usage (key, time)`
+  await sql`CREATE TABLE IF NOT EXISTS copilot_api_audit (time TEXT NOT NULL, entry JSONB NOT NULL)`
+  await sql`CREATE INDEX IF NOT EXISTS copilot_api_audit_time ON copilot_api_audit (time)`
+
+  return {
+    kind: "postgres",
+    appendUsage: async (record) => {
+      await sql`INSERT INTO copilot_api_usage (time, key, record) VALUES (${record.time}, ${record.key}, ${JSON.stringify(record)}::jsonb)`
+    },
+    readUsage: async ({ key, since }) => {
+      const rows: Array<{ record: unknown }> =
+        await sql`SELECT record FROM copilot_api_usage WHERE (${since ?? null}::text IS NULL OR time >= ${since ?? null}) AND (${key ?? null}::text IS NULL OR key = ${key ?? null}) ORDER BY time`
+      return rows.map((row) => parseJson<UsageRecord>(row.record))
+    },
+    appendAudit: async (entry) => {
+      await sql`INSERT INTO copilot_api_audit (time, entry) VALUES (${entry.time}, ${JSON.stringify(entry)}::jsonb)`
+    },
+    readAudit: async ({ since, until }) => {
+      const rows: Array<{ entry: unknown }> =
+        await sql`SELECT entry FROM copilot_api_audit WHERE (${since ?? null}::text IS NULL OR time >= ${since ?? null}) AND (${until ?? null}::text IS NULL OR time <= ${until ?? null}) ORDER BY time`
+      return rows.map((row) => parseJson<AuditEntry>(row.entry))
+    },
+    getAudit: async (id) => {
+      const rows: Array<{ entry: unknown }> =
+        await sql`SELECT entry FROM copilot_api_audit WHERE entry->>'id' = ${id} LIMIT 1`
+      return rows[0] && parseJson<AuditEntry>(rows[0].entry)
+    },
+    pruneAudit: async (beforeDay) => {
+      const result: { count: number } =
+        await sql`DELETE FROM copilot_api_audit WHERE time < ${beforeDay}`
+      return result.count
+    },
+    close: () => sql.close(),
+  }
+}
+
+/**
+ * Opens the store named by `sqlite:<path>` or a `postgres://` URL.
+ * @throws {TypeError} On any other URL.
+ */
+export async function openAccountingStore(
+  url: string,
+): Promise<AccountingStore> {
+  if (url.startsWith("sqlite:")) {
+    return openSqliteStore(url.slice("sqlite:".length).replace(/^\/\//, ""))
+  }
+  if (/^postgres(?:ql)?:/.test(url)) return openPostgresStore(url)
+  throw new TypeError(
+    `Invalid accounting store: ${url}, use sqlite:<path> or a postgres:// URL`,
+  )
+}
+
+export const accountingStore = () => state.accountingStore ?? fileStore
+
+/**
+ * Runs a command with the store of `url` selected, and closes it afterwards
+ * so an open database connection does not keep the process alive.
+ */
+export async function withAccountingStore<T>(
+  url: string | undefined,
+  task: () => Promise<T>,
+): Promise<T> {
+  if (!url) return task()
+  const store = await openAccountingStore(url)
+  state.accountingStore = store
+  try {
+    return await task()
+  } finally {
+    state.accountingStore = undefined
+    await store.close()
+  }
+}
@@ -0,0 +1,76 @@
+// The admin endpoints hand out API keys and show other clients' traffic.
+// With --admin-token they need it as a bearer token. In team mode, where the
+// clients are different people, they are refused until one is set. Without
+// a token, only requests from this machine get through, reads included:
+// audit entries, live streams and samples show other clients' prompts, and
+// turning on body logging would leak them to whoever can reach the port.
+
+import type { Context, MiddlewareHandler } from "hono"
+import type { ServerRequest } from "srvx"
+
+import { createHash, timingSafeEqual } from "node:crypto"
+
+import {
+  forwardedChain,
+  isTrustedProxy,
+  parseTrustedProxies,
+} from "./client-ip"
+import { state } from "./state"
+
+const LOOPBACK = parseTrustedProxies("127.0.0.0/8,::1")
+
+const digest = (token: string) => createHash("sha256").update(token).digest()
+
+const unauthorized = (message: string) =>
+  Response.json(
+    {
+      error: {
+        message,
+        type: "invalid_request_error",
+        code: "invalid_api_key",
+      },
+    },
+    { status: 401 },
+  )
+
+// No token would help, the gateway has none to check
+const forbidden = (message: string) =>
+  Response.json(
+    { error: { message, type: "invalid_request_error", code: "local_only" } },
+    { status: 403 },
+  )
+
+// Sent from this machine and not forwarded by a proxy on it
+function isLocalRequest(c: Context): boolean {
+  const peer = (c.req.raw as Partial<ServerRequest>).ip
+  return (
+    peer !== undefined
+    && isTrustedProxy(peer, LOOPBACK)
+    && forwardedChain(c.req.raw.headers).length === 0
+  )
+}
+
+/** Rejects requests without the admin token, when one is required. */
+export const requireAdmin: MiddlewareHandler = async (c, next) => {
+  if (!state.adminToken) {
+    if (state.teamStore) {
+      return unauthorized(
+        "The admin endpoints are disabled in team mode until the gateway is started with --admin-token",
+      )
+    }
+    if (isLocalRequest(c)) return next()
+    return forbidden(
+      "The admin endpoints only answer this machine unless the gateway is started with --admin-token",
+    )
+  }
+
+  const presented = c.req.header("authorization")?.replace(/^Bearer\s+/i, "")
+  // Compared as digests, so neither length nor content leaks through timing
+  if (
+    !presented
+    || !timingSafeEqual(digest(presented), digest(state.adminToken))
+  ) {
+    return unauthorized("A valid admin token is required")
+  }
+  return next()
+}
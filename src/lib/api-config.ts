@@ -1,7 +1,23 @@
+import { AsyncLocalStorage } from "node:async_hooks"
 import { randomUUID } from "node:crypto"
 
 import type { State } from "./state"
 
+/**
+ * In team mode, the credentials of the member making the current request,
+ * used instead of the gateway's own.
+ */
+export const memberCredentials = new AsyncLocalStorage<{
+  githubToken: string
+  copilotToken: string
+}>()
+
+export const currentCopilotToken = (state: State) =>
+  memberCredentials.getStore()?.copilotToken ?? state.copilotToken
+
+const currentGitHubToken = (state: State) =>
+  memberCredentials.getStore()?.githubToken ?? state.githubToken
+
 export const standardHeaders = () => ({
   "content-type": "application/json",
   accept: "application/json",
@@ -19,7 +35,7 @@ export const copilotBaseUrl = (state: State) =>
   : `https://api.${state.accountType}.githubcopilot.com`
 export const copilotHeaders = (state: State, vision: boolean = false) => {
   const headers: Record<string, string> = {
-    Authorization: `Bearer ${state.copilotToken}`,
+    Authorization: `Bearer ${currentCopilotToken(state)}`,
     "content-type": standardHeaders()["content-type"],
     "copilot-integration-id": "vscode-chat",
     "editor-version": `vscode/${state.vsCodeVersion}`,
@@ -39,7 +55,7 @@ export const copilotHeaders = (state: State, vision: boolean = false) => {
 export const GITHUB_API_BASE_URL = "https://api.github.com"
 export const githubHeaders = (state: State) => ({
   ...standardHeaders(),
-  authorization: `token ${state.githubToken}`,
+  authorization: `token ${currentGitHubToken(state)}`,
   "editor-version": `vscode/${state.vsCodeVersion}`,
   "editor-plugin-version": EDITOR_PLUGIN_VERSION,
   "user-agent": USER_AGENT,
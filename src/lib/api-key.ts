@@ -0,0 +1,31 @@
+import type { Context } from "hono"
+
+import { createHash } from "node:crypto"
+
+import { certificateApiKey } from "./client-cert"
+import { signedApiKey } from "./request-signing"
+
+/**
+ * The client's API key, from `x-api-key` (Anthropic clients) or an
+ * `Authorization: Bearer` header (OpenAI clients), or the key a signed
+ * request or client certificate acts as. Outside team mode, request signing
+ * and mutual TLS the gateway does not authenticate clients; keys only select
+ * per-client policies.
+ */
+export function apiKeyOf(c: Context): string | undefined {
+  const key =
+    signedApiKey()
+    ?? certificateApiKey()
+    ?? c.req.header("x-api-key")
+    ?? c.req.header("authorization")?.replace(/^Bearer\s+/i, "")
+  return key || undefined
+}
+
+/**
+ * A short, stable identifier for an API key that is safe to keep in memory
+ * and write to disk.
+ */
+export function keyId(key: string | undefined): string {
+  if (!key) return "anonymous"
+  return createHash("sha256").update(key).digest("hex").slice(0, 16)
+}
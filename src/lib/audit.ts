@@ -0,0 +1,169 @@
+import type { MiddlewareHandler } from "hono"
+
+import consola from "consola"
+import { AsyncLocalStorage } from "node:async_hooks"
+import { randomUUID } from "node:crypto"
+
+import type { ChatCompletionChunk } from "~/services/copilot/create-chat-completions"
+
+import { accountingStore } from "./accounting-store"
+import { state } from "./state"
+
+export const AUDIT_ID_HEADER = "x-audit-id"
+
+const REDACTED = "[REDACTED]"
+const HOUR_MS = 60 * 60 * 1000
+const DAY_MS = 24 * HOUR_MS
+
+// Replacements keep the surrounding text so redacted entries stay readable
+const SECRET_PATTERNS: Array<[RegExp, string]> = [
+  [
+    /-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----/g,
+    REDACTED,
+  ],
+  [/\bBearer\s+[\w.~+/-]{16,}=*/gi, `Bearer ${REDACTED}`],
+  // OpenAI and Anthropic API keys
+  [/\bsk-[\w-]{20,}/g, REDACTED],
+  // GitHub personal, OAuth, app and fine-grained tokens
+  [/\b(?:gh[pousr]|github_pat)_\w{20,}/g, REDACTED],
+  // AWS access key IDs and secret access keys
+  [/\b(?:AKIA|ASIA)[\dA-Z]{16}\b/g, REDACTED],
+  [
+    /(aws_secret_access_key\s*[:=]\s*["']?)[\d+/A-Za-z]{40}/gi,
+    `$1${REDACTED}`,
+  ],
+  [/\bxox[abprs]-[\dA-Za-z-]{10,}/g, REDACTED],
+  [/\bAIza[\w-]{35}/g, REDACTED],
+]
+
+export function redactSecrets(text: string): string {
+  let redacted = text
+  for (const [pattern, replacement] of SECRET_PATTERNS) {
+    redacted = redacted.replace(pattern, replacement)
+  }
+  return redacted
+}
+
+export function redactValue(value: unknown): unknown {
+  if (typeof value === "string") return redactSecrets(value)
+  if (Array.isArray(value)) return value.map((item) => redactValue(item))
+  if (value !== null && typeof value === "object") {
+    return Object.fromEntries(
+      Object.entries(value).map(([key, item]) => [key, redactValue(item)]),
+    )
+  }
+  return value
+}
+
+export interface AuditEntry {
+  // Sent to the client as `x-audit-id`; absent from older entries
+  id?: string
+  time: string
+  endpoint: string
+  model: string
+  stream: boolean
+  request: unknown
+  response: unknown
+}
+
+/**
+ * Appends a redacted entry to the audit log. Does nothing unless auditing
+ * was enabled with `--audit`. Write failures are logged, never thrown, so
+ * auditing cannot fail a request.
+ */
+export async function recordAudit(
+  entry: Omit<AuditEntry, "time">,
+): Promise<void> {
+  if (state.auditRetentionDays === undefined) return
+
+  const time = new Date().toISOString()
+  const id = auditId.getStore() ?? randomUUID()
+  try {
+    await accountingStore().appendAudit(
+      redactValue({ id, time, ...entry }) as AuditEntry,
+    )
+  } catch (error) {
+    consola.warn("Failed to write audit entry:", (error as Error).message)
+  }
+}
+
+/**
+ * Deletes entries older than `retentionDays`. Returns how many day files,
+ * or database rows, were removed.
+ */
+export async function pruneAuditLog(
+  retentionDays: number,
+  now = Date.now(),
+): Promise<number> {
+  const cutoff = new Date(now - retentionDays * DAY_MS)
+    .toISOString()
+    .slice(0, 10)
+  return accountingStore().pruneAudit(cutoff)
+}
+
+/**
+ * Prunes the log every `intervalMs` while the gateway runs, so entries do
+ * not outlive the retention period until the next restart.
+ */
+export function startAuditPruning(
+  retentionDays: number,
+  intervalMs = HOUR_MS,
+): NodeJS.Timeout {
+  return setInterval(() => {
+    pruneAuditLog(retentionDays).catch((error: unknown) => {
+      consola.warn("Failed to prune the audit log:", error)
+    })
+  }, intervalMs).unref()
+}
+
+const auditId = new AsyncLocalStorage<string>()
+
+/**
+ * Gives the request the id its audit entry is recorded with, and tells the
+ * client in the `x-audit-id` header, so the exchange can be looked up later.
+ */
+export const assignAuditId: MiddlewareHandler = async (c, next) => {
+  if (state.auditRetentionDays === undefined) return next()
+  const id = randomUUID()
+  c.header(AUDIT_ID_HEADER, id)
+  return auditId.run(id, next)
+}
+
+/** The entry recorded with this id, or undefined when there is none. */
+export function readAuditEntry(id: string): Promise<AuditEntry | undefined> {
+  return accountingStore().getAudit(id)
+}
+
+/** Reads entries between the given ISO timestamps (inclusive), oldest first. */
+export function readAuditLog(
+  range: { since?: string; until?: string } = {},
+): Promise<Array<AuditEntry>> {
+  return accountingStore().readAudit(range)
+}
+
+/** Accumulates a streamed completion into the shape of a non-streaming one. */
+export function createStreamTranscript() {
+  let content = ""
+  const toolCalls: Record<
+    number,
+    { id?: string; name?: string; arguments: string }
+  > = {}
+
+  return {
+    add(chunk: ChatCompletionChunk) {
+      for (const choice of chunk.choices) {
+        content += choice.delta.content ?? ""
+        for (const call of choice.delta.tool_calls ?? []) {
+          toolCalls[call.index] ??= { arguments: "" }
+          const entry = toolCalls[call.index]
+          entry.id ??= call.id
+          entry.name ??= call.function?.name
+          entry.arguments += call.function?.arguments ?? ""
+        }
+      }
+    },
+    result() {
+      return { content, tool_calls: Object.values(toolCalls) }
+    },
+  }
+}
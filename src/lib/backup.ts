@@ -0,0 +1,178 @@
+// A single JSON file with what a long-running gateway accumulates, so it can
+// be moved to a new host: the team's API keys, usage records and snapshots,
+// the given config files and, when asked for, the GitHub tokens.
+
+import consola from "consola"
+import fs from "node:fs/promises"
+import path from "node:path"
+
+import type { UsageSnapshot } from "./usage-projection"
+
+import { accountingStore } from "./accounting-store"
+import { PATHS } from "./paths"
+import { readTeamStore, updateTeamStore, type TeamStore } from "./team"
+import { readUsage, type UsageRecord } from "./usage-ledger"
+
+const BACKUP_VERSION = 1
+
+export interface GatewayBackup {
+  version: number
+  createdAt: string
+  // The gateway's own GitHub token, only with includeTokens
+  githubToken?: string
+  // Members' GitHub tokens are left out unless includeTokens is set
+  team: TeamStore
+  usage: Array<UsageRecord>
+  usageSnapshots: Array<UsageSnapshot>
+  // Contents by absolute path
+  config: Record<string, string>
+}
+
+export interface RestoreSummary {
+  githubToken: boolean
+  members: number
+  // Exported without their GitHub token, so they could not be restored
+  skippedMembers: Array<string>
+  usage: number
+  usageSnapshots: number
+  config: Array<string>
+}
+
+async function readOptional(filePath: string): Promise<string | undefined> {
+  try {
+    return await fs.readFile(filePath, "utf8")
+  } catch (error) {
+    if ((error as NodeJS.ErrnoException).code === "ENOENT") return undefined
+    throw error
+  }
+}
+
+const parseLines = <T>(content: string | undefined): Array<T> =>
+  (content ?? "")
+    .split("\n")
+    .filter(Boolean)
+    .map((line) => JSON.parse(line) as T)
+
+export async function createBackup(options: {
+  includeTokens: boolean
+  configFiles?: Array<string>
+}): Promise<GatewayBackup> {
+  const team = await readTeamStore()
+  if (!options.includeTokens) {
+    for (const member of Object.values(team.members)) member.githubToken = ""
+  }
+
+  const config: Record<string, string> = {}
+  for (const file of options.configFiles ?? []) {
+    config[path.resolve(file)] = await fs.readFile(file, "utf8")
+  }
+
+  return {
+    version: BACKUP_VERSION,
+    createdAt: new Date().toISOString(),
+    githubToken:
+      options.includeTokens ?
+        (await readOptional(PATHS.GITHUB_TOKEN_PATH))?.trim() || undefined
+      : undefined,
+    team,
+    usage: await readUsage({}),
+    usageSnapshots: parseLines<UsageSnapshot>(
+      await readOptional(PATHS.USAGE_SNAPSHOTS_PATH),
+    ),
+    config,
+  }
+}
+
+/**
+ * Restores a backup on top of the current state. Existing tokens, members
+ * and config files are kept unless `force` is set; usage records and
+ * snapshots that are already present are not added twice.
+ */
+export async function restoreBackup(
+  backup: GatewayBackup,
+  options: { force: boolean },
+): Promise<RestoreSummary> {
+  if (backup.version !== BACKUP_VERSION) {
+    throw new Error(
+      `Unsupported backup version ${backup.version}, expected ${BACKUP_VERSION}`,
+    )
+  }
+  await fs.mkdir(PATHS.APP_DIR, { recursive: true })
+
+  const summary: RestoreSummary = {
+    githubToken: false,
+    members: 0,
+    skippedMembers: [],
+    usage: 0,
+    usageSnapshots: 0,
+    config: [],
+  }
+
+  const currentToken = (await readOptional(PATHS.GITHUB_TOKEN_PATH))?.trim()
+  if (backup.githubToken && (options.force || !currentToken)) {
+    await fs.writeFile(PATHS.GITHUB_TOKEN_PATH, backup.githubToken, {
+      mode: 0o600,
+    })
+    summary.githubToken = true
+  }
+
+  await updateTeamStore((store) => {
+    for (const [id, member] of Object.entries(backup.team.members)) {
+      if (id in store.members && !options.force) continue
+      if (!member.githubToken) {
+        summary.skippedMembers.push(member.login)
+        continue
+      }
+      store.members[id] = member
+      summary.members++
+    }
+    // Events of both hosts, oldest first and without those restored before
+    const events = new Map(
+      [...(backup.team.audit ?? []), ...(store.audit ?? [])].map((event) => [
+        JSON.stringify(event),
+        event,
+      ]),
+    )
+    store.audit = [...events.values()].sort((a, b) =>
+      a.time.localeCompare(b.time),
+    )
+  })
+
+  const known = new Set(
+    (await readUsage({})).map((record) => JSON.stringify(record)),
+  )
+  for (const record of backup.usage) {
+    if (known.has(JSON.stringify(record))) continue
+    await accountingStore().appendUsage(record)
+    summary.usage++
+  }
+
+  const knownSnapshots = new Set(
+    parseLines<UsageSnapshot>(
+      await readOptional(PATHS.USAGE_SNAPSHOTS_PATH),
+    ).map((snapshot) => snapshot.time),
+  )
+  const missing = backup.usageSnapshots.filter(
+    (snapshot) => !knownSnapshots.has(snapshot.time),
+  )
+  if (missing.length > 0) {
+    await fs.appendFile(
+      PATHS.USAGE_SNAPSHOTS_PATH,
+      missing.map((snapshot) => `${JSON.stringify(snapshot)}\n`).join(""),
+      { mode: 0o600 },
+    )
+    summary.usageSnapshots = missing.length
+  }
+
+  for (const [filePath, content] of Object.entries(backup.config)) {
+    if (!options.force && (await readOptional(filePath)) !== undefined) {
+      consola.warn(`Keeping existing ${filePath}, use --force to replace it`)
+      continue
+    }
+    await fs.mkdir(path.dirname(filePath), { recursive: true })
+    await fs.writeFile(filePath, content, { mode: 0o600 })
+    summary.config.push(filePath)
+  }
+
+  return summary
+}
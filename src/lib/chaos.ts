@@ -0,0 +1,120 @@
+// Failure injection for testing client retry and resume logic against the
+// gateway. Only enabled through COPILOT_GATEWAY_CHAOS, never by a flag, so
+// it cannot be switched on by accident in a shared deployment.
+
+import type { MiddlewareHandler } from "hono"
+
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { rateLimitErrorBody } from "./error"
+import { state } from "./state"
+import { sleep } from "./utils"
+
+/** Each `rate` is the probability, from 0 to 1, of injecting that failure. */
+export interface ChaosConfig {
+  delay?: { rate: number; minMs?: number; maxMs?: number }
+  rateLimit?: { rate: number; retryAfterSeconds?: number }
+  // Ends a stream early but cleanly, as if the upstream had stopped
+  truncate?: { rate: number; maxEvents?: number }
+  // Aborts the response body mid-transfer
+  disconnect?: { rate: number; maxEvents?: number }
+  // Route prefixes to disrupt, default every POST request
+  routes?: Array<string>
+}
+
+export async function loadChaosConfig(filePath: string): Promise<ChaosConfig> {
+  return JSON.parse(await fs.readFile(filePath, "utf8")) as ChaosConfig
+}
+
+type Random = () => number
+
+const hit = (rule: { rate: number } | undefined, random: Random) =>
+  rule !== undefined && random() < rule.rate
+
+/**
+ * Passes `events` SSE events through, then ends the body. Without `events`,
+ * as for JSON responses, it ends halfway through the first chunk.
+ */
+function cutBody(
+  body: ReadableStream<Uint8Array>,
+  events: number | undefined,
+  mode: "truncate" | "disconnect",
+): ReadableStream<Uint8Array> {
+  const reader = body.getReader()
+  const decoder = new TextDecoder()
+  let seen = 0
+
+  return new ReadableStream({
+    async pull(controller) {
+      const { done, value } = await reader.read()
+      if (done) {
+        controller.close()
+        return
+      }
+      if (events === undefined) {
+        controller.enqueue(value.slice(0, Math.floor(value.length / 2)))
+      } else {
+        controller.enqueue(value)
+        seen +=
+          decoder.decode(value, { stream: true }).split("\n\n").length - 1
+        if (seen < events) return
+      }
+
+      await reader.cancel()
+      if (mode === "truncate") controller.close()
+      else controller.error(new Error("Injected disconnect"))
+    },
+    cancel(reason) {
+      return reader.cancel(reason)
+    },
+  })
+}
+
+/**
+ * Injects the failures configured in `state.chaos`. `random` is replaceable
+ * so tests can force each outcome.
+ */
+export function createChaosMiddleware(
+  random: Random = Math.random,
+): MiddlewareHandler {
+  return async (c, next) => {
+    const config = state.chaos
+    if (!config || c.req.method !== "POST") return next()
+    const routes = config.routes
+    if (routes && !routes.some((route) => c.req.path.startsWith(route))) {
+      return next()
+    }
+
+    const { delay, rateLimit } = config
+    if (delay && hit(delay, random)) {
+      const { minMs = 500, maxMs = 5000 } = delay
+      const delayMs = Math.round(minMs + random() * (maxMs - minMs))
+      consola.debug(`Chaos: delaying ${c.req.path} by ${delayMs}ms`)
+      await sleep(delayMs)
+    }
+
+    if (rateLimit && hit(rateLimit, random)) {
+      const retryAfter = rateLimit.retryAfterSeconds ?? 1
+      consola.debug(`Chaos: rejecting ${c.req.path} with 429`)
+      c.header("retry-after", String(retryAfter))
+      return c.json(rateLimitErrorBody("Injected rate limit"), 429)
+    }
+
+    await next()
+
+    const streaming = c.res.headers
+      .get("content-type")
+      ?.startsWith("text/event-stream")
+    const mode =
+      hit(config.disconnect, random) ? "disconnect"
+      : streaming && hit(config.truncate, random) ? "truncate"
+      : undefined
+    if (!mode || !c.res.body) return
+
+    const maxEvents = config[mode]?.maxEvents ?? 20
+    const events = streaming ? Math.floor(random() * maxEvents) : undefined
+    consola.debug(`Chaos: ${mode} for ${c.req.path}`)
+    c.res = new Response(cutBody(c.res.body, events, mode), c.res)
+  }
+}
@@ -0,0 +1,90 @@
+import clipboard from "clipboardy"
+import consola from "consola"
+import { spawn } from "node:child_process"
+import invariant from "tiny-invariant"
+
+import { generateEnvScript } from "./shell"
+import { state } from "./state"
+
+export interface ClaudeCodeOptions {
+  model?: string
+  smallModel?: string
+  launch: boolean
+}
+
+async function pickModel(
+  message: string,
+  preselected: string | undefined,
+): Promise<string> {
+  invariant(state.models, "Models should be loaded by now")
+  const modelIds = state.models.data.map((model) => model.id)
+
+  if (preselected) {
+    if (!modelIds.includes(preselected)) {
+      consola.warn(`Model ${preselected} is not in the Copilot model list`)
+    }
+    return preselected
+  }
+
+  return await consola.prompt(message, {
+    type: "select",
+    options: modelIds,
+  })
+}
+
+/** The variables that point Claude Code at this server. */
+export function claudeCodeEnv(
+  serverUrl: string,
+  model: string,
+  smallModel: string,
+): Record<string, string> {
+  return {
+    ANTHROPIC_BASE_URL: serverUrl,
+    ANTHROPIC_AUTH_TOKEN: "dummy",
+    ANTHROPIC_MODEL: model,
+    ANTHROPIC_SMALL_FAST_MODEL: smallModel,
+  }
+}
+
+/**
+ * Prepares the environment Claude Code needs to talk to this server, then
+ * either launches `claude` with it or hands the user a command to run.
+ */
+export async function setupClaudeCode(
+  serverUrl: string,
+  options: ClaudeCodeOptions,
+): Promise<void> {
+  const selectedModel = await pickModel(
+    "Select a model to use with Claude Code",
+    options.model,
+  )
+  const selectedSmallModel = await pickModel(
+    "Select a small model to use with Claude Code",
+    options.smallModel,
+  )
+
+  const env = claudeCodeEnv(serverUrl, selectedModel, selectedSmallModel)
+
+  if (options.launch) {
+    consola.info("Launching Claude Code")
+    const child = spawn("claude", [], {
+      stdio: "inherit",
+      env: { ...process.env, ...env },
+    })
+    child.on("error", (error) => {
+      consola.error("Failed to launch Claude Code:", error.message)
+    })
+    // The server only exists for this session, so stop together with it
+    child.on("exit", (code) => process.exit(code ?? 0))
+    return
+  }
+
+  const command = generateEnvScript(env, "claude")
+
+  try {
+    clipboard.writeSync(command)
+    consola.success("Copied Claude Code command to clipboard!")
+  } catch {
+    consola.info(`Run Claude Code with:\n${command}`)
+  }
+}
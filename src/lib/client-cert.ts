@@ -0,0 +1,92 @@
+import type { MiddlewareHandler } from "hono"
+import type { TLSSocket } from "node:tls"
+import type { ServerOptions, ServerRequest } from "srvx"
+
+import { AsyncLocalStorage } from "node:async_hooks"
+import fs from "node:fs/promises"
+
+import { state } from "./state"
+
+export interface TlsFiles {
+  cert: string
+  key: string
+  // Require client certificates issued by this CA
+  clientCa?: string
+}
+
+/** Client certificates, when the listener requires them. */
+export interface ClientCertificates {
+  // Certificate CN -> the API key its requests act as; unlisted CNs act as
+  // `cert:<CN>`
+  identities: Record<string, string>
+}
+
+export async function loadCertificateIdentities(
+  filePath: string,
+): Promise<Record<string, string>> {
+  return JSON.parse(await fs.readFile(filePath, "utf8")) as Record<
+    string,
+    string
+  >
+}
+
+// Bun takes its TLS options through `bun.tls`, next to the websocket handler
+type TlsServeOptions = Pick<ServerOptions, "tls" | "node"> & {
+  bunTls?: Bun.TLSOptions
+}
+
+/**
+ * The srvx options for HTTPS, and for mutual TLS with a client CA.
+ * Connections without a certificate from the CA fail the handshake.
+ */
+export async function tlsServeOptions(
+  files: TlsFiles,
+): Promise<TlsServeOptions> {
+  const [cert, key] = await Promise.all([
+    fs.readFile(files.cert, "utf8"),
+    fs.readFile(files.key, "utf8"),
+  ])
+  if (!files.clientCa) return { tls: { cert, key } }
+
+  const ca = await fs.readFile(files.clientCa, "utf8")
+  const mutual = { ca, requestCert: true, rejectUnauthorized: true }
+  return {
+    tls: { cert, key },
+    node: mutual,
+    bunTls: { cert, key, ...mutual },
+  }
+}
+
+// The common name of the verified client certificate. Only Node exposes the
+// peer certificate to request handlers; under Bun it is verified but unnamed.
+export function peerCommonName(request: Request): string | undefined {
+  const socket = (request as Partial<ServerRequest>).runtime?.node?.req
+    .socket as TLSSocket | undefined
+  if (!socket?.authorized) return undefined
+  const cn = socket.getPeerCertificate().subject.CN as
+    | string
+    | Array<string>
+    | undefined
+  return Array.isArray(cn) ? cn[0] : cn
+}
+
+const certificateKey = new AsyncLocalStorage<string>()
+
+export const certificateApiKey = () => certificateKey.getStore()
+
+/**
+ * Attributes requests over mutual TLS to the API key mapped to the client
+ * certificate's CN, for rate limiting, usage and per-key policies.
+ */
+export const clientCertificate: MiddlewareHandler = async (c, next) => {
+  const certificates = state.clientCertificates
+  if (!certificates) return next()
+
+  const cn = peerCommonName(c.req.raw)
+  if (!cn) return next()
+  const key =
+    Object.hasOwn(certificates.identities, cn) ?
+      certificates.identities[cn]
+    : `cert:${cn}`
+  return certificateKey.run(key, next)
+}
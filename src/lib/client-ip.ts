@@ -0,0 +1,178 @@
+import type { Context } from "hono"
+import type { ServerRequest } from "srvx"
+
+import { state } from "./state"
+
+/** An address or CIDR range from `--trusted-proxies`. */
+export interface Subnet {
+  v6: boolean
+  value: bigint
+  prefix: number
+}
+
+interface Address {
+  v6: boolean
+  value: bigint
+}
+
+function parseIPv4(raw: string): bigint | undefined {
+  const parts = raw.split(".")
+  if (parts.length !== 4) return undefined
+  let value = 0n
+  for (const part of parts) {
+    if (!/^\d{1,3}$/.test(part) || Number(part) > 255) return undefined
+    value = (value << 8n) | BigInt(part)
+  }
+  return value
+}
+
+// A trailing dotted quad, as in ::ffff:192.0.2.1, becomes two groups
+function expandEmbeddedIPv4(groups: Array<string>): Array<string> | undefined {
+  const last = groups.at(-1)
+  if (!last?.includes(".")) return groups
+  const v4 = parseIPv4(last)
+  if (v4 === undefined) return undefined
+  return [
+    ...groups.slice(0, -1),
+    (v4 >> 16n).toString(16),
+    (v4 & 0xffffn).toString(16),
+  ]
+}
+
+function parseIPv6(raw: string): bigint | undefined {
+  const halves = raw.split("::")
+  if (halves.length > 2) return undefined
+  const head = halves[0] ? halves[0].split(":") : []
+  const rawTail = halves.length === 2 && halves[1] ? halves[1].split(":") : []
+  const tail = expandEmbeddedIPv4(halves.length === 2 ? rawTail : head)
+  if (!tail) return undefined
+
+  let groups: Array<string>
+  if (halves.length === 2) {
+    const missing = 8 - head.length - tail.length
+    if (missing < 1) return undefined
+    groups = [...head, ...Array<string>(missing).fill("0"), ...tail]
+  } else {
+    groups = tail
+  }
+  if (groups.length !== 8) return undefined
+
+  let value = 0n
+  for (const group of groups) {
+    if (!/^[\da-f]{1,4}$/i.test(group)) return undefined
+    value = (value << 16n) | BigInt(`0x${group}`)
+  }
+  return value
+}
+
+/**
+ * Parses an IPv4 or IPv6 address, with optional brackets and zone. IPv4
+ * mapped IPv6 addresses are treated as IPv4.
+ */
+export function parseAddress(raw: string): Address | undefined {
+  const address = raw.replace(/^\[(.*)\]$/, "$1").replace(/%.*$/, "")
+  const v4 = parseIPv4(address)
+  if (v4 !== undefined) return { v6: false, value: v4 }
+
+  const v6 = parseIPv6(address)
+  if (v6 === undefined) return undefined
+  // ::ffff:0:0/96
+  if (v6 >> 32n === 0xffffn) {
+    return { v6: false, value: v6 & 0xffffffffn }
+  }
+  return { v6: true, value: v6 }
+}
+
+const widthOf = (address: Address) => (address.v6 ? 128 : 32)
+
+/**
+ * Parses a comma-separated list of addresses and CIDR ranges, such as
+ * `10.0.0.0/8,::1`.
+ * @throws {TypeError} On an entry that is not an address or range.
+ */
+export function parseTrustedProxies(raw: string): Array<Subnet> {
+  return raw
+    .split(",")
+    .map((entry) => entry.trim())
+    .filter(Boolean)
+    .map((entry) => {
+      const [address, prefixRaw] = entry.split("/", 2) as [
+        string,
+        string | undefined,
+      ]
+      const parsed = parseAddress(address)
+      if (!parsed) throw new TypeError(`Invalid trusted proxy: "${entry}"`)
+
+      const prefix =
+        prefixRaw === undefined ? widthOf(parsed) : Number(prefixRaw)
+      if (!/^\d{1,3}$/.test(prefixRaw ?? "0") || prefix > widthOf(parsed)) {
+        throw new TypeError(`Invalid trusted proxy: "${entry}"`)
+      }
+      return { ...parsed, prefix }
+    })
+}
+
+export function isTrustedProxy(raw: string, proxies: Array<Subnet>): boolean {
+  const address = parseAddress(raw)
+  if (!address) return false
+  const width = widthOf(address)
+  return proxies.some((subnet) => {
+    if (subnet.v6 !== address.v6) return false
+    const shift = BigInt(width - subnet.prefix)
+    return address.value >> shift === subnet.value >> shift
+  })
+}
+
+// Strips the port from `192.0.2.1:8080` and `[2001:db8::1]:8080`
+function stripPort(node: string): string {
+  if (node.startsWith("[")) return node.slice(1, node.indexOf("]"))
+  return node.split(":").length === 2 ? node.slice(0, node.indexOf(":")) : node
+}
+
+/**
+ * The client and proxy addresses a request passed through, nearest last,
+ * from `Forwarded` (RFC 7239) or else `X-Forwarded-For`.
+ */
+export function forwardedChain(headers: Headers): Array<string> {
+  const forwarded = headers.get("forwarded")
+  if (forwarded) {
+    return forwarded
+      .split(",")
+      .map((element) =>
+        element
+          .split(";")
+          .map((pair) => pair.trim())
+          .find((pair) => pair.toLowerCase().startsWith("for=")),
+      )
+      .filter((pair) => pair !== undefined)
+      .map((pair) => stripPort(pair.slice(4).replaceAll('"', "")))
+  }
+
+  return (headers.get("x-forwarded-for") ?? "")
+    .split(",")
+    .map((node) => stripPort(node.trim()))
+    .filter(Boolean)
+}
+
+/**
+ * The address of the client, given the connecting peer. Forwarding headers
+ * are only believed while the hop that added them is a trusted proxy, so a
+ * client cannot pick its own address by sending `X-Forwarded-For`.
+ */
+export function resolveClientIp(
+  peer: string | undefined,
+  headers: Headers,
+  proxies: Array<Subnet>,
+): string | undefined {
+  if (!peer || !isTrustedProxy(peer, proxies)) return peer
+
+  const chain = forwardedChain(headers)
+  const client = chain.findLast((node) => !isTrustedProxy(node, proxies))
+  return client ?? chain.at(0) ?? peer
+}
+
+/** The real client address, looking through `--trusted-proxies`. */
+export function clientIp(c: Context): string | undefined {
+  const peer = (c.req.raw as Partial<ServerRequest>).ip
+  return resolveClientIp(peer, c.req.raw.headers, state.trustedProxies ?? [])
+}
@@ -0,0 +1,184 @@
+import type { Context } from "hono"
+
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import type {
+  ChatCompletionResponse,
+  ChatCompletionsPayload,
+  Message,
+} from "~/services/copilot/create-chat-completions"
+
+import { HTTPError } from "./error"
+
+export interface PolicyContext {
+  // Sent back to the client in the `x-content-policy` header
+  annotations: Array<string>
+}
+
+/**
+ * A request/response filter. Filters run in configuration order on the
+ * OpenAI-shaped payload, so they apply to both the OpenAI and Anthropic
+ * routes. Throw an HTTPError from `onRequest` to reject the request.
+ * `onResponse` only sees non-streaming responses.
+ */
+export interface ContentFilter {
+  name: string
+  onRequest?: (
+    payload: ChatCompletionsPayload,
+    context: PolicyContext,
+  ) => ChatCompletionsPayload
+  onResponse?: (
+    response: ChatCompletionResponse,
+    context: PolicyContext,
+  ) => ChatCompletionResponse
+}
+
+export type FilterConfig =
+  | { type: "forbidden-patterns"; patterns: Array<string>; message?: string }
+  | { type: "max-data-url"; maxBytes: number }
+  | { type: "annotate"; note: string }
+
+const messageTexts = (message: Message): Array<string> => {
+  if (typeof message.content === "string") return [message.content]
+  return (message.content ?? []).flatMap((part) =>
+    part.type === "text" ? [part.text] : [],
+  )
+}
+
+export function forbiddenPatternsFilter(
+  patterns: Array<string>,
+  message = "Request blocked by content policy",
+): ContentFilter {
+  const regexes = patterns.map((pattern) => new RegExp(pattern, "i"))
+  const matches = (text: string) => regexes.some((regex) => regex.test(text))
+
+  return {
+    name: "forbidden-patterns",
+    onRequest(payload) {
+      if (payload.messages.some((m) => messageTexts(m).some(matches))) {
+        consola.warn("Request blocked by content policy")
+        throw new HTTPError(
+          message,
+          Response.json({ message }, { status: 400 }),
+        )
+      }
+      return payload
+    },
+    // Model output is flagged rather than blocked
+    onResponse(response, context) {
+      const flagged = response.choices.some(
+        (choice) => choice.message.content && matches(choice.message.content),
+      )
+      if (flagged) context.annotations.push("response-matched-forbidden-pattern")
+      return response
+    },
+  }
+}
+
+export function maxDataUrlFilter(maxBytes: number): ContentFilter {
+  return {
+    name: "max-data-url",
+    onRequest(payload, context) {
+      let stripped = 0
+      const messages = payload.messages.map((message) => {
+        if (typeof message.content === "string" || !message.content) {
+          return message
+        }
+
+        const content = message.content.map((part) => {
+          // Base64 encodes 3 bytes in 4 characters
+          if (
+            part.type !== "image_url"
+            || !part.image_url.url.startsWith("data:")
+            || (part.image_url.url.length * 3) / 4 <= maxBytes
+          ) {
+            return part
+          }
+          stripped++
+          return {
+            type: "text" as const,
+            text: `[image removed: larger than ${maxBytes} bytes]`,
+          }
+        })
+        return { ...message, content }
+      })
+
+      if (stripped === 0) return payload
+      context.annotations.push(`stripped-images=${stripped}`)
+      return { ...payload, messages }
+    },
+  }
+}
+
+export function annotateFilter(note: string): ContentFilter {
+  return {
+    name: "annotate",
+    onRequest(payload, context) {
+      context.annotations.push(note)
+      return payload
+    },
+  }
+}
+
+export function createFilter(config: FilterConfig): ContentFilter {
+  switch (config.type) {
+    case "forbidden-patterns": {
+      return forbiddenPatternsFilter(config.patterns, config.message)
+    }
+    case "max-data-url": {
+      return maxDataUrlFilter(config.maxBytes)
+    }
+    case "annotate": {
+      return annotateFilter(config.note)
+    }
+    default: {
+      throw new Error(
+        `Unknown content filter type: ${(config as { type: string }).type}`,
+      )
+    }
+  }
+}
+
+/**
+ * Reads a policy file of the form `{ "filters": [{ "type": ... }] }`.
+ * @throws When the file is unreadable or names an unknown filter.
+ */
+export async function loadContentPolicy(
+  filePath: string,
+): Promise<Array<ContentFilter>> {
+  const policy = JSON.parse(await fs.readFile(filePath, "utf8")) as {
+    filters?: Array<FilterConfig>
+  }
+  return (policy.filters ?? []).map((config) => createFilter(config))
+}
+
+export function applyRequestFilters(
+  filters: Array<ContentFilter>,
+  payload: ChatCompletionsPayload,
+  context: PolicyContext,
+): ChatCompletionsPayload {
+  let filtered = payload
+  for (const filter of filters) {
+    if (filter.onRequest) filtered = filter.onRequest(filtered, context)
+  }
+  return filtered
+}
+
+export function applyResponseFilters(
+  filters: Array<ContentFilter>,
+  response: ChatCompletionResponse,
+  context: PolicyContext,
+): ChatCompletionResponse {
+  let filtered = response
+  for (const filter of filters) {
+    if (filter.onResponse) filtered = filter.onResponse(filtered, context)
+  }
+  return filtered
+}
+
+export function setPolicyHeader(c: Context, context: PolicyContext): void {
+  if (context.annotations.length > 0) {
+    c.header("x-content-policy", context.annotations.join(", "))
+  }
+}
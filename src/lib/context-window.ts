@@ -0,0 +1,83 @@
+// Pre-flight check of a request against the model's context window, so one
+// that cannot fit is rejected with the numbers instead of by the upstream
+// after a paid round trip. Prompt tokens are the gateway's own estimate, and
+// unlike the count --token-rate-limit charges they include tool results.
+
+import consola from "consola"
+
+import { HTTPError } from "./error"
+import { state } from "./state"
+
+const reject = (
+  message: string,
+  param: "messages" | "max_tokens",
+  details: Record<string, number | undefined>,
+) => {
+  consola.warn(`Rejected request before forwarding: ${message}`)
+  return new HTTPError(
+    message,
+    Response.json(
+      {
+        error: {
+          message,
+          type: "invalid_request_error",
+          param,
+          code: "context_length_exceeded",
+          ...details,
+        },
+      },
+      { status: 400 },
+    ),
+  )
+}
+
+/**
+ * The largest `max_tokens` that fits next to the prompt, or undefined when
+ * the model's limits are unknown or the check is off.
+ * @throws {HTTPError} 400 when the prompt alone is over the model's limit,
+ * or together with `maxTokens` over its context window.
+ */
+export function checkContextWindow(
+  model: string,
+  promptTokens: number,
+  maxTokens: number | undefined,
+): number | undefined {
+  if (!state.contextCheck) return undefined
+
+  const limits = state.models?.data.find((candidate) => candidate.id === model)
+    ?.capabilities.limits
+  const contextWindow = limits?.max_context_window_tokens
+  if (!limits || contextWindow === undefined) return undefined
+
+  const promptLimit = limits.max_prompt_tokens ?? contextWindow
+  const usable = Math.max(
+    0,
+    Math.min(
+      contextWindow - promptTokens,
+      limits.max_output_tokens ?? Number.POSITIVE_INFINITY,
+    ),
+  )
+  const details = {
+    prompt_tokens: promptTokens,
+    max_tokens: maxTokens,
+    context_window: contextWindow,
+    max_prompt_tokens: promptLimit,
+    max_usable_max_tokens: usable,
+  }
+
+  if (promptTokens > promptLimit || usable === 0) {
+    throw reject(
+      `The prompt is about ${promptTokens} tokens, but ${model} accepts at most ${Math.min(promptLimit, contextWindow - 1)}. Shorten the conversation.`,
+      "messages",
+      details,
+    )
+  }
+  if (maxTokens !== undefined && promptTokens + maxTokens > contextWindow) {
+    throw reject(
+      `The prompt is about ${promptTokens} tokens and max_tokens is ${maxTokens}, over the ${contextWindow} token context window of ${model}. Set max_tokens to ${usable} or less, or shorten the conversation.`,
+      "max_tokens",
+      details,
+    )
+  }
+  return usable
+}
@@ -0,0 +1,81 @@
+// Graceful shutdown. Once draining, the listeners stay open but answer new
+// requests with 503 and Retry-After, so load balancers move traffic over and
+// SDKs retry instead of seeing connection refused, while the requests
+// already in flight, streams included, run to completion.
+
+import type { MiddlewareHandler } from "hono"
+
+import { releaseWhenDone } from "./scheduler"
+
+let draining = false
+let inFlight = 0
+let idleWaiters: Array<() => void> = []
+
+export const isDraining = (): boolean => draining
+
+export const inFlightRequests = (): number => inFlight
+
+export const rejectWhileDraining: MiddlewareHandler = async (c, next) => {
+  if (draining) {
+    c.header("retry-after", "1")
+    c.header("connection", "close")
+    return c.json(
+      {
+        error: {
+          message:
+            "The gateway is shutting down and not accepting new requests, retry shortly",
+          type: "error",
+          code: "server_draining",
+        },
+      },
+      503,
+    )
+  }
+
+  inFlight++
+  let finished = false
+  const finish = () => {
+    if (finished) return
+    finished = true
+    inFlight--
+    if (inFlight === 0) {
+      for (const resolve of idleWaiters) resolve()
+      idleWaiters = []
+    }
+  }
+
+  try {
+    await next()
+  } catch (error) {
+    finish()
+    throw error
+  }
+  c.res = releaseWhenDone(c.res, finish)
+}
+
+/**
+ * Starts refusing new requests and resolves once those in flight have
+ * finished, with false if some were still running after `timeoutMs`.
+ */
+export async function drain(timeoutMs: number): Promise<boolean> {
+  draining = true
+  if (inFlight === 0) return true
+
+  let timer: ReturnType<typeof setTimeout> | undefined
+  const idle = new Promise<boolean>((resolve) =>
+    idleWaiters.push(() => resolve(true)),
+  )
+  const timeout = new Promise<boolean>((resolve) => {
+    timer = setTimeout(() => resolve(false), timeoutMs)
+  })
+  try {
+    return await Promise.race([idle, timeout])
+  } finally {
+    clearTimeout(timer)
+  }
+}
+
+/** Accepts requests again, for tests. */
+export function stopDraining(): void {
+  draining = false
+}
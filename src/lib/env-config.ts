@@ -0,0 +1,397 @@
+import consola, { type LogObject } from "consola"
+import fs from "node:fs"
+
+const PREFIX = "COPILOT_GATEWAY_"
+
+const ACCOUNT_TYPES = ["individual", "business", "enterprise"] as const
+const LOG_FORMATS = ["text", "json"] as const
+export const STARTUP_CHECKS = ["strict", "warn"] as const
+const REDIS_PROTOCOLS = ["redis:", "rediss:"] as const
+const ACCOUNTING_STORE_PROTOCOLS = ["sqlite:", "postgres:", "postgresql:"] as const
+
+export type LogFormat = (typeof LOG_FORMATS)[number]
+export type StartupCheck = (typeof STARTUP_CHECKS)[number]
+
+export interface EnvConfig {
+  port?: number
+  portRetry?: number
+  grpcPort?: number
+  host?: string
+  pathPrefix?: string
+  reusePort?: boolean
+  trustedProxies?: string
+  upstreams?: string
+  accountType?: string
+  defaultModel?: string
+  githubToken?: string
+  // Also folded into githubToken, kept so the file can be watched
+  tokenFile?: string
+  rateLimit?: number
+  rateLimitWait?: boolean
+  rateLimitRedisUrl?: string
+  accountingStore?: string
+  tokenRateLimit?: number
+  retry429MaxWait?: number
+  retryQueueSize?: number
+  idempotencyTtl?: number
+  audit?: boolean
+  auditRetentionDays?: number
+  auditCompress?: boolean
+  sessions?: boolean
+  recordUsage?: boolean
+  team?: boolean
+  adminToken?: string
+  contentPolicy?: string
+  modelPolicy?: string
+  paramPolicy?: string
+  headerPolicy?: string
+  signingKeys?: string
+  sloAlerts?: string
+  shadow?: string
+  mediaProviders?: string
+  experiments?: string
+  quotaGuard?: string
+  pollUsage?: number
+  tlsCert?: string
+  tlsKey?: string
+  tlsClientCa?: string
+  tlsClientIdentities?: string
+  listeners?: string
+  promptCacheKey?: boolean
+  repairToolCalls?: boolean
+  contextCheck?: boolean
+  summarizeModel?: string
+  structuredOutputRetry?: boolean
+  modelAliases?: Record<string, string>
+  modelsTtl?: number
+  maxConcurrency?: number
+  priorityKeys?: string
+  sampleSlowMs?: number
+  sampleSize?: number
+  streamBuffer?: number
+  drainTimeout?: number
+  upstreamHttp?: string
+  plugins?: string
+  // Path of a failure injection config, deliberately without a CLI flag
+  chaos?: string
+  verbose?: boolean
+  logFormat?: LogFormat
+  startupCheck?: StartupCheck
+}
+
+export class EnvConfigError extends Error {
+  problems: Array<string>
+
+  constructor(problems: Array<string>) {
+    super(
+      `Invalid environment configuration:\n${problems.map((problem) => `  - ${problem}`).join("\n")}`,
+    )
+    this.problems = problems
+  }
+}
+
+type Env = Record<string, string | undefined>
+
+/**
+ * Parses a comma-separated `key=value` list such as
+ * `fast=gpt-4o-mini,smart=claude-sonnet-4`. Returns undefined when an entry
+ * is malformed.
+ */
+export function parseMapping(raw: string): Record<string, string> | undefined {
+  const mapping: Record<string, string> = {}
+  for (const entry of raw.split(",")) {
+    if (!entry.trim()) continue
+    const [key, value, ...rest] = entry.split("=").map((part) => part.trim())
+    if (!key || !value || rest.length > 0) return undefined
+    mapping[key] = value
+  }
+  return mapping
+}
+
+/**
+ * Resolves references in a config value, so secrets can stay out of the
+ * environment listing: `${NAME}` is replaced by that environment variable
+ * (`$${NAME}` stays a literal `${NAME}`), then a value of the form `file:<path>` is
+ * replaced by the trimmed contents of the file, e.g. a Docker or Kubernetes
+ * secret mounted at `/run/secrets/<name>`.
+ * @throws {Error} When a variable is unset or the file cannot be read.
+ */
+export function resolveConfigValue(raw: string, env: Env = process.env): string {
+  const expanded = raw.replaceAll(
+    /\$(\$?)\{(\w+)\}/g,
+    (match: string, escape: string, name: string) => {
+      if (escape) return match.slice(1)
+      const value = env[name]
+      if (value === undefined) {
+        throw new Error(`references unset variable ${name}`)
+      }
+      return value
+    },
+  )
+  if (!expanded.startsWith("file:")) return expanded
+
+  const filePath = expanded.slice("file:".length)
+  try {
+    return fs.readFileSync(filePath, "utf8").trim()
+  } catch (error) {
+    throw new Error(`cannot read "${filePath}" (${(error as Error).message})`)
+  }
+}
+
+// An integer between min and max, or undefined for anything else
+function parseInteger(
+  raw: string,
+  min: number,
+  max: number,
+): number | undefined {
+  const value = raw.trim() === "" ? Number.NaN : Number(raw)
+  return Number.isInteger(value) && value >= min && value <= max ?
+      value
+    : undefined
+}
+
+/**
+ * Parses the value of an integer command line option, such as `--port`,
+ * with the same checks as the environment variables.
+ * @throws {Error} When `raw` is not an integer between `min` and `max`.
+ */
+export function parseIntegerOption(
+  option: string,
+  raw: string,
+  min: number,
+  max: number,
+): number {
+  const value = parseInteger(raw, min, max)
+  if (value === undefined) {
+    throw new Error(
+      `${option}: expected an integer between ${min} and ${max}, got "${raw}"`,
+    )
+  }
+  return value
+}
+
+class EnvReader {
+  problems: Array<string> = []
+  private env: Env
+
+  constructor(env: Env) {
+    this.env = env
+  }
+
+  string(name: string): string | undefined {
+    const raw = this.env[PREFIX + name]?.trim()
+    if (raw === undefined || raw === "") return undefined
+
+    try {
+      const value = resolveConfigValue(raw, this.env)
+      return value === "" ? undefined : value
+    } catch (error) {
+      this.problems.push(`${PREFIX}${name}: ${(error as Error).message}`)
+      return undefined
+    }
+  }
+
+  integer(name: string, min: number, max: number): number | undefined {
+    const raw = this.string(name)
+    if (raw === undefined) return undefined
+
+    const value = parseInteger(raw, min, max)
+    if (value === undefined) {
+      this.problems.push(
+        `${PREFIX}${name}: expected an integer between ${min} and ${max}, got "${raw}"`,
+      )
+      return undefined
+    }
+    return value
+  }
+
+  boolean(name: string): boolean | undefined {
+    const raw = this.string(name)?.toLowerCase()
+    if (raw === undefined) return undefined
+
+    if (["1", "true", "yes", "on"].includes(raw)) return true
+    if (["0", "false", "no", "off"].includes(raw)) return false
+
+    this.problems.push(`${PREFIX}${name}: expected true or false, got "${raw}"`)
+    return undefined
+  }
+
+  oneOf<T extends string>(
+    name: string,
+    allowed: ReadonlyArray<T>,
+  ): T | undefined {
+    const raw = this.string(name)
+    if (raw === undefined) return undefined
+
+    if (!allowed.includes(raw as T)) {
+      this.problems.push(
+        `${PREFIX}${name}: expected one of ${allowed.join(", ")}, got "${raw}"`,
+      )
+      return undefined
+    }
+    return raw as T
+  }
+
+  url(name: string, protocols: ReadonlyArray<string>): string | undefined {
+    const raw = this.string(name)
+    if (raw === undefined) return undefined
+
+    let protocol: string | undefined
+    try {
+      protocol = new URL(raw).protocol
+    } catch {
+      protocol = undefined
+    }
+    if (!protocol || !protocols.includes(protocol)) {
+      this.problems.push(
+        `${PREFIX}${name}: expected a ${protocols.join(" or ")} URL, got "${raw}"`,
+      )
+      return undefined
+    }
+    return raw
+  }
+
+  mapping(name: string): Record<string, string> | undefined {
+    const raw = this.string(name)
+    if (raw === undefined) return undefined
+
+    const mapping = parseMapping(raw)
+    if (!mapping) {
+      this.problems.push(
+        `${PREFIX}${name}: expected comma-separated name=value pairs, got "${raw}"`,
+      )
+    }
+    return mapping
+  }
+
+  file(name: string): string | undefined {
+    const filePath = this.string(name)
+    if (filePath === undefined) return undefined
+
+    try {
+      return fs.readFileSync(filePath, "utf8").trim()
+    } catch (error) {
+      this.problems.push(
+        `${PREFIX}${name}: cannot read "${filePath}" (${(error as Error).message})`,
+      )
+      return undefined
+    }
+  }
+}
+
+/**
+ * Reads the `COPILOT_GATEWAY_*` environment variables into a typed config.
+ * Every variable is validated up front and all problems are reported at once.
+ * @throws {EnvConfigError} When any variable is set to an invalid value.
+ */
+export function loadEnvConfig(env: Env = process.env): EnvConfig {
+  const reader = new EnvReader(env)
+
+  const tokenFromFile = reader.file("TOKEN_FILE")
+  const githubToken = reader.string("GITHUB_TOKEN")
+  const config: EnvConfig = {
+    port: reader.integer("PORT", 0, 65535),
+    portRetry: reader.integer("PORT_RETRY", 0, 1000),
+    grpcPort: reader.integer("GRPC_PORT", 1, 65535),
+    host: reader.string("HOST"),
+    pathPrefix: reader.string("PATH_PREFIX"),
+    reusePort: reader.boolean("REUSE_PORT"),
+    trustedProxies: reader.string("TRUSTED_PROXIES"),
+    upstreams: reader.string("UPSTREAMS"),
+    accountType: reader.oneOf("ACCOUNT_TYPE", ACCOUNT_TYPES),
+    defaultModel: reader.string("DEFAULT_MODEL"),
+    // GH_TOKEN is kept for compatibility with existing Docker setups
+    githubToken: githubToken ?? tokenFromFile ?? env.GH_TOKEN?.trim(),
+    tokenFile:
+      tokenFromFile === undefined ? undefined : reader.string("TOKEN_FILE"),
+    rateLimit: reader.integer("RATE_LIMIT", 1, Number.MAX_SAFE_INTEGER),
+    rateLimitWait: reader.boolean("RATE_LIMIT_WAIT"),
+    rateLimitRedisUrl: reader.url("RATE_LIMIT_REDIS_URL", REDIS_PROTOCOLS),
+    accountingStore: reader.url(
+      "ACCOUNTING_STORE",
+      ACCOUNTING_STORE_PROTOCOLS,
+    ),
+    tokenRateLimit: reader.integer(
+      "TOKEN_RATE_LIMIT",
+      1,
+      Number.MAX_SAFE_INTEGER,
+    ),
+    retry429MaxWait: reader.integer("RETRY_429_MAX_WAIT", 0, 3600),
+    retryQueueSize: reader.integer("RETRY_QUEUE_SIZE", 1, 10_000),
+    idempotencyTtl: reader.integer("IDEMPOTENCY_TTL", 0, 86_400),
+    audit: reader.boolean("AUDIT"),
+    auditRetentionDays: reader.integer("AUDIT_RETENTION_DAYS", 1, 3650),
+    auditCompress: reader.boolean("AUDIT_COMPRESS"),
+    sessions: reader.boolean("SESSIONS"),
+    recordUsage: reader.boolean("RECORD_USAGE"),
+    team: reader.boolean("TEAM"),
+    adminToken: reader.string("ADMIN_TOKEN"),
+    contentPolicy: reader.string("CONTENT_POLICY"),
+    modelPolicy: reader.string("MODEL_POLICY"),
+    paramPolicy: reader.string("PARAM_POLICY"),
+    headerPolicy: reader.string("HEADER_POLICY"),
+    signingKeys: reader.string("SIGNING_KEYS"),
+    sloAlerts: reader.string("SLO_ALERTS"),
+    shadow: reader.string("SHADOW"),
+    mediaProviders: reader.string("MEDIA_PROVIDERS"),
+    experiments: reader.string("EXPERIMENTS"),
+    quotaGuard: reader.string("QUOTA_GUARD"),
+    pollUsage: reader.integer("POLL_USAGE", 1, 1440),
+    tlsCert: reader.string("TLS_CERT"),
+    tlsKey: reader.string("TLS_KEY"),
+    tlsClientCa: reader.string("TLS_CLIENT_CA"),
+    tlsClientIdentities: reader.string("TLS_CLIENT_IDENTITIES"),
+    listeners: reader.string("LISTENERS"),
+    promptCacheKey: reader.boolean("PROMPT_CACHE_KEY"),
+    repairToolCalls: reader.boolean("REPAIR_TOOL_CALLS"),
+    contextCheck: reader.boolean("CONTEXT_CHECK"),
+    summarizeModel: reader.string("SUMMARIZE_MODEL"),
+    structuredOutputRetry: reader.boolean("STRUCTURED_OUTPUT_RETRY"),
+    modelAliases: reader.mapping("MODEL_ALIASES"),
+    modelsTtl: reader.integer("MODELS_TTL", 0, 86_400),
+    maxConcurrency: reader.integer("MAX_CONCURRENCY", 1, 10_000),
+    priorityKeys: reader.string("PRIORITY_KEYS"),
+    sampleSlowMs: reader.integer("SAMPLE_SLOW_MS", 1, Number.MAX_SAFE_INTEGER),
+    sampleSize: reader.integer("SAMPLE_SIZE", 0, 10_000),
+    streamBuffer: reader.integer("STREAM_BUFFER", 1, 100_000),
+    drainTimeout: reader.integer("DRAIN_TIMEOUT", 0, 3600),
+    upstreamHttp: reader.string("UPSTREAM_HTTP"),
+    plugins: reader.string("PLUGINS"),
+    chaos: reader.string("CHAOS"),
+    verbose: reader.boolean("VERBOSE"),
+    logFormat: reader.oneOf("LOG_FORMAT", LOG_FORMATS),
+    startupCheck: reader.oneOf("STARTUP_CHECK", STARTUP_CHECKS),
+  }
+
+  if (githubToken && tokenFromFile) {
+    reader.problems.push(
+      `${PREFIX}GITHUB_TOKEN and ${PREFIX}TOKEN_FILE are mutually exclusive`,
+    )
+  }
+
+  if (reader.problems.length > 0) throw new EnvConfigError(reader.problems)
+
+  return config
+}
+
+export function applyLogFormat(format: LogFormat | undefined): void {
+  if (format !== "json") return
+
+  consola.setReporters([
+    {
+      log(logObj: LogObject) {
+        process.stdout.write(
+          `${JSON.stringify({
+            time: logObj.date.toISOString(),
+            level: logObj.type,
+            message: logObj.args
+              .map((arg) =>
+                typeof arg === "string" ? arg : JSON.stringify(arg),
+              )
+              .join(" "),
+          })}\n`,
+        )
+      },
+    },
+  ])
+}
@@ -3,6 +3,9 @@ import type { ContentfulStatusCode } from "hono/utils/http-status"
 
 import consola from "consola"
 
+import { retryDelayMs } from "./replay-queue"
+import { annotateSample } from "./request-samples"
+
 export class HTTPError extends Error {
   response: Response
 
@@ -12,12 +15,69 @@ export class HTTPError extends Error {
   }
 }
 
+function parseOpenAIError(
+  text: string,
+): { error: { message: string } } | undefined {
+  try {
+    const body = JSON.parse(text) as { error?: { message?: unknown } }
+    if (typeof body.error?.message === "string") {
+      return body as { error: { message: string } }
+    }
+  } catch {
+    // Not JSON
+  }
+  return undefined
+}
+
+/**
+ * The OpenAI error body for a 429, keeping the upstream message when there
+ * is one, so SDKs recognise it as a rate limit rather than a server error.
+ */
+export function rateLimitErrorBody(errorText: string) {
+  let message = parseOpenAIError(errorText)?.error.message
+  if (!message) {
+    try {
+      const body = JSON.parse(errorText) as { message?: unknown }
+      message =
+        typeof body.message === "string" ? body.message : "Rate limit exceeded"
+    } catch {
+      // Plain text
+      message = errorText || "Rate limit exceeded"
+    }
+  }
+  return {
+    error: {
+      message,
+      type: "requests",
+      param: null,
+      code: "rate_limit_exceeded",
+    },
+  }
+}
+
 export async function forwardError(c: Context, error: unknown) {
   consola.error("Error occurred:", error)
 
   if (error instanceof HTTPError) {
-    consola.error("HTTP error:", await error.response.json())
+    // The body can only be read once
     const errorText = await error.response.text()
+    consola.error("HTTP error:", errorText)
+    annotateSample(c.req.raw, {
+      upstreamStatus: error.response.status,
+      error: errorText,
+    })
+    const status = error.response.status as ContentfulStatusCode
+    if (status === 429) {
+      // `retry-after-ms` is what the OpenAI SDKs check first
+      const delayMs = retryDelayMs(error.response)
+      c.header("retry-after", String(Math.ceil(delayMs / 1000)))
+      c.header("retry-after-ms", String(Math.ceil(delayMs)))
+      return c.json(rateLimitErrorBody(errorText), 429)
+    }
+    // Already an OpenAI-style error, e.g. from the gateway's own policies
+    const openAIError = parseOpenAIError(errorText)
+    if (openAIError) return c.json(openAIError, status)
+
     return c.json(
       {
         error: {
@@ -25,10 +85,23 @@ export async function forwardError(c: Context, error: unknown) {
           type: "error",
         },
       },
-      error.response.status as ContentfulStatusCode,
+      status,
     )
   }
 
+  annotateSample(c.req.raw, { error: (error as Error).message })
+  // From x-gateway-timeout-ms
+  if ((error as Error).name === "TimeoutError") {
+    return c.json(
+      {
+        error: {
+          message: "Upstream request timed out",
+          type: "timeout_error",
+        },
+      },
+      504,
+    )
+  }
   return c.json(
     {
       error: {
@@ -0,0 +1,77 @@
+import type { Context } from "hono"
+
+import { createHash } from "node:crypto"
+import fs from "node:fs/promises"
+
+import { apiKeyOf } from "./api-key"
+import { state } from "./state"
+
+export const EXPERIMENT_HEADER = "x-experiment"
+
+/**
+ * Sends `percent` of the requests for `model` to `alternate` instead. With
+ * `sticky`, the arm is picked by API key, so each client stays on one arm.
+ */
+export interface Experiment {
+  name: string
+  model: string
+  alternate: string
+  percent: number
+  sticky?: boolean
+}
+
+export interface ExperimentConfig {
+  experiments: Array<Experiment>
+}
+
+export type ExperimentArm = "control" | "alternate"
+
+export interface ExperimentAssignment {
+  experiment: string
+  arm: ExperimentArm
+}
+
+export async function loadExperiments(
+  filePath: string,
+): Promise<ExperimentConfig> {
+  return JSON.parse(await fs.readFile(filePath, "utf8")) as ExperimentConfig
+}
+
+// From 0 to 100; the same for every request of a key
+function keyBucket(experiment: string, key: string): number {
+  const hash = createHash("sha256").update(`${experiment}\n${key}`).digest()
+  return (hash.readUInt32BE(0) / 0x1_00_00_00_00) * 100
+}
+
+export function pickArm(
+  experiment: Experiment,
+  key: string | undefined,
+  random: () => number = Math.random,
+): ExperimentArm {
+  const bucket =
+    experiment.sticky && key !== undefined ?
+      keyBucket(experiment.name, key)
+    : random() * 100
+  return bucket < experiment.percent ? "alternate" : "control"
+}
+
+const assignments = new WeakMap<Request, ExperimentAssignment>()
+
+export const experimentOf = (request: Request) => assignments.get(request)
+
+/**
+ * The model to send the request to. Requests in an experiment are tagged
+ * with an `x-experiment: <name>=<arm>` response header, and their usage
+ * records with the experiment and arm.
+ */
+export function routeExperiment(c: Context, model: string): string {
+  const experiment = state.experiments?.experiments.find(
+    (candidate) => candidate.model === model,
+  )
+  if (!experiment) return model
+
+  const arm = pickArm(experiment, apiKeyOf(c))
+  assignments.set(c.req.raw, { experiment: experiment.name, arm })
+  c.header(EXPERIMENT_HEADER, `${experiment.name}=${arm}`)
+  return arm === "alternate" ? experiment.alternate : model
+}
@@ -0,0 +1,90 @@
+import type { Context } from "hono"
+
+import { HTTPError } from "./error"
+
+export const GATEWAY_PROVIDERS = ["copilot"] as const
+
+/** Per-request overrides sent as `x-gateway-*` headers. */
+export interface GatewayOptions {
+  // x-gateway-model-override: replaces the payload's model, before aliases
+  model?: string
+  // x-gateway-no-cache: sends no prompt cache key upstream
+  noCache: boolean
+  // x-gateway-timeout-ms: aborts the upstream request, stream included
+  timeoutMs?: number
+  // x-gateway-provider: only Copilot for now
+  provider: (typeof GATEWAY_PROVIDERS)[number]
+}
+
+const HEADER_PREFIX = "x-gateway-"
+const KNOWN_HEADERS = new Set([
+  "x-gateway-model-override",
+  "x-gateway-no-cache",
+  "x-gateway-timeout-ms",
+  "x-gateway-provider",
+  // Read by request-tags.ts
+  "x-gateway-tags",
+])
+
+function invalidHeader(message: string): never {
+  throw new HTTPError(
+    message,
+    Response.json(
+      { error: { message, type: "invalid_request_error" } },
+      { status: 400 },
+    ),
+  )
+}
+
+/**
+ * Reads the `x-gateway-*` headers of a request. Unknown names are rejected
+ * so a typo does not silently leave the default behavior in place.
+ * @throws {HTTPError} 400 on an unknown header or invalid value.
+ */
+export function gatewayOptionsOf(c: Context): GatewayOptions {
+  for (const name of c.req.raw.headers.keys()) {
+    if (name.startsWith(HEADER_PREFIX) && !KNOWN_HEADERS.has(name)) {
+      invalidHeader(`Unknown gateway header: ${name}`)
+    }
+  }
+
+  const noCache = c.req.header("x-gateway-no-cache")
+  if (
+    noCache !== undefined
+    && !["true", "false", "1", "0"].includes(noCache)
+  ) {
+    invalidHeader(`x-gateway-no-cache must be true or false, got "${noCache}"`)
+  }
+
+  const timeoutRaw = c.req.header("x-gateway-timeout-ms")
+  const timeoutMs = timeoutRaw === undefined ? undefined : Number(timeoutRaw)
+  if (
+    timeoutMs !== undefined
+    && !(Number.isInteger(timeoutMs) && timeoutMs > 0)
+  ) {
+    invalidHeader(
+      `x-gateway-timeout-ms must be a positive integer, got "${timeoutRaw}"`,
+    )
+  }
+
+  const provider = c.req.header("x-gateway-provider") ?? "copilot"
+  if (!(GATEWAY_PROVIDERS as ReadonlyArray<string>).includes(provider)) {
+    invalidHeader(`Unknown provider: ${provider}, use copilot`)
+  }
+
+  return {
+    model: c.req.header("x-gateway-model-override") || undefined,
+    noCache: noCache === "true" || noCache === "1",
+    timeoutMs,
+    provider: provider as GatewayOptions["provider"],
+  }
+}
+
+/** Aborts the upstream request once `x-gateway-timeout-ms` has passed. */
+export function upstreamSignal(
+  options: GatewayOptions,
+): AbortSignal | undefined {
+  return options.timeoutMs === undefined ?
+      undefined
+    : AbortSignal.timeout(options.timeoutMs)
+}
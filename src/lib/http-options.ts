@@ -0,0 +1,164 @@
+import type { HttpOptions } from "./rust-core"
+
+import { parseMapping } from "./env-config"
+
+// Numbers, booleans and null keep their type, anything else is a string
+function coerce(value: string): unknown {
+  try {
+    return JSON.parse(value) as unknown
+  } catch {
+    return value
+  }
+}
+
+/**
+ * Parses `--upstream-http`, a comma-separated list of HTTP client options
+ * such as `poolIdleTimeoutMs=300000,http2=false`.
+ * @throws {TypeError} When an entry is not `name=value`.
+ */
+export function parseHttpOptions(raw: string): Partial<HttpOptions> {
+  const mapping = parseMapping(raw)
+  if (!mapping) {
+    throw new TypeError(
+      `--upstream-http: expected comma-separated name=value pairs, got "${raw}"`,
+    )
+  }
+  return Object.fromEntries(
+    Object.entries(mapping).map(([name, value]) => [name, coerce(value)]),
+  )
+}
+
+// Options of the undici Agent that forwards requests to Copilot
+export interface FetchAgentOptions {
+  keepAliveTimeout?: number
+  keepAliveMaxTimeout?: number
+  connections?: number
+  headersTimeout?: number
+  allowH2?: boolean
+  connect?: {
+    timeout?: number
+    keepAlive?: boolean
+    keepAliveInitialDelay?: number
+  }
+}
+
+// Applied to both the native client and fetch
+const SHARED = new Set([
+  "poolIdleTimeoutMs",
+  "poolMaxIdlePerHost",
+  "tcpKeepaliveMs",
+  "connectTimeoutMs",
+  "requestTimeoutMs",
+  "http2",
+])
+
+// Only understood by the native client, fetch has no equivalent
+const NATIVE_ONLY = new Set([
+  "http2PriorKnowledge",
+  "http2AdaptiveWindow",
+  "http2KeepAliveIntervalMs",
+  "compression",
+  "proxy",
+])
+
+function positiveNumber(name: string, value: unknown): number {
+  if (typeof value !== "number" || !Number.isFinite(value) || value <= 0) {
+    throw new TypeError(
+      `--upstream-http: ${name} must be a positive number, got ${JSON.stringify(value)}`,
+    )
+  }
+  return value
+}
+
+/**
+ * Maps `--upstream-http` options onto the connection pool `fetch` uses for
+ * Copilot, and lists the ones that only apply to the native client.
+ * @throws {TypeError} On unknown names and mistyped values.
+ */
+export function fetchAgentOptions(options: Partial<HttpOptions>): {
+  agent: FetchAgentOptions
+  nativeOnly: Array<string>
+} {
+  const agent: FetchAgentOptions = {}
+  const nativeOnly: Array<string> = []
+
+  for (const name of Object.keys(options)) {
+    if (!SHARED.has(name) && !NATIVE_ONLY.has(name)) {
+      throw new TypeError(`--upstream-http: unknown option "${name}"`)
+    }
+    if (NATIVE_ONLY.has(name)) nativeOnly.push(name)
+  }
+
+  if (options.poolIdleTimeoutMs !== undefined) {
+    const ms = positiveNumber("poolIdleTimeoutMs", options.poolIdleTimeoutMs)
+    // The server's Keep-Alive hint is capped by the same value
+    agent.keepAliveTimeout = ms
+    agent.keepAliveMaxTimeout = ms
+  }
+  if (options.poolMaxIdlePerHost !== undefined) {
+    agent.connections = positiveNumber(
+      "poolMaxIdlePerHost",
+      options.poolMaxIdlePerHost,
+    )
+  }
+  if (options.tcpKeepaliveMs !== undefined) {
+    agent.connect = {
+      ...agent.connect,
+      keepAlive: true,
+      keepAliveInitialDelay: positiveNumber(
+        "tcpKeepaliveMs",
+        options.tcpKeepaliveMs,
+      ),
+    }
+  }
+  if (options.connectTimeoutMs !== undefined) {
+    agent.connect = {
+      ...agent.connect,
+      timeout: positiveNumber("connectTimeoutMs", options.connectTimeoutMs),
+    }
+  }
+  // Streams can run for minutes, so this bounds the wait for headers only
+  if (
+    options.requestTimeoutMs !== undefined
+    && options.requestTimeoutMs !== null
+  ) {
+    agent.headersTimeout = positiveNumber(
+      "requestTimeoutMs",
+      options.requestTimeoutMs,
+    )
+  }
+  if (options.http2 !== undefined) {
+    if (typeof options.http2 !== "boolean") {
+      throw new TypeError(
+        `--upstream-http: http2 must be true or false, got ${JSON.stringify(options.http2)}`,
+      )
+    }
+    agent.allowH2 = options.http2
+  }
+
+  return { agent, nativeOnly }
+}
+
+// Shared by every request to Copilot once `--upstream-http` is set
+let dispatcher: unknown
+
+/**
+ * Creates the connection pool requests to Copilot go through.
+ * @throws {TypeError} Under Bun, whose `fetch` pools connections itself and
+ * ignores dispatchers.
+ */
+export async function configureUpstreamAgent(
+  options: FetchAgentOptions,
+): Promise<void> {
+  if (typeof Bun !== "undefined") {
+    throw new TypeError(
+      "--upstream-http needs Node, Bun's fetch keeps its own connection pool and can't be tuned",
+    )
+  }
+  const { Agent } = await import("undici")
+  dispatcher = new Agent(options)
+}
+
+/** `init` routed through the `--upstream-http` pool, when there is one. */
+export const withUpstreamAgent = (init: RequestInit): RequestInit =>
+  dispatcher === undefined ? init : ({ ...init, dispatcher } as RequestInit)
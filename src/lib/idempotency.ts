@@ -0,0 +1,194 @@
+// Caches responses by `Idempotency-Key`, so a client that retries after a
+// timeout gets the original result instead of spending a second premium
+// request when the first one succeeded upstream after all.
+
+import type { MiddlewareHandler } from "hono"
+
+import { createHash } from "node:crypto"
+
+import { apiKeyOf } from "./api-key"
+import { clientIp } from "./client-ip"
+import { clientKey } from "./token-budget"
+
+const config = {
+  // Disabled while 0
+  ttlMs: 10 * 60_000,
+  capacity: 1000,
+  // Total size of the stored bodies, a larger response is not kept
+  maxBytes: 64 * 1024 * 1024,
+}
+
+export function configureIdempotency(options: Partial<typeof config>): void {
+  config.ttlMs = options.ttlMs ?? config.ttlMs
+  config.capacity = options.capacity ?? config.capacity
+  config.maxBytes = options.maxBytes ?? config.maxBytes
+}
+
+const MAX_KEY_LENGTH = 255
+
+interface StoredResponse {
+  status: number
+  headers: Array<[string, string]>
+  body: Uint8Array
+}
+
+interface Entry {
+  bodyHash: string
+  expiresAt: number
+  // Undefined when the first attempt failed, so a retry may try again
+  response: Promise<StoredResponse | undefined>
+  // Of the stored body, 0 until the response is complete
+  bytes: number
+}
+
+const entries = new Map<string, Entry>()
+let storedBytes = 0
+
+function remove(key: string): void {
+  const entry = entries.get(key)
+  if (!entry) return
+  storedBytes -= entry.bytes
+  entries.delete(key)
+}
+
+// Maps iterate in insertion order, so the oldest entries go first
+function prune(now: number): void {
+  for (const [key, entry] of entries) {
+    if (
+      entry.expiresAt > now
+      && entries.size < config.capacity
+      && storedBytes <= config.maxBytes
+    ) {
+      break
+    }
+    remove(key)
+  }
+}
+
+export function clearIdempotencyCache(): void {
+  entries.clear()
+  storedBytes = 0
+}
+
+// The whole body, or undefined once it grows past `limit`
+async function readUpTo(
+  body: ReadableStream<Uint8Array>,
+  limit: number,
+): Promise<Uint8Array | undefined> {
+  const chunks: Array<Uint8Array> = []
+  let size = 0
+  const reader = body.getReader()
+  for (;;) {
+    const { done, value } = await reader.read()
+    if (done) break
+    size += value.byteLength
+    if (size > limit) {
+      await reader.cancel()
+      return undefined
+    }
+    chunks.push(value)
+  }
+  const buffer = new Uint8Array(size)
+  let offset = 0
+  for (const chunk of chunks) {
+    buffer.set(chunk, offset)
+    offset += chunk.byteLength
+  }
+  return buffer
+}
+
+const idempotencyError = (message: string, code: string) => ({
+  error: { message, type: "invalid_request_error", param: null, code },
+})
+
+/**
+ * Answers a repeated `Idempotency-Key` with the stored response, marked with
+ * `idempotent-replayed: true`. A retry that arrives while the first attempt
+ * is still running waits for it. Keys are scoped to the client and route,
+ * and only successful responses are kept. Clients with neither an API key
+ * nor a known address can't be told apart, so their keys are ignored.
+ */
+export const idempotency: MiddlewareHandler = async (c, next) => {
+  const key = c.req.header("idempotency-key")
+  if (!key || config.ttlMs <= 0) return next()
+  if (!apiKeyOf(c) && !clientIp(c)) return next()
+  if (key.length > MAX_KEY_LENGTH) {
+    return c.json(
+      idempotencyError(
+        `Idempotency-Key must be at most ${MAX_KEY_LENGTH} characters`,
+        "invalid_idempotency_key",
+      ),
+      400,
+    )
+  }
+
+  const cacheKey = `${clientKey(c)}:${c.req.path}:${key}`
+  const bodyHash = createHash("sha256")
+    .update(await c.req.text())
+    .digest("hex")
+  const now = Date.now()
+  prune(now)
+
+  const existing = entries.get(cacheKey)
+  if (existing) {
+    if (existing.bodyHash !== bodyHash) {
+      return c.json(
+        idempotencyError(
+          "Idempotency-Key was already used with a different request body",
+          "idempotency_key_reused",
+        ),
+        422,
+      )
+    }
+    const stored = await existing.response
+    if (stored) {
+      const response = new Response(stored.body.slice(), {
+        status: stored.status,
+        headers: stored.headers,
+      })
+      response.headers.set("idempotent-replayed", "true")
+      return response
+    }
+  }
+
+  let settle: (stored: StoredResponse | undefined) => void = () => {}
+  const entry: Entry = {
+    bodyHash,
+    expiresAt: now + config.ttlMs,
+    response: new Promise((resolve) => {
+      settle = resolve
+    }),
+    bytes: 0,
+  }
+  entries.set(cacheKey, entry)
+  const fail = () => {
+    settle(undefined)
+    if (entries.get(cacheKey) === entry) remove(cacheKey)
+  }
+
+  await next()
+
+  const { status, headers, body } = c.res
+  if (status < 200 || status >= 300 || !body) {
+    fail()
+    return
+  }
+  // Streams are stored as they are sent, and kept once complete unless they
+  // outgrow the whole cache
+  const [forClient, forCache] = body.tee()
+  c.res = new Response(forClient, c.res)
+  void readUpTo(forCache, config.maxBytes).then((stored) => {
+    if (!stored) {
+      fail()
+      return
+    }
+    settle({ status, headers: [...headers], body: stored })
+    if (entries.get(cacheKey) !== entry) return
+    entry.bytes = stored.byteLength
+    storedBytes += stored.byteLength
+    for (const oldest of entries.keys()) {
+      if (storedBytes <= config.maxBytes) break
+      remove(oldest)
+    }
+  }, fail)
+}
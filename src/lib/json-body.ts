@@ -0,0 +1,202 @@
+import type { MiddlewareHandler } from "hono"
+
+import { redactSecrets } from "./audit"
+
+const EXCERPT_BYTES = 200
+
+export interface JsonErrorLocation {
+  // In bytes of the UTF-8 body
+  offset: number
+  line: number
+  column: number
+  reason: string
+}
+
+class JsonSyntaxError extends Error {
+  constructor(
+    message: string,
+    readonly index: number,
+  ) {
+    super(message)
+  }
+}
+
+// A syntax-only scanner, because JavaScriptCore's JSON.parse messages do
+// not say where parsing failed
+function scan(text: string): void {
+  let pos = 0
+  function fail(reason: string): never {
+    throw new JsonSyntaxError(reason, pos)
+  }
+  const skipWhitespace = () => {
+    while (pos < text.length && " \t\n\r".includes(text[pos])) pos++
+  }
+  function unexpected(): never {
+    fail(
+      pos < text.length ?
+        `Unexpected character ${JSON.stringify(text[pos])}`
+      : "Unexpected end of input",
+    )
+  }
+
+  const string = () => {
+    pos++
+    while (pos < text.length) {
+      const char = text[pos]
+      if (char === '"') {
+        pos++
+        return
+      }
+      if (char === "\\") {
+        const escaped = text[pos + 1]
+        if (escaped && '"\\/bfnrt'.includes(escaped)) pos += 2
+        else if (
+          escaped === "u"
+          && /^[\da-f]{4}$/i.test(text.slice(pos + 2, pos + 6))
+        ) {
+          pos += 6
+        } else fail("Invalid escape sequence in string")
+        continue
+      }
+      if (char < " ") fail("Unescaped control character in string")
+      pos++
+    }
+    fail("Unterminated string")
+  }
+
+  const number = () => {
+    const pattern = /-?(?:0|[1-9]\d*)(?:\.\d+)?(?:[eE][+-]?\d+)?/y
+    pattern.lastIndex = pos
+    const match = pattern.exec(text)
+    if (!match) fail("Invalid number")
+    pos += match[0].length
+  }
+
+  const members = (close: "}" | "]", member: () => void) => {
+    pos++
+    skipWhitespace()
+    if (text[pos] === close) {
+      pos++
+      return
+    }
+    for (;;) {
+      member()
+      skipWhitespace()
+      if (text[pos] === close) {
+        pos++
+        return
+      }
+      if (text[pos] !== ",") {
+        fail(`Expected ',' or '${close}'`)
+      }
+      pos++
+      skipWhitespace()
+      if (text[pos] === close) fail("Trailing comma")
+    }
+  }
+
+  const value = (): void => {
+    skipWhitespace()
+    const char = text[pos]
+    if (char === "{") {
+      members("}", () => {
+        skipWhitespace()
+        if (text[pos] !== '"') fail("Expected a property name in double quotes")
+        string()
+        skipWhitespace()
+        if (text[pos] !== ":") fail("Expected ':' after a property name")
+        pos++
+        value()
+      })
+    } else if (char === "[") {
+      members("]", value)
+    } else if (char === '"') {
+      string()
+    } else if (char === "-" || (char >= "0" && char <= "9")) {
+      number()
+    } else {
+      const literal = ["true", "false", "null"].find((word) =>
+        text.startsWith(word, pos),
+      )
+      if (!literal) unexpected()
+      pos += literal.length
+    }
+  }
+
+  value()
+  skipWhitespace()
+  if (pos < text.length) fail("Unexpected data after the JSON value")
+}
+
+/** Where and why `text` is not valid JSON, or undefined when it is. */
+export function locateJsonError(text: string): JsonErrorLocation | undefined {
+  try {
+    scan(text)
+    return undefined
+  } catch (error) {
+    if (!(error instanceof JsonSyntaxError)) throw error
+    const before = text.slice(0, error.index)
+    const lineStart = before.lastIndexOf("\n") + 1
+    return {
+      offset: Buffer.byteLength(before),
+      line: before.split("\n").length,
+      column: error.index - lineStart + 1,
+      reason: error.message,
+    }
+  }
+}
+
+/**
+ * The start of the body for the error message, with secrets redacted and
+ * control characters replaced so it is safe to echo back and log.
+ */
+export function bodyExcerpt(text: string): string {
+  const redacted = Buffer.from(redactSecrets(text))
+  const excerpt = redacted.subarray(0, EXCERPT_BYTES).toString("utf8")
+  // eslint-disable-next-line no-control-regex
+  return excerpt.replaceAll(/[\u0000-\u0008\u000B\u000C\u000E-\u001F]/g, "�")
+}
+
+export function invalidJsonBody(text: string, location: JsonErrorLocation) {
+  return {
+    error: {
+      message: `Invalid JSON in request body at line ${location.line}, column ${location.column} (byte ${location.offset}): ${location.reason}`,
+      type: "invalid_request_error",
+      param: null,
+      code: "invalid_json",
+      offset: location.offset,
+      line: location.line,
+      column: location.column,
+      body_excerpt: bodyExcerpt(text),
+    },
+  }
+}
+
+/**
+ * Parses JSON request bodies once, up front, so a malformed body is
+ * answered with a 400 saying where parsing failed instead of a bare 500
+ * from the route. Routes read the parsed body with `c.req.json()` as usual.
+ */
+export const parseJsonBody: MiddlewareHandler = async (c, next) => {
+  const contentType = c.req.header("content-type") ?? "application/json"
+  if (c.req.method !== "POST" || !contentType.includes("json")) return next()
+
+  const text = await c.req.text()
+  // Left to routes that take no body
+  if (!text) return next()
+
+  let parsed: unknown
+  try {
+    parsed = JSON.parse(text)
+  } catch (error) {
+    const location = locateJsonError(text) ?? {
+      offset: 0,
+      line: 1,
+      column: 1,
+      reason: (error as Error).message,
+    }
+    return c.json(invalidJsonBody(text, location), 400)
+  }
+  c.req.bodyCache.json = Promise.resolve(parsed)
+  return next()
+}
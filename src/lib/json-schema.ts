@@ -0,0 +1,235 @@
+// A small JSON Schema validator covering the keywords used for structured
+// outputs: types, enums, objects, arrays, string and number bounds,
+// combinators and local `$ref`s. Unknown keywords are ignored.
+
+export type JsonSchema = Record<string, unknown>
+
+const typeOf = (value: unknown): string => {
+  if (value === null) return "null"
+  if (Array.isArray(value)) return "array"
+  if (Number.isInteger(value)) return "integer"
+  return typeof value
+}
+
+const matchesType = (value: unknown, type: string) => {
+  const actual = typeOf(value)
+  return actual === type || (type === "number" && actual === "integer")
+}
+
+const asSchema = (value: unknown): JsonSchema | undefined =>
+  typeof value === "object" && value !== null && !Array.isArray(value) ?
+    (value as JsonSchema)
+  : undefined
+
+function resolveRef(root: JsonSchema, ref: string): JsonSchema | undefined {
+  if (!ref.startsWith("#")) return undefined
+  let target: unknown = root
+  for (const part of ref.slice(1).split("/").filter(Boolean)) {
+    const key = decodeURIComponent(part)
+      .replaceAll("~1", "/")
+      .replaceAll("~0", "~")
+    target = asSchema(target)?.[key]
+  }
+  return asSchema(target)
+}
+
+/** Every way `value` violates `schema`, as `path: problem` strings. */
+export function validateJsonSchema(
+  value: unknown,
+  schema: JsonSchema,
+): Array<string> {
+  const errors: Array<string> = []
+  check(value, schema, "$", schema, errors)
+  return errors
+}
+
+function check(
+  value: unknown,
+  schema: JsonSchema,
+  path: string,
+  root: JsonSchema,
+  errors: Array<string>,
+): void {
+  if (typeof schema.$ref === "string") {
+    const target = resolveRef(root, schema.$ref)
+    if (!target) errors.push(`${path}: unresolved $ref ${schema.$ref}`)
+    else check(value, target, path, root, errors)
+  }
+
+  const types =
+    typeof schema.type === "string" ? [schema.type]
+    : Array.isArray(schema.type) ? (schema.type as Array<string>)
+    : undefined
+  if (types && !types.some((type) => matchesType(value, type))) {
+    errors.push(`${path}: expected ${types.join(" or ")}, got ${typeOf(value)}`)
+    return
+  }
+
+  if (
+    Array.isArray(schema.enum)
+    && !schema.enum.some((option) => deepEqual(option, value))
+  ) {
+    errors.push(`${path}: must be one of ${JSON.stringify(schema.enum)}`)
+  }
+  if ("const" in schema && !deepEqual(schema.const, value)) {
+    errors.push(`${path}: must be ${JSON.stringify(schema.const)}`)
+  }
+
+  checkCombinators(value, schema, path, root, errors)
+
+  if (typeof value === "string") checkString(value, schema, path, errors)
+  if (typeof value === "number") checkNumber(value, schema, path, errors)
+  if (Array.isArray(value)) checkArray(value, schema, path, root, errors)
+  else if (asSchema(value)) {
+    checkObject(value as Record<string, unknown>, schema, path, root, errors)
+  }
+}
+
+function checkCombinators(
+  value: unknown,
+  schema: JsonSchema,
+  path: string,
+  root: JsonSchema,
+  errors: Array<string>,
+) {
+  const branches = (key: string) =>
+    Array.isArray(schema[key]) ?
+      (schema[key] as Array<unknown>).flatMap((branch) => {
+        const branchSchema = asSchema(branch)
+        return branchSchema ? [branchSchema] : []
+      })
+    : undefined
+  const passes = (branch: JsonSchema) => {
+    const branchErrors: Array<string> = []
+    check(value, branch, path, root, branchErrors)
+    return branchErrors.length === 0
+  }
+
+  for (const branch of branches("allOf") ?? []) {
+    check(value, branch, path, root, errors)
+  }
+  const anyOf = branches("anyOf")
+  if (anyOf && !anyOf.some((branch) => passes(branch))) {
+    errors.push(`${path}: matches none of anyOf`)
+  }
+  const oneOf = branches("oneOf")
+  if (oneOf) {
+    const matched = oneOf.filter((branch) => passes(branch)).length
+    if (matched !== 1) {
+      errors.push(`${path}: matches ${matched} of oneOf, expected exactly 1`)
+    }
+  }
+}
+
+function checkString(
+  value: string,
+  schema: JsonSchema,
+  path: string,
+  errors: Array<string>,
+) {
+  const length = [...value].length
+  if (typeof schema.minLength === "number" && length < schema.minLength) {
+    errors.push(`${path}: shorter than ${schema.minLength} characters`)
+  }
+  if (typeof schema.maxLength === "number" && length > schema.maxLength) {
+    errors.push(`${path}: longer than ${schema.maxLength} characters`)
+  }
+  if (
+    typeof schema.pattern === "string"
+    && !new RegExp(schema.pattern, "u").test(value)
+  ) {
+    errors.push(`${path}: does not match ${schema.pattern}`)
+  }
+}
+
+function checkNumber(
+  value: number,
+  schema: JsonSchema,
+  path: string,
+  errors: Array<string>,
+) {
+  if (typeof schema.minimum === "number" && value < schema.minimum) {
+    errors.push(`${path}: less than ${schema.minimum}`)
+  }
+  if (typeof schema.maximum === "number" && value > schema.maximum) {
+    errors.push(`${path}: greater than ${schema.maximum}`)
+  }
+  if (
+    typeof schema.exclusiveMinimum === "number"
+    && value <= schema.exclusiveMinimum
+  ) {
+    errors.push(`${path}: not greater than ${schema.exclusiveMinimum}`)
+  }
+  if (
+    typeof schema.exclusiveMaximum === "number"
+    && value >= schema.exclusiveMaximum
+  ) {
+    errors.push(`${path}: not less than ${schema.exclusiveMaximum}`)
+  }
+}
+
+function checkArray(
+  value: Array<unknown>,
+  schema: JsonSchema,
+  path: string,
+  root: JsonSchema,
+  errors: Array<string>,
+) {
+  if (typeof schema.minItems === "number" && value.length < schema.minItems) {
+    errors.push(`${path}: fewer than ${schema.minItems} items`)
+  }
+  if (typeof schema.maxItems === "number" && value.length > schema.maxItems) {
+    errors.push(`${path}: more than ${schema.maxItems} items`)
+  }
+  const items = asSchema(schema.items)
+  if (items) {
+    for (const [index, item] of value.entries()) {
+      check(item, items, `${path}[${index}]`, root, errors)
+    }
+  }
+}
+
+function checkObject(
+  value: Record<string, unknown>,
+  schema: JsonSchema,
+  path: string,
+  root: JsonSchema,
+  errors: Array<string>,
+) {
+  const properties = asSchema(schema.properties) ?? {}
+  if (Array.isArray(schema.required)) {
+    for (const key of schema.required as Array<string>) {
+      if (!Object.hasOwn(value, key)) {
+        errors.push(`${path}: missing required property ${key}`)
+      }
+    }
+  }
+  for (const [key, child] of Object.entries(value)) {
+    const propertySchema = asSchema(properties[key])
+    if (propertySchema) {
+      check(child, propertySchema, `${path}.${key}`, root, errors)
+    } else if (schema.additionalProperties === false) {
+      errors.push(`${path}: unexpected property ${key}`)
+    } else {
+      const additional = asSchema(schema.additionalProperties)
+      if (additional) check(child, additional, `${path}.${key}`, root, errors)
+    }
+  }
+}
+
+function deepEqual(a: unknown, b: unknown): boolean {
+  if (a === b) return true
+  if (typeof a !== "object" || typeof b !== "object" || !a || !b) return false
+  if (Array.isArray(a) !== Array.isArray(b)) return false
+  const aKeys = Object.keys(a)
+  const bKeys = Object.keys(b)
+  return (
+    aKeys.length === bKeys.length
+    && aKeys.every((key) =>
+      deepEqual(
+        (a as Record<string, unknown>)[key],
+        (b as Record<string, unknown>)[key],
+      ),
+    )
+  )
+}
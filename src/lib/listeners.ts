@@ -0,0 +1,124 @@
+// Extra addresses the gateway listens on besides --host and --port, such as
+// a local one for the tools on this machine next to a TLS one for remote
+// teammates. Every listener serves the same routes and shares all state;
+// only the middleware in front of them differs.
+
+import type { MiddlewareHandler } from "hono"
+
+import { Hono } from "hono"
+import { createHash, timingSafeEqual } from "node:crypto"
+import fs from "node:fs/promises"
+
+import { resolveConfigValue } from "./env-config"
+import { normalizePathPrefix } from "./path-prefix"
+
+export interface ListenerConfig {
+  port: number
+  // All interfaces when absent
+  host?: string
+  tlsCert?: string
+  tlsKey?: string
+  // Require client certificates issued by this CA
+  tlsClientCa?: string
+  // Only requests with one of these keys are served; `${VAR}` and `file:`
+  // references are resolved when the file is loaded
+  apiKeys?: Array<string>
+  // Set to false to not serve /admin, /token and /metrics
+  admin?: boolean
+  pathPrefix?: string
+}
+
+// Endpoints that expose tokens or let callers change the gateway
+const ADMIN_PATHS = ["/admin", "/token", "/metrics"]
+
+/**
+ * @throws {Error} When a listener has no valid port, only one of its TLS
+ *   files, or an invalid path prefix.
+ */
+export async function loadListeners(
+  filePath: string,
+): Promise<Array<ListenerConfig>> {
+  const listeners = JSON.parse(
+    await fs.readFile(filePath, "utf8"),
+  ) as Array<ListenerConfig>
+  for (const [index, listener] of listeners.entries()) {
+    const name = `Listener ${index + 1}`
+    if (
+      !Number.isInteger(listener.port)
+      || listener.port < 0
+      || listener.port > 65535
+    ) {
+      throw new Error(`${name}: invalid port ${String(listener.port)}`)
+    }
+    if (Boolean(listener.tlsCert) !== Boolean(listener.tlsKey)) {
+      throw new Error(`${name}: tlsCert and tlsKey must be given together`)
+    }
+    try {
+      listener.apiKeys = listener.apiKeys?.map((key) => resolveConfigValue(key))
+      listener.pathPrefix = normalizePathPrefix(listener.pathPrefix)
+    } catch (error) {
+      throw new Error(`${name}: ${(error as Error).message}`)
+    }
+  }
+  return listeners
+}
+
+const digest = (key: string) => createHash("sha256").update(key).digest()
+
+/** Rejects requests without one of the listener's API keys. */
+export function requireListenerKey(
+  apiKeys: Array<string>,
+): MiddlewareHandler {
+  const digests = apiKeys.map((key) => digest(key))
+  return async (c, next) => {
+    const key =
+      c.req.header("x-api-key")
+      ?? c.req.header("authorization")?.replace(/^Bearer\s+/i, "")
+    // Compared as digests, so neither length nor content leaks through timing
+    const presented = key ? digest(key) : undefined
+    if (
+      !presented
+      || !digests.some((expected) => timingSafeEqual(expected, presented))
+    ) {
+      return c.json(
+        {
+          error: {
+            message: "A valid API key is required on this listener",
+            type: "invalid_request_error",
+            code: "invalid_api_key",
+          },
+        },
+        401,
+      )
+    }
+    return next()
+  }
+}
+
+/** Answers 404 for the admin endpoints under `prefix`. */
+export function hideAdminEndpoints(prefix = ""): MiddlewareHandler {
+  return async (c, next) => {
+    const path = c.req.path.slice(prefix.length)
+    if (
+      ADMIN_PATHS.some(
+        (admin) => path === admin || path.startsWith(`${admin}/`),
+      )
+    ) {
+      return c.notFound()
+    }
+    return next()
+  }
+}
+
+/**
+ * The app a listener serves: its own middleware, then `routes` under its
+ * path prefix.
+ */
+export function listenerApp(listener: ListenerConfig, routes: Hono): Hono {
+  const app = new Hono()
+  if (listener.apiKeys) app.use(requireListenerKey(listener.apiKeys))
+  if (listener.admin === false) {
+    app.use(hideAdminEndpoints(listener.pathPrefix))
+  }
+  return app.route(listener.pathPrefix ?? "/", routes)
+}
@@ -0,0 +1,143 @@
+// Changes what is logged while the gateway runs, to debug a production issue
+// without a restart: a level filter with levels per request path, such as
+// `info,/v1/messages=debug`, and the request bodies of the next few requests.
+
+import type { MiddlewareHandler } from "hono"
+
+import consola, { type ConsolaReporter } from "consola"
+import { AsyncLocalStorage } from "node:async_hooks"
+
+import { redactSecrets } from "./audit"
+
+export const LOG_LEVELS = {
+  silent: Number.NEGATIVE_INFINITY,
+  error: 0,
+  warn: 1,
+  info: 3,
+  debug: 4,
+  trace: 5,
+} as const
+
+export type LogLevelName = keyof typeof LOG_LEVELS
+
+export interface LogFilter {
+  level: number
+  // Longest prefix first, so the most specific one wins
+  paths: Array<{ prefix: string; level: number }>
+}
+
+// Bodies beyond this are cut short in the log
+const MAX_BODY_CHARS = 100_000
+
+const levelOf = (name: string): number | undefined =>
+  Object.hasOwn(LOG_LEVELS, name) ? LOG_LEVELS[name as LogLevelName] : undefined
+
+/**
+ * Parses `<level>[,<path prefix>=<level>...]`, e.g.
+ * `warn,/v1/chat/completions=debug`. Returns undefined when it is invalid.
+ */
+export function parseLogFilter(raw: string): LogFilter | undefined {
+  const filter: LogFilter = { level: LOG_LEVELS.info, paths: [] }
+  for (const entry of raw.split(",").map((part) => part.trim())) {
+    if (!entry) continue
+    const separator = entry.lastIndexOf("=")
+    const level = levelOf(entry.slice(separator + 1).trim())
+    if (level === undefined) return undefined
+    if (separator === -1) {
+      filter.level = level
+      continue
+    }
+    const prefix = entry.slice(0, separator).trim()
+    if (!prefix.startsWith("/")) return undefined
+    filter.paths.push({ prefix, level })
+  }
+  filter.paths.sort((a, b) => b.prefix.length - a.prefix.length)
+  return filter
+}
+
+const requestPath = new AsyncLocalStorage<string>()
+
+let active: { raw: string; filter: LogFilter } | undefined
+// The reporters and level from before the first filter
+let base: { reporters: Array<ConsolaReporter>; level: number } | undefined
+let bodyLogsRemaining = 0
+
+function levelFor(filter: LogFilter, path: string | undefined): number {
+  if (path === undefined) return filter.level
+  return (
+    filter.paths.find((entry) => path.startsWith(entry.prefix))?.level
+    ?? filter.level
+  )
+}
+
+/** Applies a filter; returns false, changing nothing, when it is invalid. */
+export function setLogFilter(raw: string): boolean {
+  const filter = parseLogFilter(raw)
+  if (!filter) return false
+
+  base ??= { reporters: [...consola.options.reporters], level: consola.level }
+  // consola drops messages above its own level before reporters see them
+  consola.level = Math.max(
+    filter.level,
+    ...filter.paths.map((entry) => entry.level),
+  )
+  consola.setReporters(
+    base.reporters.map((reporter) => ({
+      log(logObj, ctx) {
+        if (logObj.level <= levelFor(filter, requestPath.getStore())) {
+          reporter.log(logObj, ctx)
+        }
+      },
+    })),
+  )
+  active = { raw, filter }
+  return true
+}
+
+/** Logs the bodies of the next `count` requests that have one. */
+export function logNextBodies(count: number): void {
+  bodyLogsRemaining = count
+}
+
+export function logControlStatus() {
+  const level = Object.entries(LOG_LEVELS).findLast(
+    ([, value]) => value <= consola.level,
+  )?.[0]
+  return {
+    filter: active?.raw ?? level ?? "silent",
+    bodyLogsRemaining,
+  }
+}
+
+export function resetLogControl(): void {
+  if (base) {
+    consola.setReporters(base.reporters)
+    consola.level = base.level
+  }
+  base = undefined
+  active = undefined
+  bodyLogsRemaining = 0
+}
+
+/**
+ * Runs the request with its path known to the filter, and logs its body,
+ * with secrets redacted, while body logging is on.
+ */
+export const logControl: MiddlewareHandler = async (c, next) => {
+  if (bodyLogsRemaining > 0 && c.req.method !== "GET") {
+    const body = await c.req.text()
+    if (body) {
+      bodyLogsRemaining--
+      const excerpt =
+        body.length > MAX_BODY_CHARS ?
+          `${body.slice(0, MAX_BODY_CHARS)}… (${body.length} characters)`
+        : body
+      consola.log(
+        `Request body of ${c.req.method} ${c.req.path}:`,
+        redactSecrets(excerpt),
+      )
+    }
+  }
+  if (!active) return next()
+  return requestPath.run(c.req.path, next)
+}
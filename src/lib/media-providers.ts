@@ -0,0 +1,119 @@
+// Copilot has no audio or image models, so these endpoints are forwarded as
+// they are to an OpenAI-compatible provider configured per kind. Without one,
+// they are answered with OpenAI's model_not_found error, which SDKs probing
+// for support understand, rather than the router's bare 404.
+
+import type { Context } from "hono"
+
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { resolveConfigValue } from "./env-config"
+import { forwardError } from "./error"
+import { state } from "./state"
+
+export type MediaKind = "audio" | "images"
+
+export interface MediaProvider {
+  // Base URL of an OpenAI-compatible API, e.g. https://api.openai.com/v1
+  url: string
+  // `${VAR}` and `file:` references are resolved when the file is loaded
+  apiKey?: string
+  timeoutSeconds?: number
+}
+
+export type MediaProviders = Partial<Record<MediaKind, MediaProvider>>
+
+const DEFAULT_TIMEOUT_SECONDS = 300
+
+// Headers of the client's request that describe its body or the answer
+const FORWARDED_HEADERS = ["content-type", "accept"]
+
+export async function loadMediaProviders(
+  filePath: string,
+): Promise<MediaProviders> {
+  const providers = JSON.parse(
+    await fs.readFile(filePath, "utf8"),
+  ) as MediaProviders
+  for (const [kind, provider] of Object.entries(providers)) {
+    if (!/^https?:\/\/[^/]/.test(provider.url)) {
+      throw new Error(`Invalid ${kind} provider URL: ${provider.url}`)
+    }
+    provider.url = provider.url.replace(/\/+$/, "")
+    if (provider.apiKey) provider.apiKey = resolveConfigValue(provider.apiKey)
+  }
+  return providers
+}
+
+// The model the client asked for, from a JSON or multipart form body
+async function requestedModel(c: Context): Promise<string | undefined> {
+  try {
+    const body =
+      c.req.header("content-type")?.includes("json") ?
+        await c.req.json<Record<string, unknown>>()
+      : await c.req.parseBody()
+    return typeof body.model === "string" ? body.model : undefined
+  } catch {
+    return undefined
+  }
+}
+
+async function unsupported(c: Context, feature: string) {
+  const model = await requestedModel(c)
+  const message = `${
+    model === undefined ?
+      "No model is available for this endpoint"
+    : `The model \`${model}\` does not exist or you do not have access to it`
+  }. ${feature} is not provided by GitHub Copilot, and no provider is configured for it.`
+  return c.json(
+    {
+      error: {
+        message,
+        type: "invalid_request_error",
+        param: "model",
+        code: "model_not_found",
+      },
+    },
+    404,
+  )
+}
+
+/**
+ * A handler that forwards the request to `path` of the provider configured
+ * for `kind`, and passes its response back unchanged.
+ */
+export const forwardToProvider =
+  (kind: MediaKind, path: string, feature: string) => async (c: Context) => {
+    const provider = state.mediaProviders?.[kind]
+    if (!provider) return unsupported(c, feature)
+
+    const headers = new Headers()
+    for (const name of FORWARDED_HEADERS) {
+      const value = c.req.header(name)
+      if (value) headers.set(name, value)
+    }
+    if (provider.apiKey) {
+      headers.set("authorization", `Bearer ${provider.apiKey}`)
+    }
+
+    try {
+      const response = await fetch(`${provider.url}${path}`, {
+        method: "POST",
+        headers,
+        body: await c.req.arrayBuffer(),
+        signal: AbortSignal.timeout(
+          (provider.timeoutSeconds ?? DEFAULT_TIMEOUT_SECONDS) * 1000,
+        ),
+      })
+      if (!response.ok) {
+        consola.warn(`${feature} provider answered ${response.status}`)
+      }
+      const contentType = response.headers.get("content-type")
+      return new Response(response.body, {
+        status: response.status,
+        headers: contentType ? { "content-type": contentType } : {},
+      })
+    } catch (error) {
+      return await forwardError(c, error)
+    }
+  }
@@ -0,0 +1,402 @@
+// In-process latency histograms, exposed as Prometheus text at /metrics and
+// as JSON at /stats. Values are in seconds.
+
+import type { Usage } from "~/services/copilot/create-chat-completions"
+
+import { quotaGuardStatus } from "./quota-guard"
+import { taggedUsage } from "./request-tags"
+import { rustCore, type NativeMetrics } from "./rust-core"
+import { recordFirstToken } from "./slo-alerts"
+import { state } from "./state"
+import { upstreamStatus } from "./upstreams"
+import { projectUsage } from "./usage-projection"
+
+const LATENCY_BUCKETS = [
+  0.05, 0.1, 0.25, 0.5, 1, 2.5, 5, 10, 30, 60, 120, 300,
+] as const
+
+class Histogram {
+  // Cumulative counts per upper bound, like Prometheus buckets
+  counts: Array<number> = LATENCY_BUCKETS.map(() => 0)
+  count = 0
+  sum = 0
+
+  observe(seconds: number): void {
+    this.count++
+    this.sum += seconds
+    for (const [i, bound] of LATENCY_BUCKETS.entries()) {
+      if (seconds <= bound) this.counts[i]++
+    }
+  }
+
+  // Linear interpolation within the bucket, as histogram_quantile() does
+  quantile(q: number): number | null {
+    if (this.count === 0) return null
+
+    const rank = q * this.count
+    let lowerBound = 0
+    let lowerCount = 0
+    for (const [i, bound] of LATENCY_BUCKETS.entries()) {
+      if (this.counts[i] >= rank) {
+        const inBucket = this.counts[i] - lowerCount
+        return (
+          lowerBound + ((bound - lowerBound) * (rank - lowerCount)) / inBucket
+        )
+      }
+      lowerBound = bound
+      lowerCount = this.counts[i]
+    }
+    // Beyond the last bucket
+    return LATENCY_BUCKETS.at(-1) ?? null
+  }
+
+  summary() {
+    return {
+      count: this.count,
+      sumSeconds: this.sum,
+      p50Seconds: this.quantile(0.5),
+      p95Seconds: this.quantile(0.95),
+      p99Seconds: this.quantile(0.99),
+      buckets: Object.fromEntries(
+        LATENCY_BUCKETS.map((bound, i) => [String(bound), this.counts[i]]),
+      ),
+    }
+  }
+}
+
+class HistogramFamily {
+  series = new Map<string, Histogram>()
+  name: string
+  help: string
+  label: string
+
+  constructor(name: string, help: string, label: string) {
+    this.name = name
+    this.help = help
+    this.label = label
+  }
+
+  observe(labelValue: string, seconds: number): void {
+    let histogram = this.series.get(labelValue)
+    if (!histogram) {
+      histogram = new Histogram()
+      this.series.set(labelValue, histogram)
+    }
+    histogram.observe(seconds)
+  }
+
+  render(): Array<string> {
+    const lines = [`# HELP ${this.name} ${this.help}`, `# TYPE ${this.name} histogram`]
+    for (const [value, histogram] of this.series) {
+      const label = `${this.label}="${escapeLabel(value)}"`
+      for (const [i, bound] of LATENCY_BUCKETS.entries()) {
+        lines.push(`${this.name}_bucket{${label},le="${bound}"} ${histogram.counts[i]}`)
+      }
+      lines.push(
+        `${this.name}_bucket{${label},le="+Inf"} ${histogram.count}`,
+        `${this.name}_sum{${label}} ${histogram.sum}`,
+        `${this.name}_count{${label}} ${histogram.count}`,
+      )
+    }
+    return lines
+  }
+
+  summaries() {
+    return Object.fromEntries(
+      [...this.series].map(([value, histogram]) => [value, histogram.summary()]),
+    )
+  }
+}
+
+const escapeLabel = (value: string) =>
+  value.replaceAll("\\", "\\\\").replaceAll('"', '\\"').replaceAll("\n", "\\n")
+
+const requestDuration = new HistogramFamily(
+  "copilot_api_request_duration_seconds",
+  "Time until the response headers were sent, by route",
+  "route",
+)
+const timeToFirstToken = new HistogramFamily(
+  "copilot_api_time_to_first_token_seconds",
+  "Time from request start to the first streamed chunk, by model",
+  "model",
+)
+const streamDuration = new HistogramFamily(
+  "copilot_api_stream_duration_seconds",
+  "Time from request start to the end of a streamed response, by model",
+  "model",
+)
+
+interface PromptCacheCounts {
+  requests: number
+  hits: number
+  promptTokens: number
+  cachedTokens: number
+}
+
+// Upstream prompt cache usage by model, from the `usage` of each response
+const promptCache = new Map<string, PromptCacheCounts>()
+
+interface TransferCounts {
+  responses: number
+  bytes: number
+}
+
+// Upstream responses by content encoding, with their size on the wire
+const upstreamTransfer = new Map<string, TransferCounts>()
+
+const startedAt = Date.now()
+
+export function observeRequest(route: string, seconds: number): void {
+  requestDuration.observe(route, seconds)
+}
+
+/**
+ * Times one streamed completion from `start`, a `performance.now()` taken
+ * when the request arrived. Call `chunk()` for every chunk and `end()` once
+ * the stream is done; the first chunk sets the time to first token.
+ */
+export function startStreamTimer(model: string, start = performance.now()) {
+  let firstChunkSeen = false
+
+  return {
+    chunk() {
+      if (firstChunkSeen) return
+      firstChunkSeen = true
+      const seconds = (performance.now() - start) / 1000
+      timeToFirstToken.observe(model, seconds)
+      recordFirstToken(seconds)
+    },
+    end() {
+      streamDuration.observe(model, (performance.now() - start) / 1000)
+    },
+  }
+}
+
+export function observePromptCache(
+  model: string,
+  usage: Usage | null | undefined,
+): void {
+  if (!usage) return
+
+  const counts = promptCache.get(model) ?? {
+    requests: 0,
+    hits: 0,
+    promptTokens: 0,
+    cachedTokens: 0,
+  }
+  const cached = usage.prompt_tokens_details?.cached_tokens ?? 0
+  counts.requests++
+  if (cached > 0) counts.hits++
+  counts.promptTokens += usage.prompt_tokens
+  counts.cachedTokens += cached
+  promptCache.set(model, counts)
+}
+
+/**
+ * Counts an upstream response by `content-encoding`, with its transferred
+ * size from `content-length`. Streamed responses carry no length, so only
+ * the response is counted.
+ */
+export function observeUpstreamResponse(headers: Headers): void {
+  const encoding = headers.get("content-encoding") ?? "identity"
+  const counts = upstreamTransfer.get(encoding) ?? { responses: 0, bytes: 0 }
+  counts.responses++
+  counts.bytes += Number(headers.get("content-length")) || 0
+  upstreamTransfer.set(encoding, counts)
+}
+
+function renderUpstreamTransfer(): Array<string> {
+  const counters = [
+    [
+      "copilot_api_upstream_responses_total",
+      "Upstream responses, by content encoding",
+      "responses",
+    ],
+    [
+      "copilot_api_upstream_response_bytes_total",
+      "Upstream response bytes as transferred, by content encoding",
+      "bytes",
+    ],
+  ] as const
+  return counters.flatMap(([name, help, field]) => [
+    `# HELP ${name} ${help}`,
+    `# TYPE ${name} counter`,
+    ...[...upstreamTransfer].map(
+      ([encoding, counts]) =>
+        `${name}{encoding="${escapeLabel(encoding)}"} ${counts[field]}`,
+    ),
+  ])
+}
+
+// Only with --upstreams
+function renderUpstreamFailover(): Array<string> {
+  const upstreams = upstreamStatus()
+  if (upstreams.length === 0) return []
+
+  const label = (url: string) => `upstream="${escapeLabel(url)}"`
+  return [
+    "# HELP copilot_api_upstream_served_total Requests answered, by upstream base URL",
+    "# TYPE copilot_api_upstream_served_total counter",
+    ...upstreams.map(
+      ({ url, served }) =>
+        `copilot_api_upstream_served_total{${label(url)}} ${served}`,
+    ),
+    "# HELP copilot_api_upstream_failovers_total Requests answered by a fallback upstream because the ones before it were unreachable",
+    "# TYPE copilot_api_upstream_failovers_total counter",
+    ...upstreams.map(
+      ({ url, failedOver }) =>
+        `copilot_api_upstream_failovers_total{${label(url)}} ${failedOver}`,
+    ),
+    "# HELP copilot_api_upstream_circuit_open Whether the upstream is skipped as unreachable",
+    "# TYPE copilot_api_upstream_circuit_open gauge",
+    ...upstreams.map(
+      ({ url, state: circuit }) =>
+        `copilot_api_upstream_circuit_open{${label(url)}} ${circuit === "open" ? 1 : 0}`,
+    ),
+  ]
+}
+
+function renderPromptCache(): Array<string> {
+  const counters = [
+    [
+      "copilot_api_prompt_tokens_total",
+      "Prompt tokens reported by upstream, by model",
+      "promptTokens",
+    ],
+    [
+      "copilot_api_cached_prompt_tokens_total",
+      "Prompt tokens served from the upstream prompt cache, by model",
+      "cachedTokens",
+    ],
+  ] as const
+  return counters.flatMap(([name, help, field]) => [
+    `# HELP ${name} ${help}`,
+    `# TYPE ${name} counter`,
+    ...[...promptCache].map(
+      ([model, counts]) => `${name}{model="${escapeLabel(model)}"} ${counts[field]}`,
+    ),
+  ])
+}
+
+// Undefined when the native module is not loaded
+function nativeMetrics(): NativeMetrics | undefined {
+  try {
+    return rustCore.getNativeMetrics()
+  } catch {
+    return undefined
+  }
+}
+
+function renderNativeMetrics(): Array<string> {
+  const metrics = nativeMetrics()
+  if (!metrics) return []
+
+  const counter = (name: string, help: string, samples: Array<string>) => [
+    `# HELP ${name} ${help}`,
+    `# TYPE ${name} counter`,
+    ...samples,
+  ]
+  const { tokenizer, rateLimit, upstream } = metrics
+  return [
+    ...counter(
+      "copilot_api_native_tokenizer_calls_total",
+      "Token counts computed by the native module",
+      [`copilot_api_native_tokenizer_calls_total ${tokenizer.calls}`],
+    ),
+    ...counter(
+      "copilot_api_native_tokenizer_seconds_total",
+      "Time spent counting tokens in the native module",
+      [`copilot_api_native_tokenizer_seconds_total ${tokenizer.seconds}`],
+    ),
+    ...counter(
+      "copilot_api_native_validation_failures_total",
+      "Payload validation errors in the native module, by reason",
+      Object.entries(metrics.validationFailures).map(
+        ([reason, count]) =>
+          `copilot_api_native_validation_failures_total{reason="${escapeLabel(reason)}"} ${count}`,
+      ),
+    ),
+    ...counter(
+      "copilot_api_native_rate_limit_checks_total",
+      "Native rate limit checks, by result",
+      [
+        `copilot_api_native_rate_limit_checks_total{result="allowed"} ${rateLimit.allowed}`,
+        `copilot_api_native_rate_limit_checks_total{result="rejected"} ${rateLimit.rejected}`,
+      ],
+    ),
+    ...counter(
+      "copilot_api_native_upstream_requests_total",
+      "Upstream requests sent by the native HTTP client, by outcome",
+      (["ok", "failed", "cancelled"] as const).map(
+        (outcome) =>
+          `copilot_api_native_upstream_requests_total{outcome="${outcome}"} ${upstream[outcome]}`,
+      ),
+    ),
+    ...counter(
+      "copilot_api_native_upstream_seconds_total",
+      "Time spent on upstream requests by the native HTTP client, retries included",
+      [`copilot_api_native_upstream_seconds_total ${upstream.seconds}`],
+    ),
+  ]
+}
+
+export function renderPrometheus(): string {
+  return `${[requestDuration, timeToFirstToken, streamDuration]
+    .flatMap((family) => family.render())
+    .concat(
+      renderPromptCache(),
+      renderUpstreamTransfer(),
+      renderUpstreamFailover(),
+      renderNativeMetrics(),
+    )
+    .join("\n")}\n`
+}
+
+/** With `tag`, only the totals of matching tags are listed under `tags`. */
+export function getStats(options: { tag?: string } = {}) {
+  const models = new Set([
+    ...timeToFirstToken.series.keys(),
+    ...streamDuration.series.keys(),
+  ])
+  const ttft = timeToFirstToken.summaries()
+  const duration = streamDuration.summaries()
+
+  return {
+    uptimeSeconds: Math.round((Date.now() - startedAt) / 1000),
+    routes: requestDuration.summaries(),
+    models: Object.fromEntries(
+      [...models].map((model) => [
+        model,
+        { timeToFirstToken: ttft[model], streamDuration: duration[model] },
+      ]),
+    ),
+    promptCache: Object.fromEntries(
+      [...promptCache].map(([model, counts]) => [
+        model,
+        {
+          ...counts,
+          hitRate: counts.hits / counts.requests,
+          cachedTokenRatio:
+            counts.promptTokens > 0 ?
+              counts.cachedTokens / counts.promptTokens
+            : 0,
+        },
+      ]),
+    ),
+    upstream: Object.fromEntries(upstreamTransfer),
+    upstreams: upstreamStatus(),
+    native: nativeMetrics(),
+    quotaGuard: state.quotaGuard && quotaGuardStatus(),
+    usageProjection: state.usagePollMinutes && projectUsage(),
+    tags: taggedUsage(options.tag),
+  }
+}
+
+export function resetMetrics(): void {
+  for (const family of [requestDuration, timeToFirstToken, streamDuration]) {
+    family.series.clear()
+  }
+  promptCache.clear()
+  upstreamTransfer.clear()
+}
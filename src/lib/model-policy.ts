@@ -0,0 +1,86 @@
+import type { Context } from "hono"
+
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { apiKeyOf } from "./api-key"
+import { HTTPError } from "./error"
+import { state } from "./state"
+
+/**
+ * Model names may end in `*` to match a prefix, e.g. `claude-*`. With an
+ * `allow` list only matching models are permitted; `deny` is applied after.
+ */
+export interface ModelRule {
+  allow?: Array<string>
+  deny?: Array<string>
+}
+
+export interface ModelPolicy {
+  // Applies to keys without their own rule, including requests without a key
+  default?: ModelRule
+  keys?: Record<string, ModelRule>
+}
+
+export async function loadModelPolicy(filePath: string): Promise<ModelPolicy> {
+  return JSON.parse(await fs.readFile(filePath, "utf8")) as ModelPolicy
+}
+
+export const matchesModel = (patterns: Array<string>, model: string) =>
+  patterns.some((pattern) =>
+    pattern.endsWith("*") ?
+      model.startsWith(pattern.slice(0, -1))
+    : pattern === model,
+  )
+
+export function isModelAllowed(rule: ModelRule, model: string): boolean {
+  if (rule.allow && !matchesModel(rule.allow, model)) return false
+  return !rule.deny || !matchesModel(rule.deny, model)
+}
+
+export function ruleFor(
+  policy: ModelPolicy,
+  key: string | undefined,
+): ModelRule | undefined {
+  return (key !== undefined ? policy.keys?.[key] : undefined) ?? policy.default
+}
+
+/**
+ * @throws {HTTPError} 403 with an OpenAI-style error listing the permitted
+ * models when the client's API key may not use `model`.
+ */
+export function checkModelAccess(c: Context, model: string): void {
+  checkKeyModelAccess(apiKeyOf(c), model)
+}
+
+/** {@link checkModelAccess} for requests that do not arrive over HTTP. */
+export function checkKeyModelAccess(
+  key: string | undefined,
+  model: string,
+): void {
+  if (!state.modelPolicy) return
+
+  const rule = ruleFor(state.modelPolicy, key)
+  if (!rule || isModelAllowed(rule, model)) return
+
+  const permitted = (state.models?.data ?? [])
+    .map((candidate) => candidate.id)
+    .filter((id) => isModelAllowed(rule, id))
+  const message = `Model ${model} is not allowed for this API key. Permitted models: ${permitted.join(", ") || "none"}`
+  consola.warn(`Rejected request for disallowed model ${model}`)
+  throw new HTTPError(
+    message,
+    Response.json(
+      {
+        error: {
+          message,
+          type: "invalid_request_error",
+          param: "model",
+          code: "model_not_allowed",
+          permitted_models: permitted,
+        },
+      },
+      { status: 403 },
+    ),
+  )
+}
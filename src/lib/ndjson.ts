@@ -0,0 +1,65 @@
+// Newline-delimited JSON as an alternative to SSE for streamed completions,
+// for clients that read a stream line by line: each chunk is one JSON line,
+// and the stream ends without a `[DONE]` marker.
+
+import type { Context } from "hono"
+
+import consola from "consola"
+import { stream, streamSSE, type SSEMessage } from "hono/streaming"
+
+export const NDJSON_CONTENT_TYPE = "application/x-ndjson"
+
+export type StreamFormat = "sse" | "ndjson"
+
+/** What the stream handlers write to, whatever the format. */
+export interface ChunkStream {
+  writeSSE: (message: SSEMessage) => Promise<void>
+}
+
+/**
+ * NDJSON when the body asks for `stream_format: "ndjson"`, or the client
+ * accepts it but not SSE.
+ */
+export function streamFormatOf(
+  c: Context,
+  body: { stream_format?: unknown },
+): StreamFormat {
+  if (body.stream_format === "ndjson") return "ndjson"
+  if (body.stream_format === "sse") return "sse"
+  const accept = c.req.header("accept") ?? ""
+  return (
+      accept.includes(NDJSON_CONTENT_TYPE)
+        && !accept.includes("text/event-stream")
+    ) ?
+      "ndjson"
+    : "sse"
+}
+
+/** Streams `handler`'s events as SSE or, with "ndjson", as JSON lines. */
+export function streamChunks(
+  c: Context,
+  format: StreamFormat,
+  handler: (chunks: ChunkStream) => Promise<void>,
+): Response {
+  if (format === "sse") return streamSSE(c, handler)
+
+  c.header("content-type", NDJSON_CONTENT_TYPE)
+  c.header("cache-control", "no-cache")
+  return stream(
+    c,
+    (output) =>
+      handler({
+        async writeSSE(message) {
+          // Only SSE needs an end marker; comments and keep-alives are dropped
+          if (!message.data || message.data === "[DONE]") return
+          await output.write(`${message.data}\n`)
+        },
+      }),
+    async (error, output) => {
+      consola.error("Error in NDJSON stream:", error)
+      await output.write(
+        `${JSON.stringify({ error: { message: error.message, type: "error" } })}\n`,
+      )
+    },
+  )
+}
@@ -0,0 +1,170 @@
+import type { Context } from "hono"
+
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import type { ChatCompletionsPayload } from "~/services/copilot/create-chat-completions"
+
+import { apiKeyOf } from "./api-key"
+import { matchesModel } from "./model-policy"
+import { normalizeReasoningEffort } from "./reasoning"
+import { state } from "./state"
+
+export const PARAM_POLICY_HEADER = "x-param-policy"
+
+/**
+ * Adjusts sampling parameters for models matching `models` (a trailing `*`
+ * matches a prefix). `strip` runs first, then `clamp`, then `override`.
+ */
+export interface ParamRule {
+  models: Array<string>
+  strip?: Array<string>
+  clamp?: Record<string, { min?: number; max?: number }>
+  override?: Record<string, unknown>
+}
+
+/**
+ * Filled in when the client omits them, before any rule runs. `system` is
+ * added as the first message when there is no system or developer message.
+ */
+export interface ParamDefaults {
+  temperature?: number
+  top_p?: number
+  max_tokens?: number
+  system?: string
+}
+
+export interface ParamPolicy {
+  rules?: Array<ParamRule>
+  // Set to false to drop the built-in rules
+  builtin?: boolean
+  // Applies to keys without their own defaults, including requests without a key
+  defaults?: ParamDefaults
+  // By API key
+  keys?: Record<string, ParamDefaults>
+}
+
+// Parameters Copilot rejects for reasoning models
+export const BUILTIN_PARAM_RULES: Array<ParamRule> = [
+  {
+    models: ["o1*", "o3*", "o4*"],
+    strip: [
+      "temperature",
+      "top_p",
+      "presence_penalty",
+      "frequency_penalty",
+      "logit_bias",
+      "logprobs",
+    ],
+  },
+]
+
+export async function loadParamPolicy(filePath: string): Promise<ParamPolicy> {
+  return JSON.parse(await fs.readFile(filePath, "utf8")) as ParamPolicy
+}
+
+export function paramRulesOf(policy: ParamPolicy | undefined): Array<ParamRule> {
+  const builtin = policy?.builtin === false ? [] : BUILTIN_PARAM_RULES
+  return [...builtin, ...(policy?.rules ?? [])]
+}
+
+export function defaultsFor(
+  policy: ParamPolicy | undefined,
+  key: string | undefined,
+): ParamDefaults | undefined {
+  return (key !== undefined ? policy?.keys?.[key] : undefined) ?? policy?.defaults
+}
+
+export function applyParamDefaults(
+  payload: ChatCompletionsPayload,
+  defaults: ParamDefaults | undefined,
+): { payload: ChatCompletionsPayload; adjustments: Array<string> } {
+  if (!defaults) return { payload, adjustments: [] }
+
+  const { system, ...params } = defaults
+  const result = { ...payload } as Record<string, unknown>
+  const adjustments: Array<string> = []
+  for (const [name, value] of Object.entries(params)) {
+    if (value === undefined) continue
+    if (result[name] !== undefined && result[name] !== null) continue
+    result[name] = value
+    adjustments.push(`defaulted=${name}`)
+  }
+
+  const hasSystem = payload.messages.some(
+    (message) => message.role === "system" || message.role === "developer",
+  )
+  if (system !== undefined && !hasSystem) {
+    result.messages = [{ role: "system", content: system }, ...payload.messages]
+    adjustments.push("defaulted=system")
+  }
+  return { payload: result as unknown as ChatCompletionsPayload, adjustments }
+}
+
+/**
+ * Applies every rule matching the payload's model, returning the adjusted
+ * payload and what changed, e.g. `stripped=temperature`.
+ */
+export function applyParamRules(
+  payload: ChatCompletionsPayload,
+  rules: Array<ParamRule>,
+): { payload: ChatCompletionsPayload; adjustments: Array<string> } {
+  const params = { ...payload } as Record<string, unknown>
+  const adjustments: Array<string> = []
+
+  for (const rule of rules) {
+    if (!matchesModel(rule.models, payload.model)) continue
+
+    for (const name of rule.strip ?? []) {
+      if (params[name] === undefined || params[name] === null) continue
+      delete params[name]
+      adjustments.push(`stripped=${name}`)
+    }
+    for (const [name, { min, max }] of Object.entries(rule.clamp ?? {})) {
+      const value = params[name]
+      if (typeof value !== "number") continue
+      const clamped = Math.min(max ?? value, Math.max(min ?? value, value))
+      if (clamped === value) continue
+      params[name] = clamped
+      adjustments.push(`clamped=${name}`)
+    }
+    for (const [name, value] of Object.entries(rule.override ?? {})) {
+      params[name] = value
+      adjustments.push(`overridden=${name}`)
+    }
+  }
+  return { payload: params as unknown as ChatCompletionsPayload, adjustments }
+}
+
+/**
+ * Fills in the API key's defaults, fits `reasoning_effort` to the model,
+ * applies the configured parameter policy and reports changes in the
+ * `x-param-policy` header, so clients can see why a parameter had no effect.
+ */
+export function scrubParams(
+  c: Context,
+  payload: ChatCompletionsPayload,
+): ChatCompletionsPayload {
+  const defaulted = applyParamDefaults(
+    payload,
+    defaultsFor(state.paramPolicy, apiKeyOf(c)),
+  )
+  const reasoning = normalizeReasoningEffort(defaulted.payload)
+  const result = applyParamRules(
+    reasoning.payload,
+    paramRulesOf(state.paramPolicy),
+  )
+  const adjustments = [
+    ...defaulted.adjustments,
+    ...reasoning.adjustments,
+    ...result.adjustments,
+  ]
+  if (adjustments.length > 0) {
+    consola.debug(
+      `Adjusted parameters for ${payload.model}:`,
+      adjustments.join(", "),
+    )
+    c.header(PARAM_POLICY_HEADER, adjustments.join(", "))
+  }
+  return result.payload
+}
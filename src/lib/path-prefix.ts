@@ -0,0 +1,16 @@
+/**
+ * Normalizes `--path-prefix` to `/segment/...` without a trailing slash.
+ * Returns undefined for an empty prefix or `/`, meaning the API is served
+ * at the root.
+ * @throws {TypeError} When the prefix contains a query, fragment or spaces.
+ */
+export function normalizePathPrefix(
+  raw: string | undefined,
+): string | undefined {
+  if (raw === undefined) return undefined
+  if (/[\s?#]/.test(raw)) {
+    throw new TypeError(`Invalid path prefix: "${raw}"`)
+  }
+  const segments = raw.split("/").filter(Boolean)
+  return segments.length > 0 ? `/${segments.join("/")}` : undefined
+}
@@ -5,10 +5,25 @@ import path from "node:path"
 const APP_DIR = path.join(os.homedir(), ".local", "share", "copilot-api")
 
 const GITHUB_TOKEN_PATH = path.join(APP_DIR, "github_token")
+const AUDIT_DIR = path.join(APP_DIR, "audit")
+const SESSIONS_DIR = path.join(APP_DIR, "sessions")
+const USAGE_DIR = path.join(APP_DIR, "usage")
+// Primary and shadow answers of mirrored requests
+const SHADOW_DIR = path.join(APP_DIR, "shadow")
+// Premium request quota polled by --poll-usage, one JSON line per poll
+const USAGE_SNAPSHOTS_PATH = path.join(APP_DIR, "usage_snapshots.jsonl")
+// GitHub tokens of team members by API key id
+const TEAM_TOKENS_PATH = path.join(APP_DIR, "team_tokens.json")
 
 export const PATHS = {
   APP_DIR,
   GITHUB_TOKEN_PATH,
+  AUDIT_DIR,
+  SESSIONS_DIR,
+  USAGE_DIR,
+  SHADOW_DIR,
+  USAGE_SNAPSHOTS_PATH,
+  TEAM_TOKENS_PATH,
 }
 
 export async function ensurePaths(): Promise<void> {
@@ -0,0 +1,170 @@
+// WASM plugins that rewrite or reject JSON requests and responses, for
+// custom redaction or routing without forking the gateway. Plugins run in
+// the native module, see native/src/plugins.rs for the ABI.
+
+import type { Context, MiddlewareHandler } from "hono"
+import type { ContentfulStatusCode } from "hono/utils/http-status"
+
+import path from "node:path"
+
+import { rustCore, type PluginHook } from "./rust-core"
+import { state } from "./state"
+
+export interface Plugin {
+  name: string
+  hooks: Array<PluginHook>
+  // The output JSON, or null to leave the input unchanged
+  run: (hook: PluginHook, input: string) => string | null
+}
+
+/** What a plugin sees: the body, plus the status for responses. */
+export interface PluginInput {
+  path: string
+  status?: number
+  body: unknown
+}
+
+/** What a plugin returns, when it does not return 0. */
+export interface PluginOutput {
+  body?: unknown
+  reject?: { status?: number; message: string }
+}
+
+export type PluginResult =
+  | { body: unknown }
+  | { reject: { status?: number; message: string }; plugin: string }
+
+/** @throws {Error} When a module cannot be read or does not fit the ABI. */
+export function loadPlugins(paths: Array<string>): Array<Plugin> {
+  return paths.map((pluginPath) => {
+    const info = rustCore.loadPlugin(pluginPath)
+    return {
+      name: path.basename(pluginPath),
+      hooks: info.hooks,
+      run: (hook, input) => rustCore.runPlugin(info.id, hook, input),
+    }
+  })
+}
+
+/**
+ * Runs the plugins with `hook` in order, each seeing the body left by the
+ * previous one, until one rejects.
+ * @throws {Error} When a plugin traps, runs out of fuel or returns invalid
+ * JSON, so a broken redaction plugin does not let data through.
+ */
+export function applyPlugins(
+  plugins: Array<Plugin>,
+  hook: PluginHook,
+  input: PluginInput,
+): PluginResult {
+  let body = input.body
+  for (const plugin of plugins) {
+    if (!plugin.hooks.includes(hook)) continue
+    let output: PluginOutput | undefined
+    try {
+      const raw = plugin.run(hook, JSON.stringify({ ...input, body }))
+      output = raw === null ? undefined : (JSON.parse(raw) as PluginOutput)
+    } catch (error) {
+      throw new Error(
+        `Plugin ${plugin.name} failed: ${(error as Error).message}`,
+      )
+    }
+    if (output?.reject) return { reject: output.reject, plugin: plugin.name }
+    if (output?.body !== undefined) body = output.body
+  }
+  return { body }
+}
+
+function rejection(
+  c: Context,
+  result: Extract<PluginResult, { plugin: string }>,
+) {
+  return c.json(
+    {
+      error: {
+        message: result.reject.message,
+        type: "invalid_request_error",
+      },
+    },
+    (result.reject.status ?? 400) as ContentfulStatusCode,
+  )
+}
+
+const isJson = (contentType: string | null | undefined) =>
+  contentType?.startsWith("application/json") ?? false
+
+// streamSSE and stream mark their responses chunked
+const isStream = (headers: Headers) =>
+  headers.get("content-type")?.startsWith("text/event-stream")
+  || headers.get("transfer-encoding") === "chunked"
+
+/**
+ * Makes `body` what every later `c.req.json()`, `text()` and
+ * `arrayBuffer()` returns. The raw request is left alone: middleware before
+ * this one may already have consumed it, and samples, tags and experiments
+ * are keyed by it.
+ */
+function replaceRequestBody(c: Context, body: unknown): void {
+  const text = JSON.stringify(body)
+  // Hono caches the promises, although it types the resolved values
+  c.req.bodyCache = {
+    json: Promise.resolve(body),
+    text: Promise.resolve(text),
+    arrayBuffer: Promise.resolve(new TextEncoder().encode(text).buffer),
+  } as unknown as typeof c.req.bodyCache
+}
+
+/**
+ * Passes POSTed JSON bodies through the `request` hooks before the route
+ * sees them, and JSON responses through the `response` hooks. Streamed
+ * responses are not transformed.
+ */
+export const runPlugins: MiddlewareHandler = async (c, next) => {
+  const plugins = state.plugins
+  if (!plugins?.length) return next()
+
+  if (c.req.method === "POST" && isJson(c.req.header("content-type"))) {
+    // Read as text through Hono's cache, which holds the body when signing
+    // or body logging got to it first; a failed c.req.json() would stay
+    // cached and hide the body from parseJsonBody. Invalid JSON is left for
+    // the route to report.
+    const text = await c.req.text()
+    let body: unknown
+    try {
+      body = JSON.parse(text)
+    } catch {
+      body = undefined
+    }
+    if (body !== undefined) {
+      const result = applyPlugins(plugins, "request", {
+        path: c.req.path,
+        body,
+      })
+      if ("reject" in result) return rejection(c, result)
+      if (result.body !== body) replaceRequestBody(c, result.body)
+    }
+  }
+
+  await next()
+
+  if (!isJson(c.res.headers.get("content-type")) || isStream(c.res.headers)) {
+    return
+  }
+  const body: unknown = await c.res.clone().json()
+  const result = applyPlugins(plugins, "response", {
+    path: c.req.path,
+    status: c.res.status,
+    body,
+  })
+  if ("reject" in result) {
+    c.res = undefined
+    c.res = rejection(c, result)
+  } else if (result.body !== body) {
+    const headers = new Headers(c.res.headers)
+    headers.delete("content-length")
+    c.res = new Response(JSON.stringify(result.body), {
+      status: c.res.status,
+      headers,
+    })
+  }
+}
@@ -0,0 +1,55 @@
+import consola from "consola"
+import net from "node:net"
+
+const probePort = (port: number, host?: string) =>
+  new Promise<number | undefined>((resolve, reject) => {
+    const probe = net.createServer()
+    probe.unref()
+
+    probe.once("error", (error: NodeJS.ErrnoException) => {
+      if (error.code === "EADDRINUSE") {
+        resolve(undefined)
+      } else {
+        reject(error)
+      }
+    })
+
+    probe.listen({ port, host }, () => {
+      const { port: boundPort } = probe.address() as net.AddressInfo
+      probe.close(() => resolve(boundPort))
+    })
+  })
+
+/**
+ * Finds a port to listen on. Port 0 picks a free ephemeral port, otherwise
+ * up to `retries` successive ports are tried when the requested one is taken.
+ */
+export async function resolvePort(
+  port: number,
+  retries: number,
+  host?: string,
+): Promise<number> {
+  const lastPort = port === 0 ? 0 : Math.min(port + retries, 65535)
+
+  for (let candidate = port; candidate <= lastPort; candidate++) {
+    const boundPort = await probePort(candidate, host)
+    if (boundPort !== undefined) return boundPort
+
+    consola.warn(`Port ${candidate} is already in use`)
+  }
+
+  throw new Error(
+    retries > 0 ?
+      `Ports ${port}-${lastPort} are all in use`
+    : `Port ${port} is already in use, use --port-retry or --port 0 to pick another one`,
+  )
+}
+
+/**
+ * The host to put in URLs for a server bound to `host`. Servers bound to
+ * every interface are reached on localhost, and IPv6 addresses get brackets.
+ */
+export function urlHost(host: string | undefined): string {
+  if (!host || host === "0.0.0.0" || host === "::") return "localhost"
+  return host.includes(":") ? `[${host}]` : host
+}
@@ -0,0 +1,53 @@
+// Upstream prompt caching is keyed by `prompt_cache_key`. Requests that
+// share a stable prefix (system prompt and tools) should share a key, so a
+// key is derived from that prefix when the client does not send one.
+
+import { createHash } from "node:crypto"
+
+import type { ChatCompletionsPayload } from "~/services/copilot/create-chat-completions"
+
+import { state } from "./state"
+
+export function hashCacheKey(prefix: unknown): string {
+  const digest = createHash("sha256")
+    .update(JSON.stringify(prefix))
+    .digest("hex")
+  return `gw-${digest.slice(0, 32)}`
+}
+
+/** Derived from the model, the leading system messages and the tools. */
+export function synthesizeCacheKey(
+  payload: ChatCompletionsPayload,
+): string | undefined {
+  const leading = payload.messages.findIndex(
+    (message) => message.role !== "system" && message.role !== "developer",
+  )
+  const system = payload.messages.slice(
+    0,
+    leading === -1 ? payload.messages.length : leading,
+  )
+  // Nothing stable to cache
+  if (system.length === 0 && !payload.tools?.length) return undefined
+
+  return hashCacheKey({
+    model: payload.model,
+    system: system.map((message) => message.content),
+    tools: payload.tools ?? [],
+  })
+}
+
+/**
+ * Keeps a client-supplied key. Otherwise uses `hint`, derived from explicit
+ * cache breakpoints such as Anthropic's `cache_control`, or synthesizes one
+ * when `--prompt-cache-key` is on.
+ */
+export function applyPromptCacheKey(
+  payload: ChatCompletionsPayload,
+  hint?: string,
+): ChatCompletionsPayload {
+  if (payload.prompt_cache_key) return payload
+
+  const key =
+    hint ?? (state.synthesizeCacheKey ? synthesizeCacheKey(payload) : undefined)
+  return key ? { ...payload, prompt_cache_key: key } : payload
+}
@@ -0,0 +1,148 @@
+// Holds back batch traffic once the month's premium requests are mostly
+// used, so what is left goes to interactive clients. Usage is polled from
+// the Copilot usage endpoint of the gateway's own account.
+
+import type { MiddlewareHandler } from "hono"
+
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { getCopilotUsage } from "~/services/github/get-copilot-usage"
+
+import { priorityOf } from "./scheduler"
+import { sendAlert } from "./slo-alerts"
+import { state } from "./state"
+
+export interface QuotaGuardConfig {
+  // Share of premium requests used, from 0 to 1, at which batch traffic stops
+  threshold: number
+  // Reject batch requests with a 429, or hold them until the guard lifts
+  action?: "reject" | "queue"
+  // How long a queued request waits before it is rejected after all
+  maxQueueSeconds?: number
+  intervalMinutes?: number
+}
+
+const DEFAULT_MAX_QUEUE_SECONDS = 300
+const DEFAULT_INTERVAL_MINUTES = 10
+
+export async function loadQuotaGuard(
+  filePath: string,
+): Promise<QuotaGuardConfig> {
+  return JSON.parse(await fs.readFile(filePath, "utf8")) as QuotaGuardConfig
+}
+
+const guard = {
+  engaged: false,
+  // Share of premium requests used at the last poll
+  used: undefined as number | undefined,
+  resetDate: undefined as string | undefined,
+  waiters: [] as Array<() => void>,
+}
+
+export function quotaGuardStatus() {
+  return {
+    engaged: guard.engaged,
+    used: guard.used,
+    resetDate: guard.resetDate,
+  }
+}
+
+function setEngaged(engaged: boolean): void {
+  if (guard.engaged === engaged) return
+  guard.engaged = engaged
+  const used = `${((guard.used ?? 0) * 100).toFixed(1)}%`
+  if (engaged) {
+    consola.warn(`Premium requests ${used} used, holding back batch traffic`)
+  } else {
+    consola.info(`Premium requests ${used} used, batch traffic resumes`)
+    for (const resume of guard.waiters.splice(0)) resume()
+  }
+  void sendAlert({
+    kind: "quota",
+    text:
+      engaged ?
+        `copilot-api: ${used} of premium requests used, batch traffic is paused until ${guard.resetDate ?? "the quota resets"}`
+      : `copilot-api: ${used} of premium requests used, batch traffic resumed`,
+    value: guard.used ?? 0,
+    threshold: state.quotaGuard?.threshold ?? 1,
+  })
+}
+
+/** Polls usage once and engages or lifts the guard. */
+export async function checkQuota(): Promise<void> {
+  const config = state.quotaGuard
+  if (!config) return
+
+  let used: number
+  try {
+    const usage = await getCopilotUsage()
+    const premium = usage.quota_snapshots.premium_interactions
+    used = premium.unlimited ? 0 : 1 - premium.percent_remaining / 100
+    guard.resetDate = usage.quota_reset_date
+  } catch (error) {
+    consola.warn("Quota guard could not read usage:", error)
+    return
+  }
+  guard.used = used
+  setEngaged(used >= config.threshold)
+}
+
+export function startQuotaGuard(): void {
+  const minutes = state.quotaGuard?.intervalMinutes ?? DEFAULT_INTERVAL_MINUTES
+  void checkQuota()
+  setInterval(() => void checkQuota(), minutes * 60_000).unref()
+}
+
+export function resetQuotaGuard(): void {
+  guard.engaged = false
+  guard.used = undefined
+  guard.resetDate = undefined
+  guard.waiters.length = 0
+}
+
+function waitForLift(timeoutMs: number): Promise<boolean> {
+  return new Promise((resolve) => {
+    const resume = () => {
+      clearTimeout(timer)
+      resolve(true)
+    }
+    const timer = setTimeout(() => {
+      guard.waiters.splice(guard.waiters.indexOf(resume), 1)
+      resolve(false)
+    }, timeoutMs)
+    guard.waiters.push(resume)
+  })
+}
+
+/**
+ * Rejects or holds batch requests while the guard is engaged. Interactive
+ * requests, including those of keys without a tier, always pass.
+ */
+export const quotaGuard: MiddlewareHandler = async (c, next) => {
+  const config = state.quotaGuard
+  if (!config || !guard.engaged || priorityOf(c) !== "batch") return next()
+
+  if (config.action === "queue") {
+    const seconds = config.maxQueueSeconds ?? DEFAULT_MAX_QUEUE_SECONDS
+    if (await waitForLift(seconds * 1000)) return next()
+  }
+
+  const resetAt = guard.resetDate ? Date.parse(guard.resetDate) : Number.NaN
+  if (!Number.isNaN(resetAt)) {
+    const seconds = Math.max(1, Math.ceil((resetAt - Date.now()) / 1000))
+    c.header("retry-after", String(seconds))
+  }
+  return c.json(
+    {
+      error: {
+        message:
+          "Batch traffic is paused because most of this month's premium requests are used. Interactive requests are still served.",
+        type: "requests",
+        param: null,
+        code: "quota_guard",
+      },
+    },
+    429,
+  )
+}
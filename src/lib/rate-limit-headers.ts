@@ -0,0 +1,100 @@
+import type { MiddlewareHandler } from "hono"
+
+import { AsyncLocalStorage } from "node:async_hooks"
+
+import { state } from "./state"
+import { clientKey, tokenBudgetStatus } from "./token-budget"
+
+const RATE_LIMIT_PREFIX = "x-ratelimit-"
+
+// Upstream rate limit headers seen while handling the current request
+const upstream = new AsyncLocalStorage<{ headers?: Headers }>()
+
+/**
+ * Formats a duration the way OpenAI does in `x-ratelimit-reset-*`,
+ * e.g. `120ms`, `1.5s` or `6m0s`.
+ */
+export function formatResetDuration(ms: number): string {
+  if (ms < 1000) return `${Math.max(0, Math.ceil(ms))}ms`
+  const totalSeconds = ms / 1000
+  if (totalSeconds < 60) return `${Number(totalSeconds.toFixed(3))}s`
+
+  const seconds = Math.round(totalSeconds)
+  const hours = Math.floor(seconds / 3600)
+  const minutes = Math.floor((seconds % 3600) / 60)
+  const rest = `${minutes}m${seconds % 60}s`
+  return hours > 0 ? `${hours}h${rest}` : rest
+}
+
+/**
+ * The `x-ratelimit-*` headers for the gateway's own limits: one request per
+ * `--rate-limit` window, and the client's `--token-rate-limit` bucket.
+ * Empty when neither is configured.
+ */
+export function gatewayRateLimitHeaders(
+  key: string,
+  now = Date.now(),
+): Record<string, string> {
+  const headers: Record<string, string> = {}
+
+  if (state.rateLimitSeconds !== undefined) {
+    const windowMs = state.rateLimitSeconds * 1000
+    const resetMs =
+      state.lastRequestTimestamp ?
+        Math.max(0, state.lastRequestTimestamp + windowMs - now)
+      : 0
+    headers["x-ratelimit-limit-requests"] = "1"
+    headers["x-ratelimit-remaining-requests"] = resetMs > 0 ? "0" : "1"
+    headers["x-ratelimit-reset-requests"] = formatResetDuration(resetMs)
+  }
+
+  if (state.tokensPerMinute !== undefined) {
+    const { remaining, resetMs } = tokenBudgetStatus(
+      key,
+      state.tokensPerMinute,
+      now,
+    )
+    headers["x-ratelimit-limit-tokens"] = String(state.tokensPerMinute)
+    headers["x-ratelimit-remaining-tokens"] = String(remaining)
+    headers["x-ratelimit-reset-tokens"] = formatResetDuration(resetMs)
+  }
+
+  return headers
+}
+
+/** Remembers Copilot's `x-ratelimit-*` headers for the current request. */
+export function captureUpstreamRateLimits(headers: Headers): void {
+  const store = upstream.getStore()
+  if (store) store.headers = headers
+}
+
+/**
+ * Merges the gateway's limits over the upstream ones, so each header reports
+ * whichever limit is actually enforced here. Upstream headers the gateway
+ * has no equivalent for, like `x-ratelimit-reset`, are passed through.
+ */
+export function mergeRateLimitHeaders(
+  upstreamHeaders: Headers | undefined,
+  gateway: Record<string, string>,
+): Record<string, string> {
+  const merged: Record<string, string> = {}
+  for (const [name, value] of upstreamHeaders ?? []) {
+    if (name.startsWith(RATE_LIMIT_PREFIX)) merged[name] = value
+  }
+  return { ...merged, ...gateway }
+}
+
+/**
+ * Adds standard `x-ratelimit-*` headers to every response so SDKs that
+ * honour them can throttle themselves before hitting a 429.
+ */
+export const rateLimitHeaders: MiddlewareHandler = async (c, next) => {
+  const store: { headers?: Headers } = {}
+  await upstream.run(store, next)
+
+  const headers = mergeRateLimitHeaders(
+    store.headers,
+    gatewayRateLimitHeaders(clientKey(c)),
+  )
+  for (const [name, value] of Object.entries(headers)) c.header(name, value)
+}
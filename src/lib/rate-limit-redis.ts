@@ -0,0 +1,61 @@
+import consola from "consola"
+
+const DEFAULT_KEY = "copilot-api:rate-limit"
+
+// Claims the next request slot for every replica at once. The Redis server
+// clock is used so replicas with skewed clocks still agree. Returns 0 when the
+// slot was claimed, otherwise the milliseconds until the next slot opens.
+const CLAIM_SLOT_SCRIPT = `
+local time = redis.call("TIME")
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local interval = tonumber(ARGV[1])
+local last = tonumber(redis.call("GET", KEYS[1]))
+if last and now - last < interval then
+  return interval - (now - last)
+end
+redis.call("SET", KEYS[1], now, "PX", interval)
+return 0
+`
+
+export interface DistributedRateLimiter {
+  /** Resolves to 0 when a request may proceed, otherwise the wait in ms. */
+  claim: (intervalMs: number) => Promise<number>
+}
+
+/**
+ * Connects to the Redis store shared by all gateway replicas.
+ * Uses the Redis client built into Bun, so it is unavailable under Node.
+ */
+export async function createRedisRateLimiter(
+  url: string,
+  key = DEFAULT_KEY,
+): Promise<DistributedRateLimiter> {
+  const { RedisClient } = await import("bun")
+
+  // Fail fast while disconnected so callers can fall back to local limits
+  const client = new RedisClient(url, {
+    connectionTimeout: 2000,
+    enableOfflineQueue: false,
+  })
+
+  try {
+    await client.connect()
+  } catch (error) {
+    consola.warn(
+      "Redis rate limit store is unreachable, using local limits until it recovers:",
+      (error as Error).message,
+    )
+  }
+
+  return {
+    async claim(intervalMs) {
+      const waitMs = await client.send("EVAL", [
+        CLAIM_SLOT_SCRIPT,
+        "1",
+        key,
+        String(intervalMs),
+      ])
+      return Number(waitMs)
+    },
+  }
+}
@@ -1,13 +1,126 @@
 import consola from "consola"
 
+import type { DistributedRateLimiter } from "./rate-limit-redis"
 import type { State } from "./state"
 
 import { HTTPError } from "./error"
+import { features, rustCore, type RateLimitDecision } from "./rust-core"
 import { sleep } from "./utils"
 
+// Below "global", so a global limit from setRateLimitDefaults also applies
+const NATIVE_RATE_LIMIT_KEY = "gateway"
+
+function rateLimitExceeded(waitTimeSeconds: number): never {
+  consola.warn(
+    `Rate limit exceeded. Need to wait ${waitTimeSeconds} more seconds.`,
+  )
+  throw new HTTPError(
+    "Rate limit exceeded",
+    Response.json(
+      { message: "Rate limit exceeded" },
+      { status: 429, headers: { "retry-after": String(waitTimeSeconds) } },
+    ),
+  )
+}
+
+let distributedStoreDown = false
+
+/**
+ * Enforces the limit across replicas through the shared store.
+ * Returns false when the store is unreachable so the caller can fall back
+ * to the local limit.
+ */
+async function checkDistributedRateLimit(
+  state: State,
+  limiter: DistributedRateLimiter,
+  rateLimitSeconds: number,
+): Promise<boolean> {
+  for (;;) {
+    let waitMs: number
+    try {
+      waitMs = await limiter.claim(rateLimitSeconds * 1000)
+    } catch (error) {
+      if (!distributedStoreDown) {
+        consola.warn(
+          "Redis rate limit store failed, falling back to local limits:",
+          (error as Error).message,
+        )
+      }
+      distributedStoreDown = true
+      return false
+    }
+
+    if (distributedStoreDown) {
+      consola.info("Redis rate limit store recovered")
+      distributedStoreDown = false
+    }
+    if (waitMs <= 0) return true
+
+    const waitTimeSeconds = Math.ceil(waitMs / 1000)
+    if (!state.rateLimitWait) rateLimitExceeded(waitTimeSeconds)
+
+    consola.warn(
+      `Rate limit reached. Waiting ${waitTimeSeconds} seconds before proceeding...`,
+    )
+    // Another replica may take the slot meanwhile, so claim again afterwards
+    await sleep(waitMs)
+  }
+}
+
+/**
+ * Enforces the limit with the native token bucket, which says exactly when
+ * the next request may go, so waiting takes one sleep instead of polling.
+ * Returns false when the native module is not available.
+ */
+async function checkNativeRateLimit(
+  state: State,
+  rateLimitSeconds: number,
+): Promise<boolean> {
+  for (;;) {
+    let decision: RateLimitDecision
+    try {
+      decision = await rustCore.checkRateLimitDetailed(
+        NATIVE_RATE_LIMIT_KEY,
+        rateLimitSeconds,
+        1,
+      )
+    } catch {
+      return false
+    }
+    if (decision.allowed) return true
+
+    const waitTimeSeconds = Math.ceil(decision.retryAfterMs / 1000)
+    if (!state.rateLimitWait) rateLimitExceeded(waitTimeSeconds)
+
+    consola.warn(
+      `Rate limit reached. Waiting ${waitTimeSeconds} seconds before proceeding...`,
+    )
+    // Other waiters may take the freed token first, so check again afterwards
+    await sleep(decision.retryAfterMs)
+  }
+}
+
 export async function checkRateLimit(state: State) {
   if (state.rateLimitSeconds === undefined) return
 
+  if (
+    state.rateLimitStore
+    && (await checkDistributedRateLimit(
+      state,
+      state.rateLimitStore,
+      state.rateLimitSeconds,
+    ))
+  ) {
+    return
+  }
+
+  if (
+    features.USE_RUST_RATE_LIMIT
+    && (await checkNativeRateLimit(state, state.rateLimitSeconds))
+  ) {
+    return
+  }
+
   const now = Date.now()
 
   if (!state.lastRequestTimestamp) {
@@ -24,15 +137,7 @@ export async function checkRateLimit(state: State) {
 
   const waitTimeSeconds = Math.ceil(state.rateLimitSeconds - elapsedSeconds)
 
-  if (!state.rateLimitWait) {
-    consola.warn(
-      `Rate limit exceeded. Need to wait ${waitTimeSeconds} more seconds.`,
-    )
-    throw new HTTPError(
-      "Rate limit exceeded",
-      Response.json({ message: "Rate limit exceeded" }, { status: 429 }),
-    )
-  }
+  if (!state.rateLimitWait) rateLimitExceeded(waitTimeSeconds)
 
   const waitTimeMs = waitTimeSeconds * 1000
   consola.warn(
@@ -0,0 +1,99 @@
+import consola from "consola"
+
+import type { StartupCheck } from "./env-config"
+
+import { HTTPError } from "./error"
+import { state } from "./state"
+import { setupCopilotToken } from "./token"
+import { cacheModels } from "./utils"
+
+export const PROXY_ENV_VARS = [
+  "HTTPS_PROXY",
+  "https_proxy",
+  "HTTP_PROXY",
+  "http_proxy",
+]
+
+/** What went wrong talking to GitHub or Copilot, and how to fix it. */
+export function describeUpstreamError(error: unknown): {
+  message: string
+  hint?: string
+} {
+  if (error instanceof HTTPError) {
+    const { status } = error.response
+    if (status === 401) {
+      return {
+        message: `${error.message} (401 Unauthorized)`,
+        hint: "The GitHub token is expired or revoked. Run `copilot-api auth` to log in again.",
+      }
+    }
+    if (status === 403 || status === 404) {
+      return {
+        message: `${error.message} (${status})`,
+        hint: "This GitHub account does not seem to have an active Copilot subscription.",
+      }
+    }
+    return { message: `${error.message} (${status})` }
+  }
+
+  const message = (error as Error).message
+  const proxy = PROXY_ENV_VARS.find((name) => process.env[name])
+  return {
+    message: `Network error: ${message}`,
+    hint:
+      proxy ?
+        `Requests are routed through ${proxy}=${process.env[proxy]}. Check that the proxy is reachable.`
+      : "Check your network connection, or set HTTPS_PROXY if you are behind a proxy.",
+  }
+}
+
+export class StartupCheckError extends Error {
+  hint?: string
+
+  constructor(message: string, hint?: string) {
+    super(message)
+    this.hint = hint
+  }
+}
+
+// The token exchange is skipped once it has succeeded
+async function connectUpstream(): Promise<void> {
+  if (!state.copilotToken) await setupCopilotToken()
+  await cacheModels()
+}
+
+/**
+ * Exchanges the GitHub token for a Copilot token and fetches the model list
+ * before the server binds, so unusable credentials are reported at startup
+ * instead of as a 401 on every request. With `warn`, the server starts
+ * anyway and the check is retried in the background until it succeeds.
+ * @throws {StartupCheckError} With `strict`, when either step fails.
+ */
+export async function checkUpstream(
+  mode: StartupCheck,
+  retryMs = 30_000,
+): Promise<boolean> {
+  try {
+    await connectUpstream()
+    return true
+  } catch (error) {
+    const { message, hint } = describeUpstreamError(error)
+    if (mode === "strict") throw new StartupCheckError(message, hint)
+
+    consola.warn(`Copilot is not reachable yet: ${message}`)
+    if (hint) consola.warn(hint)
+    const retry = setInterval(async () => {
+      try {
+        await connectUpstream()
+        clearInterval(retry)
+        consola.success("Copilot is now reachable")
+      } catch (retryError) {
+        consola.debug(
+          "Copilot still not reachable:",
+          describeUpstreamError(retryError).message,
+        )
+      }
+    }, retryMs)
+    return false
+  }
+}
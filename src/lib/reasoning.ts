@@ -0,0 +1,80 @@
+// Agent clients send `reasoning_effort` (or Anthropic `thinking`) with every
+// request, whatever the model. Copilot rejects it for models without
+// reasoning support, and some reasoning models accept only some levels.
+
+import type { AnthropicMessagesPayload } from "~/routes/messages/anthropic-types"
+import type {
+  ChatCompletionsPayload,
+  ReasoningEffort,
+} from "~/services/copilot/create-chat-completions"
+
+import { matchesModel } from "./model-policy"
+import { state } from "./state"
+
+const EFFORTS: Array<ReasoningEffort> = ["minimal", "low", "medium", "high"]
+
+// Used when the model list does not say which levels a model accepts
+const FALLBACK_EFFORTS: Array<{
+  models: Array<string>
+  efforts: Array<ReasoningEffort>
+}> = [
+  { models: ["o1*", "o3*", "o4*"], efforts: ["low", "medium", "high"] },
+  { models: ["gpt-5*"], efforts: ["minimal", "low", "medium", "high"] },
+]
+
+/** The `reasoning_effort` levels `model` accepts, empty if none. */
+export function supportedEfforts(model: string): Array<ReasoningEffort> {
+  const reported = state.models?.data.find((entry) => entry.id === model)
+    ?.capabilities.supports.reasoning_effort
+  if (reported) {
+    return EFFORTS.filter((effort) => reported.includes(effort))
+  }
+  return (
+    FALLBACK_EFFORTS.find((entry) => matchesModel(entry.models, model))
+      ?.efforts ?? []
+  )
+}
+
+/** Maps an Anthropic thinking budget onto the closest effort level. */
+export function effortForThinking(
+  thinking: AnthropicMessagesPayload["thinking"],
+): ReasoningEffort | undefined {
+  if (thinking?.type !== "enabled") return undefined
+  const budget = thinking.budget_tokens ?? 0
+  if (budget === 0) return "medium"
+  if (budget < 4096) return "low"
+  if (budget < 16_384) return "medium"
+  return "high"
+}
+
+/**
+ * Drops `reasoning_effort` for models without reasoning support, and moves
+ * unsupported levels to the nearest lower one the model accepts (or the
+ * lowest, if there is none below).
+ */
+export function normalizeReasoningEffort(payload: ChatCompletionsPayload): {
+  payload: ChatCompletionsPayload
+  adjustments: Array<string>
+} {
+  const requested = payload.reasoning_effort
+  if (!requested) return { payload, adjustments: [] }
+
+  const supported = supportedEfforts(payload.model)
+  if (supported.length === 0) {
+    return {
+      payload: { ...payload, reasoning_effort: undefined },
+      adjustments: ["stripped=reasoning_effort"],
+    }
+  }
+  if (supported.includes(requested)) return { payload, adjustments: [] }
+
+  const rank = EFFORTS.indexOf(requested)
+  const lower = supported.filter((effort) => EFFORTS.indexOf(effort) < rank)
+  const effort = lower.at(-1)
+  return {
+    payload: { ...payload, reasoning_effort: effort ?? supported[0] },
+    adjustments: [
+      effort ? "downgraded=reasoning_effort" : "raised=reasoning_effort",
+    ],
+  }
+}
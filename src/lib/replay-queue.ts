@@ -0,0 +1,81 @@
+// Parks requests that Copilot answered with 429 and replays them once the
+// advertised reset has passed, holding the client connection open instead
+// of failing. Meant for batch and agent clients that prefer slow to failed.
+
+import consola from "consola"
+
+import { sleep } from "./utils"
+
+const config = {
+  // Disabled while 0
+  maxWaitMs: 0,
+  capacity: 100,
+}
+
+let parked = 0
+
+export function configureReplayQueue(options: Partial<typeof config>): void {
+  config.maxWaitMs = options.maxWaitMs ?? config.maxWaitMs
+  config.capacity = options.capacity ?? config.capacity
+}
+
+export function replayQueueSize(): number {
+  return parked
+}
+
+// Used when the upstream does not say when to come back
+const FALLBACK_DELAY_MS = 1000
+
+/**
+ * Milliseconds until the rate limit resets, from `retry-after` (seconds or
+ * an HTTP date) or `x-ratelimit-reset` (epoch seconds).
+ */
+export function retryDelayMs(response: Response, now = Date.now()): number {
+  const retryAfter = response.headers.get("retry-after")
+  if (retryAfter) {
+    const seconds = Number(retryAfter)
+    if (Number.isFinite(seconds)) return Math.max(0, seconds * 1000)
+    const date = Date.parse(retryAfter)
+    if (!Number.isNaN(date)) return Math.max(0, date - now)
+  }
+
+  const reset = Number(response.headers.get("x-ratelimit-reset"))
+  if (reset > 0) return Math.max(0, reset * 1000 - now)
+
+  return FALLBACK_DELAY_MS
+}
+
+/**
+ * Sends the request and, while the upstream answers 429, waits for the reset
+ * and sends it again. Gives up and returns the 429 when the total wait would
+ * exceed the max wait or the queue is full.
+ */
+export async function sendWithReplay(
+  send: () => Promise<Response>,
+): Promise<Response> {
+  const startedAt = Date.now()
+
+  for (let attempt = 1; ; attempt++) {
+    const response = await send()
+    if (response.status !== 429 || config.maxWaitMs <= 0) return response
+
+    // Retry a little after the reset so the window has really rolled over
+    const delayMs = retryDelayMs(response) + 100 * attempt
+    if (Date.now() - startedAt + delayMs > config.maxWaitMs) return response
+    if (parked >= config.capacity) {
+      consola.warn("Replay queue is full, returning upstream 429")
+      return response
+    }
+
+    await response.body?.cancel()
+    consola.warn(
+      `Copilot rate limit hit, retrying in ${Math.ceil(delayMs / 1000)} seconds (attempt ${attempt})`,
+    )
+    parked++
+    try {
+      await sleep(delayMs)
+    } finally {
+      parked--
+    }
+  }
+}
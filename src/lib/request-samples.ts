@@ -0,0 +1,88 @@
+// Ring buffer of recent slow or failed requests, for debugging intermittent
+// upstream problems without full audit logging. Only summaries are kept,
+// never message content.
+
+export interface RequestSample {
+  time: string
+  method: string
+  route: string
+  status: number
+  durationMs: number
+  reason: "slow" | "error"
+  // Client address, looking through --trusted-proxies
+  ip?: string
+  model?: string
+  promptTokens?: number
+  completionTokens?: number
+  upstreamStatus?: number
+  error?: string
+}
+
+type SampleDetails = Pick<
+  RequestSample,
+  "model" | "promptTokens" | "completionTokens" | "upstreamStatus" | "error"
+>
+
+const config = {
+  capacity: 50,
+  slowMs: 10_000,
+}
+
+let samples: Array<RequestSample> = []
+// Written by handlers while the request is in flight
+const details = new WeakMap<Request, SampleDetails>()
+
+export function configureSampling(options: Partial<typeof config>): void {
+  config.capacity = options.capacity ?? config.capacity
+  config.slowMs = options.slowMs ?? config.slowMs
+  samples = samples.slice(samples.length - config.capacity)
+}
+
+/** Attaches details to the sample recorded when `request` completes. */
+export function annotateSample(request: Request, extra: SampleDetails): void {
+  details.set(request, { ...details.get(request), ...extra })
+}
+
+export function recordSample(
+  request: Request,
+  summary: Pick<
+    RequestSample,
+    "method" | "route" | "status" | "durationMs" | "ip"
+  >,
+): void {
+  if (config.capacity === 0) return
+
+  const extra = details.get(request)
+  const failed = summary.status >= 400 || extra?.error !== undefined
+  if (!failed && summary.durationMs < config.slowMs) return
+
+  samples.push({
+    time: new Date().toISOString(),
+    ...summary,
+    durationMs: Math.round(summary.durationMs),
+    reason: failed ? "error" : "slow",
+    // Error text can echo request content, so keep it short
+    ...extra,
+    error: extra?.error?.slice(0, 200),
+  })
+  if (samples.length > config.capacity) samples.shift()
+}
+
+/** Newest first. */
+export function getSamples(): Array<RequestSample> {
+  return samples.toReversed()
+}
+
+export function clearSamples(): void {
+  samples = []
+}
+
+/** Writes the buffer to stderr as JSON lines, oldest first. */
+export function dumpSamples(): void {
+  process.stderr.write(
+    `--- ${samples.length} slow or failed requests (slower than ${config.slowMs}ms) ---\n`,
+  )
+  for (const sample of samples) {
+    process.stderr.write(`${JSON.stringify(sample)}\n`)
+  }
+}
@@ -0,0 +1,210 @@
+import type { Context, MiddlewareHandler } from "hono"
+
+import { AsyncLocalStorage } from "node:async_hooks"
+import { createHash, createHmac, timingSafeEqual } from "node:crypto"
+import fs from "node:fs/promises"
+
+import { resolveConfigValue } from "./env-config"
+import { state } from "./state"
+
+export const SIGNATURE_HEADER = "x-signature"
+
+const DEFAULT_WINDOW_SECONDS = 300
+
+export interface SigningClient {
+  // `${VAR}` and `file:` references are resolved when the file is loaded
+  secret: string
+  // The API key the client's requests act as, for per-key policies and team
+  // mode. Defaults to `signed:<client>`.
+  apiKey?: string
+}
+
+export interface RequestSigning {
+  clients: Record<string, SigningClient>
+  // How far the timestamp may be from the gateway's clock
+  windowSeconds?: number
+  // Reject requests that are not signed instead of accepting bearer keys
+  required?: boolean
+}
+
+export async function loadRequestSigning(
+  filePath: string,
+): Promise<RequestSigning> {
+  const config = JSON.parse(
+    await fs.readFile(filePath, "utf8"),
+  ) as RequestSigning
+  for (const [name, client] of Object.entries(config.clients)) {
+    try {
+      client.secret = resolveConfigValue(client.secret)
+    } catch (error) {
+      throw new Error(`Signing client ${name}: ${(error as Error).message}`)
+    }
+  }
+  return config
+}
+
+/**
+ * The text a client signs: the unix timestamp in seconds, the method, the
+ * path with its query, and the hex SHA-256 of the body, joined by newlines.
+ */
+export function signingPayload(
+  timestamp: number,
+  method: string,
+  path: string,
+  body: ArrayBuffer | Uint8Array | string,
+): string {
+  const bodyHash = createHash("sha256")
+    .update(typeof body === "string" ? body : new Uint8Array(body))
+    .digest("hex")
+  return [timestamp, method.toUpperCase(), path, bodyHash].join("\n")
+}
+
+/** The `x-signature` header value for a request, as clients compute it. */
+export function signRequest(
+  client: string,
+  secret: string,
+  request: {
+    method: string
+    path: string
+    body?: ArrayBuffer | Uint8Array | string
+    timestamp?: number
+  },
+): string {
+  const timestamp = request.timestamp ?? Math.floor(Date.now() / 1000)
+  const signature = createHmac("sha256", secret)
+    .update(
+      signingPayload(
+        timestamp,
+        request.method,
+        request.path,
+        request.body ?? "",
+      ),
+    )
+    .digest("hex")
+  return `client=${client},t=${timestamp},v1=${signature}`
+}
+
+// The API key of the signed request being handled
+const signedKey = new AsyncLocalStorage<string>()
+
+export const signedApiKey = () => signedKey.getStore()
+
+// Signatures accepted within the replay window, by expiry. A timestamp may
+// be up to a window ahead of the clock, so they are kept for two windows.
+// Insertion order is expiry order as long as the window does not change.
+const seen = new Map<string, number>()
+
+function pruneSeen(now: number): void {
+  for (const [signature, expiresAt] of seen) {
+    if (expiresAt > now) break
+    seen.delete(signature)
+  }
+}
+
+export function clearSeenSignatures(): void {
+  seen.clear()
+}
+
+const unauthorized = (c: Context, message: string, code: string) =>
+  c.json({ error: { message, type: "invalid_request_error", code } }, 401)
+
+function parseSignature(header: string) {
+  const fields = new Map(
+    header.split(",").map((field) => {
+      const [name, ...value] = field.trim().split("=")
+      return [name, value.join("=")] as const
+    }),
+  )
+  const timestamp = Number(fields.get("t"))
+  const client = fields.get("client")
+  const signature = fields.get("v1")
+  // Exactly one spelling of each signature, so a replay can't pass as new by
+  // changing its case or appending characters Buffer.from ignores
+  if (
+    !client
+    || !signature
+    || !/^[0-9a-f]{64}$/.test(signature)
+    || !Number.isInteger(timestamp)
+  ) {
+    return undefined
+  }
+  return { client, timestamp, signature }
+}
+
+/**
+ * Verifies `x-signature` headers against the clients of `--signing-keys`.
+ * A valid signature must be within the replay window and not seen before;
+ * the request then acts as the client's API key. Unsigned requests pass
+ * through unless signing is required.
+ */
+export const verifySignature: MiddlewareHandler = async (c, next) => {
+  const config = state.requestSigning
+  if (!config) return next()
+
+  const header = c.req.header(SIGNATURE_HEADER)
+  if (!header) {
+    if (!config.required) return next()
+    return unauthorized(
+      c,
+      "This gateway only accepts signed requests, see the x-signature header.",
+      "signature_required",
+    )
+  }
+
+  const parsed = parseSignature(header)
+  const client =
+    parsed && Object.hasOwn(config.clients, parsed.client) ?
+      config.clients[parsed.client]
+    : undefined
+  if (!parsed || !client) {
+    return unauthorized(
+      c,
+      "Malformed signature or unknown client.",
+      "invalid_signature",
+    )
+  }
+
+  const windowSeconds = config.windowSeconds ?? DEFAULT_WINDOW_SECONDS
+  const now = Date.now()
+  if (Math.abs(now / 1000 - parsed.timestamp) > windowSeconds) {
+    return unauthorized(
+      c,
+      `The signature timestamp is more than ${windowSeconds} seconds from the gateway's clock.`,
+      "expired_signature",
+    )
+  }
+
+  const url = new URL(c.req.url)
+  // Cached by Hono, so routes can still read the body
+  const body = await c.req.arrayBuffer()
+  const expected = createHmac("sha256", client.secret)
+    .update(
+      signingPayload(
+        parsed.timestamp,
+        c.req.method,
+        url.pathname + url.search,
+        body,
+      ),
+    )
+    .digest()
+  if (!timingSafeEqual(Buffer.from(parsed.signature, "hex"), expected)) {
+    return unauthorized(
+      c,
+      "The signature does not match the request.",
+      "invalid_signature",
+    )
+  }
+
+  pruneSeen(now)
+  const signature = expected.toString("hex")
+  if (seen.has(signature)) {
+    return unauthorized(
+      c,
+      "This signed request was already received.",
+      "replayed_signature",
+    )
+  }
+  seen.set(signature, now + windowSeconds * 2000)
+
+  return signedKey.run(client.apiKey ?? `signed:${parsed.client}`, next)
+}
@@ -0,0 +1,125 @@
+// Tags attribute requests to projects or cost centres rather than to API
+// keys. They are read from the OpenAI `metadata` and `user` fields and from
+// the `x-gateway-tags` header, and are stored with usage records.
+
+import type { Context } from "hono"
+
+export const TAGS_HEADER = "x-gateway-tags"
+
+// The limits OpenAI puts on `metadata`
+const MAX_TAGS = 16
+const MAX_KEY_LENGTH = 64
+const MAX_VALUE_LENGTH = 512
+// Tags are chosen by clients, so only this many are totalled in /stats
+const MAX_COUNTED_TAGS = 1000
+
+export type RequestTags = Record<string, string>
+
+export interface TagTotals {
+  requests: number
+  prompt_tokens: number
+  completion_tokens: number
+}
+
+function addTag(tags: RequestTags, key: string, value: unknown): void {
+  if (typeof value !== "string" || !key || key.length > MAX_KEY_LENGTH) return
+  if (!(key in tags) && Object.keys(tags).length >= MAX_TAGS) return
+  tags[key] = value.slice(0, MAX_VALUE_LENGTH)
+}
+
+/** Parses `project=alpha, team=infra`; entries without a `=` are ignored. */
+export function parseTagHeader(header: string | undefined): RequestTags {
+  const tags: RequestTags = {}
+  for (const entry of header?.split(",") ?? []) {
+    const separator = entry.indexOf("=")
+    if (separator === -1) continue
+    addTag(
+      tags,
+      entry.slice(0, separator).trim(),
+      entry.slice(separator + 1).trim(),
+    )
+  }
+  return tags
+}
+
+/**
+ * The tags of a request body and the tags header. `user` becomes the `user`
+ * tag; the header wins over the body, as it is usually set by the gateway's
+ * operator rather than by the client.
+ */
+export function collectTags(
+  body: { metadata?: unknown; user?: unknown },
+  header: string | undefined,
+): RequestTags | undefined {
+  const tags: RequestTags = {}
+  if (body.metadata && typeof body.metadata === "object") {
+    for (const [key, value] of Object.entries(body.metadata)) {
+      addTag(tags, key, value)
+    }
+  }
+  addTag(tags, "user", body.user)
+  Object.assign(tags, parseTagHeader(header))
+  return Object.keys(tags).length > 0 ? tags : undefined
+}
+
+const requestTags = new WeakMap<Request, RequestTags>()
+
+export const tagsOf = (request: Request) => requestTags.get(request)
+
+export function tagRequest(
+  c: Context,
+  body: { metadata?: unknown; user?: unknown },
+): void {
+  const tags = collectTags(body, c.req.header(TAGS_HEADER))
+  if (tags) requestTags.set(c.req.raw, tags)
+}
+
+/** `key=value` matches that value, a bare `key` any value of the tag. */
+export function matchesTag(
+  tags: RequestTags | undefined,
+  filter: string,
+): boolean {
+  if (!tags) return false
+  const separator = filter.indexOf("=")
+  if (separator === -1) return filter in tags
+  return tags[filter.slice(0, separator)] === filter.slice(separator + 1)
+}
+
+// By `key=value`, since startup
+const totals = new Map<string, TagTotals>()
+
+export function countTaggedUsage(
+  tags: RequestTags | undefined,
+  usage?: { prompt_tokens?: number; completion_tokens?: number } | null,
+): void {
+  for (const [key, value] of Object.entries(tags ?? {})) {
+    const tag = `${key}=${value}`
+    let total = totals.get(tag)
+    if (!total) {
+      if (totals.size >= MAX_COUNTED_TAGS) continue
+      total = { requests: 0, prompt_tokens: 0, completion_tokens: 0 }
+      totals.set(tag, total)
+    }
+    total.requests++
+    total.prompt_tokens += usage?.prompt_tokens ?? 0
+    total.completion_tokens += usage?.completion_tokens ?? 0
+  }
+}
+
+/** Totals by `key=value`, only of the tags matching `filter` when given. */
+export function taggedUsage(filter?: string): Record<string, TagTotals> {
+  return Object.fromEntries(
+    [...totals].filter(([tag]) => {
+      if (filter === undefined) return true
+      const separator = tag.indexOf("=")
+      return matchesTag(
+        { [tag.slice(0, separator)]: tag.slice(separator + 1) },
+        filter,
+      )
+    }),
+  )
+}
+
+export function resetTaggedUsage(): void {
+  totals.clear()
+}
@@ -0,0 +1,89 @@
+// Removes, renames and adds response headers before they reach clients.
+// Hop-by-hop headers only describe one connection, and Copilot's internal
+// headers, passed through with upstream errors and rate limits, confuse some
+// clients; both are removed unless the policy turns the built-in list off.
+
+import type { MiddlewareHandler } from "hono"
+
+import fs from "node:fs/promises"
+
+import { state } from "./state"
+
+/**
+ * Header names are matched case-insensitively, and a trailing `*` matches a
+ * prefix. `strip` runs first, then `rename`, then `add`, which replaces a
+ * header of the same name.
+ */
+export interface HeaderPolicy {
+  strip?: Array<string>
+  rename?: Record<string, string>
+  add?: Record<string, string>
+  // Set to false to keep the headers of BUILTIN_STRIPPED_HEADERS
+  builtin?: boolean
+}
+
+export const BUILTIN_STRIPPED_HEADERS = [
+  // Hop-by-hop, RFC 9110 section 7.6.1
+  "connection",
+  "keep-alive",
+  "proxy-connection",
+  "proxy-authenticate",
+  "te",
+  "trailer",
+  "transfer-encoding",
+  "upgrade",
+  // Copilot and its Azure backends
+  "x-github-*",
+  "x-copilot-*",
+  "x-ms-*",
+  "azureml-*",
+  "apim-request-id",
+]
+
+export async function loadHeaderPolicy(filePath: string): Promise<HeaderPolicy> {
+  return JSON.parse(await fs.readFile(filePath, "utf8")) as HeaderPolicy
+}
+
+function matchesHeader(pattern: string, name: string): boolean {
+  const lower = pattern.toLowerCase()
+  return lower.endsWith("*") ?
+      name.startsWith(lower.slice(0, -1))
+    : name === lower
+}
+
+/** Applies the policy to `headers` in place. */
+export function applyHeaderPolicy(
+  headers: Headers,
+  policy: HeaderPolicy | undefined,
+): void {
+  const strip = [
+    ...(policy?.builtin === false ? [] : BUILTIN_STRIPPED_HEADERS),
+    ...(policy?.strip ?? []),
+  ]
+  // Collected first, as deleting while iterating skips entries
+  const names = [...headers.keys()]
+  for (const name of names) {
+    if (strip.some((pattern) => matchesHeader(pattern, name))) {
+      headers.delete(name)
+    }
+  }
+
+  for (const [from, to] of Object.entries(policy?.rename ?? {})) {
+    const value = headers.get(from)
+    if (value === null) continue
+    headers.delete(from)
+    headers.set(to, value)
+  }
+
+  for (const [name, value] of Object.entries(policy?.add ?? {})) {
+    headers.set(name, value)
+  }
+}
+
+/** Scrubs the headers of every response, once the handlers have set them. */
+export const scrubResponseHeaders: MiddlewareHandler = async (c, next) => {
+  await next()
+  // A WebSocket handshake needs its `connection` and `upgrade` headers
+  if (c.res.status === 101) return
+  applyHeaderPolicy(c.res.headers, state.headerPolicy)
+}
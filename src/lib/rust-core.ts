@@ -1,11 +1,15 @@
 // Node.js integration layer for Rust native module
-import type { Message } from "~/services/copilot/create-chat-completions"
+import type { AnthropicMessagesPayload, AnthropicResponse } from "~/routes/messages/anthropic-types"
+import type { ChatCompletionResponse, ChatCompletionsPayload, Message } from "~/services/copilot/create-chat-completions"
+
+import type * as Native from "../../native/index"
 
 // Feature flags for gradual rollout
 export const features = {
   USE_RUST_TOKENIZER: process.env.USE_RUST_TOKENIZER === 'true',
   USE_RUST_RATE_LIMIT: process.env.USE_RUST_RATE_LIMIT === 'true',
   USE_RUST_HTTP_CLIENT: process.env.USE_RUST_HTTP_CLIENT === 'true',
+  USE_RUST_TRANSLATION: process.env.USE_RUST_TRANSLATION === 'true',
   PERFORMANCE_MONITORING: process.env.PERF_MONITOR === 'true'
 }
 
@@ -26,22 +30,128 @@ export class PerformanceMonitor {
   }
 }
 
+// Errors thrown by the native module, so callers can branch on the kind:
+// `error instanceof RateLimitError` or `error.code === 'ERR_NATIVE_RATE_LIMIT'`
+export class NativeError extends Error {
+  constructor(message: string, readonly code: Native.NativeErrorCode, options?: ErrorOptions) {
+    super(message, options)
+    this.name = 'NativeError'
+  }
+}
+
+// Bad arguments or payloads
+export class ValidationError extends NativeError {
+  constructor(message: string, options?: ErrorOptions) {
+    super(message, 'ERR_NATIVE_VALIDATION', options)
+    this.name = 'ValidationError'
+  }
+}
+
+// Copilot could not be reached or answered with an error, after retries
+export class UpstreamError extends NativeError {
+  constructor(message: string, options?: ErrorOptions) {
+    super(message, 'ERR_NATIVE_UPSTREAM', options)
+    this.name = 'UpstreamError'
+  }
+}
+
+export class AuthError extends NativeError {
+  constructor(message: string, options?: ErrorOptions) {
+    super(message, 'ERR_NATIVE_AUTH', options)
+    this.name = 'AuthError'
+  }
+}
+
+// Rate limiter misconfiguration; a refused request is not an error
+export class RateLimitError extends NativeError {
+  constructor(message: string, options?: ErrorOptions) {
+    super(message, 'ERR_NATIVE_RATE_LIMIT', options)
+    this.name = 'RateLimitError'
+  }
+}
+
+// A bug in the native module, such as a panic. The process keeps running.
+export class InternalError extends NativeError {
+  constructor(message: string, options?: ErrorOptions) {
+    super(message, 'ERR_NATIVE_INTERNAL', options)
+    this.name = 'InternalError'
+  }
+}
+
+const nativeErrorClasses: Record<Native.NativeErrorCode, new (message: string, options?: ErrorOptions) => NativeError> = {
+  ERR_NATIVE_VALIDATION: ValidationError,
+  ERR_NATIVE_UPSTREAM: UpstreamError,
+  ERR_NATIVE_AUTH: AuthError,
+  ERR_NATIVE_RATE_LIMIT: RateLimitError,
+  ERR_NATIVE_INTERNAL: InternalError,
+}
+
+// Neon turns a panic on the calling thread into an Error with this prefix
+const NEON_PANIC_PREFIX = 'internal error in Neon module: '
+
+// Other errors, such as argument TypeErrors and AbortError, pass through unchanged
+export function toNativeError(error: unknown): unknown {
+  if (!(error instanceof Error) || error instanceof NativeError) return error
+
+  const code = (error as { code?: unknown }).code
+  if (typeof code === 'string' && Object.hasOwn(nativeErrorClasses, code)) {
+    return new nativeErrorClasses[code as Native.NativeErrorCode](error.message, { cause: error })
+  }
+  if (error.message.startsWith(NEON_PANIC_PREFIX)) {
+    return new InternalError(`panic in native module: ${error.message.slice(NEON_PANIC_PREFIX.length)}`, { cause: error })
+  }
+  return error
+}
+
+const rethrowNativeError = (error: unknown): never => {
+  throw toNativeError(error)
+}
+
+// Wraps every export so errors, including rejections of returned promises and
+// request handles, surface as NativeError subclasses
+function withNativeErrors(module: NativeModule): NativeModule {
+  const wrapped: Record<string, unknown> = {}
+  for (const [name, value] of Object.entries(module)) {
+    if (typeof value !== 'function') {
+      wrapped[name] = value
+      continue
+    }
+    wrapped[name] = (...args: Array<unknown>) => {
+      let result: any
+      try {
+        result = value(...args)
+      } catch (error) {
+        rethrowNativeError(error)
+      }
+      if (result instanceof Promise) return result.catch(rethrowNativeError)
+      if (result?.response instanceof Promise) {
+        return { ...result, response: result.response.catch(rethrowNativeError) }
+      }
+      return result
+    }
+  }
+  return wrapped as NativeModule
+}
+
 // Lazy load the native module to handle cases where it's not available
-let nativeModule: any = null
+type NativeModule = typeof Native
+let nativeModule: NativeModule | false | null = null
 
-function loadNativeModule() {
+function loadNativeModule(): NativeModule | null {
   if (nativeModule === null) {
     try {
       // Load the native module (try different possible paths)
+      let loaded: NativeModule
       try {
-        nativeModule = require('../../native/index.node')
+        loaded = require('../../native/index.node')
       } catch {
         try {
-          nativeModule = require('../../native/target/release/copilot_api_native.node')
+          loaded = require('../../native/target/release/copilot_api_native.node')
         } catch {
-          nativeModule = require('../../native/target/release/libcopilot_api_native.so')
+          loaded = require('../../native/target/release/libcopilot_api_native.so')
         }
       }
+      nativeModule = withNativeErrors(loaded)
     } catch (error) {
       console.warn('Failed to load Rust native module:', error)
       nativeModule = false // Mark as failed to avoid retrying
@@ -50,16 +160,70 @@ function loadNativeModule() {
   return nativeModule || null
 }
 
+// Shapes shared with the native module, see native/index.d.ts
+export type RetryOptions = Native.RetryPolicy
+export type HttpOptions = Native.HttpConfig
+export type SseEvent = Native.SseEvent
+export type NativeRequestHandle = Native.RequestHandle
+export type PluginLimits = Native.PluginLimits
+export type PluginHook = Native.PluginHook
+export type PluginInfo = Native.PluginInfo
+export type RateLimitDecision = Native.RateLimitDecision
+export type NativeMetrics = Native.NativeMetrics
+export type NativeErrorCode = Native.NativeErrorCode
+// UTF-8 JSON, parsed by the native module without a JSON.stringify round trip
+export type JsonBytes = Native.JsonBytes
+
+// Where native API calls are sent; the JS layer owns the Copilot token and headers
+// Responses are always text here; Buffer responses need the native module directly
+export interface NativeRequestOptions extends Omit<Native.RequestOptions, 'responseType'> {
+  // Aborting the signal cancels the in-flight Rust request
+  signal?: AbortSignal
+}
+
+export interface SseTransformer {
+  push(chunk: string | Uint8Array): Array<SseEvent>
+  flush(): Array<SseEvent>
+}
+
+function startNativeRequest(
+  native: NativeModule,
+  operation: string,
+  label: string,
+  start: (payload: object | JsonBytes, options: Native.RequestOptions) => NativeRequestHandle,
+  payload: object | JsonBytes,
+  { signal, ...options }: NativeRequestOptions
+): NativeRequestHandle {
+  const timer = PerformanceMonitor.startTimer(operation)
+  signal?.throwIfAborted()
+
+  const handle = start(payload, options)
+  const cancel = () => native.cancelRequest(handle.id)
+  signal?.addEventListener('abort', cancel, { once: true })
+
+  const response = handle.response
+    .catch((error) => {
+      if (error?.name !== 'AbortError') console.warn(`Rust ${label} failed:`, error)
+      throw error
+    })
+    .finally(() => {
+      timer?.end()
+      signal?.removeEventListener('abort', cancel)
+    })
+
+  return { id: handle.id, response }
+}
+
 // Rust core interface
 export const rustCore = {
-  async getTokenCount(messages: Array<Message>) {
+  async getTokenCount(messages: Array<Message> | JsonBytes): Promise<Native.TokenCount> {
     const timer = PerformanceMonitor.startTimer('rust_tokenizer')
     
     try {
       const native = loadNativeModule()
       if (!native) throw new Error('Native module not available')
       
-      const result = native.getTokenCount(JSON.stringify(messages))
+      const result = native.getTokenCount(messages)
       timer?.end()
       return result
     } catch (error) {
@@ -69,7 +233,22 @@ export const rustCore = {
     }
   },
 
-  async checkRateLimit(key: string, intervalSecs: number, burstCapacity: number = 5): Promise<boolean> {
+  getTokenCacheStats(): Native.TokenCacheStats {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.getTokenCacheStats()
+  },
+
+  getNativeMetrics(): Native.NativeMetrics {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.getNativeMetrics()
+  },
+
+  // Interval and burst capacity default to the setRateLimitDefaults entry matching the key
+  async checkRateLimit(key: string, intervalSecs?: number, burstCapacity?: number): Promise<boolean> {
     const timer = PerformanceMonitor.startTimer('rust_rate_limit')
     
     try {
@@ -86,7 +265,31 @@ export const rustCore = {
     }
   },
 
-  async getRateLimitStats(): Promise<{activeLimiters: number}> {
+  async checkRateLimitDetailed(key: string, intervalSecs?: number, burstCapacity?: number): Promise<Native.RateLimitDecision> {
+    const timer = PerformanceMonitor.startTimer('rust_rate_limit')
+
+    try {
+      const native = loadNativeModule()
+      if (!native) throw new Error('Native module not available')
+
+      const result = native.checkRateLimitDetailed(key, intervalSecs, burstCapacity)
+      timer?.end()
+      return result
+    } catch (error) {
+      timer?.end()
+      console.warn('Rust rate limiter failed, falling back to JS:', error)
+      throw error
+    }
+  },
+
+  setRateLimitDefaults(config: Record<string, Native.LimitConfig>): void {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    native.setRateLimitDefaults(config)
+  },
+
+  async getRateLimitStats(): Promise<Native.RateLimitStats> {
     const timer = PerformanceMonitor.startTimer('rust_rate_limit_stats')
     
     try {
@@ -103,14 +306,15 @@ export const rustCore = {
     }
   },
 
-  async resetRateLimit(key: string): Promise<boolean> {
+  // Accepts an exact key or a glob such as 'user:*'; resolves to the number of limiters removed
+  async resetRateLimit(pattern: string): Promise<number> {
     const timer = PerformanceMonitor.startTimer('rust_rate_limit_reset')
     
     try {
       const native = loadNativeModule()
       if (!native) throw new Error('Native module not available')
       
-      const result = native.resetRateLimit(key)
+      const result = native.resetRateLimit(pattern)
       timer?.end()
       return result
     } catch (error) {
@@ -120,14 +324,45 @@ export const rustCore = {
     }
   },
 
-  async validatePayload(payload: any): Promise<boolean> {
+  async resetAllRateLimits(): Promise<number> {
+    const timer = PerformanceMonitor.startTimer('rust_rate_limit_reset_all')
+    
+    try {
+      const native = loadNativeModule()
+      if (!native) throw new Error('Native module not available')
+      
+      const result = native.resetAllRateLimits()
+      timer?.end()
+      return result
+    } catch (error) {
+      timer?.end()
+      console.warn('Rust rate limit reset failed:', error)
+      throw error
+    }
+  },
+
+  startRateLimitJanitor(intervalSecs: number, maxIdleSecs?: number, onSweep?: (reclaimed: number) => void): boolean {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.startRateLimitJanitor(intervalSecs, maxIdleSecs, onSweep)
+  },
+
+  stopRateLimitJanitor(): boolean {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.stopRateLimitJanitor()
+  },
+
+  async validatePayload(payload: unknown): Promise<boolean> {
     const timer = PerformanceMonitor.startTimer('rust_validation')
     
     try {
       const native = loadNativeModule()
       if (!native) throw new Error('Native module not available')
       
-      const result = native.validatePayload(JSON.stringify(payload))
+      const result = native.validatePayload(payload)
       timer?.end()
       return result
     } catch (error) {
@@ -137,14 +372,14 @@ export const rustCore = {
     }
   },
 
-  async validatePayloadDetailed(payload: any): Promise<{valid: boolean, error?: string, contentType?: string}> {
+  async validatePayloadDetailed(payload: unknown): Promise<Native.ValidationResult> {
     const timer = PerformanceMonitor.startTimer('rust_validation_detailed')
     
     try {
       const native = loadNativeModule()
       if (!native) throw new Error('Native module not available')
       
-      const result = native.validatePayloadDetailed(JSON.stringify(payload))
+      const result = native.validatePayloadDetailed(payload)
       timer?.end()
       return result
     } catch (error) {
@@ -154,17 +389,139 @@ export const rustCore = {
     }
   },
 
-  // Placeholder functions for Phase 3 implementation
-  async createChatCompletions(payload: any) {
-    console.warn('createChatCompletions not yet implemented in Rust')
-    return { placeholder: 'not_implemented' }
+  // Repeat validations of the same payload are answered from the cache; 0 turns it off
+  configureValidationCache(capacity: number): Native.ValidationCacheStats {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.configureValidationCache(capacity)
   },
 
-  async createEmbeddings(payload: any) {
-    console.warn('createEmbeddings not yet implemented in Rust')
-    return { placeholder: 'not_implemented' }
+  clearValidationCache(): number {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.clearValidationCache()
+  },
+
+  getValidationCacheStats(): Native.ValidationCacheStats {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.getValidationCacheStats()
+  },
+
+  async translateAnthropicToOpenAI(payload: AnthropicMessagesPayload): Promise<ChatCompletionsPayload> {
+    const timer = PerformanceMonitor.startTimer('rust_translate_anthropic_to_openai')
+    
+    try {
+      const native = loadNativeModule()
+      if (!native) throw new Error('Native module not available')
+      
+      const result = native.translateAnthropicToOpenAI(payload) as ChatCompletionsPayload
+      timer?.end()
+      return result
+    } catch (error) {
+      timer?.end()
+      console.warn('Rust Anthropic to OpenAI translation failed:', error)
+      throw error
+    }
   },
 
+  async translateOpenAIToAnthropic(response: ChatCompletionResponse): Promise<AnthropicResponse> {
+    const timer = PerformanceMonitor.startTimer('rust_translate_openai_to_anthropic')
+    
+    try {
+      const native = loadNativeModule()
+      if (!native) throw new Error('Native module not available')
+      
+      const result = native.translateOpenAIToAnthropic(response) as AnthropicResponse
+      timer?.end()
+      return result
+    } catch (error) {
+      timer?.end()
+      console.warn('Rust OpenAI to Anthropic translation failed:', error)
+      throw error
+    }
+  },
+
+  createSseTransformer(): SseTransformer {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    const handle = native.createSseTransformer()
+    return {
+      push(chunk) {
+        const timer = PerformanceMonitor.startTimer('rust_sse_push')
+        const events = native.pushSseChunk(handle, chunk)
+        timer?.end()
+        return events
+      },
+      flush() {
+        return native.flushSseTransformer(handle)
+      }
+    }
+  },
+
+  withRetry(options: Partial<RetryOptions>): RetryOptions {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.withRetry(options)
+  },
+
+  createChatCompletions(payload: ChatCompletionsPayload | JsonBytes, options: NativeRequestOptions): NativeRequestHandle {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return startNativeRequest(native, 'rust_chat_completions', 'chat completions', native.createChatCompletions, payload, options)
+  },
+
+  createEmbeddings(payload: object | JsonBytes, options: NativeRequestOptions): NativeRequestHandle {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return startNativeRequest(native, 'rust_embeddings', 'embeddings', native.createEmbeddings, payload, options)
+  },
+
+  configureHttp(options: Partial<HttpOptions>): HttpOptions {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.configureHttp(options)
+  },
+
+  cancelRequest(id: string): boolean {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.cancelRequest(id)
+  },
+
+  // Only when the native module is built with the `plugins` feature
+  pluginsAvailable(): boolean {
+    const native = loadNativeModule()
+    return native !== null && 'loadPlugin' in native
+  },
+
+  loadPlugin(path: string, limits: Partial<PluginLimits> = {}): PluginInfo {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+    if (!this.pluginsAvailable()) {
+      throw new Error('Native module was built without plugin support, rebuild it with `bun run build:native:plugins`')
+    }
+
+    return native.loadPlugin(path, limits)
+  },
+
+  runPlugin(id: number, hook: PluginHook, input: string): string | null {
+    const native = loadNativeModule()
+    if (!native) throw new Error('Native module not available')
+
+    return native.runPlugin(id, hook, input)
+  },
+
+  // Placeholder functions for Phase 3 implementation
   async getModels() {
     console.warn('getModels not yet implemented in Rust')
     return { placeholder: 'not_implemented' }
@@ -0,0 +1,143 @@
+// Priority-aware concurrency limit in front of the forwarding routes. When
+// all slots are busy, waiting interactive requests are let through before
+// batch ones. Slots are held until the response body, including streams,
+// has been fully sent.
+
+import type { Context, MiddlewareHandler } from "hono"
+
+import fs from "node:fs/promises"
+
+import { apiKeyOf } from "./api-key"
+
+export type Priority = "interactive" | "batch"
+
+const PRIORITY_ORDER: Array<Priority> = ["interactive", "batch"]
+
+const config = {
+  // No limit while undefined
+  maxConcurrent: undefined as number | undefined,
+  // API key -> priority; unknown keys are interactive
+  keys: new Map<string, Priority>(),
+}
+
+let active = 0
+const waiting: Record<Priority, Array<() => void>> = {
+  interactive: [],
+  batch: [],
+}
+
+export function configureScheduler(options: {
+  maxConcurrent?: number
+  keys?: Map<string, Priority>
+}): void {
+  config.maxConcurrent = options.maxConcurrent
+  config.keys = options.keys ?? config.keys
+}
+
+/**
+ * Reads a JSON file listing the API keys of each tier, e.g.
+ * `{ "batch": ["key-1"], "interactive": ["key-2"] }`.
+ */
+export async function loadPriorityKeys(
+  filePath: string,
+): Promise<Map<string, Priority>> {
+  const tiers = JSON.parse(await fs.readFile(filePath, "utf8")) as Partial<
+    Record<Priority, Array<string>>
+  >
+  const keys = new Map<string, Priority>()
+  for (const priority of PRIORITY_ORDER) {
+    for (const key of tiers[priority] ?? []) keys.set(key, priority)
+  }
+  return keys
+}
+
+export function priorityOf(c: Context): Priority {
+  const key = apiKeyOf(c)
+  return (key && config.keys.get(key)) || "interactive"
+}
+
+export function schedulerStats() {
+  return {
+    active,
+    waiting: {
+      interactive: waiting.interactive.length,
+      batch: waiting.batch.length,
+    },
+  }
+}
+
+/** Resolves with a release function once a slot is free. */
+export async function acquireSlot(priority: Priority): Promise<() => void> {
+  let released = false
+  const release = () => {
+    if (released) return
+    released = true
+    // Hand the slot straight to the next waiter, highest priority first
+    const next = PRIORITY_ORDER.map((p) => waiting[p]).find(
+      (queue) => queue.length > 0,
+    )
+    if (next) next.shift()?.()
+    else active--
+  }
+
+  if (config.maxConcurrent === undefined || active < config.maxConcurrent) {
+    active++
+    return release
+  }
+
+  await new Promise<void>((resolve) => waiting[priority].push(resolve))
+  return release
+}
+
+/** `response` with `release` called once its body has been sent or dropped. */
+export function releaseWhenDone(
+  response: Response,
+  release: () => void,
+): Response {
+  if (!response.body) {
+    release()
+    return response
+  }
+
+  const reader = response.body.getReader()
+  const body = new ReadableStream<Uint8Array>({
+    async pull(controller) {
+      try {
+        const { done, value } = await reader.read()
+        if (done) {
+          release()
+          controller.close()
+          return
+        }
+        controller.enqueue(value)
+      } catch (error) {
+        release()
+        controller.error(error)
+      }
+    },
+    // Client went away mid-stream
+    async cancel(reason) {
+      release()
+      await reader.cancel(reason)
+    },
+  })
+
+  return new Response(body, {
+    status: response.status,
+    statusText: response.statusText,
+    headers: response.headers,
+  })
+}
+
+export const scheduleByPriority: MiddlewareHandler = async (c, next) => {
+  if (config.maxConcurrent === undefined) return next()
+
+  const release = await acquireSlot(priorityOf(c))
+  try {
+    await next()
+  } catch (error) {
+    release()
+    throw error
+  }
+  c.res = releaseWhenDone(c.res, release)
+}
@@ -0,0 +1,98 @@
+import type { Context } from "hono"
+
+import consola from "consola"
+import fs from "node:fs/promises"
+import path from "node:path"
+
+import type { ChatCompletionsPayload } from "~/services/copilot/create-chat-completions"
+
+import { apiKeyOf, keyId } from "./api-key"
+import { PATHS } from "./paths"
+import { state } from "./state"
+
+export const SESSION_HEADER = "x-session-id"
+
+export interface SessionTurn {
+  time: string
+  endpoint: string
+  model: string
+  // The OpenAI-shaped payload sent upstream, so any turn can be replayed
+  request: ChatCompletionsPayload
+  // What the client received; streams are assembled
+  response: unknown
+}
+
+// Ids become file names
+const isValidSessionId = (id: string) => /^[\w.-]{1,128}$/.test(id)
+
+/**
+ * Whose sessions the request can see: in team mode, the key id of the
+ * caller's API key, since session ids come from clients and are easy to
+ * guess. Outside team mode everyone shares one namespace.
+ */
+export const sessionOwner = (c: Context): string | undefined =>
+  state.teamStore ? keyId(apiKeyOf(c)) : undefined
+
+const sessionDir = (owner: string | undefined) =>
+  owner === undefined ?
+    PATHS.SESSIONS_DIR
+  : path.join(PATHS.SESSIONS_DIR, owner)
+
+const sessionFile = (id: string, owner: string | undefined) =>
+  path.join(sessionDir(owner), `${id}.jsonl`)
+
+/** The client's session id, or undefined when absent or invalid. */
+export function sessionIdOf(c: Context): string | undefined {
+  const id = c.req.header(SESSION_HEADER)
+  if (id === undefined) return undefined
+  if (!isValidSessionId(id)) {
+    consola.warn(`Ignoring invalid ${SESSION_HEADER} header`)
+    return undefined
+  }
+  return id
+}
+
+/**
+ * Appends a turn to the session named by the request's `x-session-id`
+ * header. Does nothing unless `--sessions` is on. Write failures are logged,
+ * never thrown.
+ */
+export async function recordSessionTurn(
+  c: Context,
+  turn: Omit<SessionTurn, "time">,
+): Promise<void> {
+  if (!state.sessions) return
+  const id = sessionIdOf(c)
+  if (!id) return
+
+  const owner = sessionOwner(c)
+  const line = JSON.stringify({ time: new Date().toISOString(), ...turn })
+  try {
+    await fs.mkdir(sessionDir(owner), { recursive: true, mode: 0o700 })
+    await fs.appendFile(sessionFile(id, owner), `${line}\n`, { mode: 0o600 })
+  } catch (error) {
+    consola.warn("Failed to write session turn:", (error as Error).message)
+  }
+}
+
+/**
+ * Turns in the order they happened, or undefined for sessions `owner` has
+ * not recorded.
+ */
+export async function readSession(
+  id: string,
+  owner?: string,
+): Promise<Array<SessionTurn> | undefined> {
+  if (!isValidSessionId(id)) return undefined
+
+  let content: string
+  try {
+    content = await fs.readFile(sessionFile(id, owner), "utf8")
+  } catch {
+    return undefined
+  }
+  return content
+    .split("\n")
+    .filter(Boolean)
+    .map((line) => JSON.parse(line) as SessionTurn)
+}
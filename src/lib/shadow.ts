@@ -0,0 +1,165 @@
+// Mirrors a share of chat completions to a second OpenAI-compatible upstream,
+// such as a new model or a local LLM, and records both answers side by side
+// for offline comparison. The client only ever sees the primary response.
+
+import consola from "consola"
+import fs from "node:fs/promises"
+import path from "node:path"
+
+import type {
+  ChatCompletionResponse,
+  ChatCompletionsPayload,
+  Usage,
+} from "~/services/copilot/create-chat-completions"
+
+import { redactValue } from "./audit"
+import { resolveConfigValue } from "./env-config"
+import { matchesModel } from "./model-policy"
+import { PATHS } from "./paths"
+import { state } from "./state"
+
+export interface ShadowConfig {
+  // Base URL of an OpenAI-compatible API, e.g. http://localhost:11434/v1
+  url: string
+  // `${VAR}` and `file:` references are resolved when the file is loaded
+  apiKey?: string
+  // Model to ask the shadow upstream for, default the request's model
+  model?: string
+  // Share of requests to mirror, from 0 to 100
+  percent: number
+  // Only mirror requests for these models; a trailing `*` matches a prefix
+  models?: Array<string>
+  timeoutSeconds?: number
+}
+
+export interface ShadowOutput {
+  content: string | null
+  tool_calls?: unknown
+  usage?: Usage
+  durationMs: number
+}
+
+export interface ShadowRecord {
+  time: string
+  model: string
+  shadowModel: string
+  request: ChatCompletionsPayload
+  primary: ShadowOutput
+  shadow: ShadowOutput | { error: string; durationMs: number }
+}
+
+const DEFAULT_TIMEOUT_SECONDS = 120
+
+export async function loadShadowConfig(
+  filePath: string,
+): Promise<ShadowConfig> {
+  const config = JSON.parse(
+    await fs.readFile(filePath, "utf8"),
+  ) as ShadowConfig
+  if (config.apiKey) config.apiKey = resolveConfigValue(config.apiKey)
+  return config
+}
+
+export function shouldMirror(
+  config: ShadowConfig,
+  model: string,
+  random: () => number = Math.random,
+): boolean {
+  if (config.models && !matchesModel(config.models, model)) return false
+  return random() * 100 < config.percent
+}
+
+/** The answer of a non-streaming response, in the shape of a transcript. */
+export function outputOf(
+  response: ChatCompletionResponse,
+): Pick<ShadowOutput, "content" | "tool_calls" | "usage"> {
+  const message = response.choices.at(0)?.message
+  return {
+    content: message?.content ?? null,
+    tool_calls: message?.tool_calls,
+    usage: response.usage,
+  }
+}
+
+async function askShadow(
+  config: ShadowConfig,
+  payload: ChatCompletionsPayload,
+): Promise<ShadowRecord["shadow"]> {
+  const started = performance.now()
+  const durationMs = () => Math.round(performance.now() - started)
+  try {
+    const response = await fetch(
+      `${config.url.replace(/\/$/, "")}/chat/completions`,
+      {
+        method: "POST",
+        headers: {
+          "content-type": "application/json",
+          ...(config.apiKey && { authorization: `Bearer ${config.apiKey}` }),
+        },
+        // One complete answer is easier to compare than a stream
+        body: JSON.stringify({
+          ...payload,
+          model: config.model ?? payload.model,
+          stream: false,
+          stream_options: undefined,
+        }),
+        signal: AbortSignal.timeout(
+          (config.timeoutSeconds ?? DEFAULT_TIMEOUT_SECONDS) * 1000,
+        ),
+      },
+    )
+    if (!response.ok) {
+      return {
+        error: `${response.status} ${await response.text()}`,
+        durationMs: durationMs(),
+      }
+    }
+    const body = (await response.json()) as ChatCompletionResponse
+    return { ...outputOf(body), durationMs: durationMs() }
+  } catch (error) {
+    return { error: (error as Error).message, durationMs: durationMs() }
+  }
+}
+
+const shadowFile = (day: string) => path.join(PATHS.SHADOW_DIR, `${day}.jsonl`)
+
+/**
+ * Sends the request to the shadow upstream when it is picked for mirroring,
+ * without waiting for it. Call the returned function with the primary
+ * answer once it is complete; the record is written when both are done.
+ */
+export function mirrorRequest(
+  payload: ChatCompletionsPayload,
+): ((primary: Omit<ShadowOutput, "durationMs">) => void) | undefined {
+  const config = state.shadow
+  if (!config || !shouldMirror(config, payload.model)) return undefined
+
+  const started = performance.now()
+  const shadow = askShadow(config, payload)
+  return (primary) => {
+    const durationMs = Math.round(performance.now() - started)
+    void shadow.then((result) =>
+      writeShadowRecord({
+        time: new Date().toISOString(),
+        model: payload.model,
+        shadowModel: config.model ?? payload.model,
+        request: payload,
+        primary: { ...primary, durationMs },
+        shadow: result,
+      }),
+    )
+  }
+}
+
+export async function writeShadowRecord(record: ShadowRecord): Promise<void> {
+  try {
+    await fs.mkdir(PATHS.SHADOW_DIR, { recursive: true, mode: 0o700 })
+    await fs.appendFile(
+      shadowFile(record.time.slice(0, 10)),
+      `${JSON.stringify(redactValue(record))}\n`,
+      { mode: 0o600 },
+    )
+  } catch (error) {
+    consola.warn("Failed to write shadow record:", (error as Error).message)
+  }
+}
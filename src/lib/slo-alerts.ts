@@ -0,0 +1,163 @@
+// Posts an alert to a webhook when the error rate or the time to first token
+// over a sliding window exceeds its threshold. Payloads carry a `text` field,
+// so Slack incoming webhooks display them as is.
+
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { resolveConfigValue } from "./env-config"
+import { state } from "./state"
+
+export interface SloConfig {
+  // `${VAR}` and `file:` references are resolved when the file is loaded
+  webhook: string
+  windowSeconds?: number
+  // Minimum time between two alerts of the same kind
+  cooldownSeconds?: number
+  // Share of requests answered with a 5xx, from 0 to 1
+  errorRate?: number
+  // 95th percentile of the time to first token of streamed responses
+  timeToFirstTokenSeconds?: number
+  // Fewer requests or streams in the window are not judged
+  minSamples?: number
+}
+
+// `quota` alerts come from the quota guard
+export type AlertKind = "error_rate" | "time_to_first_token" | "quota"
+
+export interface Alert {
+  kind: AlertKind
+  text: string
+  value: number
+  threshold: number
+}
+
+const DEFAULT_WINDOW_SECONDS = 300
+const DEFAULT_COOLDOWN_SECONDS = 900
+const DEFAULT_MIN_SAMPLES = 10
+
+export async function loadSloConfig(filePath: string): Promise<SloConfig> {
+  const config = JSON.parse(await fs.readFile(filePath, "utf8")) as SloConfig
+  config.webhook = resolveConfigValue(config.webhook)
+  return config
+}
+
+interface Sample {
+  time: number
+  value: number
+}
+
+// Oldest first; 1 for a 5xx response, else 0
+const outcomes: Array<Sample> = []
+// Oldest first, in seconds
+const firstTokens: Array<Sample> = []
+const lastAlert = new Map<AlertKind, number>()
+
+function prune(samples: Array<Sample>, since: number): void {
+  const expired = samples.findIndex((sample) => sample.time >= since)
+  samples.splice(0, expired === -1 ? samples.length : expired)
+}
+
+export function recordResponseStatus(status: number, now = Date.now()): void {
+  if (!state.sloAlerts) return
+  outcomes.push({ time: now, value: status >= 500 ? 1 : 0 })
+}
+
+export function recordFirstToken(seconds: number, now = Date.now()): void {
+  if (!state.sloAlerts) return
+  firstTokens.push({ time: now, value: seconds })
+}
+
+function percentile(values: Array<number>, q: number): number {
+  const sorted = [...values].sort((a, b) => a - b)
+  return sorted[Math.min(sorted.length - 1, Math.floor(q * sorted.length))]
+}
+
+/** The alerts the current window warrants, ignoring cooldowns. */
+export function evaluateSlo(
+  config: SloConfig,
+  now = Date.now(),
+): Array<Alert> {
+  const windowSeconds = config.windowSeconds ?? DEFAULT_WINDOW_SECONDS
+  const minSamples = config.minSamples ?? DEFAULT_MIN_SAMPLES
+  const since = now - windowSeconds * 1000
+  prune(outcomes, since)
+  prune(firstTokens, since)
+
+  const alerts: Array<Alert> = []
+  if (config.errorRate !== undefined && outcomes.length >= minSamples) {
+    const errors = outcomes.filter((sample) => sample.value === 1).length
+    const rate = errors / outcomes.length
+    if (rate > config.errorRate) {
+      alerts.push({
+        kind: "error_rate",
+        text: `copilot-api: ${(rate * 100).toFixed(1)}% of ${outcomes.length} requests failed in the last ${windowSeconds}s (threshold ${(config.errorRate * 100).toFixed(1)}%)`,
+        value: rate,
+        threshold: config.errorRate,
+      })
+    }
+  }
+  if (
+    config.timeToFirstTokenSeconds !== undefined
+    && firstTokens.length >= minSamples
+  ) {
+    const p95 = percentile(firstTokens.map((sample) => sample.value), 0.95)
+    if (p95 > config.timeToFirstTokenSeconds) {
+      alerts.push({
+        kind: "time_to_first_token",
+        text: `copilot-api: p95 time to first token was ${p95.toFixed(2)}s over ${firstTokens.length} streams in the last ${windowSeconds}s (threshold ${config.timeToFirstTokenSeconds}s)`,
+        value: p95,
+        threshold: config.timeToFirstTokenSeconds,
+      })
+    }
+  }
+  return alerts
+}
+
+/**
+ * Posts an alert to the webhook unless one of the same kind was sent within
+ * the cooldown. Returns whether it was sent.
+ */
+export async function sendAlert(
+  alert: Alert,
+  now = Date.now(),
+): Promise<boolean> {
+  const config = state.sloAlerts
+  if (!config) return false
+  const cooldownMs =
+    (config.cooldownSeconds ?? DEFAULT_COOLDOWN_SECONDS) * 1000
+  const last = lastAlert.get(alert.kind)
+  if (last !== undefined && now - last < cooldownMs) return false
+  lastAlert.set(alert.kind, now)
+
+  try {
+    const response = await fetch(config.webhook, {
+      method: "POST",
+      headers: { "content-type": "application/json" },
+      body: JSON.stringify({ ...alert, time: new Date(now).toISOString() }),
+    })
+    if (!response.ok) {
+      consola.warn(`Alert webhook answered ${response.status}`)
+    }
+  } catch (error) {
+    consola.warn("Failed to send alert:", error)
+  }
+  return true
+}
+
+export async function checkSlo(now = Date.now()): Promise<void> {
+  if (!state.sloAlerts) return
+  for (const alert of evaluateSlo(state.sloAlerts, now)) {
+    await sendAlert(alert, now)
+  }
+}
+
+export function startSloAlerts(): void {
+  setInterval(() => void checkSlo(), 30_000).unref()
+}
+
+export function resetSloAlerts(): void {
+  outcomes.length = 0
+  firstTokens.length = 0
+  lastAlert.clear()
+}
@@ -0,0 +1,30 @@
+// Listening sockets handed over by a service manager (systemd socket
+// activation, see sd_listen_fds). The manager keeps the socket open across
+// restarts, so during an upgrade new connections wait in its backlog until
+// the new gateway process accepts them, while the old one drains.
+
+// The first passed socket is always fd 3
+const SD_LISTEN_FDS_START = 3
+
+/**
+ * The fd of the socket passed to this process, if any. The variables are
+ * removed so that child processes don't take them for their own.
+ */
+export function takeActivatedSocket(
+  env: NodeJS.ProcessEnv = process.env,
+  pid: number = process.pid,
+): number | undefined {
+  const count = Number(env.LISTEN_FDS)
+  const forUs = Number(env.LISTEN_PID) === pid
+  delete env.LISTEN_FDS
+  delete env.LISTEN_PID
+  delete env.LISTEN_FDNAMES
+
+  if (!forUs || !Number.isInteger(count) || count < 1) return undefined
+  if (count > 1) {
+    throw new Error(
+      `Received ${count} sockets from the service manager, expected one for --port; serve the --listeners on their own ports`,
+    )
+  }
+  return SD_LISTEN_FDS_START
+}
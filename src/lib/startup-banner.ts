@@ -0,0 +1,154 @@
+import consola from "consola"
+
+import type { LogFormat } from "./env-config"
+
+import { copilotBaseUrl } from "./api-config"
+import { features } from "./rust-core"
+import { state } from "./state"
+import { upstreamStatus } from "./upstreams"
+
+/** Keeps enough of a secret to tell tokens apart, e.g. `ghu_****`. */
+export function maskSecret(secret: string | undefined): string | undefined {
+  if (!secret) return undefined
+  return secret.length > 12 ? `${secret.slice(0, 4)}****` : "****"
+}
+
+/** Masks the password of a URL such as `redis://:secret@host:6379`. */
+export function maskUrl(raw: string | undefined): string | undefined {
+  if (!raw) return undefined
+  try {
+    const url = new URL(raw)
+    if (url.password) url.password = "****"
+    return url.toString()
+  } catch {
+    return "****"
+  }
+}
+
+export interface StartupInfo {
+  url: string
+  host?: string
+  // URLs of the --listeners
+  listeners?: Array<string>
+  grpcPort?: number
+  rateLimitRedisUrl?: string
+  retry429MaxWait?: number
+  maxConcurrency?: number
+  contentPolicy?: string
+  modelPolicy?: string
+  paramPolicy?: string
+  headerPolicy?: string
+  priorityKeys?: string
+  claudeCode: boolean
+}
+
+/** The configuration the server ended up with, with secrets masked. */
+export function effectiveConfig(info: StartupInfo) {
+  const upstreams = upstreamStatus().map(({ url }) => url)
+  return {
+    url: info.url,
+    host: info.host ?? "all interfaces",
+    listeners: info.listeners?.length ? info.listeners : undefined,
+    upstream: upstreams.length > 0 ? upstreams : copilotBaseUrl(state),
+    account_type: state.accountType,
+    github_token: maskSecret(state.githubToken),
+    copilot_token: maskSecret(state.copilotToken),
+    default_model: state.defaultModel,
+    models: state.models?.data.length,
+    rate_limit: {
+      seconds: state.rateLimitSeconds,
+      wait: state.rateLimitWait,
+      tokens_per_minute: state.tokensPerMinute,
+      redis: maskUrl(info.rateLimitRedisUrl),
+      retry_429_max_wait: info.retry429MaxWait,
+      max_concurrency: info.maxConcurrency,
+    },
+    compatibility: {
+      openai: true,
+      anthropic: true,
+      realtime: true,
+      grpc_port: info.grpcPort,
+      claude_code: info.claudeCode,
+    },
+    features: {
+      manual_approve: state.manualApprove,
+      audit: state.auditRetentionDays !== undefined,
+      audit_compress: Boolean(state.auditCompress),
+      sessions: Boolean(state.sessions),
+      record_usage: Boolean(state.recordUsage),
+      accounting_store: state.accountingStore?.kind ?? "files",
+      team: Boolean(state.teamStore),
+      admin_token: Boolean(state.adminToken),
+      mutual_tls: Boolean(state.clientCertificates),
+      slo_alerts: Boolean(state.sloAlerts),
+      quota_guard: state.quotaGuard?.threshold,
+      poll_usage: state.usagePollMinutes,
+      experiments: state.experiments?.experiments.map(
+        (experiment) => experiment.name,
+      ),
+      shadow: state.shadow && `${state.shadow.percent}% to ${state.shadow.url}`,
+      media_providers:
+        state.mediaProviders && Object.keys(state.mediaProviders),
+      signed_requests:
+        state.requestSigning?.required ? "required"
+        : state.requestSigning ? "optional"
+        : false,
+      prompt_cache_key: Boolean(state.synthesizeCacheKey),
+      repair_tool_calls: Boolean(state.repairToolCalls),
+      context_check: Boolean(state.contextCheck),
+      summarize_model: state.summarizeModel,
+      structured_output_retry: Boolean(state.structuredOutputRetry),
+      docs: state.swaggerUi,
+      chaos: Boolean(state.chaos),
+      plugins: state.plugins?.map((plugin) => plugin.name),
+    },
+    policies: {
+      content: info.contentPolicy,
+      model: info.modelPolicy,
+      param: info.paramPolicy,
+      headers: info.headerPolicy,
+      priority_keys: info.priorityKeys,
+    },
+    native: {
+      tokenizer: features.USE_RUST_TOKENIZER,
+      rate_limit: features.USE_RUST_RATE_LIMIT,
+      http_client: features.USE_RUST_HTTP_CLIENT,
+      translation: features.USE_RUST_TRANSLATION,
+    },
+  }
+}
+
+// `rate_limit.seconds: 30` lines, leaving out unset values
+function flatten(value: object, prefix = ""): Array<string> {
+  return Object.entries(value).flatMap(([key, item]: [string, unknown]) => {
+    if (item === undefined) return []
+    if (typeof item === "object" && item !== null) {
+      return flatten(item, `${prefix}${key}.`)
+    }
+    return [`${prefix}${key}: ${String(item)}`]
+  })
+}
+
+/**
+ * Prints the effective configuration once the server is listening, so a
+ * misconfiguration shows up at startup rather than as upstream errors.
+ * With `--log-format json` it is a single structured log line.
+ */
+export function printStartupBanner(
+  info: StartupInfo,
+  logFormat: LogFormat | undefined,
+): void {
+  const config = effectiveConfig(info)
+  if (logFormat === "json") {
+    process.stdout.write(
+      `${JSON.stringify({
+        time: new Date().toISOString(),
+        level: "info",
+        message: "Effective configuration",
+        config,
+      })}\n`,
+    )
+    return
+  }
+  consola.box(flatten(config).join("\n"))
+}
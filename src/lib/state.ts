@@ -1,25 +1,116 @@
 import type { ModelsResponse } from "~/services/copilot/get-models"
 
+import type { AccountingStore } from "./accounting-store"
+import type { ChaosConfig } from "./chaos"
+import type { ClientCertificates } from "./client-cert"
+import type { Subnet } from "./client-ip"
+import type { ContentFilter } from "./content-policy"
+import type { ExperimentConfig } from "./experiments"
+import type { MediaProviders } from "./media-providers"
+import type { ModelPolicy } from "./model-policy"
+import type { ParamPolicy } from "./param-policy"
+import type { Plugin } from "./plugins"
+import type { QuotaGuardConfig } from "./quota-guard"
+import type { DistributedRateLimiter } from "./rate-limit-redis"
+import type { RequestSigning } from "./request-signing"
+import type { HeaderPolicy } from "./response-headers"
+import type { ShadowConfig } from "./shadow"
+import type { SloConfig } from "./slo-alerts"
+
 export interface State {
   githubToken?: string
   copilotToken?: string
 
   accountType: string
   models?: ModelsResponse
+  modelsCachedAt?: number
+  // The model list is refreshed in the background once older than this
+  modelsTtlSeconds: number
+  // Gateway-side model names, e.g. { fast: "gpt-4o-mini" }
+  modelAliases?: Record<string, string>
   vsCodeVersion?: string
+  // Used for requests that omit `model` or name a model Copilot doesn't have
+  defaultModel?: string
 
   manualApprove: boolean
   rateLimitWait: boolean
   showToken: boolean
+  // Serve Swagger UI at /docs
+  swaggerUi: boolean
+  // Set by --path-prefix, e.g. /copilot
+  pathPrefix?: string
+  // Proxies whose X-Forwarded-For and Forwarded headers are believed
+  trustedProxies?: Array<Subnet>
+  // Failure injection, from COPILOT_GATEWAY_CHAOS only
+  chaos?: ChaosConfig
+  // Token store path in team mode, where each API key has its own account
+  teamStore?: string
+  // Bearer token the /admin endpoints require, see admin-auth.ts
+  adminToken?: string
+  // Set when the listener requires client certificates (--tls-client-ca)
+  clientCertificates?: ClientCertificates
+  // HMAC signing clients from --signing-keys
+  requestSigning?: RequestSigning
+  // Holds back batch traffic when premium requests run low
+  quotaGuard?: QuotaGuardConfig
+  // Usage and audit database from --accounting-store, else the files
+  accountingStore?: AccountingStore
+  // Minutes between usage snapshots for the /stats projection
+  usagePollMinutes?: number
+  // Model experiments from --experiments
+  experiments?: ExperimentConfig
+  // Second upstream that a share of chat requests is mirrored to
+  shadow?: ShadowConfig
+  // OpenAI-compatible providers for endpoints Copilot has no models for
+  mediaProviders?: MediaProviders
+  // Thresholds and webhook from --slo-alerts
+  sloAlerts?: SloConfig
+  // WASM transforms from --plugins, in the order they run
+  plugins?: Array<Plugin>
 
   // Rate limiting configuration
   rateLimitSeconds?: number
   lastRequestTimestamp?: number
+  // Shared across replicas when a Redis URL is configured
+  rateLimitStore?: DistributedRateLimiter
+  // Prompt tokens each client may send per minute
+  tokensPerMinute?: number
+
+  // Audit logging is enabled when set
+  auditRetentionDays?: number
+  // Brotli-compress audit files, with an index to read single entries
+  auditCompress?: boolean
+  // Persist conversations that carry an x-session-id header
+  sessions?: boolean
+  // Append token counts per API key to the usage ledger
+  recordUsage?: boolean
+
+  // Loaded from the --content-policy file
+  contentFilters?: Array<ContentFilter>
+  // Derive `prompt_cache_key` from the system prompt and tools when absent
+  synthesizeCacheKey?: boolean
+  // Complete truncated or invalid JSON in tool call arguments
+  repairToolCalls?: boolean
+  // Reject requests that can't fit the model's context window before sending
+  contextCheck?: boolean
+  // Cheaper model that summarizes the oldest turns of prompts over the limit
+  summarizeModel?: string
+  // Retry once when a response does not match its `json_schema`
+  structuredOutputRetry?: boolean
+
+  // Loaded from the --model-policy file
+  modelPolicy?: ModelPolicy
+  // Loaded from the --param-policy file; built-in rules apply without one
+  paramPolicy?: ParamPolicy
+  // Loaded from the --header-policy file; built-in headers are removed without one
+  headerPolicy?: HeaderPolicy
 }
 
 export const state: State = {
   accountType: "individual",
+  modelsTtlSeconds: 300,
   manualApprove: false,
   rateLimitWait: false,
   showToken: false,
+  swaggerUi: false,
 }
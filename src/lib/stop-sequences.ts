@@ -0,0 +1,125 @@
+// Gateway-side enforcement of `stop`, which Copilot ignores for some
+// models. Output is cut at the first match, which is not included, and the
+// choice finishes with `stop` as if the upstream had honored it.
+
+import type {
+  ChatCompletionChunk,
+  ChatCompletionResponse,
+  ChatCompletionsPayload,
+} from "~/services/copilot/create-chat-completions"
+
+export function stopSequencesOf(
+  stop: ChatCompletionsPayload["stop"],
+): Array<string> {
+  const sequences = typeof stop === "string" ? [stop] : (stop ?? [])
+  return sequences.filter((sequence) => sequence.length > 0)
+}
+
+/** The earliest stop sequence in `text`, and where it starts. */
+export function findStop(
+  text: string,
+  stops: Array<string>,
+): { index: number; sequence: string } | undefined {
+  let found: { index: number; sequence: string } | undefined
+  for (const sequence of stops) {
+    const index = text.indexOf(sequence)
+    if (index !== -1 && (!found || index < found.index)) {
+      found = { index, sequence }
+    }
+  }
+  return found
+}
+
+// Length of the longest suffix of `text` that a later delta could
+// complete into a stop sequence
+function pendingLength(text: string, stops: Array<string>): number {
+  let longest = 0
+  for (const sequence of stops) {
+    const max = Math.min(sequence.length - 1, text.length)
+    for (let length = max; length > longest; length--) {
+      if (text.endsWith(sequence.slice(0, length))) {
+        longest = length
+        break
+      }
+    }
+  }
+  return longest
+}
+
+/** Truncates each choice of a non-streaming response at its first stop. */
+export function applyStopSequences(
+  response: ChatCompletionResponse,
+  stops: Array<string>,
+): { response: ChatCompletionResponse; matched: Map<number, string> } {
+  const matched = new Map<number, string>()
+  const choices = response.choices.map((choice) => {
+    const content = choice.message.content
+    const match = content ? findStop(content, stops) : undefined
+    if (!content || !match) return choice
+
+    matched.set(choice.index, match.sequence)
+    return {
+      ...choice,
+      message: { ...choice.message, content: content.slice(0, match.index) },
+      finish_reason: "stop" as const,
+    }
+  })
+  return { response: { ...response, choices }, matched }
+}
+
+/**
+ * Streaming counterpart. Text that could be the start of a stop sequence
+ * is held back until the next delta shows whether it is one, and anything
+ * after a match is dropped.
+ */
+export function createStopSequenceFilter(stops: Array<string>) {
+  const choices = new Map<number, { held: string; matched?: string }>()
+
+  return {
+    /** The stop sequence that ended a choice, if any. */
+    matched(index: number): string | undefined {
+      return choices.get(index)?.matched
+    },
+    /** The chunks to send in place of `chunk`. */
+    process(chunk: ChatCompletionChunk): Array<ChatCompletionChunk> {
+      const output = chunk.choices.flatMap((choice) => {
+        const entry = choices.get(choice.index) ?? { held: "" }
+        choices.set(choice.index, entry)
+        if (entry.matched !== undefined) return []
+        if (!choice.delta.content && !entry.held) return [choice]
+
+        const text = entry.held + (choice.delta.content ?? "")
+        const match = findStop(text, stops)
+        if (match) {
+          entry.matched = match.sequence
+          entry.held = ""
+          return [
+            {
+              ...choice,
+              delta: { ...choice.delta, content: text.slice(0, match.index) },
+              finish_reason: "stop" as const,
+            },
+          ]
+        }
+
+        // Nothing can follow a finished choice, so release everything
+        const released =
+          choice.finish_reason ? text.length : (
+            text.length - pendingLength(text, stops)
+          )
+        entry.held = text.slice(released)
+        return [
+          {
+            ...choice,
+            delta: { ...choice.delta, content: text.slice(0, released) },
+          },
+        ]
+      })
+
+      if (output.length === 0 && chunk.choices.length > 0 && !chunk.usage) {
+        return []
+      }
+      return [{ ...chunk, choices: output }]
+    },
+  }
+}
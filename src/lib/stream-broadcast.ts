@@ -0,0 +1,176 @@
+// Lets observers such as a dashboard attach to a live streaming completion
+// by its id and receive a read-only copy of the events sent to the client.
+// Observers that attach late first get the events sent so far. The same
+// buffer lets a client that lost its connection resume with Last-Event-ID.
+
+import type { Context } from "hono"
+import type { SSEMessage } from "hono/streaming"
+
+import { randomUUID } from "node:crypto"
+
+import { apiKeyOf, keyId } from "./api-key"
+import { state } from "./state"
+
+export interface StreamInfo {
+  id: string
+  endpoint: string
+  model: string
+  startedAt: string
+  events: number
+  observers: number
+}
+
+const config = {
+  // Events kept per stream
+  capacity: 1000,
+  // How long a finished stream can still be resumed
+  retentionMs: 5 * 60_000,
+}
+
+export function configureStreamBuffer(options: Partial<typeof config>): void {
+  config.capacity = options.capacity ?? config.capacity
+  config.retentionMs = options.retentionMs ?? config.retentionMs
+}
+
+export class StreamBroadcast {
+  info: Omit<StreamInfo, "events" | "observers">
+  // Key id of the client that started it in team mode, see streamOwner
+  owner: string | undefined
+  // The last `capacity` events; `dropped` older ones are gone
+  events: Array<SSEMessage> = []
+  dropped = 0
+  closed = false
+  observers = 0
+  private waiting = new Set<() => void>()
+
+  constructor(
+    info: Omit<StreamInfo, "events" | "observers">,
+    owner?: string,
+  ) {
+    this.info = info
+    this.owner = owner
+  }
+
+  /** Numbers each event from 1, as `<stream id>:<number>`. */
+  eventId(sequence: number): string {
+    return `${this.info.id}:${sequence}`
+  }
+
+  /**
+   * Buffers the event and returns it with its id, which is what the client
+   * should be sent so it can resume after it.
+   */
+  publish(event: SSEMessage): SSEMessage {
+    this.events.push(event)
+    if (this.events.length > config.capacity) {
+      this.events.shift()
+      this.dropped++
+    }
+    this.wake()
+    return { ...event, id: this.eventId(this.dropped + this.events.length) }
+  }
+
+  close(): void {
+    this.closed = true
+    this.wake()
+    broadcasts.delete(this.info.id)
+    finished.set(this.info.id, this)
+    setTimeout(() => finished.delete(this.info.id), config.retentionMs).unref()
+  }
+
+  /** Whether every event after `sequence` is still buffered. */
+  canReplayAfter(sequence: number): boolean {
+    return sequence >= this.dropped
+  }
+
+  /**
+   * Yields the events after `after` (default: every buffered one) with their
+   * sequence numbers, until the stream closes.
+   */
+  async *replay(
+    after = this.dropped,
+  ): AsyncGenerator<{ sequence: number; event: SSEMessage }> {
+    this.observers++
+    try {
+      let sequence = Math.max(after, this.dropped)
+      for (;;) {
+        while (sequence < this.dropped + this.events.length) {
+          sequence = Math.max(sequence, this.dropped)
+          const event = this.events[sequence - this.dropped]
+          sequence++
+          yield { sequence, event }
+        }
+        if (this.closed) return
+        await new Promise<void>((resolve) => this.waiting.add(resolve))
+      }
+    } finally {
+      this.observers--
+    }
+  }
+
+  /** Yields every buffered event until the stream closes. */
+  async *subscribe(): AsyncGenerator<SSEMessage> {
+    for await (const { event } of this.replay()) yield event
+  }
+
+  private wake(): void {
+    for (const resolve of this.waiting) resolve()
+    this.waiting.clear()
+  }
+}
+
+const broadcasts = new Map<string, StreamBroadcast>()
+// Closed streams, kept for resuming until the retention period ends
+const finished = new Map<string, StreamBroadcast>()
+
+/**
+ * Who may resume the request's streams: in team mode only the key that
+ * started them, since they carry that member's completions. Outside team
+ * mode anyone.
+ */
+export const streamOwner = (c: Context): string | undefined =>
+  state.teamStore ? keyId(apiKeyOf(c)) : undefined
+
+/** Whether `owner`, from `streamOwner`, may resume `broadcast`. */
+export const canFollow = (
+  broadcast: StreamBroadcast,
+  owner: string | undefined,
+): boolean => broadcast.owner === undefined || broadcast.owner === owner
+
+/** Registers a new live stream; call `close()` once it has ended. */
+export function openBroadcast(
+  endpoint: string,
+  model: string,
+  owner?: string,
+): StreamBroadcast {
+  const broadcast = new StreamBroadcast(
+    {
+      id: randomUUID(),
+      endpoint,
+      model,
+      startedAt: new Date().toISOString(),
+    },
+    owner,
+  )
+  broadcasts.set(broadcast.info.id, broadcast)
+  return broadcast
+}
+
+export function getBroadcast(id: string): StreamBroadcast | undefined {
+  return broadcasts.get(id)
+}
+
+/** A live or recently finished stream that a client may resume. */
+export function getResumableBroadcast(
+  id: string,
+): StreamBroadcast | undefined {
+  return broadcasts.get(id) ?? finished.get(id)
+}
+
+export function listBroadcasts(): Array<StreamInfo> {
+  return [...broadcasts.values()].map((broadcast) => ({
+    ...broadcast.info,
+    events: broadcast.dropped + broadcast.events.length,
+    observers: broadcast.observers,
+  }))
+}
@@ -0,0 +1,63 @@
+import type { MiddlewareHandler } from "hono"
+
+import consola from "consola"
+import { streamSSE } from "hono/streaming"
+
+import {
+  canFollow,
+  getResumableBroadcast,
+  streamOwner,
+} from "./stream-broadcast"
+
+/** Splits an event id such as `<stream id>:42`. */
+export function parseEventId(
+  raw: string,
+): { streamId: string; sequence: number } | undefined {
+  const separator = raw.lastIndexOf(":")
+  const sequence = Number(raw.slice(separator + 1))
+  if (separator <= 0 || !Number.isInteger(sequence) || sequence < 0) {
+    return undefined
+  }
+  return { streamId: raw.slice(0, separator), sequence }
+}
+
+/**
+ * Continues a streamed response for a client that reconnects with the
+ * `Last-Event-ID` of the last event it received, replaying the missed
+ * events from the buffer instead of starting a new generation. Requests
+ * without `Last-Event-ID` are handled as usual.
+ */
+export const resumeStream: MiddlewareHandler = async (c, next) => {
+  const lastEventId = c.req.header("last-event-id")
+  if (!lastEventId) return next()
+
+  const parsed = parseEventId(lastEventId)
+  const broadcast = parsed && getResumableBroadcast(parsed.streamId)
+  if (
+    !parsed
+    || !broadcast?.canReplayAfter(parsed.sequence)
+    || !canFollow(broadcast, streamOwner(c))
+  ) {
+    return c.json(
+      {
+        error: {
+          message: `Stream event ${lastEventId} can no longer be resumed, send the request again without Last-Event-ID`,
+          type: "error",
+        },
+      },
+      404,
+    )
+  }
+
+  consola.info(
+    `Resuming stream ${parsed.streamId} after event ${parsed.sequence}`,
+  )
+  c.header("x-stream-id", parsed.streamId)
+  return streamSSE(c, async (stream) => {
+    const missed = broadcast.replay(parsed.sequence)
+    for await (const { sequence, event } of missed) {
+      if (stream.aborted) break
+      await stream.writeSSE({ ...event, id: broadcast.eventId(sequence) })
+    }
+  })
+}
@@ -0,0 +1,74 @@
+// The realtime WebSocket and gRPC have no HTTP request per completion, so
+// they stream completions through here to be held to the same rate limit,
+// model policy, content filters, manual approval and audit log as the
+// HTTP routes.
+
+import {
+  createChatCompletions,
+  type ChatCompletionChunk,
+  type ChatCompletionsPayload,
+} from "~/services/copilot/create-chat-completions"
+
+import { awaitApproval } from "./approval"
+import { createStreamTranscript, recordAudit } from "./audit"
+import { applyRequestFilters, type PolicyContext } from "./content-policy"
+import { checkKeyModelAccess } from "./model-policy"
+import { checkRateLimit } from "./rate-limit"
+import { state } from "./state"
+import { resolveModel } from "./utils"
+
+export interface StreamedCompletionOptions {
+  // Recorded as the endpoint of the audit entry
+  endpoint: string
+  // The client's API key, for per-key model policies
+  apiKey?: string
+  // Stops reading the upstream once the client has gone away
+  isClosed: () => boolean
+  onChunk: (chunk: ChatCompletionChunk) => void
+}
+
+/**
+ * Streams one completion to `onChunk`.
+ * @returns Whether the stream was read to the end.
+ * @throws {HTTPError} When a policy rejects the request or the upstream fails.
+ */
+export async function streamCompletion(
+  body: ChatCompletionsPayload,
+  options: StreamedCompletionOptions,
+): Promise<boolean> {
+  await checkRateLimit(state)
+
+  const model = resolveModel(body.model)
+  checkKeyModelAccess(options.apiKey, model)
+  const policy: PolicyContext = { annotations: [] }
+  const payload = applyRequestFilters(
+    state.contentFilters ?? [],
+    { ...body, model, stream: true },
+    policy,
+  )
+  if (state.manualApprove) await awaitApproval()
+
+  const response = await createChatCompletions(payload)
+  if (!(Symbol.asyncIterator in response)) {
+    throw new Error("Expected a streaming response")
+  }
+
+  const transcript = createStreamTranscript()
+  for await (const event of response) {
+    if (options.isClosed()) return false
+    if (!event.data || event.data === "[DONE]") continue
+
+    const chunk = JSON.parse(event.data) as ChatCompletionChunk
+    transcript.add(chunk)
+    options.onChunk(chunk)
+  }
+
+  await recordAudit({
+    endpoint: options.endpoint,
+    model: payload.model,
+    stream: true,
+    request: payload,
+    response: transcript.result(),
+  })
+  return true
+}
@@ -0,0 +1,136 @@
+// Keeps long sessions under the model's prompt limit by replacing the oldest
+// turns with a summary written by a cheaper model. System messages and the
+// most recent turns are sent as they are.
+
+import type { Context } from "hono"
+
+import consola from "consola"
+
+import {
+  createChatCompletions,
+  type ChatCompletionResponse,
+  type ChatCompletionsPayload,
+  type Message,
+} from "~/services/copilot/create-chat-completions"
+
+import { state } from "./state"
+import { getPromptTokenCount } from "./tokenizer"
+
+export const SUMMARIZED_HEADER = "x-context-summarized"
+
+const SUMMARY_INSTRUCTIONS =
+  "Summarize the conversation below for the assistant that continues it. Keep the user's goals, decisions made, facts and file names mentioned, tool results that still matter and open tasks. Write plain prose, without addressing the user."
+
+// Tool results count too, they are often the bulk of an agent's context
+const tokensOf = (messages: Array<Message>) => getPromptTokenCount(messages)
+
+const isInstruction = (message: Message) =>
+  message.role === "system" || message.role === "developer"
+
+const textOf = (message: Message): string => {
+  const content =
+    typeof message.content === "string" ? message.content : (
+      (message.content ?? [])
+        .map((part) => (part.type === "text" ? part.text : "[image]"))
+        .join("\n")
+    )
+  const calls = (message.tool_calls ?? []).map(
+    (call) => `[called ${call.function.name}(${call.function.arguments})]`,
+  )
+  return [content, ...calls].filter(Boolean).join("\n")
+}
+
+const transcriptOf = (messages: Array<Message>) =>
+  messages.map((message) => `${message.role}: ${textOf(message)}`).join("\n\n")
+
+// Index of the first message to keep verbatim: as many recent messages as fit
+// in half the prompt limit, leaving room for the summary and the reply. Tool
+// results stay with the assistant message that called them.
+function splitPoint(conversation: Array<Message>, budget: number): number {
+  let start = conversation.length - 1
+  let used = tokensOf(conversation.slice(start))
+  while (start > 0) {
+    const size = tokensOf([conversation[start - 1]])
+    if (used + size > budget) break
+    used += size
+    start--
+  }
+  while (start > 0 && conversation[start].role === "tool") start--
+  return start
+}
+
+async function summarize(messages: Array<Message>): Promise<string> {
+  const response = (await createChatCompletions({
+    model: state.summarizeModel as string,
+    stream: false,
+    messages: [
+      { role: "system", content: SUMMARY_INSTRUCTIONS },
+      { role: "user", content: transcriptOf(messages) },
+    ],
+  })) as ChatCompletionResponse
+  return response.choices[0]?.message.content?.trim() ?? ""
+}
+
+/**
+ * Returns `payload` with its oldest turns summarized when its prompt is over
+ * the model's limit and --summarize-model is set, or unchanged otherwise.
+ * When summarizing fails, the request goes ahead as it was sent.
+ */
+export async function summarizeToFit(
+  c: Context,
+  payload: ChatCompletionsPayload,
+): Promise<ChatCompletionsPayload> {
+  if (!state.summarizeModel) return payload
+
+  const limits = state.models?.data.find(
+    (candidate) => candidate.id === payload.model,
+  )?.capabilities.limits
+  const contextWindow = limits?.max_context_window_tokens
+  if (!limits || contextWindow === undefined) return payload
+
+  const promptLimit = Math.min(
+    limits.max_prompt_tokens ?? contextWindow,
+    contextWindow - (payload.max_tokens ?? 0),
+  )
+  const promptTokens = tokensOf(payload.messages)
+  if (promptTokens <= promptLimit) return payload
+
+  const instructions = payload.messages.filter(isInstruction)
+  const conversation = payload.messages.filter(
+    (message) => !isInstruction(message),
+  )
+  const start = splitPoint(
+    conversation,
+    Math.floor(promptLimit / 2) - tokensOf(instructions),
+  )
+  if (start === 0) return payload
+
+  const older = conversation.slice(0, start)
+  let summary: string
+  try {
+    summary = await summarize(older)
+  } catch (error) {
+    consola.warn(
+      `Could not summarize ${older.length} messages with ${state.summarizeModel}:`,
+      error,
+    )
+    return payload
+  }
+  if (!summary) return payload
+
+  consola.info(
+    `Prompt of about ${promptTokens} tokens is over the ${promptLimit} token limit of ${payload.model}, summarized the ${older.length} oldest messages with ${state.summarizeModel}`,
+  )
+  c.header(SUMMARIZED_HEADER, String(older.length))
+  return {
+    ...payload,
+    messages: [
+      ...instructions,
+      {
+        role: "system",
+        content: `Summary of the earlier conversation, which was shortened to fit the context window:\n\n${summary}`,
+      },
+      ...conversation.slice(start),
+    ],
+  }
+}
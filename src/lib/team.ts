@@ -0,0 +1,388 @@
+// Team mode: each client API key is bound to a GitHub account in the token
+// store, and requests from that key reach Copilot with the account's own
+// token, so a team can share one gateway while each member uses their own
+// quota. Keys are stored as key ids, never in plain text.
+
+import type { MiddlewareHandler } from "hono"
+
+import consola from "consola"
+import { randomBytes } from "node:crypto"
+import fs from "node:fs/promises"
+
+import { getCopilotToken } from "~/services/github/get-copilot-token"
+
+import { memberCredentials } from "./api-config"
+import { apiKeyOf, keyId } from "./api-key"
+import { forwardError } from "./error"
+import { PATHS } from "./paths"
+import { state } from "./state"
+import { sleep } from "./utils"
+
+export interface TeamMember {
+  login: string
+  githubToken: string
+  addedAt: string
+  expiresAt?: string
+  // Revoked keys are kept for the audit trail
+  revokedAt?: string
+  // Written once a minute, so up to a minute behind
+  lastUsedAt?: string
+  // Key id of the key that replaced this one, which still works until expiresAt
+  rotatedTo?: string
+}
+
+export type KeyStatus = "active" | "expired" | "revoked"
+
+export interface KeyEvent {
+  time: string
+  action: "created" | "rotated" | "revoked" | "removed"
+  id: string
+  login: string
+  // Where the change was made
+  via: "cli" | "admin"
+}
+
+export interface TeamStore {
+  // By key id of the member's API key
+  members: Record<string, TeamMember>
+  // Oldest first, the last MAX_KEY_EVENTS
+  audit?: Array<KeyEvent>
+}
+
+const MAX_KEY_EVENTS = 1000
+
+/** An empty store when the file does not exist yet. */
+export async function readTeamStore(
+  filePath: string = PATHS.TEAM_TOKENS_PATH,
+): Promise<TeamStore> {
+  try {
+    return JSON.parse(await fs.readFile(filePath, "utf8")) as TeamStore
+  } catch (error) {
+    if ((error as NodeJS.ErrnoException).code === "ENOENT") {
+      return { members: {} }
+    }
+    throw error
+  }
+}
+
+// Written to a temporary file first so the server never reads half a store.
+// The name is unique so concurrent writers don't rename each other's files.
+export async function writeTeamStore(
+  store: TeamStore,
+  filePath: string = PATHS.TEAM_TOKENS_PATH,
+): Promise<void> {
+  const temporary = `${filePath}.${process.pid}.${randomBytes(6).toString("hex")}.tmp`
+  try {
+    await fs.writeFile(temporary, `${JSON.stringify(store, null, 2)}\n`, {
+      mode: 0o600,
+    })
+    await fs.rename(temporary, filePath)
+  } catch (error) {
+    await fs.rm(temporary, { force: true })
+    throw error
+  }
+}
+
+const LOCK_TIMEOUT_MS = 10_000
+// Older locks were left by a process that died while holding them
+const LOCK_STALE_MS = 30_000
+
+/**
+ * Holds `<store>.lock` while `task` runs, so the server and the `team` and
+ * `keys` commands in other processes change the store one at a time.
+ * @throws {Error} When another process holds the lock for too long.
+ */
+async function withStoreLock<T>(
+  filePath: string,
+  task: () => Promise<T>,
+): Promise<T> {
+  const lockPath = `${filePath}.lock`
+  const deadline = Date.now() + LOCK_TIMEOUT_MS
+  for (;;) {
+    try {
+      await fs.writeFile(lockPath, `${process.pid}\n`, {
+        flag: "wx",
+        mode: 0o600,
+      })
+      break
+    } catch (error) {
+      if ((error as NodeJS.ErrnoException).code !== "EEXIST") throw error
+    }
+    const lock = await fs.stat(lockPath).catch(() => undefined)
+    if (lock && Date.now() - lock.mtimeMs > LOCK_STALE_MS) {
+      consola.warn(`Removing stale team store lock ${lockPath}`)
+      await fs.rm(lockPath, { force: true })
+      continue
+    }
+    if (Date.now() > deadline) {
+      throw new Error(
+        `Timed out waiting for ${lockPath}, remove it if no other copilot-api process is changing the team store`,
+      )
+    }
+    await sleep(20 + Math.random() * 30)
+  }
+
+  try {
+    return await task()
+  } finally {
+    await fs.rm(lockPath, { force: true })
+  }
+}
+
+// Updates from this process, run one at a time
+let updates: Promise<unknown> = Promise.resolve()
+
+/**
+ * Reads, changes and writes back the store. Updates wait for each other
+ * and for other processes, and `change` always sees the latest store, so a
+ * usage flush can't bring back a key revoked at the same time.
+ */
+export function updateTeamStore<T>(
+  change: (store: TeamStore) => T,
+  filePath: string = PATHS.TEAM_TOKENS_PATH,
+): Promise<T> {
+  const update = updates.then(() =>
+    withStoreLock(filePath, async () => {
+      const store = await readTeamStore(filePath)
+      const result = change(store)
+      await writeTeamStore(store, filePath)
+      return result
+    }),
+  )
+  // A failed update does not stop the ones queued after it
+  updates = update.catch(() => undefined)
+  return update
+}
+
+export function keyStatus(member: TeamMember, now = Date.now()): KeyStatus {
+  if (member.revokedAt) return "revoked"
+  if (member.expiresAt && Date.parse(member.expiresAt) <= now) return "expired"
+  return "active"
+}
+
+export function recordKeyEvent(
+  store: TeamStore,
+  event: Omit<KeyEvent, "time">,
+): void {
+  store.audit = [
+    ...(store.audit ?? []),
+    { time: new Date().toISOString(), ...event },
+  ].slice(-MAX_KEY_EVENTS)
+}
+
+export const generateApiKey = () =>
+  `cpk-${randomBytes(24).toString("base64url")}`
+
+interface NewKey {
+  login: string
+  githubToken: string
+  // Generated when not given
+  apiKey?: string
+  expiresAt?: string
+}
+
+/** Adds a key and returns it with its id. */
+export function createKey(
+  store: TeamStore,
+  key: NewKey,
+  via: KeyEvent["via"],
+): { apiKey: string; id: string } {
+  const apiKey = key.apiKey ?? generateApiKey()
+  const id = keyId(apiKey)
+  store.members[id] = {
+    login: key.login,
+    githubToken: key.githubToken,
+    addedAt: new Date().toISOString(),
+    ...(key.expiresAt && { expiresAt: key.expiresAt }),
+  }
+  recordKeyEvent(store, { action: "created", id, login: key.login, via })
+  return { apiKey, id }
+}
+
+/**
+ * Issues a new key for the same account. The old key keeps working for
+ * `graceMs`, so clients can switch over without downtime.
+ * @throws {Error} when the key does not exist or is no longer active.
+ */
+export function rotateKey(
+  store: TeamStore,
+  id: string,
+  options: { graceMs: number; expiresAt?: string },
+  via: KeyEvent["via"],
+): { apiKey: string; id: string } {
+  const member = store.members[id]
+  if (!member) throw new Error(`No API key ${id}`)
+  const status = keyStatus(member)
+  if (status !== "active") throw new Error(`API key ${id} is ${status}`)
+
+  const replacement = createKey(
+    store,
+    {
+      login: member.login,
+      githubToken: member.githubToken,
+      expiresAt: options.expiresAt,
+    },
+    via,
+  )
+  const graceEnd = Date.now() + options.graceMs
+  if (!member.expiresAt || Date.parse(member.expiresAt) > graceEnd) {
+    member.expiresAt = new Date(graceEnd).toISOString()
+  }
+  member.rotatedTo = replacement.id
+  recordKeyEvent(store, { action: "rotated", id, login: member.login, via })
+  return replacement
+}
+
+/**
+ * Revokes every key matching a key id or GitHub login and returns their
+ * ids. The entries stay in the store for the audit trail.
+ */
+export function revokeKeys(
+  store: TeamStore,
+  member: string,
+  via: KeyEvent["via"],
+): Array<string> {
+  const revokedAt = new Date().toISOString()
+  const ids = Object.entries(store.members)
+    .filter(([id, entry]) => id === member || entry.login === member)
+    .filter(([, entry]) => !entry.revokedAt)
+    .map(([id]) => id)
+  for (const id of ids) {
+    const entry = store.members[id]
+    entry.revokedAt = revokedAt
+    recordKeyEvent(store, { action: "revoked", id, login: entry.login, via })
+  }
+  return ids
+}
+
+// Key ids used since the last flush, so requests never wait for a write
+const pendingUse = new Map<string, string>()
+
+/** Writes last-used times to the store, once a minute in team mode. */
+export async function flushKeyUsage(
+  filePath: string = PATHS.TEAM_TOKENS_PATH,
+): Promise<void> {
+  if (pendingUse.size === 0) return
+  const used = new Map(pendingUse)
+  pendingUse.clear()
+  await updateTeamStore((store) => {
+    for (const [id, time] of used) {
+      const member = store.members[id]
+      if (member) member.lastUsedAt = time
+    }
+  }, filePath)
+}
+
+export function startKeyUsageFlush(filePath: string): void {
+  setInterval(() => {
+    flushKeyUsage(filePath).catch((error: unknown) => {
+      consola.warn("Failed to record API key usage:", error)
+    })
+  }, 60_000).unref()
+}
+
+/** A key without its GitHub token, including a last use not yet written. */
+export function describeKey(id: string, member: TeamMember) {
+  return {
+    id,
+    login: member.login,
+    addedAt: member.addedAt,
+    expiresAt: member.expiresAt,
+    revokedAt: member.revokedAt,
+    lastUsedAt: pendingUse.get(id) ?? member.lastUsedAt,
+    rotatedTo: member.rotatedTo,
+    status: keyStatus(member),
+  }
+}
+
+let cached: { filePath: string; mtimeMs: number; store: TeamStore } | undefined
+
+// Re-read when `team add` or `team remove` changed the file
+async function currentTeamStore(filePath: string): Promise<TeamStore> {
+  const { mtimeMs } = await fs.stat(filePath).catch(() => ({ mtimeMs: 0 }))
+  if (cached?.filePath !== filePath || cached.mtimeMs !== mtimeMs) {
+    cached = { filePath, mtimeMs, store: await readTeamStore(filePath) }
+  }
+  return cached.store
+}
+
+interface Exchange {
+  githubToken: string
+  result: Promise<{ token: string; refreshAt: number }>
+}
+
+const exchanges = new Map<string, Exchange>()
+
+/**
+ * The member's Copilot token, exchanged on first use and again shortly
+ * before it expires. Concurrent requests share one exchange.
+ */
+export async function copilotTokenFor(
+  id: string,
+  member: TeamMember,
+): Promise<string> {
+  const existing = exchanges.get(id)
+  if (existing?.githubToken === member.githubToken) {
+    const { token, refreshAt } = await existing.result
+    if (refreshAt > Date.now()) return token
+  }
+
+  const result = getCopilotToken(member.githubToken).then((response) => ({
+    token: response.token,
+    refreshAt: Date.now() + (response.refresh_in - 60) * 1000,
+  }))
+  const exchange = { githubToken: member.githubToken, result }
+  exchanges.set(id, exchange)
+  // A failed exchange is retried by the next request
+  result.catch(() => {
+    if (exchanges.get(id) === exchange) exchanges.delete(id)
+  })
+  return (await result).token
+}
+
+const invalidKey = (message: string, code: string) => ({
+  error: { message, type: "invalid_request_error", code },
+})
+
+/**
+ * Runs the rest of the request with the credentials of the member that owns
+ * the API key. Keys that are not in the token store, revoked or expired are
+ * rejected, so the gateway's own account is never used on a member's behalf.
+ */
+export const teamCredentials: MiddlewareHandler = async (c, next) => {
+  if (!state.teamStore) return next()
+
+  const id = keyId(apiKeyOf(c))
+  const member = (await currentTeamStore(state.teamStore)).members[id]
+  if (!member) {
+    return c.json(
+      invalidKey(
+        "This API key is not registered with the team. Ask the gateway owner to run `copilot-api team add`.",
+        "invalid_api_key",
+      ),
+      401,
+    )
+  }
+  const status = keyStatus(member)
+  if (status !== "active") {
+    return c.json(
+      invalidKey(
+        `This API key is ${status}. Ask the gateway owner for a new one.`,
+        `${status}_api_key`,
+      ),
+      401,
+    )
+  }
+  pendingUse.set(id, new Date().toISOString())
+
+  let copilotToken: string
+  try {
+    copilotToken = await copilotTokenFor(id, member)
+  } catch (error) {
+    return forwardError(c, error)
+  }
+  return memberCredentials.run(
+    { githubToken: member.githubToken, copilotToken },
+    next,
+  )
+}
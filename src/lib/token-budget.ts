@@ -0,0 +1,116 @@
+import type { Context } from "hono"
+
+import consola from "consola"
+
+import type { Message } from "~/services/copilot/create-chat-completions"
+
+import { apiKeyOf, keyId } from "./api-key"
+import { clientIp } from "./client-ip"
+import { HTTPError } from "./error"
+import { getTokenCountAsync } from "./hybrid-tokenizer"
+import { state } from "./state"
+import { sleep } from "./utils"
+
+interface Bucket {
+  tokens: number
+  updatedAt: number
+}
+
+const buckets = new Map<string, Bucket>()
+
+/**
+ * Identifies the client by its API key, so clients sharing one gateway get
+ * separate budgets. Keys are hashed so they never sit in memory in clear.
+ * Clients without a key are told apart by IP address.
+ */
+export function clientKey(c: Context): string {
+  const key = apiKeyOf(c)
+  const ip = key ? undefined : clientIp(c)
+  return ip ? `ip:${ip}` : keyId(key)
+}
+
+function refill(key: string, tokensPerMinute: number, now: number): Bucket {
+  const bucket = buckets.get(key) ?? { tokens: tokensPerMinute, updatedAt: now }
+  const elapsedMinutes = (now - bucket.updatedAt) / 60_000
+  bucket.tokens = Math.min(
+    tokensPerMinute,
+    bucket.tokens + elapsedMinutes * tokensPerMinute,
+  )
+  bucket.updatedAt = now
+  buckets.set(key, bucket)
+  return bucket
+}
+
+/**
+ * Charges `tokens` against the client's per-minute budget. A request larger
+ * than the whole budget is charged as a full bucket so it can still go
+ * through once the bucket is full.
+ * @throws {HTTPError} 429 when over budget and `--wait` is not set.
+ */
+export async function chargeTokens(
+  key: string,
+  tokens: number,
+  tokensPerMinute: number,
+  now = Date.now,
+): Promise<void> {
+  const cost = Math.min(tokens, tokensPerMinute)
+
+  for (;;) {
+    const bucket = refill(key, tokensPerMinute, now())
+    if (bucket.tokens >= cost) {
+      bucket.tokens -= cost
+      return
+    }
+
+    const waitMs = Math.ceil(
+      ((cost - bucket.tokens) / tokensPerMinute) * 60_000,
+    )
+    const waitSeconds = Math.ceil(waitMs / 1000)
+    if (!state.rateLimitWait) {
+      consola.warn(
+        `Token budget exceeded. Need to wait ${waitSeconds} more seconds.`,
+      )
+      throw new HTTPError(
+        "Token budget exceeded",
+        Response.json(
+          { message: "Token budget exceeded" },
+          { status: 429, headers: { "retry-after": String(waitSeconds) } },
+        ),
+      )
+    }
+
+    consola.warn(
+      `Token budget reached. Waiting ${waitSeconds} seconds before proceeding...`,
+    )
+    // Other requests may spend the refill meanwhile, so check again
+    await sleep(waitMs)
+  }
+}
+
+/** Estimates prompt tokens and charges them when a token budget is set. */
+export async function checkTokenBudget(
+  c: Context,
+  messages: Array<Message>,
+): Promise<void> {
+  if (state.tokensPerMinute === undefined) return
+
+  const { input } = await getTokenCountAsync(messages)
+  await chargeTokens(clientKey(c), input, state.tokensPerMinute)
+}
+
+/** Tokens left in the client's bucket and milliseconds until it is full. */
+export function tokenBudgetStatus(
+  key: string,
+  tokensPerMinute: number,
+  now = Date.now(),
+): { remaining: number; resetMs: number } {
+  const { tokens } = refill(key, tokensPerMinute, now)
+  return {
+    remaining: Math.floor(tokens),
+    resetMs: Math.ceil(((tokensPerMinute - tokens) / tokensPerMinute) * 60_000),
+  }
+}
+
+export function resetTokenBudgets(): void {
+  buckets.clear()
+}
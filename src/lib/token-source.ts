@@ -0,0 +1,63 @@
+import consola from "consola"
+import fsSync, { type FSWatcher } from "node:fs"
+import fs from "node:fs/promises"
+import path from "node:path"
+
+import { state } from "./state"
+import { refreshCopilotToken } from "./token"
+
+export async function readTokenFile(filePath: string): Promise<string> {
+  const token = (await fs.readFile(filePath, "utf8")).trim()
+  if (!token) throw new Error(`Token file ${filePath} is empty`)
+  return token
+}
+
+/** Reads the GitHub token from stdin until it is closed. */
+export async function readTokenStdin(
+  input: AsyncIterable<string | Buffer> = process.stdin,
+): Promise<string> {
+  let data = ""
+  for await (const chunk of input) data += String(chunk)
+  const token = data.trim()
+  if (!token) throw new Error("No GitHub token received on stdin")
+  return token
+}
+
+/**
+ * Loads the token file into the state if it changed, and exchanges it for a
+ * new Copilot token. Returns whether the token changed.
+ */
+export async function reloadTokenFile(filePath: string): Promise<boolean> {
+  const token = await readTokenFile(filePath)
+  if (token === state.githubToken) return false
+
+  state.githubToken = token
+  await refreshCopilotToken()
+  consola.info(`Reloaded GitHub token from ${filePath}`)
+  return true
+}
+
+/**
+ * Reloads the token whenever the file changes. The directory is watched
+ * rather than the file, because Kubernetes updates mounted secrets by
+ * swapping a symlink, which a watch on the old file would not see.
+ */
+export function watchTokenFile(filePath: string, debounceMs = 500): FSWatcher {
+  let timer: ReturnType<typeof setTimeout> | undefined
+  const reload = async () => {
+    try {
+      await reloadTokenFile(filePath)
+    } catch (error) {
+      consola.warn(
+        `Failed to reload GitHub token from ${filePath}:`,
+        (error as Error).message,
+      )
+    }
+  }
+
+  return fsSync.watch(path.dirname(filePath), () => {
+    // Editors and secret updates often write in several steps
+    clearTimeout(timer)
+    timer = setTimeout(() => void reload(), debounceMs)
+  })
+}
@@ -42,6 +42,15 @@ export const setupCopilotToken = async () => {
   }, refreshInterval)
 }
 
+/** Exchanges the current GitHub token for a Copilot token right away. */
+export async function refreshCopilotToken(): Promise<void> {
+  const { token } = await getCopilotToken()
+  state.copilotToken = token
+  if (state.showToken) {
+    consola.info("Refreshed Copilot token:", token)
+  }
+}
+
 interface SetupGitHubTokenOptions {
   force?: boolean
 }
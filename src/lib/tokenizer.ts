@@ -4,7 +4,9 @@ import type { Message } from "~/services/copilot/create-chat-completions"
 
 import { isNullish } from "./utils"
 
-export const getTokenCount = (messages: Array<Message>) => {
+export type CountTokens = typeof countTokens
+
+export const simplifyMessages = (messages: Array<Message>) => {
   const sanitized = messages.map((message) => ({
     ...message,
     content:
@@ -13,7 +15,7 @@ export const getTokenCount = (messages: Array<Message>) => {
       ),
   }))
 
-  const simplifiedMessages = sanitized.map((message) => {
+  return sanitized.map((message) => {
     let content = ""
     if (typeof message.content === "string") {
       content = message.content
@@ -23,28 +25,46 @@ export const getTokenCount = (messages: Array<Message>) => {
         .map((part) => (part as { text: string }).text)
         .join("")
     }
+    // Same "name(arguments)" lines as the native counter
+    const calls = (message.tool_calls ?? []).map(
+      (call) => `${call.function.name}(${call.function.arguments})`,
+    )
+    content = [...(content ? [content] : []), ...calls].join("\n")
     return { ...message, content }
   })
+}
 
-  let inputMessages = simplifiedMessages.filter((message) => {
-    return message.role !== "tool"
-  })
-  let outputMessages: typeof simplifiedMessages = []
+export const getTokenCount = (
+  messages: Array<Message>,
+  countChatTokens: CountTokens = countTokens,
+) => {
+  const simplifiedMessages = simplifyMessages(messages)
 
+  // A trailing assistant message is the output, tool results are not counted
   const lastMessage = simplifiedMessages.at(-1)
-
-  if (lastMessage?.role === "assistant") {
-    inputMessages = simplifiedMessages.slice(0, -1)
-    outputMessages = [lastMessage]
-  }
+  const outputMessages = lastMessage?.role === "assistant" ? [lastMessage] : []
+  const inputMessages = simplifiedMessages
+    .slice(0, simplifiedMessages.length - outputMessages.length)
+    .filter((message) => message.role !== "tool")
 
   // @ts-expect-error TS can't infer from arr.filter()
-  const inputTokens = countTokens(inputMessages)
+  const inputTokens = countChatTokens(inputMessages)
   // @ts-expect-error TS can't infer from arr.filter()
-  const outputTokens = countTokens(outputMessages)
+  const outputTokens = countChatTokens(outputMessages)
 
   return {
     input: inputTokens,
     output: outputTokens,
   }
 }
+
+/**
+ * Tokens of every message the model reads, tool results included, for
+ * checks against its context window.
+ */
+export const getPromptTokenCount = (
+  messages: Array<Message>,
+  countChatTokens: CountTokens = countTokens,
+): number =>
+  // @ts-expect-error TS can't infer from arr.map()
+  countChatTokens(simplifyMessages(messages))
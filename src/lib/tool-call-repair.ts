@@ -0,0 +1,171 @@
+// Opt-in repair of malformed `tool_calls[].function.arguments`. Models cut
+// off by max_tokens leave truncated JSON, and some emit trailing commas.
+
+import type {
+  ChatCompletionChunk,
+  ChatCompletionResponse,
+} from "~/services/copilot/create-chat-completions"
+
+export const REPAIR_HEADER = "x-tool-calls-repaired"
+
+const isValidJson = (text: string) => {
+  try {
+    JSON.parse(text)
+    return true
+  } catch {
+    return false
+  }
+}
+
+function scan(text: string) {
+  const closers: Array<string> = []
+  let inString = false
+  let escaped = false
+  for (const char of text) {
+    if (inString) {
+      if (escaped) escaped = false
+      else if (char === "\\") escaped = true
+      else if (char === '"') inString = false
+      continue
+    }
+    if (char === '"') inString = true
+    else if (char === "{") closers.push("}")
+    else if (char === "[") closers.push("]")
+    else if (char === "}" || char === "]") closers.pop()
+  }
+  return { closers, inString, escaped }
+}
+
+/**
+ * The text to append so that truncated JSON parses: closes an open string
+ * and balances brackets, filling in a missing value with `null`. Returns
+ * undefined if appending cannot fix it.
+ */
+export function completeJson(text: string): string | undefined {
+  if (isValidJson(text)) return ""
+
+  const { closers, inString, escaped } = scan(text)
+  const prefix = (escaped ? "\\" : "") + (inString ? '"' : "")
+  const closing = closers.toReversed().join("")
+  for (const value of ["", "null", ":null"]) {
+    const suffix = prefix + value + closing
+    if (isValidJson(text + suffix)) return suffix
+  }
+  return undefined
+}
+
+// Commas directly before a closing bracket or at the very end
+function removeTrailingCommas(text: string): string {
+  let result = ""
+  let inString = false
+  let escaped = false
+  for (let i = 0; i < text.length; i++) {
+    const char = text.charAt(i)
+    if (inString) {
+      if (escaped) escaped = false
+      else if (char === "\\") escaped = true
+      else if (char === '"') inString = false
+    } else if (char === '"') {
+      inString = true
+    } else if (char === ",") {
+      const next = text.slice(i + 1).trimStart().at(0)
+      if (next === undefined || next === "}" || next === "]") continue
+    }
+    result += char
+  }
+  return result
+}
+
+/** Valid JSON for `text`, or undefined if it cannot be repaired. */
+export function repairJson(text: string): string | undefined {
+  if (isValidJson(text)) return text
+  // Nothing was streamed before the cut-off
+  if (!text.trim()) return "{}"
+
+  const cleaned = removeTrailingCommas(text)
+  const suffix = completeJson(cleaned)
+  return suffix === undefined ? undefined : cleaned + suffix
+}
+
+/** Repairs every tool call in a non-streaming response. */
+export function repairToolCalls(response: ChatCompletionResponse): {
+  response: ChatCompletionResponse
+  repaired: number
+} {
+  let repaired = 0
+  const choices = response.choices.map((choice) => {
+    if (!choice.message.tool_calls) return choice
+
+    const toolCalls = choice.message.tool_calls.map((call) => {
+      const fixed = repairJson(call.function.arguments)
+      if (fixed === undefined || fixed === call.function.arguments) return call
+      repaired++
+      return { ...call, function: { ...call.function, arguments: fixed } }
+    })
+    return { ...choice, message: { ...choice.message, tool_calls: toolCalls } }
+  })
+  return { response: { ...response, choices }, repaired }
+}
+
+/**
+ * Streaming counterpart. Arguments are only complete once a choice
+ * finishes, and what was already sent cannot be changed, so truncated
+ * arguments are completed by inserting one more delta before the finish.
+ */
+export function createToolCallStreamRepairer() {
+  // Accumulated arguments by choice and tool call index
+  const argumentsSoFar = new Map<number, Map<number, string>>()
+  let repaired = 0
+
+  return {
+    get repaired() {
+      return repaired
+    },
+    /** The chunks to send in place of `chunk`. */
+    process(chunk: ChatCompletionChunk): Array<ChatCompletionChunk> {
+      const output: Array<ChatCompletionChunk> = []
+      for (const choice of chunk.choices) {
+        const calls = argumentsSoFar.get(choice.index) ?? new Map<number, string>()
+        argumentsSoFar.set(choice.index, calls)
+        for (const call of choice.delta.tool_calls ?? []) {
+          calls.set(
+            call.index,
+            (calls.get(call.index) ?? "") + (call.function?.arguments ?? ""),
+          )
+        }
+        if (!choice.finish_reason) continue
+
+        const fixes = [...calls].flatMap(([index, args]) => {
+          const suffix = args.trim() ? completeJson(args) : "{}"
+          return suffix ? [{ index, function: { arguments: suffix } }] : []
+        })
+        if (fixes.length === 0) continue
+
+        repaired += fixes.length
+        // Send this choice's delta, then the fixes, then the finish
+        output.push(
+          {
+            ...chunk,
+            choices: [{ ...choice, finish_reason: null }],
+            usage: undefined,
+          },
+          {
+            ...chunk,
+            choices: [
+              {
+                index: choice.index,
+                delta: { tool_calls: fixes },
+                finish_reason: null,
+                logprobs: null,
+              },
+            ],
+            usage: undefined,
+          },
+        )
+        choice.delta = {}
+      }
+      output.push(chunk)
+      return output
+    },
+  }
+}
@@ -0,0 +1,155 @@
+// Ordered list of Copilot base URLs, such as regional endpoints. Requests go
+// to the first upstream whose circuit is closed. Failing to connect opens
+// the circuit after a few attempts in a row, and a periodic health check
+// closes it again, which fails back to the primary.
+
+import consola from "consola"
+
+import { copilotBaseUrl, copilotHeaders } from "./api-config"
+import { withUpstreamAgent } from "./http-options"
+import { state } from "./state"
+
+interface Upstream {
+  url: string
+  // Connection failures in a row, reset by any response
+  failures: number
+  open: boolean
+  // Requests answered, and how many of those failed over to get here
+  served: number
+  failedOver: number
+}
+
+const config = {
+  failureThreshold: 3,
+  healthCheckMs: 30_000,
+  healthCheckTimeoutMs: 5000,
+}
+
+let upstreams: Array<Upstream> = []
+
+/** Parses a comma-separated list of base URLs, without trailing slashes. */
+export function parseUpstreams(value: string): Array<string> {
+  return value
+    .split(",")
+    .map((entry) => entry.trim().replace(/\/+$/, ""))
+    .filter(Boolean)
+    .map((url) => {
+      if (!/^https?:\/\/[^/]/.test(url)) {
+        throw new Error(`Invalid upstream URL: ${url}`)
+      }
+      return url
+    })
+}
+
+export function configureUpstreams(
+  urls: Array<string>,
+  options: Partial<typeof config> = {},
+): void {
+  Object.assign(config, options)
+  upstreams = urls.map((url) => ({
+    url,
+    failures: 0,
+    open: false,
+    served: 0,
+    failedOver: 0,
+  }))
+}
+
+export function upstreamStatus() {
+  return upstreams.map(({ url, open, served, failedOver }) => ({
+    url,
+    state: open ? ("open" as const) : ("closed" as const),
+    served,
+    failedOver,
+  }))
+}
+
+// Open circuits are only tried once every upstream is open
+const candidates = (): Array<Upstream> => {
+  const closed = upstreams.filter((upstream) => !upstream.open)
+  return closed.length > 0 ? closed : upstreams
+}
+
+/** Where the next request goes first, e.g. for dry runs. */
+export const preferredUpstream = (): string =>
+  candidates()[0]?.url ?? copilotBaseUrl(state)
+
+function recordFailure(upstream: Upstream, error: unknown): void {
+  upstream.failures++
+  if (!upstream.open && upstream.failures >= config.failureThreshold) {
+    upstream.open = true
+    consola.warn(
+      `Upstream ${upstream.url} unreachable ${upstream.failures} times in a row, opening its circuit:`,
+      (error as Error).message,
+    )
+  }
+}
+
+function recordSuccess(upstream: Upstream): void {
+  upstream.failures = 0
+  if (upstream.open) {
+    upstream.open = false
+    consola.info(`Upstream ${upstream.url} is reachable again`)
+  }
+}
+
+// A client that went away is not the upstream's fault
+const isAbort = (error: unknown) =>
+  error instanceof Error
+  && (error.name === "AbortError" || error.name === "TimeoutError")
+
+/**
+ * Calls `send` with the base URL of each upstream in turn until one
+ * answers. Any response counts, errors included: only failing to connect
+ * moves on to the next upstream. Without configured upstreams this is
+ * `send(copilotBaseUrl(state))`.
+ */
+export async function sendToUpstream(
+  send: (baseUrl: string) => Promise<Response>,
+): Promise<Response> {
+  if (upstreams.length === 0) return send(copilotBaseUrl(state))
+
+  const primary = upstreams[0]
+  let lastError: unknown
+  for (const upstream of candidates()) {
+    try {
+      const response = await send(upstream.url)
+      recordSuccess(upstream)
+      upstream.served++
+      if (upstream !== primary) upstream.failedOver++
+      return response
+    } catch (error) {
+      if (isAbort(error)) throw error
+      lastError = error
+      recordFailure(upstream, error)
+    }
+  }
+  throw lastError
+}
+
+/** Probes every upstream with an open circuit once. */
+export async function checkUpstreamHealth(): Promise<void> {
+  await Promise.all(
+    upstreams
+      .filter((upstream) => upstream.open)
+      .map(async (upstream) => {
+        try {
+          const response = await fetch(
+            `${upstream.url}/models`,
+            withUpstreamAgent({
+              headers: copilotHeaders(state),
+              signal: AbortSignal.timeout(config.healthCheckTimeoutMs),
+            }),
+          )
+          await response.body?.cancel()
+          if (response.status < 500) recordSuccess(upstream)
+        } catch {
+          // Still unreachable, the circuit stays open
+        }
+      }),
+  )
+}
+
+export function startUpstreamHealthChecks(): void {
+  setInterval(() => void checkUpstreamHealth(), config.healthCheckMs).unref()
+}
@@ -0,0 +1,139 @@
+import type { Context } from "hono"
+
+import consola from "consola"
+
+import { accountingStore } from "./accounting-store"
+import { apiKeyOf, keyId } from "./api-key"
+import { experimentOf, type ExperimentArm } from "./experiments"
+import {
+  countTaggedUsage,
+  matchesTag,
+  tagsOf,
+  type RequestTags,
+} from "./request-tags"
+import { state } from "./state"
+
+// Token counts per request and API key, for chargeback reports
+export interface UsageRecord {
+  time: string
+  // keyId() of the client's API key
+  key: string
+  endpoint: string
+  model: string
+  prompt_tokens: number
+  completion_tokens: number
+  // Set for requests in an --experiments experiment
+  experiment?: string
+  arm?: ExperimentArm
+  // From `metadata`, `user` and the x-gateway-tags header
+  tags?: RequestTags
+}
+
+export interface UsageSummary {
+  day: string
+  model: string
+  requests: number
+  prompt_tokens: number
+  completion_tokens: number
+  total_tokens: number
+}
+
+/**
+ * Appends the request's token counts to the usage ledger. Only the totals
+ * by tag in /stats are kept unless enabled with `--record-usage`. Like the
+ * audit log, write failures are logged rather than failing the request.
+ */
+export async function recordUsage(
+  c: Context,
+  entry: {
+    endpoint: string
+    model: string
+    usage?: { prompt_tokens?: number; completion_tokens?: number } | null
+  },
+): Promise<void> {
+  const tags = tagsOf(c.req.raw)
+  countTaggedUsage(tags, entry.usage)
+  if (!state.recordUsage) return
+
+  const record: UsageRecord = {
+    time: new Date().toISOString(),
+    key: keyId(apiKeyOf(c)),
+    endpoint: entry.endpoint,
+    model: entry.model,
+    prompt_tokens: entry.usage?.prompt_tokens ?? 0,
+    completion_tokens: entry.usage?.completion_tokens ?? 0,
+    ...experimentOf(c.req.raw),
+    tags,
+  }
+  try {
+    await accountingStore().appendUsage(record)
+  } catch (error) {
+    consola.warn("Failed to write usage record:", (error as Error).message)
+  }
+}
+
+/**
+ * Reads records at or after `since`, oldest first, of one key id and with
+ * a tag matching `tag` (see matchesTag) when given.
+ */
+export async function readUsage(options: {
+  key?: string
+  tag?: string
+  since?: string
+}): Promise<Array<UsageRecord>> {
+  const records = await accountingStore().readUsage(options)
+  const { tag } = options
+  return tag === undefined ? records : (
+      records.filter((record) => matchesTag(record.tags, tag))
+    )
+}
+
+/** Totals by UTC day and model, sorted by day then model. */
+export function summarizeUsage(
+  records: Array<UsageRecord>,
+): Array<UsageSummary> {
+  const groups = new Map<string, UsageSummary>()
+  for (const record of records) {
+    const day = record.time.slice(0, 10)
+    const id = `${day}\n${record.model}`
+    const group = groups.get(id) ?? {
+      day,
+      model: record.model,
+      requests: 0,
+      prompt_tokens: 0,
+      completion_tokens: 0,
+      total_tokens: 0,
+    }
+    group.requests++
+    group.prompt_tokens += record.prompt_tokens
+    group.completion_tokens += record.completion_tokens
+    group.total_tokens += record.prompt_tokens + record.completion_tokens
+    groups.set(id, group)
+  }
+  return [...groups.values()].sort(
+    (a, b) => a.day.localeCompare(b.day) || a.model.localeCompare(b.model),
+  )
+}
+
+const csvField = (value: string | number) => {
+  const text = String(value)
+  return /[",\n]/.test(text) ? `"${text.replaceAll('"', '""')}"` : text
+}
+
+export function formatUsageCsv(rows: Array<UsageSummary>): string {
+  const header =
+    "day,model,requests,prompt_tokens,completion_tokens,total_tokens"
+  const lines = rows.map((row) =>
+    [
+      row.day,
+      row.model,
+      row.requests,
+      row.prompt_tokens,
+      row.completion_tokens,
+      row.total_tokens,
+    ]
+      .map((value) => csvField(value))
+      .join(","),
+  )
+  return `${[header, ...lines].join("\n")}\n`
+}
@@ -0,0 +1,165 @@
+// Polls the premium request quota of the gateway's account and projects how
+// much of it will be used by the reset date, so running out shows up days
+// before it happens.
+
+import consola from "consola"
+import fs from "node:fs/promises"
+
+import { getCopilotUsage } from "~/services/github/get-copilot-usage"
+
+import { PATHS } from "./paths"
+
+export interface UsageSnapshot {
+  time: string
+  // Premium requests used in the cycle ending on resetDate
+  used: number
+  entitlement: number
+  resetDate: string
+}
+
+export interface Projection {
+  // Premium requests used by the reset date at this pace
+  projected: number
+  // When the entitlement runs out at this pace, if before the reset date
+  exhaustsAt?: string
+}
+
+export interface UsageProjection {
+  resetDate: string
+  entitlement: number
+  used: number
+  snapshots: number
+  // Pace of the whole cycle so far
+  linear: Projection
+  // Pace of the last RECENT_HOURS, once snapshots span at least an hour
+  recent?: Projection
+}
+
+const RECENT_HOURS = 24
+const HOUR_MS = 3_600_000
+
+// Oldest first, only those of the current cycle
+const snapshots: Array<UsageSnapshot> = []
+
+// Quota cycles are monthly and end at the reset date
+function cycleStart(resetDate: string): number {
+  const start = new Date(resetDate)
+  start.setUTCMonth(start.getUTCMonth() - 1)
+  return start.getTime()
+}
+
+function project(
+  used: number,
+  entitlement: number,
+  perMs: number,
+  now: number,
+  resetAt: number,
+): Projection {
+  const projected = Math.round(used + perMs * Math.max(0, resetAt - now))
+  if (projected <= entitlement || perMs <= 0) return { projected }
+  const exhaustsAt = now + Math.max(0, entitlement - used) / perMs
+  return { projected, exhaustsAt: new Date(exhaustsAt).toISOString() }
+}
+
+/** Projects end-of-cycle usage from the snapshots taken so far. */
+export function projectUsage(now = Date.now()): UsageProjection | undefined {
+  const latest = snapshots.at(-1)
+  if (!latest) return undefined
+
+  const resetAt = Date.parse(latest.resetDate)
+  const start = cycleStart(latest.resetDate)
+  const linear = project(
+    latest.used,
+    latest.entitlement,
+    latest.used / Math.max(HOUR_MS, now - start),
+    now,
+    resetAt,
+  )
+
+  const since = Date.parse(latest.time) - RECENT_HOURS * HOUR_MS
+  const first = snapshots.find((snapshot) => Date.parse(snapshot.time) >= since)
+  const span = first ? Date.parse(latest.time) - Date.parse(first.time) : 0
+  const recent =
+    first && span >= HOUR_MS ?
+      project(
+        latest.used,
+        latest.entitlement,
+        (latest.used - first.used) / span,
+        now,
+        resetAt,
+      )
+    : undefined
+
+  return {
+    resetDate: latest.resetDate,
+    entitlement: latest.entitlement,
+    used: latest.used,
+    snapshots: snapshots.length,
+    linear,
+    recent,
+  }
+}
+
+/** Adds a snapshot, dropping those of earlier cycles. */
+export function addUsageSnapshot(snapshot: UsageSnapshot): void {
+  if (snapshots.at(-1)?.resetDate !== snapshot.resetDate) snapshots.length = 0
+  snapshots.push(snapshot)
+}
+
+export async function pollUsage(now = Date.now()): Promise<void> {
+  let snapshot: UsageSnapshot
+  try {
+    const usage = await getCopilotUsage()
+    const premium = usage.quota_snapshots.premium_interactions
+    // Nothing to run out of
+    if (premium.unlimited) return
+    snapshot = {
+      time: new Date(now).toISOString(),
+      used: premium.entitlement - premium.quota_remaining,
+      entitlement: premium.entitlement,
+      resetDate: usage.quota_reset_date,
+    }
+  } catch (error) {
+    consola.warn("Failed to poll Copilot usage:", error)
+    return
+  }
+
+  addUsageSnapshot(snapshot)
+  try {
+    await fs.appendFile(
+      PATHS.USAGE_SNAPSHOTS_PATH,
+      `${JSON.stringify(snapshot)}\n`,
+      { mode: 0o600 },
+    )
+  } catch (error) {
+    consola.warn("Failed to store usage snapshot:", (error as Error).message)
+  }
+}
+
+/** Reads the stored snapshots of the current cycle back after a restart. */
+export async function loadUsageSnapshots(): Promise<void> {
+  let content: string
+  try {
+    content = await fs.readFile(PATHS.USAGE_SNAPSHOTS_PATH, "utf8")
+  } catch {
+    return
+  }
+  for (const line of content.split("\n")) {
+    if (!line) continue
+    try {
+      addUsageSnapshot(JSON.parse(line) as UsageSnapshot)
+    } catch {
+      // A line cut short by a crash
+    }
+  }
+}
+
+export async function startUsagePolling(minutes: number): Promise<void> {
+  await loadUsageSnapshots()
+  void pollUsage()
+  setInterval(() => void pollUsage(), minutes * 60_000).unref()
+}
+
+export function resetUsageSnapshots(): void {
+  snapshots.length = 0
+}
@@ -1,6 +1,6 @@
 import consola from "consola"
 
-import { getModels } from "~/services/copilot/get-models"
+import { getModels, type ModelsResponse } from "~/services/copilot/get-models"
 import { getVSCodeVersion } from "~/services/get-vscode-version"
 
 import { state } from "./state"
@@ -16,6 +16,64 @@ export const isNullish = (value: unknown): value is null | undefined =>
 export async function cacheModels(): Promise<void> {
   const models = await getModels()
   state.models = models
+  state.modelsCachedAt = Date.now()
+}
+
+let modelsRefresh: Promise<void> | undefined
+
+/**
+ * Returns the cached model list. Once it is older than the TTL it is
+ * refreshed in the background, so a slow upstream never delays the caller;
+ * the stale list is served until the refresh succeeds.
+ */
+export async function getCachedModels(
+  now = Date.now(),
+): Promise<ModelsResponse | undefined> {
+  if (!state.models) {
+    await cacheModels()
+    return state.models
+  }
+
+  const age = now - (state.modelsCachedAt ?? 0)
+  if (age > state.modelsTtlSeconds * 1000 && !modelsRefresh) {
+    modelsRefresh = cacheModels()
+      .catch((error: unknown) => {
+        consola.warn("Failed to refresh models, serving cached list:", error)
+      })
+      .finally(() => {
+        modelsRefresh = undefined
+      })
+  }
+  return state.models
+}
+
+/** Aliases that resolve to `modelId`, not counting the default model. */
+export function aliasesFor(modelId: string): Array<string> {
+  return Object.entries(state.modelAliases ?? {})
+    .filter(([, target]) => target === modelId)
+    .map(([alias]) => alias)
+}
+
+export function resolveModel(model: string): string {
+  const aliased = state.modelAliases?.[model]
+  if (aliased) {
+    consola.debug(`Resolving model alias ${model} to ${aliased}`)
+    return aliased
+  }
+
+  if (!state.defaultModel) return model
+
+  // Without a model list every named model has to be trusted
+  const known =
+    state.models === undefined ?
+      Boolean(model)
+    : state.models.data.some((candidate) => candidate.id === model)
+  if (known) return model
+
+  consola.debug(
+    `Rewriting model ${model || "(none)"} to default ${state.defaultModel}`,
+  )
+  return state.defaultModel
 }
 
 export const cacheVSCodeVersion = async () => {
@@ -0,0 +1,13 @@
+import type { ServerRequest } from "srvx"
+
+import { createBunWebSocket } from "hono/bun"
+
+// Bun upgrades the connection through its server, which Hono reads from the
+// second fetch argument. Other runtimes get no server and answer 501.
+export const { upgradeWebSocket, websocket } = createBunWebSocket()
+
+export const isWebSocketSupported = () => typeof Bun !== "undefined"
+
+export const bunServerEnv = (request: ServerRequest) => ({
+  server: request.runtime?.bun?.server,
+})
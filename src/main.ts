@@ -1,17 +1,40 @@
 #!/usr/bin/env node
 
-import { defineCommand, runMain } from "citty"
+import { defineCommand, runMain, type CommandDef } from "citty"
 
+import { audit } from "./audit"
 import { auth } from "./auth"
+import { exportBackup, importBackup } from "./backup"
+import { createCompletionsCommand, createManCommand } from "./completions"
+import { doctor } from "./doctor"
+import { models } from "./models"
+import { report } from "./report"
+import { service } from "./service"
 import { start } from "./start"
+import { team } from "./team"
+import { tokenize } from "./tokenize"
 
-const main = defineCommand({
+const main: CommandDef = defineCommand({
   meta: {
     name: "copilot-api",
     description:
       "A wrapper around GitHub Copilot API to make it OpenAI compatible, making it usable for other tools.",
   },
-  subCommands: { auth, start },
+  subCommands: {
+    auth,
+    start,
+    doctor,
+    models,
+    tokenize,
+    service,
+    audit,
+    report,
+    team,
+    export: exportBackup,
+    import: importBackup,
+    completions: createCompletionsCommand(() => main),
+    man: createManCommand(() => main),
+  },
 })
 
 await runMain(main)
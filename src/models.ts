@@ -0,0 +1,132 @@
+#!/usr/bin/env node
+
+import { defineCommand } from "citty"
+import consola from "consola"
+
+import { ensurePaths } from "./lib/paths"
+import { state } from "./lib/state"
+import { setupGitHubToken } from "./lib/token"
+import { cacheVSCodeVersion } from "./lib/utils"
+import { getModels, type Model } from "./services/copilot/get-models"
+import { getCopilotToken } from "./services/github/get-copilot-token"
+
+interface RunModelsOptions {
+  verbose: boolean
+  accountType: string
+  githubToken?: string
+  json: boolean
+}
+
+interface ModelRow {
+  id: string
+  contextWindow?: number
+  maxOutput?: number
+  vision: boolean
+  tools: boolean
+}
+
+const toRow = (model: Model): ModelRow => ({
+  id: model.id,
+  contextWindow: model.capabilities.limits.max_context_window_tokens,
+  maxOutput: model.capabilities.limits.max_output_tokens,
+  vision: Boolean(model.capabilities.supports.vision),
+  tools: Boolean(model.capabilities.supports.tool_calls),
+})
+
+function formatTable(rows: Array<ModelRow>): string {
+  const header = ["ID", "CONTEXT", "MAX OUTPUT", "VISION", "TOOLS"]
+  const cells = rows.map((row) => [
+    row.id,
+    row.contextWindow?.toString() ?? "-",
+    row.maxOutput?.toString() ?? "-",
+    row.vision ? "yes" : "no",
+    row.tools ? "yes" : "no",
+  ])
+
+  const widths = header.map((title, column) =>
+    Math.max(title.length, ...cells.map((cell) => cell[column].length)),
+  )
+
+  return [header, ...cells]
+    .map((cell) =>
+      cell
+        .map((value, column) => value.padEnd(widths[column]))
+        .join("  ")
+        .trimEnd(),
+    )
+    .join("\n")
+}
+
+export async function runModels(options: RunModelsOptions): Promise<void> {
+  if (options.verbose) {
+    consola.level = 5
+    consola.info("Verbose logging enabled")
+  } else if (options.json) {
+    // Keep stdout clean for scripting
+    consola.level = 1
+  }
+
+  state.accountType = options.accountType
+
+  await ensurePaths()
+  await cacheVSCodeVersion()
+
+  if (options.githubToken) {
+    state.githubToken = options.githubToken
+  } else {
+    await setupGitHubToken()
+  }
+
+  const { token } = await getCopilotToken()
+  state.copilotToken = token
+
+  const models = await getModels()
+  const rows = models.data.map((model) => toRow(model))
+
+  if (options.json) {
+    process.stdout.write(`${JSON.stringify(rows, null, 2)}\n`)
+    return
+  }
+
+  process.stdout.write(`${formatTable(rows)}\n`)
+}
+
+export const models = defineCommand({
+  meta: {
+    name: "models",
+    description: "List available models and their capabilities",
+  },
+  args: {
+    verbose: {
+      alias: "v",
+      type: "boolean",
+      default: false,
+      description: "Enable verbose logging",
+    },
+    "account-type": {
+      alias: "a",
+      type: "string",
+      default: "individual",
+      description: "Account type to use (individual, business, enterprise)",
+    },
+    "github-token": {
+      alias: "g",
+      type: "string",
+      description:
+        "Provide GitHub token directly (must be generated using the `auth` subcommand)",
+    },
+    json: {
+      type: "boolean",
+      default: false,
+      description: "Print the model list as JSON",
+    },
+  },
+  run({ args }) {
+    return runModels({
+      verbose: args.verbose,
+      accountType: args["account-type"],
+      githubToken: args["github-token"],
+      json: args.json,
+    })
+  },
+})
@@ -0,0 +1,94 @@
+#!/usr/bin/env node
+
+import { defineCommand } from "citty"
+
+import { withAccountingStore } from "./lib/accounting-store"
+import { keyId } from "./lib/api-key"
+import { loadEnvConfig } from "./lib/env-config"
+import {
+  formatUsageCsv,
+  readUsage,
+  summarizeUsage,
+} from "./lib/usage-ledger"
+
+interface RunReportOptions {
+  key?: string
+  tag?: string
+  since?: string
+  format: string
+  accountingStore?: string
+}
+
+export async function runReport(options: RunReportOptions): Promise<void> {
+  if (options.format !== "csv" && options.format !== "json") {
+    throw new TypeError(`Invalid format: ${options.format}, use csv or json`)
+  }
+  let since: string | undefined
+  if (options.since) {
+    const date = new Date(options.since)
+    if (Number.isNaN(date.getTime())) {
+      throw new TypeError(`Invalid date: ${options.since}`)
+    }
+    since = date.toISOString()
+  }
+
+  // The ledger stores key ids, never the keys themselves. A tag reports on
+  // all keys unless one is given.
+  const key =
+    options.tag !== undefined && options.key === undefined ?
+      undefined
+    : keyId(options.key)
+  const records = await withAccountingStore(
+    options.accountingStore ?? loadEnvConfig().accountingStore,
+    () => readUsage({ key, tag: options.tag, since }),
+  )
+  const rows = summarizeUsage(records)
+  process.stdout.write(
+    options.format === "csv" ?
+      formatUsageCsv(rows)
+    : `${JSON.stringify(rows, null, 2)}\n`,
+  )
+}
+
+export const report = defineCommand({
+  meta: {
+    name: "report",
+    description:
+      "Print token and request totals per day and model for one API key or tag, from `start --record-usage`",
+  },
+  args: {
+    key: {
+      type: "string",
+      description:
+        "API key to report on, as sent by the client (default: requests without a key)",
+    },
+    tag: {
+      type: "string",
+      description:
+        "Only requests with this tag, as key=value or a bare key for any value; all keys unless --key is given",
+    },
+    since: {
+      type: "string",
+      description: "Only requests at or after this date or ISO timestamp",
+    },
+    format: {
+      type: "string",
+      default: "json",
+      description: "Output format: csv or json",
+    },
+    "accounting-store": {
+      type: "string",
+      description:
+        "Database the usage records are kept in, as given to `start` (default: the files)",
+    },
+  },
+  run({ args }) {
+    return runReport({
+      key: args.key,
+      tag: args.tag,
+      since: args.since,
+      format: args.format,
+      accountingStore: args["accounting-store"],
+    })
+  },
+})
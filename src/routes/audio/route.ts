@@ -0,0 +1,14 @@
+import { Hono } from "hono"
+
+import { forwardToProvider } from "~/lib/media-providers"
+
+export const audioRoutes = new Hono()
+
+audioRoutes.post(
+  "/transcriptions",
+  forwardToProvider("audio", "/audio/transcriptions", "Audio transcription"),
+)
+audioRoutes.post(
+  "/speech",
+  forwardToProvider("audio", "/audio/speech", "Text to speech"),
+)
@@ -0,0 +1,19 @@
+import { Hono } from "hono"
+
+import { requireAdmin } from "~/lib/admin-auth"
+import { readAuditEntry } from "~/lib/audit"
+
+export const auditRoute = new Hono()
+
+auditRoute.use(requireAdmin)
+
+auditRoute.get("/:id", async (c) => {
+  const entry = await readAuditEntry(c.req.param("id"))
+  if (!entry) {
+    return c.json(
+      { error: { message: "No audit entry with this id", type: "error" } },
+      404,
+    )
+  }
+  return c.json(entry)
+})
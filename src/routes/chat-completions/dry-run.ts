@@ -0,0 +1,38 @@
+import { rustCore } from "~/lib/rust-core"
+import {
+  buildChatCompletionsRequest,
+  type ChatCompletionsPayload,
+} from "~/services/copilot/create-chat-completions"
+
+/**
+ * What `POST /chat/completions?dry_run=true` returns: the request that
+ * would have been sent upstream, after every rewrite the gateway applies.
+ */
+export async function describeDryRun(
+  payload: ChatCompletionsPayload,
+  tokenCount: { input: number; output: number },
+) {
+  const request = buildChatCompletionsRequest(payload)
+
+  let validation
+  try {
+    validation = await rustCore.validatePayloadDetailed(payload)
+  } catch {
+    // Validation lives in the native module
+    validation = { skipped: "native module not available" }
+  }
+
+  return {
+    dry_run: true,
+    upstream: {
+      ...request,
+      headers: {
+        ...request.headers,
+        // Never echo the Copilot token
+        Authorization: "Bearer [REDACTED]",
+      },
+    },
+    token_count: tokenCount,
+    validation,
+  }
+}
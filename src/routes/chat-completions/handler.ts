@@ -1,58 +1,258 @@
 import type { Context } from "hono"
+import type { SSEMessage } from "hono/streaming"
 
 import consola from "consola"
-import { streamSSE, type SSEMessage } from "hono/streaming"
 
 import { awaitApproval } from "~/lib/approval"
+import { createStreamTranscript, recordAudit } from "~/lib/audit"
+import {
+  applyRequestFilters,
+  applyResponseFilters,
+  setPolicyHeader,
+  type PolicyContext,
+} from "~/lib/content-policy"
+import { checkContextWindow } from "~/lib/context-window"
+import { routeExperiment } from "~/lib/experiments"
+import { gatewayOptionsOf, upstreamSignal } from "~/lib/gateway-options"
+import { observePromptCache, startStreamTimer } from "~/lib/metrics"
+import { checkModelAccess } from "~/lib/model-policy"
+import { streamChunks, streamFormatOf } from "~/lib/ndjson"
+import { scrubParams } from "~/lib/param-policy"
+import { applyPromptCacheKey } from "~/lib/prompt-cache"
 import { checkRateLimit } from "~/lib/rate-limit"
+import { annotateSample } from "~/lib/request-samples"
+import { tagRequest } from "~/lib/request-tags"
+import { recordSessionTurn } from "~/lib/sessions"
+import { mirrorRequest, outputOf } from "~/lib/shadow"
 import { state } from "~/lib/state"
-import { getTokenCount } from "~/lib/tokenizer"
-import { isNullish } from "~/lib/utils"
+import {
+  applyStopSequences,
+  createStopSequenceFilter,
+  stopSequencesOf,
+} from "~/lib/stop-sequences"
+import { openBroadcast, streamOwner } from "~/lib/stream-broadcast"
+import { summarizeToFit } from "~/lib/summarize"
+import { checkTokenBudget } from "~/lib/token-budget"
+import { getPromptTokenCount, getTokenCount } from "~/lib/tokenizer"
+import {
+  createToolCallStreamRepairer,
+  REPAIR_HEADER,
+  repairToolCalls,
+} from "~/lib/tool-call-repair"
+import { recordUsage } from "~/lib/usage-ledger"
+import { isNullish, resolveModel } from "~/lib/utils"
 import {
   createChatCompletions,
+  type ChatCompletionChunk,
   type ChatCompletionResponse,
   type ChatCompletionsPayload,
+  type Usage,
 } from "~/services/copilot/create-chat-completions"
 
+import { describeDryRun } from "./dry-run"
+import {
+  enforceSchema,
+  prepareStructuredOutput,
+  requestedSchema,
+  validateStructuredOutput,
+} from "./structured-output"
+
+// eslint-disable-next-line max-lines-per-function
 export async function handleCompletion(c: Context) {
-  await checkRateLimit(state)
+  const startedAt = performance.now()
+  // Shows what would be sent upstream without sending it or spending quota
+  const dryRun = c.req.query("dry_run") === "true"
+  if (!dryRun) await checkRateLimit(state)
+  const overrides = gatewayOptionsOf(c)
 
   let payload = await c.req.json<ChatCompletionsPayload>()
+  tagRequest(c, payload)
+  const streamFormat = streamFormatOf(c, payload)
+  payload = { ...payload, metadata: undefined, stream_format: undefined }
+  payload.model = routeExperiment(
+    c,
+    resolveModel(overrides.model ?? payload.model),
+  )
+  checkModelAccess(c, payload.model)
   for (const message of payload.messages) {
     if (isNullish((message as { content?: unknown }).content))
       (message as { content?: string }).content = ""
   }
   consola.debug("Request payload:", JSON.stringify(payload).slice(-400))
 
-  consola.info("Current token count:", getTokenCount(payload.messages))
+  const policy: PolicyContext = { annotations: [] }
+  const filters = state.contentFilters ?? []
+  payload = applyRequestFilters(filters, payload, policy)
+  payload =
+    overrides.noCache ?
+      { ...payload, prompt_cache_key: undefined }
+    : applyPromptCacheKey(payload)
+  // Summarizing is an upstream request of its own, so dry runs skip it
+  if (!dryRun) payload = await summarizeToFit(c, payload)
 
-  if (state.manualApprove) await awaitApproval()
+  if (!dryRun) await checkTokenBudget(c, payload.messages)
+
+  const tokenCount = getTokenCount(payload.messages)
+  consola.info("Current token count:", tokenCount)
+  annotateSample(c.req.raw, {
+    model: payload.model,
+    promptTokens: tokenCount.input,
+  })
+  const usableMaxTokens = checkContextWindow(
+    payload.model,
+    getPromptTokenCount(payload.messages),
+    payload.max_tokens ?? undefined,
+  )
 
   if (isNullish(payload.max_tokens)) {
     const selectedModel = state.models?.data.find(
       (model) => model.id === payload.model,
     )
+    const maxOutputTokens =
+      selectedModel?.capabilities.limits.max_output_tokens
 
     payload = {
       ...payload,
-      max_tokens: selectedModel?.capabilities.limits.max_output_tokens,
+      // Lowered to what fits with --context-check
+      max_tokens:
+        maxOutputTokens === undefined ? undefined : (
+          (usableMaxTokens ?? maxOutputTokens)
+        ),
     }
     consola.debug("Set max_tokens to:", JSON.stringify(payload.max_tokens))
   }
 
-  const response = await createChatCompletions(payload)
+  const schema = requestedSchema(payload)
+  payload = scrubParams(c, prepareStructuredOutput(payload))
+
+  if (dryRun) return c.json(await describeDryRun(payload, tokenCount))
+
+  if (state.manualApprove) await awaitApproval()
+
+  const recordShadow = mirrorRequest(payload)
+  let response = await createChatCompletions(payload, {
+    signal: upstreamSignal(overrides),
+  })
+  const stops = stopSequencesOf(payload.stop)
 
   if (isNonStreaming(response)) {
     consola.debug("Non-streaming response:", JSON.stringify(response))
-    return c.json(response)
+    if (stops.length > 0) response = applyStopSequences(response, stops).response
+    if (schema) response = await enforceSchema(c, payload, response, schema)
+    let filtered = applyResponseFilters(filters, response, policy)
+    if (state.repairToolCalls) {
+      const result = repairToolCalls(filtered)
+      filtered = result.response
+      if (result.repaired > 0) c.header(REPAIR_HEADER, String(result.repaired))
+    }
+    annotateSample(c.req.raw, {
+      completionTokens: response.usage?.completion_tokens,
+    })
+    observePromptCache(payload.model, response.usage)
+    recordShadow?.(outputOf(filtered))
+    setPolicyHeader(c, policy)
+    await recordAudit({
+      endpoint: "/chat/completions",
+      model: payload.model,
+      stream: false,
+      request: payload,
+      response: filtered,
+    })
+    await recordSessionTurn(c, {
+      endpoint: "/chat/completions",
+      model: payload.model,
+      request: payload,
+      response: filtered,
+    })
+    await recordUsage(c, {
+      endpoint: "/chat/completions",
+      model: payload.model,
+      usage: filtered.usage,
+    })
+    return c.json(filtered)
   }
 
   consola.debug("Streaming response")
-  return streamSSE(c, async (stream) => {
-    for await (const chunk of response) {
-      consola.debug("Streaming chunk:", JSON.stringify(chunk))
-      await stream.writeSSE(chunk as SSEMessage)
+  setPolicyHeader(c, policy)
+  const broadcast = openBroadcast(
+    "/chat/completions",
+    payload.model,
+    streamOwner(c),
+  )
+  // Observers attach at /admin/streams/<id>, and events carry ids for
+  // resuming with Last-Event-ID
+  c.header("x-stream-id", broadcast.info.id)
+  return streamChunks(c, streamFormat, async (stream) => {
+    const transcript = createStreamTranscript()
+    const timer = startStreamTimer(payload.model, startedAt)
+    let usage: Usage | undefined
+    const stopFilter =
+      stops.length > 0 ? createStopSequenceFilter(stops) : undefined
+    const repairer =
+      state.repairToolCalls ? createToolCallStreamRepairer() : undefined
+    try {
+      for await (const event of response) {
+        consola.debug("Streaming chunk:", JSON.stringify(event))
+        timer.chunk()
+        if (!event.data || event.data === "[DONE]") {
+          await stream.writeSSE(broadcast.publish(event as SSEMessage))
+          continue
+        }
+
+        const parsed = JSON.parse(event.data) as ChatCompletionChunk
+        let chunks = [parsed]
+        if (stopFilter) {
+          chunks = chunks.flatMap((chunk) => stopFilter.process(chunk))
+        }
+        if (repairer) {
+          chunks = chunks.flatMap((chunk) => repairer.process(chunk))
+        }
+        // Without post-processing, chunks are forwarded verbatim
+        const messages =
+          stopFilter || repairer ?
+            chunks.map((chunk) => ({ ...event, data: JSON.stringify(chunk) }))
+          : [event]
+        for (const message of messages) {
+          await stream.writeSSE(broadcast.publish(message as SSEMessage))
+          transcript.add(JSON.parse(message.data) as ChatCompletionChunk)
+        }
+        if (parsed.usage) {
+          usage = parsed.usage
+          observePromptCache(payload.model, parsed.usage)
+        }
+      }
+      if (repairer?.repaired) {
+        consola.warn(`Repaired ${repairer.repaired} streamed tool call(s)`)
+      }
+      // Streamed content has already been sent, so mismatches are only logged
+      const errors =
+        schema && validateStructuredOutput(transcript.result().content, schema)
+      if (errors && errors.length > 0) {
+        consola.warn("Streamed response does not match the schema:", errors)
+      }
+    } finally {
+      broadcast.close()
     }
+    timer.end()
+    recordShadow?.({ ...transcript.result(), usage })
+    await recordAudit({
+      endpoint: "/chat/completions",
+      model: payload.model,
+      stream: true,
+      request: payload,
+      response: transcript.result(),
+    })
+    await recordSessionTurn(c, {
+      endpoint: "/chat/completions",
+      model: payload.model,
+      request: payload,
+      response: transcript.result(),
+    })
+    await recordUsage(c, {
+      endpoint: "/chat/completions",
+      model: payload.model,
+      usage,
+    })
   })
 }
 
@@ -1,11 +1,27 @@
 import { Hono } from "hono"
 
+import { assignAuditId } from "~/lib/audit"
 import { forwardError } from "~/lib/error"
+import { idempotency } from "~/lib/idempotency"
+import { quotaGuard } from "~/lib/quota-guard"
+import { scheduleByPriority } from "~/lib/scheduler"
+import { resumeStream } from "~/lib/stream-resume"
+import { teamCredentials } from "~/lib/team"
 
 import { handleCompletion } from "./handler"
 
 export const completionRoutes = new Hono()
 
+// Checked first, so neither resumed streams nor cached answers skip it
+completionRoutes.use(teamCredentials)
+// Resumed streams replay buffered events and never reach the upstream
+completionRoutes.use(resumeStream)
+// Retries with the same Idempotency-Key are answered from the cache
+completionRoutes.use(idempotency)
+completionRoutes.use(quotaGuard)
+completionRoutes.use(scheduleByPriority)
+completionRoutes.use(assignAuditId)
+
 completionRoutes.post("/", async (c) => {
   try {
     return await handleCompletion(c)
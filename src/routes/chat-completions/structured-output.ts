@@ -0,0 +1,116 @@
+import type { Context } from "hono"
+
+import consola from "consola"
+
+import { validateJsonSchema, type JsonSchema } from "~/lib/json-schema"
+import { state } from "~/lib/state"
+import {
+  createChatCompletions,
+  type ChatCompletionResponse,
+  type ChatCompletionsPayload,
+} from "~/services/copilot/create-chat-completions"
+
+export const SCHEMA_HEADER = "x-schema-validation"
+
+/** The schema of a `json_schema` response format, if one was requested. */
+export function requestedSchema(
+  payload: ChatCompletionsPayload,
+): JsonSchema | undefined {
+  const format = payload.response_format
+  if (format?.type !== "json_schema") return undefined
+  return format.json_schema.schema ?? {}
+}
+
+/**
+ * Forwards `response_format` to models that support structured outputs.
+ * Other models get the schema as a system instruction instead.
+ */
+export function prepareStructuredOutput(
+  payload: ChatCompletionsPayload,
+): ChatCompletionsPayload {
+  const schema = requestedSchema(payload)
+  if (!schema) return payload
+
+  const model = state.models?.data.find((entry) => entry.id === payload.model)
+  if (model?.capabilities.supports.structured_outputs) return payload
+
+  return {
+    ...payload,
+    response_format: undefined,
+    messages: [
+      ...payload.messages,
+      {
+        role: "system",
+        content: `Respond only with JSON matching this JSON schema, without any other text:\n${JSON.stringify(schema)}`,
+      },
+    ],
+  }
+}
+
+/** Problems with `content` as output for `schema`, empty if it conforms. */
+export function validateStructuredOutput(
+  content: string | null,
+  schema: JsonSchema,
+): Array<string> {
+  let value: unknown
+  try {
+    value = JSON.parse(content ?? "")
+  } catch {
+    return ["$: not valid JSON"]
+  }
+  return validateJsonSchema(value, schema)
+}
+
+/** The payload for a second attempt, telling the model what was wrong. */
+export function correctivePayload(
+  payload: ChatCompletionsPayload,
+  content: string | null,
+  errors: Array<string>,
+): ChatCompletionsPayload {
+  return {
+    ...payload,
+    messages: [
+      ...payload.messages,
+      { role: "assistant", content },
+      {
+        role: "system",
+        content: `Your previous reply did not match the required JSON schema:\n${errors.join("\n")}\nReply again with only JSON that matches the schema.`,
+      },
+    ],
+  }
+}
+
+/**
+ * Validates the first choice against `schema`, retrying once with a
+ * corrective instruction when `--structured-output-retry` is set. The
+ * outcome is reported in the `x-schema-validation` header.
+ */
+export async function enforceSchema(
+  c: Context,
+  payload: ChatCompletionsPayload,
+  response: ChatCompletionResponse,
+  schema: JsonSchema,
+): Promise<ChatCompletionResponse> {
+  const content = response.choices.at(0)?.message.content ?? null
+  const errors = validateStructuredOutput(content, schema)
+  if (errors.length === 0) {
+    c.header(SCHEMA_HEADER, "valid")
+    return response
+  }
+
+  consola.warn("Response does not match the requested schema:", errors)
+  if (!state.structuredOutputRetry) {
+    c.header(SCHEMA_HEADER, "invalid")
+    return response
+  }
+
+  const retried = (await createChatCompletions(
+    correctivePayload(payload, content, errors),
+  )) as ChatCompletionResponse
+  const retryErrors = validateStructuredOutput(
+    retried.choices.at(0)?.message.content ?? null,
+    schema,
+  )
+  c.header(SCHEMA_HEADER, retryErrors.length === 0 ? "retried" : "invalid")
+  return retried
+}
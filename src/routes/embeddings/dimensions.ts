@@ -0,0 +1,33 @@
+import type { EmbeddingResponse } from "~/services/copilot/create-embeddings"
+
+/**
+ * Shortens a vector to `dimensions` and rescales it to unit length, which
+ * is how OpenAI's `text-embedding-3` models implement the parameter.
+ * Vectors that are already short enough are returned unchanged.
+ */
+export function reduceDimensions(
+  embedding: Array<number>,
+  dimensions: number,
+): Array<number> {
+  if (embedding.length <= dimensions) return embedding
+
+  const truncated = embedding.slice(0, dimensions)
+  const norm = Math.hypot(...truncated)
+  return norm === 0 ? truncated : truncated.map((value) => value / norm)
+}
+
+/** Applies `dimensions` for upstream models that ignore it. */
+export function applyDimensions(
+  response: EmbeddingResponse,
+  dimensions: number | undefined,
+): EmbeddingResponse {
+  if (dimensions === undefined) return response
+
+  return {
+    ...response,
+    data: response.data.map((item) => ({
+      ...item,
+      embedding: reduceDimensions(item.embedding, dimensions),
+    })),
+  }
+}
@@ -0,0 +1,43 @@
+import type {
+  Embedding,
+  EmbeddingResponse,
+} from "~/services/copilot/create-embeddings"
+
+export type EncodingFormat = "float" | "base64"
+
+export interface EncodedEmbeddingResponse
+  extends Omit<EmbeddingResponse, "data"> {
+  data: Array<Omit<Embedding, "embedding"> & { embedding: string }>
+}
+
+/**
+ * Packs a vector as little-endian float32 values in base64, the format
+ * OpenAI returns for `encoding_format: "base64"`.
+ */
+export function encodeBase64(embedding: Array<number>): string {
+  const bytes = new Uint8Array(embedding.length * 4)
+  const view = new DataView(bytes.buffer)
+  for (const [index, value] of embedding.entries()) {
+    view.setFloat32(index * 4, value, true)
+  }
+  return Buffer.from(bytes).toString("base64")
+}
+
+/**
+ * Encodes the vectors when base64 was requested. The upstream is always
+ * asked for floats, so `dimensions` can be applied before encoding.
+ */
+export function applyEncodingFormat(
+  response: EmbeddingResponse,
+  format: EncodingFormat | undefined,
+): EmbeddingResponse | EncodedEmbeddingResponse {
+  if (format !== "base64") return response
+
+  return {
+    ...response,
+    data: response.data.map((item) => ({
+      ...item,
+      embedding: encodeBase64(item.embedding),
+    })),
+  }
+}
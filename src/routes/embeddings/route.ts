@@ -1,19 +1,69 @@
 import { Hono } from "hono"
 
-import { forwardError } from "~/lib/error"
+import { forwardError, HTTPError } from "~/lib/error"
+import { idempotency } from "~/lib/idempotency"
+import { checkModelAccess } from "~/lib/model-policy"
+import { quotaGuard } from "~/lib/quota-guard"
+import { tagRequest } from "~/lib/request-tags"
+import { scheduleByPriority } from "~/lib/scheduler"
+import { teamCredentials } from "~/lib/team"
+import { recordUsage } from "~/lib/usage-ledger"
 import {
   createEmbeddings,
   type EmbeddingRequest,
 } from "~/services/copilot/create-embeddings"
 
+import { applyDimensions } from "./dimensions"
+import { applyEncodingFormat } from "./encoding"
+
 export const embeddingRoutes = new Hono()
 
+embeddingRoutes.use(teamCredentials)
+embeddingRoutes.use(idempotency)
+embeddingRoutes.use(quotaGuard)
+embeddingRoutes.use(scheduleByPriority)
+
 embeddingRoutes.post("/", async (c) => {
   try {
     const paylod = await c.req.json<EmbeddingRequest>()
-    const response = await createEmbeddings(paylod)
+    tagRequest(c, paylod)
+    checkModelAccess(c, paylod.model)
+    const { dimensions, encoding_format: encodingFormat } = paylod
+    if (
+      dimensions !== undefined
+      && (!Number.isInteger(dimensions) || dimensions < 1)
+    ) {
+      const message = "dimensions must be a positive integer"
+      throw new HTTPError(
+        message,
+        Response.json({ message }, { status: 400 }),
+      )
+    }
+    if (
+      encodingFormat !== undefined
+      && encodingFormat !== "float"
+      && encodingFormat !== "base64"
+    ) {
+      const message = 'encoding_format must be "float" or "base64"'
+      throw new HTTPError(
+        message,
+        Response.json({ message }, { status: 400 }),
+      )
+    }
+
+    const response = await createEmbeddings({
+      ...paylod,
+      encoding_format: undefined,
+    })
+    await recordUsage(c, {
+      endpoint: "/embeddings",
+      model: paylod.model,
+      usage: response.usage,
+    })
 
-    return c.json(response)
+    return c.json(
+      applyEncodingFormat(applyDimensions(response, dimensions), encodingFormat),
+    )
   } catch (error) {
     return await forwardError(c, error)
   }
@@ -0,0 +1,10 @@
+import { Hono } from "hono"
+
+import { forwardToProvider } from "~/lib/media-providers"
+
+export const imageRoutes = new Hono()
+
+imageRoutes.post(
+  "/generations",
+  forwardToProvider("images", "/images/generations", "Image generation"),
+)
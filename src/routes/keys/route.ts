@@ -0,0 +1,151 @@
+import type { Context } from "hono"
+
+import { Hono } from "hono"
+
+import { requireAdmin } from "~/lib/admin-auth"
+import { state } from "~/lib/state"
+import {
+  createKey,
+  describeKey,
+  keyStatus,
+  readTeamStore,
+  revokeKeys,
+  rotateKey,
+  updateTeamStore,
+} from "~/lib/team"
+
+const DAY_MS = 24 * 60 * 60 * 1000
+const HOUR_MS = 60 * 60 * 1000
+
+// The API keys of `start --team`. New keys are only shown in the response
+// that creates them.
+export const keysRoute = new Hono()
+
+const adminError = (
+  c: Context,
+  message: string,
+  status: 400 | 404 | 409,
+) => c.json({ error: { message, type: "error" } }, status)
+
+interface KeyOptions {
+  login?: unknown
+  expiresInDays?: unknown
+  graceHours?: unknown
+}
+
+async function keyOptions(c: Context): Promise<KeyOptions> {
+  const body: unknown = await c.req.json().catch(() => ({}))
+  return typeof body === "object" && body !== null ? body : {}
+}
+
+const isPositive = (value: unknown): value is number =>
+  typeof value === "number" && Number.isFinite(value) && value > 0
+
+// Undefined when not given, null when invalid
+function expiryOf(options: KeyOptions): string | undefined | null {
+  if (options.expiresInDays === undefined) return undefined
+  if (!isPositive(options.expiresInDays)) return null
+  return new Date(Date.now() + options.expiresInDays * DAY_MS).toISOString()
+}
+
+keysRoute.use(async (c, next) => {
+  if (!state.teamStore) {
+    return adminError(c, "Team mode is off, start with --team", 404)
+  }
+  return next()
+})
+keysRoute.use(requireAdmin)
+
+keysRoute.get("/", async (c) => {
+  const { members } = await readTeamStore(state.teamStore)
+  return c.json({
+    keys: Object.entries(members).map(([id, member]) =>
+      describeKey(id, member),
+    ),
+  })
+})
+
+keysRoute.get("/audit", async (c) => {
+  const { audit = [] } = await readTeamStore(state.teamStore)
+  return c.json({ events: audit })
+})
+
+// Another key for an existing member; new members need `team add`, which
+// logs them in to GitHub
+keysRoute.post("/", async (c) => {
+  const options = await keyOptions(c)
+  const expiresAt = expiryOf(options)
+  if (typeof options.login !== "string") {
+    return adminError(c, "Expected the member's `login`", 400)
+  }
+  if (expiresAt === null) {
+    return adminError(c, "`expiresInDays` must be a positive number", 400)
+  }
+
+  const login = options.login
+  const created = await updateTeamStore((store) => {
+    const existing = Object.values(store.members).find(
+      (member) => member.login === login && keyStatus(member) === "active",
+    )
+    if (!existing) return undefined
+    const { apiKey, id } = createKey(
+      store,
+      { login, githubToken: existing.githubToken, expiresAt },
+      "admin",
+    )
+    return { ...describeKey(id, store.members[id]), apiKey }
+  }, state.teamStore)
+  if (!created) {
+    return adminError(
+      c,
+      `${login} has no active API key, add new members with \`team add\``,
+      404,
+    )
+  }
+  return c.json(created, 201)
+})
+
+keysRoute.post("/:id/rotate", async (c) => {
+  const options = await keyOptions(c)
+  const expiresAt = expiryOf(options)
+  if (options.graceHours !== undefined && !isPositive(options.graceHours)) {
+    return adminError(c, "`graceHours` must be a positive number", 400)
+  }
+  if (expiresAt === null) {
+    return adminError(c, "`expiresInDays` must be a positive number", 400)
+  }
+
+  const graceHours = (options.graceHours as number | undefined) ?? 24
+
+  const id = c.req.param("id")
+  const { members } = await readTeamStore(state.teamStore)
+  if (!(id in members)) return adminError(c, `No API key ${id}`, 404)
+
+  try {
+    const rotated = await updateTeamStore((store) => {
+      const { apiKey, id: newId } = rotateKey(
+        store,
+        id,
+        { graceMs: graceHours * HOUR_MS, expiresAt },
+        "admin",
+      )
+      return { ...describeKey(newId, store.members[newId]), apiKey }
+    }, state.teamStore)
+    return c.json(rotated, 201)
+  } catch (error) {
+    return adminError(c, (error as Error).message, 409)
+  }
+})
+
+// Soft delete: the key stops working but stays listed for the audit trail
+keysRoute.delete("/:id", async (c) => {
+  const id = c.req.param("id")
+  const { members } = await readTeamStore(state.teamStore)
+  if (!(id in members)) return adminError(c, `No API key ${id}`, 404)
+
+  const revoked = await updateTeamStore((store) => {
+    revokeKeys(store, id, "admin")
+    return describeKey(id, store.members[id])
+  }, state.teamStore)
+  return c.json(revoked)
+})
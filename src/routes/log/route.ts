@@ -0,0 +1,60 @@
+import { Hono } from "hono"
+
+import { requireAdmin } from "~/lib/admin-auth"
+import {
+  logControlStatus,
+  logNextBodies,
+  setLogFilter,
+} from "~/lib/log-control"
+
+// Reads and changes logging at runtime; nothing is persisted, so a restart
+// goes back to the configured level
+export const logRoute = new Hono()
+
+logRoute.use(requireAdmin)
+
+const MAX_BODY_LOGS = 1000
+
+logRoute.get("/", (c) => c.json(logControlStatus()))
+
+logRoute.put("/", async (c) => {
+  const body = await c.req
+    .json<{ filter?: unknown; logBodies?: unknown } | null>()
+    .catch(() => null)
+  const { filter, logBodies } = body ?? {}
+
+  if (
+    logBodies !== undefined
+    && (typeof logBodies !== "number"
+      || !Number.isInteger(logBodies)
+      || logBodies < 0
+      || logBodies > MAX_BODY_LOGS)
+  ) {
+    return c.json(
+      {
+        error: {
+          message: `logBodies must be an integer from 0 to ${MAX_BODY_LOGS}`,
+          type: "error",
+        },
+      },
+      400,
+    )
+  }
+  if (
+    filter !== undefined
+    && (typeof filter !== "string" || !setLogFilter(filter))
+  ) {
+    return c.json(
+      {
+        error: {
+          message:
+            "filter must be a level (silent, error, warn, info, debug or trace), optionally followed by path=level pairs, e.g. info,/v1/messages=debug",
+          type: "error",
+        },
+      },
+      400,
+    )
+  }
+  if (logBodies !== undefined) logNextBodies(logBodies as number)
+  return c.json(logControlStatus())
+})
@@ -19,15 +19,21 @@ export interface AnthropicMessagesPayload {
     name?: string
   }
   thinking?: {
-    type: "enabled"
+    type: "enabled" | "disabled"
     budget_tokens?: number
   }
   service_tier?: "auto" | "standard_only"
 }
 
+// Marks the end of a prompt prefix to cache
+export interface AnthropicCacheControl {
+  type: "ephemeral"
+}
+
 export interface AnthropicTextBlock {
   type: "text"
   text: string
+  cache_control?: AnthropicCacheControl
 }
 
 export interface AnthropicImageBlock {
@@ -37,6 +43,7 @@ export interface AnthropicImageBlock {
     media_type: "image/jpeg" | "image/png" | "image/gif" | "image/webp"
     data: string
   }
+  cache_control?: AnthropicCacheControl
 }
 
 export interface AnthropicToolResultBlock {
@@ -44,6 +51,7 @@ export interface AnthropicToolResultBlock {
   tool_use_id: string
   content: string
   is_error?: boolean
+  cache_control?: AnthropicCacheControl
 }
 
 export interface AnthropicToolUseBlock {
@@ -84,6 +92,7 @@ export interface AnthropicTool {
   name: string
   description?: string
   input_schema: Record<string, unknown>
+  cache_control?: AnthropicCacheControl
 }
 
 export interface AnthropicResponse {
@@ -157,7 +166,12 @@ export interface AnthropicMessageDeltaEvent {
     stop_reason?: AnthropicResponse["stop_reason"]
     stop_sequence?: string | null
   }
-  usage?: { output_tokens: number }
+  // Cumulative; input tokens are included when the upstream reports them
+  usage?: {
+    input_tokens?: number
+    output_tokens: number
+    cache_read_input_tokens?: number
+  }
 }
 
 export interface AnthropicMessageStopEvent {
@@ -4,86 +4,251 @@ import consola from "consola"
 import { streamSSE } from "hono/streaming"
 
 import { awaitApproval } from "~/lib/approval"
+import { createStreamTranscript, recordAudit } from "~/lib/audit"
+import {
+  applyRequestFilters,
+  applyResponseFilters,
+  setPolicyHeader,
+  type PolicyContext,
+} from "~/lib/content-policy"
+import { checkContextWindow } from "~/lib/context-window"
+import { routeExperiment } from "~/lib/experiments"
+import { gatewayOptionsOf, upstreamSignal } from "~/lib/gateway-options"
+import { observePromptCache, startStreamTimer } from "~/lib/metrics"
+import { checkModelAccess } from "~/lib/model-policy"
+import { scrubParams } from "~/lib/param-policy"
+import { applyPromptCacheKey } from "~/lib/prompt-cache"
 import { checkRateLimit } from "~/lib/rate-limit"
+import { effortForThinking } from "~/lib/reasoning"
+import { annotateSample } from "~/lib/request-samples"
+import { tagRequest } from "~/lib/request-tags"
+import { recordSessionTurn } from "~/lib/sessions"
 import { state } from "~/lib/state"
+import {
+  applyStopSequences,
+  createStopSequenceFilter,
+  stopSequencesOf,
+} from "~/lib/stop-sequences"
+import { openBroadcast, streamOwner } from "~/lib/stream-broadcast"
+import { summarizeToFit } from "~/lib/summarize"
+import { checkTokenBudget } from "~/lib/token-budget"
+import { getPromptTokenCount } from "~/lib/tokenizer"
+import {
+  createToolCallStreamRepairer,
+  REPAIR_HEADER,
+  repairToolCalls,
+} from "~/lib/tool-call-repair"
+import { recordUsage } from "~/lib/usage-ledger"
+import { resolveModel } from "~/lib/utils"
 import {
   createChatCompletions,
   type ChatCompletionChunk,
   type ChatCompletionResponse,
+  type Usage,
 } from "~/services/copilot/create-chat-completions"
 
+import { type AnthropicMessagesPayload } from "./anthropic-types"
 import {
-  type AnthropicMessagesPayload,
-  type AnthropicStreamState,
-} from "./anthropic-types"
+  translateToAnthropicHybrid,
+  translateToOpenAIHybrid,
+} from "./hybrid-translation"
 import {
-  translateToAnthropic,
-  translateToOpenAI,
-} from "./non-stream-translation"
-import { translateChunkToAnthropicEvents } from "./stream-translation"
+  createAnthropicStreamState,
+  translateChunkToAnthropicEvents,
+} from "./stream-translation"
+import { anthropicPromptCacheKey } from "./utils"
 
 // eslint-disable-next-line max-lines-per-function
 export async function handleCompletion(c: Context) {
+  const startedAt = performance.now()
   await checkRateLimit(state)
+  const overrides = gatewayOptionsOf(c)
 
   const anthropicPayload = await c.req.json<AnthropicMessagesPayload>()
+  tagRequest(c, { user: anthropicPayload.metadata?.user_id })
+  anthropicPayload.model = routeExperiment(
+    c,
+    resolveModel(overrides.model ?? anthropicPayload.model),
+  )
+  checkModelAccess(c, anthropicPayload.model)
+  annotateSample(c.req.raw, { model: anthropicPayload.model })
   consola.debug("Anthropic request payload:", JSON.stringify(anthropicPayload))
 
-  const openAIPayload = translateToOpenAI(anthropicPayload)
+  const policy: PolicyContext = { annotations: [] }
+  const filters = state.contentFilters ?? []
+  const translated = await translateToOpenAIHybrid(anthropicPayload)
+  translated.reasoning_effort ??= effortForThinking(anthropicPayload.thinking)
+  const filtered = applyRequestFilters(filters, translated, policy)
+  const openAIPayload = await summarizeToFit(
+    c,
+    scrubParams(
+      c,
+      overrides.noCache ?
+        { ...filtered, prompt_cache_key: undefined }
+      : applyPromptCacheKey(
+          filtered,
+          anthropicPromptCacheKey(anthropicPayload),
+        ),
+    ),
+  )
   consola.debug(
     "Translated OpenAI request payload:",
     JSON.stringify(openAIPayload),
   )
 
+  await checkTokenBudget(c, openAIPayload.messages)
+  if (state.contextCheck) {
+    checkContextWindow(
+      openAIPayload.model,
+      getPromptTokenCount(openAIPayload.messages),
+      openAIPayload.max_tokens ?? undefined,
+    )
+  }
+
   if (state.manualApprove) {
     await awaitApproval()
   }
 
-  const response = await createChatCompletions(openAIPayload)
+  const response = await createChatCompletions(openAIPayload, {
+    signal: upstreamSignal(overrides),
+  })
+  const stops = stopSequencesOf(openAIPayload.stop)
 
   if (isNonStreaming(response)) {
     consola.debug(
       "Non-streaming response from Copilot:",
       JSON.stringify(response).slice(-400),
     )
-    const anthropicResponse = translateToAnthropic(response)
+    annotateSample(c.req.raw, {
+      promptTokens: response.usage?.prompt_tokens,
+      completionTokens: response.usage?.completion_tokens,
+    })
+    observePromptCache(openAIPayload.model, response.usage)
+    const stopped = applyStopSequences(response, stops)
+    let filtered = applyResponseFilters(filters, stopped.response, policy)
+    if (state.repairToolCalls) {
+      const result = repairToolCalls(filtered)
+      filtered = result.response
+      if (result.repaired > 0) c.header(REPAIR_HEADER, String(result.repaired))
+    }
+    const anthropicResponse = await translateToAnthropicHybrid(filtered)
+    const stopSequence = stopped.matched.get(0)
+    if (stopSequence !== undefined) {
+      anthropicResponse.stop_reason = "stop_sequence"
+      anthropicResponse.stop_sequence = stopSequence
+    }
+    setPolicyHeader(c, policy)
     consola.debug(
       "Translated Anthropic response:",
       JSON.stringify(anthropicResponse),
     )
+    await recordAudit({
+      endpoint: "/v1/messages",
+      model: anthropicPayload.model,
+      stream: false,
+      request: anthropicPayload,
+      response: anthropicResponse,
+    })
+    await recordSessionTurn(c, {
+      endpoint: "/v1/messages",
+      model: anthropicPayload.model,
+      request: openAIPayload,
+      response: anthropicResponse,
+    })
+    await recordUsage(c, {
+      endpoint: "/v1/messages",
+      model: openAIPayload.model,
+      usage: response.usage,
+    })
     return c.json(anthropicResponse)
   }
 
   consola.debug("Streaming response from Copilot")
+  setPolicyHeader(c, policy)
+  const broadcast = openBroadcast(
+    "/v1/messages",
+    openAIPayload.model,
+    streamOwner(c),
+  )
+  // Observers attach at /admin/streams/<id>, and events carry ids for
+  // resuming with Last-Event-ID
+  c.header("x-stream-id", broadcast.info.id)
   return streamSSE(c, async (stream) => {
-    const streamState: AnthropicStreamState = {
-      messageStartSent: false,
-      contentBlockIndex: 0,
-      contentBlockOpen: false,
-      toolCalls: {},
-    }
+    const streamState = createAnthropicStreamState()
+    const transcript = createStreamTranscript()
+    const timer = startStreamTimer(openAIPayload.model, startedAt)
+    let usage: Usage | undefined
+    const stopFilter =
+      stops.length > 0 ? createStopSequenceFilter(stops) : undefined
+    const repairer =
+      state.repairToolCalls ? createToolCallStreamRepairer() : undefined
 
-    for await (const rawEvent of response) {
-      consola.debug("Copilot raw stream event:", JSON.stringify(rawEvent))
-      if (rawEvent.data === "[DONE]") {
-        break
-      }
+    try {
+      for await (const rawEvent of response) {
+        consola.debug("Copilot raw stream event:", JSON.stringify(rawEvent))
+        if (rawEvent.data === "[DONE]") {
+          break
+        }
 
-      if (!rawEvent.data) {
-        continue
-      }
+        if (!rawEvent.data) {
+          continue
+        }
 
-      const chunk = JSON.parse(rawEvent.data) as ChatCompletionChunk
-      const events = translateChunkToAnthropicEvents(chunk, streamState)
+        const parsed = JSON.parse(rawEvent.data) as ChatCompletionChunk
+        timer.chunk()
+        if (parsed.usage) {
+          usage = parsed.usage
+          observePromptCache(openAIPayload.model, parsed.usage)
+        }
+        let chunks = [parsed]
+        if (stopFilter) {
+          chunks = chunks.flatMap((chunk) => stopFilter.process(chunk))
+        }
+        if (repairer) {
+          chunks = chunks.flatMap((chunk) => repairer.process(chunk))
+        }
 
-      for (const event of events) {
-        consola.debug("Translated Anthropic event:", JSON.stringify(event))
-        await stream.writeSSE({
-          event: event.type,
-          data: JSON.stringify(event),
-        })
+        for (const chunk of chunks) {
+          transcript.add(chunk)
+          const events = translateChunkToAnthropicEvents(chunk, streamState)
+
+          for (const event of events) {
+            const stopSequence = stopFilter?.matched(0)
+            if (event.type === "message_delta" && stopSequence !== undefined) {
+              event.delta.stop_reason = "stop_sequence"
+              event.delta.stop_sequence = stopSequence
+            }
+            consola.debug("Translated Anthropic event:", JSON.stringify(event))
+            const message = { event: event.type, data: JSON.stringify(event) }
+            await stream.writeSSE(broadcast.publish(message))
+          }
+        }
+      }
+      if (repairer?.repaired) {
+        consola.warn(`Repaired ${repairer.repaired} streamed tool call(s)`)
       }
+    } finally {
+      broadcast.close()
     }
+    timer.end()
+    await recordAudit({
+      endpoint: "/v1/messages",
+      model: anthropicPayload.model,
+      stream: true,
+      request: anthropicPayload,
+      response: transcript.result(),
+    })
+    await recordSessionTurn(c, {
+      endpoint: "/v1/messages",
+      model: anthropicPayload.model,
+      request: openAIPayload,
+      response: transcript.result(),
+    })
+    await recordUsage(c, {
+      endpoint: "/v1/messages",
+      model: openAIPayload.model,
+      usage,
+    })
   })
 }
 
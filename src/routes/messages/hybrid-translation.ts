@@ -0,0 +1,45 @@
+import type {
+  ChatCompletionResponse,
+  ChatCompletionsPayload,
+} from "~/services/copilot/create-chat-completions"
+
+import { features, rustCore } from "~/lib/rust-core"
+
+import type {
+  AnthropicMessagesPayload,
+  AnthropicResponse,
+} from "./anthropic-types"
+
+import {
+  translateToAnthropic,
+  translateToOpenAI,
+} from "./non-stream-translation"
+
+// Uses the Rust translator when USE_RUST_TRANSLATION is set, falling back to
+// the TypeScript implementation if the native module is unavailable
+
+export const translateToOpenAIHybrid = async (
+  payload: AnthropicMessagesPayload,
+): Promise<ChatCompletionsPayload> => {
+  if (features.USE_RUST_TRANSLATION) {
+    try {
+      return await rustCore.translateAnthropicToOpenAI(payload)
+    } catch {
+      return translateToOpenAI(payload)
+    }
+  }
+  return translateToOpenAI(payload)
+}
+
+export const translateToAnthropicHybrid = async (
+  response: ChatCompletionResponse,
+): Promise<AnthropicResponse> => {
+  if (features.USE_RUST_TRANSLATION) {
+    try {
+      return await rustCore.translateOpenAIToAnthropic(response)
+    } catch {
+      return translateToAnthropic(response)
+    }
+  }
+  return translateToAnthropic(response)
+}
@@ -6,6 +6,15 @@ import {
 } from "./anthropic-types"
 import { mapOpenAIStopReasonToAnthropic } from "./utils"
 
+export function createAnthropicStreamState(): AnthropicStreamState {
+  return {
+    messageStartSent: false,
+    contentBlockIndex: 0,
+    contentBlockOpen: false,
+    toolCalls: {},
+  }
+}
+
 function isToolBlockOpen(state: AnthropicStreamState): boolean {
   if (!state.contentBlockOpen) {
     return false
@@ -42,7 +51,8 @@ export function translateChunkToAnthropicEvents(
         stop_reason: null,
         stop_sequence: null,
         usage: {
-          input_tokens: 1,
+          // Usually only known at the end; the final message_delta has it
+          input_tokens: chunk.usage?.prompt_tokens ?? 1,
           output_tokens: 1, // Anthropic requires this to be > 0
         },
       },
@@ -150,9 +160,15 @@ export function translateChunkToAnthropicEvents(
         stop_reason: mapOpenAIStopReasonToAnthropic(choice.finish_reason),
         stop_sequence: null,
       },
-      usage: {
-        output_tokens: 1,
-      },
+      usage:
+        chunk.usage ?
+          {
+            input_tokens: chunk.usage.prompt_tokens,
+            output_tokens: chunk.usage.completion_tokens,
+            cache_read_input_tokens:
+              chunk.usage.prompt_tokens_details?.cached_tokens,
+          }
+        : { output_tokens: 1 },
     })
 
     events.push({
@@ -1,4 +1,9 @@
-import { type AnthropicResponse } from "./anthropic-types"
+import { hashCacheKey } from "~/lib/prompt-cache"
+
+import {
+  type AnthropicMessagesPayload,
+  type AnthropicResponse,
+} from "./anthropic-types"
 
 export function mapOpenAIStopReasonToAnthropic(
   finishReason: "stop" | "length" | "tool_calls" | "content_filter" | null,
@@ -14,3 +19,34 @@ export function mapOpenAIStopReasonToAnthropic(
   } as const
   return stopReasonMap[finishReason]
 }
+
+const hasCacheControl = (content: unknown) =>
+  Array.isArray(content)
+  && content.some(
+    (block) => (block as { cache_control?: unknown }).cache_control,
+  )
+
+/**
+ * Derives a prompt cache key from the prefix ending at the last
+ * `cache_control` breakpoint, so requests sharing that prefix share a key.
+ */
+export function anthropicPromptCacheKey(
+  payload: AnthropicMessagesPayload,
+): string | undefined {
+  let end = -1
+  for (const [index, message] of payload.messages.entries()) {
+    if (hasCacheControl(message.content)) end = index
+  }
+  const marked =
+    end !== -1
+    || hasCacheControl(payload.system)
+    || payload.tools?.some((tool) => tool.cache_control)
+  if (!marked) return undefined
+
+  return hashCacheKey({
+    model: payload.model,
+    system: payload.system,
+    tools: payload.tools,
+    messages: payload.messages.slice(0, end + 1),
+  })
+}
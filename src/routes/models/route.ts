@@ -1,19 +1,21 @@
 import { Hono } from "hono"
+import { etag } from "hono/etag"
 
 import { forwardError } from "~/lib/error"
 import { state } from "~/lib/state"
-import { cacheModels } from "~/lib/utils"
+import { aliasesFor, getCachedModels } from "~/lib/utils"
 
 export const modelRoutes = new Hono()
 
+// Editor plugins poll the list constantly; with If-None-Match an unchanged
+// list costs them a 304 instead of the whole body
+modelRoutes.use(etag())
+
 modelRoutes.get("/", async (c) => {
   try {
-    if (!state.models) {
-      // This should be handled by startup logic, but as a fallback.
-      await cacheModels()
-    }
+    const cached = await getCachedModels()
 
-    const models = state.models?.data.map((model) => ({
+    const models = cached?.data.map((model) => ({
       id: model.id,
       object: "model",
       type: "model",
@@ -21,6 +23,15 @@ modelRoutes.get("/", async (c) => {
       created_at: new Date(0).toISOString(), // No date available from source
       owned_by: model.vendor,
       display_name: model.name,
+      // Gateway-side metadata, so clients need not know Copilot's format
+      aliases: aliasesFor(model.id),
+      default: model.id === state.defaultModel,
+      capabilities: {
+        vision: model.capabilities.supports.vision ?? false,
+        tool_calls: model.capabilities.supports.tool_calls ?? false,
+        context_window: model.capabilities.limits.max_context_window_tokens,
+        max_output_tokens: model.capabilities.limits.max_output_tokens,
+      },
     }))
 
     return c.json({
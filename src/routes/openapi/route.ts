@@ -0,0 +1,41 @@
+import { Hono } from "hono"
+
+import { state } from "~/lib/state"
+
+import { openApiDocument } from "./spec"
+
+export const openApiRoute = new Hono()
+
+openApiRoute.get("/", (c) =>
+  c.json(
+    state.pathPrefix ?
+      { ...openApiDocument, servers: [{ url: state.pathPrefix }] }
+    : openApiDocument,
+  ),
+)
+
+// Swagger UI is loaded from a CDN so it adds no dependency
+const SWAGGER_UI_VERSION = "5.17.14"
+
+export const docsRoute = new Hono()
+
+// The spec URL is relative so the page also works under --path-prefix
+docsRoute.get("/", (c) => {
+  if (!state.swaggerUi) return c.notFound()
+
+  return c.html(`<!doctype html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8" />
+    <title>Copilot API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@${SWAGGER_UI_VERSION}/swagger-ui.css" />
+  </head>
+  <body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@${SWAGGER_UI_VERSION}/swagger-ui-bundle.js"></script>
+    <script>
+      SwaggerUIBundle({ url: "openapi.json", dom_id: "#swagger-ui" })
+    </script>
+  </body>
+</html>`)
+})
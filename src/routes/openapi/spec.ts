@@ -0,0 +1,1063 @@
+// OpenAPI 3.1 description of every gateway route, served at /openapi.json.
+// Request and response bodies follow the upstream OpenAI and Anthropic
+// formats, so only the fields the gateway reads or sets are spelled out and
+// everything else is passed through.
+
+const ref = (name: string) => ({ $ref: `#/components/schemas/${name}` })
+
+const json = (schema: object) => ({
+  content: { "application/json": { schema } },
+})
+
+const errorResponses = {
+  "400": {
+    description:
+      "Invalid request, or a body that is not valid JSON (`code: invalid_json`, with `line`, `column`, `offset` and `body_excerpt`)",
+    ...json(ref("Error")),
+  },
+  "403": {
+    description: "Model not allowed for this API key by the model policy",
+    ...json(ref("Error")),
+  },
+  "429": { description: "Rate limit exceeded", ...json(ref("Error")) },
+  "500": { description: "Upstream or gateway error", ...json(ref("Error")) },
+  "504": {
+    description: "Upstream request exceeded `x-gateway-timeout-ms`",
+    ...json(ref("Error")),
+  },
+}
+
+// Per-request overrides, see src/lib/gateway-options.ts
+const gatewayHeaderParameters = [
+  {
+    name: "x-gateway-model-override",
+    in: "header",
+    description:
+      "Model to use instead of the one in the body, still subject to aliases and the model policy",
+    schema: { type: "string" },
+  },
+  {
+    name: "x-gateway-no-cache",
+    in: "header",
+    description: "When `true`, no prompt cache key is sent upstream",
+    schema: { type: "boolean" },
+  },
+  {
+    name: "x-gateway-timeout-ms",
+    in: "header",
+    description:
+      "Aborts the upstream request, streamed response included, after this many milliseconds",
+    schema: { type: "integer", minimum: 1 },
+  },
+  {
+    name: "x-gateway-tags",
+    in: "header",
+    description:
+      "Comma-separated `key=value` tags stored with usage records, overriding `metadata` and `user`",
+    schema: { type: "string" },
+  },
+  {
+    name: "x-gateway-provider",
+    in: "header",
+    description: "Upstream provider, only `copilot` is supported",
+    schema: { type: "string", enum: ["copilot"] },
+  },
+]
+
+// See src/lib/idempotency.ts
+const idempotencyKeyParameter = {
+  name: "Idempotency-Key",
+  in: "header",
+  description:
+    "Retries with the same key and body get the original response, marked with `idempotent-replayed: true`",
+  schema: { type: "string", maxLength: 255 },
+}
+
+const idempotencyResponses = {
+  "422": {
+    description:
+      "The Idempotency-Key was already used with a different body (`code: idempotency_key_reused`)",
+    ...json(ref("Error")),
+  },
+}
+
+const chatCompletionOperation = {
+  summary: "Create a chat completion",
+  tags: ["OpenAI"],
+  parameters: [
+    {
+      name: "dry_run",
+      in: "query",
+      description:
+        "When `true`, returns the payload and headers that would be sent upstream (with the token redacted), the token count and the validation result, without sending anything",
+      schema: { type: "boolean" },
+    },
+    ...gatewayHeaderParameters,
+    idempotencyKeyParameter,
+  ],
+  requestBody: { required: true, ...json(ref("ChatCompletionRequest")) },
+  responses: {
+    "200": {
+      description:
+        "The completion, a `text/event-stream` of chunks when `stream` is true, or the upstream request for a dry run",
+      content: {
+        "application/json": {
+          schema: {
+            oneOf: [ref("ChatCompletionResponse"), ref("DryRunResponse")],
+          },
+        },
+        "text/event-stream": { schema: ref("ChatCompletionChunk") },
+        "application/x-ndjson": { schema: ref("ChatCompletionChunk") },
+      },
+    },
+    ...errorResponses,
+    ...idempotencyResponses,
+  },
+}
+
+const modelsOperation = {
+  summary: "List available models",
+  tags: ["OpenAI"],
+  parameters: [
+    {
+      name: "If-None-Match",
+      in: "header",
+      description: "ETag of a previously fetched list",
+      schema: { type: "string" },
+    },
+  ],
+  responses: {
+    "200": {
+      description: "Model list",
+      headers: { ETag: { schema: { type: "string" } } },
+      ...json(ref("ModelList")),
+    },
+    "304": { description: "The list has not changed since the given ETag" },
+    ...errorResponses,
+  },
+}
+
+const embeddingsOperation = {
+  summary: "Create embeddings",
+  tags: ["OpenAI"],
+  parameters: [idempotencyKeyParameter],
+  requestBody: { required: true, ...json(ref("EmbeddingRequest")) },
+  responses: {
+    "200": { description: "Embeddings", ...json(ref("EmbeddingResponse")) },
+    ...errorResponses,
+    ...idempotencyResponses,
+  },
+}
+
+// Forwarded unchanged to the provider configured with --media-providers
+const providerOperation = (
+  summary: string,
+  requestType: string,
+  responseType: string,
+) => ({
+  summary,
+  description:
+    "Copilot has no model for this. The request is forwarded as it is to the provider configured with `--media-providers`, and its response returned unchanged.",
+  tags: ["OpenAI"],
+  requestBody: { required: true, content: { [requestType]: {} } },
+  responses: {
+    "200": {
+      description: "The provider's response",
+      content: { [responseType]: {} },
+    },
+    "404": {
+      description:
+        "No provider is configured (`code: model_not_found`, `param: model`)",
+      ...json(ref("Error")),
+    },
+    "500": {
+      description: "The provider is unreachable",
+      ...json(ref("Error")),
+    },
+    "504": {
+      description: "The provider did not answer within `timeoutSeconds`",
+      ...json(ref("Error")),
+    },
+  },
+})
+
+const tokenizeOperation = {
+  summary: "Count message tokens",
+  description:
+    "Counts with the same GPT-4o tokenizer as the native module. A trailing assistant message is the output, tool results are not counted.",
+  tags: ["OpenAI"],
+  requestBody: {
+    required: true,
+    ...json({
+      type: "object",
+      required: ["messages"],
+      properties: {
+        messages: { type: "array", items: { type: "object" } },
+      },
+    }),
+  },
+  responses: {
+    "200": {
+      description: "Token counts",
+      ...json({
+        type: "object",
+        properties: {
+          input_tokens: { type: "integer" },
+          output_tokens: { type: "integer" },
+          total_tokens: { type: "integer" },
+        },
+      }),
+    },
+    ...errorResponses,
+  },
+}
+
+const sessionIdParameter = {
+  name: "id",
+  in: "path",
+  required: true,
+  schema: { type: "string", pattern: "^[\\w.-]{1,128}$" },
+}
+
+const usage = {
+  type: "object",
+  properties: {
+    prompt_tokens: { type: "integer" },
+    completion_tokens: { type: "integer" },
+    total_tokens: { type: "integer" },
+  },
+}
+
+const schemas = {
+  Error: {
+    type: "object",
+    required: ["error"],
+    properties: {
+      error: {
+        type: "object",
+        required: ["message", "type"],
+        properties: {
+          message: { type: "string" },
+          type: { type: "string", examples: ["error"] },
+        },
+      },
+    },
+  },
+  ChatMessage: {
+    type: "object",
+    required: ["role"],
+    properties: {
+      role: {
+        type: "string",
+        enum: ["system", "developer", "user", "assistant", "tool"],
+      },
+      content: {
+        oneOf: [
+          { type: "string" },
+          { type: "array", items: { type: "object" } },
+          { type: "null" },
+        ],
+      },
+      name: { type: "string" },
+      tool_calls: { type: "array", items: { type: "object" } },
+      tool_call_id: { type: "string" },
+    },
+  },
+  ChatCompletionRequest: {
+    type: "object",
+    required: ["model", "messages"],
+    additionalProperties: true,
+    properties: {
+      model: {
+        type: "string",
+        description:
+          "Unknown or empty models are replaced by the server's default model, if configured",
+      },
+      messages: { type: "array", items: ref("ChatMessage") },
+      stream: { type: "boolean" },
+      stream_format: {
+        type: "string",
+        enum: ["sse", "ndjson"],
+        description:
+          "`ndjson` streams one JSON chunk per line, as does `Accept: application/x-ndjson`",
+      },
+      max_tokens: {
+        type: ["integer", "null"],
+        description: "Defaults to the model's output limit",
+      },
+      temperature: { type: ["number", "null"] },
+      top_p: { type: ["number", "null"] },
+      stop: {
+        oneOf: [
+          { type: "string" },
+          { type: "array", items: { type: "string" } },
+        ],
+      },
+      tools: { type: "array", items: { type: "object" } },
+      tool_choice: {},
+      prompt_cache_key: {
+        type: "string",
+        description:
+          "Groups requests sharing a prompt prefix for upstream prompt caching",
+      },
+      reasoning_effort: {
+        enum: ["minimal", "low", "medium", "high", null],
+        description:
+          "Removed for models without reasoning support, and moved to the nearest level the model accepts otherwise",
+      },
+      response_format: {
+        type: "object",
+        required: ["type"],
+        properties: {
+          type: { enum: ["text", "json_object", "json_schema"] },
+          json_schema: {
+            type: "object",
+            required: ["name"],
+            properties: {
+              name: { type: "string" },
+              description: { type: "string" },
+              schema: { type: "object" },
+              strict: { type: ["boolean", "null"] },
+            },
+          },
+        },
+        description:
+          "A `json_schema` format is validated by the gateway, with the outcome in the `x-schema-validation` header",
+      },
+    },
+  },
+  ChatCompletionResponse: {
+    type: "object",
+    additionalProperties: true,
+    properties: {
+      id: { type: "string" },
+      object: { const: "chat.completion" },
+      created: { type: "integer" },
+      model: { type: "string" },
+      choices: {
+        type: "array",
+        items: {
+          type: "object",
+          properties: {
+            index: { type: "integer" },
+            message: ref("ChatMessage"),
+            finish_reason: { type: "string" },
+          },
+        },
+      },
+      usage,
+    },
+  },
+  ChatCompletionChunk: {
+    type: "object",
+    description: "One `data:` line of the stream, followed by `data: [DONE]`",
+    additionalProperties: true,
+    properties: {
+      id: { type: "string" },
+      object: { const: "chat.completion.chunk" },
+      model: { type: "string" },
+      choices: {
+        type: "array",
+        items: {
+          type: "object",
+          properties: {
+            index: { type: "integer" },
+            delta: { type: "object" },
+            finish_reason: { type: ["string", "null"] },
+          },
+        },
+      },
+      usage,
+    },
+  },
+  DryRunResponse: {
+    type: "object",
+    properties: {
+      dry_run: { const: true },
+      upstream: {
+        type: "object",
+        properties: {
+          method: { type: "string" },
+          url: { type: "string" },
+          headers: { type: "object", additionalProperties: { type: "string" } },
+          body: ref("ChatCompletionRequest"),
+        },
+      },
+      token_count: {
+        type: "object",
+        properties: {
+          input: { type: "integer" },
+          output: { type: "integer" },
+        },
+      },
+      validation: { type: "object" },
+    },
+  },
+  ModelList: {
+    type: "object",
+    properties: {
+      object: { const: "list" },
+      has_more: { type: "boolean" },
+      data: {
+        type: "array",
+        items: {
+          type: "object",
+          properties: {
+            id: { type: "string" },
+            object: { const: "model" },
+            type: { const: "model" },
+            created: { type: "integer" },
+            created_at: { type: "string", format: "date-time" },
+            owned_by: { type: "string" },
+            display_name: { type: "string" },
+            aliases: {
+              type: "array",
+              items: { type: "string" },
+              description: "Gateway-side names that resolve to this model",
+            },
+            default: {
+              type: "boolean",
+              description: "Used for requests without a known model",
+            },
+            capabilities: {
+              type: "object",
+              properties: {
+                vision: { type: "boolean" },
+                tool_calls: { type: "boolean" },
+                context_window: { type: "integer" },
+                max_output_tokens: { type: "integer" },
+              },
+            },
+          },
+        },
+      },
+    },
+  },
+  EmbeddingRequest: {
+    type: "object",
+    required: ["model", "input"],
+    properties: {
+      model: { type: "string" },
+      input: {
+        oneOf: [
+          { type: "string" },
+          { type: "array", items: { type: "string" } },
+        ],
+      },
+      dimensions: {
+        type: "integer",
+        minimum: 1,
+        description:
+          "Vectors are truncated and re-normalized to this size if the model ignores it",
+      },
+      encoding_format: {
+        type: "string",
+        enum: ["float", "base64"],
+        default: "float",
+        description:
+          "base64 returns each vector as little-endian float32 values, encoded by the gateway",
+      },
+    },
+  },
+  EmbeddingResponse: {
+    type: "object",
+    properties: {
+      object: { type: "string" },
+      model: { type: "string" },
+      data: {
+        type: "array",
+        items: {
+          type: "object",
+          properties: {
+            object: { type: "string" },
+            index: { type: "integer" },
+            embedding: {
+              oneOf: [
+                { type: "array", items: { type: "number" } },
+                {
+                  type: "string",
+                  description: "With encoding_format base64",
+                },
+              ],
+            },
+          },
+        },
+      },
+      usage,
+    },
+  },
+  RequestSample: {
+    type: "object",
+    required: ["time", "method", "route", "status", "durationMs", "reason"],
+    properties: {
+      time: { type: "string", format: "date-time" },
+      method: { type: "string" },
+      route: { type: "string" },
+      status: { type: "integer" },
+      durationMs: { type: "integer" },
+      reason: { type: "string", enum: ["slow", "error"] },
+      ip: { type: "string" },
+      model: { type: "string" },
+      promptTokens: { type: "integer" },
+      completionTokens: { type: "integer" },
+      upstreamStatus: { type: "integer" },
+      error: { type: "string", description: "Truncated to 200 characters" },
+    },
+  },
+  SessionTurn: {
+    type: "object",
+    properties: {
+      time: { type: "string", format: "date-time" },
+      endpoint: { type: "string" },
+      model: { type: "string" },
+      request: ref("ChatCompletionRequest"),
+      response: {
+        description: "What the client received; streams are assembled",
+      },
+    },
+  },
+  StreamInfo: {
+    type: "object",
+    properties: {
+      id: { type: "string" },
+      endpoint: { type: "string" },
+      model: { type: "string" },
+      startedAt: { type: "string", format: "date-time" },
+      events: { type: "integer" },
+      observers: { type: "integer" },
+    },
+  },
+  ApiKey: {
+    type: "object",
+    description: "A team API key, identified by a hash of the key",
+    properties: {
+      id: { type: "string" },
+      login: { type: "string" },
+      status: { type: "string", enum: ["active", "expired", "revoked"] },
+      addedAt: { type: "string", format: "date-time" },
+      expiresAt: { type: "string", format: "date-time" },
+      revokedAt: { type: "string", format: "date-time" },
+      lastUsedAt: { type: "string", format: "date-time" },
+      rotatedTo: { type: "string" },
+    },
+  },
+  LogControl: {
+    type: "object",
+    properties: {
+      filter: {
+        type: "string",
+        description: "The log level, with the levels of path prefixes",
+      },
+      bodyLogsRemaining: { type: "integer" },
+    },
+  },
+  AnthropicMessagesRequest: {
+    type: "object",
+    required: ["model", "messages", "max_tokens"],
+    additionalProperties: true,
+    properties: {
+      model: { type: "string" },
+      messages: {
+        type: "array",
+        items: {
+          type: "object",
+          required: ["role", "content"],
+          properties: {
+            role: { type: "string", enum: ["user", "assistant"] },
+            content: {
+              oneOf: [
+                { type: "string" },
+                { type: "array", items: { type: "object" } },
+              ],
+            },
+          },
+        },
+      },
+      max_tokens: { type: "integer" },
+      system: {
+        oneOf: [
+          { type: "string" },
+          { type: "array", items: { type: "object" } },
+        ],
+      },
+      stream: { type: "boolean" },
+      tools: { type: "array", items: { type: "object" } },
+    },
+  },
+  AnthropicMessagesResponse: {
+    type: "object",
+    additionalProperties: true,
+    properties: {
+      id: { type: "string" },
+      type: { const: "message" },
+      role: { const: "assistant" },
+      model: { type: "string" },
+      content: { type: "array", items: { type: "object" } },
+      stop_reason: { type: ["string", "null"] },
+      usage: {
+        type: "object",
+        properties: {
+          input_tokens: { type: "integer" },
+          output_tokens: { type: "integer" },
+        },
+      },
+    },
+  },
+}
+
+export const openApiDocument = {
+  openapi: "3.1.0",
+  info: {
+    title: "Copilot API",
+    version: "1.0.0",
+    description:
+      "OpenAI and Anthropic compatible gateway to the GitHub Copilot API.",
+  },
+  tags: [
+    { name: "OpenAI", description: "OpenAI compatible endpoints" },
+    { name: "Anthropic", description: "Anthropic compatible endpoints" },
+    { name: "Sessions", description: "Recorded conversations and replay" },
+    { name: "Monitoring", description: "Usage and server information" },
+    { name: "Team", description: "API keys of `start --team`" },
+  ],
+  paths: {
+    "/": {
+      get: {
+        summary: "Health check",
+        tags: ["Monitoring"],
+        responses: {
+          "200": {
+            description: "Server is running",
+            content: { "text/plain": { schema: { type: "string" } } },
+          },
+        },
+      },
+    },
+    "/v1/chat/completions": { post: chatCompletionOperation },
+    "/chat/completions": {
+      post: { ...chatCompletionOperation, summary: "Alias of /v1/chat/completions" },
+    },
+    "/v1/models": { get: modelsOperation },
+    "/models": {
+      get: { ...modelsOperation, summary: "Alias of /v1/models" },
+    },
+    "/v1/embeddings": { post: embeddingsOperation },
+    "/embeddings": {
+      post: { ...embeddingsOperation, summary: "Alias of /v1/embeddings" },
+    },
+    "/v1/tokenize": { post: tokenizeOperation },
+    "/tokenize": {
+      post: { ...tokenizeOperation, summary: "Alias of /v1/tokenize" },
+    },
+    "/v1/audio/transcriptions": {
+      post: providerOperation(
+        "Transcribe audio",
+        "multipart/form-data",
+        "application/json",
+      ),
+    },
+    "/v1/audio/speech": {
+      post: providerOperation(
+        "Generate speech",
+        "application/json",
+        "application/octet-stream",
+      ),
+    },
+    "/v1/images/generations": {
+      post: providerOperation(
+        "Generate images",
+        "application/json",
+        "application/json",
+      ),
+    },
+    "/v1/realtime": {
+      get: {
+        summary: "Stream chat completions over a WebSocket",
+        description:
+          "Send chat completion requests as JSON text messages, optionally with an `id`. Replies are `delta`, `done` and `error` messages carrying that id. Requires the Bun runtime.",
+        tags: ["OpenAI"],
+        responses: {
+          "101": { description: "Switching to the WebSocket protocol" },
+          "501": { description: "WebSockets are not supported by this runtime", ...json(ref("Error")) },
+        },
+      },
+    },
+    "/v1/messages": {
+      post: {
+        summary: "Create a message",
+        tags: ["Anthropic"],
+        parameters: [...gatewayHeaderParameters, idempotencyKeyParameter],
+        requestBody: { required: true, ...json(ref("AnthropicMessagesRequest")) },
+        responses: {
+          "200": {
+            description:
+              "The message, or a `text/event-stream` of Anthropic events when `stream` is true",
+            ...json(ref("AnthropicMessagesResponse")),
+          },
+          ...errorResponses,
+          ...idempotencyResponses,
+        },
+      },
+    },
+    "/v1/messages/count_tokens": {
+      post: {
+        summary: "Count message tokens",
+        tags: ["Anthropic"],
+        requestBody: { required: true, ...json(ref("AnthropicMessagesRequest")) },
+        responses: {
+          "200": {
+            description: "Token count",
+            ...json({
+              type: "object",
+              properties: { input_tokens: { type: "integer" } },
+            }),
+          },
+        },
+      },
+    },
+    "/sessions/{id}": {
+      get: {
+        summary: "Fetch a session transcript",
+        description: "Turns recorded with `--sessions` for this `x-session-id`",
+        tags: ["Sessions"],
+        parameters: [sessionIdParameter],
+        responses: {
+          "200": {
+            description: "The recorded turns, oldest first",
+            ...json({
+              type: "object",
+              properties: {
+                id: { type: "string" },
+                turns: { type: "array", items: ref("SessionTurn") },
+              },
+            }),
+          },
+          "404": { description: "Session not found", ...json(ref("Error")) },
+        },
+      },
+    },
+    "/sessions/{id}/replay": {
+      post: {
+        summary: "Replay a session against another model",
+        tags: ["Sessions"],
+        parameters: [sessionIdParameter],
+        requestBody: {
+          required: true,
+          ...json({
+            type: "object",
+            required: ["model"],
+            properties: {
+              model: { type: "string" },
+              turn: {
+                type: "integer",
+                description: "Replay only this turn (0-based)",
+              },
+            },
+          }),
+        },
+        responses: {
+          "200": {
+            description: "Original and replayed responses per turn",
+            ...json({
+              type: "object",
+              properties: {
+                id: { type: "string" },
+                model: { type: "string" },
+                results: {
+                  type: "array",
+                  items: {
+                    type: "object",
+                    properties: {
+                      turn: { type: "integer" },
+                      originalModel: { type: "string" },
+                      original: {},
+                      replay: ref("ChatCompletionResponse"),
+                    },
+                  },
+                },
+              },
+            }),
+          },
+          "404": { description: "Session not found", ...json(ref("Error")) },
+          ...errorResponses,
+        },
+      },
+    },
+    "/usage": {
+      get: {
+        summary: "Copilot usage and quotas",
+        tags: ["Monitoring"],
+        responses: {
+          "200": { description: "Usage as reported by GitHub", ...json({ type: "object" }) },
+          "500": {
+            description: "Usage could not be fetched",
+            ...json({ type: "object", properties: { error: { type: "string" } } }),
+          },
+        },
+      },
+    },
+    "/token": {
+      get: {
+        summary: "Current Copilot token",
+        tags: ["Monitoring"],
+        responses: {
+          "200": {
+            description: "The token used for upstream requests",
+            ...json({
+              type: "object",
+              properties: { token: { type: ["string", "null"] } },
+            }),
+          },
+        },
+      },
+    },
+    "/stats": {
+      get: {
+        summary: "Latency statistics",
+        description:
+          "Request latency by route, time to first token and stream duration by model with p50/p95/p99 estimates, upstream prompt cache hits by model, and token totals by request tag",
+        tags: ["Monitoring"],
+        parameters: [
+          {
+            name: "tag",
+            in: "query",
+            description:
+              "Only list the totals of this tag under `tags`, as `key=value` or a bare key for all its values",
+            schema: { type: "string" },
+          },
+        ],
+        responses: {
+          "200": { description: "Latency histograms", ...json({ type: "object" }) },
+        },
+      },
+    },
+    "/metrics": {
+      get: {
+        summary: "Prometheus metrics",
+        tags: ["Monitoring"],
+        responses: {
+          "200": {
+            description: "Latency histograms in the Prometheus text format",
+            content: { "text/plain": { schema: { type: "string" } } },
+          },
+        },
+      },
+    },
+    "/admin/samples": {
+      get: {
+        summary: "Recent slow or failed requests",
+        description:
+          "Summaries (route, model, token counts, upstream status, duration) of the most recent requests that failed or exceeded the slow threshold, newest first. Message content is never kept.",
+        tags: ["Monitoring"],
+        responses: {
+          "200": {
+            description: "Sampled requests",
+            ...json({
+              type: "object",
+              properties: {
+                samples: { type: "array", items: ref("RequestSample") },
+              },
+            }),
+          },
+        },
+      },
+      delete: {
+        summary: "Clear the sampled requests",
+        tags: ["Monitoring"],
+        responses: { "204": { description: "Cleared" } },
+      },
+    },
+    "/admin/streams": {
+      get: {
+        summary: "Live streaming completions",
+        description:
+          "Streams in progress, with the id sent to their client in the `x-stream-id` header",
+        tags: ["Monitoring"],
+        responses: {
+          "200": {
+            description: "Live streams",
+            ...json({
+              type: "object",
+              properties: {
+                streams: { type: "array", items: ref("StreamInfo") },
+              },
+            }),
+          },
+        },
+      },
+    },
+    "/admin/streams/{id}": {
+      get: {
+        summary: "Observe a live stream",
+        description:
+          "A read-only copy of the events sent to the client, starting from the first one, until the stream ends",
+        tags: ["Monitoring"],
+        parameters: [
+          { name: "id", in: "path", required: true, schema: { type: "string" } },
+        ],
+        responses: {
+          "200": {
+            description: "The client's events",
+            content: { "text/event-stream": { schema: { type: "string" } } },
+          },
+          "404": { description: "No live stream with this id", ...json(ref("Error")) },
+        },
+      },
+    },
+    "/admin/keys": {
+      get: {
+        summary: "List team API keys",
+        tags: ["Team"],
+        responses: {
+          "200": {
+            description: "Every key, including revoked and expired ones",
+            ...json({
+              type: "object",
+              properties: { keys: { type: "array", items: ref("ApiKey") } },
+            }),
+          },
+          "404": { description: "Team mode is off", ...json(ref("Error")) },
+        },
+      },
+      post: {
+        summary: "Create another API key for a member",
+        tags: ["Team"],
+        requestBody: {
+          required: true,
+          ...json({
+            type: "object",
+            required: ["login"],
+            properties: {
+              login: { type: "string" },
+              expiresInDays: { type: "number" },
+            },
+          }),
+        },
+        responses: {
+          "201": {
+            description: "The new key, with `apiKey` shown only here",
+            ...json(ref("ApiKey")),
+          },
+          "404": {
+            description: "The member has no active key",
+            ...json(ref("Error")),
+          },
+        },
+      },
+    },
+    "/admin/keys/audit": {
+      get: {
+        summary: "API key changes",
+        description:
+          "When keys were created, rotated, revoked or removed, oldest first",
+        tags: ["Team"],
+        responses: {
+          "200": { description: "Audit events", ...json({ type: "object" }) },
+        },
+      },
+    },
+    "/admin/keys/{id}/rotate": {
+      post: {
+        summary: "Replace an API key",
+        description:
+          "Issues a new key for the same member. The old key keeps working for `graceHours` (default 24).",
+        tags: ["Team"],
+        parameters: [
+          { name: "id", in: "path", required: true, schema: { type: "string" } },
+        ],
+        requestBody: {
+          ...json({
+            type: "object",
+            properties: {
+              graceHours: { type: "number" },
+              expiresInDays: { type: "number" },
+            },
+          }),
+        },
+        responses: {
+          "201": {
+            description: "The new key, with `apiKey` shown only here",
+            ...json(ref("ApiKey")),
+          },
+          "404": { description: "No such key", ...json(ref("Error")) },
+          "409": {
+            description: "The key is revoked or expired",
+            ...json(ref("Error")),
+          },
+        },
+      },
+    },
+    "/admin/keys/{id}": {
+      delete: {
+        summary: "Revoke an API key",
+        description:
+          "The key stops working at once but stays listed for the audit trail",
+        tags: ["Team"],
+        parameters: [
+          { name: "id", in: "path", required: true, schema: { type: "string" } },
+        ],
+        responses: {
+          "200": { description: "The revoked key", ...json(ref("ApiKey")) },
+          "404": { description: "No such key", ...json(ref("Error")) },
+        },
+      },
+    },
+    "/admin/log": {
+      get: {
+        summary: "Current log filter",
+        tags: ["Monitoring"],
+        responses: {
+          "200": { description: "Log settings", ...json(ref("LogControl")) },
+        },
+      },
+      put: {
+        summary: "Change the log filter",
+        description:
+          "Sets the log level, with levels per path prefix such as `info,/v1/messages=debug`, and logs the bodies of the next `logBodies` requests with secrets redacted. Changes last until the gateway restarts.",
+        tags: ["Monitoring"],
+        requestBody: {
+          required: true,
+          ...json({
+            type: "object",
+            properties: {
+              filter: { type: "string" },
+              logBodies: { type: "integer", minimum: 0, maximum: 1000 },
+            },
+          }),
+        },
+        responses: {
+          "200": { description: "The new settings", ...json(ref("LogControl")) },
+          "400": {
+            description: "Invalid filter or count",
+            ...json(ref("Error")),
+          },
+        },
+      },
+    },
+    "/admin/audit/{id}": {
+      get: {
+        summary: "One audit entry",
+        description:
+          "The request and response recorded with `--audit`, by the `x-audit-id` header of the response",
+        tags: ["Monitoring"],
+        parameters: [
+          { name: "id", in: "path", required: true, schema: { type: "string" } },
+        ],
+        responses: {
+          "200": { description: "The audit entry", ...json({ type: "object" }) },
+          "404": { description: "No such entry", ...json(ref("Error")) },
+        },
+      },
+    },
+    "/openapi.json": {
+      get: {
+        summary: "This document",
+        tags: ["Monitoring"],
+        responses: {
+          "200": { description: "OpenAPI 3.1 document", ...json({ type: "object" }) },
+        },
+      },
+    },
+  },
+  components: { schemas },
+}
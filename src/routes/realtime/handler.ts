@@ -0,0 +1,71 @@
+import type { WSContext } from "hono/ws"
+
+import consola from "consola"
+import { randomUUID } from "node:crypto"
+
+import type {
+  ChatCompletionChunk,
+  ChatCompletionsPayload,
+} from "~/services/copilot/create-chat-completions"
+
+import { HTTPError } from "~/lib/error"
+import { streamCompletion } from "~/lib/streamed-completion"
+
+// A chat completions payload, plus an optional id echoed on every reply
+type RealtimeRequest = ChatCompletionsPayload & { id?: string }
+
+export type RealtimeMessage =
+  | { type: "delta"; id: string; chunk: ChatCompletionChunk }
+  | { type: "done"; id: string }
+  | { type: "error"; id?: string; error: { message: string; status: number } }
+
+const send = (ws: WSContext, message: RealtimeMessage) => {
+  // The client may have gone away while the upstream was still streaming
+  if (ws.readyState === 1) ws.send(JSON.stringify(message))
+}
+
+async function errorStatus(error: unknown): Promise<{
+  message: string
+  status: number
+}> {
+  if (error instanceof HTTPError) {
+    return { message: await error.response.text(), status: error.response.status }
+  }
+  return { message: (error as Error).message, status: 500 }
+}
+
+/**
+ * Streams one completion back over the socket. Several requests may run
+ * concurrently on one connection, distinguished by their id.
+ */
+export async function handleRealtimeMessage(
+  ws: WSContext,
+  raw: string,
+  isClosed: () => boolean,
+  apiKey?: string,
+): Promise<void> {
+  let request: RealtimeRequest
+  try {
+    request = JSON.parse(raw) as RealtimeRequest
+  } catch {
+    send(ws, {
+      type: "error",
+      error: { message: "Message is not valid JSON", status: 400 },
+    })
+    return
+  }
+
+  const { id = randomUUID(), ...body } = request
+  try {
+    const finished = await streamCompletion(body, {
+      endpoint: "/v1/realtime",
+      apiKey,
+      isClosed,
+      onChunk: (chunk) => send(ws, { type: "delta", id, chunk }),
+    })
+    if (finished) send(ws, { type: "done", id })
+  } catch (error) {
+    consola.error("Realtime request failed:", error)
+    send(ws, { type: "error", id, error: await errorStatus(error) })
+  }
+}
@@ -0,0 +1,57 @@
+import { Hono } from "hono"
+
+import { memberCredentials } from "~/lib/api-config"
+import { apiKeyOf } from "~/lib/api-key"
+import { teamCredentials } from "~/lib/team"
+import { isWebSocketSupported, upgradeWebSocket } from "~/lib/websocket"
+
+import { handleRealtimeMessage } from "./handler"
+
+export const realtimeRoutes = new Hono()
+
+realtimeRoutes.get(
+  "/",
+  async (c, next) => {
+    if (isWebSocketSupported()) return next()
+    return c.json(
+      {
+        error: {
+          message: "WebSocket connections require the Bun runtime",
+          type: "error",
+        },
+      },
+      501,
+    )
+  },
+  teamCredentials,
+  upgradeWebSocket((c) => {
+    let closed = false
+    // For per-key model policies, like the credentials below
+    const apiKey = apiKeyOf(c)
+    // Messages arrive outside the upgrade request, so the team member's
+    // credentials are carried over explicitly
+    const credentials = memberCredentials.getStore()
+    return {
+      onMessage(event, ws) {
+        if (typeof event.data !== "string") {
+          ws.send(
+            JSON.stringify({
+              type: "error",
+              error: { message: "Send requests as text messages", status: 400 },
+            }),
+          )
+          return
+        }
+        const data = event.data
+        const handle = () =>
+          handleRealtimeMessage(ws, data, () => closed, apiKey)
+        void (credentials ?
+          memberCredentials.run(credentials, handle)
+        : handle())
+      },
+      onClose() {
+        closed = true
+      },
+    }
+  }),
+)
@@ -0,0 +1,16 @@
+import { Hono } from "hono"
+
+import { requireAdmin } from "~/lib/admin-auth"
+import { clearSamples, getSamples } from "~/lib/request-samples"
+
+export const samplesRoute = new Hono()
+
+// Error messages can quote requests, so only local callers or the admin
+samplesRoute.use(requireAdmin)
+
+samplesRoute.get("/", (c) => c.json({ samples: getSamples() }))
+
+samplesRoute.delete("/", (c) => {
+  clearSamples()
+  return c.body(null, 204)
+})
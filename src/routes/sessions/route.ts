@@ -0,0 +1,92 @@
+import { Hono } from "hono"
+
+import { forwardError, HTTPError } from "~/lib/error"
+import { checkModelAccess } from "~/lib/model-policy"
+import { checkRateLimit } from "~/lib/rate-limit"
+import { readSession, sessionOwner } from "~/lib/sessions"
+import { state } from "~/lib/state"
+import { teamCredentials } from "~/lib/team"
+import { resolveModel } from "~/lib/utils"
+import {
+  createChatCompletions,
+  type ChatCompletionResponse,
+} from "~/services/copilot/create-chat-completions"
+
+interface ReplayRequest {
+  model: string
+  // Replays only this turn (0-based) instead of all of them
+  turn?: number
+}
+
+const notFound = () =>
+  new HTTPError(
+    "Session not found",
+    Response.json(
+      { error: { message: "Session not found", type: "error" } },
+      { status: 404 },
+    ),
+  )
+
+export const sessionRoutes = new Hono()
+
+// Sessions are stored per API key in team mode, see sessionOwner
+sessionRoutes.use(teamCredentials)
+
+sessionRoutes.get("/:id", async (c) => {
+  try {
+    const id = c.req.param("id")
+    const turns = await readSession(id, sessionOwner(c))
+    if (!turns) throw notFound()
+    return c.json({ id, turns })
+  } catch (error) {
+    return await forwardError(c, error)
+  }
+})
+
+/**
+ * Reruns recorded turns against another model, each with the exact history
+ * the client sent originally. Replays are never streamed and not recorded.
+ */
+sessionRoutes.post("/:id/replay", async (c) => {
+  try {
+    const id = c.req.param("id")
+    const turns = await readSession(id, sessionOwner(c))
+    if (!turns) throw notFound()
+
+    const body = await c.req.json<ReplayRequest>()
+    const model = resolveModel(body.model)
+    checkModelAccess(c, model)
+
+    const indexes =
+      body.turn === undefined ? turns.map((_, index) => index) : [body.turn]
+    if (
+      indexes.some(
+        (index) => !Number.isInteger(index) || index < 0 || index >= turns.length,
+      )
+    ) {
+      const message = `Session ${id} has no turn ${body.turn}`
+      throw new HTTPError(message, Response.json({ message }, { status: 400 }))
+    }
+
+    const results = []
+    for (const index of indexes) {
+      await checkRateLimit(state)
+      const original = turns[index]
+      const response = (await createChatCompletions({
+        ...original.request,
+        model,
+        stream: false,
+      })) as ChatCompletionResponse
+      results.push({
+        turn: index,
+        originalModel: original.model,
+        original: original.response,
+        replay: response,
+      })
+    }
+
+    return c.json({ id, model, results })
+  } catch (error) {
+    return await forwardError(c, error)
+  }
+})
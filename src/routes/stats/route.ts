@@ -0,0 +1,15 @@
+import { Hono } from "hono"
+
+import { getStats, renderPrometheus } from "~/lib/metrics"
+
+export const statsRoute = new Hono()
+
+statsRoute.get("/", (c) => c.json(getStats({ tag: c.req.query("tag") })))
+
+export const metricsRoute = new Hono()
+
+metricsRoute.get("/", (c) =>
+  c.text(renderPrometheus(), 200, {
+    "content-type": "text/plain; version=0.0.4; charset=utf-8",
+  }),
+)
@@ -0,0 +1,31 @@
+import { Hono } from "hono"
+import { streamSSE } from "hono/streaming"
+
+import { requireAdmin } from "~/lib/admin-auth"
+import { getBroadcast, listBroadcasts } from "~/lib/stream-broadcast"
+
+export const streamsRoute = new Hono()
+
+// Live completions of other clients, for local callers or the admin token,
+// which sees every stream. Team members only get their own streams back,
+// through Last-Event-ID resumes.
+streamsRoute.use(requireAdmin)
+
+streamsRoute.get("/", (c) => c.json({ streams: listBroadcasts() }))
+
+streamsRoute.get("/:id", (c) => {
+  const broadcast = getBroadcast(c.req.param("id"))
+  if (!broadcast) {
+    return c.json(
+      { error: { message: "No live stream with this id", type: "error" } },
+      404,
+    )
+  }
+
+  return streamSSE(c, async (stream) => {
+    for await (const event of broadcast.subscribe()) {
+      if (stream.aborted) break
+      await stream.writeSSE(event)
+    }
+  })
+})
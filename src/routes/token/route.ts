@@ -1,13 +1,18 @@
 import { Hono } from "hono"
 
+import { currentCopilotToken } from "~/lib/api-config"
 import { state } from "~/lib/state"
+import { teamCredentials } from "~/lib/team"
 
 export const tokenRoute = new Hono()
 
+// In team mode, members get their own Copilot token
+tokenRoute.use(teamCredentials)
+
 tokenRoute.get("/", (c) => {
   try {
     return c.json({
-      token: state.copilotToken,
+      token: currentCopilotToken(state),
     })
   } catch (error) {
     console.error("Error fetching token:", error)
@@ -0,0 +1,43 @@
+import { Hono } from "hono"
+
+import type { Message } from "~/services/copilot/create-chat-completions"
+
+import { rustCore } from "~/lib/rust-core"
+import { getTokenCount } from "~/lib/tokenizer"
+
+export const tokenizeRoute = new Hono()
+
+// The native counter when the module is built, else the JS port of it
+async function countTokens(messages: Array<Message>) {
+  try {
+    return await rustCore.getTokenCount(messages)
+  } catch {
+    return getTokenCount(messages)
+  }
+}
+
+tokenizeRoute.post("/", async (c) => {
+  const payload = await c.req.json<{ messages?: unknown }>()
+  if (!Array.isArray(payload.messages)) {
+    return c.json(
+      {
+        error: {
+          message: "Expected a `messages` array",
+          type: "invalid_request_error",
+          param: "messages",
+          code: null,
+        },
+      },
+      400,
+    )
+  }
+
+  const { input, output } = await countTokens(
+    payload.messages as Array<Message>,
+  )
+  return c.json({
+    input_tokens: input,
+    output_tokens: output,
+    total_tokens: input + output,
+  })
+})
@@ -1,13 +1,23 @@
 import { Hono } from "hono"
 
+import { state } from "~/lib/state"
+import { teamCredentials } from "~/lib/team"
+import { projectUsage } from "~/lib/usage-projection"
 import { getCopilotUsage } from "~/services/github/get-copilot-usage"
 
 export const usageRoute = new Hono()
 
+// In team mode, members see their own quota
+usageRoute.use(teamCredentials)
+
 usageRoute.get("/", async (c) => {
   try {
     const usage = await getCopilotUsage()
-    return c.json(usage)
+    // The projection is of the gateway's account, not a team member's
+    if (state.usagePollMinutes === undefined || state.teamStore) {
+      return c.json(usage)
+    }
+    return c.json({ ...usage, projection: projectUsage() })
   } catch (error) {
     console.error("Error fetching Copilot usage:", error)
     return c.json({ error: "Failed to fetch Copilot usage" }, 500)
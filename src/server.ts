@@ -2,17 +2,71 @@ import { Hono } from "hono"
 import { cors } from "hono/cors"
 import { logger } from "hono/logger"
 
+import { createChaosMiddleware } from "./lib/chaos"
+import { clientCertificate } from "./lib/client-cert"
+import { clientIp } from "./lib/client-ip"
+import { rejectWhileDraining } from "./lib/drain"
+import { parseJsonBody } from "./lib/json-body"
+import { logControl } from "./lib/log-control"
+import { observeRequest } from "./lib/metrics"
+import { runPlugins } from "./lib/plugins"
+import { rateLimitHeaders } from "./lib/rate-limit-headers"
+import { recordSample } from "./lib/request-samples"
+import { verifySignature } from "./lib/request-signing"
+import { scrubResponseHeaders } from "./lib/response-headers"
+import { recordResponseStatus } from "./lib/slo-alerts"
+
+import { audioRoutes } from "./routes/audio/route"
+import { auditRoute } from "./routes/audit/route"
 import { completionRoutes } from "./routes/chat-completions/route"
 import { embeddingRoutes } from "./routes/embeddings/route"
+import { imageRoutes } from "./routes/images/route"
+import { keysRoute } from "./routes/keys/route"
+import { logRoute } from "./routes/log/route"
 import { messageRoutes } from "./routes/messages/route"
 import { modelRoutes } from "./routes/models/route"
+import { docsRoute, openApiRoute } from "./routes/openapi/route"
+import { realtimeRoutes } from "./routes/realtime/route"
+import { samplesRoute } from "./routes/samples/route"
+import { sessionRoutes } from "./routes/sessions/route"
+import { metricsRoute, statsRoute } from "./routes/stats/route"
+import { streamsRoute } from "./routes/streams/route"
 import { tokenRoute } from "./routes/token/route"
+import { tokenizeRoute } from "./routes/tokenize/route"
 import { usageRoute } from "./routes/usage/route"
 
 export const server = new Hono()
 
+server.use(scrubResponseHeaders)
+server.use(logControl)
 server.use(logger())
 server.use(cors())
+// Before the metrics, so a rolling restart's 503s don't trip the SLO alerts
+server.use(rejectWhileDraining)
+server.use(async (c, next) => {
+  const start = performance.now()
+  await next()
+  // Label by route pattern rather than raw path to keep cardinality bounded
+  const route =
+    c.req.matchedRoutes.findLast((matched) => matched.method !== "ALL")?.path
+    ?? "unmatched"
+  const durationMs = performance.now() - start
+  observeRequest(route, durationMs / 1000)
+  recordResponseStatus(c.res.status)
+  recordSample(c.req.raw, {
+    method: c.req.method,
+    route,
+    status: c.res.status,
+    durationMs,
+    ip: clientIp(c),
+  })
+})
+server.use(clientCertificate)
+server.use(verifySignature)
+server.use(rateLimitHeaders)
+server.use(createChaosMiddleware())
+server.use(runPlugins)
+server.use(parseJsonBody)
 
 server.get("/", (c) => c.text("Server running"))
 
@@ -21,12 +75,39 @@ server.route("/models", modelRoutes)
 server.route("/embeddings", embeddingRoutes)
 server.route("/usage", usageRoute)
 server.route("/token", tokenRoute)
+server.route("/tokenize", tokenizeRoute)
+server.route("/openapi.json", openApiRoute)
+server.route("/stats", statsRoute)
+server.route("/metrics", metricsRoute)
+server.route("/admin/samples", samplesRoute)
+server.route("/admin/streams", streamsRoute)
+server.route("/admin/keys", keysRoute)
+server.route("/admin/log", logRoute)
+server.route("/admin/audit", auditRoute)
+server.route("/sessions", sessionRoutes)
+server.route("/docs", docsRoute)
 
 // Compatibility with tools that expect v1/ prefix
 server.route("/v1/chat/completions", completionRoutes)
 server.route("/v1/models", modelRoutes)
 server.route("/v1/embeddings", embeddingRoutes)
+server.route("/v1/tokenize", tokenizeRoute)
+// Forwarded to the configured provider, if any
+server.route("/v1/audio", audioRoutes)
+server.route("/v1/images", imageRoutes)
+
+// WebSocket alternative to SSE streaming
+server.route("/v1/realtime", realtimeRoutes)
 
 // Anthropic compatible endpoints
 server.route("/v1/messages", messageRoutes)
 server.post("/v1/messages/count_tokens", (c) => c.json({ input_tokens: 1 }))
+
+/**
+ * The server mounted under `prefix`, e.g. `/copilot` serves
+ * `/copilot/v1/chat/completions`. Nothing is served outside the prefix.
+ */
+export function mountServer(prefix: string | undefined): Hono {
+  if (!prefix) return server
+  return new Hono().route(prefix, server)
+}
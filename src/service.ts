@@ -0,0 +1,269 @@
+#!/usr/bin/env node
+
+import { defineCommand } from "citty"
+import consola from "consola"
+import { execSync } from "node:child_process"
+import fs from "node:fs/promises"
+import os from "node:os"
+import path from "node:path"
+import process from "node:process"
+
+import { PATHS } from "./lib/paths"
+
+const SERVICE_NAME = "copilot-api"
+const LAUNCHD_LABEL = "com.github.copilot-api"
+
+interface ServiceStartOptions {
+  port: string
+  accountType: string
+  rateLimit?: string
+  wait: boolean
+  verbose: boolean
+}
+
+const isMacOS = () => process.platform === "darwin"
+
+const unitPath = () =>
+  isMacOS() ?
+    path.join(
+      os.homedir(),
+      "Library",
+      "LaunchAgents",
+      `${LAUNCHD_LABEL}.plist`,
+    )
+  : path.join(
+      os.homedir(),
+      ".config",
+      "systemd",
+      "user",
+      `${SERVICE_NAME}.service`,
+    )
+
+export function startCommand(options: ServiceStartOptions): Array<string> {
+  // Re-run this very entry point, so the service uses the same runtime and version
+  const command = [process.execPath, path.resolve(process.argv[1]), "start"]
+  command.push("--port", options.port, "--account-type", options.accountType)
+  if (options.rateLimit) command.push("--rate-limit", options.rateLimit)
+  if (options.wait) command.push("--wait")
+  if (options.verbose) command.push("--verbose")
+  return command
+}
+
+const escapeXml = (text: string) =>
+  text
+    .replaceAll("&", "&amp;")
+    .replaceAll("<", "&lt;")
+    .replaceAll(">", "&gt;")
+
+export function systemdUnit(command: Array<string>): string {
+  const execStart = command
+    .map((part) => (part.includes(" ") ? `"${part}"` : part))
+    .join(" ")
+
+  return `[Unit]
+Description=Copilot API server
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+ExecStart=${execStart}
+Restart=on-failure
+RestartSec=5
+Environment=NODE_ENV=production
+
+[Install]
+WantedBy=default.target
+`
+}
+
+export function launchdPlist(command: Array<string>): string {
+  const logPath = path.join(PATHS.APP_DIR, "service.log")
+  const args = command
+    .map((part) => `    <string>${escapeXml(part)}</string>`)
+    .join("\n")
+
+  return `<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>${LAUNCHD_LABEL}</string>
+  <key>ProgramArguments</key>
+  <array>
+${args}
+  </array>
+  <key>EnvironmentVariables</key>
+  <dict>
+    <key>NODE_ENV</key>
+    <string>production</string>
+  </dict>
+  <key>RunAtLoad</key>
+  <true/>
+  <key>KeepAlive</key>
+  <true/>
+  <key>StandardOutPath</key>
+  <string>${escapeXml(logPath)}</string>
+  <key>StandardErrorPath</key>
+  <string>${escapeXml(logPath)}</string>
+</dict>
+</plist>
+`
+}
+
+const run = (command: string) => execSync(command, { stdio: "inherit" })
+
+function ensureSupportedPlatform(): void {
+  if (process.platform !== "linux" && !isMacOS()) {
+    throw new Error(
+      `Service installation is only supported on Linux (systemd) and macOS (launchd), not ${process.platform}`,
+    )
+  }
+}
+
+export async function installService(
+  options: ServiceStartOptions,
+): Promise<void> {
+  ensureSupportedPlatform()
+
+  const command = startCommand(options)
+  const file = unitPath()
+
+  await fs.mkdir(path.dirname(file), { recursive: true })
+  await fs.writeFile(
+    file,
+    isMacOS() ? launchdPlist(command) : systemdUnit(command),
+  )
+  consola.info("Service definition written to", file)
+
+  if (isMacOS()) {
+    run(`launchctl load -w "${file}"`)
+  } else {
+    run("systemctl --user daemon-reload")
+    run(`systemctl --user enable --now ${SERVICE_NAME}.service`)
+  }
+
+  consola.success(
+    `Service installed, listening on http://localhost:${options.port}`,
+  )
+}
+
+export async function uninstallService(): Promise<void> {
+  ensureSupportedPlatform()
+
+  const file = unitPath()
+
+  if (isMacOS()) {
+    run(`launchctl unload -w "${file}"`)
+  } else {
+    run(`systemctl --user disable --now ${SERVICE_NAME}.service`)
+  }
+
+  await fs.rm(file, { force: true })
+  if (!isMacOS()) run("systemctl --user daemon-reload")
+
+  consola.success("Service uninstalled")
+}
+
+export async function serviceStatus(): Promise<void> {
+  ensureSupportedPlatform()
+
+  const file = unitPath()
+  const installed = await fs
+    .access(file)
+    .then(() => true)
+    .catch(() => false)
+
+  if (!installed) {
+    consola.info("Service is not installed")
+    return
+  }
+
+  consola.info("Service definition:", file)
+  try {
+    if (isMacOS()) {
+      run(`launchctl list ${LAUNCHD_LABEL}`)
+    } else {
+      run(`systemctl --user status ${SERVICE_NAME}.service --no-pager`)
+    }
+  } catch {
+    // systemctl status exits non-zero when the unit is not running
+    process.exitCode = 1
+  }
+}
+
+const install = defineCommand({
+  meta: {
+    name: "install",
+    description: "Install and start a user service running `start`",
+  },
+  args: {
+    port: {
+      alias: "p",
+      type: "string",
+      default: "4141",
+      description: "Port to listen on",
+    },
+    "account-type": {
+      alias: "a",
+      type: "string",
+      default: "individual",
+      description: "Account type to use (individual, business, enterprise)",
+    },
+    "rate-limit": {
+      alias: "r",
+      type: "string",
+      description: "Rate limit in seconds between requests",
+    },
+    wait: {
+      alias: "w",
+      type: "boolean",
+      default: false,
+      description: "Wait instead of error when rate limit is hit",
+    },
+    verbose: {
+      alias: "v",
+      type: "boolean",
+      default: false,
+      description: "Enable verbose logging",
+    },
+  },
+  run({ args }) {
+    return installService({
+      port: args.port,
+      accountType: args["account-type"],
+      rateLimit: args["rate-limit"],
+      wait: args.wait,
+      verbose: args.verbose,
+    })
+  },
+})
+
+const uninstall = defineCommand({
+  meta: {
+    name: "uninstall",
+    description: "Stop and remove the user service",
+  },
+  run() {
+    return uninstallService()
+  },
+})
+
+const status = defineCommand({
+  meta: {
+    name: "status",
+    description: "Show the status of the user service",
+  },
+  run() {
+    return serviceStatus()
+  },
+})
+
+export const service = defineCommand({
+  meta: {
+    name: "service",
+    description:
+      "Manage a systemd (Linux) or launchd (macOS) service for the server",
+  },
+  subCommands: { install, uninstall, status },
+})
@@ -1,26 +1,58 @@
 import consola from "consola"
 import { events } from "fetch-event-stream"
 
-import { copilotHeaders, copilotBaseUrl } from "~/lib/api-config"
+import { copilotHeaders } from "~/lib/api-config"
 import { HTTPError } from "~/lib/error"
+import { withUpstreamAgent } from "~/lib/http-options"
+import { observeUpstreamResponse } from "~/lib/metrics"
+import { captureUpstreamRateLimits } from "~/lib/rate-limit-headers"
+import { sendWithReplay } from "~/lib/replay-queue"
 import { state } from "~/lib/state"
+import { preferredUpstream, sendToUpstream } from "~/lib/upstreams"
 
-export const createChatCompletions = async (
+/** The upstream request for `payload`, as sent by `createChatCompletions`. */
+export const buildChatCompletionsRequest = (
   payload: ChatCompletionsPayload,
+  baseUrl: string = preferredUpstream(),
 ) => {
-  if (!state.copilotToken) throw new Error("Copilot token not found")
-
   const enableVision = payload.messages.some(
     (x) =>
       typeof x.content !== "string"
       && x.content?.some((x) => x.type === "image_url"),
   )
 
-  const response = await fetch(`${copilotBaseUrl(state)}/chat/completions`, {
+  return {
     method: "POST",
+    url: `${baseUrl}/chat/completions`,
     headers: copilotHeaders(state, enableVision),
-    body: JSON.stringify(payload),
-  })
+    body: payload,
+  }
+}
+
+export const createChatCompletions = async (
+  payload: ChatCompletionsPayload,
+  options: { signal?: AbortSignal } = {},
+) => {
+  if (!state.copilotToken) throw new Error("Copilot token not found")
+
+  const response = await sendWithReplay(() =>
+    sendToUpstream((baseUrl) => {
+      // Rebuilt per attempt so each gets its own x-request-id
+      const request = buildChatCompletionsRequest(payload, baseUrl)
+      return fetch(
+        request.url,
+        withUpstreamAgent({
+          method: request.method,
+          headers: request.headers,
+          body: JSON.stringify(request.body),
+          signal: options.signal,
+        }),
+      )
+    }),
+  )
+
+  captureUpstreamRateLimits(response.headers)
+  observeUpstreamResponse(response.headers)
 
   if (!response.ok) {
     consola.error("Failed to create chat completions", response)
@@ -34,6 +66,16 @@ export const createChatCompletions = async (
   return (await response.json()) as ChatCompletionResponse
 }
 
+export interface Usage {
+  prompt_tokens: number
+  completion_tokens: number
+  total_tokens: number
+  // Prompt tokens served from the upstream prompt cache
+  prompt_tokens_details?: {
+    cached_tokens?: number
+  }
+}
+
 // Streaming types
 
 export interface ChatCompletionChunk {
@@ -43,6 +85,8 @@ export interface ChatCompletionChunk {
   model: string
   choices: Array<Choice>
   system_fingerprint?: string
+  // Only on the final chunk, and only when the upstream reports it
+  usage?: Usage | null
 }
 
 interface Delta {
@@ -75,11 +119,7 @@ export interface ChatCompletionResponse {
   model: string
   choices: Array<ChoiceNonStreaming>
   system_fingerprint?: string
-  usage?: {
-    prompt_tokens: number
-    completion_tokens: number
-    total_tokens: number
-  }
+  usage?: Usage
 }
 
 interface ResponseMessage {
@@ -111,7 +151,7 @@ export interface ChatCompletionsPayload {
   presence_penalty?: number | null
   logit_bias?: Record<string, number> | null
   logprobs?: boolean | null
-  response_format?: { type: "json_object" } | null
+  response_format?: ResponseFormat | null
   seed?: number | null
   tools?: Array<Tool> | null
   tool_choice?:
@@ -121,8 +161,30 @@ export interface ChatCompletionsPayload {
     | { type: "function"; function: { name: string } }
     | null
   user?: string | null
+  // Read as request tags by the gateway and not forwarded
+  metadata?: Record<string, string> | null
+  // "ndjson" streams JSON lines instead of SSE; not forwarded
+  stream_format?: "sse" | "ndjson" | null
+  // Groups requests that share a prompt prefix for upstream prompt caching
+  prompt_cache_key?: string | null
+  reasoning_effort?: ReasoningEffort | null
 }
 
+export type ReasoningEffort = "minimal" | "low" | "medium" | "high"
+
+export type ResponseFormat =
+  | { type: "text" }
+  | { type: "json_object" }
+  | {
+      type: "json_schema"
+      json_schema: {
+        name: string
+        description?: string
+        schema?: Record<string, unknown>
+        strict?: boolean | null
+      }
+    }
+
 export interface Tool {
   type: "function"
   function: {
@@ -1,15 +1,30 @@
-import { copilotHeaders, copilotBaseUrl } from "~/lib/api-config"
+import { copilotHeaders } from "~/lib/api-config"
 import { HTTPError } from "~/lib/error"
+import { withUpstreamAgent } from "~/lib/http-options"
+import { observeUpstreamResponse } from "~/lib/metrics"
+import { captureUpstreamRateLimits } from "~/lib/rate-limit-headers"
+import { sendWithReplay } from "~/lib/replay-queue"
 import { state } from "~/lib/state"
+import { sendToUpstream } from "~/lib/upstreams"
 
 export const createEmbeddings = async (payload: EmbeddingRequest) => {
   if (!state.copilotToken) throw new Error("Copilot token not found")
 
-  const response = await fetch(`${copilotBaseUrl(state)}/embeddings`, {
-    method: "POST",
-    headers: copilotHeaders(state),
-    body: JSON.stringify(payload),
-  })
+  const response = await sendWithReplay(() =>
+    sendToUpstream((baseUrl) =>
+      fetch(
+        `${baseUrl}/embeddings`,
+        withUpstreamAgent({
+          method: "POST",
+          headers: copilotHeaders(state),
+          body: JSON.stringify(payload),
+        }),
+      ),
+    ),
+  )
+
+  captureUpstreamRateLimits(response.headers)
+  observeUpstreamResponse(response.headers)
 
   if (!response.ok) throw new HTTPError("Failed to create embeddings", response)
 
@@ -19,6 +34,11 @@ export const createEmbeddings = async (payload: EmbeddingRequest) => {
 export interface EmbeddingRequest {
   input: string | Array<string>
   model: string
+  // Forwarded, and also applied locally in case the model ignores it
+  dimensions?: number
+  // Applied locally, the upstream is always asked for floats
+  encoding_format?: "float" | "base64"
+  user?: string
 }
 
 export interface Embedding {
@@ -1,11 +1,18 @@
-import { copilotBaseUrl, copilotHeaders } from "~/lib/api-config"
+import { copilotHeaders } from "~/lib/api-config"
 import { HTTPError } from "~/lib/error"
+import { withUpstreamAgent } from "~/lib/http-options"
+import { observeUpstreamResponse } from "~/lib/metrics"
 import { state } from "~/lib/state"
+import { sendToUpstream } from "~/lib/upstreams"
 
 export const getModels = async () => {
-  const response = await fetch(`${copilotBaseUrl(state)}/models`, {
-    headers: copilotHeaders(state),
-  })
+  const response = await sendToUpstream((baseUrl) =>
+    fetch(
+      `${baseUrl}/models`,
+      withUpstreamAgent({ headers: copilotHeaders(state) }),
+    ),
+  )
+  observeUpstreamResponse(response.headers)
 
   if (!response.ok) throw new HTTPError("Failed to get models", response)
 
@@ -28,6 +35,10 @@ interface ModelSupports {
   tool_calls?: boolean
   parallel_tool_calls?: boolean
   dimensions?: boolean
+  vision?: boolean
+  structured_outputs?: boolean
+  // Accepted `reasoning_effort` values, for models that report them
+  reasoning_effort?: Array<string>
 }
 
 interface ModelCapabilities {
@@ -39,7 +50,7 @@ interface ModelCapabilities {
   type: string
 }
 
-interface Model {
+export interface Model {
   capabilities: ModelCapabilities
   id: string
   model_picker_enabled: boolean
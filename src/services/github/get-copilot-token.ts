@@ -2,11 +2,15 @@ import { GITHUB_API_BASE_URL, githubHeaders } from "~/lib/api-config"
 import { HTTPError } from "~/lib/error"
 import { state } from "~/lib/state"
 
-export const getCopilotToken = async () => {
+// Team members exchange their own GitHub token
+export const getCopilotToken = async (githubToken = state.githubToken) => {
   const response = await fetch(
     `${GITHUB_API_BASE_URL}/copilot_internal/v2/token`,
     {
-      headers: githubHeaders(state),
+      headers: {
+        ...githubHeaders(state),
+        authorization: `token ${githubToken}`,
+      },
     },
   )
 
@@ -1,28 +1,276 @@
 #!/usr/bin/env node
 
+import type { Hono } from "hono"
+
 import { defineCommand } from "citty"
-import clipboard from "clipboardy"
 import consola from "consola"
-import { serve, type ServerHandler } from "srvx"
+import { serve, type Server, type ServerHandler } from "srvx"
 import invariant from "tiny-invariant"
 
-import { ensurePaths } from "./lib/paths"
-import { generateEnvScript } from "./lib/shell"
+import { startGrpcServer } from "./grpc"
+import { openAccountingStore } from "./lib/accounting-store"
+import { pruneAuditLog, startAuditPruning } from "./lib/audit"
+import { loadChaosConfig } from "./lib/chaos"
+import { setupClaudeCode } from "./lib/claude-code"
+import { loadCertificateIdentities, tlsServeOptions } from "./lib/client-cert"
+import { type Subnet, parseTrustedProxies } from "./lib/client-ip"
+import { loadContentPolicy } from "./lib/content-policy"
+import { drain, inFlightRequests, isDraining } from "./lib/drain"
+import {
+  applyLogFormat,
+  loadEnvConfig,
+  type LogFormat,
+  STARTUP_CHECKS,
+  type StartupCheck,
+  parseIntegerOption,
+  parseMapping,
+  resolveConfigValue,
+} from "./lib/env-config"
+import { loadExperiments } from "./lib/experiments"
+import {
+  configureUpstreamAgent,
+  fetchAgentOptions,
+  parseHttpOptions,
+} from "./lib/http-options"
+import { configureIdempotency } from "./lib/idempotency"
+import { listenerApp, loadListeners } from "./lib/listeners"
+import { loadMediaProviders } from "./lib/media-providers"
+import { loadModelPolicy } from "./lib/model-policy"
+import { loadParamPolicy } from "./lib/param-policy"
+import { normalizePathPrefix } from "./lib/path-prefix"
+import { PATHS, ensurePaths } from "./lib/paths"
+import { loadPlugins } from "./lib/plugins"
+import { resolvePort, urlHost } from "./lib/port"
+import { loadQuotaGuard, startQuotaGuard } from "./lib/quota-guard"
+import { createRedisRateLimiter } from "./lib/rate-limit-redis"
+import { checkUpstream, StartupCheckError } from "./lib/readiness"
+import { configureReplayQueue } from "./lib/replay-queue"
+import { configureSampling, dumpSamples } from "./lib/request-samples"
+import { loadRequestSigning } from "./lib/request-signing"
+import { loadHeaderPolicy } from "./lib/response-headers"
+import { features, type HttpOptions, rustCore } from "./lib/rust-core"
+import { configureScheduler, loadPriorityKeys } from "./lib/scheduler"
+import { loadShadowConfig } from "./lib/shadow"
+import { loadSloConfig, startSloAlerts } from "./lib/slo-alerts"
+import { takeActivatedSocket } from "./lib/socket-activation"
+import { printStartupBanner } from "./lib/startup-banner"
 import { state } from "./lib/state"
-import { setupCopilotToken, setupGitHubToken } from "./lib/token"
-import { cacheModels, cacheVSCodeVersion } from "./lib/utils"
-import { server } from "./server"
+import { configureStreamBuffer } from "./lib/stream-broadcast"
+import { readTeamStore, startKeyUsageFlush } from "./lib/team"
+import { setupGitHubToken } from "./lib/token"
+import {
+  readTokenFile,
+  readTokenStdin,
+  watchTokenFile,
+} from "./lib/token-source"
+import {
+  configureUpstreams,
+  parseUpstreams,
+  startUpstreamHealthChecks,
+} from "./lib/upstreams"
+import { startUsagePolling } from "./lib/usage-projection"
+import { cacheVSCodeVersion } from "./lib/utils"
+import { bunServerEnv, websocket } from "./lib/websocket"
+import { mountServer, server } from "./server"
 
 interface RunServerOptions {
   port: number
+  portRetry: number
+  grpcPort?: number
+  host?: string
+  // Normalized, e.g. /copilot; the API is served at the root when undefined
+  pathPrefix?: string
+  // Bind with SO_REUSEPORT so a new process can listen next to a draining one
+  reusePort: boolean
+  trustedProxies?: Array<Subnet>
+  // Copilot base URLs in failover order, the account type's one when undefined
+  upstreams?: Array<string>
   verbose: boolean
   accountType: string
   manual: boolean
   rateLimit?: number
   rateLimitWait: boolean
+  rateLimitRedisUrl?: string
+  // sqlite:<path> or a postgres:// URL for usage records and audit entries
+  accountingStore?: string
+  tokenRateLimit?: number
+  // Seconds to hold requests rejected upstream with 429, disabled when undefined
+  retry429MaxWait?: number
+  retryQueueSize?: number
+  // Seconds to keep responses for Idempotency-Key retries, 0 disables
+  idempotencyTtl?: number
+  // Audit logging is disabled when undefined
+  auditRetentionDays?: number
+  // Brotli-compressed audit files with an index
+  auditCompress: boolean
+  sessions: boolean
+  recordUsage: boolean
+  // Each API key uses its own GitHub account from the team token store
+  team: boolean
+  // Bearer token for /admin; required there in team mode
+  adminToken?: string
+  contentPolicy?: string
+  modelPolicy?: string
+  paramPolicy?: string
+  // JSON file of response headers to remove, rename or add
+  headerPolicy?: string
+  // JSON file of HMAC signing clients
+  signingKeys?: string
+  // JSON file with error rate and time to first token alert thresholds
+  sloAlerts?: string
+  // JSON file describing the upstream chat requests are mirrored to
+  shadow?: string
+  // JSON file of providers for the audio and image endpoints
+  mediaProviders?: string
+  // JSON file of model A/B experiments
+  experiments?: string
+  // JSON file with the premium request share at which batch traffic stops
+  quotaGuard?: string
+  // Minutes between premium request usage snapshots
+  pollUsage?: number
+  // Serve HTTPS with this certificate and key
+  tlsCert?: string
+  tlsKey?: string
+  // Require client certificates issued by this CA
+  tlsClientCa?: string
+  // JSON file mapping certificate CNs to API keys
+  tlsClientIdentities?: string
+  // JSON file of additional listeners, each with its own TLS and API keys
+  listeners?: string
+  promptCacheKey: boolean
+  repairToolCalls: boolean
+  contextCheck: boolean
+  // Model that summarizes older turns of prompts over the context window
+  summarizeModel?: string
+  structuredOutputRetry: boolean
+  // Concurrent upstream requests, unlimited when undefined
+  maxConcurrency?: number
+  priorityKeys?: string
+  sampleSlowMs?: number
+  sampleSize?: number
+  // Events kept per stream for observers and Last-Event-ID resumes
+  streamBuffer?: number
+  // Seconds in-flight requests get to finish on SIGTERM or SIGINT
+  drainTimeout: number
+  // Connection pool for requests to Copilot, and the native HTTP client
+  upstreamHttp?: Partial<HttpOptions>
+  // WASM transforms, run in this order
+  plugins?: Array<string>
+  chaos?: string
   githubToken?: string
+  // Watched and reloaded on change
+  tokenFile?: string
+  tokenStdin: boolean
+  defaultModel?: string
+  selectDefaultModel: boolean
+  modelAliases?: Record<string, string>
+  modelsTtl?: number
   claudeCode: boolean
+  claudeModel?: string
+  claudeSmallModel?: string
+  claudeLaunch: boolean
   showToken: boolean
+  docs: boolean
+  logFormat?: LogFormat
+  startupCheck: StartupCheck
+}
+
+async function setupDefaultModel(options: RunServerOptions): Promise<void> {
+  if (options.selectDefaultModel && process.stdin.isTTY) {
+    invariant(state.models, "Models should be loaded by now")
+    state.defaultModel = await consola.prompt(
+      "Select a default model for requests without a known model",
+      {
+        type: "select",
+        options: state.models.data.map((model) => model.id),
+      },
+    )
+  } else {
+    if (options.selectDefaultModel) {
+      consola.warn("Not running in a terminal, skipping default model picker")
+    }
+    state.defaultModel = options.defaultModel
+  }
+
+  if (state.defaultModel) {
+    consola.info(
+      `Requests without a known model will use ${state.defaultModel}`,
+    )
+  }
+}
+
+async function setupRateLimitStore(
+  url: string,
+  rateLimit: number | undefined,
+): Promise<void> {
+  if (rateLimit === undefined) {
+    consola.warn("Ignoring Redis rate limit store because no rate limit is set")
+    return
+  }
+
+  try {
+    state.rateLimitStore = await createRedisRateLimiter(url)
+    consola.info("Sharing rate limits across replicas through Redis")
+  } catch (error) {
+    consola.warn(
+      "Distributed rate limiting needs the Bun runtime, using local limits:",
+      (error as Error).message,
+    )
+  }
+}
+
+function serveApp(
+  app: Hono,
+  port: number,
+  hostname: string | undefined,
+  tls: Awaited<ReturnType<typeof tlsServeOptions>> | undefined,
+  handover: { reusePort?: boolean; fd?: number } = {},
+): Server {
+  const server = serve({
+    fetch: ((request) =>
+      app.fetch(request, bunServerEnv(request))) as ServerHandler,
+    port,
+    hostname,
+    reusePort: handover.reusePort,
+    // Listening on an inherited socket instead of binding the port
+    manual: handover.fd !== undefined,
+    tls: tls?.tls,
+    node: tls?.node,
+    bun: { websocket, ...(tls?.bunTls && { tls: tls.bunTls }) },
+  })
+  if (handover.fd !== undefined) {
+    if (!server.node) {
+      throw new Error(
+        "Socket activation needs Node, Bun can't listen on an inherited socket",
+      )
+    }
+    server.node.server?.listen({ fd: handover.fd })
+  }
+  return server
+}
+
+// Rolling restarts send SIGTERM: new requests get a 503 with Retry-After
+// while those in flight finish, then the listeners close. A second signal
+// exits at once.
+function drainOnShutdown(servers: Array<Server>, timeoutSeconds: number): void {
+  const shutdown = async (signal: NodeJS.Signals) => {
+    if (isDraining()) {
+      consola.warn(`${signal} received again, exiting without draining`)
+      process.exit(1)
+    }
+    consola.info(
+      `${signal} received, draining ${inFlightRequests()} in-flight requests for up to ${timeoutSeconds}s`,
+    )
+    if (!(await drain(timeoutSeconds * 1000))) {
+      consola.warn(
+        `${inFlightRequests()} requests still running after ${timeoutSeconds}s, closing anyway`,
+      )
+    }
+    await Promise.all(servers.map((server) => server.close(true)))
+    process.exit(0)
+  }
+  process.on("SIGTERM", (signal) => void shutdown(signal))
+  process.on("SIGINT", (signal) => void shutdown(signal))
 }
 
 // eslint-disable-next-line max-lines-per-function
@@ -37,71 +285,385 @@ export async function runServer(options: RunServerOptions): Promise<void> {
     consola.info(`Using ${options.accountType} plan GitHub account`)
   }
 
+  if (options.upstreams) {
+    configureUpstreams(options.upstreams)
+    startUpstreamHealthChecks()
+    consola.info(`Upstreams, in failover order: ${options.upstreams.join(", ")}`)
+  }
+
   state.manualApprove = options.manual
   state.rateLimitSeconds = options.rateLimit
   state.rateLimitWait = options.rateLimitWait
+  state.tokensPerMinute = options.tokenRateLimit
   state.showToken = options.showToken
+  state.swaggerUi = options.docs
+  state.pathPrefix = options.pathPrefix
+  state.trustedProxies = options.trustedProxies
+  state.modelAliases = options.modelAliases
+  state.synthesizeCacheKey = options.promptCacheKey
+  state.repairToolCalls = options.repairToolCalls
+  state.contextCheck = options.contextCheck
+  state.summarizeModel = options.summarizeModel
+  state.structuredOutputRetry = options.structuredOutputRetry
+  state.sessions = options.sessions
+  state.recordUsage = options.recordUsage
+  state.adminToken = options.adminToken
+  if (options.team) {
+    if (options.grpcPort !== undefined) {
+      throw new Error(
+        "--grpc-port can't be combined with --team, gRPC calls carry no team API key",
+      )
+    }
+    state.teamStore = PATHS.TEAM_TOKENS_PATH
+    startKeyUsageFlush(state.teamStore)
+    const { members } = await readTeamStore(state.teamStore)
+    consola.info(
+      `Team mode with ${Object.keys(members).length} members, requests without a registered API key are rejected`,
+    )
+    if (!options.adminToken) {
+      consola.warn(
+        "The admin endpoints are disabled in team mode without --admin-token",
+      )
+    }
+  }
+  state.modelsTtlSeconds = options.modelsTtl ?? state.modelsTtlSeconds
+  if (options.idempotencyTtl !== undefined) {
+    configureIdempotency({ ttlMs: options.idempotencyTtl * 1000 })
+  }
+
+  if (options.retry429MaxWait) {
+    configureReplayQueue({
+      maxWaitMs: options.retry429MaxWait * 1000,
+      capacity: options.retryQueueSize,
+    })
+    consola.info(
+      `Retrying upstream 429 responses for up to ${options.retry429MaxWait} seconds`,
+    )
+  }
+
+  if (options.upstreamHttp) {
+    const { agent, nativeOnly } = fetchAgentOptions(options.upstreamHttp)
+    await configureUpstreamAgent(agent)
+    if (nativeOnly.length > 0) {
+      consola.warn(
+        `--upstream-http: ${nativeOnly.join(", ")} only tune the native HTTP client, requests to Copilot ignore them`,
+      )
+    }
+    if (features.USE_RUST_HTTP_CLIENT) {
+      consola.debug(
+        "Native HTTP client options:",
+        rustCore.configureHttp(options.upstreamHttp),
+      )
+    }
+  }
+
+  if (options.plugins?.length) {
+    state.plugins = loadPlugins(options.plugins)
+    consola.info(
+      `Plugins: ${state.plugins.map((plugin) => plugin.name).join(", ")}`,
+    )
+  }
+
+  configureSampling({
+    slowMs: options.sampleSlowMs,
+    capacity: options.sampleSize,
+  })
+  configureStreamBuffer({ capacity: options.streamBuffer })
+  // Not available on Windows; Node also uses it to start the inspector
+  if (process.platform !== "win32") process.on("SIGUSR1", dumpSamples)
+
+  if (options.rateLimitRedisUrl) {
+    await setupRateLimitStore(options.rateLimitRedisUrl, options.rateLimit)
+  }
+
+  if (options.accountingStore) {
+    state.accountingStore = await openAccountingStore(options.accountingStore)
+    consola.info(
+      `Keeping usage records and audit entries in ${state.accountingStore.kind}`,
+    )
+  }
+
+  if (options.contentPolicy) {
+    state.contentFilters = await loadContentPolicy(options.contentPolicy)
+    consola.info(
+      `Content policy filters: ${state.contentFilters.map((filter) => filter.name).join(", ")}`,
+    )
+  }
+
+  if (options.modelPolicy) {
+    state.modelPolicy = await loadModelPolicy(options.modelPolicy)
+    consola.info(
+      `Model policy loaded with rules for ${Object.keys(state.modelPolicy.keys ?? {}).length} API keys`,
+    )
+  }
+
+  if (options.chaos) {
+    state.chaos = await loadChaosConfig(options.chaos)
+    consola.warn(
+      `Failure injection is enabled from ${options.chaos}, requests will randomly fail`,
+    )
+  }
+
+  if (options.paramPolicy) {
+    state.paramPolicy = await loadParamPolicy(options.paramPolicy)
+    consola.info(
+      `Parameter policy loaded with ${state.paramPolicy.rules?.length ?? 0} rules`,
+    )
+  }
+
+  if (options.headerPolicy) {
+    state.headerPolicy = await loadHeaderPolicy(options.headerPolicy)
+    consola.info(`Response header policy loaded from ${options.headerPolicy}`)
+  }
+
+  if (options.signingKeys) {
+    state.requestSigning = await loadRequestSigning(options.signingKeys)
+    const clients = Object.keys(state.requestSigning.clients).length
+    consola.info(
+      state.requestSigning.required ?
+        `Only accepting requests signed by ${clients} clients`
+      : `Verifying signed requests from ${clients} clients`,
+    )
+  }
+
+  if (options.sloAlerts) {
+    state.sloAlerts = await loadSloConfig(options.sloAlerts)
+    startSloAlerts()
+    consola.info(
+      `Alerting on ${new URL(state.sloAlerts.webhook).host} when error rate or time to first token exceed their thresholds`,
+    )
+  }
+
+  if (options.experiments) {
+    state.experiments = await loadExperiments(options.experiments)
+    consola.info(
+      `Running ${state.experiments.experiments.length} model experiments`,
+    )
+  }
+
+  if (options.shadow) {
+    state.shadow = await loadShadowConfig(options.shadow)
+    consola.info(
+      `Mirroring ${state.shadow.percent}% of chat requests to ${state.shadow.url}, answers are recorded in ${PATHS.SHADOW_DIR}`,
+    )
+  }
+
+  if (options.mediaProviders) {
+    state.mediaProviders = await loadMediaProviders(options.mediaProviders)
+    consola.info(
+      `Forwarding ${Object.keys(state.mediaProviders).join(", ")} requests to their providers`,
+    )
+  }
+
+  if (options.quotaGuard) {
+    state.quotaGuard = await loadQuotaGuard(options.quotaGuard)
+    consola.info(
+      `Holding back batch traffic once ${state.quotaGuard.threshold * 100}% of premium requests are used`,
+    )
+  }
+
+  if (options.pollUsage !== undefined) {
+    state.usagePollMinutes = options.pollUsage
+    consola.info(
+      `Polling Copilot usage every ${options.pollUsage} minutes, snapshots are stored in ${PATHS.USAGE_SNAPSHOTS_PATH}`,
+    )
+  }
+
+  // Priorities order the concurrency queue, and the quota guard holds back
+  // the batch tier
+  if (options.maxConcurrency !== undefined || state.quotaGuard) {
+    const keys =
+      options.priorityKeys ?
+        await loadPriorityKeys(options.priorityKeys)
+      : undefined
+    configureScheduler({ maxConcurrent: options.maxConcurrency, keys })
+    if (options.maxConcurrency !== undefined) {
+      consola.info(
+        `Limiting to ${options.maxConcurrency} concurrent requests, ${keys?.size ?? 0} keys with an assigned priority`,
+      )
+    }
+  } else if (options.priorityKeys) {
+    consola.warn(
+      "--priority-keys has no effect without --max-concurrency or --quota-guard",
+    )
+  }
 
   await ensurePaths()
   await cacheVSCodeVersion()
 
-  if (options.githubToken) {
+  if (options.auditRetentionDays !== undefined) {
+    state.auditRetentionDays = options.auditRetentionDays
+    state.auditCompress = options.auditCompress
+    const removed = await pruneAuditLog(options.auditRetentionDays)
+    startAuditPruning(options.auditRetentionDays)
+    consola.info(
+      `Audit logging enabled, keeping ${options.auditRetentionDays} days (${removed} old files removed)`,
+    )
+  }
+
+  if (options.tokenStdin) {
+    state.githubToken = await readTokenStdin()
+    consola.info("Using GitHub token from stdin")
+  } else if (options.tokenFile) {
+    state.githubToken = await readTokenFile(options.tokenFile)
+    watchTokenFile(options.tokenFile)
+    consola.info(
+      `Using GitHub token from ${options.tokenFile}, reloaded when it changes`,
+    )
+  } else if (options.githubToken) {
     state.githubToken = options.githubToken
     consola.info("Using provided GitHub token")
   } else {
     await setupGitHubToken()
   }
 
-  await setupCopilotToken()
-  await cacheModels()
-
-  consola.info(
-    `Available models: \n${state.models?.data.map((model) => `- ${model.id}`).join("\n")}`,
-  )
+  try {
+    await checkUpstream(options.startupCheck)
+  } catch (error) {
+    if (!(error instanceof StartupCheckError)) throw error
+    consola.error(`Startup check failed: ${error.message}`)
+    if (error.hint) consola.info(error.hint)
+    consola.info("Use --startup-check warn to start anyway")
+    process.exit(1)
+  }
 
-  const serverUrl = `http://localhost:${options.port}`
+  if (state.models) {
+    consola.info(
+      `Available models: \n${state.models.data.map((model) => `- ${model.id}`).join("\n")}`,
+    )
+  }
 
-  if (options.claudeCode) {
-    invariant(state.models, "Models should be loaded by now")
+  await setupDefaultModel(options)
+  // Polls with the GitHub token, so only once it is known
+  if (state.quotaGuard) startQuotaGuard()
+  if (state.usagePollMinutes !== undefined) {
+    await startUsagePolling(state.usagePollMinutes)
+  }
 
-    const selectedModel = await consola.prompt(
-      "Select a model to use with Claude Code",
-      {
-        type: "select",
-        options: state.models.data.map((model) => model.id),
-      },
+  const tls =
+    options.tlsCert && options.tlsKey ?
+      await tlsServeOptions({
+        cert: options.tlsCert,
+        key: options.tlsKey,
+        clientCa: options.tlsClientCa,
+      })
+    : undefined
+  if (options.tlsClientCa) {
+    state.clientCertificates = {
+      identities:
+        options.tlsClientIdentities ?
+          await loadCertificateIdentities(options.tlsClientIdentities)
+        : {},
+    }
+    consola.info(
+      `Requiring client certificates, ${Object.keys(state.clientCertificates.identities).length} CNs mapped to API keys`,
     )
+    if (typeof Bun !== "undefined") {
+      consola.warn(
+        "Bun does not expose client certificates to requests, so they are verified but not mapped to API keys. Run under Node for --tls-client-identities.",
+      )
+    }
+  }
 
-    const selectedSmallModel = await consola.prompt(
-      "Select a small model to use with Claude Code",
-      {
-        type: "select",
-        options: state.models.data.map((model) => model.id),
-      },
+  const activatedSocket = takeActivatedSocket()
+  // Already bound, by the service manager or by the process being replaced
+  const port =
+    activatedSocket !== undefined || options.reusePort ?
+      options.port
+    : await resolvePort(options.port, options.portRetry, options.host)
+  if (activatedSocket !== undefined) {
+    consola.info(
+      `Listening on the socket passed by the service manager (fd ${activatedSocket})`,
     )
+  }
+  const scheme = tls ? "https" : "http"
+  const serverUrl = `${scheme}://${urlHost(options.host)}:${port}${options.pathPrefix ?? ""}`
 
-    const command = generateEnvScript(
-      {
-        ANTHROPIC_BASE_URL: serverUrl,
-        ANTHROPIC_AUTH_TOKEN: "dummy",
-        ANTHROPIC_MODEL: selectedModel,
-        ANTHROPIC_SMALL_FAST_MODEL: selectedSmallModel,
-      },
-      "claude",
+  consola.box(
+    `🌐 Usage Viewer: https://ericc-ch.github.io/copilot-api?endpoint=${serverUrl}/usage`,
+  )
+
+  const listeners =
+    options.listeners ? await loadListeners(options.listeners) : []
+  const servers = [
+    serveApp(mountServer(options.pathPrefix), port, options.host, tls, {
+      reusePort: options.reusePort,
+      fd: activatedSocket,
+    }),
+  ]
+
+  const listenerUrls: Array<string> = []
+  for (const listener of listeners) {
+    const listenerTls =
+      listener.tlsCert && listener.tlsKey ?
+        await tlsServeOptions({
+          cert: listener.tlsCert,
+          key: listener.tlsKey,
+          clientCa: listener.tlsClientCa,
+        })
+      : undefined
+    // CNs are mapped with --tls-client-identities, shared by every listener
+    if (listener.tlsClientCa) state.clientCertificates ??= { identities: {} }
+    servers.push(
+      serveApp(
+        listenerApp(listener, server),
+        listener.port,
+        listener.host,
+        listenerTls,
+        { reusePort: options.reusePort },
+      ),
     )
+    const url = `${listenerTls ? "https" : "http"}://${urlHost(listener.host)}:${listener.port}${listener.pathPrefix ?? ""}`
+    listenerUrls.push(url)
+    consola.info(
+      `Also listening on ${url}${listener.apiKeys ? ", API key required" : ""}${listener.admin === false ? ", without admin endpoints" : ""}`,
+    )
+  }
+
+  drainOnShutdown(servers, options.drainTimeout)
 
-    clipboard.writeSync(command)
-    consola.success("Copied Claude Code command to clipboard!")
+  // Machine-readable line for wrapper scripts, e.g. when using --port 0
+  process.stdout.write(`COPILOT_API_URL=${serverUrl}\n`)
+
+  let grpcPort: number | undefined
+  if (options.grpcPort !== undefined) {
+    try {
+      grpcPort = await startGrpcServer(options.grpcPort, options.host)
+      consola.info(`gRPC server listening on port ${grpcPort}`)
+    } catch (error) {
+      consola.warn(
+        "gRPC server not started (install @grpc/grpc-js and @grpc/proto-loader to enable it):",
+        (error as Error).message,
+      )
+    }
   }
 
-  consola.box(
-    `🌐 Usage Viewer: https://ericc-ch.github.io/copilot-api?endpoint=${serverUrl}/usage`,
+  printStartupBanner(
+    {
+      url: serverUrl,
+      host: options.host,
+      listeners: listenerUrls,
+      grpcPort,
+      rateLimitRedisUrl: options.rateLimitRedisUrl,
+      retry429MaxWait: options.retry429MaxWait,
+      maxConcurrency: options.maxConcurrency,
+      contentPolicy: options.contentPolicy,
+      modelPolicy: options.modelPolicy,
+      paramPolicy: options.paramPolicy,
+      headerPolicy: options.headerPolicy,
+      priorityKeys: options.priorityKeys,
+      claudeCode: options.claudeCode,
+    },
+    options.logFormat,
   )
 
-  serve({
-    fetch: server.fetch as ServerHandler,
-    port: options.port,
-  })
+  if (options.claudeCode) {
+    await setupClaudeCode(serverUrl, {
+      model: options.claudeModel,
+      smallModel: options.claudeSmallModel,
+      launch: options.claudeLaunch,
+    })
+  }
 }
 
 export const start = defineCommand({
@@ -113,8 +675,38 @@ export const start = defineCommand({
     port: {
       alias: "p",
       type: "string",
-      default: "4141",
-      description: "Port to listen on",
+      description:
+        "Port to listen on, 0 picks a free port (default: 4141)",
+    },
+    "port-retry": {
+      type: "string",
+      description:
+        "Try up to N successive ports when the requested port is in use",
+    },
+    "grpc-port": {
+      type: "string",
+      description: "Also serve the gRPC API on this port",
+    },
+    "trusted-proxies": {
+      type: "string",
+      description:
+        "Comma-separated proxy addresses or CIDR ranges whose X-Forwarded-For and Forwarded headers are trusted for the client IP",
+    },
+    "reuse-port": {
+      type: "boolean",
+      default: false,
+      description:
+        "Bind with SO_REUSEPORT, so a new gateway process can take over the port while the old one drains",
+    },
+    "path-prefix": {
+      type: "string",
+      description:
+        "Serve every endpoint under this path, e.g. /copilot for a reverse proxy that routes by path",
+    },
+    upstreams: {
+      type: "string",
+      description:
+        "Comma-separated Copilot base URLs, e.g. regional endpoints; requests fail over to the next when one is unreachable",
     },
     verbose: {
       alias: "v",
@@ -125,8 +717,8 @@ export const start = defineCommand({
     "account-type": {
       alias: "a",
       type: "string",
-      default: "individual",
-      description: "Account type to use (individual, business, enterprise)",
+      description:
+        "Account type to use (individual, business, enterprise) (default: individual)",
     },
     manual: {
       type: "boolean",
@@ -145,12 +737,257 @@ export const start = defineCommand({
       description:
         "Wait instead of error when rate limit is hit. Has no effect if rate limit is not set",
     },
+    "token-rate-limit": {
+      type: "string",
+      description:
+        "Prompt tokens each client (by API key) may send per minute. Honors --wait",
+    },
+    "retry-429": {
+      type: "string",
+      description:
+        "Hold requests rejected by Copilot with 429 and retry them after the reset, for up to this many seconds",
+    },
+    "retry-queue-size": {
+      type: "string",
+      description:
+        "Maximum number of requests waiting to be retried after a 429 (default: 100)",
+    },
+    "idempotency-ttl": {
+      type: "string",
+      description:
+        "Seconds to keep responses for retries with the same Idempotency-Key, 0 disables (default: 600)",
+    },
+    "rate-limit-redis": {
+      type: "string",
+      description:
+        "Redis URL for sharing the rate limit across replicas (requires Bun)",
+    },
+    "accounting-store": {
+      type: "string",
+      description:
+        "sqlite:<path> or postgres:// URL to keep usage records and audit entries in instead of files (requires Bun)",
+    },
+    audit: {
+      type: "boolean",
+      default: false,
+      description:
+        "Log prompts and responses, with secrets redacted, for compliance",
+    },
+    "audit-retention": {
+      type: "string",
+      description: "Days to keep audit logs, implies --audit (default: 30)",
+    },
+    "audit-compress": {
+      type: "boolean",
+      default: false,
+      description:
+        "Brotli-compress audit files, with an index to look up single requests, implies --audit",
+    },
+    sessions: {
+      type: "boolean",
+      default: false,
+      description:
+        "Persist conversations sent with an x-session-id header for /sessions",
+    },
+    "record-usage": {
+      type: "boolean",
+      default: false,
+      description:
+        "Record token counts per API key for the `report` command",
+    },
+    team: {
+      type: "boolean",
+      default: false,
+      description:
+        "Send each API key's requests with its own GitHub account, added with `team add`",
+    },
+    "admin-token": {
+      type: "string",
+      description:
+        "Bearer token required by the /admin endpoints, which are disabled in team mode without one",
+    },
+    "content-policy": {
+      type: "string",
+      description: "JSON file configuring request/response content filters",
+    },
+    "prompt-cache-key": {
+      type: "boolean",
+      default: false,
+      description:
+        "Derive prompt_cache_key from the system prompt and tools when the client sends none",
+    },
+    "repair-tool-calls": {
+      type: "boolean",
+      default: false,
+      description:
+        "Complete truncated or invalid JSON in tool call arguments before returning them",
+    },
+    "context-check": {
+      type: "boolean",
+      default: false,
+      description:
+        "Reject requests whose prompt and max_tokens exceed the model's context window before forwarding them",
+    },
+    "summarize-model": {
+      type: "string",
+      description:
+        "Cheaper model that summarizes the oldest turns of prompts over the context window instead of sending them",
+    },
+    "structured-output-retry": {
+      type: "boolean",
+      default: false,
+      description:
+        "Retry once when a response does not match the requested json_schema",
+    },
+    "model-policy": {
+      type: "string",
+      description: "JSON file restricting which models each API key may use",
+    },
+    "param-policy": {
+      type: "string",
+      description:
+        "JSON file with per-model rules stripping, clamping or overriding request parameters",
+    },
+    "header-policy": {
+      type: "string",
+      description:
+        "JSON file of response headers to strip, rename or add, on top of the built-in hop-by-hop and Copilot headers removed",
+    },
+    "slo-alerts": {
+      type: "string",
+      description:
+        "JSON file with a webhook and error rate or time to first token thresholds to alert on",
+    },
+    "quota-guard": {
+      type: "string",
+      description:
+        "JSON file with the share of premium requests at which batch traffic is rejected or queued",
+    },
+    experiments: {
+      type: "string",
+      description:
+        "JSON file routing a share of the requests for a model to an alternate model",
+    },
+    shadow: {
+      type: "string",
+      description:
+        "JSON file with a second upstream that a share of chat requests is mirrored to for comparison",
+    },
+    "media-providers": {
+      type: "string",
+      description:
+        "JSON file of OpenAI-compatible providers that audio and image requests are forwarded to",
+    },
+    "tls-cert": {
+      type: "string",
+      description: "PEM certificate file; serve HTTPS instead of HTTP",
+    },
+    "tls-key": {
+      type: "string",
+      description: "PEM private key file for --tls-cert",
+    },
+    "tls-client-ca": {
+      type: "string",
+      description:
+        "PEM CA file; require client certificates issued by it (mutual TLS)",
+    },
+    "tls-client-identities": {
+      type: "string",
+      description:
+        "JSON file mapping client certificate CNs to the API keys their requests act as",
+    },
+    listeners: {
+      type: "string",
+      description:
+        "JSON file of additional ports or interfaces to listen on, each with its own TLS certificate, API keys and admin endpoints",
+    },
+    "signing-keys": {
+      type: "string",
+      description:
+        "JSON file of clients that sign requests with HMAC instead of sending a bearer key",
+    },
+    "poll-usage": {
+      type: "string",
+      description:
+        "Minutes between Copilot usage snapshots used to project end-of-cycle usage in /stats",
+    },
+    "max-concurrency": {
+      type: "string",
+      description:
+        "Maximum concurrent upstream requests; further requests queue by priority",
+    },
+    "priority-keys": {
+      type: "string",
+      description:
+        "JSON file assigning API keys to the interactive or batch priority tier",
+    },
+    "sample-slow-ms": {
+      type: "string",
+      description:
+        "Keep requests slower than this in the /admin/samples buffer (default: 10000)",
+    },
+    "sample-size": {
+      type: "string",
+      description:
+        "Number of slow or failed requests kept for /admin/samples (default: 50)",
+    },
+    "stream-buffer": {
+      type: "string",
+      description:
+        "Events kept per stream for /admin/streams observers and Last-Event-ID resumes (default: 1000)",
+    },
+    "drain-timeout": {
+      type: "string",
+      description:
+        "Seconds to let in-flight requests finish on shutdown while new ones get a 503 (default: 30)",
+    },
+    "upstream-http": {
+      type: "string",
+      description:
+        "Connection pool tuning for requests to Copilot as name=value pairs, e.g. poolIdleTimeoutMs=300000,tcpKeepaliveMs=30000 (Node only)",
+    },
+    plugins: {
+      type: "string",
+      description:
+        "Comma-separated WASM modules that transform JSON requests and responses, run in order",
+    },
     "github-token": {
       alias: "g",
       type: "string",
       description:
         "Provide GitHub token directly (must be generated using the `auth` subcommand)",
     },
+    "token-file": {
+      type: "string",
+      description:
+        "Read the GitHub token from this file, reloading it when the file changes",
+    },
+    "token-stdin": {
+      type: "boolean",
+      default: false,
+      description: "Read the GitHub token from stdin",
+    },
+    "default-model": {
+      alias: "m",
+      type: "string",
+      description:
+        "Model used for requests that omit `model` or name an unknown model",
+    },
+    "select-default-model": {
+      type: "boolean",
+      default: false,
+      description: "Pick the default model interactively from the model list",
+    },
+    "model-alias": {
+      type: "string",
+      description:
+        "Comma-separated model aliases, e.g. fast=gpt-4o-mini,smart=claude-sonnet-4",
+    },
+    "models-ttl": {
+      type: "string",
+      description:
+        "Seconds before the cached model list is refreshed in the background (default: 300)",
+    },
     "claude-code": {
       alias: "c",
       type: "boolean",
@@ -158,28 +995,344 @@ export const start = defineCommand({
       description:
         "Generate a command to launch Claude Code with Copilot API config",
     },
+    "claude-model": {
+      type: "string",
+      description:
+        "Model for Claude Code (skips the prompt, implies --claude-code)",
+    },
+    "claude-small-model": {
+      type: "string",
+      description:
+        "Small/fast model for Claude Code (skips the prompt, implies --claude-code)",
+    },
+    "claude-launch": {
+      type: "boolean",
+      default: false,
+      description:
+        "Launch `claude` with the generated environment once the server is up (implies --claude-code)",
+    },
     "show-token": {
       type: "boolean",
       default: false,
       description: "Show GitHub and Copilot tokens on fetch and refresh",
     },
+    docs: {
+      type: "boolean",
+      default: false,
+      description: "Serve Swagger UI for /openapi.json at /docs",
+    },
+    "startup-check": {
+      type: "string",
+      description:
+        "strict exits when the Copilot token exchange or models fetch fails at startup, warn starts anyway (default: strict)",
+    },
   },
   run({ args }) {
+    // Command line flags take precedence over COPILOT_GATEWAY_* variables
+    const env = loadEnvConfig()
+    applyLogFormat(env.logFormat)
+
+    const tokenSources = [
+      args["github-token"],
+      args["token-file"],
+      args["token-stdin"] || undefined,
+    ].filter((source) => source !== undefined)
+    if (tokenSources.length > 1) {
+      throw new TypeError(
+        "--github-token, --token-file and --token-stdin are mutually exclusive",
+      )
+    }
+
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    const startupCheck = args["startup-check"] ?? env.startupCheck ?? "strict"
+    if (!STARTUP_CHECKS.includes(startupCheck as StartupCheck)) {
+      throw new TypeError(
+        `Invalid --startup-check: ${startupCheck}, use strict or warn`,
+      )
+    }
+
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    const tlsCert = args["tls-cert"] ?? env.tlsCert
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    const tlsKey = args["tls-key"] ?? env.tlsKey
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    const tlsClientCa = args["tls-client-ca"] ?? env.tlsClientCa
+    const tlsClientIdentities =
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      args["tls-client-identities"] ?? env.tlsClientIdentities
+    if (Boolean(tlsCert) !== Boolean(tlsKey)) {
+      throw new TypeError("--tls-cert and --tls-key must be given together")
+    }
+    if ((tlsClientCa || tlsClientIdentities) && !tlsCert) {
+      throw new TypeError(
+        "--tls-client-ca and --tls-client-identities need --tls-cert and --tls-key",
+      )
+    }
+    if (tlsClientIdentities && !tlsClientCa) {
+      throw new TypeError("--tls-client-identities needs --tls-client-ca")
+    }
+    // Requests would silently act as whatever key their headers name
+    if (tlsClientIdentities && typeof Bun !== "undefined") {
+      throw new TypeError(
+        "--tls-client-identities needs Node, Bun does not expose client certificates to requests",
+      )
+    }
+
     const rateLimitRaw = args["rate-limit"]
     const rateLimit =
       // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
-      rateLimitRaw === undefined ? undefined : Number.parseInt(rateLimitRaw, 10)
+      rateLimitRaw === undefined ? env.rateLimit : (
+        parseIntegerOption(
+          "--rate-limit",
+          rateLimitRaw,
+          1,
+          Number.MAX_SAFE_INTEGER,
+        )
+      )
+    const portRaw = args.port
+    const port =
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      portRaw === undefined ?
+        (env.port ?? 4141)
+      : parseIntegerOption("--port", portRaw, 0, 65535)
+
+    const portRetryRaw = args["port-retry"]
+    const portRetry =
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      portRetryRaw === undefined ?
+        (env.portRetry ?? 0)
+      : parseIntegerOption("--port-retry", portRetryRaw, 0, 1000)
+
+    const auditRetentionRaw = args["audit-retention"]
+    const auditEnabled =
+      args.audit
+      || args["audit-compress"]
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      || auditRetentionRaw !== undefined
+      || Boolean(env.audit)
+      || Boolean(env.auditCompress)
+      || env.auditRetentionDays !== undefined
+    const auditRetentionDays =
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      auditRetentionRaw === undefined ?
+        (env.auditRetentionDays ?? 30)
+      : parseIntegerOption("--audit-retention", auditRetentionRaw, 1, 3650)
+
+    const tokenRateLimitRaw = args["token-rate-limit"]
+    const tokenRateLimit =
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      tokenRateLimitRaw === undefined ? env.tokenRateLimit : (
+        parseIntegerOption(
+          "--token-rate-limit",
+          tokenRateLimitRaw,
+          1,
+          Number.MAX_SAFE_INTEGER,
+        )
+      )
+
+    const maxConcurrencyRaw = args["max-concurrency"]
+    const pollUsageRaw = args["poll-usage"]
+    const retry429Raw = args["retry-429"]
+    const retryQueueSizeRaw = args["retry-queue-size"]
+    const idempotencyTtlRaw = args["idempotency-ttl"]
+
+    const grpcPortRaw = args["grpc-port"]
+    const grpcPort =
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      grpcPortRaw === undefined ? env.grpcPort : (
+        parseIntegerOption("--grpc-port", grpcPortRaw, 1, 65_535)
+      )
+
+    const modelAliasRaw = args["model-alias"]
+    const modelAliases =
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      modelAliasRaw === undefined ? env.modelAliases : parseMapping(modelAliasRaw)
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    if (modelAliasRaw !== undefined && !modelAliases) {
+      throw new Error(
+        `--model-alias: expected comma-separated alias=model pairs, got "${modelAliasRaw}"`,
+      )
+    }
+    const modelsTtlRaw = args["models-ttl"]
+
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    const trustedProxiesRaw = args["trusted-proxies"] ?? env.trustedProxies
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    const upstreamsRaw = args.upstreams ?? env.upstreams
+
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    const upstreamHttpRaw = args["upstream-http"] ?? env.upstreamHttp
+
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+    const pluginsRaw = args.plugins ?? env.plugins
+
+    const sampleSlowMsRaw = args["sample-slow-ms"]
+    const sampleSizeRaw = args["sample-size"]
+    const streamBufferRaw = args["stream-buffer"]
+    const drainTimeoutRaw = args["drain-timeout"]
 
     return runServer({
-      port: Number.parseInt(args.port, 10),
-      verbose: args.verbose,
-      accountType: args["account-type"],
+      port,
+      portRetry,
+      grpcPort,
+      host: env.host,
+      reusePort: args["reuse-port"] || Boolean(env.reusePort),
+      pathPrefix: normalizePathPrefix(
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        args["path-prefix"] ?? env.pathPrefix,
+      ),
+      trustedProxies:
+        trustedProxiesRaw ? parseTrustedProxies(trustedProxiesRaw) : undefined,
+      upstreams: upstreamsRaw ? parseUpstreams(upstreamsRaw) : undefined,
+      verbose: args.verbose || Boolean(env.verbose),
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      accountType: args["account-type"] ?? env.accountType ?? "individual",
       manual: args.manual,
       rateLimit,
-      rateLimitWait: Boolean(args.wait),
-      githubToken: args["github-token"],
-      claudeCode: args["claude-code"],
+      rateLimitWait: Boolean(args.wait) || Boolean(env.rateLimitWait),
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      rateLimitRedisUrl: args["rate-limit-redis"] ?? env.rateLimitRedisUrl,
+      accountingStore:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        args["accounting-store"] === undefined ? env.accountingStore : (
+          resolveConfigValue(args["accounting-store"])
+        ),
+      tokenRateLimit,
+      retry429MaxWait:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        retry429Raw === undefined ? env.retry429MaxWait : (
+          parseIntegerOption("--retry-429", retry429Raw, 0, 3600)
+        ),
+      retryQueueSize:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        retryQueueSizeRaw === undefined ? env.retryQueueSize : (
+          parseIntegerOption("--retry-queue-size", retryQueueSizeRaw, 1, 10_000)
+        ),
+      idempotencyTtl:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        idempotencyTtlRaw === undefined ? env.idempotencyTtl : (
+          parseIntegerOption("--idempotency-ttl", idempotencyTtlRaw, 0, 86_400)
+        ),
+      auditRetentionDays: auditEnabled ? auditRetentionDays : undefined,
+      auditCompress: args["audit-compress"] || Boolean(env.auditCompress),
+      sessions: args.sessions || Boolean(env.sessions),
+      recordUsage: args["record-usage"] || Boolean(env.recordUsage),
+      team: args.team || Boolean(env.team),
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      adminToken:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        args["admin-token"] === undefined ? env.adminToken : (
+          resolveConfigValue(args["admin-token"])
+        ),
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      contentPolicy: args["content-policy"] ?? env.contentPolicy,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      modelPolicy: args["model-policy"] ?? env.modelPolicy,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      paramPolicy: args["param-policy"] ?? env.paramPolicy,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      headerPolicy: args["header-policy"] ?? env.headerPolicy,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      signingKeys: args["signing-keys"] ?? env.signingKeys,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      sloAlerts: args["slo-alerts"] ?? env.sloAlerts,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      shadow: args.shadow ?? env.shadow,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      mediaProviders: args["media-providers"] ?? env.mediaProviders,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      experiments: args.experiments ?? env.experiments,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      quotaGuard: args["quota-guard"] ?? env.quotaGuard,
+      pollUsage:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        pollUsageRaw === undefined ? env.pollUsage : (
+          parseIntegerOption("--poll-usage", pollUsageRaw, 1, 1440)
+        ),
+      tlsCert,
+      tlsKey,
+      tlsClientCa,
+      tlsClientIdentities,
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      listeners: args.listeners ?? env.listeners,
+      promptCacheKey:
+        args["prompt-cache-key"] || Boolean(env.promptCacheKey),
+      repairToolCalls:
+        args["repair-tool-calls"] || Boolean(env.repairToolCalls),
+      contextCheck: args["context-check"] || Boolean(env.contextCheck),
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      summarizeModel: args["summarize-model"] ?? env.summarizeModel,
+      structuredOutputRetry:
+        args["structured-output-retry"] || Boolean(env.structuredOutputRetry),
+      maxConcurrency:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        maxConcurrencyRaw === undefined ? env.maxConcurrency : (
+          parseIntegerOption("--max-concurrency", maxConcurrencyRaw, 1, 10_000)
+        ),
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      priorityKeys: args["priority-keys"] ?? env.priorityKeys,
+      sampleSlowMs:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        sampleSlowMsRaw === undefined ? env.sampleSlowMs : (
+          parseIntegerOption(
+            "--sample-slow-ms",
+            sampleSlowMsRaw,
+            1,
+            Number.MAX_SAFE_INTEGER,
+          )
+        ),
+      sampleSize:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        sampleSizeRaw === undefined ? env.sampleSize : (
+          parseIntegerOption("--sample-size", sampleSizeRaw, 0, 10_000)
+        ),
+      streamBuffer:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        streamBufferRaw === undefined ? env.streamBuffer : (
+          parseIntegerOption("--stream-buffer", streamBufferRaw, 1, 100_000)
+        ),
+      drainTimeout:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        drainTimeoutRaw === undefined ? (env.drainTimeout ?? 30) : (
+          parseIntegerOption("--drain-timeout", drainTimeoutRaw, 0, 3600)
+        ),
+      upstreamHttp:
+        upstreamHttpRaw ? parseHttpOptions(upstreamHttpRaw) : undefined,
+      plugins: pluginsRaw
+        ?.split(",")
+        .map((entry) => entry.trim())
+        .filter(Boolean),
+      chaos: env.chaos,
+      githubToken:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        args["github-token"] === undefined ? env.githubToken : (
+          resolveConfigValue(args["github-token"])
+        ),
+      tokenFile:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        args["token-file"] ?? (args["github-token"] ? undefined : env.tokenFile),
+      tokenStdin: args["token-stdin"],
+      // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+      defaultModel: args["default-model"] ?? env.defaultModel,
+      selectDefaultModel: args["select-default-model"],
+      modelAliases,
+      modelsTtl:
+        // eslint-disable-next-line @typescript-eslint/no-unnecessary-condition
+        modelsTtlRaw === undefined ? env.modelsTtl : (
+          parseIntegerOption("--models-ttl", modelsTtlRaw, 0, 86_400)
+        ),
+      claudeCode:
+        args["claude-code"]
+        || args["claude-launch"]
+        || Boolean(args["claude-model"])
+        || Boolean(args["claude-small-model"]),
+      claudeModel: args["claude-model"],
+      claudeSmallModel: args["claude-small-model"],
+      claudeLaunch: args["claude-launch"],
       showToken: args["show-token"],
+      docs: args.docs,
+      logFormat: env.logFormat,
+      startupCheck: startupCheck as StartupCheck,
     })
   },
 })
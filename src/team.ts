@@ -0,0 +1,255 @@
+#!/usr/bin/env node
+
+import { defineCommand } from "citty"
+import consola from "consola"
+
+import { ensurePaths } from "./lib/paths"
+import { state } from "./lib/state"
+import {
+  createKey,
+  describeKey,
+  readTeamStore,
+  recordKeyEvent,
+  revokeKeys,
+  rotateKey,
+  updateTeamStore,
+} from "./lib/team"
+import { getDeviceCode } from "./services/github/get-device-code"
+import { getGitHubUser } from "./services/github/get-user"
+import { pollAccessToken } from "./services/github/poll-access-token"
+
+const DAY_MS = 24 * 60 * 60 * 1000
+const HOUR_MS = 60 * 60 * 1000
+
+interface RunTeamAddOptions {
+  apiKey?: string
+  // Never expires when not set
+  expiresInDays?: number
+}
+
+const expiryIn = (days: number | undefined) =>
+  days === undefined ? undefined : (
+    new Date(Date.now() + days * DAY_MS).toISOString()
+  )
+
+function parseDuration(raw: string | undefined, name: string) {
+  if (raw === undefined) return undefined
+  const value = Number(raw)
+  if (!Number.isFinite(value) || value <= 0) {
+    throw new Error(`--${name} must be a positive number`)
+  }
+  return value
+}
+
+/**
+ * Logs a member in with the device flow and binds their GitHub token to an
+ * API key, generated unless one is given.
+ */
+export async function runTeamAdd(options: RunTeamAddOptions): Promise<void> {
+  await ensurePaths()
+
+  const device = await getDeviceCode()
+  consola.info(
+    `The new member should enter the code "${device.user_code}" in ${device.verification_uri}`,
+  )
+  const githubToken = await pollAccessToken(device)
+  state.githubToken = githubToken
+  const { login } = await getGitHubUser()
+
+  const { apiKey } = await updateTeamStore((store) =>
+    createKey(
+      store,
+      {
+        login,
+        githubToken,
+        apiKey: options.apiKey,
+        expiresAt: expiryIn(options.expiresInDays),
+      },
+      "cli",
+    ),
+  )
+
+  consola.success(`Added ${login} to the team`)
+  if (!options.apiKey) {
+    consola.info("Their API key, which is only shown now:")
+    process.stdout.write(`${apiKey}\n`)
+  }
+}
+
+/**
+ * Removes every key of a member, by GitHub login or key id. Unlike revoking,
+ * this forgets the keys, leaving only their audit events.
+ */
+export async function runTeamRemove(member: string): Promise<number> {
+  return updateTeamStore((store) => {
+    const ids = Object.entries(store.members)
+      .filter(([id, entry]) => id === member || entry.login === member)
+      .map(([id]) => id)
+    if (ids.length === 0) throw new Error(`No team member ${member}`)
+
+    for (const id of ids) {
+      const { login } = store.members[id]
+      delete store.members[id]
+      recordKeyEvent(store, { action: "removed", id, login, via: "cli" })
+    }
+    return ids.length
+  })
+}
+
+const add = defineCommand({
+  meta: {
+    name: "add",
+    description: "Log a team member in and give them an API key",
+  },
+  args: {
+    "api-key": {
+      type: "string",
+      description: "Bind this existing API key instead of generating one",
+    },
+    "expires-in": {
+      type: "string",
+      description: "Days until the API key stops working (default: never)",
+    },
+  },
+  run({ args }) {
+    return runTeamAdd({
+      apiKey: args["api-key"],
+      expiresInDays: parseDuration(args["expires-in"], "expires-in"),
+    })
+  },
+})
+
+const list = defineCommand({
+  meta: {
+    name: "list",
+    description:
+      "List API keys with their member, status, expiry and last use",
+  },
+  async run() {
+    const { members } = await readTeamStore()
+    for (const [id, member] of Object.entries(members)) {
+      const key = describeKey(id, member)
+      const columns = [
+        id,
+        key.login,
+        key.status,
+        `added ${key.addedAt}`,
+        key.expiresAt && `expires ${key.expiresAt}`,
+        `last used ${key.lastUsedAt ?? "never"}`,
+        key.rotatedTo && `rotated to ${key.rotatedTo}`,
+      ]
+      process.stdout.write(`${columns.filter(Boolean).join("  ")}\n`)
+    }
+  },
+})
+
+const rotate = defineCommand({
+  meta: {
+    name: "rotate",
+    description:
+      "Issue a new API key for the same member; the old one works for a grace period",
+  },
+  args: {
+    id: {
+      type: "positional",
+      required: true,
+      description: "Key id from `team list`",
+    },
+    grace: {
+      type: "string",
+      description: "Hours the old key keeps working (default: 24)",
+    },
+    "expires-in": {
+      type: "string",
+      description: "Days until the new API key stops working (default: never)",
+    },
+  },
+  async run({ args }) {
+    const graceHours = parseDuration(args.grace, "grace") ?? 24
+    const { apiKey, id } = await updateTeamStore((store) =>
+      rotateKey(
+        store,
+        args.id,
+        {
+          graceMs: graceHours * HOUR_MS,
+          expiresAt: expiryIn(
+            parseDuration(args["expires-in"], "expires-in"),
+          ),
+        },
+        "cli",
+      ),
+    )
+    consola.success(
+      `Rotated ${args.id} to ${id}, the old key works for ${graceHours} more hours`,
+    )
+    consola.info("The new API key, which is only shown now:")
+    process.stdout.write(`${apiKey}\n`)
+  },
+})
+
+const revoke = defineCommand({
+  meta: {
+    name: "revoke",
+    description:
+      "Stop API keys from working, by GitHub login or key id, keeping them for the audit trail",
+  },
+  args: {
+    member: {
+      type: "positional",
+      required: true,
+      description: "GitHub login or key id from `team list`",
+    },
+  },
+  async run({ args }) {
+    const ids = await updateTeamStore((store) => {
+      const revoked = revokeKeys(store, args.member, "cli")
+      if (revoked.length === 0) {
+        throw new Error(`No active API key of ${args.member}`)
+      }
+      return revoked
+    })
+    consola.success(`Revoked ${ids.length} API keys of ${args.member}`)
+  },
+})
+
+const audit = defineCommand({
+  meta: {
+    name: "audit",
+    description: "Show when API keys were created, rotated, revoked or removed",
+  },
+  async run() {
+    const { audit: events = [] } = await readTeamStore()
+    for (const event of events) {
+      process.stdout.write(
+        `${event.time}  ${event.action}  ${event.id}  ${event.login}  via ${event.via}\n`,
+      )
+    }
+  },
+})
+
+const remove = defineCommand({
+  meta: {
+    name: "remove",
+    description: "Remove a team member by GitHub login or key id",
+  },
+  args: {
+    member: {
+      type: "positional",
+      required: true,
+      description: "GitHub login or key id from `team list`",
+    },
+  },
+  async run({ args }) {
+    const removed = await runTeamRemove(args.member)
+    consola.success(`Removed ${removed} API keys of ${args.member}`)
+  },
+})
+
+export const team = defineCommand({
+  meta: {
+    name: "team",
+    description:
+      "Manage the members of `start --team`, each using their own GitHub account",
+  },
+  subCommands: { add, list, rotate, revoke, remove, audit },
+})
@@ -0,0 +1,136 @@
+#!/usr/bin/env node
+
+import { defineCommand } from "citty"
+import consola from "consola"
+import { countTokens } from "gpt-tokenizer/model/gpt-4o"
+import fs from "node:fs/promises"
+
+import type { Message } from "./services/copilot/create-chat-completions"
+
+import {
+  getTokenCount,
+  simplifyMessages,
+  type CountTokens,
+} from "./lib/tokenizer"
+import { type AnthropicMessagesPayload } from "./routes/messages/anthropic-types"
+import { translateToOpenAI } from "./routes/messages/non-stream-translation"
+
+interface RunTokenizeOptions {
+  file?: string
+  model: string
+  anthropic: boolean
+  json: boolean
+}
+
+async function readInput(file?: string): Promise<string> {
+  if (file && file !== "-") return fs.readFile(file, "utf8")
+
+  const chunks: Array<Buffer> = []
+  for await (const chunk of process.stdin) chunks.push(chunk as Buffer)
+  return Buffer.concat(chunks).toString("utf8")
+}
+
+function parseMessages(raw: string, anthropic: boolean): Array<Message> {
+  const parsed = JSON.parse(raw) as
+    | Array<Message>
+    | { messages: Array<Message> }
+    | AnthropicMessagesPayload
+
+  if (anthropic) {
+    return translateToOpenAI(parsed as AnthropicMessagesPayload).messages
+  }
+
+  return Array.isArray(parsed) ? parsed : (parsed.messages as Array<Message>)
+}
+
+async function loadTokenizer(model: string): Promise<CountTokens> {
+  if (model === "gpt-4o") return countTokens
+
+  try {
+    const tokenizer = (await import(`gpt-tokenizer/model/${model}`)) as {
+      countTokens: CountTokens
+    }
+    return tokenizer.countTokens
+  } catch {
+    consola.warn(`No tokenizer available for ${model}, using gpt-4o instead`)
+    return countTokens
+  }
+}
+
+export async function runTokenize(options: RunTokenizeOptions): Promise<void> {
+  const messages = parseMessages(
+    await readInput(options.file),
+    options.anthropic,
+  )
+  const countChatTokens = await loadTokenizer(options.model)
+
+  const perMessage = simplifyMessages(messages).map((message, index) => ({
+    index,
+    role: message.role,
+    // @ts-expect-error TS can't infer from arr.map()
+    tokens: countChatTokens([message]),
+  }))
+  const total = getTokenCount(messages, countChatTokens)
+
+  if (options.json) {
+    process.stdout.write(
+      `${JSON.stringify({ model: options.model, messages: perMessage, total }, null, 2)}\n`,
+    )
+    return
+  }
+
+  const rows = perMessage.map(
+    (entry) =>
+      `${String(entry.index).padStart(5)}  ${entry.role.padEnd(9)}  ${entry.tokens}`,
+  )
+  process.stdout.write(
+    [
+      `INDEX  ROLE       TOKENS`,
+      ...rows,
+      "",
+      `Input tokens:  ${total.input}`,
+      `Output tokens: ${total.output}`,
+      `Total tokens:  ${total.input + total.output}`,
+    ].join("\n") + "\n",
+  )
+}
+
+export const tokenize = defineCommand({
+  meta: {
+    name: "tokenize",
+    description:
+      "Count tokens per message for a messages JSON file (or stdin) without contacting Copilot",
+  },
+  args: {
+    file: {
+      type: "positional",
+      required: false,
+      description:
+        "Path to a JSON file with a messages array or request payload (reads stdin if omitted or -)",
+    },
+    model: {
+      alias: "m",
+      type: "string",
+      default: "gpt-4o",
+      description: "Model whose tokenizer should be used",
+    },
+    anthropic: {
+      type: "boolean",
+      default: false,
+      description: "Treat the input as an Anthropic Messages API payload",
+    },
+    json: {
+      type: "boolean",
+      default: false,
+      description: "Print the counts as JSON",
+    },
+  },
+  run({ args }) {
+    return runTokenize({
+      file: args.file,
+      model: args.model,
+      anthropic: args.anthropic,
+      json: args.json,
+    })
+  },
+})
@@ -0,0 +1,99 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { state } from '../../src/lib/state'
+import {
+  chatCompletion,
+  fakeUpstream,
+  streamScript,
+  usage,
+  type FakeUpstream,
+} from '../testkit/upstream'
+
+// End-to-end overhead of the gateway with an upstream that answers at once,
+// so the timings are ours alone. Budgets can be tightened per machine with
+// BENCH_TTFB_BUDGET_MS and BENCH_LATENCY_BUDGET_MS.
+const ITERATIONS = 200
+const WARMUP = 20
+const TTFB_BUDGET_MS = Number(process.env.BENCH_TTFB_BUDGET_MS ?? 25)
+const LATENCY_BUDGET_MS = Number(process.env.BENCH_LATENCY_BUDGET_MS ?? 25)
+
+const post = (stream: boolean) =>
+  server.request('/v1/chat/completions', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({
+      model: 'gpt-4o',
+      stream,
+      messages: [{ role: 'user', content: 'Summarize the gateway in one line.' }],
+    }),
+  })
+
+const percentile = (timings: Array<number>, p: number) => {
+  const sorted = [...timings].sort((a, b) => a - b)
+  return sorted[Math.min(sorted.length - 1, Math.floor((sorted.length * p) / 100))]
+}
+
+const report = (name: string, timings: Array<number>) => {
+  const [p50, p95] = [percentile(timings, 50), percentile(timings, 95)]
+  console.log(`${name}: p50 ${p50.toFixed(3)}ms, p95 ${p95.toFixed(3)}ms over ${timings.length} requests`)
+  return p95
+}
+
+describe('Forwarding Performance Benchmarks', () => {
+  const originalToken = state.copilotToken
+  let upstream: FakeUpstream | undefined
+
+  beforeEach(() => {
+    state.copilotToken = 'test-copilot-token'
+  })
+
+  afterEach(() => {
+    upstream?.restore()
+    upstream = undefined
+    state.copilotToken = originalToken
+  })
+
+  test('time to first byte of a streamed completion', async () => {
+    const text = Array.from({ length: 50 }, (_, i) => `token${i} `)
+    upstream = fakeUpstream(
+      Array.from({ length: WARMUP + ITERATIONS }, () => streamScript({ text, usage: usage(12, 50) })),
+    )
+
+    const timings: Array<number> = []
+    for (let i = 0; i < WARMUP + ITERATIONS; i++) {
+      const start = performance.now()
+      const response = await post(true)
+      const reader = response.body!.getReader()
+      const first = await reader.read()
+      const ttfb = performance.now() - start
+      while (!(await reader.read()).done) {
+        // Drain so the next request starts from an idle gateway
+      }
+
+      expect(first.done).toBe(false)
+      if (i >= WARMUP) timings.push(ttfb)
+    }
+
+    expect(report('Streamed TTFB', timings)).toBeLessThan(TTFB_BUDGET_MS)
+  })
+
+  test('latency of a non-streamed completion', async () => {
+    upstream = fakeUpstream(
+      Array.from({ length: WARMUP + ITERATIONS }, () =>
+        chatCompletion({ content: 'A gateway to Copilot.', usage: usage(12, 6) }),
+      ),
+    )
+
+    const timings: Array<number> = []
+    for (let i = 0; i < WARMUP + ITERATIONS; i++) {
+      const start = performance.now()
+      const response = await post(false)
+      await response.json()
+      if (i >= WARMUP) timings.push(performance.now() - start)
+
+      expect(response.status).toBe(200)
+    }
+
+    expect(report('Non-streamed latency', timings)).toBeLessThan(LATENCY_BUDGET_MS)
+  })
+})
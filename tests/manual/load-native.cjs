@@ -15,7 +15,7 @@ try {
   // Test a simple function
   if (native.getTokenCount) {
     console.log('Testing getTokenCount...');
-    const result = native.getTokenCount('[]');
+    const result = native.getTokenCount([]);
     console.log('Result:', result);
   }
   
@@ -106,7 +106,7 @@ describe('Phase 2: Enhanced Utility Functions', () => {
       
       // Reset one limiter
       const reset = await rustCore.resetRateLimit('user1')
-      expect(reset).toBe(true)
+      expect(reset).toBe(1)
       
       // Should be able to make request again
       const allowed = await rustCore.checkRateLimit('user1', 5)
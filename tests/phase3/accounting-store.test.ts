@@ -0,0 +1,93 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { openAccountingStore, withAccountingStore, type AccountingStore } from '../../src/lib/accounting-store'
+import { pruneAuditLog, readAuditLog, recordAudit } from '../../src/lib/audit'
+import { PATHS } from '../../src/lib/paths'
+import { state } from '../../src/lib/state'
+import { readUsage, type UsageRecord } from '../../src/lib/usage-ledger'
+
+const usage = (time: string, key: string, tags?: Record<string, string>): UsageRecord => ({
+  time,
+  key,
+  endpoint: '/chat/completions',
+  model: 'gpt-4o',
+  prompt_tokens: 10,
+  completion_tokens: 5,
+  tags,
+})
+
+const audit = (time: string) => ({ time, endpoint: '/chat/completions', model: 'gpt-4o', stream: false, request: {}, response: {} })
+
+const originalAuditDir = PATHS.AUDIT_DIR
+
+describe('Phase 3: Accounting Store', () => {
+  let dir: string
+  let store: AccountingStore
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-accounting-'))
+    PATHS.AUDIT_DIR = path.join(dir, 'audit')
+    store = await openAccountingStore(`sqlite:${path.join(dir, 'accounting.db')}`)
+  })
+
+  afterEach(async () => {
+    await store.close()
+    PATHS.AUDIT_DIR = originalAuditDir
+    state.accountingStore = undefined
+    state.auditRetentionDays = undefined
+  })
+
+  test('should keep usage records in SQLite', async () => {
+    await store.appendUsage(usage('2026-10-01T10:00:00.000Z', 'a'))
+    await store.appendUsage(usage('2026-10-02T10:00:00.000Z', 'b'))
+    await store.appendUsage(usage('2026-10-03T10:00:00.000Z', 'a'))
+
+    expect(store.kind).toBe('sqlite')
+    expect((await store.readUsage({ key: 'a' })).map((record) => record.time)).toEqual([
+      '2026-10-01T10:00:00.000Z',
+      '2026-10-03T10:00:00.000Z',
+    ])
+    expect(await store.readUsage({ since: '2026-10-02T00:00:00.000Z' })).toHaveLength(2)
+    expect(await store.readUsage({ key: 'c' })).toEqual([])
+  })
+
+  test('should keep and prune audit entries in SQLite', async () => {
+    await store.appendAudit(audit('2026-09-01T10:00:00.000Z'))
+    await store.appendAudit(audit('2026-10-01T10:00:00.000Z'))
+    await store.appendAudit(audit('2026-10-02T10:00:00.000Z'))
+
+    const range = await store.readAudit({ since: '2026-10-01T00:00:00.000Z', until: '2026-10-01T23:59:59.999Z' })
+    expect(range).toEqual([audit('2026-10-01T10:00:00.000Z')])
+    expect(await store.pruneAudit('2026-10-01')).toBe(1)
+    expect(await store.readAudit({})).toHaveLength(2)
+  })
+
+  test('should route the ledger and the audit log through the selected store', async () => {
+    state.accountingStore = store
+    state.auditRetentionDays = 30
+    await store.appendUsage(usage('2026-10-01T10:00:00.000Z', 'a', { project: 'search' }))
+    await store.appendUsage(usage('2026-10-01T11:00:00.000Z', 'a', { project: 'ads' }))
+    await recordAudit({ endpoint: '/chat/completions', model: 'gpt-4o', stream: false, request: { key: 'sk-abcdefghijklmnopqrstuvwxyz' }, response: {} })
+
+    expect(await readUsage({ tag: 'project=search' })).toHaveLength(1)
+    const [entry] = await readAuditLog()
+    expect(entry.request).toEqual({ key: '[REDACTED]' })
+    expect(await pruneAuditLog(30)).toBe(0)
+    // Nothing went to the files
+    expect(await fs.readdir(dir)).not.toContain('audit')
+  })
+
+  test('should select a store for the duration of a command only', async () => {
+    const url = `sqlite:${path.join(dir, 'report.db')}`
+    const kind = await withAccountingStore(url, async () => state.accountingStore?.kind)
+
+    expect(kind).toBe('sqlite')
+    expect(state.accountingStore).toBeUndefined()
+  })
+
+  test('should reject unknown store URLs', async () => {
+    await expect(openAccountingStore('mysql://db/accounting')).rejects.toThrow('Invalid accounting store')
+  })
+})
@@ -0,0 +1,59 @@
+import { test, expect, describe } from 'bun:test'
+import fs from 'node:fs'
+import path from 'node:path'
+import {
+  createAnthropicStreamState,
+  translateChunkToAnthropicEvents,
+} from '../../src/routes/messages/stream-translation'
+import type { ChatCompletionChunk } from '../../src/services/copilot/create-chat-completions'
+import { asJson, readFixtures, updatingGolden } from '../testkit/golden'
+
+// Each fixture holds upstream OpenAI chunks and the Anthropic events they
+// must translate to. Run with UPDATE_GOLDEN=1 to rewrite the expected events.
+const FIXTURES_DIR = path.join(import.meta.dir, 'fixtures', 'anthropic-stream')
+
+interface Fixture {
+  description: string
+  chunks: Array<ChatCompletionChunk>
+  events: Array<unknown>
+}
+
+const translate = (chunks: Array<ChatCompletionChunk>) => {
+  const state = createAnthropicStreamState()
+  // Round-trip through JSON like the SSE writer, dropping undefined fields
+  return asJson(chunks.flatMap((chunk) => translateChunkToAnthropicEvents(chunk, state))) as Array<unknown>
+}
+
+describe('Phase 3: Anthropic Stream Translation', () => {
+  for (const { file, filePath: fixturePath, fixture } of readFixtures<Fixture>(FIXTURES_DIR)) {
+    test(`${file}: ${fixture.description}`, () => {
+      const events = translate(fixture.chunks)
+      if (updatingGolden()) {
+        fs.writeFileSync(fixturePath, `${JSON.stringify({ ...fixture, events }, null, 2)}\n`)
+        return
+      }
+      expect(events).toEqual(fixture.events)
+    })
+  }
+
+  test('should emit the full event sequence in order', () => {
+    const fixture = JSON.parse(fs.readFileSync(path.join(FIXTURES_DIR, 'text-then-tools.json'), 'utf8')) as Fixture
+    const types = translate(fixture.chunks).map((event) => (event as { type: string }).type)
+
+    expect(types[0]).toBe('message_start')
+    expect(types.slice(-2)).toEqual(['message_delta', 'message_stop'])
+    // Every started block is stopped before the next one starts
+    let open = 0
+    for (const type of types) {
+      if (type === 'content_block_start') expect(open++).toBe(0)
+      if (type === 'content_block_stop') expect(--open).toBe(0)
+    }
+  })
+
+  test('should ignore chunks without choices', () => {
+    const state = createAnthropicStreamState()
+    const usageOnly = { id: 'x', object: 'chat.completion.chunk', created: 0, model: 'gpt-4o', choices: [] } as ChatCompletionChunk
+    expect(translateChunkToAnthropicEvents(usageOnly, state)).toEqual([])
+    expect(state.messageStartSent).toBe(false)
+  })
+})
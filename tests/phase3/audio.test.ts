@@ -0,0 +1,103 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { server } from '../../src/server'
+import { loadMediaProviders } from '../../src/lib/media-providers'
+import { state } from '../../src/lib/state'
+import { fakeProvider, type FakeProvider } from '../testkit/upstream'
+
+const transcriptionForm = () => {
+  const form = new FormData()
+  form.append('model', 'whisper-1')
+  form.append('file', new Blob([new Uint8Array([1, 2, 3])], { type: 'audio/wav' }), 'clip.wav')
+  return form
+}
+
+const postSpeech = () =>
+  server.request('/v1/audio/speech', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', authorization: 'Bearer client-key' },
+    body: JSON.stringify({ model: 'tts-1', input: 'Hello', voice: 'alloy' }),
+  })
+
+describe('Phase 3: Audio Endpoints', () => {
+  let provider: FakeProvider | undefined
+
+  afterEach(() => {
+    provider?.restore()
+    provider = undefined
+    state.mediaProviders = undefined
+  })
+
+  test('should answer model_not_found without a provider', async () => {
+    const response = await server.request('/v1/audio/transcriptions', {
+      method: 'POST',
+      body: transcriptionForm(),
+    })
+
+    expect(response.status).toBe(404)
+    const { error } = (await response.json()) as { error: Record<string, string> }
+    expect(error).toMatchObject({ type: 'invalid_request_error', param: 'model', code: 'model_not_found' })
+    expect(error.message).toContain('`whisper-1`')
+    expect(error.message).toContain('Audio transcription')
+
+    const speech = await postSpeech()
+    expect(speech.status).toBe(404)
+    expect(((await speech.json()) as { error: Record<string, string> }).error.message).toContain('`tts-1`')
+  })
+
+  test('should forward speech to the provider with its key', async () => {
+    state.mediaProviders = { audio: { url: 'https://audio.example.com/v1', apiKey: 'sk-provider' } }
+    provider = fakeProvider(
+      () => new Response(new Uint8Array([73, 68, 51]), { headers: { 'content-type': 'audio/mpeg' } }),
+    )
+
+    const response = await postSpeech()
+
+    expect(response.status).toBe(200)
+    expect(response.headers.get('content-type')).toBe('audio/mpeg')
+    expect(new Uint8Array(await response.arrayBuffer())).toEqual(new Uint8Array([73, 68, 51]))
+    const [forwarded] = provider.requests
+    expect(forwarded.url).toBe('https://audio.example.com/v1/audio/speech')
+    expect(forwarded.headers.get('authorization')).toBe('Bearer sk-provider')
+    expect(JSON.parse(new TextDecoder().decode(forwarded.body))).toEqual({ model: 'tts-1', input: 'Hello', voice: 'alloy' })
+  })
+
+  test('should forward multipart uploads and provider errors unchanged', async () => {
+    state.mediaProviders = { audio: { url: 'https://audio.example.com/v1' } }
+    provider = fakeProvider(() => Response.json({ error: { message: 'Invalid file format.' } }, { status: 400 }))
+
+    const request = new Request('http://localhost/v1/audio/transcriptions', {
+      method: 'POST',
+      headers: { authorization: 'Bearer client-key' },
+      body: transcriptionForm(),
+    })
+    const response = await server.request(request)
+
+    expect(response.status).toBe(400)
+    expect(((await response.json()) as { error: { message: string } }).error.message).toBe('Invalid file format.')
+    const [forwarded] = provider.requests
+    expect(forwarded.headers.get('content-type')).toStartWith('multipart/form-data; boundary=')
+    expect(forwarded.headers.get('authorization')).toBeNull()
+    expect(forwarded.body.byteLength).toBeGreaterThan(0)
+  })
+
+  test('should load providers and resolve their keys', async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-media-'))
+    const file = path.join(dir, 'providers.json')
+    process.env.TEST_AUDIO_KEY = 'sk-from-env'
+    try {
+      await fs.writeFile(file, JSON.stringify({ audio: { url: 'https://audio.example.com/v1/', apiKey: '${TEST_AUDIO_KEY}' } }))
+      expect(await loadMediaProviders(file)).toEqual({
+        audio: { url: 'https://audio.example.com/v1', apiKey: 'sk-from-env' },
+      })
+
+      await fs.writeFile(file, JSON.stringify({ audio: { url: 'audio.example.com' } }))
+      await expect(loadMediaProviders(file)).rejects.toThrow('Invalid audio provider URL')
+    } finally {
+      delete process.env.TEST_AUDIO_KEY
+      await fs.rm(dir, { recursive: true, force: true })
+    }
+  })
+})
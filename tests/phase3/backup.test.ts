@@ -0,0 +1,125 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { createBackup, restoreBackup } from '../../src/lib/backup'
+import { PATHS } from '../../src/lib/paths'
+import { readTeamStore, writeTeamStore } from '../../src/lib/team'
+import { readUsage } from '../../src/lib/usage-ledger'
+
+const originalPaths = { ...PATHS }
+
+const record = {
+  time: '2026-10-01T10:00:00.000Z',
+  key: 'abc',
+  endpoint: '/chat/completions',
+  model: 'gpt-4o',
+  prompt_tokens: 10,
+  completion_tokens: 5,
+}
+const snapshot = { time: '2026-10-01T10:00:00.000Z', used: 20, entitlement: 300, resetDate: '2026-11-01' }
+
+// Points every path at a fresh directory, standing in for a host
+async function useHost() {
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-backup-'))
+  Object.assign(PATHS, {
+    APP_DIR: dir,
+    GITHUB_TOKEN_PATH: path.join(dir, 'github_token'),
+    USAGE_DIR: path.join(dir, 'usage'),
+    USAGE_SNAPSHOTS_PATH: path.join(dir, 'usage_snapshots.jsonl'),
+    TEAM_TOKENS_PATH: path.join(dir, 'team_tokens.json'),
+  })
+  return dir
+}
+
+describe('Phase 3: Backup', () => {
+  let configFile: string
+
+  beforeEach(async () => {
+    const dir = await useHost()
+    await fs.writeFile(PATHS.GITHUB_TOKEN_PATH, 'ghu_owner')
+    await writeTeamStore({
+      members: { key1: { login: 'octocat', githubToken: 'ghu_member', addedAt: '2026-01-01T00:00:00.000Z' } },
+      audit: [{ time: '2026-01-01T00:00:00.000Z', action: 'created', id: 'key1', login: 'octocat', via: 'cli' }],
+    })
+    await fs.mkdir(PATHS.USAGE_DIR)
+    await fs.writeFile(path.join(PATHS.USAGE_DIR, '2026-10-01.jsonl'), `${JSON.stringify(record)}\n`)
+    await fs.writeFile(PATHS.USAGE_SNAPSHOTS_PATH, `${JSON.stringify(snapshot)}\n`)
+    configFile = path.join(dir, 'policy.json')
+    await fs.writeFile(configFile, '{"keys":{}}')
+  })
+
+  afterEach(() => {
+    Object.assign(PATHS, originalPaths)
+  })
+
+  test('should leave GitHub tokens out unless asked', async () => {
+    const backup = await createBackup({ includeTokens: false })
+
+    expect(backup.githubToken).toBeUndefined()
+    expect(backup.team.members.key1.githubToken).toBe('')
+    expect(backup.usage).toEqual([record])
+    expect(backup.usageSnapshots).toEqual([snapshot])
+
+    expect((await createBackup({ includeTokens: true })).githubToken).toBe('ghu_owner')
+  })
+
+  test('should restore everything on a new host', async () => {
+    const backup = await createBackup({ includeTokens: true, configFiles: [configFile] })
+    await fs.rm(configFile)
+    await useHost()
+
+    const summary = await restoreBackup(backup, { force: false })
+
+    expect(summary).toEqual({
+      githubToken: true,
+      members: 1,
+      skippedMembers: [],
+      usage: 1,
+      usageSnapshots: 1,
+      config: [configFile],
+    })
+    expect(await fs.readFile(PATHS.GITHUB_TOKEN_PATH, 'utf8')).toBe('ghu_owner')
+    const store = await readTeamStore()
+    expect(store.members.key1.githubToken).toBe('ghu_member')
+    expect(store.audit).toHaveLength(1)
+    expect(await readUsage({})).toEqual([record])
+    expect(await fs.readFile(configFile, 'utf8')).toBe('{"keys":{}}')
+  })
+
+  test('should not add anything twice when restored again', async () => {
+    const backup = await createBackup({ includeTokens: true })
+    await useHost()
+    await restoreBackup(backup, { force: false })
+
+    const summary = await restoreBackup(backup, { force: false })
+
+    expect(summary).toMatchObject({ githubToken: false, members: 0, usage: 0, usageSnapshots: 0 })
+    expect(await readUsage({})).toHaveLength(1)
+    expect((await readTeamStore()).audit).toHaveLength(1)
+  })
+
+  test('should keep what exists unless forced', async () => {
+    const backup = await createBackup({ includeTokens: true })
+    await fs.writeFile(PATHS.GITHUB_TOKEN_PATH, 'ghu_new')
+
+    expect((await restoreBackup(backup, { force: false })).githubToken).toBe(false)
+    expect(await fs.readFile(PATHS.GITHUB_TOKEN_PATH, 'utf8')).toBe('ghu_new')
+    expect((await restoreBackup(backup, { force: true })).githubToken).toBe(true)
+  })
+
+  test('should skip members exported without their token', async () => {
+    const backup = await createBackup({ includeTokens: false })
+    await useHost()
+
+    const summary = await restoreBackup(backup, { force: false })
+
+    expect(summary.skippedMembers).toEqual(['octocat'])
+    expect((await readTeamStore()).members).toEqual({})
+  })
+
+  test('should reject backups of another version', async () => {
+    const backup = await createBackup({ includeTokens: false })
+    await expect(restoreBackup({ ...backup, version: 2 }, { force: false })).rejects.toThrow('Unsupported backup version')
+  })
+})
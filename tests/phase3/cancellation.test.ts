@@ -0,0 +1,40 @@
+import { test, expect, describe, afterAll } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+describe('Phase 3: Native Request Cancellation', () => {
+  // Never answers chat completions, so requests stay in flight until cancelled
+  const server = Bun.serve({
+    port: 0,
+    fetch(req) {
+      if (new URL(req.url).pathname === '/embeddings') return Response.json({ data: [] })
+      return new Promise<Response>(() => {})
+    }
+  })
+  const baseUrl = `http://127.0.0.1:${server.port}`
+
+  afterAll(() => server.stop(true))
+
+  test('cancelRequest aborts an in-flight request', async () => {
+    const handle = rustCore.createChatCompletions({ model: 'gpt-4o', messages: [] }, { baseUrl })
+
+    expect(handle.id).toMatch(/^[0-9a-f-]{36}$/)
+    expect(rustCore.cancelRequest(handle.id)).toBe(true)
+    expect(rustCore.cancelRequest(handle.id)).toBe(false)
+    await expect(handle.response).rejects.toMatchObject({ name: 'AbortError' })
+  })
+
+  test('aborting the signal cancels the request', async () => {
+    const controller = new AbortController()
+    const handle = rustCore.createChatCompletions({ model: 'gpt-4o', messages: [] }, { baseUrl, signal: controller.signal })
+
+    setTimeout(() => controller.abort(), 50)
+    await expect(handle.response).rejects.toMatchObject({ name: 'AbortError' })
+  })
+
+  test('settled requests can no longer be cancelled', async () => {
+    const handle = rustCore.createEmbeddings({ input: 'hi' }, { baseUrl })
+
+    expect(JSON.parse(await handle.response)).toEqual({ data: [] })
+    expect(rustCore.cancelRequest(handle.id)).toBe(false)
+  })
+})
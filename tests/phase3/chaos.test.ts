@@ -0,0 +1,69 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import { streamSSE } from 'hono/streaming'
+import { createChaosMiddleware } from '../../src/lib/chaos'
+import { state } from '../../src/lib/state'
+
+// Returns the given values in turn, then 0.99 so nothing else fires
+const sequence = (...values: Array<number>) => () => values.shift() ?? 0.99
+
+function appWith(random: () => number) {
+  const app = new Hono()
+  app.use(createChaosMiddleware(random))
+  app.post('/json', (c) => c.json({ ok: true, padding: 'x'.repeat(100) }))
+  app.post('/stream', (c) =>
+    streamSSE(c, async (stream) => {
+      for (let i = 0; i < 5; i++) await stream.writeSSE({ data: String(i) })
+      await stream.writeSSE({ data: '[DONE]' })
+    }),
+  )
+  app.get('/json', (c) => c.json({ ok: true }))
+  return app
+}
+
+describe('Phase 3: Failure Injection', () => {
+  afterEach(() => {
+    state.chaos = undefined
+  })
+
+  test('should do nothing unless configured', async () => {
+    const response = await appWith(sequence(0)).request('/json', { method: 'POST' })
+    expect(response.status).toBe(200)
+  })
+
+  test('should inject a rate limit with Retry-After', async () => {
+    state.chaos = { rateLimit: { rate: 0.5, retryAfterSeconds: 3 } }
+
+    const response = await appWith(sequence(0.1)).request('/json', { method: 'POST' })
+    expect(response.status).toBe(429)
+    expect(response.headers.get('retry-after')).toBe('3')
+    const body = (await response.json()) as { error: { code: string } }
+    expect(body.error.code).toBe('rate_limit_exceeded')
+  })
+
+  test('should only disrupt POST requests on the listed routes', async () => {
+    state.chaos = { routes: ['/stream'], rateLimit: { rate: 1 } }
+    const app = appWith(sequence(0, 0))
+
+    expect((await app.request('/json', { method: 'POST' })).status).toBe(200)
+    expect((await app.request('/json')).status).toBe(200)
+  })
+
+  test('should truncate streams before [DONE]', async () => {
+    state.chaos = { truncate: { rate: 0.5, maxEvents: 10 } }
+    // 0.1 triggers truncation, 0.25 cuts after 2 of up to 10 events
+    const response = await appWith(sequence(0.1, 0.25)).request('/stream', { method: 'POST' })
+
+    const text = await response.text()
+    expect(text).toContain('data: 0')
+    expect(text).not.toContain('[DONE]')
+  })
+
+  test('should abort non-streamed bodies halfway on disconnect', async () => {
+    state.chaos = { disconnect: { rate: 0.5 } }
+    const response = await appWith(sequence(0.1)).request('/json', { method: 'POST' })
+
+    expect(response.status).toBe(200)
+    await expect(response.text()).rejects.toThrow('Injected disconnect')
+  })
+})
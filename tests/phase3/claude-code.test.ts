@@ -0,0 +1,43 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { claudeCodeEnv } from '../../src/lib/claude-code'
+import { generateEnvScript } from '../../src/lib/shell'
+
+const env = claudeCodeEnv('http://localhost:4141', 'claude-sonnet-4', 'gpt-4o-mini')
+
+describe('Phase 3: Claude Code Setup', () => {
+  const originalShell = process.env.SHELL
+
+  afterEach(() => {
+    if (originalShell === undefined) delete process.env.SHELL
+    else process.env.SHELL = originalShell
+  })
+
+  test('should point Claude Code at the server with the chosen models', () => {
+    expect(env).toEqual({
+      ANTHROPIC_BASE_URL: 'http://localhost:4141',
+      ANTHROPIC_AUTH_TOKEN: 'dummy',
+      ANTHROPIC_MODEL: 'claude-sonnet-4',
+      ANTHROPIC_SMALL_FAST_MODEL: 'gpt-4o-mini',
+    })
+  })
+
+  test.skipIf(process.platform === 'win32')('should export the variables before running claude in bash', () => {
+    process.env.SHELL = '/bin/bash'
+
+    expect(generateEnvScript(env, 'claude')).toBe(
+      'export ANTHROPIC_BASE_URL=http://localhost:4141 ANTHROPIC_AUTH_TOKEN=dummy ' +
+        'ANTHROPIC_MODEL=claude-sonnet-4 ANTHROPIC_SMALL_FAST_MODEL=gpt-4o-mini && claude',
+    )
+  })
+
+  test.skipIf(process.platform === 'win32')('should set each variable globally in fish', () => {
+    process.env.SHELL = '/usr/bin/fish'
+
+    expect(generateEnvScript(env, 'claude').split('; ')).toEqual([
+      'set -gx ANTHROPIC_BASE_URL http://localhost:4141',
+      'set -gx ANTHROPIC_AUTH_TOKEN dummy',
+      'set -gx ANTHROPIC_MODEL claude-sonnet-4',
+      'set -gx ANTHROPIC_SMALL_FAST_MODEL gpt-4o-mini && claude',
+    ])
+  })
+})
@@ -0,0 +1,335 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { defineCommand, runCommand } from 'citty'
+import consola, { type LogObject } from 'consola'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { audit } from '../../src/audit'
+import { createCompletionsCommand, createManCommand } from '../../src/completions'
+import { doctor } from '../../src/doctor'
+import { PATHS } from '../../src/lib/paths'
+import { state } from '../../src/lib/state'
+import { models } from '../../src/models'
+import { launchdPlist, service, startCommand, systemdUnit } from '../../src/service'
+import { start } from '../../src/start'
+import { team } from '../../src/team'
+import { tokenize } from '../../src/tokenize'
+
+const root = defineCommand({
+  meta: { name: 'copilot-api', description: 'Copilot gateway' },
+  subCommands: { audit, service, team, tokenize },
+})
+
+const modelList = {
+  object: 'list',
+  data: [
+    {
+      id: 'gpt-4o',
+      capabilities: {
+        limits: { max_context_window_tokens: 128000, max_output_tokens: 4096 },
+        supports: { tool_calls: true, vision: true },
+      },
+    },
+  ],
+}
+
+// Answers GitHub and Copilot from fixed responses and records what was asked;
+// `failing` maps a URL suffix to the error status it answers with instead
+function fakeGitHub(failing: Record<string, number> = {}) {
+  const requests: Array<{ url: string; authorization: string | null }> = []
+  const originalFetch = globalThis.fetch
+  globalThis.fetch = (async (input: string | URL | Request, init?: RequestInit) => {
+    const url = input instanceof Request ? input.url : input.toString()
+    requests.push({ url, authorization: new Headers(init?.headers).get('authorization') })
+    const failure = Object.entries(failing).find(([suffix]) => url.endsWith(suffix))
+    if (failure) return new Response('denied', { status: failure[1] })
+    if (url.endsWith('/user')) return Response.json({ login: 'octocat' })
+    if (url.includes('/copilot_internal/')) {
+      return Response.json({ token: 'copilot-token', expires_at: Math.floor(Date.now() / 1000) + 1800, refresh_in: 1500 })
+    }
+    if (url.endsWith('/models')) return Response.json(modelList)
+    return new Response('pkgver=1.98.1')
+  }) as unknown as typeof fetch
+  return { requests, restore: () => (globalThis.fetch = originalFetch) }
+}
+
+// Collects what a command prints to stdout
+async function captureStdout(run: () => Promise<unknown>): Promise<string> {
+  const chunks: Array<string> = []
+  const originalWrite = process.stdout.write.bind(process.stdout)
+  process.stdout.write = ((chunk: string | Uint8Array) => {
+    chunks.push(chunk.toString())
+    return true
+  }) as typeof process.stdout.write
+  try {
+    await run()
+  } finally {
+    process.stdout.write = originalWrite
+  }
+  return chunks.join('')
+}
+
+// Collects consola output as "name: message" lines by log type
+function captureLogs(): Array<{ type: string; line: string }> {
+  const logs: Array<{ type: string; line: string }> = []
+  consola.setReporters([
+    { log: (logObj: LogObject) => logs.push({ type: logObj.type, line: logObj.args.join(' ') }) },
+  ])
+  return logs
+}
+
+describe('Phase 3: CLI commands', () => {
+  const originalPaths = { ...PATHS }
+  const originalReporters = [...consola.options.reporters]
+  let dir: string
+  let github: ReturnType<typeof fakeGitHub> | undefined
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-cli-'))
+    PATHS.APP_DIR = dir
+    PATHS.GITHUB_TOKEN_PATH = path.join(dir, 'github_token')
+  })
+
+  afterEach(() => {
+    github?.restore()
+    github = undefined
+    Object.assign(PATHS, originalPaths)
+    consola.setReporters(originalReporters)
+    state.githubToken = undefined
+    state.copilotToken = undefined
+    state.accountType = 'individual'
+    process.exitCode = 0
+  })
+
+  test('doctor checks the token given with -g against the account type given with -a', async () => {
+    github = fakeGitHub()
+
+    await runCommand(doctor, { rawArgs: ['-g', 'ghu_doctor', '-a', 'business'] })
+
+    expect(state.accountType).toBe('business')
+    const user = github.requests.find((request) => request.url.endsWith('/user'))
+    expect(user?.authorization).toBe('token ghu_doctor')
+    expect(github.requests.map((request) => request.url)).toContain('https://api.business.githubcopilot.com/models')
+  })
+
+  test('doctor reports a missing stored token without contacting GitHub', async () => {
+    github = fakeGitHub()
+
+    await runCommand(doctor, { rawArgs: [] })
+
+    expect(process.exitCode).toBe(1)
+    expect(github.requests.some((request) => request.url.includes('github.com/user'))).toBe(false)
+  })
+
+  test('doctor reports every connectivity check and the tokenizer count', async () => {
+    github = fakeGitHub()
+    const logs = captureLogs()
+
+    await runCommand(doctor, { rawArgs: ['-g', 'ghu_doctor'] })
+
+    const lines = logs.filter((log) => log.type === 'success').map((log) => log.line)
+    expect(lines).toContain('GitHub user: Logged in as octocat')
+    expect(lines.some((line) => line.startsWith('Copilot token: Token exchange succeeded, expires in'))).toBe(true)
+    expect(lines).toContain('Models: Fetched 1 models from https://api.githubcopilot.com')
+    expect(lines.some((line) => /^JS tokenizer: gpt-tokenizer available \(\d+ tokens for sample\)$/.test(line))).toBe(true)
+  })
+
+  test('doctor fails on a rejected GitHub token and skips the checks that depend on it', async () => {
+    github = fakeGitHub({ '/user': 401 })
+    const logs = captureLogs()
+
+    await runCommand(doctor, { rawArgs: ['-g', 'ghu_revoked'] })
+
+    expect(process.exitCode).toBe(1)
+    const errors = logs.filter((log) => log.type === 'error').map((log) => log.line)
+    expect(errors.some((line) => line.startsWith('GitHub user:') && line.endsWith('(401 Unauthorized)'))).toBe(true)
+    expect(logs.some((log) => log.line.includes('Run `copilot-api auth` to log in again.'))).toBe(true)
+    expect(github.requests.some((request) => request.url.includes('/copilot_internal/'))).toBe(false)
+    expect(logs.some((log) => log.line.startsWith('JS tokenizer:'))).toBe(true)
+  })
+
+  test('doctor points at the subscription when the Copilot token exchange is refused', async () => {
+    github = fakeGitHub({ '/copilot_internal/v2/token': 403 })
+    const logs = captureLogs()
+
+    await runCommand(doctor, { rawArgs: ['-g', 'ghu_doctor'] })
+
+    expect(process.exitCode).toBe(1)
+    expect(logs.some((log) => log.type === 'error' && log.line.startsWith('Copilot token:'))).toBe(true)
+    expect(logs.some((log) => log.line.includes('does not seem to have an active Copilot subscription'))).toBe(true)
+    expect(github.requests.some((request) => request.url.endsWith('/models'))).toBe(false)
+  })
+
+  test('models prints the model list as JSON with --json', async () => {
+    github = fakeGitHub()
+
+    const output = await captureStdout(() =>
+      runCommand(models, { rawArgs: ['--json', '--github-token', 'ghu_models', '-a', 'enterprise'] }),
+    )
+
+    expect(JSON.parse(output)).toEqual([
+      { id: 'gpt-4o', contextWindow: 128000, maxOutput: 4096, vision: true, tools: true },
+    ])
+    const token = github.requests.find((request) => request.url.includes('/copilot_internal/'))
+    expect(token?.authorization).toBe('token ghu_models')
+    expect(github.requests.map((request) => request.url)).toContain('https://api.enterprise.githubcopilot.com/models')
+  })
+
+  test('models prints a table by default', async () => {
+    github = fakeGitHub()
+
+    const output = await captureStdout(() => runCommand(models, { rawArgs: ['-g', 'ghu_models'] }))
+
+    expect(output.split('\n')[0]).toBe('ID      CONTEXT  MAX OUTPUT  VISION  TOOLS')
+    expect(output).toContain('gpt-4o  128000   4096        yes     yes')
+  })
+
+  test('tokenize counts the messages of the file given as its argument', async () => {
+    const file = path.join(dir, 'messages.json')
+    await fs.writeFile(file, JSON.stringify({ messages: [{ role: 'user', content: 'Hello, world!' }] }))
+
+    const output = await captureStdout(() => runCommand(tokenize, { rawArgs: [file, '--json', '-m', 'gpt-4o'] }))
+    const counts = JSON.parse(output) as { model: string; messages: Array<{ role: string }>; total: { input: number } }
+
+    expect(counts.model).toBe('gpt-4o')
+    expect(counts.messages.map((message) => message.role)).toEqual(['user'])
+    expect(counts.total.input).toBeGreaterThan(0)
+  })
+
+  test('tokenize translates Anthropic payloads with --anthropic', async () => {
+    const file = path.join(dir, 'anthropic.json')
+    await fs.writeFile(
+      file,
+      JSON.stringify({
+        model: 'claude-sonnet-4',
+        max_tokens: 100,
+        system: 'Be brief.',
+        messages: [{ role: 'user', content: 'Hello, world!' }],
+      }),
+    )
+
+    const output = await captureStdout(() => runCommand(tokenize, { rawArgs: [file, '--anthropic', '--json'] }))
+    const counts = JSON.parse(output) as { messages: Array<{ role: string }> }
+
+    expect(counts.messages.map((message) => message.role)).toEqual(['system', 'user'])
+  })
+
+  test('start rejects a --rate-limit below 1 before serving', async () => {
+    for (const raw of ['0', '-5', 'abc']) {
+      await expect(runCommand(start, { rawArgs: ['--rate-limit', raw] })).rejects.toThrow(
+        `--rate-limit: expected an integer between 1 and ${Number.MAX_SAFE_INTEGER}, got "${raw}"`,
+      )
+    }
+  })
+
+  test('start resolves file references in --admin-token', async () => {
+    await expect(
+      runCommand(start, { rawArgs: ['--admin-token', `file:${dir}/missing_admin_token`] }),
+    ).rejects.toThrow('cannot read')
+  })
+
+  test('completions complete nested commands in every shell', async () => {
+    const completions = createCompletionsCommand(() => root)
+    const script = (shell: string) => captureStdout(() => runCommand(completions, { rawArgs: [shell] }))
+
+    const bash = await script('bash')
+    expect(bash).toContain('opts="install uninstall status"')
+    expect(bash).toContain('opts="add list rotate revoke remove audit"')
+    expect(bash).toContain('case "${COMP_WORDS[2]}" in')
+
+    const zsh = await script('zsh')
+    expect(zsh).toContain('_copilot_api_service() {')
+    expect(zsh).toContain("'install:")
+    expect(zsh).toContain('compdef _copilot_api copilot-api')
+
+    // `team audit` and `audit` are told apart by position
+    const fish = await script('fish')
+    expect(fish).toContain(
+      "-n 'contains -- team (commandline -opc)[2]; and not __fish_seen_subcommand_from add list rotate revoke remove audit' -a audit",
+    )
+    expect(fish).toContain("-n 'contains -- audit (commandline -opc)[2]; and not __fish_seen_subcommand_from export show prune' -a prune")
+
+    const powershell = await script('powershell')
+    expect(powershell).toContain("@('install', 'uninstall', 'status')")
+    expect(powershell).toContain('switch ($words[2])')
+  })
+
+  test('completions reject unsupported shells', async () => {
+    const completions = createCompletionsCommand(() => root)
+    await expect(runCommand(completions, { rawArgs: ['tcsh'] })).rejects.toThrow(
+      'Unsupported shell "tcsh", expected one of: bash, zsh, fish, powershell',
+    )
+  })
+
+  test('man lists nested commands with their options', async () => {
+    const page = await captureStdout(() => runCommand(createManCommand(() => root), { rawArgs: [] }))
+
+    expect(page).toStartWith('.TH COPILOT-API 1')
+    expect(page).toContain('.B service install')
+    expect(page).toContain('.B team audit')
+    expect(page).toContain(String.raw`.B \-\-port, \-p`)
+  })
+
+  test('service install passes its options on to start', () => {
+    const command = startCommand({ port: '8080', accountType: 'business', rateLimit: '30', wait: true, verbose: false })
+
+    expect(command.slice(2)).toEqual([
+      'start',
+      '--port',
+      '8080',
+      '--account-type',
+      'business',
+      '--rate-limit',
+      '30',
+      '--wait',
+    ])
+  })
+
+  test('service install writes a systemd unit that runs the start command', () => {
+    const unit = systemdUnit(['/usr/bin/node', '/opt/copilot api/main.js', 'start', '--port', '4141'])
+
+    expect(unit.split('\n')).toEqual(
+      expect.arrayContaining([
+        '[Unit]',
+        'Description=Copilot API server',
+        'After=network-online.target',
+        '[Service]',
+        'ExecStart=/usr/bin/node "/opt/copilot api/main.js" start --port 4141',
+        'Restart=on-failure',
+        'Environment=NODE_ENV=production',
+        '[Install]',
+        'WantedBy=default.target',
+      ]),
+    )
+  })
+
+  test('service install writes a launchd plist with escaped arguments and the log path', () => {
+    const plist = launchdPlist(['/usr/bin/node', '/opt/a&b/main.js', 'start', '--port', '4141'])
+
+    expect(plist).toContain('<string>com.github.copilot-api</string>')
+    expect(plist).toContain(
+      [
+        '  <array>',
+        '    <string>/usr/bin/node</string>',
+        '    <string>/opt/a&amp;b/main.js</string>',
+        '    <string>start</string>',
+        '    <string>--port</string>',
+        '    <string>4141</string>',
+        '  </array>',
+      ].join('\n'),
+    )
+    expect(plist).toContain(`<string>${path.join(dir, 'service.log')}</string>`)
+    expect(plist).toContain('<key>KeepAlive</key>\n  <true/>')
+  })
+
+  test('service status reports a service that is not installed', async () => {
+    const originalHome = process.env.HOME
+    process.env.HOME = dir
+    try {
+      await runCommand(service, { rawArgs: ['status'] })
+      expect(process.exitCode).toBe(0)
+    } finally {
+      process.env.HOME = originalHome
+    }
+  })
+})
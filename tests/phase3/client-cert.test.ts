@@ -0,0 +1,99 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { apiKeyOf } from '../../src/lib/api-key'
+import {
+  clientCertificate,
+  loadCertificateIdentities,
+  peerCommonName,
+  tlsServeOptions,
+} from '../../src/lib/client-cert'
+import { state } from '../../src/lib/state'
+
+// A request as srvx hands it over under Node, over a TLS socket
+function tlsRequest(cn: string | undefined, authorized = true, headers: HeadersInit = {}) {
+  const socket = {
+    authorized,
+    getPeerCertificate: () => (cn === undefined ? {} : { subject: { CN: cn } }),
+  }
+  return Object.assign(new Request('https://localhost/', { headers }), {
+    runtime: { node: { req: { socket } } },
+  })
+}
+
+function createApp() {
+  const app = new Hono()
+  app.use(clientCertificate)
+  app.get('/', (c) => c.json({ key: apiKeyOf(c) ?? null }))
+  return app
+}
+
+describe('Phase 3: Client Certificates', () => {
+  afterEach(() => {
+    state.clientCertificates = undefined
+  })
+
+  test('should read the CN of verified certificates only', () => {
+    expect(peerCommonName(tlsRequest('build-bot'))).toBe('build-bot')
+    expect(peerCommonName(tlsRequest('build-bot', false))).toBeUndefined()
+    expect(peerCommonName(new Request('http://localhost/'))).toBeUndefined()
+  })
+
+  test('should attribute requests to the mapped API key', async () => {
+    state.clientCertificates = { identities: { 'build-bot': 'sk-ci' } }
+    const app = createApp()
+
+    const mapped = await app.fetch(tlsRequest('build-bot', true, { authorization: 'Bearer sk-other' }))
+    expect(await mapped.json()).toEqual({ key: 'sk-ci' })
+
+    const unmapped = await app.fetch(tlsRequest('reporting'))
+    expect(await unmapped.json()).toEqual({ key: 'cert:reporting' })
+  })
+
+  test('should not map CNs that name inherited properties', async () => {
+    state.clientCertificates = { identities: { 'build-bot': 'sk-ci' } }
+    const app = createApp()
+
+    for (const cn of ['constructor', '__proto__', 'toString']) {
+      const response = await app.fetch(tlsRequest(cn))
+      expect(await response.json()).toEqual({ key: `cert:${cn}` })
+    }
+  })
+
+  test('should fall back to headers without a certificate or outside mutual TLS', async () => {
+    state.clientCertificates = { identities: {} }
+    const withoutCert = await createApp().fetch(
+      new Request('http://localhost/', { headers: { authorization: 'Bearer sk-plain' } }),
+    )
+    expect(await withoutCert.json()).toEqual({ key: 'sk-plain' })
+
+    state.clientCertificates = undefined
+    const disabled = await createApp().fetch(tlsRequest('build-bot', true, { authorization: 'Bearer sk-plain' }))
+    expect(await disabled.json()).toEqual({ key: 'sk-plain' })
+  })
+
+  test('should build HTTPS and mutual TLS serve options', async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-tls-'))
+    const file = async (name: string, content: string) => {
+      await fs.writeFile(path.join(dir, name), content)
+      return path.join(dir, name)
+    }
+    const cert = await file('cert.pem', 'CERT')
+    const key = await file('key.pem', 'KEY')
+    const clientCa = await file('ca.pem', 'CA')
+
+    expect(await tlsServeOptions({ cert, key })).toEqual({ tls: { cert: 'CERT', key: 'KEY' } })
+
+    const mutual = { ca: 'CA', requestCert: true, rejectUnauthorized: true }
+    expect(await tlsServeOptions({ cert, key, clientCa })).toEqual({
+      tls: { cert: 'CERT', key: 'KEY' },
+      node: mutual,
+      bunTls: { cert: 'CERT', key: 'KEY', ...mutual },
+    })
+
+    const identities = await file('identities.json', JSON.stringify({ 'build-bot': 'sk-ci' }))
+    expect(await loadCertificateIdentities(identities)).toEqual({ 'build-bot': 'sk-ci' })
+  })
+})
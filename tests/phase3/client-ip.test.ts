@@ -0,0 +1,65 @@
+import { test, expect, describe } from 'bun:test'
+import {
+  forwardedChain,
+  isTrustedProxy,
+  parseAddress,
+  parseTrustedProxies,
+  resolveClientIp,
+} from '../../src/lib/client-ip'
+
+describe('Phase 3: Client IP Behind Proxies', () => {
+  const proxies = parseTrustedProxies('10.0.0.0/8, 127.0.0.1, ::1, fd00::/8')
+
+  test('should parse IPv4, IPv6 and mapped addresses', () => {
+    expect(parseAddress('192.0.2.1')).toEqual({ v6: false, value: 0xc0000201n })
+    expect(parseAddress('::1')).toEqual({ v6: true, value: 1n })
+    expect(parseAddress('[2001:db8::1]')?.v6).toBe(true)
+    expect(parseAddress('::ffff:192.0.2.1')).toEqual({ v6: false, value: 0xc0000201n })
+    expect(parseAddress('256.0.0.1')).toBeUndefined()
+    expect(parseAddress('1::2::3')).toBeUndefined()
+    expect(parseAddress('unknown')).toBeUndefined()
+  })
+
+  test('should reject invalid proxy entries', () => {
+    expect(() => parseTrustedProxies('10.0.0.0/33')).toThrow(TypeError)
+    expect(() => parseTrustedProxies('10.0.0.0/')).toThrow(TypeError)
+    expect(() => parseTrustedProxies('proxy.local')).toThrow(TypeError)
+  })
+
+  test('should match addresses against ranges', () => {
+    expect(isTrustedProxy('10.1.2.3', proxies)).toBe(true)
+    expect(isTrustedProxy('::ffff:127.0.0.1', proxies)).toBe(true)
+    expect(isTrustedProxy('fd12:3456::1', proxies)).toBe(true)
+    expect(isTrustedProxy('11.0.0.1', proxies)).toBe(false)
+    expect(isTrustedProxy('::2', proxies)).toBe(false)
+  })
+
+  test('should read Forwarded before X-Forwarded-For', () => {
+    const headers = new Headers({
+      forwarded: 'for=192.0.2.60;proto=http, for="[2001:db8::1]:4711"',
+      'x-forwarded-for': '198.51.100.1',
+    })
+    expect(forwardedChain(headers)).toEqual(['192.0.2.60', '2001:db8::1'])
+    expect(
+      forwardedChain(new Headers({ 'x-forwarded-for': '198.51.100.1:80, 10.0.0.2' })),
+    ).toEqual(['198.51.100.1', '10.0.0.2'])
+  })
+
+  test('should ignore forwarding headers from untrusted peers', () => {
+    const headers = new Headers({ 'x-forwarded-for': '1.2.3.4' })
+    expect(resolveClientIp('203.0.113.9', headers, proxies)).toBe('203.0.113.9')
+  })
+
+  test('should skip trusted hops from the right', () => {
+    const headers = new Headers({
+      'x-forwarded-for': '6.6.6.6, 198.51.100.7, 10.0.0.2',
+    })
+    // 6.6.6.6 was sent by the client itself and is not believed
+    expect(resolveClientIp('127.0.0.1', headers, proxies)).toBe('198.51.100.7')
+  })
+
+  test('should fall back to the peer without forwarding headers', () => {
+    expect(resolveClientIp('127.0.0.1', new Headers(), proxies)).toBe('127.0.0.1')
+    expect(resolveClientIp(undefined, new Headers(), proxies)).toBeUndefined()
+  })
+})
@@ -0,0 +1,98 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { state } from '../../src/lib/state'
+import { translateToAnthropic } from '../../src/routes/messages/non-stream-translation'
+import {
+  createAnthropicStreamState,
+  translateChunkToAnthropicEvents,
+} from '../../src/routes/messages/stream-translation'
+import { expectGolden } from '../testkit/golden'
+import {
+  chatCompletion,
+  FAKE_ID,
+  fakeUpstream,
+  parseSseData,
+  streamScript,
+  upstreamError,
+  usage,
+  type FakeUpstream,
+} from '../testkit/upstream'
+
+const weatherCall = { id: 'call_1', name: 'get_weather', arguments: ['{"city":', '"Paris"}'] }
+
+const textResponse = () => chatCompletion({ content: 'Hello! How can I help?', usage: usage(12, 6) })
+const toolUseResponse = () => chatCompletion({ toolCalls: [weatherCall], usage: usage(20, 9) })
+const textThenToolStream = () =>
+  streamScript({ text: ['Let me check.'], toolCalls: [weatherCall], usage: usage(20, 9) })
+
+const postMessages = (body: object) =>
+  server.request('/v1/messages', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ model: 'gpt-4o', max_tokens: 100, messages: [{ role: 'user', content: 'Weather in Paris?' }], ...body }),
+  })
+
+describe('Phase 3: Compatibility Golden Files', () => {
+  const originalToken = state.copilotToken
+  let upstream: FakeUpstream | undefined
+
+  beforeEach(() => {
+    state.copilotToken = 'test-copilot-token'
+  })
+
+  afterEach(() => {
+    upstream?.restore()
+    upstream = undefined
+    state.copilotToken = originalToken
+  })
+
+  test('should translate a text completion to an Anthropic message', () => {
+    expectGolden('anthropic/text-response', translateToAnthropic(textResponse()))
+  })
+
+  test('should translate tool calls to tool_use blocks', () => {
+    expectGolden('anthropic/tool-use-response', translateToAnthropic(toolUseResponse()))
+  })
+
+  test('should translate a streamed text and tool call', () => {
+    const streamState = createAnthropicStreamState()
+    const events = textThenToolStream().flatMap((chunk) => translateChunkToAnthropicEvents(chunk, streamState))
+    expectGolden('anthropic/text-then-tool-stream', events)
+  })
+
+  test('should answer /v1/messages like the translation', async () => {
+    upstream = fakeUpstream([toolUseResponse()])
+    const response = await postMessages({})
+
+    expect(response.status).toBe(200)
+    expectGolden('anthropic/tool-use-response', await response.json())
+    expect(upstream.requests).toHaveLength(1)
+    expect(upstream.requests[0].messages.at(-1)).toMatchObject({ role: 'user' })
+  })
+
+  test('should stream /v1/messages like the translation', async () => {
+    upstream = fakeUpstream([textThenToolStream()])
+    const response = await postMessages({ stream: true })
+
+    expect(response.headers.get('content-type')).toContain('text/event-stream')
+    expectGolden('anthropic/text-then-tool-stream', parseSseData(await response.text()))
+    expect(upstream.requests[0].stream).toBe(true)
+  })
+
+  test('should pass upstream errors through to OpenAI clients', async () => {
+    upstream = fakeUpstream([upstreamError(400, 'context length exceeded')])
+    const response = await server.request('/v1/chat/completions', {
+      method: 'POST',
+      headers: { 'content-type': 'application/json' },
+      body: JSON.stringify({ model: 'gpt-4o', messages: [{ role: 'user', content: 'hi' }] }),
+    })
+
+    expect(response.status).toBe(400)
+    expect(await response.json()).toEqual({ error: { message: 'context length exceeded', type: 'error' } })
+  })
+
+  test('should build deterministic upstream responses', () => {
+    expect(textResponse().id).toBe(FAKE_ID)
+    expect(textThenToolStream().map((chunk) => chunk.choices[0].finish_reason).at(-1)).toBe('tool_calls')
+  })
+})
@@ -0,0 +1,49 @@
+import { test, expect, describe, beforeAll } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { EnvConfigError, loadEnvConfig, resolveConfigValue } from '../../src/lib/env-config'
+
+let secretsDir: string
+
+describe('Phase 3: Config References', () => {
+  beforeAll(async () => {
+    secretsDir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-secrets-'))
+    await fs.writeFile(path.join(secretsDir, 'github_token'), 'ghu_from_file\n')
+  })
+
+  test('should expand environment variables', () => {
+    const env = { HOST_NAME: 'gateway', PORT: '4141' }
+    expect(resolveConfigValue('${HOST_NAME}:${PORT}', env)).toBe('gateway:4141')
+    expect(resolveConfigValue('$${HOST_NAME}', env)).toBe('${HOST_NAME}')
+    expect(() => resolveConfigValue('${MISSING}', env)).toThrow('references unset variable MISSING')
+  })
+
+  test('should read file references after expansion', () => {
+    expect(resolveConfigValue(`file:${secretsDir}/github_token`, {})).toBe('ghu_from_file')
+    expect(resolveConfigValue('file:${DIR}/github_token', { DIR: secretsDir })).toBe('ghu_from_file')
+    expect(() => resolveConfigValue('file:/nonexistent/secret', {})).toThrow('cannot read')
+  })
+
+  test('should resolve references in gateway variables', () => {
+    const config = loadEnvConfig({
+      COPILOT_GATEWAY_GITHUB_TOKEN: `file:${secretsDir}/github_token`,
+      COPILOT_GATEWAY_PORT: '${PORT}',
+      PORT: '8080',
+    })
+    expect(config.githubToken).toBe('ghu_from_file')
+    expect(config.port).toBe(8080)
+  })
+
+  test('should report unresolved references as problems', () => {
+    let error: unknown
+    try {
+      loadEnvConfig({ COPILOT_GATEWAY_GITHUB_TOKEN: 'file:/nonexistent/secret' })
+    } catch (caught) {
+      error = caught
+    }
+    expect(error).toBeInstanceOf(EnvConfigError)
+    expect((error as EnvConfigError).problems).toHaveLength(1)
+    expect((error as EnvConfigError).problems[0]).toStartWith('COPILOT_GATEWAY_GITHUB_TOKEN: cannot read')
+  })
+})
@@ -0,0 +1,76 @@
+import { test, expect, describe } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+const classify = async (payload: Record<string, unknown>) => {
+  const result = await rustCore.validatePayloadDetailed({ model: 'gpt-4.1', ...payload })
+  expect(result.valid).toBe(true)
+  return result.contentType
+}
+
+const image = { type: 'image_url', image_url: { url: 'data:image/png;base64,AAAA' } }
+const tools = [{ type: 'function', function: { name: 'lookup', parameters: {} } }]
+
+describe('Phase 3: Content Classification', () => {
+  test('should classify OpenAI payloads', async () => {
+    expect(await classify({ messages: [{ role: 'user', content: 'Hi' }] })).toBe('text')
+    expect(await classify({ messages: [{ role: 'user', content: [image] }] })).toBe('vision')
+    expect(await classify({ messages: [{ role: 'user', content: 'Hi' }], tools })).toBe('tools')
+    expect(await classify({ messages: [{ role: 'user', content: [image] }], tools })).toBe('vision+tools')
+    expect(await classify({ messages: [{ role: 'user', content: 'Hi' }], reasoning_effort: 'high' })).toBe('reasoning')
+    expect(
+      await classify({
+        messages: [{ role: 'user', content: [{ type: 'input_audio', input_audio: { data: 'AAAA', format: 'wav' } }] }],
+      }),
+    ).toBe('audio')
+    expect(await classify({ messages: [{ role: 'user', content: 'Hi' }], modalities: ['text', 'audio'] })).toBe('audio')
+  })
+
+  test('should see tool use in the conversation without a tools list', async () => {
+    const contentType = await classify({
+      messages: [
+        { role: 'user', content: 'Hi' },
+        { role: 'assistant', content: null, tool_calls: [{ id: 'call_a', type: 'function', function: { name: 'lookup', arguments: '{}' } }] },
+        { role: 'tool', tool_call_id: 'call_a', content: '1' },
+      ],
+    })
+    expect(contentType).toBe('tools')
+  })
+
+  test('should ignore empty or null flags', async () => {
+    expect(await classify({ messages: [{ role: 'user', content: 'Hi' }], tools: [], reasoning_effort: null })).toBe('text')
+  })
+
+  test('should classify Anthropic payloads', async () => {
+    const result = await rustCore.validatePayloadDetailed({
+      model: 'claude-sonnet-4',
+      max_tokens: 1024,
+      thinking: { type: 'enabled', budget_tokens: 512 },
+      tools: [{ name: 'lookup', input_schema: { type: 'object' } }],
+      messages: [
+        { role: 'user', content: [{ type: 'image', source: { type: 'base64', media_type: 'image/png', data: 'AAAA' } }] },
+        { role: 'assistant', content: [{ type: 'tool_use', id: 'toolu_a', name: 'lookup', input: {} }] },
+        { role: 'user', content: [{ type: 'tool_result', tool_use_id: 'toolu_a', content: '1' }] },
+      ],
+    })
+
+    expect(result.valid).toBe(true)
+    expect(result.contentType).toBe('vision+tools+reasoning')
+  })
+
+  test('should keep classifying payloads only Anthropic accepts as anthropic', async () => {
+    // No model, which OpenAI requires
+    const plain = await rustCore.validatePayloadDetailed({
+      max_tokens: 1024,
+      messages: [{ role: 'user', content: 'Hello' }],
+    })
+    const withImage = await rustCore.validatePayloadDetailed({
+      max_tokens: 1024,
+      messages: [
+        { role: 'user', content: [{ type: 'image', source: { type: 'base64', media_type: 'image/png', data: 'AAAA' } }] },
+      ],
+    })
+
+    expect(plain).toEqual({ valid: true, contentType: 'anthropic' })
+    expect(withImage.contentType).toBe('anthropic+vision')
+  })
+})
@@ -0,0 +1,89 @@
+import { test, expect, describe } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import {
+  applyRequestFilters,
+  applyResponseFilters,
+  loadContentPolicy,
+  type PolicyContext,
+} from '../../src/lib/content-policy'
+import { HTTPError } from '../../src/lib/error'
+import type {
+  ChatCompletionResponse,
+  ChatCompletionsPayload,
+} from '../../src/services/copilot/create-chat-completions'
+
+async function writePolicy(policy: object): Promise<string> {
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-policy-'))
+  const file = path.join(dir, 'policy.json')
+  await fs.writeFile(file, JSON.stringify(policy))
+  return file
+}
+
+const payload = (content: ChatCompletionsPayload['messages'][number]['content']): ChatCompletionsPayload => ({
+  model: 'gpt-4o',
+  messages: [{ role: 'user', content }],
+})
+
+describe('Phase 3: Content Policy', () => {
+  test('should block requests matching a forbidden pattern', async () => {
+    const filters = await loadContentPolicy(await writePolicy({
+      filters: [{ type: 'forbidden-patterns', patterns: ['project\\s+falcon'], message: 'Nope' }],
+    }))
+    const context: PolicyContext = { annotations: [] }
+
+    expect(() => applyRequestFilters(filters, payload('Tell me about Project  Falcon'), context))
+      .toThrow(HTTPError)
+    expect(() => applyRequestFilters(filters, payload([{ type: 'text', text: 'project falcon' }]), context))
+      .toThrow('Nope')
+
+    const allowed = payload('Tell me about falcons')
+    expect(applyRequestFilters(filters, allowed, context)).toBe(allowed)
+  })
+
+  test('should flag matching responses instead of blocking them', async () => {
+    const filters = await loadContentPolicy(await writePolicy({
+      filters: [{ type: 'forbidden-patterns', patterns: ['secret'] }],
+    }))
+    const context: PolicyContext = { annotations: [] }
+    const response = {
+      choices: [{ index: 0, message: { role: 'assistant', content: 'The secret is out' }, finish_reason: 'stop', logprobs: null }],
+    } as unknown as ChatCompletionResponse
+
+    expect(applyResponseFilters(filters, response, context)).toBe(response)
+    expect(context.annotations).toEqual(['response-matched-forbidden-pattern'])
+  })
+
+  test('should strip oversized data URLs and annotate', async () => {
+    const filters = await loadContentPolicy(await writePolicy({
+      filters: [
+        { type: 'max-data-url', maxBytes: 100 },
+        { type: 'annotate', note: 'policy=v1' },
+      ],
+    }))
+    const context: PolicyContext = { annotations: [] }
+    const small = `data:image/png;base64,${'A'.repeat(40)}`
+    const large = `data:image/png;base64,${'A'.repeat(400)}`
+
+    const filtered = applyRequestFilters(filters, payload([
+      { type: 'text', text: 'What is this?' },
+      { type: 'image_url', image_url: { url: small } },
+      { type: 'image_url', image_url: { url: large } },
+      { type: 'image_url', image_url: { url: 'https://example.com/cat.png' } },
+    ]), context)
+
+    expect(filtered.messages[0].content).toEqual([
+      { type: 'text', text: 'What is this?' },
+      { type: 'image_url', image_url: { url: small } },
+      { type: 'text', text: '[image removed: larger than 100 bytes]' },
+      { type: 'image_url', image_url: { url: 'https://example.com/cat.png' } },
+    ])
+    expect(context.annotations).toEqual(['stripped-images=1', 'policy=v1'])
+  })
+
+  test('should reject unknown filter types', async () => {
+    const file = await writePolicy({ filters: [{ type: 'profanity' }] })
+    await expect(loadContentPolicy(file)).rejects.toThrow('Unknown content filter type: profanity')
+  })
+})
@@ -0,0 +1,142 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { checkContextWindow } from '../../src/lib/context-window'
+import { HTTPError } from '../../src/lib/error'
+import { state } from '../../src/lib/state'
+import type { ModelsResponse } from '../../src/services/copilot/get-models'
+import { chatCompletion, fakeUpstream, type FakeUpstream } from '../testkit/upstream'
+
+const models = {
+  object: 'list',
+  data: [
+    {
+      id: 'gpt-4o',
+      capabilities: {
+        limits: { max_context_window_tokens: 1000, max_prompt_tokens: 900, max_output_tokens: 400 },
+      },
+    },
+    { id: 'no-limits', capabilities: { limits: {} } },
+  ],
+} as unknown as ModelsResponse
+
+async function rejection(run: () => unknown) {
+  try {
+    run()
+  } catch (error) {
+    expect(error).toBeInstanceOf(HTTPError)
+    const response = (error as HTTPError).response
+    return { status: response.status, body: (await response.json()) as { error: Record<string, unknown> } }
+  }
+  throw new Error('Expected a rejection')
+}
+
+describe('Phase 3: Context Window Check', () => {
+  const originalModels = state.models
+  const originalToken = state.copilotToken
+  let upstream: FakeUpstream | undefined
+
+  beforeEach(() => {
+    state.models = models
+    state.contextCheck = true
+    state.copilotToken = 'test-copilot-token'
+  })
+
+  afterEach(() => {
+    upstream?.restore()
+    upstream = undefined
+    state.models = originalModels
+    state.contextCheck = undefined
+    state.copilotToken = originalToken
+  })
+
+  test('should return the largest usable max_tokens', () => {
+    expect(checkContextWindow('gpt-4o', 500, 300)).toBe(400)
+    expect(checkContextWindow('gpt-4o', 800, 200)).toBe(200)
+    expect(checkContextWindow('gpt-4o', 800, undefined)).toBe(200)
+  })
+
+  test('should reject max_tokens beyond the context window with the counts', async () => {
+    const { status, body } = await rejection(() => checkContextWindow('gpt-4o', 800, 300))
+    expect(status).toBe(400)
+    expect(body.error).toMatchObject({
+      type: 'invalid_request_error',
+      param: 'max_tokens',
+      code: 'context_length_exceeded',
+      prompt_tokens: 800,
+      max_tokens: 300,
+      context_window: 1000,
+      max_usable_max_tokens: 200,
+    })
+    expect(body.error.message).toContain('Set max_tokens to 200 or less')
+  })
+
+  test('should reject a prompt over the model limit on its own', async () => {
+    const { body } = await rejection(() => checkContextWindow('gpt-4o', 950, undefined))
+    expect(body.error).toMatchObject({ param: 'messages', max_prompt_tokens: 900 })
+  })
+
+  test('should skip models without limits and when turned off', () => {
+    expect(checkContextWindow('no-limits', 1_000_000, 1_000_000)).toBeUndefined()
+    expect(checkContextWindow('unknown', 1_000_000, 1_000_000)).toBeUndefined()
+    state.contextCheck = undefined
+    expect(checkContextWindow('gpt-4o', 1_000_000, 1_000_000)).toBeUndefined()
+  })
+
+  test('should answer before sending anything upstream', async () => {
+    upstream = fakeUpstream([])
+    const response = await server.request('/v1/chat/completions', {
+      method: 'POST',
+      headers: { 'content-type': 'application/json' },
+      body: JSON.stringify({
+        model: 'gpt-4o',
+        max_tokens: 400,
+        messages: [{ role: 'user', content: 'word '.repeat(700) }],
+      }),
+    })
+
+    expect(response.status).toBe(400)
+    expect((await response.json()).error.code).toBe('context_length_exceeded')
+    expect(upstream.requests).toHaveLength(0)
+  })
+
+  test('should count tool results in the prompt', async () => {
+    upstream = fakeUpstream([])
+    const response = await server.request('/v1/chat/completions', {
+      method: 'POST',
+      headers: { 'content-type': 'application/json' },
+      body: JSON.stringify({
+        model: 'gpt-4o',
+        max_tokens: 100,
+        messages: [
+          { role: 'user', content: 'Read the log file' },
+          {
+            role: 'assistant',
+            content: null,
+            tool_calls: [{ id: 'call_1', type: 'function', function: { name: 'read_file', arguments: '{}' } }],
+          },
+          { role: 'tool', tool_call_id: 'call_1', content: 'word '.repeat(1000) },
+        ],
+      }),
+    })
+
+    expect(response.status).toBe(400)
+    const body = (await response.json()) as { error: { code: string; param: string; prompt_tokens: number } }
+    expect(body.error).toMatchObject({ code: 'context_length_exceeded', param: 'messages' })
+    expect(body.error.prompt_tokens).toBeGreaterThan(1000)
+    expect(upstream.requests).toHaveLength(0)
+  })
+
+  test('should lower the default max_tokens to what fits', async () => {
+    upstream = fakeUpstream([chatCompletion({ content: 'ok' })])
+    const response = await server.request('/v1/chat/completions', {
+      method: 'POST',
+      headers: { 'content-type': 'application/json' },
+      body: JSON.stringify({ model: 'gpt-4o', messages: [{ role: 'user', content: 'word '.repeat(700) }] }),
+    })
+
+    expect(response.status).toBe(200)
+    const maxTokens = upstream.requests[0].max_tokens as number
+    expect(maxTokens).toBeGreaterThan(0)
+    expect(maxTokens).toBeLessThan(400)
+  })
+})
@@ -0,0 +1,81 @@
+import { test, expect, describe } from 'bun:test'
+import { checkRateLimit } from '../../src/lib/rate-limit'
+import { HTTPError } from '../../src/lib/error'
+import type { DistributedRateLimiter } from '../../src/lib/rate-limit-redis'
+import type { State } from '../../src/lib/state'
+
+function makeState(rateLimitStore: DistributedRateLimiter, rateLimitWait = false): State {
+  return {
+    accountType: 'individual',
+    manualApprove: false,
+    rateLimitWait,
+    showToken: false,
+    swaggerUi: false,
+    rateLimitSeconds: 10,
+    rateLimitStore,
+  }
+}
+
+describe('Phase 3: Distributed Rate Limiting', () => {
+  test('should proceed when the shared store grants the slot', async () => {
+    const intervals: Array<number> = []
+    const state = makeState({
+      claim: async (intervalMs) => {
+        intervals.push(intervalMs)
+        return 0
+      },
+    })
+
+    await checkRateLimit(state)
+    await checkRateLimit(state)
+
+    expect(intervals).toEqual([10000, 10000])
+    // The local limiter is bypassed while the store answers
+    expect(state.lastRequestTimestamp).toBeUndefined()
+  })
+
+  test('should reject with 429 when another replica holds the slot', async () => {
+    const state = makeState({ claim: async () => 4200 })
+
+    let error: unknown
+    try {
+      await checkRateLimit(state)
+    } catch (e) {
+      error = e
+    }
+
+    expect(error).toBeInstanceOf(HTTPError)
+    expect((error as HTTPError).response.status).toBe(429)
+  })
+
+  test('should wait and claim again when waiting is enabled', async () => {
+    const waits = [20, 0]
+    let claims = 0
+    const state = makeState(
+      {
+        claim: async () => {
+          claims++
+          return waits.shift() ?? 0
+        },
+      },
+      true,
+    )
+
+    await checkRateLimit(state)
+    expect(claims).toBe(2)
+  })
+
+  test('should fall back to the local limit when the store fails', async () => {
+    const state = makeState({
+      claim: async () => {
+        throw new Error('connection refused')
+      },
+    })
+
+    await checkRateLimit(state)
+    expect(state.lastRequestTimestamp).toBeDefined()
+
+    // The local limit now applies to the next request
+    await expect(checkRateLimit(state)).rejects.toBeInstanceOf(HTTPError)
+  })
+})
@@ -0,0 +1,56 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { state } from '../../src/lib/state'
+
+const dryRun = (body: object) =>
+  server.request('/v1/chat/completions?dry_run=true', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify(body),
+  })
+
+interface DryRun {
+  dry_run: boolean
+  upstream: { method: string; url: string; headers: Record<string, string>; body: Record<string, unknown> }
+  token_count: { input: number }
+}
+
+describe('Phase 3: Chat Completions Dry Run', () => {
+  afterEach(() => {
+    state.modelAliases = undefined
+    state.copilotToken = undefined
+  })
+
+  test('should return the upstream request without sending it', async () => {
+    state.copilotToken = 'secret-copilot-token'
+    state.modelAliases = { fast: 'gpt-4o-mini' }
+
+    const response = await dryRun({
+      model: 'fast',
+      messages: [{ role: 'user', content: 'hello there' }],
+    })
+    expect(response.status).toBe(200)
+
+    const body = await response.json() as DryRun
+    expect(body.dry_run).toBe(true)
+    expect(body.upstream.method).toBe('POST')
+    expect(body.upstream.url).toEndWith('/chat/completions')
+    expect(body.upstream.body.model).toBe('gpt-4o-mini')
+    expect(body.upstream.headers.Authorization).toBe('Bearer [REDACTED]')
+    expect(JSON.stringify(body)).not.toContain('secret-copilot-token')
+    expect(body.token_count.input).toBeGreaterThan(0)
+  })
+
+  test('should flag image requests for vision like a real request', async () => {
+    const response = await dryRun({
+      model: 'gpt-4o',
+      messages: [{
+        role: 'user',
+        content: [{ type: 'image_url', image_url: { url: 'data:image/png;base64,AAAA' } }],
+      }],
+    })
+
+    const body = await response.json() as DryRun
+    expect(body.upstream.headers['copilot-vision-request']).toBe('true')
+  })
+})
@@ -0,0 +1,45 @@
+import { test, expect, describe } from 'bun:test'
+import { server } from '../../src/server'
+import { applyDimensions, reduceDimensions } from '../../src/routes/embeddings/dimensions'
+
+describe('Phase 3: Embedding Dimension Reduction', () => {
+  test('should truncate and re-normalize to unit length', () => {
+    const reduced = reduceDimensions([3, 4, 12], 2)
+    expect(reduced).toEqual([0.6, 0.8])
+    expect(Math.hypot(...reduced)).toBeCloseTo(1)
+  })
+
+  test('should leave vectors that are already short enough unchanged', () => {
+    const embedding = [0.1, 0.2]
+    expect(reduceDimensions(embedding, 2)).toBe(embedding)
+    expect(reduceDimensions(embedding, 8)).toBe(embedding)
+  })
+
+  test('should not divide by zero for all-zero vectors', () => {
+    expect(reduceDimensions([0, 0, 0], 2)).toEqual([0, 0])
+  })
+
+  test('should apply to every vector in the response', () => {
+    const response = {
+      object: 'list',
+      model: 'text-embedding-3-small',
+      usage: { prompt_tokens: 2, total_tokens: 2 },
+      data: [
+        { object: 'embedding', index: 0, embedding: [1, 0, 5] },
+        { object: 'embedding', index: 1, embedding: [0, 2, 5] },
+      ],
+    }
+
+    expect(applyDimensions(response, undefined)).toBe(response)
+    expect(applyDimensions(response, 2).data.map((item) => item.embedding)).toEqual([[1, 0], [0, 1]])
+  })
+
+  test('should reject invalid dimensions before calling upstream', async () => {
+    const response = await server.request('/v1/embeddings', {
+      method: 'POST',
+      headers: { 'content-type': 'application/json' },
+      body: JSON.stringify({ model: 'text-embedding-3-small', input: 'hi', dimensions: 0 }),
+    })
+    expect(response.status).toBe(400)
+  })
+})
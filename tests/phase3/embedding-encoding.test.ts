@@ -0,0 +1,89 @@
+import { test, expect, describe, afterEach, mock } from 'bun:test'
+import { server } from '../../src/server'
+import { applyEncodingFormat, encodeBase64 } from '../../src/routes/embeddings/encoding'
+import { state } from '../../src/lib/state'
+
+const decode = (encoded: string) => {
+  const bytes = Buffer.from(encoded, 'base64')
+  return Array.from(new Float32Array(bytes.buffer, bytes.byteOffset, bytes.length / 4))
+}
+
+const upstreamResponse = () => ({
+  object: 'list',
+  model: 'text-embedding-3-small',
+  usage: { prompt_tokens: 2, total_tokens: 2 },
+  data: [
+    { object: 'embedding', index: 0, embedding: [0.5, -0.25, 3, 0] },
+    { object: 'embedding', index: 1, embedding: [1, 2, 2, 0] },
+  ],
+})
+
+const postEmbeddings = (body: object) =>
+  server.request('/v1/embeddings', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ model: 'text-embedding-3-small', input: ['a', 'b'], ...body }),
+  })
+
+describe('Phase 3: Embedding Encoding Format', () => {
+  const originalFetch = globalThis.fetch
+  const originalToken = state.copilotToken
+
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    state.copilotToken = originalToken
+  })
+
+  const mockUpstream = () => {
+    const bodies: Array<Record<string, unknown>> = []
+    state.copilotToken = 'test-copilot-token'
+    globalThis.fetch = mock(async (_input: string | URL | Request, init?: RequestInit) => {
+      bodies.push(JSON.parse(String(init?.body)) as Record<string, unknown>)
+      return Response.json(upstreamResponse())
+    }) as unknown as typeof fetch
+    return bodies
+  }
+
+  test('should pack vectors as little-endian float32', () => {
+    expect(encodeBase64([1])).toBe('AACAPw==')
+    expect(decode(encodeBase64([0.5, -0.25, 3]))).toEqual([0.5, -0.25, 3])
+    expect(encodeBase64([])).toBe('')
+  })
+
+  test('should leave float responses unchanged', () => {
+    const response = upstreamResponse()
+    expect(applyEncodingFormat(response, undefined)).toBe(response)
+    expect(applyEncodingFormat(response, 'float')).toBe(response)
+  })
+
+  test('should encode locally and ask the upstream for floats', async () => {
+    const bodies = mockUpstream()
+    const response = await postEmbeddings({ encoding_format: 'base64' })
+
+    expect(response.status).toBe(200)
+    expect(bodies[0].encoding_format).toBeUndefined()
+    const json = (await response.json()) as { data: Array<{ index: number; embedding: string }> }
+    expect(json.data.map((item) => decode(item.embedding))).toEqual([
+      [0.5, -0.25, 3, 0],
+      [1, 2, 2, 0],
+    ])
+  })
+
+  test('should reduce dimensions before encoding', async () => {
+    mockUpstream()
+    const response = await postEmbeddings({ encoding_format: 'base64', dimensions: 3 })
+
+    const json = (await response.json()) as { data: Array<{ embedding: string }> }
+    const [, second] = json.data.map((item) => decode(item.embedding))
+    expect(second.length).toBe(3)
+    expect(second[0]).toBeCloseTo(1 / 3)
+  })
+
+  test('should reject unknown encoding formats before calling upstream', async () => {
+    const bodies = mockUpstream()
+    const response = await postEmbeddings({ encoding_format: 'int8' })
+
+    expect(response.status).toBe(400)
+    expect(bodies).toHaveLength(0)
+  })
+})
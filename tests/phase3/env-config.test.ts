@@ -0,0 +1,121 @@
+import { test, expect, describe, beforeAll } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { EnvConfigError, loadEnvConfig } from '../../src/lib/env-config'
+
+let tokenFile: string
+
+// The problems loadEnvConfig reports for `env`, or none when it loads
+function problemsOf(env: Record<string, string>): Array<string> {
+  try {
+    loadEnvConfig(env)
+    return []
+  } catch (error) {
+    expect(error).toBeInstanceOf(EnvConfigError)
+    return (error as EnvConfigError).problems
+  }
+}
+
+describe('Phase 3: Environment Configuration', () => {
+  beforeAll(async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-env-'))
+    tokenFile = path.join(dir, 'token')
+    await fs.writeFile(tokenFile, 'ghu_from_token_file\n')
+  })
+
+  test('should read a valid environment', () => {
+    const config = loadEnvConfig({
+      COPILOT_GATEWAY_PORT: '8080',
+      COPILOT_GATEWAY_HOST: ' 127.0.0.1 ',
+      COPILOT_GATEWAY_ACCOUNT_TYPE: 'business',
+      COPILOT_GATEWAY_RATE_LIMIT: '5',
+      COPILOT_GATEWAY_RATE_LIMIT_WAIT: 'yes',
+      COPILOT_GATEWAY_VERBOSE: 'off',
+      COPILOT_GATEWAY_MODEL_ALIASES: 'fast=gpt-4o-mini,smart=claude-sonnet-4',
+      COPILOT_GATEWAY_LOG_FORMAT: 'json',
+      COPILOT_GATEWAY_GITHUB_TOKEN: 'ghu_direct',
+    })
+
+    expect(config).toMatchObject({
+      port: 8080,
+      host: '127.0.0.1',
+      accountType: 'business',
+      rateLimit: 5,
+      rateLimitWait: true,
+      verbose: false,
+      modelAliases: { fast: 'gpt-4o-mini', smart: 'claude-sonnet-4' },
+      logFormat: 'json',
+      githubToken: 'ghu_direct',
+    })
+    expect(config.portRetry).toBeUndefined()
+    expect(config.tokenFile).toBeUndefined()
+  })
+
+  test('should treat unset and empty variables alike', () => {
+    expect(loadEnvConfig({ COPILOT_GATEWAY_PORT: '', COPILOT_GATEWAY_VERBOSE: '  ' })).toMatchObject({
+      port: undefined,
+      verbose: undefined,
+    })
+  })
+
+  test('should reject invalid integers', () => {
+    for (const raw of ['abc', '1.5', '65536', '-1']) {
+      expect(problemsOf({ COPILOT_GATEWAY_PORT: raw })).toEqual([
+        `COPILOT_GATEWAY_PORT: expected an integer between 0 and 65535, got "${raw}"`,
+      ])
+    }
+  })
+
+  test('should reject gRPC port 0 like --grpc-port', () => {
+    expect(loadEnvConfig({ COPILOT_GATEWAY_GRPC_PORT: '50051' }).grpcPort).toBe(50051)
+    expect(problemsOf({ COPILOT_GATEWAY_GRPC_PORT: '0' })).toEqual([
+      'COPILOT_GATEWAY_GRPC_PORT: expected an integer between 1 and 65535, got "0"',
+    ])
+  })
+
+  test('should reject invalid booleans', () => {
+    expect(problemsOf({ COPILOT_GATEWAY_VERBOSE: 'maybe' })).toEqual([
+      'COPILOT_GATEWAY_VERBOSE: expected true or false, got "maybe"',
+    ])
+  })
+
+  test('should reject values outside an enum', () => {
+    expect(problemsOf({ COPILOT_GATEWAY_ACCOUNT_TYPE: 'personal' })).toEqual([
+      'COPILOT_GATEWAY_ACCOUNT_TYPE: expected one of individual, business, enterprise, got "personal"',
+    ])
+  })
+
+  test('should report every problem at once', () => {
+    expect(
+      problemsOf({
+        COPILOT_GATEWAY_PORT: 'abc',
+        COPILOT_GATEWAY_VERBOSE: 'maybe',
+        COPILOT_GATEWAY_LOG_FORMAT: 'xml',
+      }),
+    ).toHaveLength(3)
+  })
+
+  test('should read the token from TOKEN_FILE and keep its path', () => {
+    const config = loadEnvConfig({ COPILOT_GATEWAY_TOKEN_FILE: tokenFile })
+    expect(config.githubToken).toBe('ghu_from_token_file')
+    expect(config.tokenFile).toBe(tokenFile)
+
+    expect(problemsOf({ COPILOT_GATEWAY_TOKEN_FILE: '/nonexistent/token' })[0]).toStartWith(
+      'COPILOT_GATEWAY_TOKEN_FILE: cannot read "/nonexistent/token"',
+    )
+    expect(
+      problemsOf({ COPILOT_GATEWAY_TOKEN_FILE: tokenFile, COPILOT_GATEWAY_GITHUB_TOKEN: 'ghu_direct' }),
+    ).toEqual(['COPILOT_GATEWAY_GITHUB_TOKEN and COPILOT_GATEWAY_TOKEN_FILE are mutually exclusive'])
+  })
+
+  test('should fall back to GH_TOKEN', () => {
+    expect(loadEnvConfig({ GH_TOKEN: ' ghu_legacy ' }).githubToken).toBe('ghu_legacy')
+    expect(loadEnvConfig({ GH_TOKEN: 'ghu_legacy', COPILOT_GATEWAY_GITHUB_TOKEN: 'ghu_direct' }).githubToken).toBe(
+      'ghu_direct',
+    )
+    expect(loadEnvConfig({ GH_TOKEN: 'ghu_legacy', COPILOT_GATEWAY_TOKEN_FILE: tokenFile }).githubToken).toBe(
+      'ghu_from_token_file',
+    )
+  })
+})
@@ -0,0 +1,99 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { keyId } from '../../src/lib/api-key'
+import { experimentOf, pickArm, routeExperiment, type Experiment } from '../../src/lib/experiments'
+import { PATHS } from '../../src/lib/paths'
+import { state } from '../../src/lib/state'
+import { readUsage, recordUsage } from '../../src/lib/usage-ledger'
+
+const originalUsageDir = PATHS.USAGE_DIR
+
+const experiment = (overrides: Partial<Experiment> = {}): Experiment => ({
+  name: 'mini-trial',
+  model: 'gpt-4.1',
+  alternate: 'gpt-4.1-mini',
+  percent: 20,
+  ...overrides,
+})
+
+function createApp() {
+  const app = new Hono()
+  app.post('/', async (c) => {
+    const { model } = await c.req.json<{ model: string }>()
+    const routed = routeExperiment(c, model)
+    await recordUsage(c, { endpoint: '/chat/completions', model: routed, usage: { prompt_tokens: 1 } })
+    return c.json({ model: routed, assignment: experimentOf(c.req.raw) ?? null })
+  })
+  return app
+}
+
+const send = (model: string, key = 'sk-a') =>
+  createApp().request('/', {
+    method: 'POST',
+    headers: { authorization: `Bearer ${key}` },
+    body: JSON.stringify({ model }),
+  })
+
+describe('Phase 3: Model Experiments', () => {
+  beforeEach(async () => {
+    PATHS.USAGE_DIR = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-experiments-'))
+  })
+
+  afterEach(() => {
+    PATHS.USAGE_DIR = originalUsageDir
+    state.experiments = undefined
+    state.recordUsage = undefined
+  })
+
+  test('should pick the alternate arm for the configured share', () => {
+    expect(pickArm(experiment(), undefined, () => 0.1)).toBe('alternate')
+    expect(pickArm(experiment(), undefined, () => 0.3)).toBe('control')
+    expect(pickArm(experiment({ percent: 0 }), undefined, () => 0)).toBe('control')
+    expect(pickArm(experiment({ percent: 100 }), undefined, () => 0.999)).toBe('alternate')
+  })
+
+  test('should keep each key on one arm when sticky', () => {
+    const sticky = experiment({ sticky: true, percent: 50 })
+    const keys = Array.from({ length: 200 }, (_, i) => `sk-${i}`)
+    const arms = keys.map((key) => pickArm(sticky, key, () => 0))
+    // Independent of the random source, and repeatable
+    expect(keys.map((key) => pickArm(sticky, key, () => 0.99))).toEqual(arms)
+    const alternates = arms.filter((arm) => arm === 'alternate').length
+    expect(alternates).toBeGreaterThan(60)
+    expect(alternates).toBeLessThan(140)
+  })
+
+  test('should route, tag the response and record the arm', async () => {
+    state.experiments = { experiments: [experiment({ percent: 100 })] }
+    state.recordUsage = true
+
+    const response = await send('gpt-4.1')
+    expect(response.headers.get('x-experiment')).toBe('mini-trial=alternate')
+    expect(await response.json()).toEqual({
+      model: 'gpt-4.1-mini',
+      assignment: { experiment: 'mini-trial', arm: 'alternate' },
+    })
+
+    const [record] = await readUsage({ key: keyId('sk-a') })
+    expect(record).toMatchObject({ model: 'gpt-4.1-mini', experiment: 'mini-trial', arm: 'alternate' })
+  })
+
+  test('should tag the control arm too', async () => {
+    state.experiments = { experiments: [experiment({ percent: 0 })] }
+
+    const response = await send('gpt-4.1')
+    expect(response.headers.get('x-experiment')).toBe('mini-trial=control')
+    expect(((await response.json()) as { model: string }).model).toBe('gpt-4.1')
+  })
+
+  test('should leave other models alone', async () => {
+    state.experiments = { experiments: [experiment({ percent: 100 })] }
+
+    const response = await send('claude-sonnet-4')
+    expect(response.headers.get('x-experiment')).toBeNull()
+    expect(await response.json()).toEqual({ model: 'claude-sonnet-4', assignment: null })
+  })
+})
@@ -0,0 +1,67 @@
+import { test, expect, describe } from 'bun:test'
+import { Hono } from 'hono'
+import { forwardError } from '../../src/lib/error'
+import { gatewayOptionsOf, upstreamSignal } from '../../src/lib/gateway-options'
+
+function createApp() {
+  const app = new Hono()
+  app.get('/', (c) => c.json(gatewayOptionsOf(c)))
+  app.get('/timeout', async (c) => {
+    const signal = upstreamSignal(gatewayOptionsOf(c))
+    await new Promise((_, reject) =>
+      signal?.addEventListener('abort', () => reject(signal.reason)),
+    )
+    return c.text('unreachable')
+  })
+  app.onError((error, c) => forwardError(c, error))
+  return app
+}
+
+describe('Phase 3: Gateway Options', () => {
+  test('should default to no overrides', async () => {
+    const response = await createApp().request('/')
+    expect(await response.json()).toEqual({ noCache: false, provider: 'copilot' })
+  })
+
+  test('should parse every override header', async () => {
+    const response = await createApp().request('/', {
+      headers: {
+        'x-gateway-model-override': 'gpt-4.1',
+        'x-gateway-no-cache': 'true',
+        'x-gateway-timeout-ms': '1500',
+        'x-gateway-provider': 'copilot',
+      },
+    })
+    expect(await response.json()).toEqual({
+      model: 'gpt-4.1',
+      noCache: true,
+      timeoutMs: 1500,
+      provider: 'copilot',
+    })
+  })
+
+  test('should reject invalid values and unknown headers', async () => {
+    const app = createApp()
+    for (const headers of [
+      { 'x-gateway-no-cache': 'maybe' },
+      { 'x-gateway-timeout-ms': '0' },
+      { 'x-gateway-timeout-ms': '1.5' },
+      { 'x-gateway-provider': 'azure' },
+      { 'x-gateway-no-cahce': 'true' },
+    ]) {
+      const response = await app.request('/', { headers })
+      expect(response.status).toBe(400)
+      const body = (await response.json()) as { error: { type: string } }
+      expect(body.error.type).toBe('invalid_request_error')
+    }
+  })
+
+  test('should answer 504 once the timeout has passed', async () => {
+    const response = await createApp().request('/timeout', {
+      headers: { 'x-gateway-timeout-ms': '20' },
+    })
+    expect(response.status).toBe(504)
+    const body = (await response.json()) as { error: { type: string } }
+    expect(body.error.type).toBe('timeout_error')
+  })
+})
@@ -0,0 +1,71 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import { stream } from 'hono/streaming'
+import { drain, inFlightRequests, rejectWhileDraining, stopDraining } from '../../src/lib/drain'
+
+function createApp() {
+  let release: () => void = () => {}
+  const released = new Promise<void>((resolve) => (release = resolve))
+  const app = new Hono()
+  app.use(rejectWhileDraining)
+  app.get('/', (c) => c.text('ok'))
+  app.get('/stream', (c) =>
+    stream(c, async (s) => {
+      await s.write('first ')
+      await released
+      await s.write('last')
+    }),
+  )
+  return { app, release }
+}
+
+describe('Phase 3: Graceful Shutdown', () => {
+  afterEach(() => {
+    stopDraining()
+  })
+
+  test('should serve requests until draining', async () => {
+    const { app } = createApp()
+    expect(await (await app.request('/')).text()).toBe('ok')
+    expect(inFlightRequests()).toBe(0)
+  })
+
+  test('should answer new requests with 503 and Retry-After while draining', async () => {
+    const { app } = createApp()
+    expect(await drain(1000)).toBe(true)
+
+    const response = await app.request('/')
+    expect(response.status).toBe(503)
+    expect(response.headers.get('retry-after')).toBe('1')
+    expect((await response.json()).error).toMatchObject({ type: 'error', code: 'server_draining' })
+  })
+
+  test('should wait for streams in flight to finish', async () => {
+    const { app, release } = createApp()
+    const response = await app.request('/stream')
+    expect(inFlightRequests()).toBe(1)
+
+    let drained = false
+    const draining = drain(5000).then((result) => (drained = result))
+    expect((await app.request('/')).status).toBe(503)
+    await Bun.sleep(10)
+    expect(drained).toBe(false)
+
+    release()
+    expect(await response.text()).toBe('first last')
+    expect(await draining).toBe(true)
+    expect(inFlightRequests()).toBe(0)
+  })
+
+  test('should give up on requests still running after the timeout', async () => {
+    const { app, release } = createApp()
+    const response = await app.request('/stream')
+
+    expect(await drain(20)).toBe(false)
+    expect(inFlightRequests()).toBe(1)
+
+    release()
+    await response.text()
+    expect(inFlightRequests()).toBe(0)
+  })
+})
@@ -0,0 +1,165 @@
+import { test, expect, describe, beforeAll, beforeEach, afterEach } from 'bun:test'
+import type * as Grpc from '@grpc/grpc-js'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { startGrpcServer } from '../../src/grpc'
+import { readAuditLog } from '../../src/lib/audit'
+import { createFilter } from '../../src/lib/content-policy'
+import { PATHS } from '../../src/lib/paths'
+import { state } from '../../src/lib/state'
+import type { ModelsResponse } from '../../src/services/copilot/get-models'
+import { fakeUpstream, streamScript, usage, type FakeUpstream } from '../testkit/upstream'
+
+// @grpc/grpc-js and @grpc/proto-loader are optional dependencies
+const grpcInstalled = await import('@grpc/grpc-js').then(
+  () => true,
+  () => false,
+)
+
+const models = {
+  object: 'list',
+  data: [
+    {
+      id: 'gpt-4o',
+      name: 'GPT-4o',
+      vendor: 'Azure OpenAI',
+      capabilities: { limits: { max_context_window_tokens: 128000, max_output_tokens: 4096 } },
+    },
+  ],
+} as unknown as ModelsResponse
+
+interface GatewayClient extends Grpc.Client {
+  ChatCompletion: (request: object, metadata: Grpc.Metadata) => Grpc.ClientReadableStream<{ content: string }>
+  Embed: (
+    request: object,
+    metadata: Grpc.Metadata,
+    callback: (error: Grpc.ServiceError | null, response: { data: Array<{ values: Array<number> }> }) => void,
+  ) => void
+  ListModels: (
+    request: object,
+    callback: (error: Grpc.ServiceError | null, response: { models: Array<{ id: string }> }) => void,
+  ) => void
+}
+
+let grpc: typeof Grpc
+let client: GatewayClient
+
+const metadata = (apiKey?: string) => {
+  const result = new grpc.Metadata()
+  if (apiKey) result.set('authorization', `Bearer ${apiKey}`)
+  return result
+}
+
+function chat(model: string, apiKey?: string): Promise<Array<string>> {
+  return new Promise((resolve, reject) => {
+    const contents: Array<string> = []
+    client
+      .ChatCompletion({ model, messages: [{ role: 'user', content: 'Hello secret' }] }, metadata(apiKey))
+      .on('data', (chunk: { content: string }) => contents.push(chunk.content))
+      .on('end', () => resolve(contents))
+      .on('error', reject)
+  })
+}
+
+function embed(model: string, apiKey?: string): Promise<Array<Array<number>>> {
+  return new Promise((resolve, reject) => {
+    client.Embed({ model, input: ['hello'] }, metadata(apiKey), (error, response) => {
+      if (error) reject(error)
+      else resolve(response.data.map((item) => item.values))
+    })
+  })
+}
+
+describe.skipIf(!grpcInstalled)('Phase 3: gRPC API', () => {
+  let upstream: FakeUpstream | undefined
+  const originalAuditDir = PATHS.AUDIT_DIR
+
+  beforeAll(async () => {
+    grpc = await import('@grpc/grpc-js')
+    const protoLoader = await import('@grpc/proto-loader')
+    const definition = await protoLoader.load(path.join(import.meta.dir, '../../proto/copilot_gateway.proto'), {
+      longs: Number,
+      arrays: true,
+    })
+    const { CopilotGateway } = (
+      grpc.loadPackageDefinition(definition) as unknown as {
+        copilot: { gateway: { v1: { CopilotGateway: Grpc.ServiceClientConstructor } } }
+      }
+    ).copilot.gateway.v1
+
+    const port = await startGrpcServer(0, '127.0.0.1')
+    client = new CopilotGateway(`127.0.0.1:${port}`, grpc.credentials.createInsecure()) as unknown as GatewayClient
+  })
+
+  beforeEach(async () => {
+    state.models = models
+    state.copilotToken = 'test-copilot-token'
+    PATHS.AUDIT_DIR = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-grpc-audit-'))
+    state.auditRetentionDays = 30
+  })
+
+  afterEach(() => {
+    upstream?.restore()
+    upstream = undefined
+    state.models = undefined
+    state.copilotToken = undefined
+    state.contentFilters = undefined
+    state.modelPolicy = undefined
+    state.auditRetentionDays = undefined
+    PATHS.AUDIT_DIR = originalAuditDir
+  })
+
+  test('ListModels returns the cached models', async () => {
+    const response = await new Promise<{ models: Array<{ id: string }> }>((resolve, reject) => {
+      client.ListModels({}, (error, result) => (error ? reject(error) : resolve(result)))
+    })
+    expect(response.models.map((model) => model.id)).toEqual(['gpt-4o'])
+  })
+
+  test('ChatCompletion streams the completion and records an audit entry', async () => {
+    upstream = fakeUpstream([streamScript({ text: ['Hel', 'lo'], usage: usage(5, 2) })])
+
+    expect((await chat('gpt-4o')).join('')).toBe('Hello')
+    expect(upstream.requests[0].stream).toBe(true)
+
+    const [entry] = await readAuditLog()
+    expect(entry.endpoint).toBe('/copilot.gateway.v1.CopilotGateway/ChatCompletion')
+    expect(entry.model).toBe('gpt-4o')
+  })
+
+  test('ChatCompletion rejects requests a content filter blocks', async () => {
+    upstream = fakeUpstream([])
+    state.contentFilters = [createFilter({ type: 'forbidden-patterns', patterns: ['secret'] })]
+
+    await expect(chat('gpt-4o')).rejects.toMatchObject({ code: grpc.status.INVALID_ARGUMENT })
+    expect(upstream.requests).toHaveLength(0)
+  })
+
+  test('ChatCompletion and Embed enforce the model policy of the API key', async () => {
+    upstream = fakeUpstream([])
+    state.modelPolicy = { keys: { intern: { allow: ['gpt-4o-mini'] } } }
+
+    await expect(chat('gpt-4o', 'intern')).rejects.toMatchObject({ code: grpc.status.PERMISSION_DENIED })
+    await expect(embed('text-embedding-3-small', 'intern')).rejects.toMatchObject({
+      code: grpc.status.PERMISSION_DENIED,
+    })
+    expect(upstream.requests).toHaveLength(0)
+  })
+
+  test('Embed returns the upstream embeddings', async () => {
+    const originalFetch = globalThis.fetch
+    globalThis.fetch = (async () =>
+      Response.json({
+        object: 'list',
+        data: [{ object: 'embedding', index: 0, embedding: [0.5, 0.25] }],
+        model: 'text-embedding-3-small',
+        usage: { prompt_tokens: 1, total_tokens: 1 },
+      })) as unknown as typeof fetch
+    try {
+      expect(await embed('text-embedding-3-small')).toEqual([[0.5, 0.25]])
+    } finally {
+      globalThis.fetch = originalFetch
+    }
+  })
+})
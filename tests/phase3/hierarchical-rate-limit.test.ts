@@ -0,0 +1,62 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+describe('Phase 3: Hierarchical Rate Limits', () => {
+  afterEach(async () => {
+    rustCore.setRateLimitDefaults({})
+    await rustCore.resetRateLimit('global')
+  })
+
+  test('checks consume from the key and the global bucket', async () => {
+    const run = `run${Date.now()}`
+    rustCore.setRateLimitDefaults({
+      global: { intervalSecs: 60, burstCapacity: 3 },
+      'user:*': { intervalSecs: 60, burstCapacity: 2 }
+    })
+
+    const results = []
+    for (const user of ['alice', 'alice', 'alice', 'bob', 'bob', 'carol']) {
+      results.push(await rustCore.checkRateLimit(`user:${run}-${user}`))
+    }
+
+    // alice hits her per-user limit, then the shared global bucket runs out
+    expect(results).toEqual([true, true, false, true, false, false])
+  })
+
+  test('a denied check does not drain the other buckets', async () => {
+    const run = `run${Date.now()}`
+    rustCore.setRateLimitDefaults({
+      global: { intervalSecs: 60, burstCapacity: 2 },
+      'user:*': { intervalSecs: 60, burstCapacity: 1 }
+    })
+
+    expect(await rustCore.checkRateLimit(`user:${run}-alice`)).toBe(true)
+    expect(await rustCore.checkRateLimit(`user:${run}-alice`)).toBe(false)
+    expect(await rustCore.checkRateLimit(`user:${run}-alice`)).toBe(false)
+    expect(await rustCore.checkRateLimit(`user:${run}-bob`)).toBe(true)
+  })
+
+  test('intermediate levels participate when configured', async () => {
+    const org = `org:acme${Date.now()}`
+    rustCore.setRateLimitDefaults({
+      [org]: { intervalSecs: 60, burstCapacity: 2 },
+      [`${org}:user:*`]: { intervalSecs: 60, burstCapacity: 10 }
+    })
+
+    expect(await rustCore.checkRateLimit(`${org}:user:alice`)).toBe(true)
+    expect(await rustCore.checkRateLimit(`${org}:user:bob`)).toBe(true)
+    expect(await rustCore.checkRateLimit(`${org}:user:carol`)).toBe(false)
+  })
+
+  test('explicit arguments override defaults and unconfigured keys are rejected', async () => {
+    const key = `user:explicit${Date.now()}`
+    rustCore.setRateLimitDefaults({ 'user:*': { intervalSecs: 60, burstCapacity: 1 } })
+
+    expect(await rustCore.checkRateLimit(key, 60, 2)).toBe(true)
+    expect(await rustCore.checkRateLimit(key, 60, 2)).toBe(true)
+    expect(await rustCore.checkRateLimit(key, 60, 2)).toBe(false)
+
+    await expect(rustCore.checkRateLimit(`team:${Date.now()}`)).rejects.toThrow('No rate limit configured')
+    expect(() => rustCore.setRateLimitDefaults({ global: { intervalSecs: 0 } })).toThrow()
+  })
+})
@@ -0,0 +1,69 @@
+import { test, expect, describe, afterAll } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+describe('Phase 3: Shared HTTP Client', () => {
+  const remotePorts = new Set<number>()
+  let acceptEncoding: string | null = null
+  const server = Bun.serve({
+    port: 0,
+    fetch(req, server) {
+      remotePorts.add(server.requestIP(req)!.port)
+      if (new URL(req.url).pathname === '/gzip/embeddings') {
+        acceptEncoding = req.headers.get('accept-encoding')
+        return new Response(Bun.gzipSync(JSON.stringify({ compressed: true })), {
+          headers: { 'content-type': 'application/json', 'content-encoding': 'gzip' }
+        })
+      }
+      return Response.json({ ok: true })
+    }
+  })
+  const baseUrl = `http://127.0.0.1:${server.port}`
+
+  afterAll(() => {
+    rustCore.configureHttp({ requestTimeoutMs: null, poolMaxIdlePerHost: 32, proxy: null })
+    server.stop(true)
+  })
+
+  test('configureHttp merges options and returns the effective config', () => {
+    const config = rustCore.configureHttp({ requestTimeoutMs: 5000, poolMaxIdlePerHost: 4 })
+
+    expect(config).toEqual({
+      connectTimeoutMs: 10000,
+      requestTimeoutMs: 5000,
+      poolMaxIdlePerHost: 4,
+      poolIdleTimeoutMs: 90000,
+      tcpKeepaliveMs: 60000,
+      http2: true,
+      http2PriorKnowledge: false,
+      http2AdaptiveWindow: false,
+      http2KeepAliveIntervalMs: null,
+      compression: true,
+      proxy: null
+    })
+  })
+
+  test('rejects invalid options without replacing the client', () => {
+    expect(() => rustCore.configureHttp({ proxy: '::not a url' })).toThrow('Invalid proxy')
+    expect(() => rustCore.configureHttp({ http2: 'yes' as any })).toThrow('Invalid HTTP options')
+    expect(() => rustCore.configureHttp({ http2: false, http2PriorKnowledge: true })).toThrow('requires http2')
+    expect(() => rustCore.configureHttp({ poolIdleTimeout: 1000 } as any)).toThrow('unknown field')
+    expect(rustCore.configureHttp({}).poolMaxIdlePerHost).toBe(4)
+  })
+
+  test('sequential requests reuse pooled connections', async () => {
+    for (let i = 0; i < 5; i++) {
+      await rustCore.createEmbeddings({ input: 'hi' }, { baseUrl }).response
+    }
+
+    expect(remotePorts.size).toBe(1)
+    console.log('Keep-alive connection reuse: ✓')
+  })
+
+  test('requests and decodes compressed responses', async () => {
+    const body = await rustCore.createEmbeddings({ input: 'hi' }, { baseUrl: `${baseUrl}/gzip` }).response
+
+    expect(acceptEncoding).toContain('gzip')
+    expect(acceptEncoding).toContain('zstd')
+    expect(JSON.parse(body)).toEqual({ compressed: true })
+  })
+})
@@ -0,0 +1,53 @@
+import { test, expect, describe } from 'bun:test'
+import { fetchAgentOptions, parseHttpOptions } from '../../src/lib/http-options'
+
+describe('Phase 3: Upstream HTTP Options', () => {
+  test('should keep numbers, booleans and null typed', () => {
+    expect(
+      parseHttpOptions('poolIdleTimeoutMs=300000, http2AdaptiveWindow=true, requestTimeoutMs=null'),
+    ).toEqual({
+      poolIdleTimeoutMs: 300000,
+      http2AdaptiveWindow: true,
+      requestTimeoutMs: null,
+    })
+  })
+
+  test('should leave other values as strings', () => {
+    expect(parseHttpOptions('proxy=http://localhost:3128')).toEqual({
+      proxy: 'http://localhost:3128',
+    })
+  })
+
+  test('should reject entries without a value', () => {
+    expect(() => parseHttpOptions('http2')).toThrow(TypeError)
+  })
+
+  test('should map pool and keep-alive options onto the fetch agent', () => {
+    expect(
+      fetchAgentOptions(
+        parseHttpOptions('poolIdleTimeoutMs=600000,poolMaxIdlePerHost=8,tcpKeepaliveMs=30000,connectTimeoutMs=5000,http2=false'),
+      ),
+    ).toEqual({
+      agent: {
+        keepAliveTimeout: 600000,
+        keepAliveMaxTimeout: 600000,
+        connections: 8,
+        allowH2: false,
+        connect: { keepAlive: true, keepAliveInitialDelay: 30000, timeout: 5000 },
+      },
+      nativeOnly: [],
+    })
+  })
+
+  test('should list options only the native client understands', () => {
+    expect(
+      fetchAgentOptions(parseHttpOptions('http2AdaptiveWindow=true,requestTimeoutMs=null')),
+    ).toEqual({ agent: {}, nativeOnly: ['http2AdaptiveWindow'] })
+  })
+
+  test('should reject unknown names and invalid values', () => {
+    expect(() => fetchAgentOptions(parseHttpOptions('poolIdle=1'))).toThrow(TypeError)
+    expect(() => fetchAgentOptions(parseHttpOptions('poolIdleTimeoutMs=0'))).toThrow(TypeError)
+    expect(() => fetchAgentOptions(parseHttpOptions('http2=yes'))).toThrow(TypeError)
+  })
+})
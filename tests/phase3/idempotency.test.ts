@@ -0,0 +1,157 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import {
+  clearIdempotencyCache,
+  configureIdempotency,
+  idempotency,
+} from '../../src/lib/idempotency'
+
+function createApp(status = 200) {
+  let calls = 0
+  const app = new Hono()
+  app.post('/v1/chat/completions', idempotency, async (c) => {
+    calls++
+    const body = await c.req.json()
+    return c.json({ id: `completion-${calls}`, body }, status as 200)
+  })
+  return { app, calls: () => calls }
+}
+
+const post = (app: Hono, body: unknown, headers: Record<string, string> = {}) =>
+  app.request('/v1/chat/completions', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', authorization: 'Bearer key-a', ...headers },
+    body: JSON.stringify(body),
+  })
+
+describe('Phase 3: Idempotency Keys', () => {
+  afterEach(() => {
+    clearIdempotencyCache()
+    configureIdempotency({ ttlMs: 10 * 60_000, maxBytes: 64 * 1024 * 1024 })
+  })
+
+  test('should replay the first response for a retry with the same key', async () => {
+    const { app, calls } = createApp()
+    const first = await post(app, { model: 'gpt-4.1' }, { 'idempotency-key': 'abc' })
+    const retry = await post(app, { model: 'gpt-4.1' }, { 'idempotency-key': 'abc' })
+
+    expect(await first.json()).toEqual({ id: 'completion-1', body: { model: 'gpt-4.1' } })
+    expect(first.headers.get('idempotent-replayed')).toBeNull()
+    expect(retry.status).toBe(200)
+    expect(retry.headers.get('idempotent-replayed')).toBe('true')
+    expect(await retry.json()).toEqual({ id: 'completion-1', body: { model: 'gpt-4.1' } })
+    expect(calls()).toBe(1)
+  })
+
+  test('should let concurrent retries wait for the first attempt', async () => {
+    const { app, calls } = createApp()
+    const responses = await Promise.all([
+      post(app, {}, { 'idempotency-key': 'abc' }),
+      post(app, {}, { 'idempotency-key': 'abc' }),
+    ])
+
+    const bodies = await Promise.all(responses.map((response) => response.json()))
+    expect(bodies[0]).toEqual(bodies[1])
+    expect(calls()).toBe(1)
+  })
+
+  test('should scope keys to the client', async () => {
+    const { app, calls } = createApp()
+    await post(app, {}, { 'idempotency-key': 'abc' })
+    const other = await post(app, {}, { 'idempotency-key': 'abc', authorization: 'Bearer key-b' })
+
+    expect(other.headers.get('idempotent-replayed')).toBeNull()
+    expect(calls()).toBe(2)
+  })
+
+  test('should scope keys to the client address without an API key', async () => {
+    const { app, calls } = createApp()
+    const fromIp = (ip: string) =>
+      app.request(
+        Object.assign(
+          new Request('http://localhost/v1/chat/completions', {
+            method: 'POST',
+            headers: { 'content-type': 'application/json', 'idempotency-key': 'abc' },
+            body: '{}',
+          }),
+          { ip },
+        ),
+      )
+
+    await fromIp('192.0.2.1')
+    const retry = await fromIp('192.0.2.1')
+    const other = await fromIp('192.0.2.2')
+
+    expect(retry.headers.get('idempotent-replayed')).toBe('true')
+    expect(other.headers.get('idempotent-replayed')).toBeNull()
+    expect(calls()).toBe(2)
+  })
+
+  test('should not replay for clients without an API key or address', async () => {
+    const { app, calls } = createApp()
+    await post(app, {}, { 'idempotency-key': 'abc', authorization: '' })
+    const retry = await post(app, {}, { 'idempotency-key': 'abc', authorization: '' })
+
+    expect(retry.headers.get('idempotent-replayed')).toBeNull()
+    expect(calls()).toBe(2)
+  })
+
+  test('should evict the oldest responses beyond the byte cap', async () => {
+    const { app, calls } = createApp()
+    // Room for one of these responses, not two
+    configureIdempotency({ maxBytes: 60 })
+    await post(app, {}, { 'idempotency-key': 'first' })
+    await post(app, {}, { 'idempotency-key': 'second' })
+    const second = await post(app, {}, { 'idempotency-key': 'second' })
+    const first = await post(app, {}, { 'idempotency-key': 'first' })
+
+    expect(second.headers.get('idempotent-replayed')).toBe('true')
+    expect(first.headers.get('idempotent-replayed')).toBeNull()
+    expect(calls()).toBe(3)
+
+    // Larger than the whole cache
+    configureIdempotency({ maxBytes: 10 })
+    await post(app, {}, { 'idempotency-key': 'large' })
+    const large = await post(app, {}, { 'idempotency-key': 'large' })
+    expect(large.headers.get('idempotent-replayed')).toBeNull()
+    expect(calls()).toBe(5)
+  })
+
+  test('should reject a reused key with a different body', async () => {
+    const { app } = createApp()
+    await post(app, { model: 'gpt-4.1' }, { 'idempotency-key': 'abc' })
+    const response = await post(app, { model: 'gpt-4o' }, { 'idempotency-key': 'abc' })
+
+    expect(response.status).toBe(422)
+    const body = (await response.json()) as { error: { code: string } }
+    expect(body.error.code).toBe('idempotency_key_reused')
+  })
+
+  test('should not keep failed responses', async () => {
+    const { app, calls } = createApp(500)
+    await post(app, {}, { 'idempotency-key': 'abc' })
+    const retry = await post(app, {}, { 'idempotency-key': 'abc' })
+
+    expect(retry.headers.get('idempotent-replayed')).toBeNull()
+    expect(calls()).toBe(2)
+  })
+
+  test('should reject keys that are too long', async () => {
+    const { app, calls } = createApp()
+    const response = await post(app, {}, { 'idempotency-key': 'x'.repeat(256) })
+
+    expect(response.status).toBe(400)
+    expect(calls()).toBe(0)
+  })
+
+  test('should pass requests through without a key or when disabled', async () => {
+    const { app, calls } = createApp()
+    await post(app, {})
+    await post(app, {})
+    configureIdempotency({ ttlMs: 0 })
+    await post(app, {}, { 'idempotency-key': 'abc' })
+    await post(app, {}, { 'idempotency-key': 'abc' })
+
+    expect(calls()).toBe(4)
+  })
+})
@@ -0,0 +1,66 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { state } from '../../src/lib/state'
+import { fakeProvider, type FakeProvider } from '../testkit/upstream'
+
+const postGeneration = () =>
+  server.request('/v1/images/generations', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ model: 'dall-e-3', prompt: 'A lighthouse at dusk', n: 1 }),
+  })
+
+describe('Phase 3: Image Endpoints', () => {
+  let provider: FakeProvider | undefined
+
+  afterEach(() => {
+    provider?.restore()
+    provider = undefined
+    state.mediaProviders = undefined
+  })
+
+  test('should answer model_not_found without an image provider', async () => {
+    // A provider for another kind does not serve images
+    state.mediaProviders = { audio: { url: 'https://audio.example.com/v1' } }
+    provider = fakeProvider(() => {
+      throw new Error('Unexpected upstream request')
+    })
+
+    const response = await postGeneration()
+
+    expect(response.status).toBe(404)
+    const { error } = (await response.json()) as { error: Record<string, string> }
+    expect(error).toMatchObject({ type: 'invalid_request_error', param: 'model', code: 'model_not_found' })
+    expect(error.message).toContain('`dall-e-3`')
+    expect(error.message).toContain('Image generation')
+  })
+
+  test('should delegate to the image provider', async () => {
+    state.mediaProviders = { images: { url: 'http://localhost:7860/v1', apiKey: 'sk-images' } }
+    const generated = { created: 1700000000, data: [{ url: 'http://localhost:7860/out/1.png' }] }
+    provider = fakeProvider(() => Response.json(generated))
+
+    const response = await postGeneration()
+
+    expect(response.status).toBe(200)
+    expect(await response.json()).toEqual(generated)
+    const [forwarded] = provider.requests
+    expect(forwarded.url).toBe('http://localhost:7860/v1/images/generations')
+    expect(forwarded.headers.get('authorization')).toBe('Bearer sk-images')
+    expect(JSON.parse(new TextDecoder().decode(forwarded.body))).toEqual({
+      model: 'dall-e-3',
+      prompt: 'A lighthouse at dusk',
+      n: 1,
+    })
+  })
+
+  test('should report an unreachable provider as a gateway error', async () => {
+    state.mediaProviders = { images: { url: 'http://localhost:7860/v1' } }
+    provider = fakeProvider(() => {
+      throw new TypeError('Unable to connect')
+    })
+
+    const response = await postGeneration()
+    expect(response.status).toBe(500)
+  })
+})
@@ -0,0 +1,69 @@
+import { test, expect, describe } from 'bun:test'
+import { Hono } from 'hono'
+import { bodyExcerpt, locateJsonError, parseJsonBody } from '../../src/lib/json-body'
+
+function createApp() {
+  const app = new Hono()
+  app.use(parseJsonBody)
+  app.post('/echo', async (c) => c.json(await c.req.json()))
+  app.post('/ping', (c) => c.text('pong'))
+  return app
+}
+
+const post = (body: string, contentType = 'application/json') =>
+  createApp().request('/echo', {
+    method: 'POST',
+    headers: { 'content-type': contentType },
+    body,
+  })
+
+describe('Phase 3: JSON Body Errors', () => {
+  test('should locate syntax errors by line, column and byte offset', () => {
+    expect(locateJsonError('{"a":1,}')).toEqual({ offset: 7, line: 1, column: 8, reason: 'Trailing comma' })
+    expect(locateJsonError('{\n  "model": "x",\n  "messages": [tru]\n}')).toMatchObject({
+      line: 3,
+      column: 16,
+      reason: 'Unexpected character "t"',
+    })
+    // Offsets count UTF-8 bytes, columns count characters
+    expect(locateJsonError('{"é":01}')).toMatchObject({ offset: 7, column: 7 })
+    expect(locateJsonError('"abc')?.reason).toBe('Unterminated string')
+    expect(locateJsonError('')?.reason).toBe('Unexpected end of input')
+    expect(locateJsonError('{"a":[1,{"b":null}],"c":-1.5e3}')).toBeUndefined()
+  })
+
+  test('should redact and truncate the body excerpt', () => {
+    const excerpt = bodyExcerpt(`{"key":"sk-${'a'.repeat(40)}","x":"\u0001${'y'.repeat(300)}`)
+    expect(excerpt).toContain('[REDACTED]')
+    expect(excerpt).not.toContain('sk-aaaa')
+    expect(excerpt).not.toContain('\u0001')
+    expect(Buffer.byteLength(excerpt)).toBeLessThanOrEqual(202)
+  })
+
+  test('should answer malformed bodies with an OpenAI-style 400', async () => {
+    const response = await post('{"model": "gpt-4.1", "messages": [}')
+    expect(response.status).toBe(400)
+    const { error } = (await response.json()) as { error: Record<string, unknown> }
+    expect(error).toMatchObject({
+      type: 'invalid_request_error',
+      code: 'invalid_json',
+      line: 1,
+      column: 35,
+      offset: 34,
+      body_excerpt: '{"model": "gpt-4.1", "messages": [}',
+    })
+    expect(error.message).toContain('line 1, column 35')
+  })
+
+  test('should pass valid and non-JSON bodies through', async () => {
+    expect(await (await post('{"ok":true}')).json()).toEqual({ ok: true })
+    const app = createApp()
+    expect(await (await app.request('/ping', { method: 'POST' })).text()).toBe('pong')
+    const text = await app.request('/ping', {
+      method: 'POST',
+      headers: { 'content-type': 'text/plain' },
+      body: 'not json',
+    })
+    expect(text.status).toBe(200)
+  })
+})
@@ -0,0 +1,100 @@
+import { test, expect, describe, beforeEach } from 'bun:test'
+import {
+  getStats,
+  observeRequest,
+  observeUpstreamResponse,
+  renderPrometheus,
+  resetMetrics,
+  startStreamTimer,
+} from '../../src/lib/metrics'
+
+describe('Phase 3: Latency Metrics', () => {
+  beforeEach(() => {
+    resetMetrics()
+  })
+
+  test('should split stream timings by model', async () => {
+    const start = performance.now() - 300
+    const timer = startStreamTimer('gpt-4o', start)
+    timer.chunk()
+    timer.chunk()
+    timer.end()
+    startStreamTimer('claude-sonnet-4').end()
+
+    const stats = getStats()
+    expect(stats.models['gpt-4o'].timeToFirstToken.count).toBe(1)
+    expect(stats.models['gpt-4o'].timeToFirstToken.sumSeconds).toBeGreaterThanOrEqual(0.3)
+    expect(stats.models['gpt-4o'].streamDuration.count).toBe(1)
+    // A stream without chunks has no time to first token
+    expect(stats.models['claude-sonnet-4'].timeToFirstToken).toBeUndefined()
+    expect(stats.models['claude-sonnet-4'].streamDuration.count).toBe(1)
+  })
+
+  test('should estimate quantiles from the buckets', () => {
+    for (let i = 0; i < 90; i++) observeRequest('/v1/chat/completions', 0.2)
+    for (let i = 0; i < 10; i++) observeRequest('/v1/chat/completions', 4)
+
+    const route = getStats().routes['/v1/chat/completions']
+    expect(route.count).toBe(100)
+    expect(route.buckets['0.25']).toBe(90)
+    expect(route.buckets['5']).toBe(100)
+    // Interpolated within the (0.1, 0.25] bucket
+    expect(route.p50Seconds).toBeCloseTo(0.1 + 0.15 * (50 / 90))
+    expect(route.p95Seconds).toBeCloseTo(2.5 + 2.5 * 0.5)
+  })
+
+  test('should render Prometheus histograms', () => {
+    observeRequest('/v1/models', 0.07)
+    startStreamTimer('gpt-4o').end()
+
+    const text = renderPrometheus()
+    expect(text).toContain('# TYPE copilot_api_request_duration_seconds histogram')
+    expect(text).toContain('copilot_api_request_duration_seconds_bucket{route="/v1/models",le="0.05"} 0')
+    expect(text).toContain('copilot_api_request_duration_seconds_bucket{route="/v1/models",le="0.1"} 1')
+    expect(text).toContain('copilot_api_request_duration_seconds_bucket{route="/v1/models",le="+Inf"} 1')
+    expect(text).toContain('copilot_api_request_duration_seconds_count{route="/v1/models"} 1')
+    expect(text).toContain('copilot_api_stream_duration_seconds_count{model="gpt-4o"} 1')
+  })
+
+  test('should count upstream transfer sizes by encoding', () => {
+    observeUpstreamResponse(new Headers({ 'content-encoding': 'gzip', 'content-length': '1200' }))
+    observeUpstreamResponse(new Headers({ 'content-encoding': 'gzip', 'content-length': '800' }))
+    // Streamed, without a length
+    observeUpstreamResponse(new Headers())
+
+    expect(getStats().upstream).toEqual({
+      gzip: { responses: 2, bytes: 2000 },
+      identity: { responses: 1, bytes: 0 },
+    })
+    const text = renderPrometheus()
+    expect(text).toContain('copilot_api_upstream_response_bytes_total{encoding="gzip"} 2000')
+    expect(text).toContain('copilot_api_upstream_responses_total{encoding="identity"} 1')
+  })
+
+  test('should count the compressed size of a response fetch decoded', async () => {
+    const body = JSON.stringify({ data: Array.from({ length: 200 }, (_, index) => ({ id: `model-${index}` })) })
+    const compressed = Bun.gzipSync(body)
+    const server = Bun.serve({
+      port: 0,
+      fetch: () =>
+        new Response(compressed, {
+          headers: {
+            'content-type': 'application/json',
+            'content-encoding': 'gzip',
+            'content-length': String(compressed.length),
+          },
+        }),
+    })
+    try {
+      const response = await fetch(`http://127.0.0.1:${server.port}/models`)
+      observeUpstreamResponse(response.headers)
+
+      // The client gets the decoded body, the metric the bytes on the wire
+      expect(await response.text()).toBe(body)
+      expect(getStats().upstream).toEqual({ gzip: { responses: 1, bytes: compressed.length } })
+      expect(compressed.length).toBeLessThan(body.length)
+    } finally {
+      server.stop(true)
+    }
+  })
+})
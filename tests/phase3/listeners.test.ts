@@ -0,0 +1,71 @@
+import { test, expect, describe } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { listenerApp, loadListeners } from '../../src/lib/listeners'
+
+const routes = new Hono()
+routes.get('/v1/models', (c) => c.json({ data: [] }))
+routes.get('/token', (c) => c.json({ token: 'secret' }))
+routes.get('/admin/samples', (c) => c.json({ samples: [] }))
+
+async function writeListeners(value: unknown): Promise<string> {
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-listeners-'))
+  const filePath = path.join(dir, 'listeners.json')
+  await fs.writeFile(filePath, JSON.stringify(value))
+  return filePath
+}
+
+describe('Phase 3: Multiple Listeners', () => {
+  test('should serve every route without options', async () => {
+    const app = listenerApp({ port: 4141 }, routes)
+    expect((await app.request('/v1/models')).status).toBe(200)
+    expect((await app.request('/token')).status).toBe(200)
+  })
+
+  test('should require one of the API keys', async () => {
+    const app = listenerApp({ port: 8443, apiKeys: ['sk-remote', 'sk-other'] }, routes)
+
+    const missing = await app.request('/v1/models')
+    expect(missing.status).toBe(401)
+    expect(await missing.json()).toMatchObject({ error: { code: 'invalid_api_key' } })
+    expect((await app.request('/v1/models', { headers: { 'x-api-key': 'sk-wrong' } })).status).toBe(401)
+
+    expect((await app.request('/v1/models', { headers: { 'x-api-key': 'sk-other' } })).status).toBe(200)
+    expect((await app.request('/v1/models', { headers: { authorization: 'Bearer sk-remote' } })).status).toBe(200)
+  })
+
+  test('should hide admin endpoints, also under a path prefix', async () => {
+    const app = listenerApp({ port: 8443, admin: false, pathPrefix: '/copilot' }, routes)
+
+    expect((await app.request('/copilot/v1/models')).status).toBe(200)
+    expect((await app.request('/copilot/token')).status).toBe(404)
+    expect((await app.request('/copilot/admin/samples')).status).toBe(404)
+    expect((await app.request('/v1/models')).status).toBe(404)
+  })
+
+  test('should resolve keys and normalize prefixes when loading', async () => {
+    process.env.LISTENER_TEST_KEY = 'sk-from-env'
+    const filePath = await writeListeners([
+      { port: 8443, apiKeys: ['${LISTENER_TEST_KEY}'], pathPrefix: 'copilot/' },
+    ])
+
+    const [listener] = await loadListeners(filePath)
+    expect(listener.apiKeys).toEqual(['sk-from-env'])
+    expect(listener.pathPrefix).toBe('/copilot')
+    delete process.env.LISTENER_TEST_KEY
+  })
+
+  test('should reject invalid listeners', async () => {
+    await expect(loadListeners(await writeListeners([{ port: 70000 }]))).rejects.toThrow(
+      'Listener 1: invalid port 70000',
+    )
+    await expect(
+      loadListeners(await writeListeners([{ port: 1 }, { port: 2, tlsCert: 'cert.pem' }])),
+    ).rejects.toThrow('Listener 2: tlsCert and tlsKey must be given together')
+    await expect(
+      loadListeners(await writeListeners([{ port: 1, apiKeys: ['${UNSET_LISTENER_KEY}'] }])),
+    ).rejects.toThrow('Listener 1: references unset variable')
+  })
+})
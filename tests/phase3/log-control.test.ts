@@ -0,0 +1,160 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import consola, { type LogObject } from 'consola'
+import {
+  logControl,
+  logControlStatus,
+  logNextBodies,
+  parseLogFilter,
+  resetLogControl,
+  setLogFilter,
+} from '../../src/lib/log-control'
+import { state } from '../../src/lib/state'
+import { server } from '../../src/server'
+
+// Sent from this machine, which may change the settings without an admin token
+const local = (path: string, init: RequestInit) =>
+  Object.assign(new Request(`http://localhost${path}`, init), { ip: '127.0.0.1' })
+
+function captureLogs(): Array<LogObject> {
+  const logs: Array<LogObject> = []
+  consola.setReporters([{ log: (logObj) => logs.push(logObj) }])
+  return logs
+}
+
+function appLogging(): Hono {
+  const app = new Hono()
+  app.use(logControl)
+  app.all('/*', (c) => {
+    consola.debug(`debug ${c.req.path}`)
+    consola.info(`info ${c.req.path}`)
+    return c.text('ok')
+  })
+  return app
+}
+
+describe('Phase 3: Live Log Control', () => {
+  const originalReporters = [...consola.options.reporters]
+  const originalLevel = consola.level
+
+  afterEach(() => {
+    resetLogControl()
+    consola.setReporters(originalReporters)
+    consola.level = originalLevel
+  })
+
+  test('should parse levels per path prefix, longest first', () => {
+    expect(parseLogFilter('warn,/v1=info, /v1/messages=debug')).toEqual({
+      level: 1,
+      paths: [
+        { prefix: '/v1/messages', level: 4 },
+        { prefix: '/v1', level: 3 },
+      ],
+    })
+    expect(parseLogFilter('debug')?.paths).toEqual([])
+    expect(parseLogFilter('loud')).toBeUndefined()
+    expect(parseLogFilter('info,v1=debug')).toBeUndefined()
+  })
+
+  test('should log each request at the level of its path', async () => {
+    const logs = captureLogs()
+    consola.level = 3
+    expect(setLogFilter('warn,/v1/messages=debug')).toBe(true)
+
+    const app = appLogging()
+    await app.request('/v1/messages', { method: 'POST' })
+    await app.request('/v1/chat/completions', { method: 'POST' })
+    consola.info('outside any request')
+
+    expect(logs.map((log) => log.args[0])).toEqual([
+      'debug /v1/messages',
+      'info /v1/messages',
+    ])
+    expect(logControlStatus().filter).toBe('warn,/v1/messages=debug')
+  })
+
+  test('should keep the filter when given an invalid one', () => {
+    expect(setLogFilter('info')).toBe(true)
+    expect(setLogFilter('verbose')).toBe(false)
+    expect(logControlStatus().filter).toBe('info')
+  })
+
+  test('should restore the reporters and level on reset', () => {
+    const logs = captureLogs()
+    consola.level = 3
+    setLogFilter('error')
+    resetLogControl()
+
+    consola.info('shown')
+    expect(logs.map((log) => log.args[0])).toEqual(['shown'])
+    expect(consola.level).toBe(3)
+  })
+
+  test('should log the next request bodies with secrets redacted', async () => {
+    const logs = captureLogs()
+    logNextBodies(1)
+
+    const app = appLogging()
+    await app.request('/v1/chat/completions', { method: 'GET' })
+    const body = JSON.stringify({ key: `sk-${'a'.repeat(30)}` })
+    await app.request('/v1/chat/completions', { method: 'POST', body })
+    await app.request('/v1/chat/completions', { method: 'POST', body })
+
+    const bodies = logs.filter((log) => String(log.args[0]).startsWith('Request body'))
+    expect(bodies).toHaveLength(1)
+    expect(bodies[0].args[0]).toBe('Request body of POST /v1/chat/completions:')
+    expect(bodies[0].args[1]).not.toContain('sk-')
+    expect(logControlStatus().bodyLogsRemaining).toBe(0)
+  })
+
+  test('should change the settings through /admin/log', async () => {
+    const response = await server.request(
+      local('/admin/log', { method: 'PUT', body: JSON.stringify({ filter: 'info,/v1=debug', logBodies: 3 }) }),
+    )
+    expect(response.status).toBe(200)
+    expect(await response.json()).toEqual({ filter: 'info,/v1=debug', bodyLogsRemaining: 3 })
+
+    const status = await server.request(local('/admin/log', {}))
+    expect((await status.json() as { bodyLogsRemaining: number }).bodyLogsRemaining).toBe(3)
+  })
+
+  test('should reject an invalid filter or count', async () => {
+    for (const body of [{ filter: 'loud' }, { logBodies: -1 }, { logBodies: 1.5 }]) {
+      const response = await server.request(local('/admin/log', { method: 'PUT', body: JSON.stringify(body) }))
+      expect(response.status).toBe(400)
+    }
+    expect(logControlStatus().bodyLogsRemaining).toBe(0)
+  })
+
+  test('should only accept changes from this machine without an admin token', async () => {
+    const body = JSON.stringify({ logBodies: 5 })
+
+    const remote = await server.request('/admin/log', { method: 'PUT', body })
+    expect(remote.status).toBe(403)
+    // A proxy on this machine forwarding someone else's request
+    const forwarded = await server.request(
+      local('/admin/log', { method: 'PUT', body, headers: { 'x-forwarded-for': '203.0.113.9' } }),
+    )
+    expect(forwarded.status).toBe(403)
+    expect(logControlStatus().bodyLogsRemaining).toBe(0)
+
+    // Reading the settings is local only too
+    expect((await server.request('/admin/log')).status).toBe(403)
+    expect((await server.request(local('/admin/log', {}))).status).toBe(200)
+  })
+
+  test('should accept changes from anywhere with the admin token', async () => {
+    state.adminToken = 'admin-secret'
+    try {
+      const response = await server.request('/admin/log', {
+        method: 'PUT',
+        headers: { authorization: 'Bearer admin-secret' },
+        body: JSON.stringify({ logBodies: 2 }),
+      })
+      expect(response.status).toBe(200)
+      expect(logControlStatus().bodyLogsRemaining).toBe(2)
+    } finally {
+      state.adminToken = undefined
+    }
+  })
+})
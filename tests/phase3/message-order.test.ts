@@ -0,0 +1,69 @@
+import { test, expect, describe } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+const toolCall = (id: string) => ({ id, type: 'function', function: { name: 'lookup', arguments: '{}' } })
+
+const validate = (messages: Array<Record<string, unknown>>) =>
+  rustCore.validatePayloadDetailed({ model: 'gpt-4.1', messages })
+
+const codes = (violations: Array<{ code: string; index?: number }> = []) =>
+  violations.map(({ code, index }) => [code, index])
+
+describe('Phase 3: Message Order Validation', () => {
+  test('should accept tool results that answer earlier tool calls', async () => {
+    const result = await validate([
+      { role: 'system', content: 'Be helpful.' },
+      { role: 'user', content: 'Look up a and b' },
+      { role: 'assistant', content: null, tool_calls: [toolCall('call_a'), toolCall('call_b')] },
+      { role: 'tool', tool_call_id: 'call_a', content: '1' },
+      { role: 'tool', tool_call_id: 'call_b', content: '2' },
+      { role: 'assistant', content: 'Done.' },
+    ])
+
+    expect(result).toEqual({ valid: true, contentType: 'tools' })
+  })
+
+  test('should reject tool messages without a matching tool call', async () => {
+    const result = await validate([
+      { role: 'user', content: 'Hi' },
+      { role: 'tool', tool_call_id: 'call_a', content: '1' },
+      { role: 'assistant', content: null, tool_calls: [toolCall('call_a')] },
+      { role: 'tool', content: '2' },
+    ])
+
+    expect(result.valid).toBe(false)
+    expect(codes(result.errors)).toEqual([
+      ['unknown_tool_call_id', 1],
+      ['missing_tool_call_id', 3],
+    ])
+    expect(result.error).toContain('Message 1: No preceding assistant message made the tool call call_a')
+  })
+
+  test('should require the user to speak first', async () => {
+    const result = await validate([
+      { role: 'system', content: 'Be helpful.' },
+      { role: 'assistant', content: 'Hello!' },
+      { role: 'user', content: 'Hi' },
+    ])
+
+    expect(result.valid).toBe(false)
+    expect(codes(result.errors)).toEqual([['first_message_not_user', 1]])
+  })
+
+  test('should flag consecutive same-role messages without rejecting them', async () => {
+    const result = await validate([
+      { role: 'user', content: 'Hi' },
+      { role: 'user', content: 'Are you there?' },
+    ])
+
+    expect(result.valid).toBe(true)
+    expect(result.error).toBeUndefined()
+    expect(result.errors).toBeUndefined()
+    expect(codes(result.warnings)).toEqual([['consecutive_same_role', 1]])
+  })
+
+  test('should agree with the boolean validator', async () => {
+    expect(await rustCore.validatePayload({ model: 'gpt-4.1', messages: [{ role: 'assistant', content: 'Hi' }] })).toBe(false)
+    expect(await rustCore.validatePayload({ model: 'gpt-4.1', messages: [{ role: 'user', content: 'Hi' }, { role: 'user', content: 'Hi' }] })).toBe(true)
+  })
+})
@@ -0,0 +1,45 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { isModelAllowed, ruleFor } from '../../src/lib/model-policy'
+import { state } from '../../src/lib/state'
+import type { Model } from '../../src/services/copilot/get-models'
+
+const model = (id: string) => ({ id } as Model)
+
+describe('Phase 3: Model Allow/Deny Policy', () => {
+  afterEach(() => {
+    state.modelPolicy = undefined
+    state.models = undefined
+  })
+
+  test('should apply allow lists, deny lists and prefix patterns', () => {
+    expect(isModelAllowed({ allow: ['gpt-4o-mini'] }, 'gpt-4o-mini')).toBe(true)
+    expect(isModelAllowed({ allow: ['gpt-4o-mini'] }, 'gpt-4o')).toBe(false)
+    expect(isModelAllowed({ deny: ['o1*'] }, 'o1-preview')).toBe(false)
+    expect(isModelAllowed({ allow: ['claude-*'], deny: ['claude-opus-4'] }, 'claude-opus-4')).toBe(false)
+    expect(isModelAllowed({}, 'anything')).toBe(true)
+  })
+
+  test('should fall back to the default rule for unknown keys', () => {
+    const policy = { default: { deny: ['o1'] }, keys: { intern: { allow: ['gpt-4o-mini'] } } }
+    expect(ruleFor(policy, 'intern')).toEqual({ allow: ['gpt-4o-mini'] })
+    expect(ruleFor(policy, 'other')).toEqual({ deny: ['o1'] })
+    expect(ruleFor(policy, undefined)).toEqual({ deny: ['o1'] })
+  })
+
+  test('should reject disallowed models with a 403 listing permitted models', async () => {
+    state.models = { object: 'list', data: [model('gpt-4o'), model('gpt-4o-mini')] }
+    state.modelPolicy = { keys: { intern: { allow: ['gpt-4o-mini'] } } }
+
+    const response = await server.request('/v1/chat/completions', {
+      method: 'POST',
+      headers: { 'content-type': 'application/json', authorization: 'Bearer intern' },
+      body: JSON.stringify({ model: 'gpt-4o', messages: [{ role: 'user', content: 'hi' }] }),
+    })
+
+    expect(response.status).toBe(403)
+    const body = await response.json() as { error: { code: string; permitted_models: Array<string> } }
+    expect(body.error.code).toBe('model_not_allowed')
+    expect(body.error.permitted_models).toEqual(['gpt-4o-mini'])
+  })
+})
@@ -0,0 +1,139 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { parseMapping } from '../../src/lib/env-config'
+import { state } from '../../src/lib/state'
+import { aliasesFor, getCachedModels, resolveModel } from '../../src/lib/utils'
+import type { Model } from '../../src/services/copilot/get-models'
+
+const model = (id: string, vision: boolean): Model => ({
+  id,
+  name: id.toUpperCase(),
+  object: 'model',
+  vendor: 'test',
+  version: id,
+  preview: false,
+  model_picker_enabled: true,
+  capabilities: {
+    family: id,
+    object: 'model_capabilities',
+    tokenizer: 'o200k_base',
+    type: 'chat',
+    limits: { max_context_window_tokens: 128000, max_output_tokens: 4096 },
+    supports: { vision, tool_calls: true },
+  },
+})
+
+describe('Phase 3: Models Cache and Enrichment', () => {
+  afterEach(() => {
+    state.models = undefined
+    state.modelsCachedAt = undefined
+    state.modelAliases = undefined
+    state.defaultModel = undefined
+  })
+
+  test('should parse alias lists', () => {
+    expect(parseMapping('fast=gpt-4o-mini, smart=gpt-4o')).toEqual({
+      fast: 'gpt-4o-mini',
+      smart: 'gpt-4o',
+    })
+    expect(parseMapping('fast')).toBeUndefined()
+    expect(parseMapping('a=b=c')).toBeUndefined()
+  })
+
+  test('should resolve aliases before the default model', () => {
+    state.models = { object: 'list', data: [model('gpt-4o', true)] }
+    state.modelAliases = { smart: 'gpt-4o' }
+    state.defaultModel = 'gpt-4o-mini'
+
+    expect(resolveModel('smart')).toBe('gpt-4o')
+    expect(resolveModel('unknown')).toBe('gpt-4o-mini')
+    expect(aliasesFor('gpt-4o')).toEqual(['smart'])
+  })
+
+  test('should use the default model when the request names none', () => {
+    expect(resolveModel('')).toBe('')
+
+    state.defaultModel = 'gpt-4o-mini'
+    expect(resolveModel('')).toBe('gpt-4o-mini')
+
+    state.models = { object: 'list', data: [model('gpt-4o', true)] }
+    expect(resolveModel('')).toBe('gpt-4o-mini')
+  })
+
+  test('should keep an explicit model the list knows, or any model without a list', () => {
+    expect(resolveModel('gpt-4o')).toBe('gpt-4o')
+
+    state.defaultModel = 'gpt-4o-mini'
+    expect(resolveModel('o3-preview')).toBe('o3-preview')
+
+    state.models = { object: 'list', data: [model('gpt-4o', true)] }
+    expect(resolveModel('gpt-4o')).toBe('gpt-4o')
+    expect(resolveModel('o3-preview')).toBe('gpt-4o-mini')
+  })
+
+  test('should follow an alias even when its target is not in the list', () => {
+    state.models = { object: 'list', data: [model('gpt-4o', true)] }
+    state.modelAliases = { fast: 'gpt-4o-mini' }
+    state.defaultModel = 'claude-sonnet-4'
+
+    expect(resolveModel('fast')).toBe('gpt-4o-mini')
+    expect(resolveModel('gpt-4o')).toBe('gpt-4o')
+    expect(resolveModel('slow')).toBe('claude-sonnet-4')
+  })
+
+  test('should serve the stale list while refreshing in the background', async () => {
+    const cached = { object: 'list', data: [model('gpt-4o', true)] }
+    state.models = cached
+    state.modelsCachedAt = 0
+
+    // The refresh fails without a Copilot token, the cached list survives
+    expect(await getCachedModels()).toBe(cached)
+    await new Promise((resolve) => setTimeout(resolve, 50))
+    expect(state.models).toBe(cached)
+  })
+
+  test('should enrich /v1/models entries with gateway metadata', async () => {
+    state.models = { object: 'list', data: [model('gpt-4o', true), model('gpt-4o-mini', false)] }
+    state.modelsCachedAt = Date.now()
+    state.modelAliases = { fast: 'gpt-4o-mini' }
+    state.defaultModel = 'gpt-4o'
+
+    const response = await server.request('/v1/models')
+    const body = await response.json() as {
+      data: Array<{ id: string; aliases: Array<string>; default: boolean; capabilities: Record<string, unknown> }>
+    }
+
+    const [gpt4o, mini] = body.data
+    expect(gpt4o.default).toBe(true)
+    expect(gpt4o.capabilities).toEqual({
+      vision: true,
+      tool_calls: true,
+      context_window: 128000,
+      max_output_tokens: 4096,
+    })
+    expect(mini.aliases).toEqual(['fast'])
+    expect(mini.capabilities.vision).toBe(false)
+  })
+
+  test('should answer If-None-Match with 304 while the list is unchanged', async () => {
+    state.models = { object: 'list', data: [model('gpt-4o', true)] }
+    state.modelsCachedAt = Date.now()
+
+    const first = await server.request('/v1/models')
+    const tag = first.headers.get('etag')
+    expect(tag).toBeTruthy()
+
+    const unchanged = await server.request('/v1/models', {
+      headers: { 'if-none-match': tag! },
+    })
+    expect(unchanged.status).toBe(304)
+    expect(await unchanged.text()).toBe('')
+
+    state.models = { object: 'list', data: [model('gpt-4o-mini', false)] }
+    const changed = await server.request('/v1/models', {
+      headers: { 'if-none-match': tag! },
+    })
+    expect(changed.status).toBe(200)
+    expect(changed.headers.get('etag')).not.toBe(tag)
+  })
+})
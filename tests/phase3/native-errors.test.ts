@@ -0,0 +1,105 @@
+import { test, expect, describe } from 'bun:test'
+import {
+  AuthError,
+  InternalError,
+  NativeError,
+  RateLimitError,
+  UpstreamError,
+  ValidationError,
+  rustCore,
+  toNativeError,
+} from '../../src/lib/rust-core'
+
+const caught = (fn: () => unknown) => {
+  try {
+    fn()
+  } catch (error) {
+    return error as NativeError
+  }
+  throw new Error('expected a throw')
+}
+
+describe('Phase 3: Native Errors', () => {
+  test('should throw ValidationError for bad options', () => {
+    const error = caught(() => rustCore.withRetry({ baseDelayMs: 10, maxDelayMs: 1 }))
+
+    expect(error).toBeInstanceOf(ValidationError)
+    expect(error).toBeInstanceOf(NativeError)
+    expect(error.name).toBe('ValidationError')
+    expect(error.code).toBe('ERR_NATIVE_VALIDATION')
+    expect(error.message).toBe('baseDelayMs must not exceed maxDelayMs')
+  })
+
+  test('should throw ValidationError for an SSE chunk of the wrong type', () => {
+    const transformer = rustCore.createSseTransformer()
+    const error = caught(() => transformer.push(42 as unknown as string))
+
+    expect(error).toBeInstanceOf(ValidationError)
+    expect(error.code).toBe('ERR_NATIVE_VALIDATION')
+    expect(error.message).toBe('Chunk must be a string or Uint8Array')
+  })
+
+  test('should throw RateLimitError for a bad limiter config', () => {
+    const error = caught(() => rustCore.setRateLimitDefaults({ global: { intervalSecs: 0 } }))
+
+    expect(error).toBeInstanceOf(RateLimitError)
+    expect(error.code).toBe('ERR_NATIVE_RATE_LIMIT')
+  })
+
+  test('should reject explicit intervals and bursts below 1', async () => {
+    const key = `bad-interval-${Date.now()}`
+    await expect(rustCore.checkRateLimit(key, 0)).rejects.toThrow('intervalSecs must be at least 1, got 0')
+    await expect(rustCore.checkRateLimit(key, Number.NaN)).rejects.toThrow('intervalSecs must be at least 1')
+    await expect(rustCore.checkRateLimitDetailed(key, 10, -1)).rejects.toThrow('burstCapacity must be at least 1')
+  })
+
+  test('should reject request handles with UpstreamError', async () => {
+    const policy = rustCore.withRetry({})
+    rustCore.withRetry({ maxRetries: 0 })
+    const handle = rustCore.createChatCompletions(
+      { model: 'gpt-4.1', messages: [] },
+      { baseUrl: 'http://127.0.0.1:1' },
+    )
+
+    const error = await handle.response.catch((error) => error)
+    rustCore.withRetry(policy)
+    expect(error).toBeInstanceOf(UpstreamError)
+    expect(error.code).toBe('ERR_NATIVE_UPSTREAM')
+  })
+
+  test('should keep AbortError for cancelled requests', async () => {
+    const controller = new AbortController()
+    const handle = rustCore.createChatCompletions(
+      { model: 'gpt-4.1', messages: [] },
+      { baseUrl: 'http://10.255.255.1', signal: controller.signal },
+    )
+    controller.abort()
+
+    const error = await handle.response.catch((error) => error)
+    expect(error).not.toBeInstanceOf(NativeError)
+    expect(error.name).toBe('AbortError')
+  })
+
+  test('should map native errors by code', () => {
+    const auth = Object.assign(new Error('no token'), { code: 'ERR_NATIVE_AUTH' })
+
+    const mapped = toNativeError(auth) as AuthError
+    expect(mapped).toBeInstanceOf(AuthError)
+    expect(mapped.message).toBe('no token')
+    expect(mapped.cause).toBe(auth)
+  })
+
+  test('should map Neon panics to InternalError', () => {
+    const mapped = toNativeError(new Error('internal error in Neon module: index out of bounds'))
+
+    expect(mapped).toBeInstanceOf(InternalError)
+    expect((mapped as InternalError).code).toBe('ERR_NATIVE_INTERNAL')
+    expect((mapped as InternalError).message).toBe('panic in native module: index out of bounds')
+  })
+
+  test('should pass other errors through', () => {
+    const error = new TypeError('missing field `role`')
+    expect(toNativeError(error)).toBe(error)
+    expect(toNativeError('not an error')).toBe('not an error')
+  })
+})
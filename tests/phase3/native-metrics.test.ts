@@ -0,0 +1,58 @@
+import { test, expect, describe } from 'bun:test'
+import { getStats, renderPrometheus } from '../../src/lib/metrics'
+import { rustCore } from '../../src/lib/rust-core'
+
+describe('Phase 3: Native Metrics', () => {
+  test('should count tokenizer calls and their time', async () => {
+    const before = rustCore.getNativeMetrics().tokenizer
+    await rustCore.getTokenCount([{ role: 'user', content: 'Hello world' }])
+    const after = rustCore.getNativeMetrics().tokenizer
+
+    expect(after.calls).toBe(before.calls + 1)
+    expect(after.seconds).toBeGreaterThan(before.seconds)
+  })
+
+  test('should count validation failures by reason', async () => {
+    const before = rustCore.getNativeMetrics().validationFailures
+    await rustCore.validatePayloadDetailed({ model: 'gpt-4.1', messages: [{ role: 'assistant', content: 'Hi' }] })
+    await rustCore.validatePayload({})
+    await rustCore.validatePayload({ model: 'gpt-4.1', messages: [{ role: 'user', content: 'Hi' }] })
+    const after = rustCore.getNativeMetrics().validationFailures
+
+    expect(after.first_message_not_user).toBe((before.first_message_not_user ?? 0) + 1)
+    expect(after.invalid_payload).toBe((before.invalid_payload ?? 0) + 1)
+  })
+
+  test('should count rate limit rejections', async () => {
+    const key = `metrics-${Date.now()}`
+    const before = rustCore.getNativeMetrics().rateLimit
+    await rustCore.checkRateLimit(key, 60, 1)
+    await rustCore.checkRateLimit(key, 60, 1)
+    await rustCore.checkRateLimitDetailed(key, 60, 1)
+    const after = rustCore.getNativeMetrics().rateLimit
+
+    expect(after.allowed - before.allowed).toBe(1)
+    expect(after.rejected - before.rejected).toBe(2)
+  })
+
+  test('should count cancelled upstream requests', async () => {
+    const before = rustCore.getNativeMetrics().upstream
+    const controller = new AbortController()
+    const { response } = rustCore.createChatCompletions(
+      { model: 'gpt-4.1', messages: [] },
+      // Unroutable, so the request is still connecting when aborted
+      { baseUrl: 'http://10.255.255.1', signal: controller.signal },
+    )
+    controller.abort()
+    await response.catch(() => undefined)
+    await Bun.sleep(50)
+
+    expect(rustCore.getNativeMetrics().upstream.cancelled).toBe(before.cancelled + 1)
+  })
+
+  test('should be reported with the server metrics', () => {
+    expect(getStats().native?.tokenizer.calls).toBeGreaterThan(0)
+    expect(renderPrometheus()).toContain('copilot_api_native_rate_limit_checks_total{result="rejected"}')
+    expect(renderPrometheus()).toMatch(/copilot_api_native_validation_failures_total\{reason="invalid_payload"\} \d+/)
+  })
+})
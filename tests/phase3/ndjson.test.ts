@@ -0,0 +1,60 @@
+import { test, expect, describe } from 'bun:test'
+import { Hono } from 'hono'
+import { streamChunks, streamFormatOf } from '../../src/lib/ndjson'
+
+const chunks = [
+  { data: '{"id":"1","choices":[{"index":0,"delta":{"content":"Hel"}}]}' },
+  { data: '{"id":"1","choices":[{"index":0,"delta":{"content":"lo"}}]}', id: 's:1' },
+  { data: '[DONE]' },
+]
+
+function createApp(fail = false): Hono {
+  const app = new Hono()
+  app.post('/', async (c) => {
+    const body = await c.req.json<{ stream_format?: string }>()
+    return streamChunks(c, streamFormatOf(c, body), async (stream) => {
+      for (const chunk of chunks) await stream.writeSSE(chunk)
+      if (fail) throw new Error('upstream went away')
+    })
+  })
+  return app
+}
+
+const post = (app: Hono, body: object, headers: Record<string, string> = {}) =>
+  app.request('/', { method: 'POST', body: JSON.stringify(body), headers })
+
+describe('Phase 3: NDJSON Streaming', () => {
+  test('should stream SSE by default', async () => {
+    const response = await post(createApp(), {})
+    expect(response.headers.get('content-type')).toContain('text/event-stream')
+    expect(await response.text()).toContain('data: [DONE]')
+  })
+
+  test('should write one chunk per line with stream_format', async () => {
+    const response = await post(createApp(), { stream_format: 'ndjson' })
+    expect(response.headers.get('content-type')).toBe('application/x-ndjson')
+
+    const lines = (await response.text()).split('\n')
+    expect(lines.pop()).toBe('')
+    expect(lines.map((line) => JSON.parse(line).choices[0].delta.content)).toEqual(['Hel', 'lo'])
+  })
+
+  test('should honour the Accept header unless SSE is also accepted', async () => {
+    const ndjson = await post(createApp(), {}, { accept: 'application/x-ndjson' })
+    expect(ndjson.headers.get('content-type')).toBe('application/x-ndjson')
+
+    const both = await post(createApp(), {}, { accept: 'text/event-stream, application/x-ndjson' })
+    expect(both.headers.get('content-type')).toContain('text/event-stream')
+
+    const explicit = await post(createApp(), { stream_format: 'sse' }, { accept: 'application/x-ndjson' })
+    expect(explicit.headers.get('content-type')).toContain('text/event-stream')
+  })
+
+  test('should end with an error line when the stream fails', async () => {
+    const response = await post(createApp(true), { stream_format: 'ndjson' })
+    const lines = (await response.text()).trim().split('\n')
+    expect(JSON.parse(lines.at(-1) as string)).toEqual({
+      error: { message: 'upstream went away', type: 'error' },
+    })
+  })
+})
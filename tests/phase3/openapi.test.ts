@@ -0,0 +1,45 @@
+import { test, expect, describe } from 'bun:test'
+import { server } from '../../src/server'
+import { openApiDocument } from '../../src/routes/openapi/spec'
+
+describe('Phase 3: OpenAPI Document', () => {
+  test('should be served at /openapi.json', async () => {
+    const response = await server.request('/openapi.json')
+    expect(response.status).toBe(200)
+
+    const document = await response.json() as typeof openApiDocument
+    expect(document.openapi).toBe('3.1.0')
+    expect(document.components.schemas.Error.required).toEqual(['error'])
+  })
+
+  test('should describe every registered route', () => {
+    const documented = openApiDocument.paths as Record<string, Record<string, unknown>>
+    const routes = server.routes
+      .filter((route) => route.method !== 'ALL')
+      .map((route) => ({
+        path: (route.path.length > 1 ? route.path.replace(/\/$/, '') : route.path)
+          .replace(/:(\w+)/g, '{$1}'),
+        method: route.method.toLowerCase(),
+      }))
+      // Swagger UI is optional and not part of the API
+      .filter((route) => route.path !== '/docs')
+
+    expect(routes.length).toBeGreaterThan(0)
+    for (const route of routes) {
+      expect(documented[route.path]?.[route.method], `${route.method} ${route.path}`).toBeDefined()
+    }
+  })
+
+  test('should only resolve local schema references', () => {
+    const refs = JSON.stringify(openApiDocument).match(/"\$ref":"[^"]+"/g) ?? []
+    const schemas = Object.keys(openApiDocument.components.schemas)
+    for (const ref of refs) {
+      const name = ref.slice('"$ref":"#/components/schemas/'.length, -1)
+      expect(schemas).toContain(name)
+    }
+  })
+
+  test('should hide Swagger UI unless enabled', async () => {
+    expect((await server.request('/docs')).status).toBe(404)
+  })
+})
@@ -0,0 +1,80 @@
+import { test, expect, describe } from 'bun:test'
+import { applyParamDefaults, applyParamRules, defaultsFor, paramRulesOf } from '../../src/lib/param-policy'
+import type { ChatCompletionsPayload } from '../../src/services/copilot/create-chat-completions'
+
+const payload = (model: string, params: Partial<ChatCompletionsPayload> = {}): ChatCompletionsPayload => ({
+  model,
+  messages: [{ role: 'user', content: 'hi' }],
+  ...params,
+})
+
+describe('Phase 3: Parameter Policy', () => {
+  test('should strip sampling parameters for reasoning models by default', () => {
+    const result = applyParamRules(
+      payload('o3-mini', { temperature: 0.2, top_p: 1, logit_bias: { '50256': -100 }, max_tokens: 100 }),
+      paramRulesOf(undefined),
+    )
+    expect(result.payload).toEqual(payload('o3-mini', { max_tokens: 100 }))
+    expect(result.adjustments).toEqual(['stripped=temperature', 'stripped=top_p', 'stripped=logit_bias'])
+  })
+
+  test('should leave other models alone by default', () => {
+    const original = payload('gpt-4o', { temperature: 0.2 })
+    expect(applyParamRules(original, paramRulesOf(undefined))).toEqual({ payload: original, adjustments: [] })
+  })
+
+  test('should clamp and override in order after stripping', () => {
+    const rules = paramRulesOf({
+      rules: [
+        { models: ['claude-*'], clamp: { temperature: { min: 0, max: 1 }, top_p: { max: 1 } } },
+        { models: ['claude-sonnet-4'], override: { top_p: 0.9 } },
+      ],
+    })
+    const result = applyParamRules(payload('claude-sonnet-4', { temperature: 1.5, top_p: 0.5 }), rules)
+    expect(result.payload.temperature).toBe(1)
+    expect(result.payload.top_p).toBe(0.9)
+    expect(result.adjustments).toEqual(['clamped=temperature', 'overridden=top_p'])
+  })
+
+  test('should drop built-in rules when asked', () => {
+    const rules = paramRulesOf({ builtin: false })
+    expect(rules).toEqual([])
+    expect(applyParamRules(payload('o1', { temperature: 1 }), rules).payload.temperature).toBe(1)
+  })
+
+  test('should fill in omitted parameters from the defaults', () => {
+    const result = applyParamDefaults(payload('gpt-4.1', { temperature: 0.9 }), {
+      temperature: 0.2,
+      top_p: 0.5,
+      max_tokens: 1000,
+    })
+    expect(result.payload).toEqual(payload('gpt-4.1', { temperature: 0.9, top_p: 0.5, max_tokens: 1000 }))
+    expect(result.adjustments).toEqual(['defaulted=top_p', 'defaulted=max_tokens'])
+  })
+
+  test('should add the default system prompt only without one', () => {
+    const defaults = { system: 'Be concise.' }
+    const added = applyParamDefaults(payload('gpt-4.1'), defaults)
+    expect(added.payload.messages).toEqual([
+      { role: 'system', content: 'Be concise.' },
+      { role: 'user', content: 'hi' },
+    ])
+    expect(added.adjustments).toEqual(['defaulted=system'])
+
+    const own = payload('gpt-4.1', { messages: [{ role: 'developer', content: 'Be verbose.' }, { role: 'user', content: 'hi' }] })
+    expect(applyParamDefaults(own, defaults)).toEqual({ payload: own, adjustments: [] })
+  })
+
+  test('should pick defaults by API key', () => {
+    const policy = { keys: { 'sk-editor': { temperature: 0.2 } }, defaults: { temperature: 0.7 } }
+    expect(defaultsFor(policy, 'sk-editor')).toEqual({ temperature: 0.2 })
+    expect(defaultsFor(policy, 'sk-other')).toEqual({ temperature: 0.7 })
+    expect(defaultsFor(policy, undefined)).toEqual({ temperature: 0.7 })
+    expect(defaultsFor({ rules: [] }, 'sk-editor')).toBeUndefined()
+  })
+
+  test('should let rules strip defaulted parameters', () => {
+    const defaulted = applyParamDefaults(payload('o3-mini'), { temperature: 0.2 })
+    expect(applyParamRules(defaulted.payload, paramRulesOf(undefined)).payload.temperature).toBeUndefined()
+  })
+})
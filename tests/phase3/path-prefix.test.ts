@@ -0,0 +1,35 @@
+import { test, expect, describe } from 'bun:test'
+import { normalizePathPrefix } from '../../src/lib/path-prefix'
+import { mountServer } from '../../src/server'
+
+describe('Phase 3: Path Prefix', () => {
+  test('should normalize slashes', () => {
+    expect(normalizePathPrefix('copilot')).toBe('/copilot')
+    expect(normalizePathPrefix('/copilot/v1/')).toBe('/copilot/v1')
+    expect(normalizePathPrefix('//a//b')).toBe('/a/b')
+    expect(normalizePathPrefix('/')).toBeUndefined()
+    expect(normalizePathPrefix('')).toBeUndefined()
+    expect(normalizePathPrefix(undefined)).toBeUndefined()
+  })
+
+  test('should reject queries and fragments', () => {
+    expect(() => normalizePathPrefix('/copilot?x=1')).toThrow(TypeError)
+    expect(() => normalizePathPrefix('/copilot#top')).toThrow(TypeError)
+  })
+
+  test('should serve routes only under the prefix', async () => {
+    const app = mountServer('/copilot')
+
+    const prefixed = await app.request('/copilot')
+    expect(prefixed.status).toBe(200)
+    expect(await prefixed.text()).toBe('Server running')
+
+    const root = await app.request('/')
+    expect(root.status).toBe(404)
+  })
+
+  test('should serve at the root without a prefix', async () => {
+    const response = await mountServer(undefined).request('/')
+    expect(response.status).toBe(200)
+  })
+})
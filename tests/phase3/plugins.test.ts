@@ -0,0 +1,224 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs'
+import os from 'node:os'
+import path from 'node:path'
+import { forwardError } from '../../src/lib/error'
+import { parseJsonBody } from '../../src/lib/json-body'
+import { applyPlugins, loadPlugins, runPlugins, type Plugin } from '../../src/lib/plugins'
+import { clearSeenSignatures, signRequest, verifySignature } from '../../src/lib/request-signing'
+import { rustCore } from '../../src/lib/rust-core'
+import { state } from '../../src/lib/state'
+import { server } from '../../src/server'
+import { chatCompletion, fakeUpstream, streamScript, type FakeUpstream } from '../testkit/upstream'
+
+// The plugins feature is opt-in, see `bun run build:native:plugins`
+const pluginsBuilt = rustCore.pluginsAvailable()
+
+function fakePlugin(name: string, transform: (input: any) => unknown): Plugin {
+  return {
+    name,
+    hooks: ['request', 'response'],
+    run: (_hook, input) => {
+      const output = transform(JSON.parse(input))
+      return output === null ? null : JSON.stringify(output)
+    },
+  }
+}
+
+// Answers every request with a constant output stored at offset 16
+function watPlugin(output: string, transformBody = `(i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const ${output.length}))`) {
+  const escaped = output.replaceAll('"', '\\"')
+  const file = path.join(fs.mkdtempSync(path.join(os.tmpdir(), 'plugin-')), 'plugin.wat')
+  fs.writeFileSync(file, `(module
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    (data (i32.const 16) "${escaped}")
+    (func (export "alloc") (param $len i32) (result i32)
+      (local $ptr i32)
+      (local.set $ptr (global.get $next))
+      (global.set $next (i32.add (global.get $next) (local.get $len)))
+      (local.get $ptr))
+    (func (export "transform_request") (param i32 i32) (result i64)
+      ${transformBody}))`)
+  return file
+}
+
+function createApp() {
+  const app = new Hono()
+  app.use(runPlugins)
+  app.post('/echo', async (c) => c.json(await c.req.json()))
+  app.onError((error, c) => forwardError(c, error))
+  return app
+}
+
+const post = (app: Hono, body: unknown) =>
+  app.request('/echo', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify(body),
+  })
+
+const redactPrompts = () =>
+  fakePlugin('redact', (input) =>
+    input.status === undefined ?
+      {
+        body: {
+          ...input.body,
+          messages: input.body.messages.map((message: { content: string }) => ({
+            ...message,
+            content: message.content.replaceAll('hunter2', '[redacted]'),
+          })),
+        },
+      }
+    : null,
+  )
+
+const chat = (stream: boolean) =>
+  server.request('/v1/chat/completions', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ model: 'gpt-4o', stream, messages: [{ role: 'user', content: 'my password is hunter2' }] }),
+  })
+
+describe('Phase 3: WASM Plugins', () => {
+  const originalToken = state.copilotToken
+  let upstream: FakeUpstream | undefined
+
+  afterEach(() => {
+    upstream?.restore()
+    upstream = undefined
+    state.copilotToken = originalToken
+    state.plugins = undefined
+    state.requestSigning = undefined
+  })
+
+  test('should chain plugins and stop at the first rejection', () => {
+    const plugins = [
+      fakePlugin('route', (input) => ({ body: { ...input.body, model: 'gpt-4.1' } })),
+      fakePlugin('noop', () => null),
+      fakePlugin('guard', (input) =>
+        input.body.model === 'gpt-4.1' ? { reject: { status: 403, message: 'no' } } : null,
+      ),
+    ]
+
+    expect(applyPlugins(plugins.slice(0, 2), 'request', { path: '/', body: { model: 'x' } })).toEqual({
+      body: { model: 'gpt-4.1' },
+    })
+    expect(applyPlugins(plugins, 'request', { path: '/', body: { model: 'x' } })).toEqual({
+      reject: { status: 403, message: 'no' },
+      plugin: 'guard',
+    })
+  })
+
+  test('should rewrite requests and responses', async () => {
+    state.plugins = [
+      fakePlugin('redact', (input) =>
+        input.status === undefined ?
+          { body: { ...input.body, secret: '[redacted]' } }
+        : { body: { ...input.body, seen: input.status } },
+      ),
+    ]
+
+    const response = await post(createApp(), { secret: 'hunter2' })
+    expect(await response.json()).toEqual({ secret: '[redacted]', seen: 200 })
+  })
+
+  test('should rewrite bodies that request signing already read', async () => {
+    clearSeenSignatures()
+    state.requestSigning = { clients: { ci: { secret: 'ci-secret' } } }
+    state.plugins = [fakePlugin('redact', (input) => ({ body: { ...input.body, secret: '[redacted]' } }))]
+    const app = new Hono()
+    app.use(verifySignature)
+    app.use(runPlugins)
+    app.use(parseJsonBody)
+    app.post('/echo', async (c) => c.json({ json: await c.req.json(), text: await c.req.text() }))
+    app.onError((error, c) => forwardError(c, error))
+
+    const body = JSON.stringify({ secret: 'hunter2' })
+    const response = await app.request('/echo', {
+      method: 'POST',
+      headers: {
+        'content-type': 'application/json',
+        'x-signature': signRequest('ci', 'ci-secret', { method: 'POST', path: '/echo', body }),
+      },
+      body,
+    })
+
+    expect(response.status).toBe(200)
+    expect(await response.json()).toEqual({
+      json: { secret: '[redacted]' },
+      text: JSON.stringify({ secret: '[redacted]' }),
+    })
+  })
+
+  test('should send the redacted prompt upstream', async () => {
+    state.copilotToken = 'test-copilot-token'
+    state.plugins = [redactPrompts()]
+    upstream = fakeUpstream([chatCompletion({ content: 'ok' })])
+
+    const response = await chat(false)
+
+    expect(response.status).toBe(200)
+    expect(upstream.requests[0].messages).toEqual([{ role: 'user', content: 'my password is [redacted]' }])
+  })
+
+  test('should stream responses untouched', async () => {
+    state.copilotToken = 'test-copilot-token'
+    state.plugins = [redactPrompts()]
+    upstream = fakeUpstream([streamScript({ text: ['o', 'k'] })])
+
+    const response = await chat(true)
+
+    expect(response.status).toBe(200)
+    expect(response.headers.get('content-type')).toStartWith('text/event-stream')
+    expect(await response.text()).toContain('data: [DONE]')
+    expect(upstream.requests[0].messages).toEqual([{ role: 'user', content: 'my password is [redacted]' }])
+  })
+
+  test('should answer rejections with an OpenAI-style error', async () => {
+    state.plugins = [fakePlugin('guard', () => ({ reject: { status: 403, message: 'Blocked' } }))]
+
+    const response = await post(createApp(), {})
+    expect(response.status).toBe(403)
+    expect(await response.json()).toEqual({ error: { message: 'Blocked', type: 'invalid_request_error' } })
+  })
+
+  test('should fail closed when a plugin breaks', async () => {
+    state.plugins = [{ name: 'broken', hooks: ['request'], run: () => 'not json' }]
+
+    const response = await post(createApp(), {})
+    expect(response.status).toBe(500)
+    const body = (await response.json()) as { error: { message: string } }
+    expect(body.error.message).toContain('Plugin broken failed')
+  })
+
+  test.skipIf(!pluginsBuilt)('should run WASM modules in the native module', async () => {
+    const [plugin] = loadPlugins([watPlugin('{"body":{"model":"gpt-4.1"}}')])
+    expect(plugin!.hooks).toEqual(['request'])
+    state.plugins = [plugin!]
+
+    const response = await post(createApp(), { model: 'gpt-4o' })
+    expect(await response.json()).toEqual({ model: 'gpt-4.1' })
+  })
+
+  test.skipIf(!pluginsBuilt)('should stop plugins that run out of fuel', () => {
+    const [plugin] = loadPlugins([watPlugin('', '(loop $spin (br $spin)) (i64.const 0)')])
+    expect(() => applyPlugins([plugin!], 'request', { path: '/', body: {} })).toThrow(/fuel/)
+  })
+
+  test.skipIf(!pluginsBuilt)('should refuse answers over the limit or outside memory before reading them', () => {
+    // 4 GiB - 1 at offset 16, and 10 bytes past the single 64 KiB page
+    const huge = loadPlugins([watPlugin('', `(i64.const ${16 * 2 ** 32 + 0xffff_ffff})`)])
+    expect(() => applyPlugins(huge, 'request', { path: '/', body: {} })).toThrow(/over the 16777216 byte limit/)
+
+    const outside = loadPlugins([watPlugin('', `(i64.const ${131_072 * 2 ** 32 + 10})`)])
+    expect(() => applyPlugins(outside, 'request', { path: '/', body: {} })).toThrow(/outside the plugin's memory/)
+  })
+
+  test.skipIf(!pluginsBuilt)('should reject modules that do not fit the ABI', () => {
+    const file = path.join(fs.mkdtempSync(path.join(os.tmpdir(), 'plugin-')), 'empty.wat')
+    fs.writeFileSync(file, '(module (memory (export "memory") 1))')
+    expect(() => loadPlugins([file])).toThrow(/missing export `alloc`/)
+  })
+})
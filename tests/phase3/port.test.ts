@@ -0,0 +1,38 @@
+import { test, expect, describe } from 'bun:test'
+import net from 'node:net'
+import { parseIntegerOption } from '../../src/lib/env-config'
+import { resolvePort, urlHost } from '../../src/lib/port'
+
+describe('Phase 3: Port Selection', () => {
+  test('should reject ports that are not integers in range', () => {
+    expect(parseIntegerOption('--port', '8080', 0, 65535)).toBe(8080)
+    expect(parseIntegerOption('--port', '0', 0, 65535)).toBe(0)
+    for (const raw of ['abc', '80abc', '-1', '65536', '']) {
+      expect(() => parseIntegerOption('--port', raw, 0, 65535)).toThrow(
+        `--port: expected an integer between 0 and 65535, got "${raw}"`,
+      )
+    }
+    expect(() => parseIntegerOption('--port-retry', '1001', 0, 1000)).toThrow('--port-retry')
+  })
+
+  test('should pick a free port for port 0 and retry taken ones', async () => {
+    const taken = net.createServer()
+    await new Promise<void>((resolve) => taken.listen({ port: 0, host: '127.0.0.1' }, resolve))
+    const { port } = taken.address() as net.AddressInfo
+    try {
+      expect(await resolvePort(0, 0, '127.0.0.1')).toBeGreaterThan(0)
+      await expect(resolvePort(port, 0, '127.0.0.1')).rejects.toThrow(`Port ${port} is already in use`)
+      expect(await resolvePort(port, 5, '127.0.0.1')).toBeGreaterThan(port)
+    } finally {
+      taken.close()
+    }
+  })
+
+  test('should name the bound host in URLs', () => {
+    expect(urlHost(undefined)).toBe('localhost')
+    expect(urlHost('0.0.0.0')).toBe('localhost')
+    expect(urlHost('::')).toBe('localhost')
+    expect(urlHost('192.168.1.20')).toBe('192.168.1.20')
+    expect(urlHost('::1')).toBe('[::1]')
+  })
+})
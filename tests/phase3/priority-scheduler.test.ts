@@ -0,0 +1,64 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import { acquireSlot, configureScheduler, scheduleByPriority, schedulerStats } from '../../src/lib/scheduler'
+
+describe('Phase 3: Priority Scheduler', () => {
+  afterEach(() => {
+    configureScheduler({ maxConcurrent: undefined, keys: new Map() })
+  })
+
+  test('should not queue when no limit is configured', async () => {
+    const releases = await Promise.all([1, 2, 3].map(() => acquireSlot('batch')))
+    expect(schedulerStats().active).toBe(3)
+    for (const release of releases) release()
+    expect(schedulerStats().active).toBe(0)
+  })
+
+  test('should let waiting interactive requests go before batch ones', async () => {
+    configureScheduler({ maxConcurrent: 1 })
+    const order: Array<string> = []
+
+    const releaseFirst = await acquireSlot('batch')
+    const batch = acquireSlot('batch').then((release) => {
+      order.push('batch')
+      release()
+    })
+    const interactive = acquireSlot('interactive').then((release) => {
+      order.push('interactive')
+      release()
+    })
+    expect(schedulerStats().waiting).toEqual({ interactive: 1, batch: 1 })
+
+    releaseFirst()
+    await Promise.all([batch, interactive])
+
+    expect(order).toEqual(['interactive', 'batch'])
+    expect(schedulerStats().active).toBe(0)
+  })
+
+  test('should ignore a second release', async () => {
+    configureScheduler({ maxConcurrent: 2 })
+    const release = await acquireSlot('interactive')
+    release()
+    release()
+    expect(schedulerStats().active).toBe(0)
+  })
+
+  test('should hold the slot until a streamed body has been read', async () => {
+    configureScheduler({ maxConcurrent: 1, keys: new Map([['batch-key', 'batch']]) })
+    const app = new Hono()
+    app.use(scheduleByPriority)
+    app.get('/', () => new Response(new ReadableStream({
+      start(controller) {
+        controller.enqueue(new TextEncoder().encode('data'))
+        controller.close()
+      },
+    })))
+
+    const response = await app.request('/', { headers: { 'x-api-key': 'batch-key' } })
+    expect(schedulerStats().active).toBe(1)
+
+    expect(await response.text()).toBe('data')
+    expect(schedulerStats().active).toBe(0)
+  })
+})
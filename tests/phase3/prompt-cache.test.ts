@@ -0,0 +1,75 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { applyPromptCacheKey, synthesizeCacheKey } from '../../src/lib/prompt-cache'
+import { getStats, observePromptCache, renderPrometheus, resetMetrics } from '../../src/lib/metrics'
+import { state } from '../../src/lib/state'
+import { anthropicPromptCacheKey } from '../../src/routes/messages/utils'
+import type { ChatCompletionsPayload } from '../../src/services/copilot/create-chat-completions'
+import type { AnthropicMessagesPayload } from '../../src/routes/messages/anthropic-types'
+
+const payload = (user: string, system = 'You are helpful'): ChatCompletionsPayload => ({
+  model: 'gpt-4o',
+  messages: [
+    { role: 'system', content: system },
+    { role: 'user', content: user },
+  ],
+})
+
+describe('Phase 3: Prompt Cache Keys', () => {
+  afterEach(() => {
+    state.synthesizeCacheKey = undefined
+    resetMetrics()
+  })
+
+  test('should derive the same key for requests sharing a system prompt', () => {
+    const first = synthesizeCacheKey(payload('one'))
+    expect(first).toStartWith('gw-')
+    expect(synthesizeCacheKey(payload('two'))).toBe(first)
+    expect(synthesizeCacheKey(payload('one', 'Other system prompt'))).not.toBe(first)
+  })
+
+  test('should not derive a key without a stable prefix', () => {
+    expect(synthesizeCacheKey({ model: 'gpt-4o', messages: [{ role: 'user', content: 'hi' }] })).toBeUndefined()
+  })
+
+  test('should pass client keys through and only synthesize when enabled', () => {
+    const withKey = { ...payload('hi'), prompt_cache_key: 'client-key' }
+    expect(applyPromptCacheKey(withKey).prompt_cache_key).toBe('client-key')
+
+    expect(applyPromptCacheKey(payload('hi')).prompt_cache_key).toBeUndefined()
+    expect(applyPromptCacheKey(payload('hi'), 'hint').prompt_cache_key).toBe('hint')
+
+    state.synthesizeCacheKey = true
+    expect(applyPromptCacheKey(payload('hi')).prompt_cache_key).toBe(synthesizeCacheKey(payload('hi')))
+  })
+
+  test('should key Anthropic requests by the prefix up to the last cache_control block', () => {
+    const anthropic = (question: string): AnthropicMessagesPayload => ({
+      model: 'claude-sonnet-4',
+      max_tokens: 100,
+      messages: [
+        { role: 'user', content: [{ type: 'text', text: 'long document', cache_control: { type: 'ephemeral' } }] },
+        { role: 'user', content: question },
+      ],
+    })
+
+    const key = anthropicPromptCacheKey(anthropic('first question'))
+    expect(key).toBeDefined()
+    expect(anthropicPromptCacheKey(anthropic('second question'))).toBe(key)
+    expect(anthropicPromptCacheKey({ model: 'claude-sonnet-4', max_tokens: 100, messages: [{ role: 'user', content: 'hi' }] })).toBeUndefined()
+  })
+
+  test('should report cache hits in /stats and /metrics', () => {
+    observePromptCache('gpt-4o', { prompt_tokens: 1000, completion_tokens: 10, total_tokens: 1010, prompt_tokens_details: { cached_tokens: 800 } })
+    observePromptCache('gpt-4o', { prompt_tokens: 1000, completion_tokens: 10, total_tokens: 1010 })
+
+    expect(getStats().promptCache['gpt-4o']).toEqual({
+      requests: 2,
+      hits: 1,
+      promptTokens: 2000,
+      cachedTokens: 800,
+      hitRate: 0.5,
+      cachedTokenRatio: 0.4,
+    })
+    expect(renderPrometheus()).toContain('copilot_api_cached_prompt_tokens_total{model="gpt-4o"} 800')
+  })
+})
@@ -0,0 +1,116 @@
+import { test, expect, describe, beforeEach, afterEach, mock } from 'bun:test'
+import { Hono } from 'hono'
+import { checkQuota, quotaGuard, quotaGuardStatus, resetQuotaGuard } from '../../src/lib/quota-guard'
+import { configureScheduler } from '../../src/lib/scheduler'
+import { resetSloAlerts } from '../../src/lib/slo-alerts'
+import { state } from '../../src/lib/state'
+
+const originalFetch = globalThis.fetch
+let webhookPosts: Array<{ kind: string; text: string }>
+
+// The usage endpoint reports this share of premium requests remaining
+function mockUpstream(percentRemaining: number) {
+  globalThis.fetch = mock(async (url: string, init?: RequestInit) => {
+    if (url.includes('hooks.example.com')) {
+      webhookPosts.push(JSON.parse(init?.body as string))
+      return new Response('ok')
+    }
+    return Response.json({
+      quota_reset_date: '2099-01-01',
+      quota_snapshots: { premium_interactions: { percent_remaining: percentRemaining, unlimited: false } },
+    })
+  }) as unknown as typeof fetch
+}
+
+function createApp() {
+  const app = new Hono()
+  app.use(quotaGuard)
+  app.post('/', (c) => c.text('ok'))
+  return app
+}
+
+const send = (key: string) => createApp().request('/', { method: 'POST', headers: { authorization: `Bearer ${key}` } })
+
+describe('Phase 3: Quota Guard', () => {
+  beforeEach(() => {
+    resetQuotaGuard()
+    resetSloAlerts()
+    webhookPosts = []
+    state.githubToken = 'ghu_owner'
+    state.quotaGuard = { threshold: 0.9 }
+    configureScheduler({ keys: new Map([['sk-batch', 'batch']]) })
+  })
+
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    state.quotaGuard = undefined
+    state.sloAlerts = undefined
+    state.githubToken = undefined
+    configureScheduler({ keys: new Map() })
+  })
+
+  test('should let everything through below the threshold', async () => {
+    mockUpstream(50)
+    await checkQuota()
+
+    expect(quotaGuardStatus()).toEqual({ engaged: false, used: 0.5, resetDate: '2099-01-01' })
+    expect((await send('sk-batch')).status).toBe(200)
+  })
+
+  test('should reject batch traffic and keep interactive traffic', async () => {
+    mockUpstream(5)
+    await checkQuota()
+
+    const rejected = await send('sk-batch')
+    expect(rejected.status).toBe(429)
+    expect(Number(rejected.headers.get('retry-after'))).toBeGreaterThan(0)
+    const body = (await rejected.json()) as { error: { code: string } }
+    expect(body.error.code).toBe('quota_guard')
+
+    expect((await send('sk-person')).status).toBe(200)
+    expect((await createApp().request('/', { method: 'POST' })).status).toBe(200)
+  })
+
+  test('should queue batch traffic until the guard lifts', async () => {
+    state.quotaGuard = { threshold: 0.9, action: 'queue', maxQueueSeconds: 5 }
+    mockUpstream(5)
+    await checkQuota()
+
+    const pending = send('sk-batch')
+    await new Promise((resolve) => setTimeout(resolve, 20))
+    mockUpstream(100)
+    await checkQuota()
+
+    expect((await pending).status).toBe(200)
+  })
+
+  test('should reject queued traffic after the maximum wait', async () => {
+    state.quotaGuard = { threshold: 0.9, action: 'queue', maxQueueSeconds: 0.05 }
+    mockUpstream(5)
+    await checkQuota()
+
+    expect((await send('sk-batch')).status).toBe(429)
+  })
+
+  test('should notify the alert webhook when engaging and lifting', async () => {
+    state.sloAlerts = { webhook: 'https://hooks.example.com/alerts', cooldownSeconds: 0 }
+    mockUpstream(5)
+    await checkQuota()
+    mockUpstream(100)
+    await checkQuota()
+    await new Promise((resolve) => setTimeout(resolve, 10))
+
+    expect(webhookPosts.map((post) => post.kind)).toEqual(['quota', 'quota'])
+    expect(webhookPosts[0].text).toContain('95.0% of premium requests used, batch traffic is paused')
+    expect(webhookPosts[1].text).toContain('batch traffic resumed')
+  })
+
+  test('should keep the last state when usage cannot be read', async () => {
+    mockUpstream(5)
+    await checkQuota()
+    globalThis.fetch = mock(async () => new Response('down', { status: 503 })) as unknown as typeof fetch
+    await checkQuota()
+
+    expect(quotaGuardStatus().engaged).toBe(true)
+  })
+})
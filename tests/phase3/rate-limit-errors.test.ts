@@ -0,0 +1,64 @@
+import { test, expect, describe } from 'bun:test'
+import { Hono } from 'hono'
+import { forwardError, HTTPError, rateLimitErrorBody } from '../../src/lib/error'
+
+function appThrowing(response: Response) {
+  const app = new Hono()
+  app.get('/', (c) => forwardError(c, new HTTPError('Upstream failed', response)))
+  return app
+}
+
+describe('Phase 3: Rate Limit Errors', () => {
+  test('should keep the upstream message in an OpenAI-style body', () => {
+    const body = rateLimitErrorBody(
+      JSON.stringify({ error: { message: 'Too many requests' } }),
+    )
+    expect(body.error).toEqual({
+      message: 'Too many requests',
+      type: 'requests',
+      param: null,
+      code: 'rate_limit_exceeded',
+    })
+    expect(rateLimitErrorBody('{"message":"Rate limit exceeded"}').error.message).toBe('Rate limit exceeded')
+    expect(rateLimitErrorBody('slow down').error.message).toBe('slow down')
+  })
+
+  test('should forward Retry-After from the upstream 429', async () => {
+    const app = appThrowing(
+      new Response('quota exceeded', {
+        status: 429,
+        headers: { 'retry-after': '12' },
+      }),
+    )
+
+    const response = await app.request('/')
+    expect(response.status).toBe(429)
+    expect(response.headers.get('retry-after')).toBe('12')
+    expect(response.headers.get('retry-after-ms')).toBe('12000')
+    const body = (await response.json()) as { error: { code: string } }
+    expect(body.error.code).toBe('rate_limit_exceeded')
+  })
+
+  test('should derive Retry-After from x-ratelimit-reset', async () => {
+    const reset = Math.floor(Date.now() / 1000) + 30
+    const app = appThrowing(
+      new Response('{}', {
+        status: 429,
+        headers: { 'x-ratelimit-reset': String(reset) },
+      }),
+    )
+
+    const response = await app.request('/')
+    const seconds = Number(response.headers.get('retry-after'))
+    expect(seconds).toBeGreaterThan(25)
+    expect(seconds).toBeLessThanOrEqual(30)
+  })
+
+  test('should leave other errors unchanged', async () => {
+    const app = appThrowing(new Response('boom', { status: 502 }))
+
+    const response = await app.request('/')
+    expect(response.status).toBe(502)
+    expect(response.headers.get('retry-after')).toBeNull()
+  })
+})
@@ -0,0 +1,74 @@
+import { test, expect, describe, beforeEach } from 'bun:test'
+import {
+  formatResetDuration,
+  gatewayRateLimitHeaders,
+  mergeRateLimitHeaders,
+} from '../../src/lib/rate-limit-headers'
+import { chargeTokens, resetTokenBudgets } from '../../src/lib/token-budget'
+import { state } from '../../src/lib/state'
+
+describe('Phase 3: Rate Limit Headers', () => {
+  beforeEach(() => {
+    resetTokenBudgets()
+    state.rateLimitSeconds = undefined
+    state.lastRequestTimestamp = undefined
+    state.tokensPerMinute = undefined
+  })
+
+  test('should format reset durations like OpenAI', () => {
+    expect(formatResetDuration(120)).toBe('120ms')
+    expect(formatResetDuration(1500)).toBe('1.5s')
+    expect(formatResetDuration(360_000)).toBe('6m0s')
+    expect(formatResetDuration(3_725_000)).toBe('1h2m5s')
+  })
+
+  test('should emit nothing when no limit is configured', () => {
+    expect(gatewayRateLimitHeaders('anonymous')).toEqual({})
+  })
+
+  test('should report the request window', () => {
+    state.rateLimitSeconds = 30
+    state.lastRequestTimestamp = 10_000
+
+    expect(gatewayRateLimitHeaders('anonymous', 20_000)).toEqual({
+      'x-ratelimit-limit-requests': '1',
+      'x-ratelimit-remaining-requests': '0',
+      'x-ratelimit-reset-requests': '20s',
+    })
+    expect(
+      gatewayRateLimitHeaders('anonymous', 50_000)[
+        'x-ratelimit-remaining-requests'
+      ],
+    ).toBe('1')
+  })
+
+  test('should report the token bucket of the client', async () => {
+    state.tokensPerMinute = 1000
+    await chargeTokens('client', 250, 1000, () => 0)
+
+    expect(gatewayRateLimitHeaders('client', 0)).toEqual({
+      'x-ratelimit-limit-tokens': '1000',
+      'x-ratelimit-remaining-tokens': '750',
+      'x-ratelimit-reset-tokens': '15s',
+    })
+  })
+
+  test('should pass upstream headers through unless the gateway overrides them', () => {
+    const upstream = new Headers({
+      'x-ratelimit-remaining-requests': '42',
+      'x-ratelimit-reset': '1700000000',
+      'content-type': 'application/json',
+    })
+
+    expect(mergeRateLimitHeaders(upstream, {})).toEqual({
+      'x-ratelimit-remaining-requests': '42',
+      'x-ratelimit-reset': '1700000000',
+    })
+    expect(
+      mergeRateLimitHeaders(upstream, { 'x-ratelimit-remaining-requests': '0' }),
+    ).toEqual({
+      'x-ratelimit-remaining-requests': '0',
+      'x-ratelimit-reset': '1700000000',
+    })
+  })
+})
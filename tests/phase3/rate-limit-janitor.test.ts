@@ -0,0 +1,55 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+describe('Phase 3: Rate Limit Janitor', () => {
+  afterEach(() => {
+    rustCore.stopRateLimitJanitor()
+  })
+
+  test('background sweeps reclaim idle limiters and report the count', async () => {
+    await rustCore.resetAllRateLimits()
+    for (const key of ['janitor-a', 'janitor-b', 'janitor-c']) {
+      await rustCore.checkRateLimit(key, 10)
+    }
+    const before = await rustCore.getRateLimitStats()
+
+    const reclaimed = await new Promise<number>((resolve) => {
+      expect(rustCore.startRateLimitJanitor(0.05, 0.1, resolve)).toBe(true)
+    })
+    const after = await rustCore.getRateLimitStats()
+
+    expect(reclaimed).toBe(3)
+    expect(after.activeLimiters).toBe(0)
+    expect(after.reclaimedLimiters - before.reclaimedLimiters).toBe(3)
+    expect(after.janitorRunning).toBe(true)
+  })
+
+  test('only one janitor runs at a time', () => {
+    expect(rustCore.startRateLimitJanitor(60)).toBe(true)
+    expect(rustCore.startRateLimitJanitor(60)).toBe(false)
+    expect(rustCore.stopRateLimitJanitor()).toBe(true)
+    expect(rustCore.stopRateLimitJanitor()).toBe(false)
+    expect(() => rustCore.startRateLimitJanitor(0)).toThrow('intervalSecs')
+  })
+
+  test('a client that keeps hitting a denied bucket stays denied', async () => {
+    await rustCore.resetAllRateLimits()
+    rustCore.startRateLimitJanitor(60, 0.1)
+    expect(await rustCore.checkRateLimit('janitor-denied', 60, 1)).toBe(true)
+
+    // Three times the idle timeout, but never idle for that long
+    for (let attempt = 0; attempt < 15; attempt++) {
+      await Bun.sleep(20)
+      expect(await rustCore.checkRateLimit('janitor-denied', 60, 1)).toBe(false)
+    }
+  })
+
+  test('idle limiters are not counted as active', async () => {
+    await rustCore.resetAllRateLimits()
+    rustCore.startRateLimitJanitor(60, 0.05)
+    await rustCore.checkRateLimit('janitor-idle', 10)
+
+    await Bun.sleep(100)
+    expect((await rustCore.getRateLimitStats()).activeLimiters).toBe(0)
+  })
+})
@@ -0,0 +1,32 @@
+import { test, expect, describe } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+describe('Phase 3: Rate Limit Reset Patterns', () => {
+  test('exact keys remove a single limiter', async () => {
+    const key = `reset-exact-${Date.now()}`
+    await rustCore.checkRateLimit(key, 10)
+
+    expect(await rustCore.resetRateLimit(key)).toBe(1)
+    expect(await rustCore.resetRateLimit(key)).toBe(0)
+  })
+
+  test('globs remove every matching limiter', async () => {
+    const run = `reset${Date.now()}`
+    for (const key of [`${run}:user:a`, `${run}:user:b`, `${run}:user:ab`, `${run}:org:1`]) {
+      await rustCore.checkRateLimit(key, 10)
+    }
+
+    expect(await rustCore.resetRateLimit(`${run}:user:?`)).toBe(2)
+    expect(await rustCore.resetRateLimit(`${run}:user:*`)).toBe(1)
+    expect(await rustCore.resetRateLimit(`${run}:*`)).toBe(1)
+    expect(await rustCore.resetRateLimit(`${run}:*`)).toBe(0)
+  })
+
+  test('resetAllRateLimits clears every limiter', async () => {
+    await rustCore.checkRateLimit(`reset-all-a-${Date.now()}`, 10)
+    await rustCore.checkRateLimit(`reset-all-b-${Date.now()}`, 10)
+
+    expect(await rustCore.resetAllRateLimits()).toBeGreaterThanOrEqual(2)
+    expect((await rustCore.getRateLimitStats()).activeLimiters).toBe(0)
+  })
+})
@@ -0,0 +1,69 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { checkRateLimit } from '../../src/lib/rate-limit'
+import { features, rustCore } from '../../src/lib/rust-core'
+import { state } from '../../src/lib/state'
+
+describe('Phase 3: Rate Limit Wait Time', () => {
+  afterEach(async () => {
+    features.USE_RUST_RATE_LIMIT = false
+    state.rateLimitSeconds = undefined
+    state.rateLimitWait = false
+    await rustCore.resetRateLimit('gateway')
+  })
+
+  test('should report remaining tokens and the wait for the next one', async () => {
+    const key = `wait-${Date.now()}`
+
+    expect(await rustCore.checkRateLimitDetailed(key, 2, 2)).toEqual({ allowed: true, retryAfterMs: 0, remaining: 1 })
+    expect(await rustCore.checkRateLimitDetailed(key, 2, 2)).toEqual({ allowed: true, retryAfterMs: 0, remaining: 0 })
+
+    const refused = await rustCore.checkRateLimitDetailed(key, 2, 2)
+    expect(refused.allowed).toBe(false)
+    expect(refused.remaining).toBe(0)
+    expect(refused.retryAfterMs).toBeGreaterThan(1900)
+    expect(refused.retryAfterMs).toBeLessThanOrEqual(2000)
+  })
+
+  test('should free a token exactly after the reported wait', async () => {
+    const key = `wait-exact-${Date.now()}`
+    await rustCore.checkRateLimitDetailed(key, 1, 1)
+
+    const { retryAfterMs } = await rustCore.checkRateLimitDetailed(key, 1, 1)
+    await Bun.sleep(retryAfterMs)
+    expect((await rustCore.checkRateLimitDetailed(key, 1, 1)).allowed).toBe(true)
+  })
+
+  test('should not charge any bucket when refused', async () => {
+    const key = `wait-refused-${Date.now()}`
+    await rustCore.checkRateLimitDetailed(key, 1, 1)
+    await rustCore.checkRateLimitDetailed(key, 1, 1)
+    await Bun.sleep(1000)
+
+    expect(await rustCore.checkRateLimitDetailed(key, 1, 1)).toEqual({ allowed: true, retryAfterMs: 0, remaining: 0 })
+  })
+
+  test('should answer 429 with the native wait time', async () => {
+    features.USE_RUST_RATE_LIMIT = true
+    state.rateLimitSeconds = 30
+
+    await checkRateLimit(state)
+    const error = await checkRateLimit(state).catch((e: unknown) => e)
+    const response = (error as { response: Response }).response
+    expect(response.status).toBe(429)
+    expect(response.headers.get('retry-after')).toBe('30')
+  })
+
+  test('should sleep once until the token frees with --wait', async () => {
+    features.USE_RUST_RATE_LIMIT = true
+    state.rateLimitSeconds = 1
+    state.rateLimitWait = true
+
+    await checkRateLimit(state)
+    const start = performance.now()
+    await checkRateLimit(state)
+    const waited = performance.now() - start
+
+    expect(waited).toBeGreaterThanOrEqual(900)
+    expect(waited).toBeLessThan(1500)
+  })
+})
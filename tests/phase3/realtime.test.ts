@@ -0,0 +1,100 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import type { WSContext } from 'hono/ws'
+import { handleRealtimeMessage, type RealtimeMessage } from '../../src/routes/realtime/handler'
+import { state } from '../../src/lib/state'
+import { fakeUpstream, streamScript, upstreamError, usage, type FakeUpstream } from '../testkit/upstream'
+
+// Records the frames the bridge sends back to the client
+function fakeSocket(readyState = 1) {
+  const frames: Array<RealtimeMessage> = []
+  const ws = { readyState, send: (data: string) => frames.push(JSON.parse(data)) } as unknown as WSContext
+  return { ws, frames }
+}
+
+const request = (body: object) =>
+  JSON.stringify({ model: 'gpt-4o', messages: [{ role: 'user', content: 'Hello' }], ...body })
+
+describe('Phase 3: Realtime WebSocket Bridge', () => {
+  let upstream: FakeUpstream | undefined
+
+  beforeEach(() => {
+    state.copilotToken = 'test-copilot-token'
+  })
+
+  afterEach(() => {
+    upstream?.restore()
+    upstream = undefined
+    state.copilotToken = undefined
+  })
+
+  test('should translate each upstream chunk into a delta frame followed by done', async () => {
+    const chunks = streamScript({ text: ['Hel', 'lo'], usage: usage(5, 2) })
+    upstream = fakeUpstream([chunks])
+    const { ws, frames } = fakeSocket()
+
+    await handleRealtimeMessage(ws, request({ id: 'req-1' }), () => false)
+
+    expect(frames).toEqual([
+      ...chunks.map((chunk) => ({ type: 'delta' as const, id: 'req-1', chunk })),
+      { type: 'done', id: 'req-1' },
+    ])
+    // The id is the bridge's own, only the completion goes upstream
+    expect(upstream.requests[0]).not.toHaveProperty('id')
+    expect(upstream.requests[0].stream).toBe(true)
+  })
+
+  test('should generate an id when the request has none', async () => {
+    upstream = fakeUpstream([streamScript({ text: ['Hi'] })])
+    const { ws, frames } = fakeSocket()
+
+    await handleRealtimeMessage(ws, request({}), () => false)
+
+    const ids = new Set(frames.map((frame) => frame.id))
+    expect(ids.size).toBe(1)
+    expect([...ids][0]).toMatch(/^[0-9a-f-]{36}$/)
+    expect(frames.at(-1)?.type).toBe('done')
+  })
+
+  test('should answer malformed JSON with an error frame and no upstream request', async () => {
+    upstream = fakeUpstream([])
+    const { ws, frames } = fakeSocket()
+
+    await handleRealtimeMessage(ws, '{"model": ', () => false)
+
+    expect(frames).toEqual([{ type: 'error', error: { message: 'Message is not valid JSON', status: 400 } }])
+    expect(upstream.requests).toHaveLength(0)
+  })
+
+  test('should carry the upstream status and body in the error frame', async () => {
+    upstream = fakeUpstream([upstreamError(429, 'Slow down')])
+    const { ws, frames } = fakeSocket()
+
+    await handleRealtimeMessage(ws, request({ id: 'req-2' }), () => false)
+
+    expect(frames).toHaveLength(1)
+    const [frame] = frames
+    expect(frame).toMatchObject({ type: 'error', id: 'req-2', error: { status: 429 } })
+    expect(frame.type === 'error' && frame.error.message).toContain('Slow down')
+  })
+
+  test('should stop without done once the client has gone away', async () => {
+    upstream = fakeUpstream([streamScript({ text: ['a', 'b', 'c'] })])
+    const { ws, frames } = fakeSocket()
+    // The client disconnects as soon as it has the first frame
+    const closed = () => frames.length > 0
+
+    await handleRealtimeMessage(ws, request({ id: 'req-3' }), closed)
+
+    expect(frames).toHaveLength(1)
+    expect(frames[0]).toMatchObject({ type: 'delta', id: 'req-3' })
+  })
+
+  test('should not write to a socket that is no longer open', async () => {
+    upstream = fakeUpstream([streamScript({ text: ['Hi'] })])
+    const { ws, frames } = fakeSocket(3)
+
+    await handleRealtimeMessage(ws, request({}), () => false)
+
+    expect(frames).toHaveLength(0)
+  })
+})
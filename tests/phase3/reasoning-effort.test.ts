@@ -0,0 +1,49 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { effortForThinking, normalizeReasoningEffort, supportedEfforts } from '../../src/lib/reasoning'
+import { state } from '../../src/lib/state'
+import type { ChatCompletionsPayload, ReasoningEffort } from '../../src/services/copilot/create-chat-completions'
+import type { Model } from '../../src/services/copilot/get-models'
+
+const payload = (model: string, effort?: ReasoningEffort): ChatCompletionsPayload => ({
+  model,
+  messages: [{ role: 'user', content: 'hi' }],
+  reasoning_effort: effort,
+})
+
+describe('Phase 3: Reasoning Effort Mapping', () => {
+  afterEach(() => {
+    state.models = undefined
+  })
+
+  test('should strip reasoning_effort for models without reasoning', () => {
+    const result = normalizeReasoningEffort(payload('gpt-4o', 'high'))
+    expect(result.payload.reasoning_effort).toBeUndefined()
+    expect(result.adjustments).toEqual(['stripped=reasoning_effort'])
+  })
+
+  test('should pass supported levels through', () => {
+    const original = payload('o3-mini', 'high')
+    expect(normalizeReasoningEffort(original)).toEqual({ payload: original, adjustments: [] })
+  })
+
+  test('should move unsupported levels to the nearest accepted one', () => {
+    expect(normalizeReasoningEffort(payload('o4-mini', 'minimal')).payload.reasoning_effort).toBe('low')
+    state.models = {
+      object: 'list',
+      data: [{ id: 'gpt-5', capabilities: { supports: { reasoning_effort: ['low', 'medium'] } } } as unknown as Model],
+    }
+    expect(supportedEfforts('gpt-5')).toEqual(['low', 'medium'])
+    const result = normalizeReasoningEffort(payload('gpt-5', 'high'))
+    expect(result.payload.reasoning_effort).toBe('medium')
+    expect(result.adjustments).toEqual(['downgraded=reasoning_effort'])
+  })
+
+  test('should map Anthropic thinking budgets', () => {
+    expect(effortForThinking(undefined)).toBeUndefined()
+    expect(effortForThinking({ type: 'disabled' })).toBeUndefined()
+    expect(effortForThinking({ type: 'enabled' })).toBe('medium')
+    expect(effortForThinking({ type: 'enabled', budget_tokens: 1024 })).toBe('low')
+    expect(effortForThinking({ type: 'enabled', budget_tokens: 8000 })).toBe('medium')
+    expect(effortForThinking({ type: 'enabled', budget_tokens: 32_000 })).toBe('high')
+  })
+})
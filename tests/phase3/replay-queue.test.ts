@@ -0,0 +1,78 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { configureReplayQueue, retryDelayMs, sendWithReplay } from '../../src/lib/replay-queue'
+
+const tooManyRequests = (headers: Record<string, string> = { 'retry-after': '0' }) =>
+  new Response('rate limited', { status: 429, headers })
+
+describe('Phase 3: 429 Replay Queue', () => {
+  afterEach(() => {
+    configureReplayQueue({ maxWaitMs: 0, capacity: 100 })
+  })
+
+  test('should read the reset from Retry-After seconds, dates and x-ratelimit-reset', () => {
+    const now = Date.parse('2025-01-01T00:00:00Z')
+    expect(retryDelayMs(tooManyRequests({ 'retry-after': '3' }), now)).toBe(3000)
+    expect(retryDelayMs(tooManyRequests({ 'retry-after': 'Wed, 01 Jan 2025 00:00:05 GMT' }), now)).toBe(5000)
+    expect(retryDelayMs(tooManyRequests({ 'x-ratelimit-reset': String(now / 1000 + 7) }), now)).toBe(7000)
+    expect(retryDelayMs(tooManyRequests({}), now)).toBe(1000)
+  })
+
+  test('should return the 429 untouched when disabled', async () => {
+    let calls = 0
+    const response = await sendWithReplay(async () => {
+      calls++
+      return tooManyRequests()
+    })
+
+    expect(response.status).toBe(429)
+    expect(calls).toBe(1)
+  })
+
+  test('should retry after the reset until the upstream accepts', async () => {
+    configureReplayQueue({ maxWaitMs: 5000 })
+    let calls = 0
+    const response = await sendWithReplay(async () => {
+      calls++
+      return calls < 3 ? tooManyRequests() : new Response('ok')
+    })
+
+    expect(response.status).toBe(200)
+    expect(calls).toBe(3)
+  })
+
+  test('should give up when the reset is beyond the max wait', async () => {
+    configureReplayQueue({ maxWaitMs: 5000 })
+    let calls = 0
+    const response = await sendWithReplay(async () => {
+      calls++
+      return tooManyRequests({ 'retry-after': '60' })
+    })
+
+    expect(response.status).toBe(429)
+    expect(calls).toBe(1)
+  })
+
+  test('should return the 429 when the queue is full', async () => {
+    configureReplayQueue({ maxWaitMs: 5000, capacity: 1 })
+    let release: () => void = () => {}
+    const first = sendWithReplay(async () => {
+      await new Promise<void>((resolve) => (release = resolve))
+      return new Response('ok')
+    })
+
+    let parkedCalls = 0
+    const parked = sendWithReplay(async () => {
+      parkedCalls++
+      return parkedCalls === 1 ? tooManyRequests({ 'retry-after': '0.2' }) : new Response('ok')
+    })
+    // Give the second request time to be parked
+    await new Promise((resolve) => setTimeout(resolve, 20))
+
+    const rejected = await sendWithReplay(async () => tooManyRequests())
+    expect(rejected.status).toBe(429)
+
+    release()
+    expect((await first).status).toBe(200)
+    expect((await parked).status).toBe(200)
+  })
+})
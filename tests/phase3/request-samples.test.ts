@@ -0,0 +1,95 @@
+import { test, expect, describe, beforeEach } from 'bun:test'
+import {
+  annotateSample,
+  clearSamples,
+  configureSampling,
+  getSamples,
+  recordSample,
+} from '../../src/lib/request-samples'
+import { state } from '../../src/lib/state'
+import { server } from '../../src/server'
+
+const from = (ip: string, init: RequestInit = {}) =>
+  Object.assign(new Request('http://localhost/admin/samples', init), { ip })
+
+const summary = (status: number, durationMs: number) => ({
+  method: 'POST',
+  route: '/v1/chat/completions',
+  status,
+  durationMs,
+})
+
+describe('Phase 3: Slow and Failed Request Sampling', () => {
+  beforeEach(() => {
+    clearSamples()
+    configureSampling({ capacity: 3, slowMs: 1000 })
+  })
+
+  test('should keep only slow or failed requests', () => {
+    recordSample(new Request('http://localhost/a'), summary(200, 20))
+    recordSample(new Request('http://localhost/b'), summary(200, 1500.4))
+    recordSample(new Request('http://localhost/c'), summary(502, 30))
+
+    const samples = getSamples()
+    expect(samples.map((sample) => sample.reason)).toEqual(['error', 'slow'])
+    expect(samples[1].durationMs).toBe(1500)
+  })
+
+  test('should attach handler details to the sample', () => {
+    const request = new Request('http://localhost/v1/chat/completions')
+    annotateSample(request, { model: 'gpt-4o', promptTokens: 12 })
+    annotateSample(request, { upstreamStatus: 429, error: 'x'.repeat(500) })
+    recordSample(request, summary(429, 40))
+
+    const [sample] = getSamples()
+    expect(sample.model).toBe('gpt-4o')
+    expect(sample.promptTokens).toBe(12)
+    expect(sample.upstreamStatus).toBe(429)
+    expect(sample.error).toHaveLength(200)
+  })
+
+  test('should drop the oldest samples beyond capacity', () => {
+    for (let i = 0; i < 5; i++) {
+      recordSample(new Request('http://localhost/'), { ...summary(500, i), route: `/r${i}` })
+    }
+    expect(getSamples().map((sample) => sample.route)).toEqual(['/r4', '/r3', '/r2'])
+
+    configureSampling({ capacity: 0 })
+    expect(getSamples()).toEqual([])
+  })
+
+  test('should record failed requests and serve them at /admin/samples', async () => {
+    // Fails before reaching upstream because no Copilot token is set
+    await server.request('/v1/chat/completions', {
+      method: 'POST',
+      body: JSON.stringify({ model: 'gpt-4o', messages: [{ role: 'user', content: 'hi' }] }),
+      headers: { 'content-type': 'application/json' },
+    })
+
+    const response = await server.request(from('127.0.0.1'))
+    const { samples } = await response.json() as { samples: Array<Record<string, unknown>> }
+    expect(samples[0]).toMatchObject({
+      route: '/v1/chat/completions',
+      status: 500,
+      reason: 'error',
+      model: 'gpt-4o',
+      error: 'Copilot token not found',
+    })
+  })
+
+  test('should not serve or clear samples for other hosts without an admin token', async () => {
+    recordSample(new Request('http://localhost/a'), summary(500, 20))
+
+    expect((await server.request(from('203.0.113.9'))).status).toBe(403)
+    expect((await server.request(from('203.0.113.9', { method: 'DELETE' }))).status).toBe(403)
+    expect(getSamples()).toHaveLength(1)
+
+    state.adminToken = 'admin-secret'
+    try {
+      const response = await server.request(from('203.0.113.9', { headers: { authorization: 'Bearer admin-secret' } }))
+      expect(response.status).toBe(200)
+    } finally {
+      state.adminToken = undefined
+    }
+  })
+})
@@ -0,0 +1,144 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { apiKeyOf } from '../../src/lib/api-key'
+import {
+  clearSeenSignatures,
+  loadRequestSigning,
+  signRequest,
+  verifySignature,
+} from '../../src/lib/request-signing'
+import { state } from '../../src/lib/state'
+
+const SECRET = 'ci-secret'
+const body = JSON.stringify({ model: 'gpt-4.1', messages: [] })
+
+function createApp() {
+  const app = new Hono()
+  app.use(verifySignature)
+  app.post('/v1/chat/completions', async (c) => c.json({ key: apiKeyOf(c) ?? null, body: await c.req.json() }))
+  app.get('/v1/models', (c) => c.json({ key: apiKeyOf(c) ?? null }))
+  return app
+}
+
+const post = (signature?: string, payload = body) =>
+  createApp().request('/v1/chat/completions', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', ...(signature && { 'x-signature': signature }) },
+    body: payload,
+  })
+
+const errorCode = async (response: Response) =>
+  ((await response.json()) as { error: { code: string } }).error.code
+
+describe('Phase 3: Request Signing', () => {
+  beforeEach(() => {
+    clearSeenSignatures()
+    state.requestSigning = {
+      clients: { ci: { secret: SECRET, apiKey: 'sk-ci' }, batch: { secret: 'batch-secret' } },
+    }
+  })
+
+  afterEach(() => {
+    state.requestSigning = undefined
+  })
+
+  test('should accept signed requests as the client key', async () => {
+    const signature = signRequest('ci', SECRET, { method: 'POST', path: '/v1/chat/completions', body })
+    const response = await post(signature)
+
+    expect(response.status).toBe(200)
+    // The route still reads the body after it was hashed
+    expect(await response.json()).toEqual({ key: 'sk-ci', body: JSON.parse(body) })
+  })
+
+  test('should sign the query and use signed:<client> without an API key', async () => {
+    const signature = signRequest('batch', 'batch-secret', { method: 'GET', path: '/v1/models?limit=1' })
+    const response = await createApp().request('/v1/models?limit=1', { headers: { 'x-signature': signature } })
+
+    expect(await response.json()).toEqual({ key: 'signed:batch' })
+  })
+
+  test('should reject tampered bodies, wrong secrets and unknown clients', async () => {
+    const signed = signRequest('ci', SECRET, { method: 'POST', path: '/v1/chat/completions', body })
+    expect(await errorCode(await post(signed, body.replace('gpt-4.1', 'o3')))).toBe('invalid_signature')
+
+    const wrongSecret = signRequest('ci', 'guess', { method: 'POST', path: '/v1/chat/completions', body })
+    expect(await errorCode(await post(wrongSecret))).toBe('invalid_signature')
+
+    const unknown = signRequest('mallory', SECRET, { method: 'POST', path: '/v1/chat/completions', body })
+    expect(await errorCode(await post(unknown))).toBe('invalid_signature')
+    expect(await errorCode(await post('garbage'))).toBe('invalid_signature')
+  })
+
+  test('should enforce the replay window', async () => {
+    state.requestSigning!.windowSeconds = 60
+    const old = Math.floor(Date.now() / 1000) - 61
+    const stale = signRequest('ci', SECRET, { method: 'POST', path: '/v1/chat/completions', body, timestamp: old })
+
+    const response = await post(stale)
+    expect(response.status).toBe(401)
+    expect(await errorCode(response)).toBe('expired_signature')
+  })
+
+  test('should accept a signature only once', async () => {
+    const signature = signRequest('ci', SECRET, { method: 'POST', path: '/v1/chat/completions', body })
+
+    expect((await post(signature)).status).toBe(200)
+    const replayed = await post(signature)
+    expect(replayed.status).toBe(401)
+    expect(await errorCode(replayed)).toBe('replayed_signature')
+  })
+
+  test('should not accept a replay with a differently spelled signature', async () => {
+    const signature = signRequest('ci', SECRET, { method: 'POST', path: '/v1/chat/completions', body })
+    expect((await post(signature)).status).toBe(200)
+
+    const [prefix, hex] = signature.split('v1=')
+    for (const variant of [hex.toUpperCase(), `${hex}0`, `${hex}zz`, hex.slice(0, 62)]) {
+      const response = await post(`${prefix}v1=${variant}`)
+      expect(response.status).toBe(401)
+      expect(await errorCode(response)).toBe('invalid_signature')
+    }
+  })
+
+  test('should not treat inherited properties as clients', async () => {
+    for (const client of ['constructor', '__proto__', 'toString']) {
+      const signature = signRequest(client, SECRET, { method: 'POST', path: '/v1/chat/completions', body })
+      const response = await post(signature)
+      expect(response.status).toBe(401)
+      expect(await errorCode(response)).toBe('invalid_signature')
+    }
+  })
+
+  test('should only require signatures when configured', async () => {
+    const unsigned = await createApp().request('/v1/models', { headers: { authorization: 'Bearer sk-plain' } })
+    expect(await unsigned.json()).toEqual({ key: 'sk-plain' })
+
+    state.requestSigning!.required = true
+    const rejected = await createApp().request('/v1/models', { headers: { authorization: 'Bearer sk-plain' } })
+    expect(rejected.status).toBe(401)
+    expect(await errorCode(rejected)).toBe('signature_required')
+  })
+
+  test('should resolve secret references when loading', async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-signing-'))
+    await fs.writeFile(path.join(dir, 'secret'), 'from-file\n')
+    const filePath = path.join(dir, 'signing.json')
+    await fs.writeFile(filePath, JSON.stringify({ clients: { ci: { secret: `file:${dir}/secret` } } }))
+
+    const config = await loadRequestSigning(filePath)
+    expect(config.clients.ci.secret).toBe('from-file')
+
+    await fs.writeFile(filePath, JSON.stringify({ clients: { ci: { secret: '${UNSET_SIGNING_SECRET}' } } }))
+    await expect(loadRequestSigning(filePath)).rejects.toThrow('Signing client ci: references unset variable')
+  })
+
+  test('should leave requests alone without signing keys', async () => {
+    state.requestSigning = undefined
+    const response = await createApp().request('/v1/models', { headers: { 'x-signature': 'garbage' } })
+    expect(await response.json()).toEqual({ key: null })
+  })
+})
@@ -0,0 +1,103 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { keyId } from '../../src/lib/api-key'
+import { getStats } from '../../src/lib/metrics'
+import { PATHS } from '../../src/lib/paths'
+import {
+  collectTags,
+  matchesTag,
+  parseTagHeader,
+  resetTaggedUsage,
+  tagRequest,
+  taggedUsage,
+} from '../../src/lib/request-tags'
+import { state } from '../../src/lib/state'
+import { readUsage, recordUsage } from '../../src/lib/usage-ledger'
+
+const originalUsageDir = PATHS.USAGE_DIR
+
+function createApp() {
+  const app = new Hono()
+  app.post('/', async (c) => {
+    const body = await c.req.json<{ metadata?: unknown; user?: unknown }>()
+    tagRequest(c, body)
+    await recordUsage(c, { endpoint: '/chat/completions', model: 'gpt-4o', usage: { prompt_tokens: 10, completion_tokens: 5 } })
+    return c.text('ok')
+  })
+  return app
+}
+
+const send = (body: object, headers: Record<string, string> = {}) =>
+  createApp().request('/', { method: 'POST', headers, body: JSON.stringify(body) })
+
+describe('Phase 3: Request Tags', () => {
+  beforeEach(async () => {
+    resetTaggedUsage()
+    PATHS.USAGE_DIR = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-tags-'))
+  })
+
+  afterEach(() => {
+    PATHS.USAGE_DIR = originalUsageDir
+    state.recordUsage = undefined
+  })
+
+  test('should parse the tags header', () => {
+    expect(parseTagHeader(' project=search, env = staging,broken,=x ')).toEqual({ project: 'search', env: 'staging' })
+    expect(parseTagHeader(undefined)).toEqual({})
+  })
+
+  test('should collect metadata, user and header tags with the header winning', () => {
+    expect(
+      collectTags({ metadata: { team: 'infra', project: 'a', count: 3 }, user: 'alice' }, 'project=b'),
+    ).toEqual({ team: 'infra', project: 'b', user: 'alice' })
+    expect(collectTags({ metadata: null }, undefined)).toBeUndefined()
+  })
+
+  test('should cap the number and size of tags', () => {
+    const metadata = Object.fromEntries(Array.from({ length: 20 }, (_, i) => [`k${i}`, 'v'.repeat(600)]))
+    const tags = collectTags({ metadata }, undefined) ?? {}
+    expect(Object.keys(tags)).toHaveLength(16)
+    expect(tags.k0).toHaveLength(512)
+    expect(collectTags({ metadata: { ['x'.repeat(65)]: 'v' } }, undefined)).toBeUndefined()
+  })
+
+  test('should match tags by value or by key', () => {
+    const tags = { project: 'search', env: 'staging' }
+    expect(matchesTag(tags, 'project=search')).toBe(true)
+    expect(matchesTag(tags, 'project=other')).toBe(false)
+    expect(matchesTag(tags, 'env')).toBe(true)
+    expect(matchesTag(tags, 'team')).toBe(false)
+    expect(matchesTag(undefined, 'env')).toBe(false)
+  })
+
+  test('should total tagged usage for /stats', async () => {
+    await send({ metadata: { project: 'search' } })
+    await send({}, { 'x-gateway-tags': 'project=search,env=prod' })
+    await send({}, { 'x-gateway-tags': 'project=ads' })
+    await send({})
+
+    expect(getStats().tags).toEqual({
+      'project=search': { requests: 2, prompt_tokens: 20, completion_tokens: 10 },
+      'env=prod': { requests: 1, prompt_tokens: 10, completion_tokens: 5 },
+      'project=ads': { requests: 1, prompt_tokens: 10, completion_tokens: 5 },
+    })
+    expect(Object.keys(taggedUsage('project'))).toEqual(['project=search', 'project=ads'])
+    expect(Object.keys(getStats({ tag: 'project=ads' }).tags)).toEqual(['project=ads'])
+  })
+
+  test('should store tags with usage records and filter reports by tag', async () => {
+    state.recordUsage = true
+    await send({ user: 'alice' }, { authorization: 'Bearer sk-a', 'x-gateway-tags': 'project=search' })
+    await send({}, { authorization: 'Bearer sk-b', 'x-gateway-tags': 'project=search' })
+    await send({}, { authorization: 'Bearer sk-b', 'x-gateway-tags': 'project=ads' })
+
+    const [record] = await readUsage({ key: keyId('sk-a') })
+    expect(record.tags).toEqual({ user: 'alice', project: 'search' })
+    expect(await readUsage({ tag: 'project=search' })).toHaveLength(2)
+    expect(await readUsage({ key: keyId('sk-b'), tag: 'project=search' })).toHaveLength(1)
+    expect(await readUsage({ tag: 'project' })).toHaveLength(3)
+  })
+})
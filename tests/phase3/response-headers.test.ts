@@ -0,0 +1,71 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import { applyHeaderPolicy, scrubResponseHeaders } from '../../src/lib/response-headers'
+import { state } from '../../src/lib/state'
+
+function appWithHeaders(headers: Record<string, string>, status = 200): Hono {
+  const app = new Hono()
+  app.use(scrubResponseHeaders)
+  app.get('/', () => new Response('ok', { status, headers }))
+  return app
+}
+
+describe('Phase 3: Response Header Policy', () => {
+  afterEach(() => {
+    state.headerPolicy = undefined
+  })
+
+  test('should remove hop-by-hop and Copilot headers by default', async () => {
+    const response = await appWithHeaders({
+      connection: 'keep-alive',
+      'keep-alive': 'timeout=5',
+      'x-github-request-id': 'ABCD:1234',
+      'X-MS-Region': 'eastus',
+      'azureml-model-session': 'd1',
+      'x-ratelimit-remaining-requests': '9',
+      'content-type': 'text/plain',
+    }).request('/')
+
+    expect(response.headers.get('connection')).toBeNull()
+    expect(response.headers.get('keep-alive')).toBeNull()
+    expect(response.headers.get('x-github-request-id')).toBeNull()
+    expect(response.headers.get('x-ms-region')).toBeNull()
+    expect(response.headers.get('azureml-model-session')).toBeNull()
+    expect(response.headers.get('x-ratelimit-remaining-requests')).toBe('9')
+    expect(response.headers.get('content-type')).toBe('text/plain')
+  })
+
+  test('should strip, then rename, then add', () => {
+    const headers = new Headers({
+      'x-stream-id': 's1',
+      'x-param-policy': 'stripped=temperature',
+      'x-ratelimit-reset': '30',
+      'x-served-by': 'upstream',
+    })
+    applyHeaderPolicy(headers, {
+      strip: ['X-Stream-Id', 'x-param-*'],
+      rename: { 'x-ratelimit-reset': 'x-upstream-ratelimit-reset' },
+      add: { 'x-served-by': 'gateway' },
+    })
+
+    expect(Object.fromEntries(headers)).toEqual({
+      'x-upstream-ratelimit-reset': '30',
+      'x-served-by': 'gateway',
+    })
+  })
+
+  test('should keep the built-in headers when turned off', () => {
+    const headers = new Headers({ 'x-github-request-id': 'ABCD:1234' })
+    applyHeaderPolicy(headers, { builtin: false })
+    expect(headers.get('x-github-request-id')).toBe('ABCD:1234')
+  })
+
+  test('should apply the loaded policy to every response', async () => {
+    state.headerPolicy = { add: { 'x-served-by': 'gateway' } }
+    const response = await appWithHeaders({ 'x-copilot-trace': 't' }, 404).request('/')
+
+    expect(response.status).toBe(404)
+    expect(response.headers.get('x-served-by')).toBe('gateway')
+    expect(response.headers.get('x-copilot-trace')).toBeNull()
+  })
+})
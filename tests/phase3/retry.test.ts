@@ -0,0 +1,56 @@
+import { test, expect, describe, afterAll } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+describe('Phase 3: Native Retry Policy', () => {
+  let hits = 0
+  const server = Bun.serve({
+    port: 0,
+    async fetch(req) {
+      hits++
+      if (hits === 1) return new Response('slow down', { status: 429, headers: { 'retry-after': '0' } })
+      if (hits === 2) return new Response('busy', { status: 503 })
+      if (new URL(req.url).pathname === '/embeddings') return new Response('bad request', { status: 400 })
+      return Response.json({ echo: await req.json(), auth: req.headers.get('authorization') })
+    }
+  })
+  const baseUrl = `http://127.0.0.1:${server.port}`
+
+  afterAll(() => {
+    rustCore.withRetry({ maxRetries: 3, baseDelayMs: 250, maxDelayMs: 10000 })
+    server.stop()
+  })
+
+  test('withRetry merges options and returns the effective policy', () => {
+    const policy = rustCore.withRetry({ baseDelayMs: 10, maxDelayMs: 50 })
+
+    expect(policy).toEqual({
+      maxRetries: 3,
+      baseDelayMs: 10,
+      maxDelayMs: 50,
+      jitter: true,
+      retryOnStatus: [429, 500, 502, 503, 504]
+    })
+    expect(() => rustCore.withRetry({ baseDelayMs: 100, maxDelayMs: 10 })).toThrow()
+  })
+
+  test('withRetry rejects unknown options', () => {
+    expect(() => rustCore.withRetry({ maxRetry: 5 })).toThrow('unknown field `maxRetry`')
+    expect(rustCore.withRetry({}).maxRetries).toBe(3)
+  })
+
+  test('retries 429 and 5xx responses until success', async () => {
+    const { response } = rustCore.createChatCompletions(
+      { model: 'gpt-4o', messages: [] },
+      { baseUrl, headers: { authorization: 'Bearer test' } }
+    )
+    const body = await response
+
+    expect(JSON.parse(body)).toEqual({ echo: { model: 'gpt-4o', messages: [] }, auth: 'Bearer test' })
+    expect(hits).toBe(3)
+  })
+
+  test('does not retry other client errors', async () => {
+    await expect(rustCore.createEmbeddings({ input: 'hi' }, { baseUrl }).response).rejects.toThrow('HTTP 400')
+    expect(hits).toBe(4)
+  })
+})
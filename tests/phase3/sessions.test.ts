@@ -0,0 +1,129 @@
+import { test, expect, describe, beforeEach, afterEach, afterAll, mock } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { server } from '../../src/server'
+import { keyId } from '../../src/lib/api-key'
+import { PATHS } from '../../src/lib/paths'
+import { readSession, recordSessionTurn } from '../../src/lib/sessions'
+import { state } from '../../src/lib/state'
+import { writeTeamStore } from '../../src/lib/team'
+
+const originalSessionsDir = PATHS.SESSIONS_DIR
+
+// Records one turn for whatever x-session-id the request carries
+const recorder = new Hono()
+recorder.post('/', async (c) => {
+  await recordSessionTurn(c, {
+    endpoint: '/chat/completions',
+    model: 'gpt-4o',
+    request: { model: 'gpt-4o', messages: [{ role: 'user', content: c.req.header('x-prompt') ?? 'hi' }] },
+    response: { content: 'hello' },
+  })
+  return c.body(null, 204)
+})
+
+const record = (sessionId?: string, headers: Record<string, string> = {}) =>
+  recorder.request('/', {
+    method: 'POST',
+    headers: { ...headers, ...(sessionId && { 'x-session-id': sessionId }) },
+  })
+
+const originalFetch = globalThis.fetch
+
+describe('Phase 3: Session Persistence and Replay', () => {
+  beforeEach(async () => {
+    PATHS.SESSIONS_DIR = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-sessions-'))
+    state.sessions = true
+  })
+
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    state.teamStore = undefined
+  })
+
+  afterAll(() => {
+    PATHS.SESSIONS_DIR = originalSessionsDir
+    state.sessions = undefined
+  })
+
+  test('should append turns to the session named by the header', async () => {
+    await record('abc-1')
+    await record('abc-1')
+
+    const turns = await readSession('abc-1')
+    expect(turns).toHaveLength(2)
+    expect(turns?.[0].request.messages[0].content).toBe('hi')
+  })
+
+  test('should not record without the flag, the header or a valid id', async () => {
+    await record()
+    await record('../escape')
+    state.sessions = false
+    await record('abc-2')
+
+    expect(await fs.readdir(PATHS.SESSIONS_DIR)).toEqual([])
+  })
+
+  test('should serve transcripts at /sessions/:id', async () => {
+    await record('abc-3')
+
+    const response = await server.request('/sessions/abc-3')
+    expect(response.status).toBe(200)
+    const body = await response.json() as { id: string; turns: Array<{ model: string }> }
+    expect(body.id).toBe('abc-3')
+    expect(body.turns.map((turn) => turn.model)).toEqual(['gpt-4o'])
+
+    expect((await server.request('/sessions/unknown')).status).toBe(404)
+  })
+
+  test('should reject replays of turns that do not exist', async () => {
+    await record('abc-4')
+
+    const response = await server.request('/sessions/abc-4/replay', {
+      method: 'POST',
+      headers: { 'content-type': 'application/json' },
+      body: JSON.stringify({ model: 'gpt-4o-mini', turn: 5 }),
+    })
+    expect(response.status).toBe(400)
+  })
+
+  test('should keep team members with the same session id apart', async () => {
+    const storePath = path.join(PATHS.SESSIONS_DIR, 'team_tokens.json')
+    await writeTeamStore(
+      {
+        members: {
+          [keyId('cpk-alice')]: { login: 'alice', githubToken: 'ghu_alice', addedAt: '' },
+          [keyId('cpk-bob')]: { login: 'bob', githubToken: 'ghu_bob', addedAt: '' },
+        },
+      },
+      storePath,
+    )
+    state.teamStore = storePath
+    globalThis.fetch = mock(async () =>
+      Response.json({ token: 'copilot-member', expires_at: 0, refresh_in: 1500 }),
+    ) as unknown as typeof fetch
+
+    await record('default', { authorization: 'Bearer cpk-alice', 'x-prompt': 'from alice' })
+    await record('alice-only', { authorization: 'Bearer cpk-alice' })
+    await record('default', { authorization: 'Bearer cpk-bob', 'x-prompt': 'from bob' })
+
+    const transcript = async (apiKey: string, id: string) => {
+      const response = await server.request(`/sessions/${id}`, { headers: { authorization: `Bearer ${apiKey}` } })
+      if (response.status !== 200) return response.status
+      const body = (await response.json()) as { turns: Array<{ request: { messages: Array<{ content: string }> } }> }
+      return body.turns.map((turn) => turn.request.messages[0].content)
+    }
+    expect(await transcript('cpk-alice', 'default')).toEqual(['from alice'])
+    expect(await transcript('cpk-bob', 'default')).toEqual(['from bob'])
+    expect(await transcript('cpk-bob', 'alice-only')).toBe(404)
+
+    const replay = await server.request('/sessions/alice-only/replay', {
+      method: 'POST',
+      headers: { authorization: 'Bearer cpk-bob', 'content-type': 'application/json' },
+      body: JSON.stringify({ model: 'gpt-4o' }),
+    })
+    expect(replay.status).toBe(404)
+  })
+})
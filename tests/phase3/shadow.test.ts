@@ -0,0 +1,103 @@
+import { test, expect, describe, beforeEach, afterEach, mock } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PATHS } from '../../src/lib/paths'
+import { mirrorRequest, shouldMirror, type ShadowConfig, type ShadowRecord } from '../../src/lib/shadow'
+import { state } from '../../src/lib/state'
+
+const originalFetch = globalThis.fetch
+const originalShadowDir = PATHS.SHADOW_DIR
+
+const config = (overrides: Partial<ShadowConfig> = {}): ShadowConfig => ({
+  url: 'http://localhost:11434/v1/',
+  model: 'llama3.1',
+  percent: 100,
+  ...overrides,
+})
+
+const payload = () => ({
+  model: 'gpt-4.1',
+  messages: [{ role: 'user' as const, content: 'Say hi' }],
+  stream: true,
+  stream_options: { include_usage: true },
+})
+
+async function readRecords(): Promise<Array<ShadowRecord>> {
+  // Records are written in the background
+  for (let i = 0; i < 50; i++) {
+    const files = await fs.readdir(PATHS.SHADOW_DIR).catch(() => [])
+    if (files.length > 0) {
+      const content = await fs.readFile(path.join(PATHS.SHADOW_DIR, files[0]), 'utf8')
+      return content.trim().split('\n').map((line) => JSON.parse(line))
+    }
+    await new Promise((resolve) => setTimeout(resolve, 10))
+  }
+  return []
+}
+
+describe('Phase 3: Shadow Traffic', () => {
+  beforeEach(async () => {
+    PATHS.SHADOW_DIR = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-shadow-'))
+  })
+
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    PATHS.SHADOW_DIR = originalShadowDir
+    state.shadow = undefined
+  })
+
+  test('should sample by percent and model', () => {
+    expect(shouldMirror(config({ percent: 10 }), 'gpt-4.1', () => 0.05)).toBe(true)
+    expect(shouldMirror(config({ percent: 10 }), 'gpt-4.1', () => 0.2)).toBe(false)
+    expect(shouldMirror(config({ percent: 0 }), 'gpt-4.1', () => 0)).toBe(false)
+    expect(shouldMirror(config({ models: ['claude-*'] }), 'gpt-4.1', () => 0)).toBe(false)
+    expect(shouldMirror(config({ models: ['gpt-4*'] }), 'gpt-4.1', () => 0)).toBe(true)
+  })
+
+  test('should do nothing without shadow traffic configured', () => {
+    expect(mirrorRequest(payload())).toBeUndefined()
+  })
+
+  test('should record the primary and shadow answers', async () => {
+    state.shadow = config({ apiKey: 'shadow-key' })
+    const sent: Array<{ url: string; authorization: string | null; body: Record<string, unknown> }> = []
+    globalThis.fetch = mock(async (url: string, init?: RequestInit) => {
+      sent.push({
+        url,
+        authorization: new Headers(init?.headers).get('authorization'),
+        body: JSON.parse(init?.body as string),
+      })
+      return Response.json({
+        choices: [{ index: 0, message: { role: 'assistant', content: 'Hello!' }, finish_reason: 'stop' }],
+        usage: { prompt_tokens: 3, completion_tokens: 2, total_tokens: 5 },
+      })
+    }) as unknown as typeof fetch
+
+    const record = mirrorRequest(payload())
+    record?.({ content: 'Hi there', tool_calls: [] })
+
+    const [entry] = await readRecords()
+    expect(sent).toHaveLength(1)
+    expect(sent[0].url).toBe('http://localhost:11434/v1/chat/completions')
+    expect(sent[0].authorization).toBe('Bearer shadow-key')
+    // Always a complete answer, from the configured model
+    expect(sent[0].body).toMatchObject({ model: 'llama3.1', stream: false })
+    expect(sent[0].body).not.toHaveProperty('stream_options')
+
+    expect(entry.model).toBe('gpt-4.1')
+    expect(entry.shadowModel).toBe('llama3.1')
+    expect(entry.primary.content).toBe('Hi there')
+    expect(entry.shadow).toMatchObject({ content: 'Hello!', usage: { completion_tokens: 2 } })
+  })
+
+  test('should record shadow failures without throwing', async () => {
+    state.shadow = config()
+    globalThis.fetch = mock(async () => new Response('model not found', { status: 404 })) as unknown as typeof fetch
+
+    mirrorRequest(payload())?.({ content: 'Hi there' })
+
+    const [entry] = await readRecords()
+    expect(entry.shadow).toMatchObject({ error: '404 model not found' })
+  })
+})
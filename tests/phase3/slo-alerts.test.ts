@@ -0,0 +1,99 @@
+import { test, expect, describe, beforeEach, afterEach, mock } from 'bun:test'
+import { state } from '../../src/lib/state'
+import {
+  checkSlo,
+  evaluateSlo,
+  recordFirstToken,
+  recordResponseStatus,
+  resetSloAlerts,
+  sendAlert,
+  type SloConfig,
+} from '../../src/lib/slo-alerts'
+
+const originalFetch = globalThis.fetch
+const NOW = 1_750_000_000_000
+
+const config = (overrides: Partial<SloConfig> = {}): SloConfig => ({
+  webhook: 'https://hooks.example.com/alerts',
+  errorRate: 0.1,
+  timeToFirstTokenSeconds: 5,
+  minSamples: 10,
+  ...overrides,
+})
+
+function captureWebhook() {
+  const posted: Array<Record<string, unknown>> = []
+  globalThis.fetch = mock(async (_url: string, init?: RequestInit) => {
+    posted.push(JSON.parse(init?.body as string))
+    return new Response('ok')
+  }) as unknown as typeof fetch
+  return posted
+}
+
+describe('Phase 3: SLO Alerts', () => {
+  beforeEach(() => {
+    resetSloAlerts()
+    state.sloAlerts = config()
+  })
+
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    state.sloAlerts = undefined
+  })
+
+  test('should alert when the error rate exceeds its threshold', () => {
+    for (let i = 0; i < 8; i++) recordResponseStatus(200, NOW)
+    for (let i = 0; i < 2; i++) recordResponseStatus(502, NOW)
+
+    const [alert] = evaluateSlo(config(), NOW)
+    expect(alert.kind).toBe('error_rate')
+    expect(alert.value).toBeCloseTo(0.2)
+    expect(alert.text).toContain('20.0% of 10 requests failed')
+  })
+
+  test('should not count 4xx as errors or judge small windows', () => {
+    for (let i = 0; i < 10; i++) recordResponseStatus(429, NOW)
+    expect(evaluateSlo(config(), NOW)).toEqual([])
+
+    resetSloAlerts()
+    for (let i = 0; i < 5; i++) recordResponseStatus(500, NOW)
+    expect(evaluateSlo(config(), NOW)).toEqual([])
+  })
+
+  test('should alert on the p95 time to first token', () => {
+    for (let i = 0; i < 20; i++) recordFirstToken(1, NOW)
+    recordFirstToken(12, NOW)
+    expect(evaluateSlo(config(), NOW)).toEqual([])
+
+    recordFirstToken(12, NOW)
+    const [alert] = evaluateSlo(config(), NOW)
+    expect(alert.kind).toBe('time_to_first_token')
+    expect(alert.value).toBe(12)
+  })
+
+  test('should forget samples outside the window', () => {
+    for (let i = 0; i < 10; i++) recordResponseStatus(500, NOW - 301_000)
+    expect(evaluateSlo(config({ windowSeconds: 300 }), NOW)).toEqual([])
+  })
+
+  test('should post to the webhook with a cooldown', async () => {
+    const posted = captureWebhook()
+    for (let i = 0; i < 10; i++) recordResponseStatus(500, NOW)
+
+    await checkSlo(NOW)
+    await checkSlo(NOW + 60_000)
+    expect(posted).toHaveLength(1)
+    expect(posted[0]).toMatchObject({ kind: 'error_rate', threshold: 0.1 })
+    expect(posted[0].text).toStartWith('copilot-api:')
+
+    const alert = { kind: 'error_rate' as const, text: '', value: 1, threshold: 0.1 }
+    expect(await sendAlert(alert, NOW + 901_000)).toBe(true)
+    expect(posted).toHaveLength(2)
+  })
+
+  test('should record nothing without alerting configured', () => {
+    state.sloAlerts = undefined
+    for (let i = 0; i < 10; i++) recordResponseStatus(500, NOW)
+    expect(evaluateSlo(config(), NOW)).toEqual([])
+  })
+})
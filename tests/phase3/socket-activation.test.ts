@@ -0,0 +1,26 @@
+import { test, expect, describe } from 'bun:test'
+import { takeActivatedSocket } from '../../src/lib/socket-activation'
+
+describe('Phase 3: Socket Activation', () => {
+  test('should take the socket passed to this process', () => {
+    const env: NodeJS.ProcessEnv = { LISTEN_FDS: '1', LISTEN_PID: '1234', LISTEN_FDNAMES: 'copilot-api.socket' }
+    expect(takeActivatedSocket(env, 1234)).toBe(3)
+    // Not inherited by child processes
+    expect(env).toEqual({})
+  })
+
+  test('should ignore sockets meant for another process', () => {
+    const env: NodeJS.ProcessEnv = { LISTEN_FDS: '1', LISTEN_PID: '99' }
+    expect(takeActivatedSocket(env, 1234)).toBeUndefined()
+    expect(env).toEqual({})
+  })
+
+  test('should listen normally without socket activation', () => {
+    expect(takeActivatedSocket({}, 1234)).toBeUndefined()
+    expect(takeActivatedSocket({ LISTEN_FDS: '0', LISTEN_PID: '1234' }, 1234)).toBeUndefined()
+  })
+
+  test('should reject more sockets than it can serve', () => {
+    expect(() => takeActivatedSocket({ LISTEN_FDS: '2', LISTEN_PID: '1234' }, 1234)).toThrow('Received 2 sockets')
+  })
+})
@@ -0,0 +1,81 @@
+import { test, expect, describe } from 'bun:test'
+import { rustCore, type SseEvent } from '../../src/lib/rust-core'
+
+describe('Phase 3: SSE Transformer', () => {
+  const lines = [
+    'data: {"id":"1","choices":[{"index":0,"delta":{"role":"assistant","content":"Héllo"},"finish_reason":null}]}',
+    '',
+    ': keep-alive',
+    '',
+    'data: {"id":"1","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":"{\\"city\\""}}]},"finish_reason":null}]}',
+    '',
+    'data: {"id":"1","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":":\\"Paris\\"}"}}]},"finish_reason":"tool_calls"}],"usage":{"prompt_tokens":10,"completion_tokens":5,"total_tokens":15}}',
+    '',
+    'data: [DONE]',
+    '',
+    ''
+  ]
+  const stream = lines.join('\r\n')
+
+  const expected: Array<SseEvent> = [
+    { type: 'text_delta', index: 0, text: 'Héllo' },
+    { type: 'tool_call_delta', index: 0, toolIndex: 0, id: 'call_1', name: 'get_weather', arguments: '{"city"' },
+    { type: 'tool_call_delta', index: 0, toolIndex: 0, arguments: ':"Paris"}' },
+    { type: 'finish', index: 0, reason: 'tool_calls' },
+    { type: 'usage', promptTokens: 10, completionTokens: 5, totalTokens: 15 },
+    { type: 'done' }
+  ]
+
+  test('parses a stream delivered in one chunk', () => {
+    const transformer = rustCore.createSseTransformer()
+    const events = [...transformer.push(stream), ...transformer.flush()]
+
+    expect(events).toEqual(expected)
+  })
+
+  test('handles chunks split at arbitrary byte boundaries', () => {
+    const bytes = new TextEncoder().encode(stream)
+
+    for (const size of [1, 3, 7, 64]) {
+      const transformer = rustCore.createSseTransformer()
+      const events: Array<SseEvent> = []
+      for (let i = 0; i < bytes.length; i += size) {
+        events.push(...transformer.push(bytes.subarray(i, i + size)))
+      }
+      events.push(...transformer.flush())
+
+      expect(events).toEqual(expected)
+    }
+
+    console.log('Split chunk parsing: ✓')
+  })
+
+  test('accepts lines ending in a bare CR', () => {
+    const bytes = new TextEncoder().encode(lines.join('\r'))
+
+    for (const size of [1, 2, 64, bytes.length]) {
+      const transformer = rustCore.createSseTransformer()
+      const events: Array<SseEvent> = []
+      for (let i = 0; i < bytes.length; i += size) {
+        events.push(...transformer.push(bytes.subarray(i, i + size)))
+      }
+      events.push(...transformer.flush())
+
+      expect(events).toEqual(expected)
+    }
+  })
+
+  test('flush dispatches a trailing event without a blank line', () => {
+    const transformer = rustCore.createSseTransformer()
+
+    expect(transformer.push('data: {"choices":[{"index":0,"delta":{"content":"hi"}}]}')).toEqual([])
+    expect(transformer.flush()).toEqual([{ type: 'text_delta', index: 0, text: 'hi' }])
+  })
+
+  test('reports malformed data as an error event', () => {
+    const transformer = rustCore.createSseTransformer()
+    const [event] = transformer.push('data: {not json}\n\n')
+
+    expect(event.type).toBe('error')
+  })
+})
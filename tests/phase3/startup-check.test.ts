@@ -0,0 +1,41 @@
+import { test, expect, describe, afterEach, mock } from 'bun:test'
+import { HTTPError } from '../../src/lib/error'
+import { checkUpstream, describeUpstreamError, StartupCheckError } from '../../src/lib/readiness'
+import { state } from '../../src/lib/state'
+
+const originalFetch = globalThis.fetch
+
+describe('Phase 3: Startup Check', () => {
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    state.githubToken = undefined
+    state.copilotToken = undefined
+    state.models = undefined
+  })
+
+  test('should explain rejected credentials', () => {
+    const unauthorized = describeUpstreamError(new HTTPError('Failed to get Copilot token', new Response(null, { status: 401 })))
+    expect(unauthorized.message).toBe('Failed to get Copilot token (401 Unauthorized)')
+    expect(unauthorized.hint).toContain('copilot-api auth')
+    const forbidden = describeUpstreamError(new HTTPError('Failed to get Copilot token', new Response(null, { status: 403 })))
+    expect(forbidden.hint).toContain('Copilot subscription')
+  })
+
+  test('should fail strict checks when the token exchange is rejected', async () => {
+    state.githubToken = ''
+    globalThis.fetch = mock(async () => new Response('Bad credentials', { status: 401 })) as unknown as typeof fetch
+
+    const check = checkUpstream('strict')
+    await expect(check).rejects.toBeInstanceOf(StartupCheckError)
+    await expect(check).rejects.toThrow('401 Unauthorized')
+    expect(state.copilotToken).toBeUndefined()
+  })
+
+  test('should only warn in warn mode', async () => {
+    state.githubToken = ''
+    globalThis.fetch = mock(async () => new Response('Bad credentials', { status: 401 })) as unknown as typeof fetch
+
+    // A long retry interval keeps the background retry out of the test
+    expect(await checkUpstream('warn', 3_600_000)).toBe(false)
+  })
+})
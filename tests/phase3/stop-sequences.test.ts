@@ -0,0 +1,102 @@
+import { test, expect, describe } from 'bun:test'
+import {
+  applyStopSequences,
+  createStopSequenceFilter,
+  findStop,
+  stopSequencesOf,
+} from '../../src/lib/stop-sequences'
+import type {
+  ChatCompletionChunk,
+  ChatCompletionResponse,
+} from '../../src/services/copilot/create-chat-completions'
+
+const chunk = (
+  content: string | undefined,
+  finishReason: 'stop' | 'length' | null = null,
+): ChatCompletionChunk => ({
+  id: 'chatcmpl-1',
+  object: 'chat.completion.chunk',
+  created: 0,
+  model: 'gpt-4o',
+  choices: [
+    {
+      index: 0,
+      delta: content === undefined ? {} : { content },
+      finish_reason: finishReason,
+      logprobs: null,
+    },
+  ],
+})
+
+const streamed = (stops: Array<string>, deltas: Array<string>) => {
+  const filter = createStopSequenceFilter(stops)
+  const output = [
+    ...deltas.map((delta) => chunk(delta)),
+    chunk(undefined, 'length'),
+  ].flatMap((input) => filter.process(input))
+  return {
+    filter,
+    text: output.map((out) => out.choices[0]?.delta.content ?? '').join(''),
+    finishReasons: output.map((out) => out.choices[0]?.finish_reason),
+  }
+}
+
+describe('Phase 3: Stop Sequence Emulation', () => {
+  test('should normalize the stop parameter', () => {
+    expect(stopSequencesOf('END')).toEqual(['END'])
+    expect(stopSequencesOf(['a', '', 'b'])).toEqual(['a', 'b'])
+    expect(stopSequencesOf(null)).toEqual([])
+  })
+
+  test('should find the earliest match', () => {
+    expect(findStop('one two three', ['three', 'two'])).toEqual({ index: 4, sequence: 'two' })
+    expect(findStop('nothing here', ['END'])).toBeUndefined()
+  })
+
+  test('should truncate non-streaming responses', () => {
+    const response: ChatCompletionResponse = {
+      id: 'chatcmpl-1',
+      object: 'chat.completion',
+      created: 0,
+      model: 'gpt-4o',
+      choices: [
+        {
+          index: 0,
+          message: { role: 'assistant', content: 'Answer: 42\nEND\nextra' },
+          logprobs: null,
+          finish_reason: 'length',
+        },
+      ],
+    }
+    const result = applyStopSequences(response, ['\nEND'])
+    expect(result.response.choices[0].message.content).toBe('Answer: 42')
+    expect(result.response.choices[0].finish_reason).toBe('stop')
+    expect(result.matched.get(0)).toBe('\nEND')
+  })
+
+  test('should catch stop sequences split across deltas', () => {
+    const result = streamed(['</answer>'], ['The answer', ' is 42</ans', 'wer> and more', ' text'])
+    expect(result.text).toBe('The answer is 42')
+    expect(result.finishReasons.filter(Boolean)).toEqual(['stop'])
+    expect(result.filter.matched(0)).toBe('</answer>')
+  })
+
+  test('should release held text that turns out not to be a stop', () => {
+    const result = streamed(['</answer>'], ['a </an', 'other tag', ' </a'])
+    expect(result.text).toBe('a </another tag </a')
+    expect(result.finishReasons.at(-1)).toBe('length')
+    expect(result.filter.matched(0)).toBeUndefined()
+  })
+
+  test('should keep usage-only chunks after a stop', () => {
+    const filter = createStopSequenceFilter(['STOP'])
+    filter.process(chunk('done STOP'))
+    expect(filter.process(chunk('ignored'))).toEqual([])
+    const usage: ChatCompletionChunk = {
+      ...chunk(undefined),
+      choices: [],
+      usage: { prompt_tokens: 5, completion_tokens: 3, total_tokens: 8 },
+    }
+    expect(filter.process(usage)).toEqual([usage])
+  })
+})
@@ -0,0 +1,109 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import { keyId } from '../../src/lib/api-key'
+import { state } from '../../src/lib/state'
+import { getBroadcast, listBroadcasts, openBroadcast } from '../../src/lib/stream-broadcast'
+import { resumeStream } from '../../src/lib/stream-resume'
+import { server } from '../../src/server'
+
+// A request from this machine, which needs no admin token
+const local = (url: string, headers: Record<string, string> = {}) =>
+  Object.assign(new Request(`http://localhost${url}`, { headers }), { ip: '127.0.0.1' })
+
+describe('Phase 3: Stream Tee to Observers', () => {
+  afterEach(() => {
+    state.teamStore = undefined
+    state.adminToken = undefined
+  })
+
+  test('should replay earlier events to late observers and follow live ones', async () => {
+    const broadcast = openBroadcast('/chat/completions', 'gpt-4o')
+    broadcast.publish({ data: 'one' })
+
+    const received: Array<string> = []
+    const observer = (async () => {
+      for await (const event of broadcast.subscribe()) received.push(event.data as string)
+    })()
+
+    broadcast.publish({ data: 'two' })
+    broadcast.publish({ data: '[DONE]' })
+    broadcast.close()
+    await observer
+
+    expect(received).toEqual(['one', 'two', '[DONE]'])
+  })
+
+  test('should support several observers at once', async () => {
+    const broadcast = openBroadcast('/v1/messages', 'claude-sonnet-4')
+    const collect = async () => {
+      const events: Array<string> = []
+      for await (const event of broadcast.subscribe()) events.push(event.data as string)
+      return events
+    }
+    const observers = [collect(), collect()]
+
+    await Promise.resolve()
+    expect(listBroadcasts().find((info) => info.id === broadcast.info.id)?.observers).toBe(2)
+
+    broadcast.publish({ event: 'message_stop', data: '{}' })
+    broadcast.close()
+    expect(await Promise.all(observers)).toEqual([['{}'], ['{}']])
+  })
+
+  test('should forget streams once closed', () => {
+    const broadcast = openBroadcast('/chat/completions', 'gpt-4o')
+    expect(getBroadcast(broadcast.info.id)).toBe(broadcast)
+    broadcast.close()
+    expect(getBroadcast(broadcast.info.id)).toBeUndefined()
+  })
+
+  test('should serve the copy over SSE at /admin/streams/:id', async () => {
+    const broadcast = openBroadcast('/chat/completions', 'gpt-4o')
+    broadcast.publish({ data: '{"choices":[]}' })
+    broadcast.close()
+    // Closed streams are no longer listed
+    expect((await server.request(local(`/admin/streams/${broadcast.info.id}`))).status).toBe(404)
+
+    const live = openBroadcast('/chat/completions', 'gpt-4o')
+    live.publish({ data: 'hello' })
+    const response = server.request(local(`/admin/streams/${live.info.id}`))
+    setTimeout(() => live.close(), 10)
+
+    expect(await (await response).text()).toBe('data: hello\n\n')
+  })
+
+  test('should not serve live streams to other hosts without an admin token', async () => {
+    const live = openBroadcast('/chat/completions', 'gpt-4o')
+    live.publish({ data: 'secret completion' })
+
+    const remote = Object.assign(new Request(`http://localhost/admin/streams/${live.info.id}`), { ip: '203.0.113.9' })
+    const response = await server.request(remote)
+    expect(response.status).toBe(403)
+    expect(await response.text()).not.toContain('secret completion')
+    live.close()
+  })
+
+  test('should show the admin every stream but let members resume only their own', async () => {
+    state.teamStore = '/nonexistent/team_tokens.json'
+    state.adminToken = 'admin-secret'
+    const alice = openBroadcast('/chat/completions', 'gpt-4o', keyId('cpk-alice'))
+    const bob = openBroadcast('/chat/completions', 'gpt-4o', keyId('cpk-bob'))
+    const first = alice.publish({ data: 'for alice' })
+
+    // The admin token needs no member key to see every stream
+    const listed = await server.request('/admin/streams', { headers: { authorization: 'Bearer admin-secret' } })
+    const ids = ((await listed.json()) as { streams: Array<{ id: string }> }).streams.map((info) => info.id)
+    expect(ids).toEqual(expect.arrayContaining([alice.info.id, bob.info.id]))
+
+    const app = new Hono()
+    app.use(resumeStream)
+    app.post('/', (c) => c.text('new generation'))
+    const resume = (apiKey: string) =>
+      app.request('/', { method: 'POST', headers: { authorization: `Bearer ${apiKey}`, 'last-event-id': first.id! } })
+    expect((await resume('cpk-bob')).status).toBe(404)
+    const resumed = resume('cpk-alice')
+    alice.close()
+    bob.close()
+    expect((await resumed).status).toBe(200)
+  })
+})
@@ -0,0 +1,75 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { configureStreamBuffer, openBroadcast } from '../../src/lib/stream-broadcast'
+import { parseEventId } from '../../src/lib/stream-resume'
+
+const resume = (lastEventId: string) =>
+  server.request('/v1/chat/completions', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', 'last-event-id': lastEventId },
+    body: '{}',
+  })
+
+describe('Phase 3: Stream Resume with Last-Event-ID', () => {
+  afterEach(() => {
+    configureStreamBuffer({ capacity: 1000 })
+  })
+
+  test('should number published events', () => {
+    const broadcast = openBroadcast('/chat/completions', 'gpt-4o')
+    expect(broadcast.publish({ data: 'one' }).id).toBe(`${broadcast.info.id}:1`)
+    expect(broadcast.publish({ data: 'two' }).id).toBe(`${broadcast.info.id}:2`)
+    broadcast.close()
+  })
+
+  test('should parse event ids', () => {
+    expect(parseEventId('abc-123:42')).toEqual({ streamId: 'abc-123', sequence: 42 })
+    expect(parseEventId('abc-123')).toBeUndefined()
+    expect(parseEventId(':4')).toBeUndefined()
+    expect(parseEventId('abc:x')).toBeUndefined()
+  })
+
+  test('should replay the events after Last-Event-ID from a finished stream', async () => {
+    const broadcast = openBroadcast('/chat/completions', 'gpt-4o')
+    const first = broadcast.publish({ data: 'one' })
+    broadcast.publish({ data: 'two' })
+    broadcast.publish({ data: '[DONE]' })
+    broadcast.close()
+
+    const response = await resume(first.id!)
+    expect(response.status).toBe(200)
+    expect(response.headers.get('x-stream-id')).toBe(broadcast.info.id)
+    expect(await response.text()).toBe(
+      `data: two\nid: ${broadcast.info.id}:2\n\ndata: [DONE]\nid: ${broadcast.info.id}:3\n\n`,
+    )
+  })
+
+  test('should follow a live stream after the replay', async () => {
+    const broadcast = openBroadcast('/chat/completions', 'gpt-4o')
+    broadcast.publish({ data: 'one' })
+
+    const response = resume(`${broadcast.info.id}:0`)
+    setTimeout(() => {
+      broadcast.publish({ data: 'two' })
+      broadcast.close()
+    }, 10)
+
+    const text = await (await response).text()
+    expect(text).toContain('data: one')
+    expect(text).toContain('data: two')
+  })
+
+  test('should refuse resumes once the events are gone', async () => {
+    configureStreamBuffer({ capacity: 2 })
+    const broadcast = openBroadcast('/chat/completions', 'gpt-4o')
+    const first = broadcast.publish({ data: 'one' })
+    broadcast.publish({ data: 'two' })
+    broadcast.publish({ data: 'three' })
+    broadcast.close()
+
+    // Event 2 is still buffered, so resuming after event 1 works
+    expect((await resume(first.id!)).status).toBe(200)
+    expect((await resume(`${broadcast.info.id}:0`)).status).toBe(404)
+    expect((await resume('unknown:3')).status).toBe(404)
+  })
+})
@@ -0,0 +1,101 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { validateJsonSchema } from '../../src/lib/json-schema'
+import { state } from '../../src/lib/state'
+import {
+  correctivePayload,
+  prepareStructuredOutput,
+  requestedSchema,
+  validateStructuredOutput,
+} from '../../src/routes/chat-completions/structured-output'
+import type { ChatCompletionsPayload } from '../../src/services/copilot/create-chat-completions'
+import type { Model } from '../../src/services/copilot/get-models'
+
+const schema = {
+  type: 'object',
+  required: ['city', 'days'],
+  additionalProperties: false,
+  properties: {
+    city: { type: 'string', minLength: 1 },
+    days: { type: 'integer', minimum: 1 },
+    units: { enum: ['metric', 'imperial'] },
+    tags: { type: 'array', items: { $ref: '#/$defs/tag' } },
+  },
+  $defs: { tag: { type: 'string', pattern: '^[a-z]+$' } },
+}
+
+const payload = (model = 'gpt-4o'): ChatCompletionsPayload => ({
+  model,
+  messages: [{ role: 'user', content: 'Weather?' }],
+  response_format: {
+    type: 'json_schema',
+    json_schema: { name: 'forecast', schema, strict: true },
+  },
+})
+
+const model = (id: string, structuredOutputs: boolean) =>
+  ({
+    id,
+    capabilities: { supports: { structured_outputs: structuredOutputs } },
+  }) as unknown as Model
+
+describe('Phase 3: Structured Outputs', () => {
+  afterEach(() => {
+    state.models = undefined
+  })
+
+  test('should accept conforming values', () => {
+    expect(validateJsonSchema({ city: 'Oslo', days: 3, tags: ['cold'] }, schema)).toEqual([])
+  })
+
+  test('should report every violation with its path', () => {
+    const errors = validateJsonSchema(
+      { city: '', days: 1.5, units: 'kelvin', tags: ['Cold'], extra: true },
+      schema,
+    )
+    expect(errors).toContain('$.city: shorter than 1 characters')
+    expect(errors).toContain('$.days: expected integer, got number')
+    expect(errors).toContain('$.units: must be one of ["metric","imperial"]')
+    expect(errors).toContain('$.tags[0]: does not match ^[a-z]+$')
+    expect(errors).toContain('$: unexpected property extra')
+    expect(validateJsonSchema({}, schema)).toEqual([
+      '$: missing required property city',
+      '$: missing required property days',
+    ])
+  })
+
+  test('should support combinators', () => {
+    const either = { anyOf: [{ type: 'string' }, { type: 'null' }] }
+    expect(validateJsonSchema(null, either)).toEqual([])
+    expect(validateJsonSchema(1, either)).toEqual(['$: matches none of anyOf'])
+    const exactlyOne = { oneOf: [{ type: 'number' }, { type: 'integer' }] }
+    expect(validateJsonSchema(1.5, exactlyOne)).toEqual([])
+    expect(validateJsonSchema(2, exactlyOne)).toHaveLength(1)
+  })
+
+  test('should reject content that is not JSON', () => {
+    expect(validateStructuredOutput('Sure! {"city": "Oslo"}', schema)).toEqual(['$: not valid JSON'])
+    expect(validateStructuredOutput(null, schema)).toEqual(['$: not valid JSON'])
+    expect(validateStructuredOutput('{"city": "Oslo", "days": 2}', schema)).toEqual([])
+  })
+
+  test('should forward response_format only to models that support it', () => {
+    state.models = {
+      object: 'list',
+      data: [model('gpt-4o', true), model('claude-sonnet-4', false)],
+    }
+    expect(prepareStructuredOutput(payload())).toEqual(payload())
+
+    const fallback = prepareStructuredOutput(payload('claude-sonnet-4'))
+    expect(fallback.response_format).toBeUndefined()
+    expect(fallback.messages).toHaveLength(2)
+    expect(fallback.messages[1].role).toBe('system')
+    expect(fallback.messages[1].content).toContain(JSON.stringify(schema))
+    expect(requestedSchema(payload())).toEqual(schema)
+  })
+
+  test('should add the failed reply and errors to the retry', () => {
+    const retry = correctivePayload(payload(), '{"city": "Oslo"}', ['$: missing required property days'])
+    expect(retry.messages.slice(1).map((message) => message.role)).toEqual(['assistant', 'system'])
+    expect(retry.messages[2].content).toContain('$: missing required property days')
+  })
+})
@@ -0,0 +1,148 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { server } from '../../src/server'
+import { state } from '../../src/lib/state'
+import type { ModelsResponse } from '../../src/services/copilot/get-models'
+import type { Message } from '../../src/services/copilot/create-chat-completions'
+import { chatCompletion, fakeUpstream, upstreamError, type FakeUpstream } from '../testkit/upstream'
+
+const models = {
+  object: 'list',
+  data: [
+    {
+      id: 'gpt-4o',
+      capabilities: {
+        limits: { max_context_window_tokens: 1000, max_prompt_tokens: 900, max_output_tokens: 400 },
+      },
+    },
+  ],
+} as unknown as ModelsResponse
+
+const text = (words: number) => 'word '.repeat(words).trim()
+
+// Three 300 token exchanges, well over the 900 token prompt limit
+const longConversation: Array<Message> = [
+  { role: 'system', content: 'You are a helpful assistant.' },
+  ...[1, 2, 3].flatMap((turn): Array<Message> => [
+    { role: 'user', content: `Question ${turn}: ${text(150)}` },
+    { role: 'assistant', content: `Answer ${turn}: ${text(150)}` },
+  ]),
+  { role: 'user', content: 'And what about the weather?' },
+]
+
+const postChat = (messages: Array<Message>) =>
+  server.request('/v1/chat/completions', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ model: 'gpt-4o', max_tokens: 100, messages }),
+  })
+
+describe('Phase 3: Conversation Summarization', () => {
+  const originalModels = state.models
+  const originalToken = state.copilotToken
+  let upstream: FakeUpstream | undefined
+
+  beforeEach(() => {
+    state.models = models
+    state.summarizeModel = 'gpt-4o-mini'
+    state.copilotToken = 'test-copilot-token'
+  })
+
+  afterEach(() => {
+    upstream?.restore()
+    upstream = undefined
+    state.models = originalModels
+    state.summarizeModel = undefined
+    state.copilotToken = originalToken
+  })
+
+  test('should replace the oldest turns with a summary', async () => {
+    upstream = fakeUpstream([
+      chatCompletion({ content: 'The user asked three questions.' }),
+      chatCompletion({ content: 'Sunny.' }),
+    ])
+    const response = await postChat(longConversation)
+
+    expect(response.status).toBe(200)
+    const [summaryRequest, forwarded] = upstream.requests
+    expect(summaryRequest.model).toBe('gpt-4o-mini')
+    expect(String(summaryRequest.messages[1].content)).toContain('Question 1')
+
+    const summarized = Number(response.headers.get('x-context-summarized'))
+    expect(summarized).toBeGreaterThan(0)
+    expect(forwarded.model).toBe('gpt-4o')
+    expect(forwarded.messages[0]).toEqual(longConversation[0])
+    expect(forwarded.messages[1].role).toBe('system')
+    expect(String(forwarded.messages[1].content)).toContain('The user asked three questions.')
+    expect(forwarded.messages.slice(2)).toEqual(longConversation.slice(1 + summarized))
+    expect(forwarded.messages.at(-1)).toEqual(longConversation.at(-1))
+  })
+
+  test('should keep tool results with the call that produced them', async () => {
+    upstream = fakeUpstream([chatCompletion({ content: 'Summary.' }), chatCompletion({ content: 'Done.' })])
+    const response = await postChat([
+      ...longConversation.slice(0, -1),
+      {
+        role: 'assistant',
+        content: text(120),
+        tool_calls: [{ id: 'call_1', type: 'function', function: { name: 'read_file', arguments: '{}' } }],
+      },
+      { role: 'tool', tool_call_id: 'call_1', content: text(300) },
+      { role: 'user', content: text(50) },
+    ])
+
+    expect(response.status).toBe(200)
+    const kept = upstream.requests[1].messages.slice(2)
+    expect(kept[0].role).not.toBe('tool')
+    expect(kept.some((message) => message.role === 'tool')).toBe(true)
+    expect(kept[kept.findIndex((message) => message.role === 'tool') - 1].tool_calls).toBeDefined()
+  })
+
+  test('should count tool results towards the prompt limit', async () => {
+    upstream = fakeUpstream([chatCompletion({ content: 'The log was read.' }), chatCompletion({ content: 'Done.' })])
+    const response = await postChat([
+      { role: 'system', content: 'You are a helpful assistant.' },
+      { role: 'user', content: 'Read the log file' },
+      {
+        role: 'assistant',
+        content: null,
+        tool_calls: [{ id: 'call_1', type: 'function', function: { name: 'read_file', arguments: '{}' } }],
+      },
+      // Over the 900 token prompt limit on its own
+      { role: 'tool', tool_call_id: 'call_1', content: `Log: ${text(1000)}` },
+      { role: 'user', content: 'What went wrong?' },
+    ])
+
+    expect(response.status).toBe(200)
+    expect(response.headers.get('x-context-summarized')).toBe('3')
+    expect(String(upstream.requests[0].messages[1].content)).toContain('tool: Log: word')
+    expect(upstream.requests[1].messages.map((message) => message.role)).toEqual(['system', 'system', 'user'])
+  })
+
+  test('should forward prompts under the limit unchanged', async () => {
+    upstream = fakeUpstream([chatCompletion({ content: 'Hi.' })])
+    const messages: Array<Message> = [{ role: 'user', content: 'Hello' }]
+    const response = await postChat(messages)
+
+    expect(response.headers.get('x-context-summarized')).toBeNull()
+    expect(upstream.requests).toHaveLength(1)
+    expect(upstream.requests[0].messages).toEqual(messages)
+  })
+
+  test('should forward the request as sent when summarizing fails', async () => {
+    upstream = fakeUpstream([upstreamError(500, 'Summarizer down'), chatCompletion({ content: 'Sunny.' })])
+    const response = await postChat(longConversation)
+
+    expect(response.status).toBe(200)
+    expect(response.headers.get('x-context-summarized')).toBeNull()
+    expect(upstream.requests[1].messages).toEqual(longConversation)
+  })
+
+  test('should be off without a summarize model', async () => {
+    state.summarizeModel = undefined
+    upstream = fakeUpstream([chatCompletion({ content: 'Sunny.' })])
+    await postChat(longConversation)
+
+    expect(upstream.requests).toHaveLength(1)
+    expect(upstream.requests[0].messages).toEqual(longConversation)
+  })
+})
@@ -0,0 +1,292 @@
+import { test, expect, describe, beforeEach, afterEach } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { state } from '../../src/lib/state'
+import {
+  createKey,
+  flushKeyUsage,
+  keyStatus,
+  readTeamStore,
+  revokeKeys,
+  rotateKey,
+  teamCredentials,
+  updateTeamStore,
+  writeTeamStore,
+  type TeamStore,
+} from '../../src/lib/team'
+import { keysRoute } from '../../src/routes/keys/route'
+
+const HOUR_MS = 60 * 60 * 1000
+let storePath: string
+
+const emptyStore = (): TeamStore => ({ members: {} })
+
+function createApp() {
+  const app = new Hono()
+  app.use(teamCredentials)
+  app.get('/', (c) => c.text('ok'))
+  return app
+}
+
+const ADMIN_TOKEN = 'admin-secret'
+
+function adminRoutes() {
+  const app = new Hono()
+  app.route('/admin/keys', keysRoute)
+  return app
+}
+
+// Sends the admin token with every request
+function adminApp() {
+  const app = adminRoutes()
+  return {
+    request: (url: string, init: RequestInit = {}) =>
+      app.request(url, { ...init, headers: { authorization: `Bearer ${ADMIN_TOKEN}` } }),
+  }
+}
+
+describe('Phase 3: Team API Key Lifecycle', () => {
+  beforeEach(async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-keys-'))
+    storePath = path.join(dir, 'team_tokens.json')
+    state.adminToken = ADMIN_TOKEN
+  })
+
+  afterEach(() => {
+    state.teamStore = undefined
+    state.adminToken = undefined
+  })
+
+  test('should create keys with an optional expiry and audit them', () => {
+    const store = emptyStore()
+    const { apiKey, id } = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+
+    expect(apiKey).toStartWith('cpk-')
+    expect(keyStatus(store.members[id])).toBe('active')
+    expect(store.audit).toEqual([
+      { time: expect.any(String), action: 'created', id, login: 'octocat', via: 'cli' },
+    ])
+
+    const past = new Date(Date.now() - 1000).toISOString()
+    const expired = createKey(store, { login: 'octocat', githubToken: 'ghu_cat', expiresAt: past }, 'admin')
+    expect(keyStatus(store.members[expired.id])).toBe('expired')
+  })
+
+  test('should rotate keys with a grace period', () => {
+    const store = emptyStore()
+    const { id } = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+
+    const before = Date.now()
+    const rotated = rotateKey(store, id, { graceMs: HOUR_MS }, 'cli')
+    const old = store.members[id]
+
+    expect(rotated.id).not.toBe(id)
+    expect(store.members[rotated.id].githubToken).toBe('ghu_cat')
+    expect(old.rotatedTo).toBe(rotated.id)
+    // Still usable until the grace period ends
+    expect(keyStatus(old)).toBe('active')
+    expect(Date.parse(old.expiresAt!)).toBeGreaterThanOrEqual(before + HOUR_MS)
+    expect(keyStatus(old, Date.now() + 2 * HOUR_MS)).toBe('expired')
+    expect(store.audit?.map((event) => event.action)).toEqual(['created', 'created', 'rotated'])
+  })
+
+  test('should keep an earlier expiry when rotating', () => {
+    const store = emptyStore()
+    const soon = new Date(Date.now() + 60_000).toISOString()
+    const { id } = createKey(store, { login: 'octocat', githubToken: 'ghu_cat', expiresAt: soon }, 'cli')
+
+    rotateKey(store, id, { graceMs: HOUR_MS }, 'cli')
+    expect(store.members[id].expiresAt).toBe(soon)
+  })
+
+  test('should refuse to rotate missing or revoked keys', () => {
+    const store = emptyStore()
+    const { id } = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+    revokeKeys(store, id, 'cli')
+
+    expect(() => rotateKey(store, id, { graceMs: 0 }, 'cli')).toThrow(`API key ${id} is revoked`)
+    expect(() => rotateKey(store, 'nope', { graceMs: 0 }, 'cli')).toThrow('No API key nope')
+  })
+
+  test('should revoke every key of a login but keep them listed', () => {
+    const store = emptyStore()
+    const first = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+    const second = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+    const other = createKey(store, { login: 'hubot', githubToken: 'ghu_bot' }, 'cli')
+
+    expect(revokeKeys(store, 'octocat', 'admin').sort()).toEqual([first.id, second.id].sort())
+    expect(keyStatus(store.members[first.id])).toBe('revoked')
+    expect(keyStatus(store.members[other.id])).toBe('active')
+    // Already revoked keys are not revoked again
+    expect(revokeKeys(store, 'octocat', 'admin')).toEqual([])
+  })
+
+  test('should reject revoked and expired keys with their own codes', async () => {
+    const store = emptyStore()
+    const revoked = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+    revokeKeys(store, revoked.id, 'cli')
+    const past = new Date(Date.now() - 1000).toISOString()
+    const expired = createKey(store, { login: 'octocat', githubToken: 'ghu_cat', expiresAt: past }, 'cli')
+    await writeTeamStore(store, storePath)
+    state.teamStore = storePath
+
+    for (const [apiKey, code] of [
+      [revoked.apiKey, 'revoked_api_key'],
+      [expired.apiKey, 'expired_api_key'],
+    ]) {
+      const response = await createApp().request('/', { headers: { authorization: `Bearer ${apiKey}` } })
+      expect(response.status).toBe(401)
+      const body = (await response.json()) as { error: { code: string } }
+      expect(body.error.code).toBe(code)
+    }
+  })
+
+  test('should not count rejected requests as key use', async () => {
+    const store = emptyStore()
+    const { apiKey, id } = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+    revokeKeys(store, id, 'cli')
+    await writeTeamStore(store, storePath)
+    state.teamStore = storePath
+
+    // Rejected requests do not count as use
+    await createApp().request('/', { headers: { authorization: `Bearer ${apiKey}` } })
+    await flushKeyUsage(storePath)
+    expect((await readTeamStore(storePath)).members[id].lastUsedAt).toBeUndefined()
+  })
+
+  test('should not lose a revocation to a concurrent usage flush', async () => {
+    const store = emptyStore()
+    const { id } = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+    await writeTeamStore(store, storePath)
+
+    await Promise.all([
+      // What the usage flush does
+      updateTeamStore((current) => {
+        current.members[id].lastUsedAt = new Date().toISOString()
+      }, storePath),
+      updateTeamStore((current) => revokeKeys(current, id, 'admin'), storePath),
+      ...Array.from({ length: 10 }, (_, index) =>
+        updateTeamStore(
+          (current) => createKey(current, { login: `member-${index}`, githubToken: 'ghu_x' }, 'admin'),
+          storePath,
+        ),
+      ),
+    ])
+
+    const { members } = await readTeamStore(storePath)
+    expect(keyStatus(members[id])).toBe('revoked')
+    expect(members[id].lastUsedAt).toBeDefined()
+    expect(Object.keys(members)).toHaveLength(11)
+    // No lock or temporary files are left behind
+    expect(await fs.readdir(path.dirname(storePath))).toEqual(['team_tokens.json'])
+  })
+
+  test('should wait for another process holding the store lock', async () => {
+    await writeTeamStore(emptyStore(), storePath)
+    await fs.writeFile(`${storePath}.lock`, '1\n')
+    setTimeout(() => void fs.rm(`${storePath}.lock`), 100)
+
+    const started = Date.now()
+    await updateTeamStore((store) => createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli'), storePath)
+
+    expect(Date.now() - started).toBeGreaterThanOrEqual(90)
+    expect(Object.keys((await readTeamStore(storePath)).members)).toHaveLength(1)
+  })
+
+  test('should manage keys through the admin API', async () => {
+    const store = emptyStore()
+    const { id } = createKey(store, { login: 'octocat', githubToken: 'ghu_cat' }, 'cli')
+    await writeTeamStore(store, storePath)
+    state.teamStore = storePath
+    const app = adminApp()
+
+    const created = await app.request('/admin/keys', {
+      method: 'POST',
+      body: JSON.stringify({ login: 'octocat', expiresInDays: 30 }),
+    })
+    expect(created.status).toBe(201)
+    const key = (await created.json()) as { id: string; apiKey: string; status: string; expiresAt: string }
+    expect(key.apiKey).toStartWith('cpk-')
+    expect(key.status).toBe('active')
+    expect(Date.parse(key.expiresAt)).toBeGreaterThan(Date.now() + 29 * 24 * HOUR_MS)
+
+    const rotated = await app.request(`/admin/keys/${id}/rotate`, {
+      method: 'POST',
+      body: JSON.stringify({ graceHours: 1 }),
+    })
+    expect(rotated.status).toBe(201)
+
+    const revoked = await app.request(`/admin/keys/${key.id}`, { method: 'DELETE' })
+    expect(((await revoked.json()) as { status: string }).status).toBe('revoked')
+
+    const listed = (await (await app.request('/admin/keys')).json()) as {
+      keys: Array<Record<string, unknown>>
+    }
+    expect(listed.keys).toHaveLength(3)
+    for (const entry of listed.keys) {
+      expect(entry).not.toHaveProperty('githubToken')
+      expect(entry).not.toHaveProperty('apiKey')
+    }
+
+    const { events } = (await (await app.request('/admin/keys/audit')).json()) as {
+      events: Array<{ action: string; via: string }>
+    }
+    expect(events.map((event) => `${event.action}:${event.via}`)).toEqual([
+      'created:cli',
+      'created:admin',
+      'created:admin',
+      'rotated:admin',
+      'revoked:admin',
+    ])
+  })
+
+  test('should reject unknown members and bad options', async () => {
+    await writeTeamStore(emptyStore(), storePath)
+    state.teamStore = storePath
+    const app = adminApp()
+
+    const unknown = await app.request('/admin/keys', { method: 'POST', body: JSON.stringify({ login: 'nobody' }) })
+    expect(unknown.status).toBe(404)
+    const invalid = await app.request('/admin/keys', {
+      method: 'POST',
+      body: JSON.stringify({ login: 'nobody', expiresInDays: -1 }),
+    })
+    expect(invalid.status).toBe(400)
+    const missing = await app.request('/admin/keys/nope', { method: 'DELETE' })
+    expect(missing.status).toBe(404)
+  })
+
+  test('should hide the admin API outside team mode', async () => {
+    const response = await adminApp().request('/admin/keys')
+    expect(response.status).toBe(404)
+  })
+
+  test('should reject callers without the admin token', async () => {
+    await writeTeamStore(emptyStore(), storePath)
+    state.teamStore = storePath
+
+    const missing = await adminRoutes().request('/admin/keys')
+    expect(missing.status).toBe(401)
+    const body = (await missing.json()) as { error: { code: string } }
+    expect(body.error.code).toBe('invalid_api_key')
+
+    const wrong = await adminRoutes().request('/admin/keys', {
+      method: 'POST',
+      headers: { authorization: 'Bearer not-the-token' },
+      body: JSON.stringify({ login: 'octocat' }),
+    })
+    expect(wrong.status).toBe(401)
+    expect((await readTeamStore(storePath)).members).toEqual({})
+  })
+
+  test('should refuse the admin API in team mode without an admin token', async () => {
+    await writeTeamStore(emptyStore(), storePath)
+    state.teamStore = storePath
+    state.adminToken = undefined
+
+    const response = await adminRoutes().request('/admin/keys')
+    expect(response.status).toBe(401)
+  })
+})
@@ -0,0 +1,116 @@
+import { test, expect, describe, beforeEach, afterEach, mock } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { copilotHeaders, githubHeaders } from '../../src/lib/api-config'
+import { keyId } from '../../src/lib/api-key'
+import { state } from '../../src/lib/state'
+import { openBroadcast } from '../../src/lib/stream-broadcast'
+import { readTeamStore, teamCredentials, writeTeamStore } from '../../src/lib/team'
+import { server } from '../../src/server'
+
+const originalFetch = globalThis.fetch
+let storePath: string
+
+function createApp() {
+  const app = new Hono()
+  app.use(teamCredentials)
+  app.get('/', (c) =>
+    c.json({
+      copilot: copilotHeaders(state).Authorization,
+      github: githubHeaders(state).authorization,
+    }),
+  )
+  return app
+}
+
+describe('Phase 3: Team Mode', () => {
+  beforeEach(async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-team-'))
+    storePath = path.join(dir, 'team_tokens.json')
+    state.copilotToken = 'copilot-owner'
+    state.githubToken = 'ghu_owner'
+  })
+
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    state.teamStore = undefined
+    state.copilotToken = undefined
+    state.githubToken = undefined
+  })
+
+  test('should store members privately and start empty', async () => {
+    expect(await readTeamStore(storePath)).toEqual({ members: {} })
+
+    const store = { members: { abc: { login: 'octocat', githubToken: 'ghu_cat', addedAt: '2025-01-01T00:00:00.000Z' } } }
+    await writeTeamStore(store, storePath)
+    expect(await readTeamStore(storePath)).toEqual(store)
+    expect((await fs.stat(storePath)).mode & 0o777).toBe(0o600)
+  })
+
+  test('should use the credentials of the key owner', async () => {
+    await writeTeamStore(
+      { members: { [keyId('cpk-alice')]: { login: 'alice', githubToken: 'ghu_alice', addedAt: '' } } },
+      storePath,
+    )
+    state.teamStore = storePath
+    const exchanged: Array<string | null> = []
+    globalThis.fetch = mock(async (_url: string, init?: RequestInit) => {
+      exchanged.push(new Headers(init?.headers).get('authorization'))
+      return Response.json({ token: 'copilot-alice', expires_at: 0, refresh_in: 1500 })
+    }) as unknown as typeof fetch
+
+    const app = createApp()
+    for (let i = 0; i < 2; i++) {
+      const response = await app.request('/', { headers: { authorization: 'Bearer cpk-alice' } })
+      expect(await response.json()).toEqual({ copilot: 'Bearer copilot-alice', github: 'token ghu_alice' })
+    }
+    // Exchanged once, then cached
+    expect(exchanged).toEqual(['token ghu_alice'])
+    // The gateway's own credentials are unchanged outside the request
+    expect(copilotHeaders(state).Authorization).toBe('Bearer copilot-owner')
+  })
+
+  test('should reject keys that are not in the store', async () => {
+    await writeTeamStore({ members: {} }, storePath)
+    state.teamStore = storePath
+
+    for (const headers of [{ authorization: 'Bearer cpk-mallory' }, {}]) {
+      const response = await createApp().request('/', { headers })
+      expect(response.status).toBe(401)
+      const body = (await response.json()) as { error: { code: string } }
+      expect(body.error.code).toBe('invalid_api_key')
+    }
+  })
+
+  test('should check the key before resuming streams, replaying sessions or /admin', async () => {
+    await writeTeamStore({ members: {} }, storePath)
+    state.teamStore = storePath
+    const broadcast = openBroadcast('/chat/completions', 'gpt-4o')
+    const first = broadcast.publish({ data: 'one' })
+    broadcast.close()
+
+    const resumed = await server.request('/v1/chat/completions', {
+      method: 'POST',
+      headers: { 'content-type': 'application/json', 'last-event-id': first.id! },
+      body: '{}',
+    })
+    expect(resumed.status).toBe(401)
+
+    const replayed = await server.request('/sessions/some-session/replay', {
+      method: 'POST',
+      body: JSON.stringify({ model: 'gpt-4o' }),
+    })
+    expect(replayed.status).toBe(401)
+
+    for (const url of ['/admin/streams', `/admin/streams/${broadcast.info.id}`, '/admin/samples']) {
+      expect((await server.request(url)).status).toBe(401)
+    }
+  })
+
+  test('should leave requests alone outside team mode', async () => {
+    const response = await createApp().request('/')
+    expect(await response.json()).toEqual({ copilot: 'Bearer copilot-owner', github: 'token ghu_owner' })
+  })
+})
@@ -0,0 +1,56 @@
+import { test, expect, describe, beforeEach } from 'bun:test'
+import { chargeTokens, resetTokenBudgets } from '../../src/lib/token-budget'
+import { HTTPError } from '../../src/lib/error'
+import { state } from '../../src/lib/state'
+
+describe('Phase 3: Token Budget Rate Limiting', () => {
+  beforeEach(() => {
+    resetTokenBudgets()
+    state.rateLimitWait = false
+  })
+
+  test('should allow requests within the budget', async () => {
+    const now = () => 0
+    await chargeTokens('client', 600, 1000, now)
+    await chargeTokens('client', 400, 1000, now)
+  })
+
+  test('should reject with 429 and Retry-After once the budget is spent', async () => {
+    const now = () => 0
+    await chargeTokens('client', 1000, 1000, now)
+
+    let error: unknown
+    try {
+      await chargeTokens('client', 500, 1000, now)
+    } catch (e) {
+      error = e
+    }
+
+    expect(error).toBeInstanceOf(HTTPError)
+    const response = (error as HTTPError).response
+    expect(response.status).toBe(429)
+    // 500 tokens at 1000 per minute refill in 30 seconds
+    expect(response.headers.get('retry-after')).toBe('30')
+  })
+
+  test('should refill the bucket over time', async () => {
+    let time = 0
+    const now = () => time
+    await chargeTokens('client', 1000, 1000, now)
+
+    time = 30_000
+    await chargeTokens('client', 500, 1000, now)
+  })
+
+  test('should keep separate budgets per client', async () => {
+    const now = () => 0
+    await chargeTokens('a', 1000, 1000, now)
+    await chargeTokens('b', 1000, 1000, now)
+  })
+
+  test('should cap oversized prompts at the full budget', async () => {
+    const now = () => 0
+    await chargeTokens('client', 5000, 1000, now)
+    await expect(chargeTokens('client', 1, 1000, now)).rejects.toBeInstanceOf(HTTPError)
+  })
+})
@@ -0,0 +1,48 @@
+import { test, expect, describe } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+import type { Message } from '../../src/services/copilot/create-chat-completions'
+
+describe('Phase 3: Token Count Memoization', () => {
+  test('growing conversations only encode new messages', async () => {
+    const conversation: Array<Message> = [{ role: 'system', content: `You are a helpful assistant. ${Date.now()}` }]
+
+    for (let turn = 0; turn < 10; turn++) {
+      conversation.push({ role: 'user', content: `Question ${turn}: what is ${turn} squared?` })
+      const before = rustCore.getTokenCacheStats()
+      await rustCore.getTokenCount(conversation)
+      const after = rustCore.getTokenCacheStats()
+
+      // Misses: the previous question (now followed by a newline), the reply and the new question
+      expect(after.misses - before.misses).toBeLessThanOrEqual(3)
+      expect(after.hits - before.hits).toBeGreaterThanOrEqual(conversation.length - 3)
+
+      conversation.push({ role: 'assistant', content: `${turn * turn}.` })
+    }
+
+    console.log('Incremental token counting: ✓')
+  })
+
+  test('cached counts match encoding the whole conversation at once', async () => {
+    const messages: Array<Message> = [
+      { role: 'system', content: 'Be terse!\n' },
+      { role: 'user', content: 'Hello   ' },
+      { role: 'assistant', content: 'Hi 🙂\r\n' },
+      { role: 'user', content: [{ type: 'text', text: "What's 1+1?" }, { type: 'image_url', image_url: { url: 'data:,' } }] }
+    ]
+    const joined = "Be terse!\n\nuser: Hello   \nassistant: Hi 🙂\r\n\nuser: What's 1+1?"
+
+    const segmented = await rustCore.getTokenCount(messages)
+    const whole = await rustCore.getTokenCount([{ role: 'system', content: joined }])
+
+    expect(segmented.input).toBe(whole.input)
+  })
+
+  test('cache size stays bounded', async () => {
+    for (let i = 0; i < 5000; i++) {
+      await rustCore.getTokenCount([{ role: 'user', content: `unique message ${i} ${Math.random()}` }])
+    }
+
+    const stats = rustCore.getTokenCacheStats()
+    expect(stats.entries).toBeLessThanOrEqual(stats.capacity)
+  })
+})
@@ -0,0 +1,135 @@
+import { test, expect, describe } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+import { getTokenCount } from '../../src/lib/tokenizer'
+import { tokenizeRoute } from '../../src/routes/tokenize/route'
+import type { Message } from '../../src/services/copilot/create-chat-completions'
+
+const toolCall = (name: string, args: string) => ({
+  id: `call_${name}`,
+  type: 'function' as const,
+  function: { name, arguments: args },
+})
+
+const conversations: Record<string, Array<Message>> = {
+  empty: [],
+  'single user message': [{ role: 'user', content: 'Hello world' }],
+  'every role': [
+    { role: 'system', content: 'You are terse.' },
+    { role: 'developer', content: 'Answer in French.' },
+    { role: 'user', content: 'Hi' },
+    { role: 'assistant', content: 'Salut' },
+    { role: 'user', content: 'How are you?' },
+  ],
+  'trailing assistant': [
+    { role: 'user', content: 'Hi' },
+    { role: 'assistant', content: 'Hello! How can I help?' },
+  ],
+  'only an assistant': [{ role: 'assistant', content: 'Hello' }],
+  'multi-part content': [
+    {
+      role: 'user',
+      content: [
+        { type: 'text', text: 'Describe ' },
+        { type: 'image_url', image_url: { url: 'data:image/png;base64,AAAA' } },
+        { type: 'text', text: 'this image' },
+      ],
+    },
+  ],
+  'null content': [
+    { role: 'user', content: 'Hi' },
+    { role: 'assistant', content: null },
+    { role: 'user', content: 'Still there?' },
+  ],
+  'tool calls': [
+    { role: 'user', content: 'Weather in Paris and Rome?' },
+    {
+      role: 'assistant',
+      content: 'Checking.',
+      tool_calls: [toolCall('get_weather', '{"city":"Paris"}'), toolCall('get_weather', '{"city":"Rome"}')],
+    },
+  ],
+  'tool results': [
+    { role: 'user', content: 'Weather in Paris?' },
+    { role: 'assistant', content: null, tool_calls: [toolCall('get_weather', '{"city":"Paris"}')] },
+    { role: 'tool', content: '{"forecast":"sunny"}', tool_call_id: 'call_get_weather' },
+  ],
+  'answer after tool results': [
+    { role: 'user', content: 'Weather in Paris?' },
+    { role: 'assistant', content: null, tool_calls: [toolCall('get_weather', '{"city":"Paris"}')] },
+    { role: 'tool', content: '{"forecast":"sunny"}', tool_call_id: 'call_get_weather' },
+    { role: 'assistant', content: 'It is sunny.' },
+  ],
+  'unicode and whitespace': [
+    { role: 'user', content: 'Ünïcödé 🙂\r\n\ttabs   and spaces\n' },
+    { role: 'assistant', content: '日本語の答え' },
+  ],
+}
+
+const tokenize = async (messages: unknown) => {
+  const response = await tokenizeRoute.request('/', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body: JSON.stringify({ messages }),
+  })
+  return { status: response.status, body: (await response.json()) as Record<string, number> }
+}
+
+describe('Phase 3: Token Counter', () => {
+  for (const [name, messages] of Object.entries(conversations)) {
+    test(`native and JS counts match: ${name}`, async () => {
+      expect(await rustCore.getTokenCount(messages)).toEqual(getTokenCount(messages))
+    })
+  }
+
+  test('should serve the native count from /v1/tokenize', async () => {
+    for (const messages of Object.values(conversations)) {
+      const { input, output } = await rustCore.getTokenCount(messages)
+      const { status, body } = await tokenize(messages)
+      expect(status).toBe(200)
+      expect(body).toEqual({ input_tokens: input, output_tokens: output, total_tokens: input + output })
+    }
+  })
+
+  test('should count a trailing assistant message as output only', async () => {
+    const [user] = conversations['trailing assistant']!
+    const { input } = await rustCore.getTokenCount([user!])
+    const count = await rustCore.getTokenCount(conversations['trailing assistant']!)
+
+    expect(count.input).toBe(input)
+    expect(count.output).toBeGreaterThan(0)
+    expect((await rustCore.getTokenCount(conversations['only an assistant']!)).input).toBe(0)
+  })
+
+  test('should not count tool results', async () => {
+    const [user, assistant, result] = conversations['tool results']!
+    const count = await rustCore.getTokenCount([user!, assistant!, result!])
+    const longerResult = await rustCore.getTokenCount([user!, assistant!, { ...result!, content: 'x '.repeat(100) }])
+
+    expect(count.output).toBe(0)
+    expect(longerResult).toEqual(count)
+    expect((await rustCore.getTokenCount([user!, result!, assistant!])).input).toBe((await rustCore.getTokenCount([user!])).input)
+  })
+
+  test('should count tool calls as name(arguments) lines', async () => {
+    const calls = await rustCore.getTokenCount(conversations['tool calls']!)
+    const text = await rustCore.getTokenCount([
+      conversations['tool calls']![0]!,
+      { role: 'assistant', content: 'Checking.\nget_weather({"city":"Paris"})\nget_weather({"city":"Rome"})' },
+    ])
+
+    expect(calls).toEqual(text)
+  })
+
+  test('should join text parts and ignore images', async () => {
+    const parts = await rustCore.getTokenCount(conversations['multi-part content']!)
+    const text = await rustCore.getTokenCount([{ role: 'user', content: 'Describe this image' }])
+
+    expect(parts).toEqual(text)
+  })
+
+  test('should reject bodies without messages', async () => {
+    const { status, body } = await tokenize(undefined)
+    expect(status).toBe(400)
+    expect(body).toMatchObject({ error: { param: 'messages' } })
+  })
+})
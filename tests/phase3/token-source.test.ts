@@ -0,0 +1,51 @@
+import { test, expect, describe, beforeEach, afterEach, mock } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { Readable } from 'node:stream'
+import { state } from '../../src/lib/state'
+import { readTokenFile, readTokenStdin, reloadTokenFile } from '../../src/lib/token-source'
+
+const originalFetch = globalThis.fetch
+let tokenFile: string
+
+describe('Phase 3: Token Sources', () => {
+  beforeEach(async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-token-'))
+    tokenFile = path.join(dir, 'github_token')
+    await fs.writeFile(tokenFile, 'ghu_first\n')
+  })
+
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    state.githubToken = undefined
+    state.copilotToken = undefined
+  })
+
+  test('should read trimmed tokens from files and stdin', async () => {
+    expect(await readTokenFile(tokenFile)).toBe('ghu_first')
+    expect(await readTokenStdin(Readable.from(['ghu_', 'piped\n']))).toBe('ghu_piped')
+    await expect(readTokenStdin(Readable.from(['\n']))).rejects.toThrow('No GitHub token')
+
+    await fs.writeFile(tokenFile, '  \n')
+    await expect(readTokenFile(tokenFile)).rejects.toThrow('is empty')
+  })
+
+  test('should exchange a changed token for a new Copilot token', async () => {
+    const authorizations: Array<string | null> = []
+    globalThis.fetch = mock(async (_url: string, init?: RequestInit) => {
+      authorizations.push(new Headers(init?.headers).get('authorization'))
+      return Response.json({ token: 'copilot-second', expires_at: 0, refresh_in: 1500 })
+    }) as unknown as typeof fetch
+
+    state.githubToken = 'ghu_first'
+    expect(await reloadTokenFile(tokenFile)).toBe(false)
+    expect(authorizations).toEqual([])
+
+    await fs.writeFile(tokenFile, 'ghu_second\n')
+    expect(await reloadTokenFile(tokenFile)).toBe(true)
+    expect(state.githubToken).toBe('ghu_second')
+    expect(state.copilotToken).toBe('copilot-second')
+    expect(authorizations).toEqual(['token ghu_second'])
+  })
+})
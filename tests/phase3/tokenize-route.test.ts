@@ -0,0 +1,63 @@
+import { test, expect, describe } from 'bun:test'
+import { Hono } from 'hono'
+import { parseJsonBody } from '../../src/lib/json-body'
+import { tokenizeRoute } from '../../src/routes/tokenize/route'
+
+// Mounted as in server.ts, behind the JSON body parser
+function createApp() {
+  const app = new Hono()
+  app.use(parseJsonBody)
+  app.route('/v1/tokenize', tokenizeRoute)
+  return app
+}
+
+const post = (body: string) =>
+  createApp().request('/v1/tokenize', {
+    method: 'POST',
+    headers: { 'content-type': 'application/json' },
+    body,
+  })
+
+describe('Phase 3: Tokenize Route', () => {
+  test('should count the tokens of a messages array', async () => {
+    const response = await post(
+      JSON.stringify({
+        messages: [
+          { role: 'system', content: 'You are terse.' },
+          { role: 'user', content: 'Hello, world!' },
+          { role: 'assistant', content: 'Hi.' },
+        ],
+      }),
+    )
+
+    expect(response.status).toBe(200)
+    const counts = (await response.json()) as Record<string, number>
+    expect(counts.input_tokens).toBeGreaterThan(0)
+    expect(counts.output_tokens).toBeGreaterThan(0)
+    expect(counts.total_tokens).toBe(counts.input_tokens + counts.output_tokens)
+  })
+
+  test('should reject a payload without a messages array', async () => {
+    for (const body of [{}, { messages: 'Hello' }, { messages: { role: 'user' } }]) {
+      const response = await post(JSON.stringify(body))
+
+      expect(response.status).toBe(400)
+      expect(await response.json()).toEqual({
+        error: {
+          message: 'Expected a `messages` array',
+          type: 'invalid_request_error',
+          param: 'messages',
+          code: null,
+        },
+      })
+    }
+  })
+
+  test('should reject a body that is not valid JSON', async () => {
+    const response = await post('{"messages": [')
+
+    expect(response.status).toBe(400)
+    const { error } = (await response.json()) as { error: Record<string, unknown> }
+    expect(error).toMatchObject({ type: 'invalid_request_error', code: 'invalid_json' })
+  })
+})
@@ -0,0 +1,105 @@
+import { test, expect, describe } from 'bun:test'
+import {
+  createToolCallStreamRepairer,
+  repairJson,
+  repairToolCalls,
+} from '../../src/lib/tool-call-repair'
+import type {
+  ChatCompletionChunk,
+  ChatCompletionResponse,
+} from '../../src/services/copilot/create-chat-completions'
+
+const response = (...args: Array<string>): ChatCompletionResponse => ({
+  id: 'chatcmpl-1',
+  object: 'chat.completion',
+  created: 0,
+  model: 'gpt-4o',
+  choices: [
+    {
+      index: 0,
+      message: {
+        role: 'assistant',
+        content: null,
+        tool_calls: args.map((value, i) => ({
+          id: `call_${i}`,
+          type: 'function' as const,
+          function: { name: 'lookup', arguments: value },
+        })),
+      },
+      logprobs: null,
+      finish_reason: 'tool_calls',
+    },
+  ],
+})
+
+const chunk = (
+  args: string | undefined,
+  finishReason: 'tool_calls' | 'length' | null = null,
+): ChatCompletionChunk => ({
+  id: 'chatcmpl-1',
+  object: 'chat.completion.chunk',
+  created: 0,
+  model: 'gpt-4o',
+  choices: [
+    {
+      index: 0,
+      delta:
+        args === undefined ? {} : (
+          { tool_calls: [{ index: 0, function: { arguments: args } }] }
+        ),
+      finish_reason: finishReason,
+      logprobs: null,
+    },
+  ],
+})
+
+describe('Phase 3: Tool Call Repair', () => {
+  test('should complete truncated JSON', () => {
+    expect(repairJson('{"city": "Par')).toBe('{"city": "Par"}')
+    expect(repairJson('{"ids": [1, 2')).toBe('{"ids": [1, 2]}')
+    expect(repairJson('{"a": {"b":')).toBe('{"a": {"b":null}}')
+    expect(repairJson('{"a"')).toBe('{"a":null}')
+  })
+
+  test('should remove trailing commas outside strings', () => {
+    expect(repairJson('{"a": [1, 2,], "b": ",}",}')).toBe('{"a": [1, 2], "b": ",}"}')
+    expect(repairJson('{"a": 1,')).toBe('{"a": 1}')
+  })
+
+  test('should leave valid JSON alone and give up on garbage', () => {
+    expect(repairJson('{"a": 1}')).toBe('{"a": 1}')
+    expect(repairJson('')).toBe('{}')
+    expect(repairJson('not json')).toBeUndefined()
+  })
+
+  test('should count repaired calls in non-streaming responses', () => {
+    const result = repairToolCalls(response('{"a": 1}', '{"b": 2', 'oops'))
+    expect(result.repaired).toBe(1)
+    const args = result.response.choices[0].message.tool_calls?.map(
+      (call) => call.function.arguments,
+    )
+    expect(args).toEqual(['{"a": 1}', '{"b": 2}', 'oops'])
+  })
+
+  test('should insert the missing suffix before the finishing chunk', () => {
+    const repairer = createToolCallStreamRepairer()
+    expect(repairer.process(chunk('{"city": '))).toHaveLength(1)
+
+    const output = repairer.process(chunk('"Par', 'length'))
+    expect(output).toHaveLength(3)
+    expect(output[0].choices[0].delta.tool_calls?.[0].function?.arguments).toBe('"Par')
+    expect(output[0].choices[0].finish_reason).toBeNull()
+    expect(output[1].choices[0].delta.tool_calls?.[0].function?.arguments).toBe('"}')
+    expect(output[2].choices[0].delta).toEqual({})
+    expect(output[2].choices[0].finish_reason).toBe('length')
+    expect(repairer.repaired).toBe(1)
+  })
+
+  test('should pass complete streamed calls through unchanged', () => {
+    const repairer = createToolCallStreamRepairer()
+    repairer.process(chunk('{"city": "Paris"}'))
+    const finish = chunk(undefined, 'tool_calls')
+    expect(repairer.process(finish)).toEqual([finish])
+    expect(repairer.repaired).toBe(0)
+  })
+})
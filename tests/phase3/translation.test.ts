@@ -0,0 +1,93 @@
+import { test, expect, describe } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+import { translateToAnthropic, translateToOpenAI } from '../../src/routes/messages/non-stream-translation'
+
+describe('Phase 3: Payload Translation', () => {
+  const anthropicPayload = {
+    model: 'claude-3-5-sonnet',
+    max_tokens: 256,
+    system: [{ type: 'text' as const, text: 'You are a helpful assistant.' }],
+    metadata: { user_id: 'user-1' },
+    messages: [
+      { role: 'user' as const, content: 'What is the weather in Paris?' },
+      {
+        role: 'assistant' as const,
+        content: [
+          { type: 'text' as const, text: 'Let me check.' },
+          { type: 'tool_use' as const, id: 'toolu_1', name: 'get_weather', input: { city: 'Paris' } }
+        ]
+      },
+      {
+        role: 'user' as const,
+        content: [
+          { type: 'tool_result' as const, tool_use_id: 'toolu_1', content: 'Sunny, 24C' },
+          { type: 'text' as const, text: 'Thanks!' }
+        ]
+      }
+    ],
+    tools: [{ name: 'get_weather', description: 'Get the weather', input_schema: { type: 'object' } }],
+    tool_choice: { type: 'any' as const }
+  }
+
+  test('Anthropic to OpenAI matches the TypeScript translator', async () => {
+    const rustResult = await rustCore.translateAnthropicToOpenAI(anthropicPayload)
+    const jsResult = JSON.parse(JSON.stringify(translateToOpenAI(anthropicPayload)))
+
+    expect(rustResult).toEqual(jsResult)
+    expect(rustResult.tool_choice).toBe('required')
+    expect(rustResult.messages.map((m) => m.role)).toEqual(['system', 'user', 'assistant', 'user', 'tool'])
+
+    console.log('Anthropic → OpenAI parity: ✓')
+  })
+
+  test('image blocks become data URLs', async () => {
+    const result = await rustCore.translateAnthropicToOpenAI({
+      model: 'claude-3-5-sonnet',
+      max_tokens: 100,
+      messages: [{
+        role: 'user',
+        content: [
+          { type: 'text', text: 'Describe this' },
+          { type: 'image', source: { type: 'base64', media_type: 'image/png', data: 'iVBORw0' } }
+        ]
+      }]
+    })
+
+    expect(result.messages[0].content).toEqual([
+      { type: 'text', text: 'Describe this' },
+      { type: 'image_url', image_url: { url: 'data:image/png;base64,iVBORw0' } }
+    ])
+  })
+
+  test('OpenAI to Anthropic matches the TypeScript translator', async () => {
+    const response = {
+      id: 'chatcmpl-1',
+      object: 'chat.completion' as const,
+      created: 0,
+      model: 'gpt-4o',
+      choices: [{
+        index: 0,
+        logprobs: null,
+        finish_reason: 'tool_calls' as const,
+        message: {
+          role: 'assistant' as const,
+          content: 'Calling a tool',
+          tool_calls: [{ id: 'call_1', type: 'function' as const, function: { name: 'get_weather', arguments: '{"city":"Paris"}' } }]
+        }
+      }],
+      usage: { prompt_tokens: 12, completion_tokens: 7, total_tokens: 19 }
+    }
+
+    const rustResult = await rustCore.translateOpenAIToAnthropic(response)
+
+    expect(rustResult).toEqual(translateToAnthropic(response))
+    expect(rustResult.stop_reason).toBe('tool_use')
+
+    console.log('OpenAI → Anthropic parity: ✓')
+  })
+
+  test('invalid input is rejected', async () => {
+    await expect(rustCore.translateOpenAIToAnthropic({ choices: [] } as any)).rejects.toThrow()
+    await expect(rustCore.translateAnthropicToOpenAI({} as any)).rejects.toThrow()
+  })
+})
@@ -0,0 +1,127 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { copilotBaseUrl } from '../../src/lib/api-config'
+import { renderPrometheus } from '../../src/lib/metrics'
+import { state } from '../../src/lib/state'
+import {
+  checkUpstreamHealth,
+  configureUpstreams,
+  parseUpstreams,
+  preferredUpstream,
+  sendToUpstream,
+  upstreamStatus,
+} from '../../src/lib/upstreams'
+import { fakeProvider, type FakeProvider } from '../testkit/upstream'
+
+const PRIMARY = 'https://primary.example.com'
+const SECONDARY = 'https://secondary.example.com'
+
+// Answers with the base URL it was called with, unless that one is down
+function createSend(down: Set<string>) {
+  const calls: Array<string> = []
+  const send = async (baseUrl: string) => {
+    calls.push(baseUrl)
+    if (down.has(baseUrl)) throw new TypeError(`Unable to connect to ${baseUrl}`)
+    return new Response(baseUrl)
+  }
+  return { send, calls }
+}
+
+describe('Phase 3: Upstream Failover', () => {
+  let provider: FakeProvider | undefined
+
+  afterEach(() => {
+    configureUpstreams([])
+    provider?.restore()
+    provider = undefined
+  })
+
+  test('should parse and validate the upstream list', () => {
+    expect(parseUpstreams(`${PRIMARY}/, ${SECONDARY}`)).toEqual([PRIMARY, SECONDARY])
+    expect(() => parseUpstreams('primary.example.com')).toThrow('Invalid upstream URL')
+  })
+
+  test('should use the account type base URL without upstreams', async () => {
+    const { send, calls } = createSend(new Set())
+    await sendToUpstream(send)
+    expect(calls).toEqual([copilotBaseUrl(state)])
+    expect(upstreamStatus()).toEqual([])
+  })
+
+  test('should fail over to the next upstream when one is unreachable', async () => {
+    configureUpstreams([PRIMARY, SECONDARY])
+    const { send, calls } = createSend(new Set([PRIMARY]))
+
+    expect(await (await sendToUpstream(send)).text()).toBe(SECONDARY)
+    expect(calls).toEqual([PRIMARY, SECONDARY])
+    expect(upstreamStatus()).toEqual([
+      { url: PRIMARY, state: 'closed', served: 0, failedOver: 0 },
+      { url: SECONDARY, state: 'closed', served: 1, failedOver: 1 },
+    ])
+  })
+
+  test('should not fail over on upstream error responses or aborts', async () => {
+    configureUpstreams([PRIMARY, SECONDARY])
+
+    const response = await sendToUpstream(async () => new Response('busy', { status: 503 }))
+    expect(response.status).toBe(503)
+    expect(upstreamStatus()[0].served).toBe(1)
+
+    const aborted = sendToUpstream(async () => {
+      throw new DOMException('The operation was aborted', 'AbortError')
+    })
+    await expect(aborted).rejects.toThrow('aborted')
+    expect(upstreamStatus()[1].served).toBe(0)
+  })
+
+  test('should skip an open circuit and fail back after a health check', async () => {
+    configureUpstreams([PRIMARY, SECONDARY], { failureThreshold: 2 })
+    const down = new Set([PRIMARY])
+    const { send, calls } = createSend(down)
+
+    await sendToUpstream(send)
+    await sendToUpstream(send)
+    expect(upstreamStatus()[0].state).toBe('open')
+    expect(preferredUpstream()).toBe(SECONDARY)
+
+    calls.length = 0
+    await sendToUpstream(send)
+    expect(calls).toEqual([SECONDARY])
+
+    // Still down
+    provider = fakeProvider(() => {
+      throw new TypeError('Unable to connect')
+    })
+    await checkUpstreamHealth()
+    expect(upstreamStatus()[0].state).toBe('open')
+    provider.restore()
+
+    down.clear()
+    provider = fakeProvider(() => new Response('{}'))
+    await checkUpstreamHealth()
+    expect(upstreamStatus()[0].state).toBe('closed')
+    expect(provider.requests.map((request) => request.url)).toEqual([`${PRIMARY}/models`])
+
+    calls.length = 0
+    expect(await (await sendToUpstream(send)).text()).toBe(PRIMARY)
+    expect(calls).toEqual([PRIMARY])
+  })
+
+  test('should try open circuits when every upstream is down', async () => {
+    configureUpstreams([PRIMARY, SECONDARY], { failureThreshold: 1 })
+    const { send, calls } = createSend(new Set([PRIMARY, SECONDARY]))
+
+    await expect(sendToUpstream(send)).rejects.toThrow(`Unable to connect to ${SECONDARY}`)
+    await sendToUpstream(send).catch(() => {})
+    expect(calls).toEqual([PRIMARY, SECONDARY, PRIMARY, SECONDARY])
+  })
+
+  test('should export which upstream served requests', async () => {
+    configureUpstreams([PRIMARY, SECONDARY])
+    await sendToUpstream(createSend(new Set([PRIMARY])).send)
+
+    const metrics = renderPrometheus()
+    expect(metrics).toContain(`copilot_api_upstream_served_total{upstream="${SECONDARY}"} 1`)
+    expect(metrics).toContain(`copilot_api_upstream_failovers_total{upstream="${SECONDARY}"} 1`)
+    expect(metrics).toContain(`copilot_api_upstream_circuit_open{upstream="${PRIMARY}"} 0`)
+  })
+})
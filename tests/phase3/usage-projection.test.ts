@@ -0,0 +1,118 @@
+import { test, expect, describe, beforeEach, afterEach, mock } from 'bun:test'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PATHS } from '../../src/lib/paths'
+import { state } from '../../src/lib/state'
+import {
+  addUsageSnapshot,
+  loadUsageSnapshots,
+  pollUsage,
+  projectUsage,
+  resetUsageSnapshots,
+} from '../../src/lib/usage-projection'
+
+const DAY = 86_400_000
+const NOW = Date.parse('2026-10-16T00:00:00Z')
+const RESET = '2026-11-01'
+
+const snapshot = (time: number, used: number) => ({
+  time: new Date(time).toISOString(),
+  used,
+  entitlement: 300,
+  resetDate: RESET,
+})
+
+const originalFetch = globalThis.fetch
+const originalSnapshotsPath = PATHS.USAGE_SNAPSHOTS_PATH
+
+describe('Phase 3: Usage Projection', () => {
+  beforeEach(async () => {
+    resetUsageSnapshots()
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-usage-'))
+    PATHS.USAGE_SNAPSHOTS_PATH = path.join(dir, 'usage_snapshots.jsonl')
+  })
+
+  afterEach(() => {
+    globalThis.fetch = originalFetch
+    PATHS.USAGE_SNAPSHOTS_PATH = originalSnapshotsPath
+    state.githubToken = undefined
+  })
+
+  test('should have no projection before the first snapshot', () => {
+    expect(projectUsage(NOW)).toBeUndefined()
+  })
+
+  test('should project the pace of the cycle so far', () => {
+    // 150 used in the first 15 days of the cycle, 10 a day
+    addUsageSnapshot(snapshot(NOW, 150))
+
+    const projection = projectUsage(NOW)
+    expect(projection?.used).toBe(150)
+    expect(projection?.linear.projected).toBe(310)
+    expect(Date.parse(projection?.linear.exhaustsAt ?? '')).toBeCloseTo(NOW + 15 * DAY, -4)
+    // A single snapshot has no recent pace
+    expect(projection?.recent).toBeUndefined()
+  })
+
+  test('should project the pace of the last day', () => {
+    addUsageSnapshot(snapshot(NOW - 3 * DAY, 100))
+    addUsageSnapshot(snapshot(NOW - DAY, 130))
+    addUsageSnapshot(snapshot(NOW, 150))
+
+    const projection = projectUsage(NOW)
+    // 20 a day over the remaining 16 days
+    expect(projection?.recent?.projected).toBe(470)
+    expect(Date.parse(projection?.recent?.exhaustsAt ?? '')).toBeCloseTo(NOW + 7.5 * DAY, -4)
+    expect(projection?.snapshots).toBe(3)
+  })
+
+  test('should not set exhaustsAt when the quota lasts', () => {
+    addUsageSnapshot(snapshot(NOW - DAY, 60))
+    addUsageSnapshot(snapshot(NOW, 60))
+
+    const projection = projectUsage(NOW)
+    expect(projection?.linear).toEqual({ projected: 124 })
+    expect(projection?.recent).toEqual({ projected: 60 })
+  })
+
+  test('should start over when the quota resets', () => {
+    addUsageSnapshot(snapshot(NOW - DAY, 280))
+    addUsageSnapshot({ ...snapshot(NOW, 5), resetDate: '2026-12-01' })
+
+    expect(projectUsage(NOW)?.snapshots).toBe(1)
+  })
+
+  test('should poll usage and read the snapshots back after a restart', async () => {
+    state.githubToken = 'ghu_owner'
+    globalThis.fetch = mock(async () =>
+      Response.json({
+        quota_reset_date: RESET,
+        quota_snapshots: {
+          premium_interactions: { entitlement: 300, quota_remaining: 90, percent_remaining: 30, unlimited: false },
+        },
+      }),
+    ) as unknown as typeof fetch
+
+    await pollUsage(NOW)
+    expect(projectUsage(NOW)?.used).toBe(210)
+
+    resetUsageSnapshots()
+    await loadUsageSnapshots()
+    expect(projectUsage(NOW)?.used).toBe(210)
+    const lines = (await fs.readFile(PATHS.USAGE_SNAPSHOTS_PATH, 'utf8')).trim().split('\n')
+    expect(lines).toHaveLength(1)
+  })
+
+  test('should not record unlimited plans', async () => {
+    globalThis.fetch = mock(async () =>
+      Response.json({
+        quota_reset_date: RESET,
+        quota_snapshots: { premium_interactions: { unlimited: true } },
+      }),
+    ) as unknown as typeof fetch
+
+    await pollUsage(NOW)
+    expect(projectUsage(NOW)).toBeUndefined()
+  })
+})
@@ -0,0 +1,84 @@
+import { test, expect, describe, beforeEach, afterAll } from 'bun:test'
+import { Hono } from 'hono'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { keyId } from '../../src/lib/api-key'
+import { PATHS } from '../../src/lib/paths'
+import { state } from '../../src/lib/state'
+import { formatUsageCsv, readUsage, recordUsage, summarizeUsage, type UsageRecord } from '../../src/lib/usage-ledger'
+
+const originalUsageDir = PATHS.USAGE_DIR
+
+const recorder = new Hono()
+recorder.post('/:model', async (c) => {
+  await recordUsage(c, {
+    endpoint: '/chat/completions',
+    model: c.req.param('model'),
+    usage: { prompt_tokens: 10, completion_tokens: 5 },
+  })
+  return c.body(null, 204)
+})
+
+const record = (model: string, key?: string) =>
+  recorder.request(`/${model}`, {
+    method: 'POST',
+    headers: key ? { 'x-api-key': key } : {},
+  })
+
+const usage = (time: string, model: string, prompt: number, completion: number): UsageRecord => ({
+  time,
+  key: keyId('team-a'),
+  endpoint: '/chat/completions',
+  model,
+  prompt_tokens: prompt,
+  completion_tokens: completion,
+})
+
+describe('Phase 3: Usage Report', () => {
+  beforeEach(async () => {
+    PATHS.USAGE_DIR = await fs.mkdtemp(path.join(os.tmpdir(), 'copilot-usage-'))
+    state.recordUsage = true
+  })
+
+  afterAll(() => {
+    PATHS.USAGE_DIR = originalUsageDir
+    state.recordUsage = undefined
+  })
+
+  test('should record usage by hashed key', async () => {
+    await record('gpt-4o', 'team-a')
+    await record('gpt-4o', 'team-b')
+    await record('gpt-4o')
+
+    const records = await readUsage({ key: keyId('team-a') })
+    expect(records).toHaveLength(1)
+    expect(records[0].key).not.toContain('team-a')
+    expect(records[0].prompt_tokens).toBe(10)
+    expect(await readUsage({ key: 'anonymous' })).toHaveLength(1)
+    expect(await readUsage({ key: keyId('team-a'), since: '2999-01-01T00:00:00.000Z' })).toEqual([])
+  })
+
+  test('should not record without the flag', async () => {
+    state.recordUsage = false
+    await record('gpt-4o', 'team-a')
+    expect(await fs.readdir(PATHS.USAGE_DIR)).toEqual([])
+  })
+
+  test('should total requests and tokens by day and model', () => {
+    const rows = summarizeUsage([
+      usage('2025-01-02T09:00:00.000Z', 'gpt-4o', 100, 20),
+      usage('2025-01-01T09:00:00.000Z', 'gpt-4o', 10, 1),
+      usage('2025-01-02T18:00:00.000Z', 'gpt-4o', 50, 5),
+      usage('2025-01-02T12:00:00.000Z', 'claude-sonnet-4', 7, 3),
+    ])
+    expect(rows).toEqual([
+      { day: '2025-01-01', model: 'gpt-4o', requests: 1, prompt_tokens: 10, completion_tokens: 1, total_tokens: 11 },
+      { day: '2025-01-02', model: 'claude-sonnet-4', requests: 1, prompt_tokens: 7, completion_tokens: 3, total_tokens: 10 },
+      { day: '2025-01-02', model: 'gpt-4o', requests: 2, prompt_tokens: 150, completion_tokens: 25, total_tokens: 175 },
+    ])
+    expect(formatUsageCsv(rows.slice(0, 1))).toBe(
+      'day,model,requests,prompt_tokens,completion_tokens,total_tokens\n2025-01-01,gpt-4o,1,10,1,11\n',
+    )
+  })
+})
@@ -0,0 +1,68 @@
+import { test, expect, describe, afterEach } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+const payload = (content: string) => ({ model: 'gpt-4.1', messages: [{ role: 'user', content }] })
+
+describe('Phase 3: Validation Cache', () => {
+  afterEach(() => {
+    rustCore.configureValidationCache(0)
+  })
+
+  test('should be off by default', async () => {
+    const before = rustCore.getValidationCacheStats()
+    await rustCore.validatePayload(payload('Hi'))
+    await rustCore.validatePayload(payload('Hi'))
+
+    expect(rustCore.getValidationCacheStats()).toEqual(before)
+    expect(before.capacity).toBe(0)
+  })
+
+  test('should answer repeat validations from the cache', async () => {
+    rustCore.configureValidationCache(16)
+    const before = rustCore.getValidationCacheStats()
+    const invalid = { model: 'gpt-4.1', messages: [{ role: 'assistant', content: 'Hi' }] }
+
+    const first = await rustCore.validatePayloadDetailed(invalid)
+    const repeat = await rustCore.validatePayloadDetailed(invalid)
+    const asBytes = await rustCore.validatePayloadDetailed(Buffer.from(JSON.stringify(invalid)))
+    const stats = rustCore.getValidationCacheStats()
+
+    expect(repeat).toEqual(first)
+    expect(asBytes).toEqual(first)
+    expect(stats.misses - before.misses).toBe(1)
+    expect(stats.hits - before.hits).toBe(2)
+    expect(stats.entries).toBe(1)
+  })
+
+  test('should still count failures answered from the cache', async () => {
+    rustCore.configureValidationCache(16)
+    const invalid = { model: 'gpt-4.1', messages: [{ role: 'assistant', content: 'Cached' }] }
+    const before = rustCore.getNativeMetrics().validationFailures.first_message_not_user ?? 0
+
+    await rustCore.validatePayload(invalid)
+    await rustCore.validatePayload(invalid)
+
+    expect(rustCore.getNativeMetrics().validationFailures.first_message_not_user).toBe(before + 2)
+  })
+
+  test('should evict the least recently used results beyond the capacity', async () => {
+    rustCore.configureValidationCache(8)
+    for (let i = 0; i < 20; i++) await rustCore.validatePayload(payload(`message ${i}`))
+
+    expect(rustCore.getValidationCacheStats().entries).toBeLessThanOrEqual(8)
+  })
+
+  test('should clear results but keep counters', async () => {
+    rustCore.configureValidationCache(16)
+    await rustCore.validatePayload(payload('Hi'))
+    await rustCore.validatePayload(payload('Hi'))
+    const before = rustCore.getValidationCacheStats()
+
+    expect(rustCore.clearValidationCache()).toBe(1)
+    expect(rustCore.getValidationCacheStats()).toEqual({ ...before, entries: 0 })
+  })
+
+  test('should reject a negative capacity', () => {
+    expect(() => rustCore.configureValidationCache(-1)).toThrow('capacity must be a non-negative number')
+  })
+})
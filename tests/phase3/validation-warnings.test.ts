@@ -0,0 +1,63 @@
+import { test, expect, describe } from 'bun:test'
+import { rustCore } from '../../src/lib/rust-core'
+
+const messages = [{ role: 'user', content: 'Hi' }]
+
+describe('Phase 3: Validation Warnings', () => {
+  test('should keep payloads with warnings valid', async () => {
+    const result = await rustCore.validatePayloadDetailed({ model: 'o3-mini', messages, temperature: 0.2, top_p: 0.9 })
+
+    expect(result.valid).toBe(true)
+    expect(result.errors).toBeUndefined()
+    expect(result.warnings).toEqual([
+      { code: 'parameter_ignored', message: 'temperature is likely ignored by o3-mini' },
+      { code: 'parameter_ignored', message: 'top_p is likely ignored by o3-mini' },
+    ])
+    expect(await rustCore.validatePayload({ model: 'o3-mini', messages, temperature: 0.2 })).toBe(true)
+  })
+
+  test('should not warn about sampling parameters for other models', async () => {
+    const result = await rustCore.validatePayloadDetailed({ model: 'gpt-4.1', messages, temperature: 0.2 })
+    expect(result).toEqual({ valid: true, contentType: 'text' })
+  })
+
+  test('should warn about very large system prompts in both formats', async () => {
+    const prompt = 'You are a helpful assistant. '.repeat(4000)
+    const openai = await rustCore.validatePayloadDetailed({
+      model: 'gpt-4.1',
+      messages: [{ role: 'system', content: prompt }, ...messages],
+    })
+    const anthropic = await rustCore.validatePayloadDetailed({
+      model: 'claude-sonnet-4',
+      max_tokens: 100,
+      system: [{ type: 'text', text: prompt }],
+      messages,
+    })
+
+    for (const result of [openai, anthropic]) {
+      expect(result.valid).toBe(true)
+      expect(result.warnings?.map(({ code }) => code)).toEqual(['large_system_prompt'])
+    }
+  })
+
+  test('should list errors and warnings separately', async () => {
+    const result = await rustCore.validatePayloadDetailed({
+      model: 'o1',
+      temperature: 1,
+      messages: [{ role: 'assistant', content: 'Hello' }, { role: 'assistant', content: 'Anyone?' }],
+    })
+
+    expect(result.valid).toBe(false)
+    expect(result.errors?.map(({ code }) => code)).toEqual(['first_message_not_user'])
+    expect(result.warnings?.map(({ code }) => code)).toEqual(['consecutive_same_role', 'parameter_ignored'])
+    expect(result.error).toBe('Message 0: The first non-system message must be from the user, not assistant')
+  })
+
+  test('should report malformed payloads as a single error', async () => {
+    const result = await rustCore.validatePayloadDetailed({ model: 'gpt-4.1' })
+
+    expect(result.valid).toBe(false)
+    expect(result.errors).toEqual([{ code: 'invalid_payload', message: result.error! }])
+    expect(result.warnings).toBeUndefined()
+  })
+})
@@ -0,0 +1,68 @@
+import { test, expect, describe, afterAll } from 'bun:test'
+import { rustCore, ValidationError } from '../../src/lib/rust-core'
+
+const encode = (value: unknown) => Buffer.from(JSON.stringify(value))
+
+const messages = [
+  { role: 'user' as const, content: 'Summarize this: ' + 'lorem ipsum '.repeat(1000) },
+  { role: 'assistant' as const, content: 'It repeats lorem ipsum.' },
+]
+
+describe('Phase 3: JSON Bytes Across the Native Boundary', () => {
+  let received: string | null = null
+  const server = Bun.serve({
+    port: 0,
+    async fetch(req) {
+      received = await req.text()
+      return new Response(received, { headers: { 'content-type': 'application/json' } })
+    }
+  })
+  const baseUrl = `http://127.0.0.1:${server.port}`
+
+  afterAll(() => server.stop(true))
+
+  test('should count tokens from a Buffer, Uint8Array or ArrayBuffer', async () => {
+    const expected = await rustCore.getTokenCount(messages)
+    const bytes = new TextEncoder().encode(JSON.stringify(messages))
+
+    expect(await rustCore.getTokenCount(encode(messages))).toEqual(expected)
+    expect(await rustCore.getTokenCount(bytes)).toEqual(expected)
+    expect(await rustCore.getTokenCount(bytes.buffer as ArrayBuffer)).toEqual(expected)
+  })
+
+  test('should validate payload bytes', async () => {
+    const payload = { model: 'gpt-4.1', messages }
+
+    expect(await rustCore.validatePayloadDetailed(encode(payload))).toEqual(await rustCore.validatePayloadDetailed(payload))
+    expect(await rustCore.validatePayload(encode({ model: 'gpt-4.1', messages: [] }))).toBe(false)
+  })
+
+  test('should throw ValidationError for malformed bytes', async () => {
+    const error = await rustCore.validatePayload(Buffer.from('{"model": ')).catch((error) => error)
+
+    expect(error).toBeInstanceOf(ValidationError)
+    expect(error.message).toStartWith('Invalid JSON')
+  })
+
+  test('should return translations of bytes as a Buffer', async () => {
+    const payload = { model: 'claude-sonnet-4', max_tokens: 100, messages: [{ role: 'user', content: 'Hi' }] }
+    const translated = await rustCore.translateAnthropicToOpenAI(encode(payload) as never) as unknown
+
+    expect(Buffer.isBuffer(translated)).toBe(true)
+    expect(JSON.parse(String(translated))).toEqual(await rustCore.translateAnthropicToOpenAI(payload as never))
+  })
+
+  test('should send payload bytes unchanged', async () => {
+    const body = '{ "model": "gpt-4.1",  "messages": [] }'
+    const response = await rustCore.createChatCompletions(Buffer.from(body), { baseUrl }).response
+
+    expect(received!).toBe(body)
+    expect(response).toBe(body)
+  })
+
+  test('should still accept payload objects', async () => {
+    await rustCore.createEmbeddings({ model: 'text-embedding-3-small', input: 'hi' }, { baseUrl }).response
+
+    expect(JSON.parse(received!)).toEqual({ model: 'text-embedding-3-small', input: 'hi' })
+  })
+})
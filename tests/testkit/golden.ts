@@ -0,0 +1,44 @@
+// Golden files: expected outputs kept as JSON next to the tests, so a change
+// in a compatibility layer shows up as a reviewable diff. Run the tests with
+// UPDATE_GOLDEN=1 to rewrite them from the current behaviour.
+
+import { expect } from 'bun:test'
+import fs from 'node:fs'
+import path from 'node:path'
+
+export const GOLDEN_DIR = path.join(import.meta.dir, '..', 'phase3', 'fixtures', 'golden')
+
+export const updatingGolden = () => Boolean(process.env.UPDATE_GOLDEN)
+
+// Drops undefined fields like the JSON the gateway sends
+export const asJson = <T>(value: T): T => JSON.parse(JSON.stringify(value)) as T
+
+/**
+ * Compares `actual` with `<GOLDEN_DIR>/<name>.json`. A missing file fails
+ * the test unless golden files are being updated.
+ */
+export function expectGolden(name: string, actual: unknown): void {
+  const filePath = path.join(GOLDEN_DIR, `${name}.json`)
+  const value = asJson(actual)
+  if (updatingGolden()) {
+    fs.mkdirSync(path.dirname(filePath), { recursive: true })
+    fs.writeFileSync(filePath, `${JSON.stringify(value, null, 2)}\n`)
+    return
+  }
+  if (!fs.existsSync(filePath)) {
+    throw new Error(`Missing golden file ${filePath}, run with UPDATE_GOLDEN=1 to create it`)
+  }
+  expect(value).toEqual(JSON.parse(fs.readFileSync(filePath, 'utf8')))
+}
+
+/** The `.json` fixtures of a directory, parsed, by file name. */
+export function readFixtures<T>(dir: string): Array<{ file: string; filePath: string; fixture: T }> {
+  return fs
+    .readdirSync(dir)
+    .filter((name) => name.endsWith('.json'))
+    .sort()
+    .map((file) => {
+      const filePath = path.join(dir, file)
+      return { file, filePath, fixture: JSON.parse(fs.readFileSync(filePath, 'utf8')) as T }
+    })
+}
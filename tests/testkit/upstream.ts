@@ -0,0 +1,227 @@
+// Builders for what Copilot sends back, a fake upstream that answers the
+// gateway's requests from a script, and a fake provider for any other host,
+// so handlers can be exercised without the network. Everything is
+// deterministic: fixed ids, timestamps and models.
+
+import { mock } from 'bun:test'
+import type {
+  ChatCompletionChunk,
+  ChatCompletionResponse,
+  ChatCompletionsPayload,
+  Usage,
+} from '../../src/services/copilot/create-chat-completions'
+
+export const FAKE_ID = 'chatcmpl-testkit'
+export const FAKE_CREATED = 1700000000
+export const FAKE_MODEL = 'gpt-4o'
+
+export interface FakeToolCall {
+  id: string
+  name: string
+  // Sent in these pieces when streamed, joined when not
+  arguments: Array<string>
+}
+
+type FinishReason = ChatCompletionChunk['choices'][number]['finish_reason']
+
+export const usage = (prompt: number, completion: number): Usage => ({
+  prompt_tokens: prompt,
+  completion_tokens: completion,
+  total_tokens: prompt + completion,
+})
+
+/** A non-streamed completion with text, tool calls or both. */
+export function chatCompletion(options: {
+  content?: string | null
+  toolCalls?: Array<FakeToolCall>
+  finishReason?: ChatCompletionResponse['choices'][number]['finish_reason']
+  usage?: Usage
+  model?: string
+} = {}): ChatCompletionResponse {
+  return {
+    id: FAKE_ID,
+    object: 'chat.completion',
+    created: FAKE_CREATED,
+    model: options.model ?? FAKE_MODEL,
+    choices: [
+      {
+        index: 0,
+        message: {
+          role: 'assistant',
+          content: options.content ?? null,
+          tool_calls: options.toolCalls?.map((call) => ({
+            id: call.id,
+            type: 'function' as const,
+            function: { name: call.name, arguments: call.arguments.join('') },
+          })),
+        },
+        logprobs: null,
+        finish_reason: options.finishReason ?? (options.toolCalls ? 'tool_calls' : 'stop'),
+      },
+    ],
+    usage: options.usage,
+  }
+}
+
+export function chunk(
+  delta: ChatCompletionChunk['choices'][number]['delta'],
+  options: { finishReason?: FinishReason; usage?: Usage; model?: string } = {},
+): ChatCompletionChunk {
+  return {
+    id: FAKE_ID,
+    object: 'chat.completion.chunk',
+    created: FAKE_CREATED,
+    model: options.model ?? FAKE_MODEL,
+    choices: [{ index: 0, delta, finish_reason: options.finishReason ?? null, logprobs: null }],
+    ...(options.usage && { usage: options.usage }),
+  }
+}
+
+/**
+ * The chunks of a streamed answer: a role chunk, one chunk per text piece,
+ * then each tool call's name and argument pieces, and a final chunk with
+ * the finish reason and usage.
+ */
+export function streamScript(options: {
+  text?: Array<string>
+  toolCalls?: Array<FakeToolCall>
+  finishReason?: FinishReason
+  usage?: Usage
+}): Array<ChatCompletionChunk> {
+  const chunks = [chunk({ role: 'assistant', content: '' })]
+  for (const piece of options.text ?? []) chunks.push(chunk({ content: piece }))
+  for (const [index, call] of (options.toolCalls ?? []).entries()) {
+    chunks.push(
+      chunk({
+        tool_calls: [{ index, id: call.id, type: 'function', function: { name: call.name, arguments: '' } }],
+      }),
+    )
+    for (const piece of call.arguments) {
+      chunks.push(chunk({ tool_calls: [{ index, function: { arguments: piece } }] }))
+    }
+  }
+  chunks.push(
+    chunk({}, {
+      finishReason: options.finishReason ?? (options.toolCalls ? 'tool_calls' : 'stop'),
+      usage: options.usage,
+    }),
+  )
+  return chunks
+}
+
+/** The SSE body Copilot sends for `chunks`, ending with `[DONE]`. */
+export const sseBody = (chunks: Array<ChatCompletionChunk>): string =>
+  [...chunks.map((item) => JSON.stringify(item)), '[DONE]']
+    .map((data) => `data: ${data}\n\n`)
+    .join('')
+
+/** The JSON `data` of each event of an SSE body, without `[DONE]`. */
+export const parseSseData = (body: string): Array<unknown> =>
+  body
+    .split('\n')
+    .filter((line) => line.startsWith('data: ') && line !== 'data: [DONE]')
+    .map((line) => JSON.parse(line.slice('data: '.length)) as unknown)
+
+/** An upstream failure with an OpenAI-style error body. */
+export function upstreamError(
+  status: number,
+  message: string,
+  headers: Record<string, string> = {},
+): Response {
+  return new Response(JSON.stringify({ error: { message, type: 'error' } }), {
+    status,
+    headers: { 'content-type': 'application/json', ...headers },
+  })
+}
+
+export type FakeResponse =
+  | ChatCompletionResponse
+  | Array<ChatCompletionChunk>
+  | Response
+
+function toResponse(response: FakeResponse): Response {
+  if (response instanceof Response) return response
+  if (Array.isArray(response)) {
+    return new Response(sseBody(response), {
+      headers: { 'content-type': 'text/event-stream' },
+    })
+  }
+  return Response.json(response)
+}
+
+export interface FakeUpstream {
+  // Bodies of the chat completion requests, in order
+  requests: Array<ChatCompletionsPayload>
+  restore: () => void
+}
+
+/**
+ * Replaces `fetch` so chat completion requests are answered with `script`
+ * in order: a completion as JSON, chunks as an SSE stream, or a Response
+ * as is. Requests beyond the script, or to other URLs, fail the test.
+ */
+export function fakeUpstream(script: Array<FakeResponse>): FakeUpstream {
+  const originalFetch = globalThis.fetch
+  const requests: Array<ChatCompletionsPayload> = []
+  const remaining = [...script]
+
+  globalThis.fetch = mock(async (input: string | URL | Request, init?: RequestInit) => {
+    const url = input instanceof Request ? input.url : String(input)
+    if (!url.endsWith('/chat/completions')) {
+      throw new Error(`Unexpected upstream request to ${url}`)
+    }
+    requests.push(JSON.parse(String(init?.body)) as ChatCompletionsPayload)
+    const next = remaining.shift()
+    if (!next) throw new Error(`Upstream script exhausted after ${script.length} responses`)
+    return toResponse(next)
+  }) as unknown as typeof fetch
+
+  return {
+    requests,
+    restore: () => {
+      globalThis.fetch = originalFetch
+    },
+  }
+}
+
+export interface ProviderRequest {
+  url: string
+  headers: Headers
+  // Empty when the request has no body
+  body: Uint8Array
+}
+
+export interface FakeProvider {
+  requests: Array<ProviderRequest>
+  restore: () => void
+}
+
+/**
+ * Replaces `fetch` so every request, such as those to media providers or
+ * upstream health checks, is recorded and answered by `respond`. An error
+ * thrown by `respond` stands for a host that can't be reached.
+ */
+export function fakeProvider(
+  respond: (request: ProviderRequest) => Response | Promise<Response>,
+): FakeProvider {
+  const originalFetch = globalThis.fetch
+  const requests: Array<ProviderRequest> = []
+
+  globalThis.fetch = mock(async (input: string | URL | Request, init?: RequestInit) => {
+    const request = new Request(input, init)
+    const recorded = {
+      url: request.url,
+      headers: request.headers,
+      body: new Uint8Array(await request.arrayBuffer()),
+    }
+    requests.push(recorded)
+    return respond(recorded)
+  }) as unknown as typeof fetch
+
+  return {
+    requests,
+    restore: () => {
+      globalThis.fetch = originalFetch
+    },
+  }
+}
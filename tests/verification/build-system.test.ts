@@ -41,12 +41,12 @@ describe('Build System Verification', () => {
   test('Cargo.toml is properly configured for native module', () => {
     const cargoToml = readFileSync(path.join(projectRoot, 'native/Cargo.toml'), 'utf8')
     
-    // Should be configured as cdylib
-    expect(cargoToml).toContain('crate-type = ["cdylib"]')
+    // Should be configured as cdylib, plus rlib for the fuzz targets
+    expect(cargoToml).toContain('crate-type = ["cdylib", "rlib"]')
     expect(cargoToml).toContain('name = "copilot-api-native"')
     
     // Should have required dependencies
-    expect(cargoToml).toContain('neon = "1.0"')
+    expect(cargoToml).toContain('neon = { version = "1.0"')
     expect(cargoToml).toContain('tokio')
     expect(cargoToml).toContain('reqwest')
     expect(cargoToml).toContain('serde')
@@ -68,6 +68,19 @@ describe('Build System Verification', () => {
     }).not.toThrow()
   })
 
+  test('tokenizer and validation core compiles without Node bindings', () => {
+    const cargoToml = readFileSync(path.join(projectRoot, 'native/Cargo.toml'), 'utf8')
+    expect(cargoToml).toContain('default = ["node"]')
+    expect(cargoToml).toContain('wasm = ["dep:wasm-bindgen", "dep:js-sys"]')
+
+    expect(() => {
+      execSync('cd native && cargo check --no-default-features --features wasm', {
+        cwd: projectRoot,
+        stdio: 'pipe'
+      })
+    }).not.toThrow()
+  })
+
   test('TypeScript code still compiles', () => {
     // Verify TypeScript compilation still works
     expect(() => {
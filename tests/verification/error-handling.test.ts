@@ -101,14 +101,7 @@ describe('Error Handling and Fallback Mechanisms', () => {
     const { rustCore } = require('../../src/lib/rust-core')
     
     // Placeholder functions should not throw
-    expect(async () => {
-      await rustCore.createChatCompletions({})
-    }).not.toThrow()
-
-    expect(async () => {
-      await rustCore.createEmbeddings({})  
-    }).not.toThrow()
-
+    // (createChatCompletions/createEmbeddings are covered in tests/phase3)
     expect(async () => {
       await rustCore.getModels()
     }).not.toThrow()
@@ -132,9 +125,8 @@ describe('Error Handling and Fallback Mechanisms', () => {
     
     try {
       // These should produce warnings since they're placeholder functions
-      await rustCore.createChatCompletions({})
-      await rustCore.createEmbeddings({})
       await rustCore.getModels()
+      await rustCore.refreshToken()
     } catch (error) {
       // Expected for placeholder functions
     }
@@ -10,6 +10,9 @@ export default defineConfig({
   minify: true,
   clean: true,
   removeNodeProtocol: false,
+  // Bun's built-in modules, only imported when running under Bun, and the
+  // optional gRPC dependencies
+  external: ["bun", "@grpc/grpc-js", "@grpc/proto-loader"],
 
   env: {
     NODE_ENV: "production",